@@ -2,8 +2,12 @@ use num_bigint::{BigInt, BigUint};
 use ton_abi::{Int, ParamType, TokenValue, Uint};
 use ton_types::UInt256;
 
-use super::{BuildTokenValue, KnownParamType, UnpackerError, UnpackerResult};
+use super::{
+    BuildTokenValue, KnownParamType, StandaloneToken, UnpackAbi, UnpackerError, UnpackerResult,
+};
 
+/// Number of the `uint128` ABI type
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
 pub struct BigUint128(pub BigUint);
 
 impl BuildTokenValue for BigUint128 {
@@ -21,6 +25,19 @@ impl KnownParamType for BigUint128 {
     }
 }
 
+impl UnpackAbi<BigUint128> for TokenValue {
+    fn unpack(self) -> UnpackerResult<BigUint128> {
+        match self {
+            TokenValue::Uint(Uint { number, size: 128 }) => Ok(BigUint128(number)),
+            value => Err(UnpackerError::unexpected::<BigUint128>(&value)),
+        }
+    }
+}
+
+impl StandaloneToken for BigUint128 {}
+
+/// Number of the `uint256` ABI type
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
 pub struct BigUint256(pub BigUint);
 
 impl BuildTokenValue for BigUint256 {
@@ -38,6 +55,165 @@ impl KnownParamType for BigUint256 {
     }
 }
 
+impl UnpackAbi<BigUint256> for TokenValue {
+    fn unpack(self) -> UnpackerResult<BigUint256> {
+        match self {
+            TokenValue::Uint(Uint { number, size: 256 }) => Ok(BigUint256(number)),
+            value => Err(UnpackerError::unexpected::<BigUint256>(&value)),
+        }
+    }
+}
+
+impl StandaloneToken for BigUint256 {}
+
+/// Number of the `varuintN` ABI type, where `N` is the max length in bytes (16 or 32)
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct VarUint<const N: usize>(pub BigUint);
+
+pub type VarUint16 = VarUint<16>;
+pub type VarUint32 = VarUint<32>;
+
+impl<const N: usize> VarUint<N> {
+    /// Returns `None` if the number doesn't fit into `N - 1` bytes
+    pub fn new<T: Into<BigUint>>(value: T) -> Option<Self> {
+        let value = value.into();
+        (value.bits() <= var_int_max_bits(N)).then_some(Self(value))
+    }
+}
+
+impl<const N: usize> BuildTokenValue for VarUint<N> {
+    fn token_value(self) -> TokenValue {
+        TokenValue::VarUint(N, self.0)
+    }
+}
+
+impl<const N: usize> KnownParamType for VarUint<N> {
+    fn param_type() -> ParamType {
+        ParamType::VarUint(N)
+    }
+}
+
+impl<const N: usize> UnpackAbi<VarUint<N>> for TokenValue {
+    fn unpack(self) -> UnpackerResult<VarUint<N>> {
+        match self {
+            TokenValue::VarUint(size, number) if size == N => Ok(VarUint(number)),
+            value => Err(UnpackerError::unexpected::<VarUint<N>>(&value)),
+        }
+    }
+}
+
+impl<const N: usize> StandaloneToken for VarUint<N> {}
+
+/// Number of the `varintN` ABI type, where `N` is the max length in bytes (16 or 32)
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct VarInt<const N: usize>(pub BigInt);
+
+pub type VarInt16 = VarInt<16>;
+pub type VarInt32 = VarInt<32>;
+
+impl<const N: usize> VarInt<N> {
+    /// Returns `None` if the number doesn't fit into `N - 1` bytes
+    pub fn new<T: Into<BigInt>>(value: T) -> Option<Self> {
+        let value = value.into();
+        // NOTE: one bit is reserved for sign
+        (value.bits() < var_int_max_bits(N)).then_some(Self(value))
+    }
+}
+
+impl<const N: usize> BuildTokenValue for VarInt<N> {
+    fn token_value(self) -> TokenValue {
+        TokenValue::VarInt(N, self.0)
+    }
+}
+
+impl<const N: usize> KnownParamType for VarInt<N> {
+    fn param_type() -> ParamType {
+        ParamType::VarInt(N)
+    }
+}
+
+impl<const N: usize> UnpackAbi<VarInt<N>> for TokenValue {
+    fn unpack(self) -> UnpackerResult<VarInt<N>> {
+        match self {
+            TokenValue::VarInt(size, number) if size == N => Ok(VarInt(number)),
+            value => Err(UnpackerError::unexpected::<VarInt<N>>(&value)),
+        }
+    }
+}
+
+impl<const N: usize> StandaloneToken for VarInt<N> {}
+
+fn var_int_max_bits(size: usize) -> u64 {
+    (size.saturating_sub(1) * 8) as u64
+}
+
+/// Signed 256-bit integer, stored as big-endian two's complement bytes
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Int256(pub [u8; 32]);
+
+impl Int256 {
+    pub const MIN: Self = {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 0x80;
+        Self(bytes)
+    };
+
+    pub const MAX: Self = {
+        let mut bytes = [0xffu8; 32];
+        bytes[0] = 0x7f;
+        Self(bytes)
+    };
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_slice(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0[0] & 0x80 != 0
+    }
+
+    pub fn to_bigint(&self) -> BigInt {
+        BigInt::from_signed_bytes_be(&self.0)
+    }
+
+    /// Converts number into the sign-extended representation.
+    /// Returns `None` if the number doesn't fit into 256 bits
+    pub fn from_bigint(number: &BigInt) -> Option<Self> {
+        let data = number.to_signed_bytes_be();
+        if data.len() > 32 {
+            return None;
+        }
+
+        let fill = if number.sign() == num_bigint::Sign::Minus {
+            0xff
+        } else {
+            0x00
+        };
+
+        let mut result = [fill; 32];
+        result[32 - data.len()..].copy_from_slice(&data);
+        Some(Self(result))
+    }
+}
+
+impl From<Int256> for BigInt {
+    fn from(value: Int256) -> Self {
+        value.to_bigint()
+    }
+}
+
+impl TryFrom<BigInt> for Int256 {
+    type Error = UnpackerError;
+
+    fn try_from(value: BigInt) -> Result<Self, Self::Error> {
+        Self::from_bigint(&value).ok_or(UnpackerError::InvalidAbi)
+    }
+}
+
 pub mod bytes_as_string {
     use super::*;
 
@@ -47,8 +223,8 @@ pub mod bytes_as_string {
 
     pub fn unpack(value: &TokenValue) -> UnpackerResult<String> {
         match value {
-            TokenValue::Bytes(data) => Ok(String::from_utf8_lossy(data).to_string()),
-            _ => Err(UnpackerError::InvalidAbi),
+            TokenValue::Bytes(data) => Ok(String::from_utf8_lossy(data).into_owned()),
+            value => Err(UnpackerError::unexpected::<String>(value)),
         }
     }
 
@@ -67,7 +243,7 @@ pub mod uint256_number {
     pub fn unpack(value: &TokenValue) -> UnpackerResult<BigUint> {
         match value {
             TokenValue::Uint(Uint { number, size: 256 }) => Ok(number.clone()),
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<BigUint>(value)),
         }
     }
 
@@ -100,7 +276,7 @@ pub mod uint160_bytes {
 
                 Ok(result)
             }
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<[u8; 20]>(value)),
         }
     }
 
@@ -124,7 +300,7 @@ pub mod array_uint160_bytes {
             TokenValue::Array(ParamType::Uint(160), array) => {
                 array.iter().map(uint160_bytes::unpack).collect()
             }
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<Vec<[u8; 20]>>(value)),
         }
     }
 
@@ -146,7 +322,7 @@ pub mod uint128_number {
     pub fn unpack(value: &TokenValue) -> UnpackerResult<BigUint> {
         match value {
             TokenValue::Uint(Uint { number, size: 128 }) => Ok(number.clone()),
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<BigUint>(value)),
         }
     }
 
@@ -155,6 +331,41 @@ pub mod uint128_number {
     }
 }
 
+macro_rules! define_var_number_helpers {
+    ($($name:ident => $kind:ident($size:literal), $number:ty, $wrapper:ident);*$(;)?) => {
+        $(
+            /// Packs and unpacks Rust integers as var-length ABI numbers with range checks
+            pub mod $name {
+                use super::*;
+
+                pub fn pack<T: Into<$number>>(value: T) -> TokenValue {
+                    TokenValue::$kind($size, value.into())
+                }
+
+                pub fn unpack<T: TryFrom<$number>>(value: &TokenValue) -> UnpackerResult<T> {
+                    match value {
+                        TokenValue::$kind($size, number) => {
+                            T::try_from(number.clone()).map_err(|_| UnpackerError::InvalidAbi)
+                        }
+                        value => Err(UnpackerError::unexpected::<$wrapper<$size>>(value)),
+                    }
+                }
+
+                pub fn param_type() -> ParamType {
+                    ParamType::$kind($size)
+                }
+            }
+        )*
+    };
+}
+
+define_var_number_helpers! {
+    varuint16 => VarUint(16), BigUint, VarUint;
+    varuint32 => VarUint(32), BigUint, VarUint;
+    varint16 => VarInt(16), BigInt, VarInt;
+    varint32 => VarInt(32), BigInt, VarInt;
+}
+
 pub mod int256_number {
     use super::*;
 
@@ -165,7 +376,7 @@ pub mod int256_number {
     pub fn unpack(value: &TokenValue) -> UnpackerResult<BigInt> {
         match value {
             TokenValue::Int(Int { number, size: 256 }) => Ok(number.clone()),
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<BigInt>(value)),
         }
     }
 
@@ -181,7 +392,7 @@ pub mod array_int256_number {
             TokenValue::Array(ParamType::Int(256), array) => {
                 array.iter().map(int256_number::unpack).collect()
             }
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<Vec<BigInt>>(value)),
         }
     }
 
@@ -207,7 +418,7 @@ pub mod address_only_hash {
                 address,
                 ..
             })) => Ok(UInt256::from_be_bytes(&address.get_bytestring(0))),
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<UInt256>(value)),
         }
     }
 
@@ -244,12 +455,12 @@ pub mod array_address_only_hash {
                         TokenValue::Address(ton_block::MsgAddress::AddrStd(
                             ton_block::MsgAddrStd { address, .. },
                         )) => result.push(UInt256::from_be_bytes(&address.get_bytestring(0))),
-                        _ => return Err(UnpackerError::InvalidAbi),
+                        value => return Err(UnpackerError::unexpected::<UInt256>(value)),
                     }
                 }
                 Ok(result)
             }
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<Vec<UInt256>>(value)),
         }
     }
 
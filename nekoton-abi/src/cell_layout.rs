@@ -0,0 +1,603 @@
+use ton_block::{Deserializable, Grams, MsgAddressInt, Serializable};
+use ton_types::{BuilderData, Cell, HashmapE, IBitstring, SliceData, UInt256};
+
+/// Sequential reader for the hand-packed (non-ABI) cell layouts.
+///
+/// Each read takes a field name which is used in errors along with
+/// the path of parent cells, e.g. `state.owner`
+pub struct SliceReader {
+    slice: SliceData,
+    path: String,
+}
+
+impl SliceReader {
+    pub fn new(slice: SliceData) -> Self {
+        Self {
+            slice,
+            path: String::new(),
+        }
+    }
+
+    pub fn from_cell(cell: &Cell) -> Result<Self, CellLayoutError> {
+        SliceData::load_cell_ref(cell)
+            .map(Self::new)
+            .map_err(|_| CellLayoutError::PrunedCell {
+                field: String::new(),
+            })
+    }
+
+    /// Bits position in the original slice
+    pub fn offset(&self) -> usize {
+        self.slice.pos()
+    }
+
+    pub fn remaining_bits(&self) -> usize {
+        self.slice.remaining_bits()
+    }
+
+    pub fn remaining_refs(&self) -> usize {
+        self.slice.remaining_references()
+    }
+
+    pub fn into_inner(self) -> SliceData {
+        self.slice
+    }
+
+    /// Reads a value which implements [`ReadFromSlice`]
+    pub fn read<T: ReadFromSlice>(&mut self, field: &str) -> Result<T, CellLayoutError> {
+        T::read_from(self, field)
+    }
+
+    pub fn read_bool(&mut self, field: &str) -> Result<bool, CellLayoutError> {
+        self.ensure_bits(field, 1)?;
+        let value = self.slice.get_next_bit();
+        self.map_err(field, value)
+    }
+
+    pub fn read_u8(&mut self, field: &str) -> Result<u8, CellLayoutError> {
+        self.ensure_bits(field, 8)?;
+        let value = self.slice.get_next_byte();
+        self.map_err(field, value)
+    }
+
+    pub fn read_u16(&mut self, field: &str) -> Result<u16, CellLayoutError> {
+        self.ensure_bits(field, 16)?;
+        let value = self.slice.get_next_u16();
+        self.map_err(field, value)
+    }
+
+    pub fn read_u32(&mut self, field: &str) -> Result<u32, CellLayoutError> {
+        self.ensure_bits(field, 32)?;
+        let value = self.slice.get_next_u32();
+        self.map_err(field, value)
+    }
+
+    pub fn read_u64(&mut self, field: &str) -> Result<u64, CellLayoutError> {
+        self.ensure_bits(field, 64)?;
+        let value = self.slice.get_next_u64();
+        self.map_err(field, value)
+    }
+
+    pub fn read_u128(&mut self, field: &str) -> Result<u128, CellLayoutError> {
+        self.ensure_bits(field, 128)?;
+        let value = self.slice.get_next_u128();
+        self.map_err(field, value)
+    }
+
+    /// Reads an unsigned integer of arbitrary width (up to 64 bits)
+    pub fn read_uint(&mut self, field: &str, bits: usize) -> Result<u64, CellLayoutError> {
+        if bits > 64 {
+            return Err(CellLayoutError::InvalidValue {
+                field: self.field_path(field),
+                offset: self.offset(),
+                reason: format!("integer width {bits} is too large"),
+            });
+        }
+        self.ensure_bits(field, bits)?;
+        let value = self.slice.get_next_int(bits);
+        self.map_err(field, value)
+    }
+
+    pub fn read_u256(&mut self, field: &str) -> Result<UInt256, CellLayoutError> {
+        self.ensure_bits(field, 256)?;
+        let value = self.slice.get_next_hash();
+        self.map_err(field, value)
+    }
+
+    pub fn read_bytes(&mut self, field: &str, len: usize) -> Result<Vec<u8>, CellLayoutError> {
+        self.ensure_bits(field, len * 8)?;
+        let value = self.slice.get_next_bytes(len);
+        self.map_err(field, value)
+    }
+
+    pub fn read_grams(&mut self, field: &str) -> Result<u128, CellLayoutError> {
+        self.read_deserializable::<Grams>(field)
+            .map(|grams| grams.as_u128())
+    }
+
+    pub fn read_address(&mut self, field: &str) -> Result<MsgAddressInt, CellLayoutError> {
+        self.read_deserializable(field)
+    }
+
+    /// Reads any TL-B structure
+    pub fn read_deserializable<T: Deserializable>(
+        &mut self,
+        field: &str,
+    ) -> Result<T, CellLayoutError> {
+        let offset = self.offset();
+        T::construct_from(&mut self.slice).map_err(|e| CellLayoutError::InvalidValue {
+            field: self.field_path(field),
+            offset,
+            reason: e.to_string(),
+        })
+    }
+
+    pub fn read_ref(&mut self, field: &str) -> Result<Cell, CellLayoutError> {
+        if self.remaining_refs() == 0 {
+            return Err(CellLayoutError::NotEnoughRefs {
+                field: self.field_path(field),
+                offset: self.offset(),
+            });
+        }
+        let value = self.slice.checked_drain_reference();
+        self.map_err(field, value)
+    }
+
+    /// Reads `Maybe ^Cell`
+    pub fn read_maybe_ref(&mut self, field: &str) -> Result<Option<Cell>, CellLayoutError> {
+        match self.read_bool(field)? {
+            true => self.read_ref(field).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    /// Reads `HashmapE` with the specified key length
+    pub fn read_dict(&mut self, field: &str, key_bits: usize) -> Result<HashmapE, CellLayoutError> {
+        self.read_maybe_ref(field)
+            .map(|root| HashmapE::with_hashmap(key_bits, root))
+    }
+
+    /// Parses the next reference with the nested reader.
+    ///
+    /// The nested reader must consume the whole cell
+    pub fn read_ref_with<T, F>(&mut self, field: &str, f: F) -> Result<T, CellLayoutError>
+    where
+        F: FnOnce(&mut SliceReader) -> Result<T, CellLayoutError>,
+    {
+        let cell = self.read_ref(field)?;
+        let mut reader =
+            SliceReader::from_cell(&cell).map_err(|_| CellLayoutError::PrunedCell {
+                field: self.field_path(field),
+            })?;
+        reader.path = self.field_path(field);
+
+        let result = f(&mut reader)?;
+        reader.finish()?;
+        Ok(result)
+    }
+
+    /// Skips the specified number of bits
+    pub fn skip_bits(&mut self, field: &str, bits: usize) -> Result<(), CellLayoutError> {
+        self.ensure_bits(field, bits)?;
+        let value = self.slice.move_by(bits);
+        self.map_err(field, value)
+    }
+
+    /// Checks that the slice contains at least the specified number of bits and refs
+    pub fn ensure_remaining(&self, bits: usize, refs: usize) -> Result<(), CellLayoutError> {
+        self.ensure_bits("", bits)?;
+        if self.remaining_refs() < refs {
+            return Err(CellLayoutError::NotEnoughRefs {
+                field: self.path.clone(),
+                offset: self.offset(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that all data was consumed
+    pub fn finish(&self) -> Result<(), CellLayoutError> {
+        if self.remaining_bits() > 0 || self.remaining_refs() > 0 {
+            return Err(CellLayoutError::UnexpectedData {
+                path: self.path.clone(),
+                remaining_bits: self.remaining_bits(),
+                remaining_refs: self.remaining_refs(),
+            });
+        }
+        Ok(())
+    }
+
+    fn ensure_bits(&self, field: &str, bits: usize) -> Result<(), CellLayoutError> {
+        let remaining = self.remaining_bits();
+        if remaining < bits {
+            return Err(CellLayoutError::NotEnoughBits {
+                field: self.field_path(field),
+                offset: self.offset(),
+                expected: bits,
+                remaining,
+            });
+        }
+        Ok(())
+    }
+
+    fn map_err<T>(&self, field: &str, value: ton_types::Result<T>) -> Result<T, CellLayoutError> {
+        value.map_err(|e| CellLayoutError::InvalidValue {
+            field: self.field_path(field),
+            offset: self.offset(),
+            reason: e.to_string(),
+        })
+    }
+
+    fn field_path(&self, field: &str) -> String {
+        match (self.path.is_empty(), field.is_empty()) {
+            (true, _) => field.to_owned(),
+            (false, true) => self.path.clone(),
+            (false, false) => format!("{}.{field}", self.path),
+        }
+    }
+}
+
+/// Builder counterpart of the [`SliceReader`]
+#[derive(Default)]
+pub struct CellWriter {
+    builder: BuilderData,
+}
+
+impl CellWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write<T: WriteToBuilder + ?Sized>(
+        &mut self,
+        field: &str,
+        value: &T,
+    ) -> Result<&mut Self, CellLayoutError> {
+        value.write_to(self, field)?;
+        Ok(self)
+    }
+
+    pub fn write_bool(&mut self, field: &str, value: bool) -> Result<&mut Self, CellLayoutError> {
+        let result = self.builder.append_bit_bool(value).map(|_| ());
+        self.map_err(field, result)
+    }
+
+    pub fn write_uint(
+        &mut self,
+        field: &str,
+        value: u64,
+        bits: usize,
+    ) -> Result<&mut Self, CellLayoutError> {
+        if bits > 64 || (bits < 64 && value >> bits != 0) {
+            return Err(CellLayoutError::ValueOverflow {
+                field: field.to_owned(),
+                bits,
+            });
+        }
+        if bits == 0 {
+            return Ok(self);
+        }
+        let data = (value << (64 - bits)).to_be_bytes();
+        let result = self.builder.append_raw(&data, bits).map(|_| ());
+        self.map_err(field, result)
+    }
+
+    pub fn write_u8(&mut self, field: &str, value: u8) -> Result<&mut Self, CellLayoutError> {
+        let result = self.builder.append_u8(value).map(|_| ());
+        self.map_err(field, result)
+    }
+
+    pub fn write_u16(&mut self, field: &str, value: u16) -> Result<&mut Self, CellLayoutError> {
+        let result = self.builder.append_u16(value).map(|_| ());
+        self.map_err(field, result)
+    }
+
+    pub fn write_u32(&mut self, field: &str, value: u32) -> Result<&mut Self, CellLayoutError> {
+        let result = self.builder.append_u32(value).map(|_| ());
+        self.map_err(field, result)
+    }
+
+    pub fn write_u64(&mut self, field: &str, value: u64) -> Result<&mut Self, CellLayoutError> {
+        let result = self.builder.append_u64(value).map(|_| ());
+        self.map_err(field, result)
+    }
+
+    pub fn write_u128(&mut self, field: &str, value: u128) -> Result<&mut Self, CellLayoutError> {
+        let result = self.builder.append_u128(value).map(|_| ());
+        self.map_err(field, result)
+    }
+
+    pub fn write_u256(
+        &mut self,
+        field: &str,
+        value: &UInt256,
+    ) -> Result<&mut Self, CellLayoutError> {
+        let result = self.builder.append_raw(value.as_slice(), 256).map(|_| ());
+        self.map_err(field, result)
+    }
+
+    pub fn write_grams(&mut self, field: &str, value: u128) -> Result<&mut Self, CellLayoutError> {
+        let grams = Grams::new(value).map_err(|_| CellLayoutError::ValueOverflow {
+            field: field.to_owned(),
+            bits: 120,
+        })?;
+        self.write_serializable(field, &grams)
+    }
+
+    pub fn write_address(
+        &mut self,
+        field: &str,
+        value: &MsgAddressInt,
+    ) -> Result<&mut Self, CellLayoutError> {
+        self.write_serializable(field, value)
+    }
+
+    /// Writes any TL-B structure
+    pub fn write_serializable<T: Serializable>(
+        &mut self,
+        field: &str,
+        value: &T,
+    ) -> Result<&mut Self, CellLayoutError> {
+        let result = value.write_to(&mut self.builder);
+        self.map_err(field, result)
+    }
+
+    pub fn write_ref(&mut self, field: &str, cell: Cell) -> Result<&mut Self, CellLayoutError> {
+        let result = self.builder.checked_append_reference(cell).map(|_| ());
+        self.map_err(field, result)
+    }
+
+    /// Writes `Maybe ^Cell`
+    pub fn write_maybe_ref(
+        &mut self,
+        field: &str,
+        cell: Option<Cell>,
+    ) -> Result<&mut Self, CellLayoutError> {
+        match cell {
+            Some(cell) => self.write_bool(field, true)?.write_ref(field, cell),
+            None => self.write_bool(field, false),
+        }
+    }
+
+    pub fn write_dict(
+        &mut self,
+        field: &str,
+        dict: &HashmapE,
+    ) -> Result<&mut Self, CellLayoutError> {
+        self.write_maybe_ref(field, dict.data().cloned())
+    }
+
+    /// Builds the nested cell and stores it as a reference
+    pub fn write_ref_with<F>(&mut self, field: &str, f: F) -> Result<&mut Self, CellLayoutError>
+    where
+        F: FnOnce(&mut CellWriter) -> Result<(), CellLayoutError>,
+    {
+        let mut writer = CellWriter::new();
+        f(&mut writer)?;
+        let cell = writer.build()?;
+        self.write_ref(field, cell)
+    }
+
+    pub fn build(self) -> Result<Cell, CellLayoutError> {
+        self.builder
+            .into_cell()
+            .map_err(|e| CellLayoutError::InvalidValue {
+                field: String::new(),
+                offset: 0,
+                reason: e.to_string(),
+            })
+    }
+
+    pub fn into_builder(self) -> BuilderData {
+        self.builder
+    }
+
+    fn map_err(
+        &mut self,
+        field: &str,
+        result: ton_types::Result<()>,
+    ) -> Result<&mut Self, CellLayoutError> {
+        match result {
+            Ok(()) => Ok(self),
+            Err(_) => Err(CellLayoutError::CellOverflow {
+                field: field.to_owned(),
+                bits: self.builder.length_in_bits(),
+                refs: self.builder.references_used(),
+            }),
+        }
+    }
+}
+
+/// Value which can be read with the [`SliceReader`]
+pub trait ReadFromSlice: Sized {
+    fn read_from(reader: &mut SliceReader, field: &str) -> Result<Self, CellLayoutError>;
+}
+
+/// Value which can be written with the [`CellWriter`]
+pub trait WriteToBuilder {
+    fn write_to(&self, writer: &mut CellWriter, field: &str) -> Result<(), CellLayoutError>;
+}
+
+macro_rules! impl_cell_layout {
+    ($($ty:ty => $read:ident, $write:ident);*$(;)?) => {
+        $(
+            impl ReadFromSlice for $ty {
+                fn read_from(reader: &mut SliceReader, field: &str) -> Result<Self, CellLayoutError> {
+                    reader.$read(field)
+                }
+            }
+
+            impl WriteToBuilder for $ty {
+                fn write_to(&self, writer: &mut CellWriter, field: &str) -> Result<(), CellLayoutError> {
+                    writer.$write(field, *self).map(|_| ())
+                }
+            }
+        )*
+    };
+}
+
+impl_cell_layout! {
+    bool => read_bool, write_bool;
+    u8 => read_u8, write_u8;
+    u16 => read_u16, write_u16;
+    u32 => read_u32, write_u32;
+    u64 => read_u64, write_u64;
+    u128 => read_u128, write_u128;
+}
+
+impl ReadFromSlice for Cell {
+    fn read_from(reader: &mut SliceReader, field: &str) -> Result<Self, CellLayoutError> {
+        reader.read_ref(field)
+    }
+}
+
+impl WriteToBuilder for Cell {
+    fn write_to(&self, writer: &mut CellWriter, field: &str) -> Result<(), CellLayoutError> {
+        writer.write_ref(field, self.clone()).map(|_| ())
+    }
+}
+
+impl ReadFromSlice for UInt256 {
+    fn read_from(reader: &mut SliceReader, field: &str) -> Result<Self, CellLayoutError> {
+        reader.read_u256(field)
+    }
+}
+
+impl WriteToBuilder for UInt256 {
+    fn write_to(&self, writer: &mut CellWriter, field: &str) -> Result<(), CellLayoutError> {
+        writer.write_u256(field, self).map(|_| ())
+    }
+}
+
+impl ReadFromSlice for MsgAddressInt {
+    fn read_from(reader: &mut SliceReader, field: &str) -> Result<Self, CellLayoutError> {
+        reader.read_address(field)
+    }
+}
+
+impl WriteToBuilder for MsgAddressInt {
+    fn write_to(&self, writer: &mut CellWriter, field: &str) -> Result<(), CellLayoutError> {
+        writer.write_address(field, self).map(|_| ())
+    }
+}
+
+/// `Maybe ^Cell`
+impl ReadFromSlice for Option<Cell> {
+    fn read_from(reader: &mut SliceReader, field: &str) -> Result<Self, CellLayoutError> {
+        reader.read_maybe_ref(field)
+    }
+}
+
+impl WriteToBuilder for Option<Cell> {
+    fn write_to(&self, writer: &mut CellWriter, field: &str) -> Result<(), CellLayoutError> {
+        writer.write_maybe_ref(field, self.clone()).map(|_| ())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum CellLayoutError {
+    #[error(
+        "not enough bits for `{field}` at {offset}: expected {expected}, remaining {remaining}"
+    )]
+    NotEnoughBits {
+        field: String,
+        offset: usize,
+        expected: usize,
+        remaining: usize,
+    },
+    #[error("not enough refs for `{field}` at {offset}")]
+    NotEnoughRefs { field: String, offset: usize },
+    #[error("invalid `{field}` at {offset}: {reason}")]
+    InvalidValue {
+        field: String,
+        offset: usize,
+        reason: String,
+    },
+    #[error("pruned cell for `{field}`")]
+    PrunedCell { field: String },
+    #[error("unexpected data in `{path}`: {remaining_bits} bits, {remaining_refs} refs")]
+    UnexpectedData {
+        path: String,
+        remaining_bits: usize,
+        remaining_refs: usize,
+    },
+    #[error("`{field}` doesn't fit into {bits} bits")]
+    ValueOverflow { field: String, bits: usize },
+    #[error("cell overflow while writing `{field}` ({bits} bits, {refs} refs used)")]
+    CellOverflow {
+        field: String,
+        bits: usize,
+        refs: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn read_write_layout() {
+        let owner = MsgAddressInt::from_str(
+            "0:1111111111111111111111111111111111111111111111111111111111111111",
+        )
+        .unwrap();
+
+        let mut writer = CellWriter::new();
+        writer
+            .write_bool("active", true)
+            .unwrap()
+            .write_uint("version", 5, 4)
+            .unwrap()
+            .write("seqno", &42u32)
+            .unwrap()
+            .write_grams("balance", 1_000_000_000)
+            .unwrap()
+            .write_ref_with("state", |writer| {
+                writer
+                    .write("owner", &owner)?
+                    .write_dict("extensions", &HashmapE::with_bit_len(256))?;
+                Ok(())
+            })
+            .unwrap();
+        let cell = writer.build().unwrap();
+
+        let mut reader = SliceReader::from_cell(&cell).unwrap();
+        assert!(reader.read_bool("active").unwrap());
+        assert_eq!(reader.read_uint("version", 4).unwrap(), 5);
+        assert_eq!(reader.read::<u32>("seqno").unwrap(), 42);
+        assert_eq!(reader.read_grams("balance").unwrap(), 1_000_000_000);
+        let (parsed_owner, extensions) = reader
+            .read_ref_with("state", |reader| {
+                Ok((
+                    reader.read::<MsgAddressInt>("owner")?,
+                    reader.read_dict("extensions", 256)?,
+                ))
+            })
+            .unwrap();
+        assert_eq!(parsed_owner, owner);
+        assert!(extensions.is_empty());
+        reader.finish().unwrap();
+
+        // Errors contain the full field path
+        let mut reader = SliceReader::from_cell(&cell).unwrap();
+        reader.skip_bits("", 1 + 4 + 32).unwrap();
+        reader.read_grams("balance").unwrap();
+        let error = reader
+            .read_ref_with("state", |reader| {
+                reader.read_address("owner")?;
+                reader.read_u256("hash")
+            })
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            CellLayoutError::NotEnoughBits { field, .. } if field == "state.hash"
+        ));
+
+        assert!(matches!(
+            CellWriter::new().write_uint("value", 16, 4),
+            Err(CellLayoutError::ValueOverflow { .. })
+        ));
+    }
+}
@@ -1,5 +1,8 @@
 use anyhow::Result;
-use ton_types::{BuilderData, Cell, SliceData};
+use ton_block::MsgAddressInt;
+use ton_types::{BuilderData, Cell, SliceData, UInt256};
+
+use super::Tvc;
 
 pub fn set_code_salt(code: Cell, salt: Cell) -> Result<Cell> {
     let code_data = code
@@ -28,6 +31,22 @@ pub fn get_code_salt(code: Cell) -> Result<Option<Cell>> {
     }
 }
 
+/// Computes the hash of the code with the specified salt
+pub fn compute_salted_code_hash(code: Cell, salt: Cell) -> Result<UInt256> {
+    set_code_salt(code, salt).map(|code| code.repr_hash())
+}
+
+/// Computes the address of the contract with the salted code and the specified data
+pub fn compute_salted_contract_address(
+    code: Cell,
+    salt: Cell,
+    data: Option<Cell>,
+    workchain_id: i8,
+) -> Result<MsgAddressInt> {
+    let code = set_code_salt(code, salt)?;
+    Tvc::from_parts(code, data).compute_address(workchain_id)
+}
+
 fn set_salt(code: Cell, salt: Cell, replace_last_ref: bool) -> Result<Cell> {
     let mut builder: BuilderData = code.into();
     if replace_last_ref {
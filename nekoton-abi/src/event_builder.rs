@@ -3,6 +3,8 @@ use ton_abi::{Event, Param, ParamType};
 
 #[derive(Debug, Clone)]
 pub struct EventBuilder {
+    /// Explicit event id
+    id: Option<u32>,
     /// Contract function specification.
     /// ABI version
     abi_version: AbiVersion,
@@ -15,6 +17,7 @@ pub struct EventBuilder {
 impl EventBuilder {
     pub fn new(event_name: &str) -> Self {
         Self {
+            id: None,
             name: event_name.to_string(),
             abi_version: ABI_VERSION_2_0,
             inputs: Vec::new(),
@@ -26,6 +29,11 @@ impl EventBuilder {
         self
     }
 
+    pub fn id(mut self, id: u32) -> Self {
+        self.id = Some(id);
+        self
+    }
+
     /// Adds input param
     #[deprecated(note = "use `input` instead")]
     pub fn in_arg(self, name: &str, ty: ParamType) -> Self {
@@ -53,8 +61,10 @@ impl EventBuilder {
             inputs: self.inputs,
             id: 0,
         };
-        let id = event.get_function_id();
-        event.id = id & 0x7FFFFFFF;
+        event.id = match self.id {
+            Some(id) => id,
+            None => event.get_function_id() & 0x7FFFFFFF,
+        };
         event
     }
 }
@@ -78,4 +88,13 @@ mod tests {
             .build();
         assert_eq!(original, imposter)
     }
+
+    #[test]
+    fn build_with_explicit_id() {
+        let event = EventBuilder::new("TransferAccepted")
+            .id(0x12345678)
+            .input("payload", ParamType::Bytes)
+            .build();
+        assert_eq!(event.id, 0x12345678);
+    }
 }
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use rustc_hash::FxHashMap;
+use ton_abi::{Event, Token};
+use ton_types::SliceData;
+
+use super::{read_function_id, UnpackAbiPlain, UnpackerResult};
+
+/// Decodes contract events from the external outbound message bodies
+#[derive(Debug, Clone)]
+pub struct EventUnpacker<'a> {
+    events: FxHashMap<u32, &'a Event>,
+}
+
+impl<'a> EventUnpacker<'a> {
+    pub fn new(contract: &'a ton_abi::Contract) -> Self {
+        Self::from_events(contract.events.values())
+    }
+
+    pub fn from_events<I>(events: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Event>,
+    {
+        Self {
+            events: events.into_iter().map(|event| (event.id, event)).collect(),
+        }
+    }
+
+    /// Finds the event by its id and decodes the body.
+    ///
+    /// Returns `None` if there is no event with such id
+    pub fn decode(&self, body: SliceData) -> Result<Option<DecodedEvent<'a>>> {
+        let event = match self.events.get(&read_function_id(&body)?) {
+            Some(event) => *event,
+            None => return Ok(None),
+        };
+
+        Ok(Some(DecodedEvent {
+            event,
+            tokens: event.decode_input(body)?,
+        }))
+    }
+
+    /// Decodes the event from the message if it is an external outbound message
+    pub fn decode_message(&self, message: &ton_block::Message) -> Result<Option<DecodedEvent<'a>>> {
+        if !matches!(message.header(), ton_block::CommonMsgInfo::ExtOutMsgInfo(_)) {
+            return Ok(None);
+        }
+
+        match message.body() {
+            Some(body) => self.decode(body),
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes the event with the specified name into the typed struct.
+    ///
+    /// Returns `None` if the body contains some other event
+    pub fn unpack<T>(&self, name: &str, body: SliceData) -> Result<Option<T>>
+    where
+        Vec<Token>: UnpackAbiPlain<T>,
+    {
+        match self.decode(body)? {
+            Some(decoded) if decoded.event.name == name => Ok(Some(decoded.unpack()?)),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodedEvent<'a> {
+    pub event: &'a Event,
+    pub tokens: Vec<Token>,
+}
+
+impl DecodedEvent<'_> {
+    pub fn name(&self) -> &str {
+        &self.event.name
+    }
+
+    pub fn unpack<T>(self) -> UnpackerResult<T>
+    where
+        Vec<Token>: UnpackAbiPlain<T>,
+    {
+        self.tokens.unpack()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ton_abi::ParamType;
+
+    use super::*;
+    use crate::{BuildTokenValue, EventBuilder, FunctionBuilder, TokenValueExt};
+
+    fn encode_event(event: &Event, tokens: &[Token]) -> SliceData {
+        FunctionBuilder::new(&event.name)
+            .id(event.id)
+            .inputs(event.inputs.clone())
+            .build()
+            .encode_internal_input(tokens)
+            .and_then(SliceData::load_builder)
+            .unwrap()
+    }
+
+    #[test]
+    fn decode_events() {
+        let transfer = EventBuilder::new("Transfer")
+            .input("amount", ParamType::Uint(128))
+            .input("notify", ParamType::Bool)
+            .build();
+        let burn = EventBuilder::new("Burn")
+            .input("amount", ParamType::Uint(128))
+            .build();
+
+        let unpacker = EventUnpacker::from_events([&transfer, &burn]);
+
+        let body = encode_event(
+            &transfer,
+            &[
+                100u128.token_value().named("amount"),
+                true.token_value().named("notify"),
+            ],
+        );
+
+        let decoded = unpacker.decode(body.clone()).unwrap().unwrap();
+        assert_eq!(decoded.name(), "Transfer");
+
+        let (amount, notify): (u128, bool) =
+            unpacker.unpack("Transfer", body.clone()).unwrap().unwrap();
+        assert_eq!(amount, 100);
+        assert!(notify);
+
+        assert!(unpacker.unpack::<(u128,)>("Burn", body).unwrap().is_none());
+
+        let unknown = EventBuilder::new("Unknown").build();
+        let body = encode_event(&unknown, &[]);
+        assert!(unpacker.decode(body).unwrap().is_none());
+    }
+}
@@ -1,11 +1,14 @@
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap};
 use std::hash::BuildHasher;
+use std::sync::Arc;
 
+use smallvec::SmallVec;
 use ton_abi::{Param, ParamType};
 use ton_block::{MsgAddrStd, MsgAddress, MsgAddressInt};
 use ton_types::{BuilderData, Cell};
 
-use super::{MaybeRef, StandaloneToken};
+use super::{Int256, MaybeRef, StandaloneToken};
 
 pub trait KnownParamTypePlain {
     fn param_type() -> Vec<Param>;
@@ -103,12 +106,37 @@ impl KnownParamType for ton_types::UInt256 {
     }
 }
 
+impl KnownParamType for ed25519_dalek::PublicKey {
+    fn param_type() -> ParamType {
+        ParamType::Uint(256)
+    }
+}
+
+impl KnownParamType for Int256 {
+    fn param_type() -> ParamType {
+        ParamType::Int(256)
+    }
+}
+
 impl KnownParamType for bool {
     fn param_type() -> ParamType {
         ParamType::Bool
     }
 }
 
+impl KnownParamType for std::time::SystemTime {
+    fn param_type() -> ParamType {
+        ParamType::Uint(64)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl KnownParamType for chrono::DateTime<chrono::Utc> {
+    fn param_type() -> ParamType {
+        ParamType::Uint(64)
+    }
+}
+
 impl KnownParamType for Cell {
     fn param_type() -> ParamType {
         ParamType::Cell
@@ -139,6 +167,18 @@ impl KnownParamType for &str {
     }
 }
 
+impl KnownParamType for String {
+    fn param_type() -> ParamType {
+        ParamType::String
+    }
+}
+
+impl KnownParamType for Cow<'_, str> {
+    fn param_type() -> ParamType {
+        ParamType::String
+    }
+}
+
 impl KnownParamType for ton_block::Grams {
     fn param_type() -> ParamType {
         ParamType::Token
@@ -151,6 +191,19 @@ impl KnownParamType for Vec<u8> {
     }
 }
 
+#[cfg(feature = "bytes")]
+impl KnownParamType for bytes::Bytes {
+    fn param_type() -> ParamType {
+        ParamType::Bytes
+    }
+}
+
+impl<const N: usize> KnownParamType for SmallVec<[u8; N]> {
+    fn param_type() -> ParamType {
+        ParamType::Bytes
+    }
+}
+
 impl<K, V, S> KnownParamType for HashMap<K, V, S>
 where
     K: KnownParamType,
@@ -195,6 +248,33 @@ where
     }
 }
 
+impl<T> KnownParamType for Box<T>
+where
+    T: KnownParamType,
+{
+    fn param_type() -> ParamType {
+        T::param_type()
+    }
+}
+
+impl<T> KnownParamType for Arc<T>
+where
+    T: KnownParamType,
+{
+    fn param_type() -> ParamType {
+        T::param_type()
+    }
+}
+
+impl<T, const N: usize> KnownParamType for [T; N]
+where
+    T: KnownParamType,
+{
+    fn param_type() -> ParamType {
+        ParamType::FixedArray(Box::new(T::param_type()), N)
+    }
+}
+
 impl<K, V> KnownParamType for BTreeMap<K, V>
 where
     K: KnownParamType,
@@ -204,3 +284,32 @@ where
         ParamType::Map(Box::new(K::param_type()), Box::new(V::param_type()))
     }
 }
+
+macro_rules! impl_known_tuple {
+    ($($idx:tt $ty:ident),+) => {
+        impl<$($ty),+> KnownParamType for ($($ty,)+)
+        where
+            $($ty: KnownParamType),+
+        {
+            fn param_type() -> ParamType {
+                ParamType::Tuple(vec![$(Param::new(
+                    concat!("value", stringify!($idx)),
+                    $ty::param_type(),
+                )),+])
+            }
+        }
+    };
+}
+
+impl_known_tuple!(0 T0);
+impl_known_tuple!(0 T0, 1 T1);
+impl_known_tuple!(0 T0, 1 T1, 2 T2);
+impl_known_tuple!(0 T0, 1 T1, 2 T2, 3 T3);
+impl_known_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4);
+impl_known_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5);
+impl_known_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6);
+impl_known_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7);
+impl_known_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8);
+impl_known_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9);
+impl_known_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10);
+impl_known_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10, 11 T11);
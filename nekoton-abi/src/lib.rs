@@ -70,38 +70,50 @@ use ton_vm::executor::BehaviorModifiers;
 
 #[cfg(feature = "derive")]
 pub use {
+    anyhow,
     nekoton_derive::{
-        KnownParamType, KnownParamTypePlain, PackAbi, PackAbiPlain, UnpackAbi, UnpackAbiPlain,
+        abigen, KnownParamType, KnownParamTypePlain, PackAbi, PackAbiPlain, UnpackAbi,
+        UnpackAbiPlain,
     },
-    num_bigint, num_traits,
+    num_bigint, num_traits, once_cell, ton_abi, ton_block, ton_types,
 };
 
 use nekoton_utils::*;
 
 pub use self::abi_helpers::*;
+pub use self::cell_layout::*;
 pub use self::code_salt::*;
 pub use self::event_builder::*;
+pub use self::event_unpacker::*;
 pub use self::function_builder::*;
 pub use self::known_param_type::*;
 pub use self::message_builder::*;
 pub use self::models::*;
+pub use self::raw_dict::*;
+pub use self::token_deserializer::*;
 pub use self::token_packer::*;
 pub use self::token_unpacker::*;
 pub use self::tokens_json::*;
 pub use self::transaction_parser::TransactionParser;
+pub use self::tvc::Tvc;
 pub use self::tvm::{BriefBlockchainConfig, StackItem, VmGetterOutput};
 
 mod abi_helpers;
+mod cell_layout;
 mod code_salt;
 mod event_builder;
+mod event_unpacker;
 mod function_builder;
 mod known_param_type;
 mod message_builder;
 mod models;
+mod raw_dict;
+mod token_deserializer;
 mod token_packer;
 mod token_unpacker;
 mod tokens_json;
 pub mod transaction_parser;
+pub mod tvc;
 pub mod tvm;
 
 pub fn read_function_id(data: &SliceData) -> Result<u32> {
@@ -133,6 +145,17 @@ pub fn read_input_function_id(
                 }
                 ton_abi::ParamType::Time => body.move_by(64)?,
                 ton_abi::ParamType::Expire => body.move_by(32)?,
+                // Newer compilers allow arbitrary header params
+                ref kind if kind.is_supported(&contract.abi_version) => {
+                    let (_, cursor) = TokenValue::decode_params_with_cursor(
+                        std::slice::from_ref(header),
+                        body.into(),
+                        &contract.abi_version,
+                        true,
+                        false,
+                    )?;
+                    body = cursor.slice;
+                }
                 _ => return Err(AbiError::UnsupportedHeader.into()),
             }
         }
@@ -205,23 +228,101 @@ pub fn create_comment_payload(comment: &str) -> Result<SliceData> {
     .and_then(SliceData::load_builder)
 }
 
+/// Creates slice data with string, encoded as comment in the snake format.
+///
+/// Text follows the opcode in the same cell and continues in the chain of references.
+/// Cells are split on the character boundaries, so each of them contains valid UTF-8
+pub fn create_snake_comment_payload(comment: &str) -> Result<SliceData> {
+    let mut root = ton_types::BuilderData::new();
+    root.append_u32(0)?;
+
+    let mut chunks = Vec::new();
+    let mut capacity = root.bits_free() / 8;
+    let mut rest = comment;
+    while !rest.is_empty() {
+        let mut len = std::cmp::min(rest.len(), capacity);
+        while !rest.is_char_boundary(len) {
+            len -= 1;
+        }
+        let (chunk, tail) = rest.split_at(len);
+        chunks.push(chunk.as_bytes());
+        rest = tail;
+        capacity = SNAKE_CELL_BYTES;
+    }
+
+    build_snake(root, &chunks).and_then(SliceData::load_builder)
+}
+
+/// Appends bytes to the root cell in the snake format
+pub fn create_snake_payload(root: ton_types::BuilderData, data: &[u8]) -> Result<SliceData> {
+    let first_len = std::cmp::min(data.len(), root.bits_free() / 8);
+    let (first, rest) = data.split_at(first_len);
+
+    let mut chunks = vec![first];
+    chunks.extend(rest.chunks(SNAKE_CELL_BYTES));
+    build_snake(root, &chunks).and_then(SliceData::load_builder)
+}
+
+/// Reads the remaining bytes of the slice and the chain of its first references
+pub fn read_snake_bytes(payload: &SliceData) -> Option<Vec<u8>> {
+    let bits = payload.remaining_bits();
+    if bits % 8 != 0 {
+        return None;
+    }
+
+    let mut data = payload.get_bytestring(0);
+    data.truncate(bits / 8);
+
+    let mut next = payload.reference_opt(0);
+    while let Some(cell) = next {
+        if cell.bit_length() % 8 != 0 {
+            return None;
+        }
+        data.extend_from_slice(&cell.data()[..cell.bit_length() / 8]);
+        next = cell.reference(0).ok();
+    }
+
+    Some(data)
+}
+
+fn build_snake(
+    mut root: ton_types::BuilderData,
+    chunks: &[&[u8]],
+) -> Result<ton_types::BuilderData> {
+    let mut next: Option<ton_types::Cell> = None;
+    for chunk in chunks.iter().skip(1).rev() {
+        let mut builder = ton_types::BuilderData::new();
+        builder.append_raw(chunk, chunk.len() * 8)?;
+        if let Some(cell) = next {
+            builder.checked_append_reference(cell)?;
+        }
+        next = Some(builder.into_cell()?);
+    }
+
+    if let Some(first) = chunks.first() {
+        root.append_raw(first, first.len() * 8)?;
+    }
+    if let Some(cell) = next {
+        root.checked_append_reference(cell)?;
+    }
+    Ok(root)
+}
+
+const SNAKE_CELL_BYTES: usize = 127;
+
+/// Parses comments, encoded either with ABI (text in the references chain)
+/// or in the snake format (text right after the opcode)
 pub fn parse_comment_payload(mut payload: SliceData) -> Option<String> {
     if payload.get_next_u32().ok()? != 0 {
         return None;
     }
 
-    let mut cell = payload.checked_drain_reference().ok()?;
-
-    let mut data = Vec::new();
-    loop {
-        data.extend_from_slice(cell.data());
-        cell = match cell.reference(0) {
-            Ok(cell) => cell.clone(),
-            Err(_) => break,
-        };
+    // NOTE: bare opcode is not a comment
+    if payload.remaining_bits() == 0 && payload.remaining_references() == 0 {
+        return None;
     }
 
-    String::from_utf8(data).ok()
+    String::from_utf8(read_snake_bytes(&payload)?).ok()
 }
 
 /// Creates slice data from base64 encoded boc
@@ -259,6 +360,27 @@ pub fn unpack_from_cell(
     }
 }
 
+/// Creates tokens with default values for the specified params.
+///
+/// Fails if some param type is not supported by the ABI version
+pub fn make_default_tokens(
+    params: &[Param],
+    abi_version: ton_abi::contract::AbiVersion,
+) -> Result<Vec<Token>> {
+    params
+        .iter()
+        .map(|param| {
+            if !param.kind.is_supported(&abi_version) {
+                return Err(AbiError::UnsupportedParamType(param.kind.type_signature()).into());
+            }
+            Ok(Token::new(
+                &param.name,
+                TokenValue::default_value(&param.kind),
+            ))
+        })
+        .collect()
+}
+
 pub fn extract_public_key(
     account: &AccountStuff,
 ) -> Result<ed25519_dalek::PublicKey, ExtractionError> {
@@ -586,6 +708,32 @@ pub fn code_to_tvc(code: ton_types::Cell) -> Result<ton_block::StateInit> {
     })
 }
 
+/// Creates the data map with the public key and the static variables (by their keys).
+///
+/// Zero public key is used if it is not specified
+pub fn make_init_data(
+    public_key: Option<&ed25519_dalek::PublicKey>,
+    values: &[(u64, TokenValue)],
+    abi_version: ton_abi::contract::AbiVersion,
+) -> Result<ton_types::Cell> {
+    let mut map = ton_types::HashmapE::with_bit_len(ton_abi::Contract::DATA_MAP_KEYLEN);
+
+    let public_key = public_key.map(|key| *key.as_bytes()).unwrap_or_default();
+    map.set_builder(
+        0u64.serialize().and_then(SliceData::load_cell)?,
+        ton_types::BuilderData::new().append_raw(&public_key, 256)?,
+    )?;
+
+    for (key, value) in values {
+        map.set_builder(
+            key.serialize().and_then(SliceData::load_cell)?,
+            &value.pack_into_chain(&abi_version)?,
+        )?;
+    }
+
+    map.write_to_new_cell()?.into_cell()
+}
+
 #[derive(Copy, Clone)]
 pub struct ExecutionContext<'a> {
     pub clock: &'a dyn Clock,
@@ -605,6 +753,30 @@ impl ExecutionContext<'_> {
         function.run_local_responsible(self.clock, self.account_stuff.clone(), input)
     }
 
+    /// Runs multiple functions against the same account state and block time.
+    ///
+    /// The state is shared between all calls without copying
+    pub fn run_getters(
+        &self,
+        calls: &[(Function, Vec<Token>)],
+        config: &BriefBlockchainConfig,
+    ) -> Result<Vec<ExecutionOutput>> {
+        let clock = ConstClock::from_millis(self.clock.now_ms_u64());
+
+        calls
+            .iter()
+            .map(|(function, input)| {
+                FunctionAbi::new(function).run_local(
+                    &clock,
+                    self.account_stuff,
+                    input,
+                    false,
+                    config,
+                )
+            })
+            .collect()
+    }
+
     pub fn run_getter<M>(
         &self,
         method_id: &M,
@@ -748,12 +920,12 @@ impl FunctionExt for Function {
     fn run_local_ext(
         &self,
         clock: &dyn Clock,
-        mut account_stuff: AccountStuff,
+        account_stuff: AccountStuff,
         input: &[Token],
         responsible: bool,
         config: &BriefBlockchainConfig,
     ) -> Result<ExecutionOutput> {
-        FunctionAbi::new(self).run_local(clock, &mut account_stuff, input, responsible, config)
+        FunctionAbi::new(self).run_local(clock, &account_stuff, input, responsible, config)
     }
 }
 
@@ -774,15 +946,17 @@ impl<'a> FunctionAbi<'a> {
     fn run_local(
         &self,
         clock: &dyn Clock,
-        account_stuff: &mut AccountStuff,
+        account_stuff: &AccountStuff,
         input: &[Token],
         responsible: bool,
         config: &BriefBlockchainConfig,
     ) -> Result<ExecutionOutput> {
         let function = self.abi;
 
+        let mut account_stuff = std::borrow::Cow::Borrowed(account_stuff);
         let answer_id = if responsible {
-            account_stuff.storage.balance.grams = ton_block::Grams::from(100_000_000_000_000u64); // 100 000 TON
+            account_stuff.to_mut().storage.balance.grams =
+                ton_block::Grams::from(100_000_000_000_000u64); // 100 000 TON
 
             match input.first().map(|token| &token.value) {
                 Some(TokenValue::Uint(ton_abi::Uint { number, size: 32 })) => number
@@ -831,10 +1005,11 @@ impl<'a> FunctionAbi<'a> {
         let tvm::ActionPhaseOutput {
             messages,
             exit_code: result_code,
+            gas_used,
         } = tvm::call_msg(
             gen_utime,
             gen_lt,
-            account_stuff,
+            &account_stuff,
             &msg,
             config,
             &Default::default(),
@@ -886,6 +1061,7 @@ impl<'a> FunctionAbi<'a> {
         Ok(ExecutionOutput {
             tokens,
             result_code,
+            gas_used,
         })
     }
 }
@@ -894,6 +1070,7 @@ impl<'a> FunctionAbi<'a> {
 pub struct ExecutionOutput {
     pub tokens: Option<Vec<Token>>,
     pub result_code: i32,
+    pub gas_used: u64,
 }
 
 pub fn process_out_messages(
@@ -973,6 +1150,8 @@ enum AbiError {
     IncompleteDeserialization(SliceData),
     #[error("Unsupported header")]
     UnsupportedHeader,
+    #[error("Param type `{}` is not supported by the ABI version", .0)]
+    UnsupportedParamType(String),
     #[error("Answer id not found")]
     AnswerIdNotFound,
 }
@@ -1137,7 +1316,11 @@ fn get_block_stats(
 #[derive(Debug)]
 pub struct MaybeRef<T>(pub Option<T>);
 
+/// Marker for types which can be used as array items.
+///
+/// `u8` is intentionally excluded so that `Vec<u8>` is always treated as `bytes`
 pub trait StandaloneToken {}
+impl StandaloneToken for i8 {}
 impl StandaloneToken for i16 {}
 impl StandaloneToken for u16 {}
 impl StandaloneToken for i32 {}
@@ -1149,17 +1332,53 @@ impl StandaloneToken for u128 {}
 impl StandaloneToken for bool {}
 impl StandaloneToken for MsgAddressInt {}
 impl StandaloneToken for MsgAddrStd {}
+impl StandaloneToken for ton_block::MsgAddress {}
+impl StandaloneToken for String {}
+impl StandaloneToken for std::borrow::Cow<'_, str> {}
+impl StandaloneToken for num_bigint::BigInt {}
+impl StandaloneToken for num_bigint::BigUint {}
 impl StandaloneToken for UInt256 {}
+impl StandaloneToken for Int256 {}
+impl StandaloneToken for ed25519_dalek::PublicKey {}
 impl StandaloneToken for TokenValue {}
 impl StandaloneToken for ton_block::Grams {}
 impl StandaloneToken for ton_types::Cell {}
+impl StandaloneToken for ton_types::BuilderData {}
+impl StandaloneToken for std::time::SystemTime {}
+#[cfg(feature = "chrono")]
+impl StandaloneToken for chrono::DateTime<chrono::Utc> {}
+#[cfg(feature = "bytes")]
+impl StandaloneToken for bytes::Bytes {}
 impl<T> StandaloneToken for Option<T> {}
 impl<T> StandaloneToken for MaybeRef<T> {}
 impl<T> StandaloneToken for Vec<T> {}
+impl<T, const N: usize> StandaloneToken for [T; N] {}
+impl<const N: usize> StandaloneToken for smallvec::SmallVec<[u8; N]> {}
+impl<K, V> StandaloneToken for std::collections::BTreeMap<K, V> {}
+impl<K, V, S> StandaloneToken for HashMap<K, V, S> {}
 impl<T: StandaloneToken> StandaloneToken for Box<T> {}
 impl<T: StandaloneToken> StandaloneToken for Arc<T> {}
 impl<T: StandaloneToken> StandaloneToken for &T {}
 
+macro_rules! impl_standalone_tuple {
+    ($($ty:ident),+) => {
+        impl<$($ty),+> StandaloneToken for ($($ty,)+) {}
+    };
+}
+
+impl_standalone_tuple!(T0);
+impl_standalone_tuple!(T0, T1);
+impl_standalone_tuple!(T0, T1, T2);
+impl_standalone_tuple!(T0, T1, T2, T3);
+impl_standalone_tuple!(T0, T1, T2, T3, T4);
+impl_standalone_tuple!(T0, T1, T2, T3, T4, T5);
+impl_standalone_tuple!(T0, T1, T2, T3, T4, T5, T6);
+impl_standalone_tuple!(T0, T1, T2, T3, T4, T5, T6, T7);
+impl_standalone_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8);
+impl_standalone_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_standalone_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_standalone_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+
 pub fn default_blockchain_config() -> &'static ton_executor::BlockchainConfig {
     use once_cell::race::OnceBox;
 
@@ -1271,6 +1490,35 @@ mod tests {
         assert_eq!(decoded_comment, comment);
     }
 
+    #[test]
+    fn test_snake_comment() {
+        let comment = "i love memes and 🦀".repeat(20);
+
+        let encoded_comment = create_snake_comment_payload(&comment).unwrap();
+        let mut slice = encoded_comment.clone();
+        assert_eq!(slice.get_next_u32().unwrap(), 0);
+        assert_eq!(slice.get_next_bytes(4).unwrap(), b"i lo");
+
+        let mut cell = encoded_comment.reference(0).unwrap();
+        loop {
+            assert!(std::str::from_utf8(cell.data()).is_ok());
+            cell = match cell.reference(0) {
+                Ok(cell) => cell,
+                Err(_) => break,
+            };
+        }
+
+        let decoded_comment = parse_comment_payload(encoded_comment).unwrap();
+        assert_eq!(decoded_comment, comment);
+
+        let short = create_snake_comment_payload("test").unwrap();
+        assert_eq!(short.remaining_references(), 0);
+        assert_eq!(parse_comment_payload(short).unwrap(), "test");
+
+        let bare_opcode = create_snake_comment_payload("").unwrap();
+        assert!(parse_comment_payload(bare_opcode).is_none());
+    }
+
     #[test]
     fn execute_getter() {
         let cell = ton_types::deserialize_tree_of_cells(&mut base64::decode("te6ccgEBAwEA1wACcIAStWnZig414CoO3Ix5SSSgxF+4p0D15b9rxM7Q6hTG2AQNApWGauQIQAABez2Soaga3FkkG3ymAgEAUAAACtJLqS2Krp5U49k0sATqkF/7CPTREi6T4gLBqodDaVGp3w9YHEEA3v8AIN0gggFMl7ohggEznLqxn3Gw7UTQ0x/THzHXC//jBOCk8mCDCNcYINMf0x/TH/gjE7vyY+1E0NMf0x/T/9FRMrryoVFEuvKiBPkBVBBV+RDyo/gAkyDXSpbTB9QC+wDo0QGkyMsfyx/L/8ntVA==").unwrap().as_slice()).unwrap();
@@ -0,0 +1,436 @@
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use ton_abi::contract::AbiVersion;
+use ton_abi::{Param, ParamType, TokenValue};
+use ton_block::{Deserializable, MsgAddressInt, Serializable};
+use ton_types::{BuilderData, Cell, HashmapE, HashmapType, IBitstring, SliceData, UInt256};
+
+use super::unpack_from_cell;
+
+/// Read-only view over the raw `HashmapE` with typed keys.
+///
+/// Entries are decoded lazily while walking the tree, so lookups and
+/// prefix scans only load the relevant branches of huge dictionaries
+#[derive(Clone)]
+pub struct RawDict<K> {
+    root: Option<Cell>,
+    key_bits: usize,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<K: DictKey> RawDict<K> {
+    pub fn new(root: Option<Cell>, key_bits: usize) -> Self {
+        Self {
+            root,
+            key_bits,
+            _key: PhantomData,
+        }
+    }
+
+    pub fn from_hashmap(map: &HashmapE) -> Self {
+        Self::new(map.data().cloned(), map.bit_len())
+    }
+
+    pub fn key_bits(&self) -> usize {
+        self.key_bits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Searches the raw value by key
+    pub fn get(&self, key: &K) -> Result<Option<SliceData>> {
+        let key = key.write_key(self.key_bits)?;
+        if key.remaining_bits() != self.key_bits {
+            return Err(DictError::InvalidKeyLength {
+                expected: self.key_bits,
+                found: key.remaining_bits(),
+            }
+            .into());
+        }
+
+        match self.iter_prefix(&key)?.next() {
+            Some(item) => item.map(|(_, value)| Some(value)),
+            None => Ok(None),
+        }
+    }
+
+    /// Searches the value by key and unpacks it with the specified ABI type
+    pub fn get_token(
+        &self,
+        key: &K,
+        kind: &ParamType,
+        abi_version: AbiVersion,
+    ) -> Result<Option<TokenValue>> {
+        match self.get(key)? {
+            Some(value) => unpack_dict_value(value, kind, abi_version).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Iterates over all entries in ascending key order
+    pub fn iter(&self) -> DictIter<K> {
+        let mut iter = DictIter::empty();
+        if let Some(root) = &self.root {
+            iter.push(root.clone(), BuilderData::new(), self.key_bits);
+        }
+        iter
+    }
+
+    /// Iterates over entries which keys start with the specified bits.
+    ///
+    /// Only the subtree with the prefix is visited
+    pub fn iter_prefix(&self, prefix: &SliceData) -> Result<DictIter<K>> {
+        let root = match &self.root {
+            Some(root) if prefix.remaining_bits() <= self.key_bits => root,
+            _ => return Ok(DictIter::empty()),
+        };
+
+        let mut prefix = prefix.clone();
+        let mut cell = root.clone();
+        let mut key = BuilderData::new();
+        let mut remaining = self.key_bits;
+
+        loop {
+            if prefix.remaining_bits() == 0 {
+                let mut iter = DictIter::empty();
+                iter.push(cell, key, remaining);
+                return Ok(iter);
+            }
+
+            let mut slice = SliceData::load_cell(cell.clone())?;
+            let label = read_label(&mut slice, remaining)?;
+            let label_len = label.length_in_bits();
+
+            let mut label_bits = SliceData::load_builder(label.clone())?;
+            for _ in 0..std::cmp::min(label_len, prefix.remaining_bits()) {
+                if label_bits.get_next_bit()? != prefix.get_next_bit()? {
+                    return Ok(DictIter::empty());
+                }
+            }
+
+            if prefix.remaining_bits() == 0 {
+                // NOTE: prefix ends inside the label, so the whole node matches
+                let mut iter = DictIter::empty();
+                iter.push(cell, key, remaining);
+                return Ok(iter);
+            }
+
+            remaining -= label_len;
+            if remaining == 0 {
+                return Err(DictError::InvalidLabel.into());
+            }
+
+            let bit = prefix.get_next_bit()?;
+            key.append_builder(&label)?;
+            key.append_bit_bool(bit)?;
+            remaining -= 1;
+            cell = slice.reference(bit as usize)?;
+        }
+    }
+}
+
+/// Lazy iterator over the [`RawDict`] entries
+pub struct DictIter<K> {
+    stack: Vec<(Cell, BuilderData, usize)>,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<K: DictKey> DictIter<K> {
+    /// Unpacks each value with the specified ABI type
+    pub fn unpack_values(
+        self,
+        kind: ParamType,
+        abi_version: AbiVersion,
+    ) -> impl Iterator<Item = Result<(K, TokenValue)>> {
+        self.map(move |item| {
+            let (key, value) = item?;
+            Ok((key, unpack_dict_value(value, &kind, abi_version)?))
+        })
+    }
+
+    fn empty() -> Self {
+        Self {
+            stack: Vec::new(),
+            _key: PhantomData,
+        }
+    }
+
+    fn push(&mut self, cell: Cell, key: BuilderData, remaining: usize) {
+        self.stack.push((cell, key, remaining));
+    }
+
+    fn visit(
+        &mut self,
+        cell: Cell,
+        mut key: BuilderData,
+        remaining: usize,
+    ) -> Result<Option<(K, SliceData)>> {
+        let mut slice = SliceData::load_cell(cell)?;
+        let label = read_label(&mut slice, remaining)?;
+        let remaining = remaining - label.length_in_bits();
+        key.append_builder(&label)?;
+
+        if remaining == 0 {
+            let key = K::read_key(SliceData::load_builder(key)?)?;
+            return Ok(Some((key, slice)));
+        }
+
+        let left = slice.checked_drain_reference()?;
+        let right = slice.checked_drain_reference()?;
+
+        // NOTE: right branch is pushed first to visit keys in ascending order
+        for (bit, cell) in [(true, right), (false, left)] {
+            let mut key = key.clone();
+            key.append_bit_bool(bit)?;
+            self.push(cell, key, remaining - 1);
+        }
+        Ok(None)
+    }
+}
+
+impl<K: DictKey> Iterator for DictIter<K> {
+    type Item = Result<(K, SliceData)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((cell, key, remaining)) = self.stack.pop() {
+            match self.visit(cell, key, remaining) {
+                Ok(Some(item)) => return Some(Ok(item)),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.stack.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Unpacks the raw dictionary value with the specified ABI type
+pub fn unpack_dict_value(
+    value: SliceData,
+    kind: &ParamType,
+    abi_version: AbiVersion,
+) -> Result<TokenValue> {
+    let mut tokens = unpack_from_cell(
+        &[Param::new("value", kind.clone())],
+        value,
+        false,
+        abi_version,
+    )?;
+    match tokens.pop() {
+        Some(token) => Ok(token.value),
+        None => Err(DictError::InvalidValue.into()),
+    }
+}
+
+/// Reads `HmLabel` of the node with at most `max_len` key bits
+fn read_label(slice: &mut SliceData, max_len: usize) -> Result<BuilderData> {
+    let len_bits = (usize::BITS - max_len.leading_zeros()) as usize;
+    let read_len = |slice: &mut SliceData| -> Result<usize> {
+        Ok(match len_bits {
+            0 => 0,
+            bits => slice.get_next_int(bits)? as usize,
+        })
+    };
+
+    let mut label = BuilderData::new();
+    if !slice.get_next_bit()? {
+        // hml_short$0 len:(Unary ~n) s:(n * Bit)
+        let mut len = 0;
+        while slice.get_next_bit()? {
+            len += 1;
+        }
+        if len > max_len {
+            return Err(DictError::InvalidLabel.into());
+        }
+        if len > 0 {
+            label.append_raw(&slice.get_next_bits(len)?, len)?;
+        }
+    } else if !slice.get_next_bit()? {
+        // hml_long$10 n:(#<= m) s:(n * Bit)
+        let len = read_len(slice)?;
+        if len > max_len {
+            return Err(DictError::InvalidLabel.into());
+        }
+        if len > 0 {
+            label.append_raw(&slice.get_next_bits(len)?, len)?;
+        }
+    } else {
+        // hml_same$11 v:Bit n:(#<= m)
+        let value = slice.get_next_bit()?;
+        let len = read_len(slice)?;
+        if len > max_len {
+            return Err(DictError::InvalidLabel.into());
+        }
+        for _ in 0..len {
+            label.append_bit_bool(value)?;
+        }
+    }
+    Ok(label)
+}
+
+/// Typed key of the [`RawDict`]
+pub trait DictKey: Sized {
+    /// Serializes the key into exactly `bits` bits
+    fn write_key(&self, bits: usize) -> Result<SliceData>;
+
+    fn read_key(key: SliceData) -> Result<Self>;
+}
+
+macro_rules! impl_integer_key {
+    ($($ty:ty),*$(,)?) => {$(
+        impl DictKey for $ty {
+            fn write_key(&self, bits: usize) -> Result<SliceData> {
+                let value = u128::from(*self);
+                if bits > 128 || (bits < 128 && value >> bits != 0) {
+                    return Err(DictError::KeyOverflow { bits }.into());
+                }
+                let value = value.checked_shl((128 - bits) as u32).unwrap_or_default();
+
+                let mut builder = BuilderData::new();
+                builder.append_raw(&value.to_be_bytes(), bits)?;
+                SliceData::load_builder(builder)
+            }
+
+            fn read_key(mut key: SliceData) -> Result<Self> {
+                let bits = key.remaining_bits();
+                if bits > 128 {
+                    return Err(DictError::KeyOverflow { bits }.into());
+                }
+
+                let hi = match bits.saturating_sub(64) {
+                    0 => 0,
+                    hi_bits => key.get_next_int(hi_bits)? as u128,
+                };
+                let lo = match std::cmp::min(bits, 64) {
+                    0 => 0,
+                    lo_bits => key.get_next_int(lo_bits)? as u128,
+                };
+                let value = (hi << 64) | lo;
+
+                <$ty>::try_from(value).map_err(|_| DictError::KeyOverflow { bits }.into())
+            }
+        }
+    )*};
+}
+
+impl_integer_key!(u8, u16, u32, u64, u128);
+
+impl DictKey for UInt256 {
+    fn write_key(&self, _: usize) -> Result<SliceData> {
+        let mut builder = BuilderData::new();
+        builder.append_raw(self.as_slice(), 256)?;
+        SliceData::load_builder(builder)
+    }
+
+    fn read_key(mut key: SliceData) -> Result<Self> {
+        Ok(UInt256::from_be_bytes(&key.get_next_bytes(32)?))
+    }
+}
+
+impl DictKey for MsgAddressInt {
+    fn write_key(&self, _: usize) -> Result<SliceData> {
+        self.write_to_new_cell().and_then(SliceData::load_builder)
+    }
+
+    fn read_key(mut key: SliceData) -> Result<Self> {
+        MsgAddressInt::construct_from(&mut key)
+    }
+}
+
+/// Raw key bits
+impl DictKey for SliceData {
+    fn write_key(&self, _: usize) -> Result<SliceData> {
+        Ok(self.clone())
+    }
+
+    fn read_key(key: SliceData) -> Result<Self> {
+        Ok(key)
+    }
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum DictError {
+    #[error("Invalid key length: expected {expected} bits, found {found}")]
+    InvalidKeyLength { expected: usize, found: usize },
+    #[error("Key doesn't fit into {bits} bits")]
+    KeyOverflow { bits: usize },
+    #[error("Invalid dictionary label")]
+    InvalidLabel,
+    #[error("Invalid dictionary value")]
+    InvalidValue,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_dict(keys: impl IntoIterator<Item = u32>) -> HashmapE {
+        let mut map = HashmapE::with_bit_len(32);
+        for key in keys {
+            let mut value = BuilderData::new();
+            value.append_u64(key as u64 * 10).unwrap();
+            map.set_builder(key.write_key(32).unwrap(), &value).unwrap();
+        }
+        map
+    }
+
+    #[test]
+    fn typed_lookup() {
+        let dict = RawDict::<u32>::from_hashmap(&make_dict([1, 2, 1000, 0x01000000]));
+        assert!(!dict.is_empty());
+
+        let mut value = dict.get(&1000).unwrap().unwrap();
+        assert_eq!(value.get_next_u64().unwrap(), 10000);
+        assert!(dict.get(&3).unwrap().is_none());
+
+        let token = dict
+            .get_token(&2, &ParamType::Uint(64), ton_abi::contract::ABI_VERSION_2_0)
+            .unwrap();
+        assert_eq!(token, Some(TokenValue::Uint(ton_abi::Uint::new(20, 64))));
+
+        assert!(RawDict::<u64>::new(None, 32).get(&1).unwrap().is_none());
+        assert!(RawDict::<u64>::from_hashmap(&make_dict([1]))
+            .get(&(u32::MAX as u64 + 1))
+            .is_err());
+    }
+
+    #[test]
+    fn lazy_iteration() {
+        let keys = [5, 1, 0x01000001, 0x01000000, 0x02000000, 0x010000ff];
+        let dict = RawDict::<u32>::from_hashmap(&make_dict(keys));
+
+        let mut sorted = keys.to_vec();
+        sorted.sort_unstable();
+        let all = dict
+            .iter()
+            .map(|item| item.map(|(key, _)| key))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(all, sorted);
+
+        let prefix = 0x01u8.write_key(8).unwrap();
+        let values = dict
+            .iter_prefix(&prefix)
+            .unwrap()
+            .unpack_values(ParamType::Uint(64), ton_abi::contract::ABI_VERSION_2_0)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            values,
+            [0x01000000, 0x01000001, 0x010000ff].map(|key: u32| {
+                (
+                    key,
+                    TokenValue::Uint(ton_abi::Uint::new(key as u128 * 10, 64)),
+                )
+            })
+        );
+
+        let prefix = 0x03u8.write_key(8).unwrap();
+        assert_eq!(dict.iter_prefix(&prefix).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,375 @@
+use std::fmt;
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::ToPrimitive;
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{IntoDeserializer, Visitor};
+use serde::Deserializer;
+use ton_abi::{Token, TokenValue};
+
+/// Deserializes any `serde` compatible type from the ABI tokens.
+///
+/// Tokens are treated as a struct with the token names as fields,
+/// or as a tuple when the type expects a sequence
+pub fn deserialize_tokens<T>(tokens: Vec<Token>) -> Result<T, TokenDeserializerError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    T::deserialize(TokenValueDeserializer::new(TokenValue::Tuple(tokens)))
+}
+
+/// Deserializes any `serde` compatible type from the ABI token value
+pub fn deserialize_token_value<T>(value: TokenValue) -> Result<T, TokenDeserializerError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    T::deserialize(TokenValueDeserializer::new(value))
+}
+
+/// `serde` deserializer over the ABI token value.
+///
+/// Values are represented the same way as in [`make_abi_token_value`](super::make_abi_token_value):
+/// addresses and big numbers can be read as strings, cells as base64 encoded BOCs and
+/// public keys as hex strings. Enums are read from their index or name
+pub struct TokenValueDeserializer(TokenValue);
+
+impl TokenValueDeserializer {
+    pub fn new(mut value: TokenValue) -> Self {
+        // NOTE: refs are transparent for the deserialized values
+        while let TokenValue::Ref(inner) = value {
+            value = *inner;
+        }
+        Self(value)
+    }
+}
+
+impl<'de> Deserializer<'de> for TokenValueDeserializer {
+    type Error = TokenDeserializerError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            TokenValue::Uint(value) => visit_biguint(value.number, visitor),
+            TokenValue::VarUint(_, value) => visit_biguint(value, visitor),
+            TokenValue::Int(value) => visit_bigint(value.number, visitor),
+            TokenValue::VarInt(_, value) => visit_bigint(value, visitor),
+            TokenValue::Bool(value) => visitor.visit_bool(value),
+            TokenValue::Tuple(tokens) => visitor.visit_map(MapDeserializer::new(
+                tokens
+                    .into_iter()
+                    .map(|token| (token.name, TokenValueDeserializer::new(token.value))),
+            )),
+            TokenValue::Array(_, values) | TokenValue::FixedArray(_, values) => visitor.visit_seq(
+                SeqDeserializer::new(values.into_iter().map(TokenValueDeserializer::new)),
+            ),
+            TokenValue::Cell(value) => {
+                let data = ton_types::serialize_toc(&value)
+                    .map_err(|e| TokenDeserializerError::Custom(e.to_string()))?;
+                visitor.visit_string(base64::encode(data))
+            }
+            TokenValue::Map(_, _, values) => visitor.visit_map(MapDeserializer::new(
+                values.into_iter().map(|(key, value)| {
+                    (
+                        TokenValueDeserializer::new(key.into()),
+                        TokenValueDeserializer::new(value),
+                    )
+                }),
+            )),
+            TokenValue::Address(value) | TokenValue::AddressStd(value) => {
+                visitor.visit_string(value.to_string())
+            }
+            TokenValue::Bytes(value) | TokenValue::FixedBytes(value) => {
+                visitor.visit_byte_buf(value)
+            }
+            TokenValue::String(value) => visitor.visit_string(value),
+            TokenValue::Token(value) => visit_biguint(BigUint::from(value.as_u128()), visitor),
+            TokenValue::Time(value) => visitor.visit_u64(value),
+            TokenValue::Expire(value) => visitor.visit_u32(value),
+            TokenValue::PublicKey(Some(value)) => {
+                visitor.visit_string(hex::encode(value.as_bytes()))
+            }
+            TokenValue::PublicKey(None) | TokenValue::Optional(_, None) => visitor.visit_none(),
+            TokenValue::Optional(_, Some(value)) => {
+                visitor.visit_some(TokenValueDeserializer::new(*value))
+            }
+            TokenValue::Ref(value) => TokenValueDeserializer::new(*value).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            TokenValue::Optional(..) | TokenValue::PublicKey(None) => self.deserialize_any(visitor),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            TokenValue::Uint(value) => visitor.visit_string(value.number.to_string()),
+            TokenValue::Int(value) => visitor.visit_string(value.number.to_string()),
+            TokenValue::VarUint(_, value) => visitor.visit_string(value.to_string()),
+            TokenValue::VarInt(_, value) => visitor.visit_string(value.to_string()),
+            TokenValue::Token(value) => visitor.visit_string(value.as_u128().to_string()),
+            TokenValue::Time(value) => visitor.visit_string(value.to_string()),
+            TokenValue::Expire(value) => visitor.visit_string(value.to_string()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            TokenValue::Tuple(tokens) => visitor.visit_seq(SeqDeserializer::new(
+                tokens
+                    .into_iter()
+                    .map(|token| TokenValueDeserializer::new(token.value)),
+            )),
+            TokenValue::Bytes(value) | TokenValue::FixedBytes(value) => {
+                visitor.visit_seq(SeqDeserializer::new(value.into_iter()))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match &self.0 {
+            TokenValue::Tuple(tokens) if tokens.is_empty() => visitor.visit_unit(),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        // NOTE: only unit variants are supported, ABI enums are encoded as integers
+        match self.0 {
+            TokenValue::Uint(value) => match value.number.to_u32() {
+                Some(index) => visitor.visit_enum(index.into_deserializer()),
+                None => Err(TokenDeserializerError::InvalidEnumIndex(value.number)),
+            },
+            TokenValue::String(value) => visitor.visit_enum(value.into_deserializer()),
+            value => Err(TokenDeserializerError::UnexpectedToken {
+                expected: "enum",
+                found: format!("{value:?}"),
+            }),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
+        bytes byte_buf map struct identifier
+    }
+}
+
+impl<'de> IntoDeserializer<'de, TokenDeserializerError> for TokenValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+fn visit_biguint<'de, V>(number: BigUint, visitor: V) -> Result<V::Value, TokenDeserializerError>
+where
+    V: Visitor<'de>,
+{
+    if let Some(number) = number.to_u64() {
+        visitor.visit_u64(number)
+    } else if let Some(number) = number.to_u128() {
+        visitor.visit_u128(number)
+    } else {
+        visitor.visit_string(number.to_string())
+    }
+}
+
+fn visit_bigint<'de, V>(number: BigInt, visitor: V) -> Result<V::Value, TokenDeserializerError>
+where
+    V: Visitor<'de>,
+{
+    if let Some(number) = number.to_i64() {
+        visitor.visit_i64(number)
+    } else if let Some(number) = number.to_i128() {
+        visitor.visit_i128(number)
+    } else {
+        visitor.visit_string(number.to_string())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum TokenDeserializerError {
+    #[error("{0}")]
+    Custom(String),
+    #[error("Expected {expected}, found {found}")]
+    UnexpectedToken {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("Invalid enum index: {0}")]
+    InvalidEnumIndex(BigUint),
+}
+
+impl serde::de::Error for TokenDeserializerError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+    use ton_block::MsgAddressInt;
+
+    use super::*;
+    use crate::{BuildTokenValue, TokenValueExt};
+
+    #[derive(Debug, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Output {
+        value: u32,
+        #[serde(with = "nekoton_utils::serde_address")]
+        owner: MsgAddressInt,
+        payload: Vec<u8>,
+        maybe_amount: Option<u64>,
+        total_supply: String,
+        status: Status,
+        inner: Inner,
+        items: Vec<u16>,
+        flags: HashMap<u32, bool>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Deserialize)]
+    struct Inner(i8, bool);
+
+    #[derive(Debug, PartialEq, Eq, Deserialize)]
+    enum Status {
+        Uninit,
+        Active,
+    }
+
+    #[test]
+    fn deserialize_struct() {
+        let owner = MsgAddressInt::default();
+        let tokens = vec![
+            123u32.token_value().named("value"),
+            owner.clone().token_value().named("owner"),
+            vec![1u8, 2, 3].token_value().named("payload"),
+            Some(10u64).token_value().named("maybeAmount"),
+            TokenValue::Uint(ton_abi::Uint::new(u128::MAX, 256)).named("totalSupply"),
+            1u8.token_value().named("status"),
+            TokenValue::Tuple(vec![
+                (-1i8).token_value().named("a"),
+                true.token_value().named("b"),
+            ])
+            .named("inner"),
+            vec![1u16, 2].token_value().named("items"),
+            HashMap::from([(1u32, true)]).token_value().named("flags"),
+        ];
+
+        let output: Output = deserialize_tokens(tokens).unwrap();
+        assert_eq!(
+            output,
+            Output {
+                value: 123,
+                owner,
+                payload: vec![1, 2, 3],
+                maybe_amount: Some(10),
+                total_supply: u128::MAX.to_string(),
+                status: Status::Active,
+                inner: Inner(-1, true),
+                items: vec![1, 2],
+                flags: HashMap::from([(1, true)]),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_tuple() {
+        let tokens = vec![
+            1u64.token_value().unnamed(),
+            "test".token_value().unnamed(),
+            None::<u8>.token_value().unnamed(),
+        ];
+
+        let output: (u64, String, Option<u8>) = deserialize_tokens(tokens).unwrap();
+        assert_eq!(output, (1, "test".to_owned(), None));
+
+        assert!(deserialize_token_value::<u8>(300u16.token_value()).is_err());
+        assert!(deserialize_token_value::<Status>(2u8.token_value()).is_err());
+    }
+}
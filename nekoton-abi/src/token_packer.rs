@@ -1,12 +1,15 @@
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap};
 use std::hash::BuildHasher;
+use std::sync::Arc;
 
 use num_bigint::{BigInt, BigUint};
+use smallvec::SmallVec;
 use ton_abi::{MapKeyTokenValue, Token, TokenValue};
 use ton_block::{MsgAddrStd, MsgAddress, MsgAddressInt};
 use ton_types::{BuilderData, Cell};
 
-use super::{KnownParamType, MaybeRef, StandaloneToken};
+use super::{Int256, KnownParamType, MaybeRef, StandaloneToken};
 
 pub trait PackAbiPlain {
     fn pack(self) -> Vec<Token>;
@@ -69,12 +72,69 @@ impl BuildMapKeyTokenValue for ton_types::UInt256 {
     }
 }
 
+/// Public key is stored as `uint256`
+impl BuildTokenValue for ed25519_dalek::PublicKey {
+    fn token_value(self) -> TokenValue {
+        self.map_key_token_value().into()
+    }
+}
+
+impl BuildMapKeyTokenValue for ed25519_dalek::PublicKey {
+    fn map_key_token_value(self) -> MapKeyTokenValue {
+        MapKeyTokenValue::Uint(ton_abi::Uint {
+            number: BigUint::from_bytes_be(self.as_bytes()),
+            size: 256,
+        })
+    }
+}
+
+impl BuildTokenValue for Int256 {
+    fn token_value(self) -> TokenValue {
+        self.map_key_token_value().into()
+    }
+}
+
+impl BuildMapKeyTokenValue for Int256 {
+    fn map_key_token_value(self) -> MapKeyTokenValue {
+        MapKeyTokenValue::Int(ton_abi::Int {
+            number: self.to_bigint(),
+            size: 256,
+        })
+    }
+}
+
 impl BuildTokenValue for bool {
     fn token_value(self) -> TokenValue {
         TokenValue::Bool(self)
     }
 }
 
+/// Converts time into the unix timestamp in seconds which can be packed as `uint64`
+pub trait UnixTimestamp {
+    fn unix_timestamp(&self) -> Result<u64, TimestampError>;
+}
+
+impl UnixTimestamp for std::time::SystemTime {
+    fn unix_timestamp(&self) -> Result<u64, TimestampError> {
+        self.duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .map_err(|_| TimestampError::BeforeEpoch)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl UnixTimestamp for chrono::DateTime<chrono::Utc> {
+    fn unix_timestamp(&self) -> Result<u64, TimestampError> {
+        u64::try_from(self.timestamp()).map_err(|_| TimestampError::BeforeEpoch)
+    }
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TimestampError {
+    #[error("Time is before the unix epoch")]
+    BeforeEpoch,
+}
+
 impl BuildTokenValue for Cell {
     fn token_value(self) -> TokenValue {
         TokenValue::Cell(self)
@@ -108,6 +168,18 @@ impl BuildMapKeyTokenValue for MsgAddrStd {
     }
 }
 
+impl BuildTokenValue for MsgAddress {
+    fn token_value(self) -> TokenValue {
+        TokenValue::Address(self)
+    }
+}
+
+impl BuildMapKeyTokenValue for MsgAddress {
+    fn map_key_token_value(self) -> MapKeyTokenValue {
+        MapKeyTokenValue::Address(self)
+    }
+}
+
 impl BuildTokenValue for &str {
     fn token_value(self) -> TokenValue {
         TokenValue::String(self.to_string())
@@ -120,6 +192,12 @@ impl BuildTokenValue for String {
     }
 }
 
+impl BuildTokenValue for Cow<'_, str> {
+    fn token_value(self) -> TokenValue {
+        TokenValue::String(self.into_owned())
+    }
+}
+
 impl BuildTokenValue for ton_block::Grams {
     fn token_value(self) -> TokenValue {
         TokenValue::Token(self)
@@ -132,6 +210,20 @@ impl BuildTokenValue for Vec<u8> {
     }
 }
 
+#[cfg(feature = "bytes")]
+impl BuildTokenValue for bytes::Bytes {
+    fn token_value(self) -> TokenValue {
+        // NOTE: doesn't copy the buffer if it is not shared
+        TokenValue::Bytes(Vec::from(self))
+    }
+}
+
+impl<const N: usize> BuildTokenValue for SmallVec<[u8; N]> {
+    fn token_value(self) -> TokenValue {
+        TokenValue::Bytes(self.into_vec())
+    }
+}
+
 impl BuildTokenValue for BuilderData {
     fn token_value(self) -> TokenValue {
         TokenValue::Cell(self.into_cell().unwrap())
@@ -175,6 +267,18 @@ where
     }
 }
 
+impl<T, const N: usize> BuildTokenValue for [T; N]
+where
+    T: KnownParamType + BuildTokenValue,
+{
+    fn token_value(self) -> TokenValue {
+        TokenValue::FixedArray(
+            T::param_type(),
+            self.into_iter().map(BuildTokenValue::token_value).collect(),
+        )
+    }
+}
+
 impl<K, V> BuildTokenValue for BTreeMap<K, V>
 where
     K: KnownParamType + BuildMapKeyTokenValue,
@@ -204,6 +308,55 @@ where
     }
 }
 
+macro_rules! impl_build_tuple {
+    ($($idx:tt $ty:ident),+) => {
+        impl<$($ty),+> BuildTokenValue for ($($ty,)+)
+        where
+            $($ty: BuildTokenValue),+
+        {
+            fn token_value(self) -> TokenValue {
+                TokenValue::Tuple(vec![$(Token::new(
+                    concat!("value", stringify!($idx)),
+                    self.$idx.token_value(),
+                )),+])
+            }
+        }
+    };
+}
+
+impl_build_tuple!(0 T0);
+impl_build_tuple!(0 T0, 1 T1);
+impl_build_tuple!(0 T0, 1 T1, 2 T2);
+impl_build_tuple!(0 T0, 1 T1, 2 T2, 3 T3);
+impl_build_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4);
+impl_build_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5);
+impl_build_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6);
+impl_build_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7);
+impl_build_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8);
+impl_build_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9);
+impl_build_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10);
+impl_build_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10, 11 T11);
+
+impl<T> BuildTokenValue for Box<T>
+where
+    T: BuildTokenValue,
+{
+    fn token_value(self) -> TokenValue {
+        (*self).token_value()
+    }
+}
+
+impl<T> BuildTokenValue for Arc<T>
+where
+    T: BuildTokenValue + Clone,
+{
+    fn token_value(self) -> TokenValue {
+        Arc::try_unwrap(self)
+            .unwrap_or_else(|value| value.as_ref().clone())
+            .token_value()
+    }
+}
+
 impl BuildTokenValue for TokenValue {
     fn token_value(self) -> TokenValue {
         self
@@ -221,7 +374,28 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+
     use super::*;
+    use crate::{BigUint128, BigUint256, UnpackAbi, VarUint16};
+
+    /// Packs the value, checks that the token matches its param type and unpacks it back
+    fn round_trip<T>(value: T) -> T
+    where
+        T: BuildTokenValue + KnownParamType,
+        TokenValue: UnpackAbi<T>,
+    {
+        let token = value.token_value();
+        assert_eq!(token.get_param_type(), T::param_type());
+        token.unpack().unwrap()
+    }
+
+    macro_rules! assert_round_trip {
+        ($($value:expr),+$(,)?) => {$(
+            let value = $value;
+            assert_eq!(round_trip(value.clone()), value);
+        )+};
+    }
 
     #[test]
     fn test_maybe_ref_serialization() {
@@ -251,4 +425,73 @@ mod tests {
         let serialized = base64::encode(ton_types::serialize_toc(&value).unwrap());
         assert_eq!(serialized, "te6ccgEBAQEAAwAAAUA=");
     }
+
+    #[test]
+    fn test_nested_containers() {
+        assert_round_trip!(
+            vec![vec![1u32, 2], vec![], vec![3]],
+            Some(vec![1u64, 2]),
+            vec![Some(1u32), None],
+            vec![BTreeMap::from([(1u32, true)]), BTreeMap::new()],
+            vec!["a".to_owned(), "b".to_owned()],
+            vec![vec![0u8, 1], vec![2]],
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let address = MsgAddressInt::from_str(
+            "0:18c99afffe13d3081370f77c10fc4d51bc54e52b8e181db6a0e8bb75456d91ff",
+        )
+        .unwrap();
+        let address_std = match address.clone() {
+            MsgAddressInt::AddrStd(address) => address,
+            MsgAddressInt::AddrVar(_) => unreachable!(),
+        };
+        let public_key = ed25519_dalek::PublicKey::from(
+            &ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap(),
+        );
+
+        assert_round_trip!(
+            -1i8,
+            u8::MAX,
+            -1i16,
+            u16::MAX,
+            -1i32,
+            u32::MAX,
+            i64::MIN,
+            u64::MAX,
+            i128::MIN,
+            u128::MAX,
+            true,
+            ton_types::UInt256::with_array([1; 32]),
+            public_key,
+            Int256([0xff; 32]),
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000),
+            Cell::default(),
+            address.clone(),
+            address_std.clone(),
+            MsgAddress::AddrStd(address_std.clone()),
+            "hello".to_owned(),
+            ton_block::Grams::from(123u64),
+            vec![1u8, 2, 3],
+            BigUint128(BigUint::from(u128::MAX)),
+            BigUint256(BigUint::from(u128::MAX) << 64),
+            VarUint16::new(1_000_000u64).unwrap(),
+            Some(1u32),
+            None::<u32>,
+            Box::new(1u32),
+            Arc::new(1u32),
+            [1u32, 2, 3],
+            (1u32, true, "a".to_owned()),
+            HashMap::<_, _>::from([(address.clone(), 1u32)]),
+            BTreeMap::from([(MsgAddress::AddrStd(address_std.clone()), 1u32)]),
+            HashMap::<_, _>::from([(address_std, vec![1u32])]),
+            BTreeMap::from([(ton_types::UInt256::with_array([2; 32]), Some(1u32))]),
+            HashMap::<i64, u32>::from([(-1, 1)]),
+        );
+
+        let value = round_trip(MaybeRef(Some(123u32)));
+        assert_eq!(value.0, Some(123));
+    }
 }
@@ -1,14 +1,16 @@
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap};
 use std::hash::{BuildHasher, Hash};
 use std::sync::Arc;
 
 use num_bigint::{BigInt, BigUint};
 use num_traits::ToPrimitive;
-use ton_abi::{Token, TokenValue};
+use smallvec::SmallVec;
+use ton_abi::{MapKeyTokenValue, Token, TokenValue};
 use ton_block::{MsgAddrStd, MsgAddress, MsgAddressInt};
 use ton_types::Cell;
 
-use super::{MaybeRef, StandaloneToken};
+use super::{Int256, MaybeRef, StandaloneToken};
 
 pub trait TokenValueExt {
     fn unnamed(self) -> Token;
@@ -76,6 +78,59 @@ impl<I: Iterator<Item = Token>> ContractOutputUnpacker<I> {
     {
         self.0.next().unpack()
     }
+
+    /// Ensures that all tokens were consumed
+    pub fn finish(self) -> UnpackerResult<()> {
+        match self.0.count() {
+            0 => Ok(()),
+            count => Err(UnpackerError::TrailingTokens { count }),
+        }
+    }
+}
+
+/// Tokens indexed by their names.
+///
+/// Used to unpack structs from ABIs with unstable field ordering
+#[derive(Debug)]
+pub struct NamedTokens(HashMap<String, Option<TokenValue>>);
+
+impl NamedTokens {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let mut map = HashMap::with_capacity(tokens.len());
+        for token in tokens {
+            match map.entry(token.name) {
+                // Mark duplicates to fail only when they are requested
+                std::collections::hash_map::Entry::Occupied(mut entry) => *entry.get_mut() = None,
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(Some(token.value));
+                }
+            }
+        }
+        Self(map)
+    }
+
+    /// Removes the token with the specified name
+    pub fn take(&mut self, name: &'static str) -> UnpackerResult<Token> {
+        match self.0.remove(name) {
+            Some(Some(value)) => Ok(value.named(name)),
+            Some(None) => Err(UnpackerError::DuplicateField { name }),
+            None => Err(UnpackerError::MissingField { name }),
+        }
+    }
+
+    /// Removes the token with the specified name and unpacks its value
+    pub fn unpack<T>(&mut self, name: &'static str) -> UnpackerResult<T>
+    where
+        TokenValue: UnpackAbi<T>,
+    {
+        self.take(name)?.unpack()
+    }
+}
+
+impl From<Vec<Token>> for NamedTokens {
+    fn from(tokens: Vec<Token>) -> Self {
+        Self::new(tokens)
+    }
 }
 
 pub trait UnpackAbiPlain<T>: FunctionOutputMarker {
@@ -172,6 +227,9 @@ impl UnpackAbi<u128> for TokenValue {
 impl UnpackAbi<ton_types::UInt256> for TokenValue {
     fn unpack(self) -> UnpackerResult<ton_types::UInt256> {
         match self {
+            TokenValue::FixedBytes(bytes) if bytes.len() == 32 => {
+                Ok(ton_types::UInt256::from_be_bytes(&bytes))
+            }
             TokenValue::Uint(ton_abi::Uint { number, size: 256 }) => {
                 let mut result = [0u8; 32];
                 let data = number.to_bytes_be();
@@ -182,16 +240,66 @@ impl UnpackAbi<ton_types::UInt256> for TokenValue {
 
                 Ok(result.into())
             }
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<ton_types::UInt256>(&value)),
+        }
+    }
+}
+
+/// Unpacks `uint256` (or `pubkey` header) and checks that it is a valid curve point
+impl UnpackAbi<ed25519_dalek::PublicKey> for TokenValue {
+    fn unpack(self) -> UnpackerResult<ed25519_dalek::PublicKey> {
+        match self {
+            TokenValue::PublicKey(Some(public_key)) => Ok(public_key),
+            value @ TokenValue::Uint(ton_abi::Uint { size: 256, .. }) => {
+                let bytes: ton_types::UInt256 = value.unpack()?;
+                ed25519_dalek::PublicKey::from_bytes(bytes.as_slice())
+                    .map_err(|_| UnpackerError::InvalidAbi)
+            }
+            value => Err(UnpackerError::unexpected::<ed25519_dalek::PublicKey>(
+                &value,
+            )),
         }
     }
 }
 
+impl UnpackAbi<Int256> for TokenValue {
+    fn unpack(self) -> UnpackerResult<Int256> {
+        match self {
+            TokenValue::Int(ton_abi::Int { number, size: 256 }) => {
+                Int256::from_bigint(&number).ok_or(UnpackerError::InvalidAbi)
+            }
+            value => Err(UnpackerError::unexpected::<Int256>(&value)),
+        }
+    }
+}
+
+/// Unpacks unix timestamp in seconds (e.g. `uint32 now`)
+impl UnpackAbi<std::time::SystemTime> for TokenValue {
+    fn unpack(self) -> UnpackerResult<std::time::SystemTime> {
+        let seconds: u64 = self.unpack()?;
+        std::time::UNIX_EPOCH
+            .checked_add(std::time::Duration::from_secs(seconds))
+            .ok_or(UnpackerError::InvalidAbi)
+    }
+}
+
+/// Unpacks unix timestamp in seconds (e.g. `uint32 now`)
+#[cfg(feature = "chrono")]
+impl UnpackAbi<chrono::DateTime<chrono::Utc>> for TokenValue {
+    fn unpack(self) -> UnpackerResult<chrono::DateTime<chrono::Utc>> {
+        let seconds: u64 = self.unpack()?;
+        i64::try_from(seconds)
+            .ok()
+            .and_then(|seconds| chrono::TimeZone::timestamp_opt(&chrono::Utc, seconds, 0).single())
+            .ok_or(UnpackerError::InvalidAbi)
+    }
+}
+
 impl UnpackAbi<bool> for TokenValue {
     fn unpack(self) -> UnpackerResult<bool> {
         match self {
             TokenValue::Bool(confirmed) => Ok(confirmed),
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<bool>(&value)),
         }
     }
 }
@@ -200,7 +308,8 @@ impl UnpackAbi<Cell> for TokenValue {
     fn unpack(self) -> UnpackerResult<Cell> {
         match self {
             TokenValue::Cell(cell) => Ok(cell),
-            _ => Err(UnpackerError::InvalidAbi),
+            TokenValue::Ref(value) => value.unpack(),
+            value => Err(UnpackerError::unexpected::<Cell>(&value)),
         }
     }
 }
@@ -208,13 +317,14 @@ impl UnpackAbi<Cell> for TokenValue {
 impl UnpackAbi<MsgAddressInt> for TokenValue {
     fn unpack(self) -> UnpackerResult<MsgAddressInt> {
         match self {
-            TokenValue::Address(ton_block::MsgAddress::AddrStd(addr)) => {
+            TokenValue::Address(ton_block::MsgAddress::AddrStd(addr))
+            | TokenValue::AddressStd(ton_block::MsgAddress::AddrStd(addr)) => {
                 Ok(MsgAddressInt::AddrStd(addr))
             }
             TokenValue::Address(ton_block::MsgAddress::AddrVar(addr)) => {
                 Ok(MsgAddressInt::AddrVar(addr))
             }
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<MsgAddressInt>(&value)),
         }
     }
 }
@@ -222,8 +332,8 @@ impl UnpackAbi<MsgAddressInt> for TokenValue {
 impl UnpackAbi<MsgAddress> for TokenValue {
     fn unpack(self) -> UnpackerResult<MsgAddress> {
         match self {
-            TokenValue::Address(address) => Ok(address),
-            _ => Err(UnpackerError::InvalidAbi),
+            TokenValue::Address(address) | TokenValue::AddressStd(address) => Ok(address),
+            value => Err(UnpackerError::unexpected::<MsgAddress>(&value)),
         }
     }
 }
@@ -231,8 +341,9 @@ impl UnpackAbi<MsgAddress> for TokenValue {
 impl UnpackAbi<MsgAddrStd> for TokenValue {
     fn unpack(self) -> UnpackerResult<MsgAddrStd> {
         match self {
-            TokenValue::Address(ton_block::MsgAddress::AddrStd(addr)) => Ok(addr),
-            _ => Err(UnpackerError::InvalidAbi),
+            TokenValue::Address(ton_block::MsgAddress::AddrStd(addr))
+            | TokenValue::AddressStd(ton_block::MsgAddress::AddrStd(addr)) => Ok(addr),
+            value => Err(UnpackerError::unexpected::<MsgAddrStd>(&value)),
         }
     }
 }
@@ -241,16 +352,23 @@ impl UnpackAbi<String> for TokenValue {
     fn unpack(self) -> UnpackerResult<String> {
         match self {
             TokenValue::String(data) => Ok(data),
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<String>(&value)),
         }
     }
 }
 
+impl UnpackAbi<Cow<'static, str>> for TokenValue {
+    fn unpack(self) -> UnpackerResult<Cow<'static, str>> {
+        UnpackAbi::<String>::unpack(self).map(Cow::Owned)
+    }
+}
+
 impl UnpackAbi<BigInt> for TokenValue {
     fn unpack(self) -> UnpackerResult<BigInt> {
         match self {
             TokenValue::Int(data) => Ok(data.number),
-            _ => Err(UnpackerError::InvalidAbi),
+            TokenValue::VarInt(_, number) => Ok(number),
+            value => Err(UnpackerError::unexpected::<BigInt>(&value)),
         }
     }
 }
@@ -259,7 +377,8 @@ impl UnpackAbi<BigUint> for TokenValue {
     fn unpack(self) -> UnpackerResult<BigUint> {
         match self {
             TokenValue::Uint(data) => Ok(data.number),
-            _ => Err(UnpackerError::InvalidAbi),
+            TokenValue::VarUint(_, number) => Ok(number),
+            value => Err(UnpackerError::unexpected::<BigUint>(&value)),
         }
     }
 }
@@ -267,12 +386,25 @@ impl UnpackAbi<BigUint> for TokenValue {
 impl UnpackAbi<Vec<u8>> for TokenValue {
     fn unpack(self) -> UnpackerResult<Vec<u8>> {
         match self {
-            TokenValue::Bytes(bytes) => Ok(bytes),
-            _ => Err(UnpackerError::InvalidAbi),
+            TokenValue::Bytes(bytes) | TokenValue::FixedBytes(bytes) => Ok(bytes),
+            value => Err(UnpackerError::unexpected::<Vec<u8>>(&value)),
         }
     }
 }
 
+#[cfg(feature = "bytes")]
+impl UnpackAbi<bytes::Bytes> for TokenValue {
+    fn unpack(self) -> UnpackerResult<bytes::Bytes> {
+        UnpackAbi::<Vec<u8>>::unpack(self).map(bytes::Bytes::from)
+    }
+}
+
+impl<const N: usize> UnpackAbi<SmallVec<[u8; N]>> for TokenValue {
+    fn unpack(self) -> UnpackerResult<SmallVec<[u8; N]>> {
+        UnpackAbi::<Vec<u8>>::unpack(self).map(SmallVec::from_vec)
+    }
+}
+
 impl<T> UnpackAbi<Vec<T>> for TokenValue
 where
     TokenValue: UnpackAbi<T>,
@@ -282,12 +414,40 @@ where
         match self {
             TokenValue::Array(_, tokens) | TokenValue::FixedArray(_, tokens) => {
                 let mut vec = Vec::with_capacity(tokens.len());
-                for token in tokens {
-                    vec.push(token.unpack()?);
+                for (i, token) in tokens.into_iter().enumerate() {
+                    vec.push(token.unpack().map_err(|e| e.with_index(i))?);
                 }
                 Ok(vec)
             }
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<Vec<T>>(&value)),
+        }
+    }
+}
+
+impl<T, const N: usize> UnpackAbi<[T; N]> for TokenValue
+where
+    TokenValue: UnpackAbi<T>,
+{
+    fn unpack(self) -> UnpackerResult<[T; N]> {
+        match self {
+            TokenValue::Array(_, tokens) | TokenValue::FixedArray(_, tokens) => {
+                if tokens.len() != N {
+                    return Err(UnpackerError::InvalidLength {
+                        expected: N,
+                        found: tokens.len(),
+                    });
+                }
+
+                let mut vec = Vec::with_capacity(N);
+                for (i, token) in tokens.into_iter().enumerate() {
+                    vec.push(token.unpack().map_err(|e| e.with_index(i))?);
+                }
+                match vec.try_into() {
+                    Ok(array) => Ok(array),
+                    Err(_) => Err(UnpackerError::InvalidAbi),
+                }
+            }
+            value => Err(UnpackerError::unexpected::<[T; N]>(&value)),
         }
     }
 }
@@ -296,7 +456,7 @@ impl UnpackAbi<ton_block::Grams> for TokenValue {
     fn unpack(self) -> UnpackerResult<ton_block::Grams> {
         match self {
             TokenValue::Token(grams) => Ok(grams),
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<ton_block::Grams>(&value)),
         }
     }
 }
@@ -311,13 +471,12 @@ where
             TokenValue::Map(_, _, values) => {
                 let mut map = BTreeMap::<K, V>::new();
                 for (key, value) in values {
-                    let key = TokenValue::from(key.to_owned()).unpack()?;
-                    let value: V = value.to_owned().unpack()?;
+                    let (key, value) = unpack_map_entry(key, value)?;
                     map.insert(key, value);
                 }
                 Ok(map)
             }
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<BTreeMap<K, V>>(&value)),
         }
     }
 }
@@ -333,17 +492,66 @@ where
             TokenValue::Map(_, _, values) => {
                 let mut map = HashMap::with_capacity_and_hasher(values.len(), Default::default());
                 for (key, value) in values {
-                    let key = TokenValue::from(key.to_owned()).unpack()?;
-                    let value = value.to_owned().unpack()?;
+                    let (key, value) = unpack_map_entry(key, value)?;
                     map.insert(key, value);
                 }
                 Ok(map)
             }
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<HashMap<K, V, S>>(&value)),
         }
     }
 }
 
+/// Lazily unpacks map entries, consuming the map token.
+///
+/// Useful for large dictionaries which are processed sequentially
+pub fn unpack_map_iter<K, V>(value: TokenValue) -> UnpackerResult<MapEntries<K, V>>
+where
+    TokenValue: UnpackAbi<K> + UnpackAbi<V>,
+{
+    match value {
+        TokenValue::Map(_, _, values) => Ok(MapEntries {
+            entries: values.into_iter(),
+            _marker: std::marker::PhantomData,
+        }),
+        value => Err(UnpackerError::unexpected::<BTreeMap<K, V>>(&value)),
+    }
+}
+
+/// Iterator over the unpacked map entries. See [`unpack_map_iter`]
+#[derive(Debug)]
+pub struct MapEntries<K, V> {
+    entries: std::collections::btree_map::IntoIter<MapKeyTokenValue, TokenValue>,
+    _marker: std::marker::PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> Iterator for MapEntries<K, V>
+where
+    TokenValue: UnpackAbi<K> + UnpackAbi<V>,
+{
+    type Item = UnpackerResult<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.entries.next()?;
+        Some(unpack_map_entry(key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for MapEntries<K, V> where TokenValue: UnpackAbi<K> + UnpackAbi<V> {}
+
+fn unpack_map_entry<K, V>(key: MapKeyTokenValue, value: TokenValue) -> UnpackerResult<(K, V)>
+where
+    TokenValue: UnpackAbi<K> + UnpackAbi<V>,
+{
+    let value = value.unpack().map_err(|e| e.with_key(&key))?;
+    let key = TokenValue::from(key).unpack()?;
+    Ok((key, value))
+}
+
 impl UnpackAbi<TokenValue> for TokenValue {
     #[inline]
     fn unpack(self) -> UnpackerResult<TokenValue> {
@@ -358,7 +566,7 @@ where
     fn unpack(self) -> UnpackerResult<Option<T>> {
         match self {
             TokenValue::Optional(_, item) => item.map(|item| item.unpack()).transpose(),
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<Option<T>>(&value)),
         }
     }
 }
@@ -371,10 +579,10 @@ where
         match self {
             TokenValue::Optional(_, Some(item)) => match *item {
                 TokenValue::Ref(item) => Ok(MaybeRef(Some(item.unpack()?))),
-                _ => Err(UnpackerError::InvalidAbi),
+                value => Err(UnpackerError::unexpected::<MaybeRef<T>>(&value)),
             },
             TokenValue::Optional(_, None) => Ok(MaybeRef(None)),
-            _ => Err(UnpackerError::InvalidAbi),
+            value => Err(UnpackerError::unexpected::<MaybeRef<T>>(&value)),
         }
     }
 }
@@ -397,6 +605,78 @@ where
     }
 }
 
+macro_rules! impl_unpack_tuple {
+    ($($idx:tt $ty:ident),+) => {
+        impl<$($ty),+> UnpackAbi<($($ty,)+)> for TokenValue
+        where
+            $(TokenValue: UnpackAbi<$ty>),+
+        {
+            fn unpack(self) -> UnpackerResult<($($ty,)+)> {
+                match self {
+                    TokenValue::Tuple(tokens) => {
+                        let mut tokens = tokens.into_iter();
+                        let result = ($(
+                            UnpackAbi::<$ty>::unpack(tokens.next())
+                                .map_err(|e| e.with_index($idx))?,
+                        )+);
+                        match tokens.next() {
+                            None => Ok(result),
+                            Some(_) => Err(UnpackerError::InvalidAbi),
+                        }
+                    }
+                    value => Err(UnpackerError::unexpected::<($($ty,)+)>(&value)),
+                }
+            }
+        }
+    };
+}
+
+impl_unpack_tuple!(0 T0);
+impl_unpack_tuple!(0 T0, 1 T1);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2, 3 T3);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10);
+impl_unpack_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10, 11 T11);
+
+macro_rules! impl_unpack_plain_tuple {
+    ($($idx:tt $ty:ident),+) => {
+        impl<$($ty),+> UnpackAbiPlain<($($ty,)+)> for Vec<Token>
+        where
+            $(TokenValue: UnpackAbi<$ty>),+
+        {
+            fn unpack(self) -> UnpackerResult<($($ty,)+)> {
+                let mut tokens = self.into_unpacker();
+                let result = ($(
+                    tokens
+                        .unpack_next::<$ty>()
+                        .map_err(|e| e.with_index($idx))?,
+                )+);
+                tokens.finish()?;
+                Ok(result)
+            }
+        }
+    };
+}
+
+impl_unpack_plain_tuple!(0 T0);
+impl_unpack_plain_tuple!(0 T0, 1 T1);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2, 3 T3);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10);
+impl_unpack_plain_tuple!(0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10, 11 T11);
+
 impl<T> UnpackAbi<T> for Option<Token>
 where
     TokenValue: UnpackAbi<T>,
@@ -430,10 +710,808 @@ where
     }
 }
 
+/// Human-readable representation of the token value.
+///
+/// Formatter width is used as the indentation size for nested values (2 by default),
+/// e.g. `format!("{:4}", DisplayTokenValue(&value))`
+#[derive(Debug, Copy, Clone)]
+pub struct DisplayTokenValue<'a>(pub &'a TokenValue);
+
+impl std::fmt::Display for DisplayTokenValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let indent = f.width().unwrap_or(DEFAULT_DISPLAY_INDENT);
+        write_token_value(f, self.0, indent, 0)
+    }
+}
+
+/// Human-readable representation of the named tokens (e.g. function output)
+#[derive(Debug, Copy, Clone)]
+pub struct DisplayTokens<'a>(pub &'a [Token]);
+
+impl std::fmt::Display for DisplayTokens<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let indent = f.width().unwrap_or(DEFAULT_DISPLAY_INDENT);
+        write_tokens(f, self.0, indent, 0)
+    }
+}
+
+const DEFAULT_DISPLAY_INDENT: usize = 2;
+
+fn write_token_value(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &TokenValue,
+    indent: usize,
+    level: usize,
+) -> std::fmt::Result {
+    use std::fmt::Write;
+
+    match value {
+        TokenValue::Uint(value) => write!(f, "{}", value.number),
+        TokenValue::Int(value) => write!(f, "{}", value.number),
+        TokenValue::VarUint(_, value) => write!(f, "{value}"),
+        TokenValue::VarInt(_, value) => write!(f, "{value}"),
+        TokenValue::Bool(value) => write!(f, "{value}"),
+        TokenValue::Tuple(tokens) => write_tokens(f, tokens, indent, level),
+        TokenValue::Array(_, values) | TokenValue::FixedArray(_, values) => {
+            if values.is_empty() {
+                return f.write_str("[]");
+            }
+
+            f.write_char('[')?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                write_line_prefix(f, indent, level + 1)?;
+                write_token_value(f, value, indent, level + 1)?;
+            }
+            write_line_prefix(f, indent, level)?;
+            f.write_char(']')
+        }
+        TokenValue::Cell(cell) => write!(f, "cell({})", cell.repr_hash().to_hex_string()),
+        TokenValue::Map(_, _, values) => {
+            if values.is_empty() {
+                return f.write_str("{}");
+            }
+
+            f.write_char('{')?;
+            for (i, (key, value)) in values.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                write_line_prefix(f, indent, level + 1)?;
+                write!(f, "{key}: ")?;
+                write_token_value(f, value, indent, level + 1)?;
+            }
+            write_line_prefix(f, indent, level)?;
+            f.write_char('}')
+        }
+        TokenValue::Address(value) | TokenValue::AddressStd(value) => write!(f, "{value}"),
+        TokenValue::Bytes(value) | TokenValue::FixedBytes(value) => {
+            f.write_str(&hex::encode(value))
+        }
+        TokenValue::String(value) => write!(f, "{value:?}"),
+        TokenValue::Token(value) => write!(f, "{}", value.as_u128()),
+        TokenValue::Time(value) => write!(f, "{value}"),
+        TokenValue::Expire(value) => write!(f, "{value}"),
+        TokenValue::PublicKey(Some(key)) => f.write_str(&hex::encode(key.as_bytes())),
+        TokenValue::PublicKey(None) | TokenValue::Optional(_, None) => f.write_str("null"),
+        TokenValue::Optional(_, Some(value)) | TokenValue::Ref(value) => {
+            write_token_value(f, value, indent, level)
+        }
+    }
+}
+
+fn write_tokens(
+    f: &mut std::fmt::Formatter<'_>,
+    tokens: &[Token],
+    indent: usize,
+    level: usize,
+) -> std::fmt::Result {
+    use std::fmt::Write;
+
+    if tokens.is_empty() {
+        return f.write_str("{}");
+    }
+
+    f.write_char('{')?;
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+        write_line_prefix(f, indent, level + 1)?;
+        write!(f, "{}: ", token.name)?;
+        write_token_value(f, &token.value, indent, level + 1)?;
+    }
+    write_line_prefix(f, indent, level)?;
+    f.write_char('}')
+}
+
+fn write_line_prefix(
+    f: &mut std::fmt::Formatter<'_>,
+    indent: usize,
+    level: usize,
+) -> std::fmt::Result {
+    write!(f, "\n{:width$}", "", width = indent * level)
+}
+
 pub type UnpackerResult<T> = Result<T, UnpackerError>;
 
 #[derive(thiserror::Error, Debug, Clone, Copy)]
 pub enum UnpackerError {
     #[error("Invalid ABI")]
     InvalidAbi,
+    #[error("Missing field: {name}")]
+    MissingField { name: &'static str },
+    #[error("Duplicate field: {name}")]
+    DuplicateField { name: &'static str },
+    #[error("Unexpected trailing tokens: {count}")]
+    TrailingTokens { count: usize },
+    #[error("Expected {expected} elements, found {found}")]
+    InvalidLength { expected: usize, found: usize },
+    #[error("Expected {expected}, found {found}")]
+    UnexpectedToken {
+        expected: &'static str,
+        found: TokenKind,
+    },
+    #[error("{source} at `{path}`")]
+    AtPath {
+        path: UnpackerPath,
+        source: UnpackerErrorCause,
+    },
+}
+
+impl UnpackerError {
+    /// Creates an error for the token value which can't be unpacked as `T`
+    pub fn unexpected<T>(value: &TokenValue) -> Self {
+        Self::UnexpectedToken {
+            expected: std::any::type_name::<T>(),
+            found: TokenKind::of(value),
+        }
+    }
+
+    /// Prepends the struct field name to the error path
+    pub fn with_field(self, name: &str) -> Self {
+        self.with_path_segment(name)
+    }
+
+    /// Prepends the array or tuple element index to the error path
+    pub fn with_index(self, index: usize) -> Self {
+        self.with_path_segment(&format!("[{index}]"))
+    }
+
+    /// Prepends the map key to the error path
+    pub fn with_key<K: std::fmt::Display>(self, key: K) -> Self {
+        self.with_path_segment(&format!("[{key}]"))
+    }
+
+    /// Path to the value which failed to unpack (e.g. `details.owners[3].addr`)
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::AtPath { path, .. } => Some(path.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Error without the path context
+    pub fn root_cause(&self) -> Self {
+        match self {
+            Self::AtPath { source, .. } => (*source).into(),
+            error => *error,
+        }
+    }
+
+    fn with_path_segment(self, segment: &str) -> Self {
+        let (mut path, source) = match self {
+            Self::AtPath { path, source } => (path, source),
+            Self::InvalidAbi => (UnpackerPath::default(), UnpackerErrorCause::InvalidAbi),
+            Self::MissingField { name } => (
+                UnpackerPath::default(),
+                UnpackerErrorCause::MissingField { name },
+            ),
+            Self::DuplicateField { name } => (
+                UnpackerPath::default(),
+                UnpackerErrorCause::DuplicateField { name },
+            ),
+            Self::TrailingTokens { count } => (
+                UnpackerPath::default(),
+                UnpackerErrorCause::TrailingTokens { count },
+            ),
+            Self::InvalidLength { expected, found } => (
+                UnpackerPath::default(),
+                UnpackerErrorCause::InvalidLength { expected, found },
+            ),
+            Self::UnexpectedToken { expected, found } => (
+                UnpackerPath::default(),
+                UnpackerErrorCause::UnexpectedToken { expected, found },
+            ),
+        };
+        path.prepend(segment);
+        Self::AtPath { path, source }
+    }
+}
+
+/// Same as [`UnpackerError`], but without the path context
+#[derive(Debug, Clone, Copy)]
+pub enum UnpackerErrorCause {
+    InvalidAbi,
+    MissingField {
+        name: &'static str,
+    },
+    DuplicateField {
+        name: &'static str,
+    },
+    TrailingTokens {
+        count: usize,
+    },
+    InvalidLength {
+        expected: usize,
+        found: usize,
+    },
+    UnexpectedToken {
+        expected: &'static str,
+        found: TokenKind,
+    },
+}
+
+impl From<UnpackerErrorCause> for UnpackerError {
+    fn from(cause: UnpackerErrorCause) -> Self {
+        match cause {
+            UnpackerErrorCause::InvalidAbi => Self::InvalidAbi,
+            UnpackerErrorCause::MissingField { name } => Self::MissingField { name },
+            UnpackerErrorCause::DuplicateField { name } => Self::DuplicateField { name },
+            UnpackerErrorCause::TrailingTokens { count } => Self::TrailingTokens { count },
+            UnpackerErrorCause::InvalidLength { expected, found } => {
+                Self::InvalidLength { expected, found }
+            }
+            UnpackerErrorCause::UnexpectedToken { expected, found } => {
+                Self::UnexpectedToken { expected, found }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for UnpackerErrorCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&UnpackerError::from(*self), f)
+    }
+}
+
+impl std::error::Error for UnpackerErrorCause {}
+
+/// Path to the value which failed to unpack.
+///
+/// Stored inline to keep [`UnpackerError`] `Copy`, so only the innermost
+/// segments are kept for deeply nested values
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct UnpackerPath {
+    /// Path is aligned to the end of the buffer to prepend segments cheaply
+    data: [u8; UnpackerPath::CAPACITY],
+    start: u8,
+    truncated: bool,
+}
+
+impl UnpackerPath {
+    const CAPACITY: usize = 62;
+
+    /// Returns the kept part of the path (without the dropped outer segments)
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.data[self.start as usize..]).unwrap_or_default()
+    }
+
+    /// Whether some outer segments didn't fit into the path
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    fn prepend(&mut self, segment: &str) {
+        if self.truncated {
+            return;
+        }
+
+        let start = self.start as usize;
+        let separator = match self.data.get(start) {
+            Some(b'[') | None => "",
+            Some(_) => ".",
+        };
+
+        let len = segment.len() + separator.len();
+        if len > start {
+            self.truncated = true;
+            return;
+        }
+
+        let new_start = start - len;
+        self.data[new_start..new_start + segment.len()].copy_from_slice(segment.as_bytes());
+        self.data[new_start + segment.len()..start].copy_from_slice(separator.as_bytes());
+        self.start = new_start as u8;
+    }
+}
+
+impl Default for UnpackerPath {
+    fn default() -> Self {
+        Self {
+            data: [0; Self::CAPACITY],
+            start: Self::CAPACITY as u8,
+            truncated: false,
+        }
+    }
+}
+
+impl std::fmt::Display for UnpackerPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.truncated {
+            f.write_str("...")?;
+        }
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Debug for UnpackerPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+/// ABI type of the encountered token value (e.g. `uint32`)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TokenKind {
+    pub name: &'static str,
+    pub size: Option<u16>,
+}
+
+impl TokenKind {
+    pub fn of(value: &TokenValue) -> Self {
+        let (name, size) = match value {
+            TokenValue::Uint(value) => ("uint", Some(value.size)),
+            TokenValue::Int(value) => ("int", Some(value.size)),
+            TokenValue::VarUint(size, _) => ("varuint", Some(*size)),
+            TokenValue::VarInt(size, _) => ("varint", Some(*size)),
+            TokenValue::Bool(_) => ("bool", None),
+            TokenValue::Tuple(_) => ("tuple", None),
+            TokenValue::Array(..) => ("array", None),
+            TokenValue::FixedArray(..) => ("fixed array", None),
+            TokenValue::Cell(_) => ("cell", None),
+            TokenValue::Map(..) => ("map", None),
+            TokenValue::Address(_) => ("address", None),
+            TokenValue::AddressStd(_) => ("address_std", None),
+            TokenValue::Bytes(_) => ("bytes", None),
+            TokenValue::FixedBytes(value) => ("fixedbytes", Some(value.len())),
+            TokenValue::String(_) => ("string", None),
+            TokenValue::Token(_) => ("gram", None),
+            TokenValue::Time(_) => ("time", None),
+            TokenValue::Expire(_) => ("expire", None),
+            TokenValue::PublicKey(_) => ("pubkey", None),
+            TokenValue::Optional(..) => ("optional", None),
+            TokenValue::Ref(_) => ("ref", None),
+        };
+        Self {
+            name,
+            size: size.map(|size| size as u16),
+        }
+    }
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)?;
+        if let Some(size) = self.size {
+            write!(f, "{size}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_traits::One;
+
+    use super::*;
+    use crate::{BuildTokenValue, KnownParamType, TimestampError, UnixTimestamp};
+
+    fn int256_token(number: BigInt) -> TokenValue {
+        TokenValue::Int(ton_abi::Int { number, size: 256 })
+    }
+
+    #[test]
+    fn finish_unpacker() {
+        let tokens = vec![1u32.token_value().unnamed(), true.token_value().unnamed()];
+
+        let mut unpacker = tokens.clone().into_unpacker();
+        assert_eq!(unpacker.unpack_next::<u32>().unwrap(), 1);
+        assert!(unpacker.unpack_next::<bool>().unwrap());
+        assert!(unpacker.finish().is_ok());
+
+        let mut unpacker = tokens.into_unpacker();
+        assert_eq!(unpacker.unpack_next::<u32>().unwrap(), 1);
+        assert!(matches!(
+            unpacker.finish(),
+            Err(UnpackerError::TrailingTokens { count: 1 })
+        ));
+    }
+
+    #[test]
+    fn unpack_abi_v2_4_values() {
+        let addr = MsgAddrStd::with_address(None, 0, [0x11; 32].into());
+
+        let value = TokenValue::AddressStd(MsgAddress::AddrStd(addr.clone()));
+        let unpacked: MsgAddressInt = value.unpack().unwrap();
+        assert_eq!(unpacked, MsgAddressInt::AddrStd(addr));
+
+        let value = TokenValue::FixedBytes(vec![0x22; 32]);
+        let unpacked: ton_types::UInt256 = value.clone().unpack().unwrap();
+        assert_eq!(unpacked, ton_types::UInt256::from([0x22; 32]));
+        let unpacked: Vec<u8> = value.unpack().unwrap();
+        assert_eq!(unpacked, vec![0x22; 32]);
+
+        // Buffer is moved without copying
+        let data = vec![0x33; 1024];
+        let ptr = data.as_ptr();
+        let unpacked: Vec<u8> = TokenValue::FixedBytes(data).unpack().unwrap();
+        assert_eq!(unpacked.as_ptr(), ptr);
+
+        let cell = ton_types::Cell::default();
+        let value = TokenValue::Ref(Box::new(TokenValue::Cell(cell.clone())));
+        let unpacked: ton_types::Cell = value.unpack().unwrap();
+        assert_eq!(unpacked, cell);
+    }
+
+    #[test]
+    fn unpack_var_integers() {
+        let value = TokenValue::VarUint(16, BigUint::from(1000u32));
+        assert_eq!(UnpackAbi::<u32>::unpack(value.clone()).unwrap(), 1000);
+        assert!(UnpackAbi::<u8>::unpack(value.clone()).is_err());
+        assert_eq!(
+            UnpackAbi::<crate::VarUint16>::unpack(value.clone()).unwrap(),
+            crate::VarUint(BigUint::from(1000u32))
+        );
+        assert!(UnpackAbi::<crate::VarUint32>::unpack(value.clone()).is_err());
+        assert_eq!(crate::varuint16::unpack::<u64>(&value).unwrap(), 1000);
+
+        let value = TokenValue::VarInt(32, BigInt::from(-5));
+        assert_eq!(UnpackAbi::<i64>::unpack(value.clone()).unwrap(), -5);
+        assert!(crate::varint32::unpack::<u64>(&value).is_err());
+
+        assert_eq!(
+            crate::VarInt16::new(-5).unwrap().token_value(),
+            TokenValue::VarInt(16, BigInt::from(-5))
+        );
+        assert!(crate::VarUint16::new(u128::MAX).is_none());
+        assert!(crate::VarUint32::new(u128::MAX).is_some());
+    }
+
+    #[test]
+    fn unpack_public_key() {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public_key = ed25519_dalek::PublicKey::from(&secret);
+
+        let value = public_key.token_value();
+        assert_eq!(
+            value,
+            TokenValue::Uint(ton_abi::Uint {
+                number: BigUint::from_bytes_be(public_key.as_bytes()),
+                size: 256,
+            })
+        );
+        let unpacked: ed25519_dalek::PublicKey = value.unpack().unwrap();
+        assert_eq!(unpacked, public_key);
+
+        // Not a valid curve point (y = 2)
+        let mut bytes = [0; 32];
+        bytes[0] = 2;
+        let invalid = TokenValue::Uint(ton_abi::Uint {
+            number: BigUint::from_bytes_be(&bytes),
+            size: 256,
+        });
+        assert!(UnpackAbi::<ed25519_dalek::PublicKey>::unpack(invalid).is_err());
+    }
+
+    #[test]
+    fn unpack_named_tokens() {
+        let mut tokens = NamedTokens::new(vec![
+            123u32.token_value().named("second"),
+            true.token_value().named("first"),
+            1u8.token_value().named("dup"),
+            2u8.token_value().named("dup"),
+        ]);
+
+        assert!(tokens.unpack::<bool>("first").unwrap());
+        assert_eq!(tokens.unpack::<u32>("second").unwrap(), 123);
+        assert!(matches!(
+            tokens.unpack::<u8>("dup"),
+            Err(UnpackerError::DuplicateField { name: "dup" })
+        ));
+        assert!(matches!(
+            tokens.unpack::<bool>("first"),
+            Err(UnpackerError::MissingField { name: "first" })
+        ));
+    }
+
+    #[test]
+    fn unpack_optional_tuple() {
+        let address = MsgAddressInt::default();
+
+        let value = Some((123u32, address.clone())).token_value();
+        assert!(matches!(
+            &value,
+            TokenValue::Optional(ton_abi::ParamType::Tuple(params), Some(_)) if params.len() == 2
+        ));
+
+        let unpacked: Option<(u32, MsgAddressInt)> = value.unpack().unwrap();
+        assert_eq!(unpacked, Some((123, address)));
+
+        let value = Option::<(u32, MsgAddressInt)>::None.token_value();
+        let unpacked: Option<(u32, MsgAddressInt)> = value.unpack().unwrap();
+        assert_eq!(unpacked, None);
+
+        // Tuple arity must match exactly
+        let value = Some((1u32, true, 2u8)).token_value();
+        assert!(UnpackAbi::<Option<(u32, bool)>>::unpack(value).is_err());
+    }
+
+    #[test]
+    fn display_token_value() {
+        let value = TokenValue::Tuple(vec![
+            123u32.token_value().named("amount"),
+            (-5i8).token_value().named("delta"),
+            vec![1u8, 255].token_value().named("payload"),
+            TokenValue::Array(ton_abi::ParamType::Bool, vec![true.token_value()]).named("flags"),
+            TokenValue::Tuple(vec!["test".token_value().named("name")]).named("inner"),
+            Option::<u32>::None.token_value().named("empty"),
+        ]);
+
+        assert_eq!(
+            DisplayTokenValue(&value).to_string(),
+            r#"{
+  amount: 123,
+  delta: -5,
+  payload: 01ff,
+  flags: [
+    true
+  ],
+  inner: {
+    name: "test"
+  },
+  empty: null
+}"#
+        );
+
+        let value = TokenValue::Tuple(vec![TokenValue::Tuple(Vec::new()).named("inner")]);
+        assert_eq!(
+            format!("{:4}", DisplayTokenValue(&value)),
+            "{\n    inner: {}\n}"
+        );
+
+        let address = MsgAddressInt::default();
+        assert_eq!(
+            DisplayTokens(&[address.clone().token_value().named("addr")]).to_string(),
+            format!("{{\n  addr: {address}\n}}")
+        );
+    }
+
+    #[test]
+    fn unpack_negative_int256() {
+        let min = -(BigInt::one() << 255);
+
+        let value: Int256 = int256_token(min.clone()).unpack().unwrap();
+        assert_eq!(value, Int256::MIN);
+        assert!(value.is_negative());
+        assert_eq!(value.to_bigint(), min);
+
+        let value: Int256 = int256_token(min.clone() + 1).unpack().unwrap();
+        assert_eq!(value.to_bigint(), min + 1);
+
+        let value: Int256 = int256_token(BigInt::from(-1)).unpack().unwrap();
+        assert_eq!(value.0, [0xff; 32]);
+    }
+
+    #[test]
+    fn unpack_int256_out_of_range() {
+        let overflow = -(BigInt::one() << 255) - 1;
+        assert!(UnpackAbi::<Int256>::unpack(int256_token(overflow)).is_err());
+
+        let overflow = BigInt::one() << 255;
+        assert!(UnpackAbi::<Int256>::unpack(int256_token(overflow)).is_err());
+
+        let wrong_size = TokenValue::Int(ton_abi::Int::new(-1, 128));
+        assert!(UnpackAbi::<Int256>::unpack(wrong_size).is_err());
+    }
+
+    #[test]
+    fn int256_round_trip() {
+        let max = (BigInt::one() << 255) - 1;
+        for number in [
+            -(BigInt::one() << 255),
+            -(BigInt::one() << 255) + 1,
+            BigInt::from(-1),
+            BigInt::from(0),
+            max,
+        ] {
+            let value = Int256::from_bigint(&number).unwrap();
+            let token = value.token_value();
+            assert_eq!(token, int256_token(number));
+
+            let unpacked: Int256 = token.unpack().unwrap();
+            assert_eq!(unpacked, value);
+        }
+        assert_eq!(
+            Int256::from_bigint(&((BigInt::one() << 255) - 1)),
+            Some(Int256::MAX)
+        );
+    }
+
+    #[test]
+    fn unpacker_error_path() {
+        let item = |flag: TokenValue| {
+            TokenValue::Tuple(vec![
+                1u32.token_value().named("value0"),
+                flag.named("value1"),
+            ])
+        };
+        let array = TokenValue::Array(
+            <(u32, bool)>::param_type(),
+            vec![item(true.token_value()), item(2u32.token_value())],
+        );
+
+        let error = UnpackAbi::<Vec<(u32, bool)>>::unpack(array)
+            .unwrap_err()
+            .with_field("owners")
+            .with_field("details");
+        assert_eq!(error.path(), Some("details.owners[1][1]"));
+        assert!(matches!(
+            error.root_cause(),
+            UnpackerError::UnexpectedToken { expected: "bool", found } if found.to_string() == "uint32"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Expected bool, found uint32 at `details.owners[1][1]`"
+        );
+    }
+
+    #[test]
+    fn unpacker_error_is_copy() {
+        let error = UnpackerError::InvalidAbi.with_field("value");
+        let copy = error;
+        assert_eq!(error.path(), copy.path());
+
+        let error = (0..20).fold(UnpackerError::InvalidAbi, |error, _| {
+            error.with_field("field")
+        });
+        assert!(matches!(error, UnpackerError::AtPath { path, .. } if path.is_truncated()));
+        assert!(error.path().unwrap().ends_with("field.field"));
+        assert!(error
+            .to_string()
+            .starts_with("Invalid ABI at `...field.field"));
+    }
+
+    #[test]
+    fn unpack_borrowed_and_small_values() {
+        let token = Cow::Borrowed("hello").token_value();
+        assert_eq!(token, TokenValue::String("hello".to_owned()));
+        let unpacked: Cow<'static, str> = token.unpack().unwrap();
+        assert_eq!(unpacked, "hello");
+
+        let small: SmallVec<[u8; 4]> = SmallVec::from_slice(&[1, 2, 3]);
+        let token = small.clone().token_value();
+        assert_eq!(<SmallVec<[u8; 4]>>::param_type(), ton_abi::ParamType::Bytes);
+        let unpacked: SmallVec<[u8; 4]> = token.unpack().unwrap();
+        assert_eq!(unpacked, small);
+        assert!(!unpacked.spilled());
+    }
+
+    #[test]
+    fn unpack_fixed_array() {
+        let bytes = [1u8, 2, 3, 4];
+        let token = bytes.token_value();
+        assert_eq!(
+            <[u8; 4]>::param_type(),
+            ton_abi::ParamType::FixedArray(Box::new(ton_abi::ParamType::Uint(8)), 4)
+        );
+
+        let unpacked: [u8; 4] = token.clone().unpack().unwrap();
+        assert_eq!(unpacked, bytes);
+
+        assert!(matches!(
+            UnpackAbi::<[u8; 3]>::unpack(token),
+            Err(UnpackerError::InvalidLength {
+                expected: 3,
+                found: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn unpack_plain_tuple() {
+        let tokens = vec![
+            100u128.token_value().named("total"),
+            true.token_value().named("flag"),
+            7u32.token_value().named("count"),
+        ];
+
+        let (total, flag, count): (u128, bool, u32) =
+            UnpackAbiPlain::unpack(tokens.clone()).unwrap();
+        assert_eq!(total, 100);
+        assert!(flag);
+        assert_eq!(count, 7);
+
+        assert!(matches!(
+            UnpackAbiPlain::<(u128, bool)>::unpack(tokens.clone()),
+            Err(UnpackerError::TrailingTokens { count: 1 })
+        ));
+
+        let error = UnpackAbiPlain::<(u128, u32)>::unpack(tokens).unwrap_err();
+        assert_eq!(error.path(), Some("[1]"));
+    }
+
+    #[test]
+    fn unpack_map_entries() {
+        let map = BTreeMap::from([(1u32, 10u128), (2, 20), (3, 30)]);
+        let token = map.clone().token_value();
+
+        let unpacked: BTreeMap<u32, u128> = token.clone().unpack().unwrap();
+        assert_eq!(unpacked, map);
+
+        let entries = unpack_map_iter::<u32, u128>(token.clone()).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries.collect::<UnpackerResult<Vec<_>>>().unwrap(),
+            map.into_iter().collect::<Vec<_>>()
+        );
+
+        let error = unpack_map_iter::<u32, bool>(token)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.path(), Some("[1]"));
+    }
+
+    #[test]
+    fn unpack_timestamps() {
+        let now = TokenValue::Uint(ton_abi::Uint::new(1_700_000_000, 32));
+        let time: std::time::SystemTime = now.unpack().unwrap();
+        assert_eq!(
+            time,
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000)
+        );
+
+        let token = time.unix_timestamp().unwrap().token_value();
+        assert_eq!(
+            token,
+            TokenValue::Uint(ton_abi::Uint::new(1_700_000_000, 64))
+        );
+        assert_eq!(
+            UnpackAbi::<std::time::SystemTime>::unpack(token).unwrap(),
+            time
+        );
+
+        let before_epoch = std::time::UNIX_EPOCH - std::time::Duration::from_secs(1);
+        assert_eq!(
+            before_epoch.unix_timestamp(),
+            Err(TimestampError::BeforeEpoch)
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn unpack_chrono_timestamps() {
+        let now = TokenValue::Uint(ton_abi::Uint::new(1_700_000_000, 32));
+        let time: chrono::DateTime<chrono::Utc> = now.unpack().unwrap();
+        assert_eq!(time.timestamp(), 1_700_000_000);
+
+        let overflow = TokenValue::Uint(ton_abi::Uint::new(u64::MAX as u128, 64));
+        assert!(UnpackAbi::<chrono::DateTime<chrono::Utc>>::unpack(overflow).is_err());
+
+        let token = time.unix_timestamp().unwrap().token_value();
+        assert_eq!(
+            token,
+            TokenValue::Uint(ton_abi::Uint::new(1_700_000_000, 64))
+        );
+
+        let before_epoch = chrono::TimeZone::timestamp_opt(&chrono::Utc, -1, 0).unwrap();
+        assert_eq!(
+            before_epoch.unix_timestamp(),
+            Err(TimestampError::BeforeEpoch)
+        );
+    }
 }
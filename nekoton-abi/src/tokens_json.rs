@@ -478,3 +478,69 @@ pub enum TokensJsonError {
     #[error("Integer overflow")]
     IntegerOverflow,
 }
+
+#[cfg(test)]
+mod tests {
+    use ton_abi::{Param, ParamType};
+
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let params = [
+            Param::new("amount", ParamType::Uint(256)),
+            Param::new("delta", ParamType::Int(64)),
+            Param::new("owner", ParamType::Address),
+            Param::new("payload", ParamType::Cell),
+            Param::new(
+                "balances",
+                ParamType::Map(
+                    Box::new(ParamType::Uint(32)),
+                    Box::new(ParamType::Uint(128)),
+                ),
+            ),
+            Param::new("comment", ParamType::Optional(Box::new(ParamType::String))),
+            Param::new(
+                "details",
+                ParamType::Tuple(vec![
+                    Param::new("flag", ParamType::Bool),
+                    Param::new("data", ParamType::Array(Box::new(ParamType::Bytes))),
+                ]),
+            ),
+        ];
+
+        let json = serde_json::json!({
+            "amount": "115792089237316195423570985008687907853269984665640564039457584007913129639935",
+            "delta": "-123",
+            "owner": "0:3333333333333333333333333333333333333333333333333333333333333333",
+            "payload": "te6ccgEBAQEAAgAAAA==",
+            "balances": [["1", "1000"], ["2", "2000"]],
+            "comment": null,
+            "details": {
+                "flag": true,
+                "data": ["AQID"],
+            },
+        });
+
+        let tokens = parse_abi_tokens(&params, json.clone()).unwrap();
+        assert_eq!(make_abi_tokens(&tokens).unwrap(), json);
+    }
+
+    #[test]
+    fn json_parse_errors() {
+        let params = [Param::new("amount", ParamType::Uint(32))];
+
+        assert!(matches!(
+            parse_abi_tokens(&params, serde_json::json!({ "value": 1 })),
+            Err(TokensJsonError::ParameterNotFound(name)) if name == "amount"
+        ));
+        assert!(matches!(
+            parse_abi_tokens(&params, serde_json::json!({ "amount": -1 })),
+            Err(TokensJsonError::UnsignedValueExpected(_))
+        ));
+        assert!(matches!(
+            parse_abi_tokens(&params, serde_json::json!([1])),
+            Err(TokensJsonError::ObjectExpected)
+        ));
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use ton_abi::{Contract, Token, TokenValue};
+use ton_block::{Deserializable, GetRepresentationHash, MsgAddrStd, MsgAddressInt, Serializable};
+use ton_types::{Cell, SliceData, UInt256};
+
+use super::{insert_state_init_data, unpack_from_cell, BuildTokenValue};
+
+/// Contract state init (TVC) with typed helpers for deploy flows
+#[derive(Debug, Clone, Default)]
+pub struct Tvc {
+    state_init: ton_block::StateInit,
+}
+
+impl Tvc {
+    pub fn new(state_init: ton_block::StateInit) -> Self {
+        Self { state_init }
+    }
+
+    /// Merges code and data into the new state init
+    pub fn from_parts(code: Cell, data: Option<Cell>) -> Self {
+        Self::new(ton_block::StateInit {
+            code: Some(code),
+            data,
+            ..Default::default()
+        })
+    }
+
+    /// Creates state init with the empty data map (only with zero public key)
+    pub fn from_code(code: Cell) -> Result<Self> {
+        super::code_to_tvc(code).map(Self::new)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ton_block::StateInit::construct_from_bytes(bytes).map(Self::new)
+    }
+
+    pub fn from_base64(boc: &str) -> Result<Self> {
+        ton_block::StateInit::construct_from_base64(boc).map(Self::new)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.state_init.write_to_bytes()
+    }
+
+    pub fn to_base64(&self) -> Result<String> {
+        self.to_bytes().map(base64::encode)
+    }
+
+    pub fn state_init(&self) -> &ton_block::StateInit {
+        &self.state_init
+    }
+
+    pub fn into_state_init(self) -> ton_block::StateInit {
+        self.state_init
+    }
+
+    pub fn code(&self) -> Option<&Cell> {
+        self.state_init.code.as_ref()
+    }
+
+    pub fn data(&self) -> Option<&Cell> {
+        self.state_init.data.as_ref()
+    }
+
+    pub fn set_code(&mut self, code: Cell) {
+        self.state_init.code = Some(code);
+    }
+
+    pub fn set_data(&mut self, data: Cell) {
+        self.state_init.data = Some(data);
+    }
+
+    /// Splits state init into code and data
+    pub fn into_parts(self) -> (Option<Cell>, Option<Cell>) {
+        (self.state_init.code, self.state_init.data)
+    }
+
+    /// Replaces the whole initial data.
+    ///
+    /// All static variables (or init fields for ABI 2.4) must be specified in `tokens`
+    pub fn set_init_data(
+        &mut self,
+        contract: &Contract,
+        public_key: &Option<ed25519_dalek::PublicKey>,
+        tokens: Vec<Token>,
+    ) -> Result<()> {
+        let data = insert_state_init_data(contract, self.data_slice()?, public_key, tokens)?;
+        self.state_init.data = Some(data.into_cell());
+        Ok(())
+    }
+
+    /// Replaces a single static variable (or init field for ABI 2.4) by its name
+    pub fn set_init_value(
+        &mut self,
+        contract: &Contract,
+        name: &str,
+        value: TokenValue,
+    ) -> Result<()> {
+        let data = self.data_slice()?;
+
+        let data = if contract.data_map_supported() {
+            let param = contract
+                .data
+                .get(name)
+                .ok_or_else(|| TvcError::ParamNotFound(name.to_owned()))?;
+            if !value.type_check(&param.value.kind) {
+                return Err(TvcError::ParamTypeMismatch(name.to_owned()).into());
+            }
+
+            let mut map =
+                ton_types::HashmapE::with_hashmap(Contract::DATA_MAP_KEYLEN, data.reference_opt(0));
+            let key = param.key.serialize().and_then(SliceData::load_cell)?;
+            map.set_builder(key, &value.pack_into_chain(&contract.abi_version)?)?;
+            map.write_to_new_cell().and_then(SliceData::load_builder)?
+        } else if contract.init_fields_supported() {
+            if !contract.init_fields.iter().any(|field| field == name) {
+                return Err(TvcError::ParamNotFound(name.to_owned()).into());
+            }
+
+            let mut init_fields = decode_init_fields(contract, data)?;
+            match init_fields.get_mut(name) {
+                Some(field) if value.type_check(&field.get_param_type()) => *field = value,
+                _ => return Err(TvcError::ParamTypeMismatch(name.to_owned()).into()),
+            }
+            SliceData::load_builder(contract.encode_storage_fields(init_fields)?)?
+        } else {
+            return Err(TvcError::UnsupportedDataLayout.into());
+        };
+
+        self.state_init.data = Some(data.into_cell());
+        Ok(())
+    }
+
+    /// Replaces the public key in the initial data
+    pub fn set_public_key(
+        &mut self,
+        contract: &Contract,
+        public_key: &ed25519_dalek::PublicKey,
+    ) -> Result<()> {
+        let data = self.data_slice()?;
+
+        let data = if contract.data_map_supported() {
+            Contract::insert_pubkey(data, public_key.as_bytes())?
+        } else if contract.init_fields_supported() {
+            let mut init_fields = decode_init_fields(contract, data)?;
+            init_fields.insert(
+                PUBKEY_FIELD.to_owned(),
+                UInt256::with_array(*public_key.as_bytes()).token_value(),
+            );
+            SliceData::load_builder(contract.encode_storage_fields(init_fields)?)?
+        } else {
+            return Err(TvcError::UnsupportedDataLayout.into());
+        };
+
+        self.state_init.data = Some(data.into_cell());
+        Ok(())
+    }
+
+    pub fn hash(&self) -> Result<UInt256> {
+        self.state_init.hash()
+    }
+
+    /// Computes the address of the contract with this state init
+    pub fn compute_address(&self, workchain_id: i8) -> Result<MsgAddressInt> {
+        Ok(MsgAddressInt::AddrStd(MsgAddrStd::with_address(
+            None,
+            workchain_id,
+            self.hash()?.into(),
+        )))
+    }
+
+    fn data_slice(&self) -> Result<SliceData> {
+        SliceData::load_cell(self.state_init.data.clone().unwrap_or_default())
+    }
+}
+
+impl From<ton_block::StateInit> for Tvc {
+    fn from(state_init: ton_block::StateInit) -> Self {
+        Self::new(state_init)
+    }
+}
+
+impl From<Tvc> for ton_block::StateInit {
+    fn from(tvc: Tvc) -> Self {
+        tvc.state_init
+    }
+}
+
+/// Decodes the current values of init fields from the storage
+fn decode_init_fields(contract: &Contract, data: SliceData) -> Result<HashMap<String, TokenValue>> {
+    if data.remaining_bits() == 0 && data.remaining_references() == 0 {
+        return Err(TvcError::EmptyData.into());
+    }
+
+    let tokens = unpack_from_cell(&contract.fields, data, true, contract.abi_version)?;
+    Ok(tokens
+        .into_iter()
+        .filter(|token| {
+            contract
+                .init_fields
+                .iter()
+                .any(|field| field == &token.name)
+        })
+        .map(|token| (token.name, token.value))
+        .collect())
+}
+
+const PUBKEY_FIELD: &str = "_pubkey";
+
+#[derive(thiserror::Error, Debug)]
+enum TvcError {
+    #[error("Param not found: {0}")]
+    ParamNotFound(String),
+    #[error("Param type mismatch: {0}")]
+    ParamTypeMismatch(String),
+    #[error("Contract data is empty")]
+    EmptyData,
+    #[error("Contract doesn't support initial data")]
+    UnsupportedDataLayout,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ton_types::BuilderData;
+
+    use super::*;
+
+    const ABI: &str = r#"{"ABI version":2,"version":"2.2","header":["time"],"functions":[],"events":[],"data":[{"key":1,"name":"_randomNonce","type":"uint256"}],"fields":[{"name":"_pubkey","type":"uint256"},{"name":"_timestamp","type":"uint64"},{"name":"_constructorFlag","type":"bool"},{"name":"_randomNonce","type":"uint256"}]}"#;
+
+    #[test]
+    fn modify_tvc() {
+        let contract = Contract::load(ABI.as_bytes()).unwrap();
+        let code = BuilderData::with_raw(vec![0xaa], 8)
+            .and_then(BuilderData::into_cell)
+            .unwrap();
+
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public_key = ed25519_dalek::PublicKey::from(&secret);
+        let nonce = UInt256::with_array([0x11; 32]);
+
+        let mut tvc = Tvc::from_code(code.clone()).unwrap();
+        tvc.set_public_key(&contract, &public_key).unwrap();
+        tvc.set_init_value(&contract, "_randomNonce", nonce.token_value())
+            .unwrap();
+
+        assert!(tvc
+            .clone()
+            .set_init_value(&contract, "_randomNonce", 1u32.token_value())
+            .is_err());
+        assert!(tvc
+            .clone()
+            .set_init_value(&contract, "unknown", nonce.token_value())
+            .is_err());
+
+        let expected = crate::get_state_init_hash(
+            Tvc::from_code(code.clone()).unwrap().into_state_init(),
+            &contract,
+            &Some(public_key),
+            vec![nonce.token_value().named("_randomNonce")],
+        )
+        .unwrap();
+        assert_eq!(tvc.hash().unwrap(), expected);
+
+        let data = crate::make_init_data(
+            Some(&public_key),
+            &[(1, nonce.token_value())],
+            contract.abi_version,
+        )
+        .unwrap();
+        assert_eq!(tvc.data(), Some(&data));
+
+        let address = tvc.compute_address(-1).unwrap();
+        assert_eq!(
+            address,
+            MsgAddressInt::from_str(&format!("-1:{}", expected.to_hex_string())).unwrap()
+        );
+
+        let restored = Tvc::from_base64(&tvc.to_base64().unwrap()).unwrap();
+        assert_eq!(restored.hash().unwrap(), expected);
+
+        let (restored_code, data) = restored.into_parts();
+        assert_eq!(restored_code.as_ref(), Some(&code));
+        assert_eq!(Tvc::from_parts(code, data).hash().unwrap(), expected);
+    }
+}
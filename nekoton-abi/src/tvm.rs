@@ -114,7 +114,7 @@ pub fn call(
 pub fn call_msg(
     utime: u32,
     lt: u64,
-    account: &mut AccountStuff,
+    account: &AccountStuff,
     msg: &Message,
     config: &BriefBlockchainConfig,
     modifiers: &ton_vm::executor::BehaviorModifiers,
@@ -142,10 +142,12 @@ pub fn call_msg(
         .push(function_selector); // function selector
 
     let (engine, exit_code, success) = call(utime, lt, account, stack, config, modifiers)?;
+    let gas_used = engine.gas_used() as u64;
     if !success {
         return Ok(ActionPhaseOutput {
             messages: None,
             exit_code,
+            gas_used,
         });
     }
 
@@ -169,6 +171,7 @@ pub fn call_msg(
     Ok(ActionPhaseOutput {
         messages: Some(msgs),
         exit_code,
+        gas_used,
     })
 }
 
@@ -190,6 +193,7 @@ pub fn call_getter(
     let (mut engine, exit_code, is_ok) = call(utime, lt, account, stack, config, modifiers)?;
 
     Ok(VmGetterOutput {
+        gas_used: engine.gas_used() as u64,
         stack: engine.withdraw_stack().storage,
         exit_code,
         is_ok,
@@ -201,6 +205,7 @@ pub struct VmGetterOutput {
     pub stack: Vec<ton_vm::stack::StackItem>,
     pub exit_code: i32,
     pub is_ok: bool,
+    pub gas_used: u64,
 }
 
 fn build_contract_info(
@@ -227,6 +232,7 @@ fn build_contract_info(
 pub struct ActionPhaseOutput {
     pub messages: Option<Vec<Message>>,
     pub exit_code: i32,
+    pub gas_used: u64,
 }
 
 #[derive(thiserror::Error, Debug, Clone, Copy)]
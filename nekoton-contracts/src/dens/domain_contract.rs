@@ -45,3 +45,22 @@ pub fn get_path() -> &'static ton_abi::Function {
         outputs: vec![Param::new("path", ParamType::String)],
     }
 }
+
+/// Returns the domain expiration timestamp
+///
+/// # Type
+/// Responsible getter method
+///
+/// # Inputs
+/// * `answerId: uint32` - responsible answer id
+///
+/// # Outputs
+/// * `expireTime: uint32`
+pub fn expires_at() -> &'static ton_abi::Function {
+    declare_function! {
+        abi: v2_2,
+        name: "expiresAt",
+        inputs: vec![Param::new("answerId", ParamType::Uint(32))],
+        outputs: vec![Param::new("expireTime", ParamType::Uint(32))],
+    }
+}
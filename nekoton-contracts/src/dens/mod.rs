@@ -51,6 +51,16 @@ impl DomainContract<'_> {
         Ok(result)
     }
 
+    /// Returns domain expiration timestamp (in seconds)
+    pub fn expires_at(&self) -> Result<u32> {
+        let inputs = [0u32.token_value().named("answerId")];
+        let result = self
+            .0
+            .run_local_responsible_simple(domain_contract::expires_at(), &inputs)?
+            .unpack_first()?;
+        Ok(result)
+    }
+
     pub fn query<T>(&self) -> Result<Option<T::Value>>
     where
         T: CertificateRecord,
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+
+use nekoton_abi::*;
+use ton_abi::{Param, ParamType};
+use ton_block::MsgAddressInt;
+use ton_types::UInt256;
+
+use crate::utils::declare_function;
+
+#[derive(Debug, Clone, KnownParamTypePlain, UnpackAbiPlain)]
+pub struct DePoolInfo {
+    #[abi(bool, name = "poolClosed")]
+    pub pool_closed: bool,
+    #[abi(uint64, name = "minStake")]
+    pub min_stake: u64,
+    #[abi(uint64, name = "validatorAssurance")]
+    pub validator_assurance: u64,
+    #[abi(uint8, name = "participantRewardFraction")]
+    pub participant_reward_fraction: u8,
+    #[abi(uint8, name = "validatorRewardFraction")]
+    pub validator_reward_fraction: u8,
+    #[abi(uint64, name = "balanceThreshold")]
+    pub balance_threshold: u64,
+    #[abi(address, name = "validatorWallet")]
+    pub validator_wallet: MsgAddressInt,
+    #[abi]
+    pub proxies: Vec<MsgAddressInt>,
+    #[abi(uint64, name = "stakeFee")]
+    pub stake_fee: u64,
+    #[abi(uint64, name = "retOrReinvFee")]
+    pub ret_or_reinv_fee: u64,
+    #[abi(uint64, name = "proxyFee")]
+    pub proxy_fee: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, KnownParamType, UnpackAbi)]
+pub struct InvestParams {
+    #[abi(uint64, name = "remainingAmount")]
+    pub remaining_amount: u64,
+    #[abi(uint64, name = "lastWithdrawalTime")]
+    pub last_withdrawal_time: u64,
+    #[abi(uint32, name = "withdrawalPeriod")]
+    pub withdrawal_period: u32,
+    #[abi(uint64, name = "withdrawalValue")]
+    pub withdrawal_value: u64,
+    #[abi(address)]
+    pub owner: MsgAddressInt,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, KnownParamTypePlain, UnpackAbiPlain)]
+pub struct ParticipantInfo {
+    #[abi(uint64)]
+    pub total: u64,
+    #[abi(uint64, name = "withdrawValue")]
+    pub withdraw_value: u64,
+    #[abi(bool)]
+    pub reinvest: bool,
+    #[abi(uint64)]
+    pub reward: u64,
+    #[abi]
+    pub stakes: BTreeMap<u64, u64>,
+    #[abi]
+    pub vestings: BTreeMap<u64, InvestParams>,
+    #[abi]
+    pub locks: BTreeMap<u64, InvestParams>,
+    #[abi(address, name = "vestingDonor")]
+    pub vesting_donor: MsgAddressInt,
+    #[abi(address, name = "lockDonor")]
+    pub lock_donor: MsgAddressInt,
+}
+
+#[derive(Debug, Clone, KnownParamType, UnpackAbi)]
+pub struct TruncatedRound {
+    #[abi(uint64)]
+    pub id: u64,
+    #[abi(uint32, name = "supposedElectedAt")]
+    pub supposed_elected_at: u32,
+    #[abi(uint32)]
+    pub unfreeze: u32,
+    #[abi(uint32, name = "stakeHeldFor")]
+    pub stake_held_for: u32,
+    #[abi(uint256, name = "vsetHashInElectionPhase")]
+    pub vset_hash_in_election_phase: UInt256,
+    #[abi(uint8)]
+    pub step: u8,
+    #[abi(uint8, name = "completionReason")]
+    pub completion_reason: u8,
+    #[abi(uint64)]
+    pub stake: u64,
+    #[abi(uint64, name = "recoveredStake")]
+    pub recovered_stake: u64,
+    #[abi(uint64)]
+    pub unused: u64,
+    #[abi(bool, name = "isValidatorStakeCompleted")]
+    pub is_validator_stake_completed: bool,
+    #[abi(uint64, name = "participantReward")]
+    pub participant_reward: u64,
+    #[abi(uint32, name = "participantQty")]
+    pub participant_qty: u32,
+    #[abi(uint64, name = "validatorStake")]
+    pub validator_stake: u64,
+    #[abi(uint64, name = "validatorRemainingStake")]
+    pub validator_remaining_stake: u64,
+    #[abi(uint64, name = "handledStakesAndRewards")]
+    pub handled_stakes_and_rewards: u64,
+}
+
+/// Get general DePool info
+///
+/// # Type
+/// Getter method
+///
+/// # Outputs
+/// See [`DePoolInfo`]
+///
+pub fn get_depool_info() -> &'static ton_abi::Function {
+    declare_function! {
+        abi: v2_0,
+        header: [time, expire],
+        name: "getDePoolInfo",
+        inputs: Vec::new(),
+        outputs: DePoolInfo::param_type(),
+    }
+}
+
+/// Get participant stakes
+///
+/// # Type
+/// Getter method
+///
+/// # Inputs
+/// * `addr: address` - participant address
+///
+/// # Outputs
+/// See [`ParticipantInfo`]
+///
+pub fn get_participant_info() -> &'static ton_abi::Function {
+    declare_function! {
+        abi: v2_0,
+        header: [time, expire],
+        name: "getParticipantInfo",
+        inputs: vec![Param::new("addr", ParamType::Address)],
+        outputs: ParticipantInfo::param_type(),
+    }
+}
+
+/// Get active rounds
+///
+/// # Type
+/// Getter method
+///
+/// # Outputs
+/// * `rounds: map(uint64, tuple)` - rounds by id
+///
+pub fn get_rounds() -> &'static ton_abi::Function {
+    declare_function! {
+        abi: v2_0,
+        header: [time, expire],
+        name: "getRounds",
+        inputs: Vec::new(),
+        outputs: vec![Param::new(
+            "rounds",
+            ParamType::Map(
+                Box::new(ParamType::Uint(64)),
+                Box::new(TruncatedRound::param_type()),
+            ),
+        )],
+    }
+}
+
+/// Add ordinary stake
+///
+/// # Type
+/// Internal method
+///
+/// # Dev
+/// Attached value must cover the stake and the stake fee
+///
+/// # Inputs
+/// * `stake: uint64` - stake amount in nano EVER
+///
+pub fn add_ordinary_stake() -> &'static ton_abi::Function {
+    declare_function! {
+        abi: v2_0,
+        header: [time, expire],
+        name: "addOrdinaryStake",
+        inputs: vec![Param::new("stake", ParamType::Uint(64))],
+        outputs: Vec::new(),
+    }
+}
+
+/// Withdraw part of the ordinary stake after the round completion
+///
+/// # Type
+/// Internal method
+///
+/// # Inputs
+/// * `withdrawValue: uint64` - amount in nano EVER
+///
+pub fn withdraw_part() -> &'static ton_abi::Function {
+    declare_function! {
+        abi: v2_0,
+        header: [time, expire],
+        name: "withdrawPart",
+        inputs: vec![Param::new("withdrawValue", ParamType::Uint(64))],
+        outputs: Vec::new(),
+    }
+}
+
+/// Withdraw the whole ordinary stake after the round completion
+///
+/// # Type
+/// Internal method
+///
+pub fn withdraw_all() -> &'static ton_abi::Function {
+    declare_function! {
+        abi: v2_0,
+        header: [time, expire],
+        name: "withdrawAll",
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    }
+}
+
+/// Cancel the pending withdrawal request
+///
+/// # Type
+/// Internal method
+///
+pub fn cancel_withdrawal() -> &'static ton_abi::Function {
+    declare_function! {
+        abi: v2_0,
+        header: [time, expire],
+        name: "cancelWithdrawal",
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    }
+}
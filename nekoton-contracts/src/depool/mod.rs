@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use nekoton_abi::*;
+
+pub use self::depool_contract::{DePoolInfo, InvestParams, ParticipantInfo, TruncatedRound};
+use crate::RunLocalSimple;
+
+pub mod depool_contract;
+
+#[derive(Copy, Clone)]
+pub struct DePoolContract<'a>(pub ExecutionContext<'a>);
+
+impl DePoolContract<'_> {
+    pub fn get_info(&self) -> Result<DePoolInfo> {
+        let result = self
+            .0
+            .run_local_simple(depool_contract::get_depool_info(), &[])?
+            .unpack()?;
+        Ok(result)
+    }
+
+    pub fn get_participant_info(
+        &self,
+        participant: &ton_block::MsgAddressInt,
+    ) -> Result<ParticipantInfo> {
+        let inputs = [participant.token_value().named("addr")];
+        let result = self
+            .0
+            .run_local_simple(depool_contract::get_participant_info(), &inputs)?
+            .unpack()?;
+        Ok(result)
+    }
+
+    pub fn get_rounds(&self) -> Result<BTreeMap<u64, TruncatedRound>> {
+        let result = self
+            .0
+            .run_local_simple(depool_contract::get_rounds(), &[])?
+            .unpack_first()?;
+        Ok(result)
+    }
+}
@@ -55,8 +55,10 @@ use anyhow::Result;
 use nekoton_abi::{ExecutionContext, ExecutionOutput};
 
 pub mod dens;
+pub mod depool;
 pub mod jetton;
 pub mod old_tip3;
+pub mod stever;
 pub mod tip1155;
 pub mod tip3;
 pub mod tip3_1;
@@ -91,6 +93,7 @@ impl RunLocalSimple for ExecutionContext<'_> {
         let ExecutionOutput {
             tokens,
             result_code,
+            ..
         } = self.run_local(function, inputs)?;
         tokens.ok_or_else(|| NonZeroResultCode(result_code).into())
     }
@@ -103,6 +106,7 @@ impl RunLocalSimple for ExecutionContext<'_> {
         let ExecutionOutput {
             tokens,
             result_code,
+            ..
         } = self.run_local_responsible(function, inputs)?;
         tokens.ok_or_else(|| NonZeroResultCode(result_code).into())
     }
@@ -0,0 +1,36 @@
+use nekoton_abi::*;
+use ton_abi::{Param, ParamType};
+
+use crate::utils::declare_function;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, KnownParamType, UnpackAbi)]
+pub struct WithdrawRequest {
+    #[abi(uint128)]
+    pub amount: u128,
+    #[abi(uint64)]
+    pub timestamp: u64,
+    #[abi(uint64, name = "unlockTime")]
+    pub unlock_time: u64,
+}
+
+/// Get pending withdrawal requests of the user
+///
+/// # Type
+/// Getter method
+///
+/// # Outputs
+/// * `withdrawRequests: map(uint64, tuple)` - requests by nonce
+///
+pub fn withdraw_requests() -> &'static ton_abi::Function {
+    declare_function! {
+        name: "withdrawRequests",
+        inputs: Vec::new(),
+        outputs: vec![Param::new(
+            "withdrawRequests",
+            ParamType::Map(
+                Box::new(ParamType::Uint(64)),
+                Box::new(WithdrawRequest::param_type()),
+            ),
+        )],
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use nekoton_abi::*;
+use ton_block::MsgAddressInt;
+use ton_types::{BuilderData, Cell};
+
+pub use self::account_contract::WithdrawRequest;
+use crate::RunLocalSimple;
+
+pub mod account_contract;
+pub mod vault_contract;
+
+#[derive(Copy, Clone)]
+pub struct StEverVaultContract<'a>(pub ExecutionContext<'a>);
+
+impl StEverVaultContract<'_> {
+    pub fn get_deposit_stever_amount(&self, amount: u128) -> Result<u128> {
+        let inputs = [amount.token_value().named("_amount")];
+        let result = self
+            .0
+            .run_local_simple(vault_contract::get_deposit_stever_amount(), &inputs)?
+            .unpack_first()?;
+        Ok(result)
+    }
+
+    pub fn get_withdraw_ever_amount(&self, amount: u128) -> Result<u128> {
+        let inputs = [amount.token_value().named("_amount")];
+        let result = self
+            .0
+            .run_local_simple(vault_contract::get_withdraw_ever_amount(), &inputs)?
+            .unpack_first()?;
+        Ok(result)
+    }
+
+    pub fn get_account_address(&self, user: &MsgAddressInt) -> Result<MsgAddressInt> {
+        let inputs = [
+            0u32.token_value().named("answerId"),
+            user.token_value().named("_user"),
+        ];
+        let result = self
+            .0
+            .run_local_responsible_simple(vault_contract::get_account_address(), &inputs)?
+            .unpack_first()?;
+        Ok(result)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct StEverAccountContract<'a>(pub ExecutionContext<'a>);
+
+impl StEverAccountContract<'_> {
+    pub fn withdraw_requests(&self) -> Result<BTreeMap<u64, WithdrawRequest>> {
+        let result = self
+            .0
+            .run_local_simple(account_contract::withdraw_requests(), &[])?
+            .unpack_first()?;
+        Ok(result)
+    }
+}
+
+/// Payload of the stEVER transfer to the vault, which creates a withdrawal request
+pub fn make_withdraw_payload(nonce: u64) -> Result<Cell> {
+    let mut builder = BuilderData::new();
+    builder.append_u64(nonce)?;
+    builder.into_cell()
+}
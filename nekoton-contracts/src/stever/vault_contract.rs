@@ -0,0 +1,103 @@
+use ton_abi::{Param, ParamType};
+
+use crate::utils::declare_function;
+
+/// Compute the amount of stEVER, minted for the deposit
+///
+/// # Type
+/// Getter method
+///
+/// # Inputs
+/// * `_amount: uint128` - deposit amount in nano EVER
+///
+/// # Outputs
+/// * `value0: uint128` - stEVER amount
+///
+pub fn get_deposit_stever_amount() -> &'static ton_abi::Function {
+    declare_function! {
+        name: "getDepositStEverAmount",
+        inputs: vec![Param::new("_amount", ParamType::Uint(128))],
+        outputs: vec![Param::new("value0", ParamType::Uint(128))],
+    }
+}
+
+/// Compute the amount of EVER, returned for the withdrawal
+///
+/// # Type
+/// Getter method
+///
+/// # Inputs
+/// * `_amount: uint128` - stEVER amount
+///
+/// # Outputs
+/// * `value0: uint128` - withdrawn amount in nano EVER
+///
+pub fn get_withdraw_ever_amount() -> &'static ton_abi::Function {
+    declare_function! {
+        name: "getWithdrawEverAmount",
+        inputs: vec![Param::new("_amount", ParamType::Uint(128))],
+        outputs: vec![Param::new("value0", ParamType::Uint(128))],
+    }
+}
+
+/// Get address of the user account, which holds pending withdrawal requests
+///
+/// # Type
+/// Responsible getter method
+///
+/// # Inputs
+/// * `answerId: uint32` - responsible answer id
+/// * `_user: address` - user address
+///
+/// # Outputs
+/// * `value0: address` - user account address
+///
+pub fn get_account_address() -> &'static ton_abi::Function {
+    declare_function! {
+        name: "getAccountAddress",
+        inputs: vec![
+            Param::new("answerId", ParamType::Uint(32)),
+            Param::new("_user", ParamType::Address),
+        ],
+        outputs: vec![Param::new("value0", ParamType::Address)],
+    }
+}
+
+/// Deposit EVER and receive stEVER
+///
+/// # Type
+/// Internal method
+///
+/// # Dev
+/// Attached value must cover the deposit amount and the deposit fee
+///
+/// # Inputs
+/// * `_amount: uint128` - deposit amount in nano EVER
+/// * `_nonce: uint64` - request nonce, returned in the callback
+///
+pub fn deposit() -> &'static ton_abi::Function {
+    declare_function! {
+        name: "deposit",
+        inputs: vec![
+            Param::new("_amount", ParamType::Uint(128)),
+            Param::new("_nonce", ParamType::Uint(64)),
+        ],
+        outputs: Vec::new(),
+    }
+}
+
+/// Cancel the pending withdrawal request and return stEVER
+///
+/// # Type
+/// Internal method
+///
+/// # Inputs
+/// * `_nonce: uint64` - nonce of the withdrawal request
+///
+pub fn remove_pending_withdraw() -> &'static ton_abi::Function {
+    declare_function! {
+        name: "removePendingWithdraw",
+        inputs: vec![Param::new("_nonce", ParamType::Uint(64))],
+        outputs: Vec::new(),
+    }
+}
@@ -0,0 +1,74 @@
+use nekoton_abi::num_bigint::BigUint;
+use nekoton_abi::*;
+
+use crate::utils::declare_function;
+
+#[derive(Debug, Clone, KnownParamTypePlain, PackAbiPlain, UnpackAbiPlain)]
+pub struct OnAcceptTokensTransferInputs {
+    #[abi(address, name = "tokenRoot")]
+    pub token_root: ton_block::MsgAddressInt,
+    #[abi(with = "uint128_number")]
+    pub amount: BigUint,
+    #[abi(address)]
+    pub sender: ton_block::MsgAddressInt,
+    #[abi(address, name = "senderWallet")]
+    pub sender_wallet: ton_block::MsgAddressInt,
+    #[abi(address, name = "remainingGasTo")]
+    pub remaining_gas_to: ton_block::MsgAddressInt,
+    #[abi(cell)]
+    pub payload: ton_types::Cell,
+}
+
+/// Notification about the incoming transfer, sent by the recipient token wallet
+/// to its owner if `notify` was set
+///
+/// # Type
+/// Internal method
+///
+/// # Inputs
+/// * `tokenRoot: address` - root token contract
+/// * `amount: uint128` - how much tokens were received
+/// * `sender: address` - sender token wallet owner
+/// * `senderWallet: address` - sender token wallet
+/// * `remainingGasTo: address` - remaining gas receiver
+/// * `payload: cell` - arbitrary payload from the transfer
+///
+pub fn on_accept_tokens_transfer() -> &'static ton_abi::Function {
+    declare_function! {
+        name: "onAcceptTokensTransfer",
+        inputs: OnAcceptTokensTransferInputs::param_type(),
+        outputs: Vec::new(),
+    }
+}
+
+#[derive(Debug, Clone, KnownParamTypePlain, PackAbiPlain, UnpackAbiPlain)]
+pub struct OnAcceptTokensMintInputs {
+    #[abi(address, name = "tokenRoot")]
+    pub token_root: ton_block::MsgAddressInt,
+    #[abi(with = "uint128_number")]
+    pub amount: BigUint,
+    #[abi(address, name = "remainingGasTo")]
+    pub remaining_gas_to: ton_block::MsgAddressInt,
+    #[abi(cell)]
+    pub payload: ton_types::Cell,
+}
+
+/// Notification about the minted tokens, sent by the token wallet
+/// to its owner if `notify` was set
+///
+/// # Type
+/// Internal method
+///
+/// # Inputs
+/// * `tokenRoot: address` - root token contract
+/// * `amount: uint128` - how much tokens were minted
+/// * `remainingGasTo: address` - remaining gas receiver
+/// * `payload: cell` - arbitrary payload from the mint
+///
+pub fn on_accept_tokens_mint() -> &'static ton_abi::Function {
+    declare_function! {
+        name: "onAcceptTokensMint",
+        inputs: OnAcceptTokensMintInputs::param_type(),
+        outputs: Vec::new(),
+    }
+}
@@ -2,7 +2,10 @@ use anyhow::Result;
 use nekoton_abi::*;
 
 use crate::RunLocalSimple;
+use ton_block::MsgAddressInt;
+use ton_types::Cell;
 
+pub mod callbacks;
 pub mod root_token_contract;
 pub mod token_wallet_contract;
 
@@ -45,3 +48,21 @@ impl TokenWalletContract<'_> {
         Ok(result)
     }
 }
+
+/// Computes the token wallet address locally, without calling the root contract.
+///
+/// `wallet_code` is the code returned by the `walletCode` getter of the root,
+/// `abi_version` is the ABI version of the token wallet contract
+pub fn compute_wallet_address(
+    wallet_code: Cell,
+    root: &MsgAddressInt,
+    owner: &MsgAddressInt,
+    abi_version: ton_abi::contract::AbiVersion,
+) -> Result<MsgAddressInt> {
+    let data = make_init_data(
+        None,
+        &[(1, root.token_value()), (2, owner.token_value())],
+        abi_version,
+    )?;
+    Tvc::from_parts(wallet_code, Some(data)).compute_address(root.workchain_id() as i8)
+}
@@ -7,7 +7,8 @@ use crate::RunLocalSimple;
 use anyhow::Result;
 use nekoton_abi::ExecutionContext;
 use nekoton_abi::*;
-use ton_types::Cell;
+use ton_block::{MsgAddressInt, Serializable};
+use ton_types::{BuilderData, Cell, UInt256};
 
 #[derive(Copy, Clone)]
 pub struct CollectionContract<'a>(pub ExecutionContext<'a>);
@@ -36,3 +37,86 @@ impl IndexContract<'_> {
         Ok(result)
     }
 }
+
+/// Builds the salt of the index code for NFTs of the `collection` owned by `owner`
+pub fn make_index_salt(collection: &MsgAddressInt, owner: &MsgAddressInt) -> Result<Cell> {
+    let collection_cell = collection.serialize()?;
+    let owner_cell = owner.serialize()?;
+
+    let mut builder = BuilderData::new();
+    builder.append_raw(collection_cell.data(), collection_cell.bit_length())?;
+    builder.append_raw(owner_cell.data(), owner_cell.bit_length())?;
+
+    let mut stamp = BuilderData::new();
+    stamp.append_raw(NFT_STAMP, 24)?;
+    builder.checked_append_reference(stamp.into_cell()?)?;
+
+    builder.into_cell()
+}
+
+/// Computes the code hash of all indices for NFTs of the `collection` owned by `owner`
+pub fn compute_index_code_hash(
+    index_code: Cell,
+    collection: &MsgAddressInt,
+    owner: &MsgAddressInt,
+) -> Result<UInt256> {
+    compute_salted_code_hash(index_code, make_index_salt(collection, owner)?)
+}
+
+/// Computes the address of the index for the `nft` of the `collection` owned by `owner`.
+///
+/// `abi_version` is the ABI version of the index contract
+pub fn compute_index_address(
+    index_code: Cell,
+    collection: &MsgAddressInt,
+    owner: &MsgAddressInt,
+    nft: &MsgAddressInt,
+    abi_version: ton_abi::contract::AbiVersion,
+) -> Result<MsgAddressInt> {
+    let data = make_init_data(None, &[(1, nft.token_value())], abi_version)?;
+    compute_salted_contract_address(
+        index_code,
+        make_index_salt(collection, owner)?,
+        Some(data),
+        collection.workchain_id() as i8,
+    )
+}
+
+const NFT_STAMP: &[u8; 3] = b"nft";
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ton_block::Deserializable;
+
+    use super::*;
+
+    #[test]
+    fn index_salt_layout() {
+        let collection = MsgAddressInt::from_str(
+            "0:9b1c9a1ee4e6f6c9a4e1ab5e1b2f3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d",
+        )
+        .unwrap();
+        let owner = MsgAddressInt::from_str(
+            "-1:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb",
+        )
+        .unwrap();
+
+        let salt = make_index_salt(&collection, &owner).unwrap();
+        let mut slice = ton_types::SliceData::load_cell(salt).unwrap();
+
+        // Addresses go one after another, in this order
+        let salted_collection = MsgAddressInt::construct_from(&mut slice).unwrap();
+        let salted_owner = MsgAddressInt::construct_from(&mut slice).unwrap();
+        assert_eq!(salted_collection, collection);
+        assert_eq!(salted_owner, owner);
+        assert_eq!(slice.remaining_bits(), 0);
+
+        // Followed by the reference with the stamp
+        assert_eq!(slice.remaining_references(), 1);
+        let stamp = slice.reference(0).unwrap();
+        assert_eq!(stamp.bit_length(), 24);
+        assert_eq!(&stamp.data()[..3], NFT_STAMP);
+    }
+}
@@ -0,0 +1,590 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+
+/// `abigen!(module_name, "path/to/contract.abi.json")`
+pub struct AbigenInput {
+    module: syn::Ident,
+    path: syn::LitStr,
+}
+
+impl Parse for AbigenInput {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let module = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        let path = input.parse()?;
+        let _ = input.parse::<Option<syn::Token![,]>>()?;
+        Ok(Self { module, path })
+    }
+}
+
+pub fn impl_abigen(input: AbigenInput) -> Result<TokenStream, syn::Error> {
+    let error = |message: String| syn::Error::new(input.path.span(), message);
+
+    // Relative paths are resolved from the root of the crate which uses the macro
+    let mut path = PathBuf::from(input.path.value());
+    if path.is_relative() {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+            .map_err(|_| error("CARGO_MANIFEST_DIR is not set".to_owned()))?;
+        path = PathBuf::from(manifest_dir).join(path);
+    }
+
+    let abi = std::fs::read_to_string(&path)
+        .map_err(|e| error(format!("failed to read {}: {e}", path.display())))?;
+    let abi: serde_json::Value =
+        serde_json::from_str(&abi).map_err(|e| error(format!("invalid ABI json: {e}")))?;
+
+    let abi_path = path.to_string_lossy().into_owned();
+    let module = &input.module;
+
+    let functions = parse_entries(&abi, "functions").map_err(error)?;
+    let events = parse_entries(&abi, "events").map_err(error)?;
+
+    let mut generator = Generator::default();
+    let functions = functions
+        .iter()
+        .map(|function| generator.function(function))
+        .collect::<Vec<_>>();
+    let events = generator.events(&events);
+    let types = generator.types;
+
+    Ok(quote! {
+        pub mod #module {
+            #![allow(dead_code, non_snake_case, non_camel_case_types, clippy::all)]
+
+            const ABI: &str = include_str!(#abi_path);
+
+            /// Parsed contract ABI
+            pub fn contract() -> ::nekoton_abi::anyhow::Result<&'static ::nekoton_abi::ton_abi::Contract> {
+                static CONTRACT: ::nekoton_abi::once_cell::race::OnceBox<::nekoton_abi::ton_abi::Contract> =
+                    ::nekoton_abi::once_cell::race::OnceBox::new();
+                CONTRACT.get_or_try_init(|| {
+                    let contract = ::nekoton_abi::ton_abi::Contract::load(ABI.as_bytes())?;
+                    Ok(Box::new(contract))
+                })
+            }
+
+            pub mod functions {
+                use super::*;
+
+                #(#functions)*
+            }
+
+            pub mod events {
+                use super::*;
+
+                #events
+            }
+
+            #(#types)*
+        }
+    })
+}
+
+fn parse_entries(abi: &serde_json::Value, key: &str) -> Result<Vec<Entry>, String> {
+    let entries = match abi.get(key) {
+        Some(serde_json::Value::Array(entries)) => entries,
+        Some(_) => return Err(format!("`{key}` must be an array")),
+        None => return Ok(Vec::new()),
+    };
+
+    entries
+        .iter()
+        .map(|entry| {
+            let name = entry
+                .get("name")
+                .and_then(serde_json::Value::as_str)
+                .ok_or_else(|| format!("`{key}` entry without name"))?;
+            Ok(Entry {
+                name: name.to_owned(),
+                inputs: parse_params(entry.get("inputs"))?,
+                outputs: parse_params(entry.get("outputs"))?,
+            })
+        })
+        .collect()
+}
+
+fn parse_params(params: Option<&serde_json::Value>) -> Result<Vec<AbiParam>, String> {
+    let params = match params {
+        Some(serde_json::Value::Array(params)) => params,
+        Some(_) => return Err("params must be an array".to_owned()),
+        None => return Ok(Vec::new()),
+    };
+
+    params
+        .iter()
+        .map(|param| {
+            let name = param.get("name").and_then(serde_json::Value::as_str);
+            let ty = param.get("type").and_then(serde_json::Value::as_str);
+            match (name, ty) {
+                (Some(name), Some(ty)) => Ok(AbiParam {
+                    name: name.to_owned(),
+                    ty: ty.to_owned(),
+                    components: parse_params(param.get("components"))?,
+                }),
+                _ => Err("param must have name and type".to_owned()),
+            }
+        })
+        .collect()
+}
+
+struct Entry {
+    name: String,
+    inputs: Vec<AbiParam>,
+    outputs: Vec<AbiParam>,
+}
+
+struct AbiParam {
+    name: String,
+    ty: String,
+    components: Vec<AbiParam>,
+}
+
+#[derive(Default)]
+struct Generator {
+    /// Structs for tuples
+    types: Vec<TokenStream>,
+    /// Names of the structs for tuples
+    type_names: Names,
+    /// Function names (both snake and camel case)
+    function_names: Names,
+}
+
+impl Generator {
+    fn function(&mut self, function: &Entry) -> TokenStream {
+        let name = &function.name;
+        let fn_ident = self.function_names.field(name);
+        let camel_name = self.function_names.type_name(name);
+
+        let input = self.params_struct(&format_ident!("{}Input", camel_name), &function.inputs);
+        let output = self.params_struct(&format_ident!("{}Output", camel_name), &function.outputs);
+
+        quote! {
+            pub fn #fn_ident() -> ::nekoton_abi::anyhow::Result<&'static ::nekoton_abi::ton_abi::Function> {
+                Ok(super::contract()?.function(#name)?)
+            }
+
+            #input
+            #output
+        }
+    }
+
+    fn events(&mut self, events: &[Entry]) -> TokenStream {
+        if events.is_empty() {
+            return TokenStream::new();
+        }
+
+        let mut items = Vec::with_capacity(events.len());
+        let mut variants = Vec::with_capacity(events.len());
+        let mut unpack_variants = Vec::with_capacity(events.len());
+
+        let mut names = Names::default();
+        for event in events {
+            let name = &event.name;
+            let fn_ident = names.field(name);
+            let camel_name = names.type_name(name);
+            let variant = format_ident!("{}", camel_name);
+            let struct_ident = format_ident!("{}Event", camel_name);
+
+            let definition = self.params_struct(&struct_ident, &event.inputs);
+            items.push(quote! {
+                pub fn #fn_ident() -> ::nekoton_abi::anyhow::Result<&'static ::nekoton_abi::ton_abi::Event> {
+                    super::contract()?
+                        .events
+                        .get(#name)
+                        .ok_or_else(|| ::nekoton_abi::anyhow::anyhow!("event {} not found", #name))
+                }
+
+                #definition
+            });
+
+            if event.inputs.is_empty() {
+                variants.push(quote! { #variant });
+                unpack_variants.push(quote! { #name => Self::#variant });
+            } else {
+                variants.push(quote! { #variant(#struct_ident) });
+                unpack_variants.push(quote! {
+                    #name => Self::#variant(::nekoton_abi::UnpackAbiPlain::unpack(tokens)?)
+                });
+            }
+        }
+
+        quote! {
+            #(#items)*
+
+            #[derive(Debug, Clone)]
+            pub enum Event {
+                #(#variants,)*
+            }
+
+            impl Event {
+                /// Unpacks decoded event tokens. Returns `None` for unknown events
+                pub fn unpack(
+                    name: &str,
+                    tokens: Vec<::nekoton_abi::ton_abi::Token>,
+                ) -> ::nekoton_abi::UnpackerResult<Option<Self>> {
+                    Ok(Some(match name {
+                        #(#unpack_variants,)*
+                        _ => return Ok(None),
+                    }))
+                }
+            }
+        }
+    }
+
+    fn params_struct(&mut self, ident: &syn::Ident, params: &[AbiParam]) -> TokenStream {
+        if params.is_empty() {
+            return TokenStream::new();
+        }
+
+        let mut names = Names::default();
+        let fields = params
+            .iter()
+            .map(|param| self.field(&ident.to_string(), param, &mut names))
+            .collect::<Vec<_>>();
+
+        quote! {
+            #[derive(Debug, Clone, ::nekoton_abi::PackAbiPlain, ::nekoton_abi::UnpackAbiPlain)]
+            pub struct #ident {
+                #(#fields,)*
+            }
+        }
+    }
+
+    fn field(&mut self, parent: &str, param: &AbiParam, names: &mut Names) -> TokenStream {
+        let name = &param.name;
+        let ident = names.field(name);
+        let ty = self.rust_type(parent, param).ty;
+        quote! {
+            #[abi(name = #name)]
+            pub #ident: #ty
+        }
+    }
+
+    fn rust_type(&mut self, parent: &str, param: &AbiParam) -> RustType {
+        let ty = param.ty.as_str();
+
+        if let Some(item) = ty.strip_suffix("[]") {
+            let item = self.rust_type(parent, &param.with_type(item));
+            return match item.known {
+                // `Vec<u8>` is used for bytes, strings are not standalone tokens
+                true if !matches!(item.ty.to_string().as_str(), "u8" | "String") => {
+                    let item = item.ty;
+                    RustType::known(quote! { Vec<#item> })
+                }
+                _ => RustType::raw(),
+            };
+        }
+
+        if let Some(inner) = ty
+            .strip_prefix("optional(")
+            .and_then(|ty| ty.strip_suffix(')'))
+        {
+            let inner = self.rust_type(parent, &param.with_type(inner));
+            return match inner.known {
+                true => {
+                    let inner = inner.ty;
+                    RustType::known(quote! { Option<#inner> })
+                }
+                false => RustType::raw(),
+            };
+        }
+
+        if ty == "tuple" {
+            return self.tuple(parent, param);
+        }
+
+        match primitive_type(ty) {
+            Some(ty) => RustType::known(ty),
+            None => RustType::raw(),
+        }
+    }
+
+    fn tuple(&mut self, parent: &str, param: &AbiParam) -> RustType {
+        let ident = self.type_names.unique(
+            format!("{}{}", parent, to_camel_case(&sanitize(&param.name))),
+            "",
+        );
+        let ident = format_ident!("{}", ident);
+
+        let mut known = true;
+        let mut names = Names::default();
+        let fields = param
+            .components
+            .iter()
+            .map(|component| {
+                let name = &component.name;
+                let field = names.field(name);
+                let ty = self.rust_type(&ident.to_string(), component);
+                known &= ty.known;
+                let ty = ty.ty;
+                quote! {
+                    #[abi(name = #name)]
+                    pub #field: #ty
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Param type is only required for tuples inside arrays or optionals
+        let known_param_type = match known {
+            true => quote! {
+                #[derive(::nekoton_abi::KnownParamType)]
+            },
+            false => TokenStream::new(),
+        };
+
+        self.types.push(quote! {
+            #[derive(Debug, Clone, ::nekoton_abi::PackAbi, ::nekoton_abi::UnpackAbi)]
+            #known_param_type
+            pub struct #ident {
+                #(#fields,)*
+            }
+
+            impl ::nekoton_abi::StandaloneToken for #ident {}
+        });
+
+        RustType {
+            ty: quote! { #ident },
+            known,
+        }
+    }
+}
+
+impl AbiParam {
+    fn with_type(&self, ty: &str) -> Self {
+        Self {
+            name: self.name.clone(),
+            ty: ty.to_owned(),
+            components: self
+                .components
+                .iter()
+                .map(|component| component.with_type(&component.ty))
+                .collect(),
+        }
+    }
+}
+
+struct RustType {
+    ty: TokenStream,
+    /// Whether the type implements `KnownParamType`
+    known: bool,
+}
+
+impl RustType {
+    fn known(ty: TokenStream) -> Self {
+        Self { ty, known: true }
+    }
+
+    /// Types without a direct mapping are left as is
+    fn raw() -> Self {
+        Self {
+            ty: quote! { ::nekoton_abi::ton_abi::TokenValue },
+            known: false,
+        }
+    }
+}
+
+fn primitive_type(ty: &str) -> Option<TokenStream> {
+    Some(match ty {
+        "uint8" => quote! { u8 },
+        "uint16" => quote! { u16 },
+        "uint32" => quote! { u32 },
+        "uint64" => quote! { u64 },
+        "uint128" => quote! { u128 },
+        "uint256" => quote! { ::nekoton_abi::ton_types::UInt256 },
+        "int8" => quote! { i8 },
+        "int16" => quote! { i16 },
+        "int32" => quote! { i32 },
+        "int64" => quote! { i64 },
+        "int128" => quote! { i128 },
+        "int256" => quote! { ::nekoton_abi::Int256 },
+        "bool" => quote! { bool },
+        "address" => quote! { ::nekoton_abi::ton_block::MsgAddressInt },
+        "cell" => quote! { ::nekoton_abi::ton_types::Cell },
+        "string" => quote! { String },
+        "bytes" => quote! { Vec<u8> },
+        "gram" | "token" => quote! { ::nekoton_abi::ton_block::Grams },
+        _ => return None,
+    })
+}
+
+/// Generated names which must be unique within their scope
+#[derive(Default)]
+struct Names(HashSet<String>);
+
+impl Names {
+    /// Returns a unique snake case identifier for the ABI name
+    fn field(&mut self, name: &str) -> syn::Ident {
+        let name = self.unique(field_name(name), "_");
+        match syn::parse_str::<syn::Ident>(&name) {
+            Ok(ident) => ident,
+            // Other keywords can be used as raw identifiers
+            Err(_) => syn::Ident::new_raw(&name, Span::call_site()),
+        }
+    }
+
+    /// Returns a unique camel case name for the ABI name
+    fn type_name(&mut self, name: &str) -> String {
+        let mut name = to_camel_case(&sanitize(name));
+        if name.is_empty() || name == "Self" || name.starts_with(|c: char| c.is_ascii_digit()) {
+            name.insert_str(0, "Value");
+        }
+        self.unique(name, "")
+    }
+
+    /// Appends a numeric suffix to the name if it is already used
+    fn unique(&mut self, name: String, separator: &str) -> String {
+        let separator = if name.ends_with('_') { "" } else { separator };
+        let mut result = name.clone();
+        let mut index = 1;
+        while !self.0.insert(result.clone()) {
+            result = format!("{name}{separator}{index}");
+            index += 1;
+        }
+        result
+    }
+}
+
+fn field_name(name: &str) -> String {
+    let mut name = to_snake_case(sanitize(name).trim_start_matches('_'));
+    if name.is_empty() {
+        name.push_str("value");
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert_str(0, "value_");
+    }
+
+    // NOTE: these keywords can't be raw identifiers
+    if matches!(name.as_str(), "self" | "super" | "crate") {
+        name.push('_');
+    }
+    name
+}
+
+/// Replaces characters which are not allowed in identifiers
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len() + 4);
+    let mut prev_lowercase = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if prev_lowercase {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+            prev_lowercase = false;
+        } else {
+            prev_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn to_camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => format!("{}{}", first.to_ascii_uppercase(), chars.as_str()),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_conversion() {
+        assert_eq!(to_snake_case("reqConfirms"), "req_confirms");
+        assert_eq!(to_snake_case("sendTransaction"), "send_transaction");
+        assert_eq!(to_snake_case("value0"), "value0");
+
+        let field = |name: &str| Names::default().field(name).to_string();
+        assert_eq!(field("_answer_id"), "answer_id");
+        assert_eq!(field("type"), "r#type");
+        assert_eq!(field("self"), "self_");
+        assert_eq!(field("Self"), "self_");
+        assert_eq!(field("super"), "super_");
+        assert_eq!(field("crate"), "crate_");
+        assert_eq!(field("0x"), "value_0x");
+        assert_eq!(field("_"), "value");
+        assert_eq!(field("__"), "value");
+        assert_eq!(field("a-b"), "a_b");
+
+        let mut names = Names::default();
+        assert_eq!(names.field("value").to_string(), "value");
+        assert_eq!(names.field("_value").to_string(), "value_1");
+        assert_eq!(names.field("__value").to_string(), "value_2");
+        assert_eq!(names.field("self").to_string(), "self_");
+        assert_eq!(names.field("_self").to_string(), "self_1");
+        assert_eq!(names.type_name("_"), "Value");
+        assert_eq!(names.type_name("0"), "Value0");
+        assert_eq!(names.type_name("__"), "Value1");
+        assert_eq!(names.type_name("self"), "ValueSelf");
+
+        assert_eq!(to_camel_case("submitTransaction"), "SubmitTransaction");
+        assert_eq!(to_camel_case("_answer_id"), "AnswerId");
+    }
+
+    #[test]
+    fn type_mapping() {
+        let mut generator = Generator::default();
+
+        let param = |ty: &str| AbiParam {
+            name: "value".to_owned(),
+            ty: ty.to_owned(),
+            components: vec![
+                AbiParam {
+                    name: "flag".to_owned(),
+                    ty: "bool".to_owned(),
+                    components: Vec::new(),
+                },
+                AbiParam {
+                    name: "amount".to_owned(),
+                    ty: "uint128".to_owned(),
+                    components: Vec::new(),
+                },
+            ],
+        };
+
+        let mut check = |ty: &str, expected: &str, known: bool| {
+            let result = generator.rust_type("Test", &param(ty));
+            assert_eq!(result.ty.to_string().replace(' ', ""), expected);
+            assert_eq!(result.known, known);
+        };
+
+        check("uint32", "u32", true);
+        check("uint256[]", "Vec<::nekoton_abi::ton_types::UInt256>", true);
+        check(
+            "optional(cell)",
+            "Option<::nekoton_abi::ton_types::Cell>",
+            true,
+        );
+        check("uint8[]", "::nekoton_abi::ton_abi::TokenValue", false);
+        check("string[]", "::nekoton_abi::ton_abi::TokenValue", false);
+        check("varuint16", "::nekoton_abi::ton_abi::TokenValue", false);
+        check(
+            "map(address,uint128)",
+            "::nekoton_abi::ton_abi::TokenValue",
+            false,
+        );
+        check("tuple[]", "Vec<TestValue>", true);
+
+        assert_eq!(generator.types.len(), 1);
+    }
+}
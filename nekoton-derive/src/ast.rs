@@ -19,6 +19,7 @@ pub enum Data<'a> {
 
 pub struct Variant<'a> {
     pub ident: syn::Ident,
+    pub discriminant: u64,
     pub style: StructStyle,
     pub fields: Vec<Field<'a>>,
     pub original: &'a syn::Variant,
@@ -47,6 +48,44 @@ impl<'a> Container<'a> {
             }
         };
 
+        if let Data::Enum(variants) = &data {
+            let tag_bits = attrs.tag_bits;
+            for variant in variants {
+                if tag_bits < 64 && variant.discriminant >> tag_bits != 0 {
+                    cx.error_spanned_by(
+                        variant.original,
+                        format!("discriminant doesn't fit into {tag_bits} bits"),
+                    );
+                }
+            }
+        }
+
+        if let Data::Struct(style, fields) = &data {
+            match style {
+                StructStyle::Tuple => {
+                    cx.error_spanned_by(
+                        input,
+                        "tuple structs are only supported with a single field",
+                    );
+                }
+                StructStyle::NewType => {
+                    if attrs.by_name {
+                        cx.error_spanned_by(input, "Invalid attribute 'by_name' for newtype");
+                    }
+                    for field in fields {
+                        if field.attrs.skip || field.attrs.flatten || field.attrs.default.is_some()
+                        {
+                            cx.error_spanned_by(
+                                field.original,
+                                "#[abi(skip)], #[abi(flatten)] and #[abi(default)] are not supported for newtypes",
+                            );
+                        }
+                    }
+                }
+                StructStyle::Struct | StructStyle::Unit => {}
+            }
+        }
+
         let item = Self {
             ident: input.ident.clone(),
             attrs,
@@ -90,12 +129,20 @@ fn enum_from_ast<'a>(
         return None;
     }
 
+    let mut next_discriminant = 0u64;
     let result: Vec<Variant<'_>> = variants
         .iter()
         .flat_map(|variant| {
+            let discriminant = match &variant.discriminant {
+                Some((_, expr)) => parse_discriminant(cx, expr)?,
+                None => next_discriminant,
+            };
+            next_discriminant = discriminant.wrapping_add(1);
+
             let (style, fields) = struct_from_ast(cx, &variant.fields)?;
             Some(Variant {
                 ident: variant.ident.clone(),
+                discriminant,
                 style,
                 fields,
                 original: variant,
@@ -106,6 +153,25 @@ fn enum_from_ast<'a>(
     (result.len() == variants.len()).then_some(result)
 }
 
+fn parse_discriminant(cx: &ParsingContext, expr: &syn::Expr) -> Option<u64> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(lit),
+            ..
+        }) => match lit.base10_parse::<u64>() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                cx.syn_error(e);
+                None
+            }
+        },
+        _ => {
+            cx.error_spanned_by(expr, "expected unsigned integer literal as discriminant");
+            None
+        }
+    }
+}
+
 fn struct_from_ast<'a>(
     cx: &ParsingContext,
     fields: &'a syn::Fields,
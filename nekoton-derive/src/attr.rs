@@ -9,11 +9,18 @@ use crate::symbol::*;
 
 pub struct Container {
     pub enum_bool: bool,
+    pub by_name: bool,
+    pub tag_bits: usize,
+    /// `#[abi(validate = "path")]`, called with the unpacked value
+    pub validate: Option<syn::Expr>,
 }
 
 impl Container {
     pub fn from_ast(cx: &ParsingContext, input: &syn::DeriveInput) -> Option<Self> {
         let mut enum_bool = BoolAttr::none(cx, ENUM_BOOL);
+        let mut by_name = BoolAttr::none(cx, BY_NAME);
+        let mut tag_bits = Attr::none(cx, TAG_BITS);
+        let mut validate = Attr::none(cx, VALIDATE);
 
         for (from, meta_item) in input
             .attrs
@@ -23,6 +30,17 @@ impl Container {
         {
             match (from, &meta_item) {
                 (AttrFrom::Abi, Meta(Path(word))) if word == ENUM_BOOL => enum_bool.set_true(word),
+                (AttrFrom::Abi, Meta(Path(word))) if word == BY_NAME => by_name.set_true(word),
+                (AttrFrom::Abi, Meta(NameValue(m))) if m.path == TAG_BITS => {
+                    if let Ok(bits) = get_lit_int(cx, TAG_BITS, &m.lit) {
+                        tag_bits.set(&m.path, bits);
+                    }
+                }
+                (AttrFrom::Abi, Meta(NameValue(m))) if m.path == VALIDATE => {
+                    if let Ok(expr) = parse_lit_into_expr(cx, VALIDATE, &m.lit) {
+                        validate.set(&m.path, expr);
+                    }
+                }
                 (AttrFrom::Abi, token) => {
                     cx.error_spanned_by(token, "unexpected token");
                     return None;
@@ -30,18 +48,55 @@ impl Container {
             }
         }
 
-        if let syn::Data::Struct(_) = input.data {
-            if enum_bool.get() {
+        let tag_bits = tag_bits.get();
+
+        match &input.data {
+            syn::Data::Struct(_) if enum_bool.get() => {
                 cx.error_spanned_by(input, "Invalid attribute 'boolean' for struct");
             }
+            syn::Data::Struct(_) if tag_bits.is_some() => {
+                cx.error_spanned_by(input, "Invalid attribute 'tag_bits' for struct");
+            }
+            syn::Data::Enum(_) if by_name.get() => {
+                cx.error_spanned_by(input, "Invalid attribute 'by_name' for enum");
+            }
+            syn::Data::Enum(_) if enum_bool.get() && tag_bits.is_some() => {
+                cx.error_spanned_by(
+                    input,
+                    "#[abi(boolean)] attribute can't be used with 'tag_bits'",
+                );
+            }
+            syn::Data::Enum(data)
+                if enum_bool.get()
+                    && data
+                        .variants
+                        .iter()
+                        .any(|variant| !matches!(variant.fields, syn::Fields::Unit)) =>
+            {
+                cx.error_spanned_by(
+                    input,
+                    "#[abi(boolean)] attribute is not supported for enums with data",
+                );
+            }
+            _ => {}
+        }
+
+        let tag_bits = tag_bits.unwrap_or(DEFAULT_TAG_BITS);
+        if !(1..=64).contains(&tag_bits) {
+            cx.error_spanned_by(input, "'tag_bits' must be in range 1..=64");
         }
 
         Some(Self {
             enum_bool: enum_bool.get(),
+            by_name: by_name.get(),
+            tag_bits,
+            validate: validate.get(),
         })
     }
 }
 
+const DEFAULT_TAG_BITS: usize = 8;
+
 pub struct Field {
     pub skip: bool,
     pub name: Option<String>,
@@ -51,18 +106,32 @@ pub struct Field {
     pub unpack_with: Option<syn::Expr>,
     pub param_type_with: Option<syn::Expr>,
     pub is_array: bool,
+    pub flatten: bool,
+    pub default: Option<DefaultValue>,
+}
+
+/// Value which is used for skipped or missing fields
+pub enum DefaultValue {
+    /// `#[abi(default)]`
+    Trait,
+    /// `#[abi(default = "path")]`
+    Path(syn::Expr),
 }
 
 impl Field {
     pub fn from_ast(cx: &ParsingContext, _index: usize, input: &syn::Field) -> Option<Self> {
         let mut name = Attr::none(cx, NAME);
+        let mut rename = Attr::none(cx, RENAME);
         let mut type_name = Attr::none(cx, TYPE_NAME);
         let mut with = Attr::none(cx, WITH);
+        let mut custom = Attr::none(cx, CUSTOM);
         let mut pack_with = Attr::none(cx, PACK_WITH);
         let mut unpack_with = Attr::none(cx, UNPACK_WITH);
         let mut param_type_with = Attr::none(cx, PARAM_TYPE_WITH);
         let mut is_array = BoolAttr::none(cx, ARRAY);
         let mut skip = BoolAttr::none(cx, SKIP);
+        let mut flatten = BoolAttr::none(cx, FLATTEN);
+        let mut default = Attr::none(cx, DEFAULT);
 
         let has_abi_attr = has_abi_attr(&input.attrs);
 
@@ -78,8 +147,22 @@ impl Field {
                         name.set(&m.path, s.value());
                     }
                 }
+                (AttrFrom::Abi, Meta(NameValue(m))) if m.path == RENAME => {
+                    if let Ok(s) = get_lit_str(cx, RENAME, &m.lit) {
+                        rename.set(&m.path, s.value());
+                    }
+                }
                 (AttrFrom::Abi, Meta(Path(word))) if word == ARRAY => is_array.set_true(word),
                 (AttrFrom::Abi, Meta(Path(word))) if word == SKIP => skip.set_true(word),
+                (AttrFrom::Abi, Meta(Path(word))) if word == FLATTEN => flatten.set_true(word),
+                (AttrFrom::Abi, Meta(Path(word))) if word == DEFAULT => {
+                    default.set(word, DefaultValue::Trait)
+                }
+                (AttrFrom::Abi, Meta(NameValue(m))) if m.path == DEFAULT => {
+                    if let Ok(expr) = parse_lit_into_expr(cx, DEFAULT, &m.lit) {
+                        default.set(&m.path, DefaultValue::Path(expr));
+                    }
+                }
                 (AttrFrom::Abi, Meta(Path(word))) => {
                     if let Some(word) = word.get_ident() {
                         let pt = TypeName::from(&word.to_string());
@@ -95,6 +178,11 @@ impl Field {
                         with.set(&m.path, expr);
                     }
                 }
+                (AttrFrom::Abi, Meta(NameValue(m))) if m.path == CUSTOM => {
+                    if let Ok(expr) = parse_lit_into_expr(cx, CUSTOM, &m.lit) {
+                        custom.set(&m.path, expr);
+                    }
+                }
                 (AttrFrom::Abi, Meta(NameValue(m))) if m.path == PACK_WITH => {
                     if let Ok(expr) = parse_lit_into_expr(cx, PACK_WITH, &m.lit) {
                         pack_with.set(&m.path, expr);
@@ -126,8 +214,29 @@ impl Field {
 
         let skip = skip.get();
 
+        // `rename` and `custom` are aliases for `name` and `with`
+        let name = match (name.get(), rename.get()) {
+            (Some(_), Some(_)) => {
+                cx.error_spanned_by(
+                    input,
+                    "'name' and 'rename' attributes can't be used together",
+                );
+                None
+            }
+            (name, rename) => name.or(rename),
+        };
+
         let type_name = type_name.get();
-        let with = with.get();
+        let with = match (with.get(), custom.get()) {
+            (Some(_), Some(_)) => {
+                cx.error_spanned_by(
+                    input,
+                    "'with' and 'custom' attributes can't be used together",
+                );
+                None
+            }
+            (with, custom) => with.or(custom),
+        };
         let pack_with = pack_with.get();
         let unpack_with = unpack_with.get();
         let param_type_with = param_type_with.get();
@@ -152,19 +261,40 @@ impl Field {
             | (_, None, Some(_), None, None, None)
             | (_, None, None, ..) => {}
             _ => {
-                cx.error_spanned_by(input, "Only one of attributes ('type', 'with', 'pack_with/unpack_with/param_type_with') can be selected at time");
+                cx.error_spanned_by(input, "Only one of attributes ('type', 'with'/'custom', 'pack_with/unpack_with/param_type_with') can be selected at time");
             }
         };
 
+        let flatten = flatten.get();
+        let is_array = is_array.get();
+        let default = default.get();
+        if flatten
+            && (skip
+                || is_array
+                || default.is_some()
+                || type_name.is_some()
+                || with.is_some()
+                || pack_with.is_some()
+                || unpack_with.is_some()
+                || param_type_with.is_some())
+        {
+            cx.error_spanned_by(
+                input,
+                "#[abi(flatten)] attribute can't be used with other attributes",
+            );
+        }
+
         Some(Self {
             skip,
-            name: name.get(),
+            name,
             type_name,
             with,
             pack_with,
             unpack_with,
             param_type_with,
-            is_array: is_array.get(),
+            is_array,
+            flatten,
+            default,
         })
     }
 }
@@ -246,6 +376,18 @@ fn get_lit_str_special<'a>(
     }
 }
 
+fn get_lit_int(cx: &ParsingContext, attr_name: Symbol, lit: &syn::Lit) -> Result<usize, ()> {
+    if let syn::Lit::Int(lit) = lit {
+        lit.base10_parse().map_err(|e| cx.syn_error(e))
+    } else {
+        cx.error_spanned_by(
+            lit,
+            format!("expected {attr_name} attribute to be an integer: `{attr_name} = ...`"),
+        );
+        Err(())
+    }
+}
+
 fn get_meta_items(
     cx: &ParsingContext,
     attr: &syn::Attribute,
@@ -400,43 +542,43 @@ impl TypeName {
     pub fn get_param_type(&self) -> proc_macro2::TokenStream {
         match self {
             TypeName::Int8 => quote! {
-                ::ton_abi::ParamType::Int(8)
+                ::nekoton_abi::ton_abi::ParamType::Int(8)
             },
             TypeName::Uint8 => quote! {
-                ::ton_abi::ParamType::Uint(8)
+                ::nekoton_abi::ton_abi::ParamType::Uint(8)
             },
             TypeName::Uint16 => quote! {
-                ::ton_abi::ParamType::Uint(16)
+                ::nekoton_abi::ton_abi::ParamType::Uint(16)
             },
             TypeName::Uint32 => quote! {
-                ::ton_abi::ParamType::Uint(32)
+                ::nekoton_abi::ton_abi::ParamType::Uint(32)
             },
             TypeName::Uint64 => quote! {
-                ::ton_abi::ParamType::Uint(64)
+                ::nekoton_abi::ton_abi::ParamType::Uint(64)
             },
             TypeName::Uint128 => quote! {
-                ::ton_abi::ParamType::Uint(128)
+                ::nekoton_abi::ton_abi::ParamType::Uint(128)
             },
             TypeName::Uint256 => quote! {
-                ::ton_abi::ParamType::Uint(256)
+                ::nekoton_abi::ton_abi::ParamType::Uint(256)
             },
             TypeName::Grams => quote! {
-                ::ton_abi::ParamType::Token
+                ::nekoton_abi::ton_abi::ParamType::Token
             },
             TypeName::Address => quote! {
-                ::ton_abi::ParamType::Address
+                ::nekoton_abi::ton_abi::ParamType::Address
             },
             TypeName::Cell => quote! {
-                ::ton_abi::ParamType::Cell
+                ::nekoton_abi::ton_abi::ParamType::Cell
             },
             TypeName::Bool => quote! {
-                ::ton_abi::ParamType::Bool
+                ::nekoton_abi::ton_abi::ParamType::Bool
             },
             TypeName::String => quote! {
-                ::ton_abi::ParamType::String
+                ::nekoton_abi::ton_abi::ParamType::String
             },
             TypeName::Bytes => quote! {
-                ::ton_abi::ParamType::Bytes
+                ::nekoton_abi::ton_abi::ParamType::Bytes
             },
             TypeName::None => unreachable!(),
         }
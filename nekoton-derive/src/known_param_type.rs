@@ -17,6 +17,18 @@ pub fn impl_derive_known_param_type(
     if plain && matches!(&container.data, Data::Enum(_)) {
         cx.error_spanned_by(&input.ident, "Plain packer is not supported for enums");
     }
+    if plain && matches!(&container.data, Data::Struct(StructStyle::NewType, _)) {
+        cx.error_spanned_by(&input.ident, "Plain packer is not supported for newtypes");
+    }
+
+    if let Data::Enum(variants) = &container.data {
+        if variants.iter().any(|variant| !variant.fields.is_empty()) {
+            cx.error_spanned_by(
+                &input.ident,
+                "Known param type is not supported for enums with data",
+            );
+        }
+    }
 
     cx.check()?;
 
@@ -24,14 +36,25 @@ pub fn impl_derive_known_param_type(
     let result = match &container.data {
         Data::Enum(_) => {
             let body = if container.attrs.enum_bool {
-                quote! { ::ton_abi::ParamType::Bool }
+                quote! { ::nekoton_abi::ton_abi::ParamType::Bool }
             } else {
-                quote! { ::ton_abi::ParamType::Uint(8) }
+                let tag_bits = container.attrs.tag_bits;
+                quote! { ::nekoton_abi::ton_abi::ParamType::Uint(#tag_bits) }
             };
 
             quote! {
                 impl ::nekoton_abi::KnownParamType for #ident {
-                    fn param_type() -> ::ton_abi::ParamType {
+                    fn param_type() -> ::nekoton_abi::ton_abi::ParamType {
+                        #body
+                    }
+                }
+            }
+        }
+        Data::Struct(StructStyle::NewType, fields) => {
+            let body = field_param_type(&fields[0]);
+            quote! {
+                impl ::nekoton_abi::KnownParamType for #ident {
+                    fn param_type() -> ::nekoton_abi::ton_abi::ParamType {
                         #body
                     }
                 }
@@ -42,7 +65,7 @@ pub fn impl_derive_known_param_type(
                 let body = serialize_struct(&container, fields, StructType::Plain);
                 quote! {
                     impl ::nekoton_abi::KnownParamTypePlain for #ident {
-                        fn param_type() -> Vec<::ton_abi::Param> {
+                        fn param_type() -> Vec<::nekoton_abi::ton_abi::Param> {
                             #body
                         }
                     }
@@ -51,7 +74,7 @@ pub fn impl_derive_known_param_type(
                 let body = serialize_struct(&container, fields, StructType::Tuple);
                 quote! {
                     impl ::nekoton_abi::KnownParamType for #ident {
-                        fn param_type() -> ::ton_abi::ParamType {
+                        fn param_type() -> ::nekoton_abi::ton_abi::ParamType {
                             #body
                         }
                     }
@@ -70,7 +93,7 @@ fn serialize_struct(
     let field_count = fields.len();
 
     let definition = quote! {
-        let mut params: Vec<::ton_abi::Param> = Vec::with_capacity(#field_count);
+        let mut params: Vec<::nekoton_abi::ton_abi::Param> = Vec::with_capacity(#field_count);
     };
 
     let build_fields = fields.iter().map(|f| {
@@ -84,51 +107,15 @@ fn serialize_struct(
             None => name.to_string(),
         };
 
-        let ty = &f.original.ty;
-
-        if let Some(type_name) = f.attrs.type_name.as_ref() {
-            let param_type = type_name.get_param_type();
-            match f.attrs.is_array {
-                true => {
-                    quote! {
-                        params.push(::ton_abi::Param::new(
-                            #field_name,
-                            ::ton_abi::ParamType::Array(Box::new(#param_type))
-                        ))
-                    }
-                }
-                false => {
-                    quote! {
-                        params.push(::ton_abi::Param::new(#field_name, #param_type))
-                    }
-                }
-            }
-        } else if let Some(with) = f.attrs.with.as_ref() {
-            quote! {
-                params.push(::ton_abi::Param::new(#field_name, #with::param_type()))
-            }
-        } else if let Some(param_type_with) = f.attrs.param_type_with.as_ref() {
+        if f.attrs.flatten {
+            let ty = &f.original.ty;
             quote! {
-                params.push(::ton_abi::Param::new(#field_name, #param_type_with()))
+                params.extend(<#ty as ::nekoton_abi::KnownParamTypePlain>::param_type())
             }
         } else {
-            match f.attrs.is_array {
-                true => {
-                    quote! {
-                        params.push(::ton_abi::Param::new(
-                            #field_name,
-                            ::ton_abi::ParamType::Array(Box::new(<#ty as ::nekoton_abi::KnownParamTypeArray<_>>::item_param_type())),
-                        ))
-                    }
-                }
-                false => {
-                    quote! {
-                        params.push(::ton_abi::Param::new(
-                            #field_name,
-                            <#ty as ::nekoton_abi::KnownParamType>::param_type(),
-                        ))
-                    }
-                }
+            let param_type = field_param_type(f);
+            quote! {
+                params.push(::nekoton_abi::ton_abi::Param::new(#field_name, #param_type))
             }
         }
     });
@@ -145,8 +132,31 @@ fn serialize_struct(
             quote! {
                 #definition
                 #(#build_fields;)*
-                ::ton_abi::ParamType::Tuple(params)
+                ::nekoton_abi::ton_abi::ParamType::Tuple(params)
             }
         }
     }
 }
+
+fn field_param_type(f: &Field<'_>) -> proc_macro2::TokenStream {
+    let ty = &f.original.ty;
+
+    if let Some(type_name) = f.attrs.type_name.as_ref() {
+        let param_type = type_name.get_param_type();
+        match f.attrs.is_array {
+            true => quote! { ::nekoton_abi::ton_abi::ParamType::Array(Box::new(#param_type)) },
+            false => param_type,
+        }
+    } else if let Some(with) = f.attrs.with.as_ref() {
+        quote! { #with::param_type() }
+    } else if let Some(param_type_with) = f.attrs.param_type_with.as_ref() {
+        quote! { #param_type_with() }
+    } else {
+        match f.attrs.is_array {
+            true => quote! {
+                ::nekoton_abi::ton_abi::ParamType::Array(Box::new(<#ty as ::nekoton_abi::KnownParamTypeArray<_>>::item_param_type()))
+            },
+            false => quote! { <#ty as ::nekoton_abi::KnownParamType>::param_type() },
+        }
+    }
+}
@@ -55,10 +55,12 @@
 use quote::quote;
 use syn::parse_macro_input;
 
+use self::abigen::*;
 use self::known_param_type::*;
 use self::pack_abi::*;
 use self::unpack_abi::*;
 
+mod abigen;
 mod ast;
 mod attr;
 mod known_param_type;
@@ -116,6 +118,27 @@ pub fn derive_unpack_abi_plain(input: proc_macro::TokenStream) -> proc_macro::To
         .into()
 }
 
+/// Generates typed wrappers for the contract ABI.
+///
+/// `abigen!(wallet, "abi/wallet.abi.json")` produces module `wallet` with
+/// `contract()`, a function getter with `Input`/`Output` structs for each
+/// function and an `Event` enum. Relative paths are resolved from the crate root.
+/// Types without a direct Rust mapping are left as `ton_abi::TokenValue`.
+///
+/// Names are converted to snake case (or camel case for types). Names which are
+/// not valid identifiers get a `value` prefix (e.g. `0` -> `value_0`), `self`,
+/// `super` and `crate` get a `_` suffix, and duplicates get a numeric suffix.
+///
+/// Getters return an error for the ABI which can't be parsed by `ton_abi`.
+/// Generated code only refers to `nekoton_abi` (with the `derive` feature)
+#[proc_macro]
+pub fn abigen(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as AbigenInput);
+    impl_abigen(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 fn to_compile_errors(errors: Vec<syn::Error>) -> proc_macro2::TokenStream {
     let compile_errors = errors.iter().map(syn::Error::to_compile_error);
     quote!(#(#compile_errors)*)
@@ -18,6 +18,9 @@ pub fn impl_derive_pack_abi(
     if plain && matches!(&container.data, Data::Enum(_)) {
         cx.error_spanned_by(&input.ident, "Plain packer is not supported for enums");
     }
+    if plain && matches!(&container.data, Data::Struct(StructStyle::NewType, _)) {
+        cx.error_spanned_by(&input.ident, "Plain packer is not supported for newtypes");
+    }
 
     cx.check()?;
 
@@ -32,13 +35,29 @@ pub fn impl_derive_pack_abi(
             let body = serialize_enum(&container, variants, enum_type);
             quote! {
                 impl ::nekoton_abi::BuildTokenValue for #ident {
-                    fn token_value(self) -> ::ton_abi::TokenValue {
+                    fn token_value(self) -> ::nekoton_abi::ton_abi::TokenValue {
                         #body
                     }
                 }
 
                 impl ::nekoton_abi::PackAbi for #ident {
-                    fn pack(self) -> ::ton_abi::TokenValue {
+                    fn pack(self) -> ::nekoton_abi::ton_abi::TokenValue {
+                        ::nekoton_abi::BuildTokenValue::token_value(self)
+                    }
+                }
+            }
+        }
+        Data::Struct(StructStyle::NewType, fields) => {
+            let value = field_value(&fields[0], quote! { self.0 });
+            quote! {
+                impl ::nekoton_abi::BuildTokenValue for #ident {
+                    fn token_value(self) -> ::nekoton_abi::ton_abi::TokenValue {
+                        #value
+                    }
+                }
+
+                impl ::nekoton_abi::PackAbi for #ident {
+                    fn pack(self) -> ::nekoton_abi::ton_abi::TokenValue {
                         ::nekoton_abi::BuildTokenValue::token_value(self)
                     }
                 }
@@ -49,7 +68,7 @@ pub fn impl_derive_pack_abi(
                 let body = serialize_struct(&container, fields, StructType::Plain);
                 quote! {
                     impl ::nekoton_abi::PackAbiPlain for #ident {
-                        fn pack(self) -> Vec<::ton_abi::Token> {
+                        fn pack(self) -> Vec<::nekoton_abi::ton_abi::Token> {
                             #body
                         }
                     }
@@ -58,13 +77,13 @@ pub fn impl_derive_pack_abi(
                 let body = serialize_struct(&container, fields, StructType::Tuple);
                 quote! {
                     impl ::nekoton_abi::BuildTokenValue for #ident {
-                        fn token_value(self) -> ::ton_abi::TokenValue {
+                        fn token_value(self) -> ::nekoton_abi::ton_abi::TokenValue {
                             #body
                         }
                     }
 
                     impl ::nekoton_abi::PackAbi for #ident {
-                        fn pack(self) -> ::ton_abi::TokenValue {
+                        fn pack(self) -> ::nekoton_abi::ton_abi::TokenValue {
                             ::nekoton_abi::BuildTokenValue::token_value(self)
                         }
                     }
@@ -81,35 +100,80 @@ fn serialize_enum(
     enum_type: EnumType,
 ) -> proc_macro2::TokenStream {
     let name = &container.ident;
+    let tag_bits = container.attrs.tag_bits;
+    let has_data = variants.iter().any(|variant| !variant.fields.is_empty());
 
-    let build_variants = variants
-        .iter()
-        .filter_map(|variant| {
+    let build_variants = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let number = variant.discriminant as u128;
+
+        let tag = match enum_type {
+            EnumType::Int => quote! {
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint::new(#number, #tag_bits))
+            },
+            EnumType::Bool => {
+                let value = number != 0;
+                quote! { ::nekoton_abi::ton_abi::TokenValue::Bool(#value) }
+            }
+        };
+
+        if !has_data {
+            return quote! {
+                #name::#ident => #tag
+            };
+        }
+
+        // Enums with data are stored as a tuple of the tag followed by the variant fields
+        let field_count = variant.fields.len() + 1;
+        let bindings = variant
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| match &f.member {
+                syn::Member::Named(field) => field.clone(),
+                syn::Member::Unnamed(_) => quote::format_ident!("__field{}", i),
+            })
+            .collect::<Vec<_>>();
+
+        let build_fields =
             variant
-                .original
-                .discriminant
-                .as_ref()
-                .map(|(_, discriminant)| (variant.ident.clone(), discriminant))
-        })
-        .map(|(ident, discriminant)| {
-            let token = quote::ToTokens::to_token_stream(discriminant).to_string();
-            let number = token.parse::<u8>().unwrap();
+                .fields
+                .iter()
+                .zip(&bindings)
+                .enumerate()
+                .map(|(i, (f, binding))| {
+                    let field_name = match (&f.attrs.name, &f.member) {
+                        (Some(v), _) => v.clone(),
+                        (None, syn::Member::Named(field)) => field.to_string(),
+                        (None, syn::Member::Unnamed(_)) => format!("value{i}"),
+                    };
+                    pack_field(f, quote! { #binding }, &field_name)
+                });
 
-            match enum_type {
-                EnumType::Int => quote! {
-                    #name::#ident => ::nekoton_abi::BuildTokenValue::token_value(#number)
-                },
-                EnumType::Bool => match number {
-                    0 => quote! {
-                        #name::#ident => ::nekoton_abi::BuildTokenValue::token_value(false)
-                    },
-                    _ => quote! {
-                        #name::#ident => ::nekoton_abi::BuildTokenValue::token_value(true)
-                    },
-                },
+        let patterns = variant.fields.iter().zip(&bindings).map(|(f, binding)| {
+            match (&f.member, f.attrs.skip) {
+                (_, false) => quote! { #binding },
+                (syn::Member::Named(_), true) => quote! { #binding: _ },
+                (syn::Member::Unnamed(_), true) => quote! { _ },
             }
         });
 
+        let pattern = match variant.style {
+            StructStyle::Struct => quote! { #name::#ident { #(#patterns,)* } },
+            StructStyle::Tuple | StructStyle::NewType => quote! { #name::#ident(#(#patterns,)*) },
+            StructStyle::Unit => quote! { #name::#ident },
+        };
+
+        quote! {
+            #pattern => {
+                let mut tokens: Vec<::nekoton_abi::ton_abi::Token> = Vec::with_capacity(#field_count);
+                tokens.push(::nekoton_abi::ton_abi::Token::new("tag", #tag));
+                #(#build_fields;)*
+                ::nekoton_abi::ton_abi::TokenValue::Tuple(tokens)
+            }
+        }
+    });
+
     quote! {
         match self {
             #(#build_variants,)*
@@ -125,75 +189,17 @@ fn serialize_struct(
     let field_count = fields.len();
 
     let definition = quote! {
-        let mut tokens: Vec<::ton_abi::Token> = Vec::with_capacity(#field_count);
+        let mut tokens: Vec<::nekoton_abi::ton_abi::Token> = Vec::with_capacity(#field_count);
     };
 
     let build_fields = fields.iter().map(|f| {
-        if f.attrs.skip {
-            return quote! {}; // do nothing
-        }
-
         let name = f.original.ident.as_ref().unwrap();
         let field_name = match &f.attrs.name {
             Some(v) => v.clone(),
             None => name.to_string(),
         };
 
-        let ty = &f.original.ty;
-
-        if let Some(type_name) = f.attrs.type_name.as_ref() {
-            let param_type = type_name.get_param_type();
-            let handler = type_name.get_handler();
-            match f.attrs.is_array {
-                true => {
-                    quote! {
-                        tokens.push(::ton_abi::Token::new(
-                            #field_name,
-                            ::ton_abi::TokenValue::Array(
-                                #param_type,
-                                self.#name.into_iter().map(|value| #handler).collect()
-                            )
-                        ))
-                    }
-                }
-                false => {
-                    quote! {
-                        let value = self.#name;
-                        tokens.push(::ton_abi::Token::new(#field_name, #handler))
-                    }
-                }
-            }
-        } else if let Some(with) = f.attrs.with.as_ref() {
-            quote! {
-                tokens.push(::ton_abi::Token::new(#field_name, #with::pack(self.#name)))
-            }
-        } else if let Some(pack_with) = f.attrs.pack_with.as_ref() {
-            quote! {
-                tokens.push(::ton_abi::Token::new(#field_name, #pack_with(self.#name)))
-            }
-        } else {
-            match f.attrs.is_array {
-                true => {
-                    quote! {
-                        tokens.push(::nekoton_abi::TokenValueExt::named(
-                            ::ton_abi::TokenValue::Array(
-                                <#ty as ::nekoton_abi::KnownParamTypeArray<_>>::item_param_type(),
-                                self.#name.into_iter().map(::nekoton_abi::BuildTokenValue::token_value).collect()
-                            ),
-                            #field_name
-                        ))
-                    }
-                }
-                false => {
-                    quote! {
-                        tokens.push(::nekoton_abi::TokenValueExt::named(
-                            ::nekoton_abi::BuildTokenValue::token_value(self.#name),
-                            #field_name
-                        ))
-                    }
-                }
-            }
-        }
+        pack_field(f, quote! { self.#name }, &field_name)
     });
 
     match struct_type {
@@ -208,7 +214,73 @@ fn serialize_struct(
             quote! {
                 #definition
                 #(#build_fields;)*
-                return ::ton_abi::TokenValue::Tuple(tokens);
+                return ::nekoton_abi::ton_abi::TokenValue::Tuple(tokens);
+            }
+        }
+    }
+}
+
+/// Pushes the packed field `value` into `tokens`
+fn pack_field(
+    f: &Field<'_>,
+    value: proc_macro2::TokenStream,
+    field_name: &str,
+) -> proc_macro2::TokenStream {
+    if f.attrs.skip {
+        return quote! {}; // do nothing
+    }
+
+    if f.attrs.flatten {
+        return quote! {
+            tokens.extend(::nekoton_abi::PackAbiPlain::pack(#value))
+        };
+    }
+
+    let value = field_value(f, value);
+    quote! {
+        tokens.push(::nekoton_abi::ton_abi::Token::new(#field_name, #value))
+    }
+}
+
+/// Converts the field `value` into `TokenValue`
+fn field_value(f: &Field<'_>, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let ty = &f.original.ty;
+
+    if let Some(type_name) = f.attrs.type_name.as_ref() {
+        let param_type = type_name.get_param_type();
+        let handler = type_name.get_handler();
+        match f.attrs.is_array {
+            true => {
+                quote! {
+                    ::nekoton_abi::ton_abi::TokenValue::Array(
+                        #param_type,
+                        #value.into_iter().map(|value| #handler).collect()
+                    )
+                }
+            }
+            false => {
+                quote! {{
+                    let value = #value;
+                    #handler
+                }}
+            }
+        }
+    } else if let Some(with) = f.attrs.with.as_ref() {
+        quote! { #with::pack(#value) }
+    } else if let Some(pack_with) = f.attrs.pack_with.as_ref() {
+        quote! { #pack_with(#value) }
+    } else {
+        match f.attrs.is_array {
+            true => {
+                quote! {
+                    ::nekoton_abi::ton_abi::TokenValue::Array(
+                        <#ty as ::nekoton_abi::KnownParamTypeArray<_>>::item_param_type(),
+                        #value.into_iter().map(::nekoton_abi::BuildTokenValue::token_value).collect()
+                    )
+                }
+            }
+            false => {
+                quote! { ::nekoton_abi::BuildTokenValue::token_value(#value) }
             }
         }
     }
@@ -218,46 +290,46 @@ impl TypeName {
     fn get_handler(&self) -> proc_macro2::TokenStream {
         match self {
             TypeName::Int8 => quote! {
-                ::ton_abi::TokenValue::Int(::ton_abi::Int { number: ::nekoton_abi::num_bigint::BigInt::from(value), size: 8 })
+                ::nekoton_abi::ton_abi::TokenValue::Int(::nekoton_abi::ton_abi::Int { number: ::nekoton_abi::num_bigint::BigInt::from(value), size: 8 })
             },
             TypeName::Uint8 => quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 8 })
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 8 })
             },
             TypeName::Uint16 => quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 16 })
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 16 })
             },
             TypeName::Uint32 => quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 32 })
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 32 })
             },
             TypeName::Uint64 => quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 64 })
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 64 })
             },
             TypeName::Uint128 => quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 128 })
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from(value), size: 128 })
             },
             TypeName::Uint256 => quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from_bytes_be(value.as_slice()), size: 256 })
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: ::nekoton_abi::num_bigint::BigUint::from_bytes_be(value.as_slice()), size: 256 })
             },
             TypeName::Grams => quote! {
-                ::ton_abi::TokenValue::Grams(::ton_block::Grams::from(value))
+                ::nekoton_abi::ton_abi::TokenValue::Grams(::nekoton_abi::ton_block::Grams::from(value))
             },
             TypeName::Address => quote! {
-                ::ton_abi::TokenValue::Address(match value {
-                    ::ton_block::MsgAddressInt::AddrStd(addr) => ::ton_block::MsgAddress::AddrStd(addr),
-                    ::ton_block::MsgAddressInt::AddrVar(addr) => ::ton_block::MsgAddress::AddrVar(addr),
+                ::nekoton_abi::ton_abi::TokenValue::Address(match value {
+                    ::nekoton_abi::ton_block::MsgAddressInt::AddrStd(addr) => ::nekoton_abi::ton_block::MsgAddress::AddrStd(addr),
+                    ::nekoton_abi::ton_block::MsgAddressInt::AddrVar(addr) => ::nekoton_abi::ton_block::MsgAddress::AddrVar(addr),
                 })
             },
             TypeName::Cell => quote! {
-                ::ton_abi::TokenValue::Cell(value)
+                ::nekoton_abi::ton_abi::TokenValue::Cell(value)
             },
             TypeName::Bool => quote! {
-                ::ton_abi::TokenValue::Bool(value)
+                ::nekoton_abi::ton_abi::TokenValue::Bool(value)
             },
             TypeName::String => quote! {
-                ::ton_abi::TokenValue::String(value.into())
+                ::nekoton_abi::ton_abi::TokenValue::String(value.into())
             },
             TypeName::Bytes => quote! {
-                ::ton_abi::TokenValue::Bytes(value)
+                ::nekoton_abi::ton_abi::TokenValue::Bytes(value)
             },
             TypeName::None => unreachable!(),
         }
@@ -13,16 +13,23 @@ define_symbols! {
 
     // container attributes
     ENUM_BOOL => "boolean",
+    BY_NAME => "by_name",
+    TAG_BITS => "tag_bits",
+    VALIDATE => "validate",
 
     // field attributes
     NAME => "name",
+    RENAME => "rename",
     TYPE_NAME => "type",
     WITH => "with",
+    CUSTOM => "custom",
     PACK_WITH => "pack_with",
     UNPACK_WITH => "unpack_with",
     PARAM_TYPE_WITH => "param_type_with",
     ARRAY => "array",
     SKIP => "skip",
+    FLATTEN => "flatten",
+    DEFAULT => "default",
 }
 
 #[derive(Copy, Clone)]
@@ -1,7 +1,7 @@
 use quote::quote;
 
 use crate::ast::*;
-use crate::attr::TypeName;
+use crate::attr::{DefaultValue, TypeName};
 use crate::parsing_context::*;
 use crate::utils::*;
 
@@ -18,6 +18,28 @@ pub fn impl_derive_unpack_abi(
     if plain && matches!(&container.data, Data::Enum(_)) {
         cx.error_spanned_by(&input.ident, "Plain unpacker is not supported for enums");
     }
+    if plain && matches!(&container.data, Data::Struct(StructStyle::NewType, _)) {
+        cx.error_spanned_by(&input.ident, "Plain unpacker is not supported for newtypes");
+    }
+
+    match &container.data {
+        Data::Struct(_, fields) => {
+            if container.attrs.by_name && fields.iter().any(|f| f.attrs.flatten) {
+                cx.error_spanned_by(
+                    &input.ident,
+                    "#[abi(flatten)] is not supported for structs unpacked by name",
+                );
+            }
+        }
+        Data::Enum(variants) => {
+            if variants
+                .iter()
+                .any(|variant| variant.fields.iter().any(|f| f.attrs.flatten))
+            {
+                cx.error_spanned_by(&input.ident, "#[abi(flatten)] is not supported for enums");
+            }
+        }
+    }
 
     cx.check()?;
 
@@ -30,8 +52,20 @@ pub fn impl_derive_unpack_abi(
                 EnumType::Int
             };
             let body = serialize_enum(&container, variants, enum_type);
+            let body = with_validation(&container, body);
+            quote! {
+                impl ::nekoton_abi::UnpackAbi<#ident> for ::nekoton_abi::ton_abi::TokenValue {
+                    fn unpack(self) -> ::nekoton_abi::UnpackerResult<#ident> {
+                        #body
+                    }
+                }
+            }
+        }
+        Data::Struct(StructStyle::NewType, fields) => {
+            let body = serialize_newtype(&container, &fields[0]);
+            let body = with_validation(&container, body);
             quote! {
-                impl ::nekoton_abi::UnpackAbi<#ident> for ::ton_abi::TokenValue {
+                impl ::nekoton_abi::UnpackAbi<#ident> for ::nekoton_abi::ton_abi::TokenValue {
                     fn unpack(self) -> ::nekoton_abi::UnpackerResult<#ident> {
                         #body
                     }
@@ -41,8 +75,9 @@ pub fn impl_derive_unpack_abi(
         Data::Struct(_, fields) => {
             if plain {
                 let body = serialize_struct(&container, fields, StructType::Plain);
+                let body = with_validation(&container, body);
                 quote! {
-                    impl ::nekoton_abi::UnpackAbiPlain<#ident> for Vec<::ton_abi::Token> {
+                    impl ::nekoton_abi::UnpackAbiPlain<#ident> for Vec<::nekoton_abi::ton_abi::Token> {
                         fn unpack(self) -> ::nekoton_abi::UnpackerResult<#ident> {
                             #body
                         }
@@ -50,8 +85,9 @@ pub fn impl_derive_unpack_abi(
                 }
             } else {
                 let body = serialize_struct(&container, fields, StructType::Tuple);
+                let body = with_validation(&container, body);
                 quote! {
-                    impl ::nekoton_abi::UnpackAbi<#ident> for ::ton_abi::TokenValue {
+                    impl ::nekoton_abi::UnpackAbi<#ident> for ::nekoton_abi::ton_abi::TokenValue {
                         fn unpack(self) -> ::nekoton_abi::UnpackerResult<#ident> {
                             #body
                         }
@@ -70,48 +106,43 @@ fn serialize_enum(
 ) -> proc_macro2::TokenStream {
     let name = &container.ident;
 
-    let build_variants = variants
-        .iter()
-        .filter_map(|variant| {
-            variant
-                .original
-                .discriminant
-                .as_ref()
-                .map(|(_, discriminant)| (variant.ident.clone(), discriminant))
-        })
-        .map(|(ident, discriminant)| {
-            let token = quote::ToTokens::to_token_stream(discriminant).to_string();
-            let number = token.parse::<u8>().unwrap();
-
-            match enum_type {
-                EnumType::Int => {
+    if variants.iter().any(|variant| !variant.fields.is_empty()) {
+        return serialize_data_enum(container, variants);
+    }
+
+    let build_variants = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let number = variant.discriminant;
+
+        match enum_type {
+            EnumType::Int => {
+                quote! {
+                    Some(#number) => Ok(#name::#ident)
+                }
+            }
+            EnumType::Bool => {
+                if number == 0 {
                     quote! {
-                        Some(#number) => Ok(#name::#ident)
+                        ::nekoton_abi::ton_abi::TokenValue::Bool(false) => Ok(#name::#ident)
                     }
-                }
-                EnumType::Bool => {
-                    if number == 0 {
-                        quote! {
-                            ::ton_abi::TokenValue::Bool(false) => Ok(#name::#ident)
-                        }
-                    } else {
-                        quote! {
-                            ::ton_abi::TokenValue::Bool(true) => Ok(#name::#ident)
-                        }
+                } else {
+                    quote! {
+                        ::nekoton_abi::ton_abi::TokenValue::Bool(true) => Ok(#name::#ident)
                     }
                 }
             }
-        });
+        }
+    });
 
     match enum_type {
         EnumType::Int => {
             quote! {
                 match self {
-                    ::ton_abi::TokenValue::Uint(int) => match ::nekoton_abi::num_traits::ToPrimitive::to_u8(&int.number) {
+                    ::nekoton_abi::ton_abi::TokenValue::Uint(int) => match ::nekoton_abi::num_traits::ToPrimitive::to_u64(&int.number) {
                         #(#build_variants,)*
                         _ => Err(::nekoton_abi::UnpackerError::InvalidAbi),
                     },
-                    _ => Err(::nekoton_abi::UnpackerError::InvalidAbi),
+                    value => Err(::nekoton_abi::UnpackerError::unexpected::<#name>(&value)),
                 }
             }
         }
@@ -119,48 +150,198 @@ fn serialize_enum(
             quote! {
                 match self {
                     #(#build_variants,)*
-                    _ => Err(::nekoton_abi::UnpackerError::InvalidAbi),
+                    value => Err(::nekoton_abi::UnpackerError::unexpected::<#name>(&value)),
                 }
             }
         }
     }
 }
 
+/// Enums with data are stored as a tuple of the tag followed by the variant fields
+fn serialize_data_enum(
+    container: &Container<'_>,
+    variants: &[Variant<'_>],
+) -> proc_macro2::TokenStream {
+    let name = &container.ident;
+
+    let build_variants = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let number = variant.discriminant;
+
+        let build_fields = variant.fields.iter().map(|f| {
+            let try_unpack = try_unpack(
+                f.ty,
+                &f.attrs.type_name,
+                &f.attrs.with,
+                &f.attrs.unpack_with,
+                f.attrs.is_array,
+            );
+            let with_path = match &f.member {
+                syn::Member::Named(field) => {
+                    let field_name = field.to_string();
+                    quote! { e.with_field(#field_name) }
+                }
+                syn::Member::Unnamed(index) => {
+                    let index = index.index as usize;
+                    quote! { e.with_index(#index) }
+                }
+            };
+            let value = if f.attrs.skip {
+                default_value(&f.attrs)
+            } else if f.attrs.default.is_some() {
+                let default = default_value(&f.attrs);
+                quote! {
+                    match tokens.next() {
+                        Some(token) => {
+                            let token = Some(token);
+                            let value: ::nekoton_abi::UnpackerResult<_> = #try_unpack;
+                            value.map_err(|e| #with_path)?
+                        }
+                        None => #default,
+                    }
+                }
+            } else {
+                quote! {{
+                    let token = tokens.next();
+                    let value: ::nekoton_abi::UnpackerResult<_> = #try_unpack;
+                    value.map_err(|e| #with_path)?
+                }}
+            };
+
+            match &f.member {
+                syn::Member::Named(field) => quote! { #field: #value },
+                syn::Member::Unnamed(_) => value,
+            }
+        });
+
+        match variant.style {
+            StructStyle::Struct => quote! {
+                #number => #name::#ident { #(#build_fields,)* }
+            },
+            StructStyle::Tuple | StructStyle::NewType => quote! {
+                #number => #name::#ident(#(#build_fields,)*)
+            },
+            StructStyle::Unit => quote! {
+                #number => #name::#ident
+            },
+        }
+    });
+
+    quote! {
+        match self {
+            ::nekoton_abi::ton_abi::TokenValue::Tuple(tokens) => {
+                let mut tokens = tokens.into_iter();
+                let tag: u64 = ::nekoton_abi::UnpackAbi::unpack(tokens.next())?;
+                let value = match tag {
+                    #(#build_variants,)*
+                    _ => return Err(::nekoton_abi::UnpackerError::InvalidAbi),
+                };
+                match tokens.count() {
+                    0 => Ok(value),
+                    count => Err(::nekoton_abi::UnpackerError::TrailingTokens { count }),
+                }
+            }
+            value => Err(::nekoton_abi::UnpackerError::unexpected::<#name>(&value)),
+        }
+    }
+}
+
 fn serialize_struct(
     container: &Container<'_>,
     fields: &[Field<'_>],
     struct_type: StructType,
 ) -> proc_macro2::TokenStream {
     let name = &container.ident;
+    let by_name = container.attrs.by_name;
 
     let build_fields = fields.iter().map(|f| {
         let name = f.original.ident.as_ref().unwrap();
 
         if f.attrs.skip {
+            let default = default_value(&f.attrs);
             quote! {
-               #name: std::default::Default::default()
+               #name: #default
+            }
+        } else if f.attrs.flatten {
+            // NOTE: flattened struct consumes as many tokens as it has params
+            let ty = f.ty;
+            let field_name = name.to_string();
+            quote! {
+                #name: {
+                    let param_count = <#ty as ::nekoton_abi::KnownParamTypePlain>::param_type().len();
+                    let tokens: Vec<::nekoton_abi::ton_abi::Token> = tokens.by_ref().take(param_count).collect();
+                    if tokens.len() != param_count {
+                        return Err(::nekoton_abi::UnpackerError::InvalidAbi.with_field(#field_name));
+                    }
+                    let value: ::nekoton_abi::UnpackerResult<#ty> = ::nekoton_abi::UnpackAbiPlain::unpack(tokens);
+                    value.map_err(|e| e.with_field(#field_name))?
+                }
             }
         } else {
             let try_unpack = try_unpack(
+                f.ty,
                 &f.attrs.type_name,
                 &f.attrs.with,
                 &f.attrs.unpack_with,
                 f.attrs.is_array,
             );
 
-            quote! {
-                #name: {
-                    let token = tokens.next();
-                    #try_unpack
+            let next_token = if by_name {
+                let field_name = match &f.attrs.name {
+                    Some(v) => v.clone(),
+                    None => name.to_string(),
+                };
+                match f.attrs.default {
+                    Some(_) => quote! {
+                        match tokens.take(#field_name) {
+                            Ok(token) => Some(token),
+                            Err(::nekoton_abi::UnpackerError::MissingField { .. }) => None,
+                            Err(e) => return Err(e),
+                        }
+                    },
+                    None => quote! { Some(tokens.take(#field_name)?) },
+                }
+            } else {
+                quote! { tokens.next() }
+            };
+
+            let field_name = name.to_string();
+            match f.attrs.default {
+                Some(_) => {
+                    let default = default_value(&f.attrs);
+                    quote! {
+                        #name: match #next_token {
+                            Some(token) => {
+                                let token = Some(token);
+                                let value: ::nekoton_abi::UnpackerResult<_> = #try_unpack;
+                                value.map_err(|e| e.with_field(#field_name))?
+                            }
+                            None => #default,
+                        }
+                    }
                 }
+                None => quote! {
+                    #name: {
+                        let token = #next_token;
+                        let value: ::nekoton_abi::UnpackerResult<_> = #try_unpack;
+                        value.map_err(|e| e.with_field(#field_name))?
+                    }
+                },
             }
         }
     });
 
+    let into_tokens = if by_name {
+        quote! { ::nekoton_abi::NamedTokens::new(tokens) }
+    } else {
+        quote! { tokens.into_iter() }
+    };
+
     match struct_type {
         StructType::Plain => {
             quote! {
-                let mut tokens = self.into_iter();
+                let tokens = self;
+                let mut tokens = #into_tokens;
 
                 std::result::Result::Ok(#name {
                     #(#build_fields,)*
@@ -170,8 +351,8 @@ fn serialize_struct(
         StructType::Tuple => {
             quote! {
                 let mut tokens = match self {
-                    ::ton_abi::TokenValue::Tuple(tokens) => tokens.into_iter(),
-                    _ => return Err(::nekoton_abi::UnpackerError::InvalidAbi),
+                    ::nekoton_abi::ton_abi::TokenValue::Tuple(tokens) => #into_tokens,
+                    value => return Err(::nekoton_abi::UnpackerError::unexpected::<#name>(&value)),
                 };
 
                 std::result::Result::Ok(#name {
@@ -182,7 +363,55 @@ fn serialize_struct(
     }
 }
 
+/// Newtypes are unpacked as their inner value
+fn serialize_newtype(container: &Container<'_>, field: &Field<'_>) -> proc_macro2::TokenStream {
+    let name = &container.ident;
+    let try_unpack = try_unpack(
+        field.ty,
+        &field.attrs.type_name,
+        &field.attrs.with,
+        &field.attrs.unpack_with,
+        field.attrs.is_array,
+    );
+
+    quote! {
+        let token = Some(::nekoton_abi::ton_abi::Token::new("value", self));
+        let value: ::nekoton_abi::UnpackerResult<_> = #try_unpack;
+        std::result::Result::Ok(#name(value?))
+    }
+}
+
+/// Passes the unpacked value to the `#[abi(validate = "path")]` hook
+fn with_validation(
+    container: &Container<'_>,
+    body: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let validate = match &container.attrs.validate {
+        Some(validate) => validate,
+        None => return body,
+    };
+    let name = &container.ident;
+
+    quote! {
+        #[allow(clippy::redundant_closure_call)]
+        let value: ::nekoton_abi::UnpackerResult<#name> = (move || { #body })();
+        let value = value?;
+        #validate(&value)?;
+        std::result::Result::Ok(value)
+    }
+}
+
+/// Value for skipped fields and fields with `#[abi(default)]`
+fn default_value(attrs: &crate::attr::Field) -> proc_macro2::TokenStream {
+    match &attrs.default {
+        Some(DefaultValue::Path(path)) => quote! { #path() },
+        Some(DefaultValue::Trait) | None => quote! { std::default::Default::default() },
+    }
+}
+
+/// Produces an expression of type `UnpackerResult<#ty>` from the `token: Option<Token>` binding
 fn try_unpack(
+    ty: &syn::Type,
     type_name: &Option<TypeName>,
     with: &Option<syn::Expr>,
     unpack_with: &Option<syn::Expr>,
@@ -193,24 +422,25 @@ fn try_unpack(
         match is_array {
             true => {
                 quote! {
-                    let array = match token {
+                    match token {
                         Some(token) => match token.value {
-                            ::ton_abi::TokenValue::Array(_, tokens) | ::ton_abi::TokenValue::FixedArray(_, tokens) => {
-                                let mut vec = Vec::with_capacity(tokens.len());
-                                for token in tokens {
-                                    let item = match token {
-                                        #handler
-                                        _ => return Err(::nekoton_abi::UnpackerError::InvalidAbi),
-                                    };
-                                    vec.push(item);
-                                }
-                                Ok(vec)
+                            ::nekoton_abi::ton_abi::TokenValue::Array(_, tokens) | ::nekoton_abi::ton_abi::TokenValue::FixedArray(_, tokens) => {
+                                tokens
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(i, token)| {
+                                        let item: ::nekoton_abi::UnpackerResult<_> = match token {
+                                            #handler
+                                            _ => Err(::nekoton_abi::UnpackerError::InvalidAbi),
+                                        };
+                                        item.map_err(|e| e.with_index(i))
+                                    })
+                                    .collect()
                             },
-                            _ => Err(::nekoton_abi::UnpackerError::InvalidAbi),
-                        }
+                            value => Err(::nekoton_abi::UnpackerError::unexpected::<#ty>(&value)),
+                        },
                         None => Err(::nekoton_abi::UnpackerError::InvalidAbi),
-                    };
-                    array?
+                    }
                 }
             }
             false => {
@@ -218,9 +448,9 @@ fn try_unpack(
                     match token {
                         Some(token) => match token.value {
                             #handler
-                            _ => return Err(::nekoton_abi::UnpackerError::InvalidAbi),
+                            value => Err(::nekoton_abi::UnpackerError::unexpected::<#ty>(&value)),
                         },
-                        None => return Err(::nekoton_abi::UnpackerError::InvalidAbi),
+                        None => Err(::nekoton_abi::UnpackerError::InvalidAbi),
                     }
                 }
             }
@@ -228,37 +458,41 @@ fn try_unpack(
     } else if let Some(with) = with.as_ref() {
         quote! {
             match token {
-                Some(token) => #with::unpack(&token.value)?,
-                None => return Err(::nekoton_abi::UnpackerError::InvalidAbi),
+                Some(token) => #with::unpack(&token.value),
+                None => Err(::nekoton_abi::UnpackerError::InvalidAbi),
             }
         }
     } else if let Some(unpack_with) = unpack_with.as_ref() {
         quote! {
             match token {
-                Some(token) => #unpack_with(&token.value)?,
-                None => return Err(::nekoton_abi::UnpackerError::InvalidAbi),
+                Some(token) => #unpack_with(&token.value),
+                None => Err(::nekoton_abi::UnpackerError::InvalidAbi),
             }
         }
     } else {
         match is_array {
             true => {
                 quote! {
-                    let array = match token {
+                    match token {
                         Some(token) => match token.value {
-                            ::ton_abi::TokenValue::Array(_, tokens) | ::ton_abi::TokenValue::FixedArray(_, tokens) => tokens,
-                            _ => return Err(::nekoton_abi::UnpackerError::InvalidAbi),
-                        }
-                        .into_iter()
-                        .map(::nekoton_abi::UnpackAbi::unpack)
-                        .collect(),
+                            ::nekoton_abi::ton_abi::TokenValue::Array(_, tokens) | ::nekoton_abi::ton_abi::TokenValue::FixedArray(_, tokens) => {
+                                tokens
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(i, token)| {
+                                        ::nekoton_abi::UnpackAbi::unpack(token).map_err(|e| e.with_index(i))
+                                    })
+                                    .collect()
+                            },
+                            value => Err(::nekoton_abi::UnpackerError::unexpected::<#ty>(&value)),
+                        },
                         None => Err(::nekoton_abi::UnpackerError::InvalidAbi),
-                    };
-                    array?
+                    }
                 }
             }
             false => {
                 quote! {
-                    ::nekoton_abi::UnpackAbi::unpack(token)?
+                    ::nekoton_abi::UnpackAbi::unpack(token)
                 }
             }
         }
@@ -269,95 +503,95 @@ fn get_handler(type_name: &TypeName) -> proc_macro2::TokenStream {
     match type_name {
         TypeName::Int8 => {
             quote! {
-                ::ton_abi::TokenValue::Int(::ton_abi::Int { number: value, size: 8 }) => {
+                ::nekoton_abi::ton_abi::TokenValue::Int(::nekoton_abi::ton_abi::Int { number: value, size: 8 }) => {
                     ::nekoton_abi::num_traits::ToPrimitive::to_i8(&value)
-                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)?
+                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)
                 },
             }
         }
         TypeName::Uint8 => {
             quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: value, size: 8 }) => {
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: value, size: 8 }) => {
                     ::nekoton_abi::num_traits::ToPrimitive::to_u8(&value)
-                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)?
+                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)
                 },
             }
         }
         TypeName::Uint16 => {
             quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: value, size: 16 }) => {
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: value, size: 16 }) => {
                     ::nekoton_abi::num_traits::ToPrimitive::to_u16(&value)
-                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)?
+                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)
                 },
             }
         }
         TypeName::Uint32 => {
             quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: value, size: 32 }) => {
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: value, size: 32 }) => {
                     ::nekoton_abi::num_traits::ToPrimitive::to_u32(&value)
-                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)?
+                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)
                 },
             }
         }
         TypeName::Uint64 => {
             quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: value, size: 64 }) => {
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: value, size: 64 }) => {
                     ::nekoton_abi::num_traits::ToPrimitive::to_u64(&value)
-                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)?
+                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)
                 },
             }
         }
         TypeName::Uint128 => {
             quote! {
-                ::ton_abi::TokenValue::Uint(::ton_abi::Uint { number: value, size: 128 }) => {
+                ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { number: value, size: 128 }) => {
                     ::nekoton_abi::num_traits::ToPrimitive::to_u128(&value)
-                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)?
+                    .ok_or(::nekoton_abi::UnpackerError::InvalidAbi)
                 },
             }
         }
         TypeName::Uint256 => {
             quote! {
-                value @ ::ton_abi::TokenValue::Uint(::ton_abi::Uint { size: 256, .. }) => {
-                    ::nekoton_abi::UnpackAbi::<::ton_types::UInt256>::unpack(value)?
+                value @ ::nekoton_abi::ton_abi::TokenValue::Uint(::nekoton_abi::ton_abi::Uint { size: 256, .. }) => {
+                    ::nekoton_abi::UnpackAbi::<::nekoton_abi::ton_types::UInt256>::unpack(value)
                 }
             }
         }
         TypeName::Grams => {
             quote! {
-                ::ton_abi::TokenValue::Token(grams) => {
+                ::nekoton_abi::ton_abi::TokenValue::Token(grams) => {
                     ::std::convert::TryFrom::try_from(grams.as_u128())
-                    .map_err(|_| ::nekoton_abi::UnpackerError::InvalidAbi)?
+                    .map_err(|_| ::nekoton_abi::UnpackerError::InvalidAbi)
                 }
             }
         }
         TypeName::Address => {
             quote! {
-                ::ton_abi::TokenValue::Address(::ton_block::MsgAddress::AddrStd(addr)) => {
-                    ::ton_block::MsgAddressInt::AddrStd(addr)
+                ::nekoton_abi::ton_abi::TokenValue::Address(::nekoton_abi::ton_block::MsgAddress::AddrStd(addr)) => {
+                    Ok(::nekoton_abi::ton_block::MsgAddressInt::AddrStd(addr))
                 },
-                ::ton_abi::TokenValue::Address(::ton_block::MsgAddress::AddrVar(addr)) => {
-                    ::ton_block::MsgAddressInt::AddrVar(addr)
+                ::nekoton_abi::ton_abi::TokenValue::Address(::nekoton_abi::ton_block::MsgAddress::AddrVar(addr)) => {
+                    Ok(::nekoton_abi::ton_block::MsgAddressInt::AddrVar(addr))
                 },
             }
         }
         TypeName::Cell => {
             quote! {
-                ::ton_abi::TokenValue::Cell(cell) => cell,
+                ::nekoton_abi::ton_abi::TokenValue::Cell(cell) => Ok(cell),
             }
         }
         TypeName::Bool => {
             quote! {
-                ::ton_abi::TokenValue::Bool(value) => value,
+                ::nekoton_abi::ton_abi::TokenValue::Bool(value) => Ok(value),
             }
         }
         TypeName::String => {
             quote! {
-                ::ton_abi::TokenValue::String(data) => data,
+                ::nekoton_abi::ton_abi::TokenValue::String(data) => Ok(data),
             }
         }
         TypeName::Bytes => {
             quote! {
-                ton_abi::TokenValue::Bytes(bytes) => bytes,
+                ::nekoton_abi::ton_abi::TokenValue::Bytes(bytes) => Ok(bytes),
             }
         }
         TypeName::None => unreachable!(),
@@ -0,0 +1,102 @@
+use nekoton_abi::ton_abi::{Token, TokenValue, Uint};
+use nekoton_abi::{PackAbiPlain, UnpackAbiPlain};
+
+nekoton_abi::abigen!(safe_multisig, "../nekoton-abi/test/msig_abi.json");
+
+use safe_multisig::events::{Event, TransferAcceptedEvent};
+use safe_multisig::functions;
+
+#[test]
+fn safe_multisig_functions() {
+    let function = functions::submit_transaction().unwrap();
+    assert_eq!(function.name, "submitTransaction");
+
+    let input = functions::SubmitTransactionInput {
+        dest: Default::default(),
+        value: 1_000_000_000,
+        bounce: false,
+        all_balance: false,
+        payload: Default::default(),
+    };
+    let tokens = input.pack();
+    assert_eq!(tokens.len(), function.inputs.len());
+    function.encode_internal_input(&tokens).unwrap();
+
+    let output: functions::SubmitTransactionOutput =
+        vec![Token::new("transId", TokenValue::Uint(Uint::new(123, 64)))]
+            .unpack()
+            .unwrap();
+    assert_eq!(output.trans_id, 123);
+
+    assert!(functions::get_custodians().is_ok());
+}
+
+#[test]
+fn safe_multisig_events() {
+    let event = safe_multisig::events::transfer_accepted().unwrap();
+    assert_eq!(event.name, "TransferAccepted");
+
+    let tokens = vec![Token::new("payload", TokenValue::Bytes(vec![1, 2, 3]))];
+    match Event::unpack(&event.name, tokens).unwrap() {
+        Some(Event::TransferAccepted(TransferAcceptedEvent { payload })) => {
+            assert_eq!(payload, [1, 2, 3])
+        }
+        _ => panic!("unexpected event"),
+    }
+
+    assert!(Event::unpack("Unknown", Vec::new()).unwrap().is_none());
+}
+
+nekoton_abi::abigen!(names, "tests/abi/names.abi.json");
+
+#[test]
+fn sanitized_names() {
+    use names::events::{self, ValueEvent};
+    use names::{functions, ValueSelfInputTuple};
+
+    assert_eq!(functions::self_().unwrap().name, "self");
+    assert_eq!(functions::self_1().unwrap().name, "_self");
+    assert_eq!(functions::value_0().unwrap().name, "0");
+
+    let input = functions::ValueSelfInput {
+        self_: 1,
+        super_: 2,
+        crate_: 3,
+        value_0x: 4,
+        value: 5,
+        value_1: 6,
+        r#type: 7,
+        type_1: 8,
+        tuple: ValueSelfInputTuple {
+            self_: true,
+            self_1: false,
+        },
+    };
+    let tokens = input.pack();
+    let token_names = tokens
+        .iter()
+        .map(|token| token.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        token_names,
+        ["self", "super", "crate", "0x", "_", "__", "_type", "type", "tuple"]
+    );
+
+    let input: functions::ValueSelfInput = tokens.unpack().unwrap();
+    assert_eq!((input.value, input.value_1), (5, 6));
+    assert_eq!((input.r#type, input.type_1), (7, 8));
+    assert!(input.tuple.self_ && !input.tuple.self_1);
+
+    assert_eq!(events::value().unwrap().name, "_");
+    assert_eq!(events::value_1().unwrap().name, "__");
+
+    let tokens = vec![Token::new("self", TokenValue::Uint(Uint::new(1, 8)))];
+    match events::Event::unpack("_", tokens).unwrap() {
+        Some(events::Event::Value(ValueEvent { self_ })) => assert_eq!(self_, 1),
+        _ => panic!("unexpected event"),
+    }
+    assert!(matches!(
+        events::Event::unpack("__", Vec::new()).unwrap(),
+        Some(events::Event::Value1)
+    ));
+}
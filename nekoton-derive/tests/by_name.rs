@@ -0,0 +1,76 @@
+use ton_abi::{Token, TokenValue, Uint};
+
+use nekoton_abi::{BuildTokenValue, UnpackAbi, UnpackAbiPlain, UnpackerError};
+
+#[derive(UnpackAbiPlain, Debug)]
+#[abi(by_name)]
+struct Output {
+    #[abi(uint32)]
+    first: u32,
+    #[abi(name = "secondValue")]
+    second: bool,
+    #[abi]
+    nested: Nested,
+}
+
+#[derive(UnpackAbi, Debug)]
+#[abi(by_name)]
+struct Nested {
+    #[abi]
+    a: u8,
+    #[abi(string)]
+    b: String,
+}
+
+fn nested() -> Token {
+    Token::new(
+        "nested",
+        TokenValue::Tuple(vec![
+            Token::new("b", TokenValue::String("asd".to_string())),
+            Token::new("a", TokenValue::Uint(Uint::new(7, 8))),
+        ]),
+    )
+}
+
+fn test_reordered() -> Output {
+    let tokens = vec![
+        Token::new("secondValue", true.token_value()),
+        nested(),
+        Token::new("first", TokenValue::Uint(Uint::new(123, 32))),
+    ];
+    tokens.unpack().unwrap()
+}
+
+fn test_missing() -> UnpackerResultOutput {
+    let tokens = vec![nested(), Token::new("secondValue", true.token_value())];
+    UnpackAbiPlain::<Output>::unpack(tokens).map(|_| ())
+}
+
+fn test_duplicate() -> UnpackerResultOutput {
+    let tokens = vec![
+        Token::new("first", TokenValue::Uint(Uint::new(1, 32))),
+        Token::new("first", TokenValue::Uint(Uint::new(2, 32))),
+        Token::new("secondValue", true.token_value()),
+        nested(),
+    ];
+    UnpackAbiPlain::<Output>::unpack(tokens).map(|_| ())
+}
+
+type UnpackerResultOutput = nekoton_abi::UnpackerResult<()>;
+
+fn main() {
+    let data = test_reordered();
+    assert_eq!(data.first, 123);
+    assert!(data.second);
+    assert_eq!(data.nested.a, 7);
+    assert_eq!(data.nested.b, "asd");
+
+    assert!(matches!(
+        test_missing(),
+        Err(UnpackerError::MissingField { name: "first" })
+    ));
+    assert!(matches!(
+        test_duplicate(),
+        Err(UnpackerError::DuplicateField { name: "first" })
+    ));
+}
@@ -0,0 +1,65 @@
+use num_bigint::BigUint;
+use ton_abi::{Token, TokenValue, Uint};
+
+use nekoton_abi::{PackAbiPlain, UnpackAbiPlain};
+
+#[derive(PackAbiPlain, UnpackAbiPlain, Debug)]
+#[abi(by_name)]
+struct Data {
+    #[abi(custom = "nekoton_abi::uint128_number")]
+    amount: BigUint,
+    #[abi(rename = "isActive")]
+    active: bool,
+    #[abi(uint32, default)]
+    nonce: u32,
+    #[abi(skip, default = "default_version")]
+    version: u8,
+}
+
+#[derive(PackAbiPlain, UnpackAbiPlain, Debug)]
+struct Positional {
+    #[abi(uint32)]
+    first: u32,
+    #[abi(bool, default = "default_flag")]
+    flag: bool,
+}
+
+fn default_version() -> u8 {
+    2
+}
+
+fn default_flag() -> bool {
+    true
+}
+
+fn main() {
+    let data = Data {
+        amount: BigUint::from(123u32),
+        active: true,
+        nonce: 10,
+        version: 1,
+    };
+
+    let tokens: Vec<Token> = data.pack();
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens[1].name, "isActive");
+
+    let parsed: Data = tokens.unpack().unwrap();
+    assert_eq!(parsed.amount, BigUint::from(123u32));
+    assert!(parsed.active);
+    assert_eq!(parsed.nonce, 10);
+    assert_eq!(parsed.version, 2);
+
+    // Missing fields with `default` attribute are filled with default values
+    let tokens = vec![
+        Token::new("amount", TokenValue::Uint(Uint::new(1, 128))),
+        Token::new("isActive", TokenValue::Bool(false)),
+    ];
+    let parsed: Data = tokens.unpack().unwrap();
+    assert_eq!(parsed.nonce, 0);
+
+    let tokens = vec![Token::new("first", TokenValue::Uint(Uint::new(5, 32)))];
+    let parsed: Positional = tokens.unpack().unwrap();
+    assert_eq!(parsed.first, 5);
+    assert!(parsed.flag);
+}
@@ -15,6 +15,26 @@ enum Voting {
     Confirm = 1,
 }
 
+#[derive(PackAbi, UnpackAbi, KnownParamType, PartialEq, Debug)]
+#[abi(tag_bits = 16)]
+enum Status {
+    Pending,
+    Active,
+    Closed = 300,
+}
+
+#[derive(PackAbi, UnpackAbi, PartialEq, Debug)]
+enum Action {
+    Stop,
+    Transfer {
+        #[abi]
+        amount: u128,
+        #[abi(name = "notify")]
+        notify_receiver: bool,
+    },
+    SetCode(#[abi] ton_types::Cell),
+}
+
 #[derive(PackAbi, UnpackAbi)]
 struct Test {
     #[abi(array, name = "eventTypes")]
@@ -53,7 +73,72 @@ fn test() -> Test {
     parsed
 }
 
+fn test_status() {
+    use nekoton_abi::BuildTokenValue;
+
+    assert_eq!(Status::param_type(), ton_abi::ParamType::Uint(16));
+
+    let token = Status::Closed.token_value();
+    assert_eq!(token, TokenValue::Uint(Uint::new(300, 16)));
+    let parsed: Status = token.unpack().unwrap();
+    assert_eq!(parsed, Status::Closed);
+
+    let parsed: Status = TokenValue::Uint(Uint::new(1, 16)).unpack().unwrap();
+    assert_eq!(parsed, Status::Active);
+}
+
+fn test_action() {
+    use nekoton_abi::BuildTokenValue;
+
+    let token = Action::Transfer {
+        amount: 123,
+        notify_receiver: true,
+    }
+    .token_value();
+    match &token {
+        TokenValue::Tuple(tokens) => {
+            assert_eq!(tokens.len(), 3);
+            assert_eq!(tokens[0].value, TokenValue::Uint(Uint::new(1, 8)));
+            assert_eq!(tokens[2].name, "notify");
+        }
+        _ => panic!("tuple expected"),
+    }
+    let parsed: Action = token.unpack().unwrap();
+    assert_eq!(
+        parsed,
+        Action::Transfer {
+            amount: 123,
+            notify_receiver: true
+        }
+    );
+
+    let parsed: Action = Action::Stop.token_value().unpack().unwrap();
+    assert_eq!(parsed, Action::Stop);
+
+    let code = ton_types::Cell::default();
+    let parsed: Action = Action::SetCode(code.clone())
+        .token_value()
+        .unpack()
+        .unwrap();
+    assert_eq!(parsed, Action::SetCode(code));
+
+    // Tuples with extra tokens are rejected
+    let mut tokens = match Action::Stop.token_value() {
+        TokenValue::Tuple(tokens) => tokens,
+        _ => panic!("tuple expected"),
+    };
+    tokens.push(Token::new("extra", TokenValue::Bool(true)));
+    let result: nekoton_abi::UnpackerResult<Action> = TokenValue::Tuple(tokens).unpack();
+    assert!(matches!(
+        result,
+        Err(nekoton_abi::UnpackerError::TrailingTokens { count: 1 })
+    ));
+}
+
 fn main() {
+    test_status();
+    test_action();
+
     let event = test_event_type();
     assert_eq!(event, EventType::Ton);
 
@@ -0,0 +1,56 @@
+use ton_abi::{Param, ParamType, Token, TokenValue, Uint};
+
+use nekoton_abi::{UnpackAbi, UnpackAbiPlain, UnpackerError};
+
+#[derive(UnpackAbiPlain)]
+struct Output {
+    #[abi]
+    details: Details,
+}
+
+#[derive(UnpackAbi)]
+struct Details {
+    #[abi(array)]
+    owners: Vec<Owner>,
+}
+
+#[derive(UnpackAbi)]
+struct Owner {
+    #[abi(uint32)]
+    index: u32,
+    #[abi(bool)]
+    active: bool,
+}
+
+fn owner(index: u32, active: TokenValue) -> TokenValue {
+    TokenValue::Tuple(vec![
+        Token::new("index", TokenValue::Uint(Uint::new(index as u128, 32))),
+        Token::new("active", active),
+    ])
+}
+
+fn test_error_path() -> Result<Output, UnpackerError> {
+    let owner_type = ParamType::Tuple(vec![
+        Param::new("index", ParamType::Uint(32)),
+        Param::new("active", ParamType::Bool),
+    ]);
+    let owners = TokenValue::Array(
+        owner_type,
+        vec![
+            owner(0, TokenValue::Bool(true)),
+            owner(1, TokenValue::Uint(Uint::new(1, 8))),
+        ],
+    );
+    let details = TokenValue::Tuple(vec![Token::new("owners", owners)]);
+
+    vec![Token::new("details", details)].unpack()
+}
+
+fn main() {
+    let error = test_error_path().err().unwrap();
+    assert_eq!(error.path(), Some("details.owners[1].active"));
+    assert_eq!(
+        error.to_string(),
+        "Expected bool, found uint8 at `details.owners[1].active`"
+    );
+}
@@ -0,0 +1,116 @@
+use ton_abi::{Param, ParamType, Token, TokenValue};
+
+use nekoton_abi::{
+    BuildTokenValue, KnownParamType, KnownParamTypePlain, PackAbi, PackAbiPlain, UnpackAbi,
+    UnpackAbiPlain,
+};
+
+#[derive(
+    PackAbi,
+    PackAbiPlain,
+    UnpackAbi,
+    UnpackAbiPlain,
+    KnownParamType,
+    KnownParamTypePlain,
+    Debug,
+    Eq,
+    PartialEq,
+)]
+pub struct Point {
+    #[abi(uint32)]
+    pub x: u32,
+    #[abi(uint32)]
+    pub y: u32,
+}
+
+#[derive(PackAbi, UnpackAbi, KnownParamType, Debug, Eq, PartialEq)]
+pub struct Segment {
+    #[abi]
+    pub from: Point,
+    #[abi]
+    pub to: Point,
+}
+
+#[derive(PackAbiPlain, UnpackAbiPlain, KnownParamTypePlain, Debug, Eq, PartialEq)]
+pub struct Shape {
+    #[abi(uint8)]
+    pub kind: u8,
+    #[abi(flatten)]
+    pub origin: Point,
+    #[abi]
+    pub segment: Segment,
+    #[abi(bool)]
+    pub closed: bool,
+}
+
+fn main() {
+    let shape = Shape {
+        kind: 1,
+        origin: Point { x: 10, y: 20 },
+        segment: Segment {
+            from: Point { x: 1, y: 2 },
+            to: Point { x: 3, y: 4 },
+        },
+        closed: true,
+    };
+
+    let point_type = ParamType::Tuple(vec![
+        Param::new("x", ParamType::Uint(32)),
+        Param::new("y", ParamType::Uint(32)),
+    ]);
+    let params = vec![
+        Param::new("kind", ParamType::Uint(8)),
+        Param::new("x", ParamType::Uint(32)),
+        Param::new("y", ParamType::Uint(32)),
+        Param::new(
+            "segment",
+            ParamType::Tuple(vec![
+                Param::new("from", point_type.clone()),
+                Param::new("to", point_type),
+            ]),
+        ),
+        Param::new("closed", ParamType::Bool),
+    ];
+    assert_eq!(Shape::param_type(), params);
+
+    let tokens = vec![
+        Token::new("kind", 1u8.token_value()),
+        Token::new("x", 10u32.token_value()),
+        Token::new("y", 20u32.token_value()),
+        Token::new(
+            "segment",
+            TokenValue::Tuple(vec![
+                Token::new(
+                    "from",
+                    TokenValue::Tuple(vec![
+                        Token::new("x", 1u32.token_value()),
+                        Token::new("y", 2u32.token_value()),
+                    ]),
+                ),
+                Token::new(
+                    "to",
+                    TokenValue::Tuple(vec![
+                        Token::new("x", 3u32.token_value()),
+                        Token::new("y", 4u32.token_value()),
+                    ]),
+                ),
+            ]),
+        ),
+        Token::new("closed", true.token_value()),
+    ];
+
+    let packed = PackAbiPlain::pack(shape);
+    assert_eq!(packed, tokens);
+
+    let parsed: Shape = tokens.unpack().unwrap();
+    assert_eq!(parsed.origin, Point { x: 10, y: 20 });
+    assert_eq!(parsed.segment.to, Point { x: 3, y: 4 });
+    assert!(parsed.closed);
+
+    let truncated = vec![
+        Token::new("kind", 1u8.token_value()),
+        Token::new("x", 10u32.token_value()),
+    ];
+    let result: nekoton_abi::UnpackerResult<Shape> = truncated.unpack();
+    assert!(result.is_err());
+}
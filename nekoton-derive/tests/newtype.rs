@@ -0,0 +1,58 @@
+use ton_abi::{ParamType, Token, TokenValue, Uint};
+
+use nekoton_abi::*;
+
+#[derive(PackAbi, UnpackAbi, KnownParamType, Debug, PartialEq)]
+struct Nonce(#[abi(uint64)] u64);
+
+#[derive(PackAbi, UnpackAbi, KnownParamType, Debug, PartialEq)]
+#[abi(validate = "check_decimals")]
+struct Decimals(#[abi] u8);
+
+fn check_decimals(decimals: &Decimals) -> UnpackerResult<()> {
+    if decimals.0 <= 18 {
+        Ok(())
+    } else {
+        Err(UnpackerError::InvalidAbi)
+    }
+}
+
+#[derive(PackAbi, UnpackAbi, Debug)]
+struct Data {
+    #[abi]
+    nonce: Nonce,
+    #[abi]
+    decimals: Decimals,
+}
+
+fn main() {
+    assert_eq!(Nonce::param_type(), ParamType::Uint(64));
+    assert_eq!(Decimals::param_type(), ParamType::Uint(8));
+
+    let token = Nonce(123).token_value();
+    assert_eq!(token, TokenValue::Uint(Uint::new(123, 64)));
+    let parsed: Nonce = token.unpack().unwrap();
+    assert_eq!(parsed, Nonce(123));
+
+    let data = Data {
+        nonce: Nonce(1),
+        decimals: Decimals(9),
+    };
+    let tuple = Token::new("tuple", data.token_value());
+    let parsed: Data = tuple.unpack().unwrap();
+    assert_eq!(parsed.nonce, Nonce(1));
+    assert_eq!(parsed.decimals, Decimals(9));
+
+    let invalid: UnpackerResult<Decimals> = TokenValue::Uint(Uint::new(30, 8)).unpack();
+    assert!(invalid.is_err());
+
+    let invalid = Token::new(
+        "tuple",
+        TokenValue::Tuple(vec![
+            Token::new("nonce", TokenValue::Uint(Uint::new(1, 64))),
+            Token::new("decimals", TokenValue::Uint(Uint::new(30, 8))),
+        ]),
+    );
+    let err = UnpackAbi::<Data>::unpack(invalid).unwrap_err();
+    assert_eq!(err.path(), Some("decimals"));
+}
@@ -1,9 +1,14 @@
 #[test]
 fn tests() {
     let t = trybuild::TestCases::new();
+    t.pass("tests/by_name.rs");
+    t.pass("tests/custom_attrs.rs");
     t.pass("tests/enum.rs");
+    t.pass("tests/error_path.rs");
+    t.pass("tests/flatten.rs");
     t.pass("tests/known_param_type.rs");
     t.pass("tests/map.rs");
+    t.pass("tests/newtype.rs");
     t.pass("tests/pack_with.rs");
     t.pass("tests/plain_struct.rs");
     t.pass("tests/struct.rs");
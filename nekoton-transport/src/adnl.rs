@@ -0,0 +1,312 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use anyhow::Result;
+use nekoton::transport::adnl::tl::{TlReader, TlWriter};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use ton_types::UInt256;
+
+/// Liteserver address and its public key
+#[derive(Debug, Clone)]
+pub struct AdnlConfig {
+    pub server_address: SocketAddr,
+    pub server_key: [u8; 32],
+    /// Max duration of one query, including the reconnect. Default: `10s`
+    pub query_timeout: Duration,
+}
+
+impl AdnlConfig {
+    pub fn new(server_address: SocketAddr, server_key: [u8; 32]) -> Self {
+        Self {
+            server_address,
+            server_key,
+            query_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// ADNL over TCP connection to a single liteserver.
+///
+/// Queries are sent one by one over the same connection, which is reestablished
+/// after any error
+pub struct AdnlClient {
+    config: AdnlConfig,
+    server_key: ed25519_dalek::PublicKey,
+    connection: tokio::sync::Mutex<Option<Connection>>,
+}
+
+impl AdnlClient {
+    pub fn new(config: AdnlConfig) -> Result<Arc<Self>> {
+        let server_key = ed25519_dalek::PublicKey::from_bytes(&config.server_key)
+            .map_err(|_| AdnlError::InvalidServerKey)?;
+
+        Ok(Arc::new(Self {
+            config,
+            server_key,
+            connection: Default::default(),
+        }))
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl nekoton::external::AdnlConnection for AdnlClient {
+    async fn query(&self, request: Vec<u8>) -> Result<Vec<u8>> {
+        let mut connection = self.connection.lock().await;
+
+        let result = tokio::time::timeout(self.config.query_timeout, async {
+            if let Some(connection) = &mut *connection {
+                match connection.query(&request).await {
+                    Ok(answer) => return Ok(answer),
+                    // NOTE: idle connections may be closed by the server,
+                    // so the query is retried once with a new connection
+                    Err(e) => log::debug!("ADNL query failed, reconnecting: {e:?}"),
+                }
+            }
+
+            let new_connection =
+                Connection::connect(self.config.server_address, &self.server_key).await?;
+            connection.insert(new_connection).query(&request).await
+        })
+        .await;
+
+        match result {
+            Ok(Ok(answer)) => Ok(answer),
+            Ok(Err(e)) => {
+                *connection = None;
+                Err(e)
+            }
+            Err(_) => {
+                *connection = None;
+                Err(AdnlError::Timeout.into())
+            }
+        }
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    send_cipher: Aes256Ctr,
+    recv_cipher: Aes256Ctr,
+}
+
+impl Connection {
+    async fn connect(address: SocketAddr, server_key: &ed25519_dalek::PublicKey) -> Result<Self> {
+        let mut stream = TcpStream::connect(address).await?;
+
+        let secret = ed25519_dalek::SecretKey::from_bytes(&rand::random::<[u8; 32]>())
+            .expect("shouldn't fail for 32 bytes");
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        let shared = nekoton::crypto::x25519::compute_shared(&secret, server_key);
+
+        // Session keys and nonces
+        let mut params = [0u8; 160];
+        rand::thread_rng().fill(&mut params[..]);
+        let hash: [u8; 32] = Sha256::digest(params).into();
+
+        let mut handshake = Vec::with_capacity(256);
+        handshake.extend_from_slice(&server_key_id(server_key));
+        handshake.extend_from_slice(public.as_bytes());
+        handshake.extend_from_slice(&hash);
+        handshake.extend_from_slice(&params);
+        handshake_cipher(&shared, &hash).apply_keystream(&mut handshake[96..]);
+        stream.write_all(&handshake).await?;
+
+        let mut connection = Self {
+            stream,
+            send_cipher: make_cipher(&params[32..64], &params[80..96]),
+            recv_cipher: make_cipher(&params[0..32], &params[64..80]),
+        };
+
+        // Server confirms the handshake with an empty packet
+        if !connection.recv_packet().await?.is_empty() {
+            return Err(AdnlError::HandshakeFailed.into());
+        }
+        Ok(connection)
+    }
+
+    async fn query(&mut self, request: &[u8]) -> Result<Vec<u8>> {
+        let query_id = UInt256::from(rand::random::<[u8; 32]>());
+
+        let mut query = TlWriter::new(LITE_SERVER_QUERY);
+        query.write_bytes(request);
+
+        let mut message = TlWriter::new(ADNL_MESSAGE_QUERY);
+        message.write_int256(&query_id);
+        message.write_bytes(&query.finish());
+        self.send_packet(&message.finish()).await?;
+
+        loop {
+            let packet = self.recv_packet().await?;
+
+            // Empty packets, pongs and answers to other queries are skipped
+            let mut reader = TlReader::new(&packet);
+            if reader.read_u32() != Some(ADNL_MESSAGE_ANSWER) {
+                continue;
+            }
+            match (reader.read_int256(), reader.read_bytes()) {
+                (Some(id), Some(answer)) if id == query_id => return Ok(answer.to_vec()),
+                (Some(_), Some(_)) => continue,
+                _ => return Err(AdnlError::InvalidPacket.into()),
+            }
+        }
+    }
+
+    async fn send_packet(&mut self, payload: &[u8]) -> Result<()> {
+        let mut nonce = [0u8; 32];
+        rand::thread_rng().fill(&mut nonce);
+
+        let mut packet = Vec::with_capacity(4 + 32 + payload.len() + 32);
+        packet.extend_from_slice(&((32 + payload.len() + 32) as u32).to_le_bytes());
+        packet.extend_from_slice(&nonce);
+        packet.extend_from_slice(payload);
+        packet.extend_from_slice(
+            &Sha256::new()
+                .chain_update(nonce)
+                .chain_update(payload)
+                .finalize(),
+        );
+
+        self.send_cipher.apply_keystream(&mut packet);
+        self.stream.write_all(&packet).await?;
+        Ok(())
+    }
+
+    async fn recv_packet(&mut self) -> Result<Vec<u8>> {
+        let mut len = [0u8; 4];
+        self.stream.read_exact(&mut len).await?;
+        self.recv_cipher.apply_keystream(&mut len);
+
+        let len = u32::from_le_bytes(len) as usize;
+        if !(64..=MAX_PACKET_SIZE).contains(&len) {
+            return Err(AdnlError::InvalidPacket.into());
+        }
+
+        let mut packet = vec![0; len];
+        self.stream.read_exact(&mut packet).await?;
+        self.recv_cipher.apply_keystream(&mut packet);
+
+        let (data, checksum) = packet.split_at(len - 32);
+        if Sha256::digest(data).as_slice() != checksum {
+            return Err(AdnlError::InvalidPacket.into());
+        }
+
+        // Strip the nonce and the checksum
+        packet.truncate(len - 32);
+        packet.drain(..32);
+        Ok(packet)
+    }
+}
+
+/// `sha256(pub.ed25519 key:int256)`
+fn server_key_id(server_key: &ed25519_dalek::PublicKey) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(PUB_ED25519.to_le_bytes())
+        .chain_update(server_key.as_bytes())
+        .finalize()
+        .into()
+}
+
+/// Cipher of the session params in the handshake packet
+fn handshake_cipher(shared: &[u8; 32], hash: &[u8; 32]) -> Aes256Ctr {
+    let mut key = [0u8; 32];
+    key[..16].copy_from_slice(&shared[..16]);
+    key[16..].copy_from_slice(&hash[16..]);
+    let mut iv = [0u8; 16];
+    iv[..4].copy_from_slice(&hash[..4]);
+    iv[4..].copy_from_slice(&shared[20..]);
+    make_cipher(&key, &iv)
+}
+
+fn make_cipher(key: &[u8], iv: &[u8]) -> Aes256Ctr {
+    Aes256Ctr::new_from_slices(key, iv).expect("shouldn't fail for valid lengths")
+}
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+const PUB_ED25519: u32 = 0x4813b4c6;
+const ADNL_MESSAGE_QUERY: u32 = 0xb48bf97a;
+const ADNL_MESSAGE_ANSWER: u32 = 0x0fac8416;
+const LITE_SERVER_QUERY: u32 = 0x798c06df;
+
+const MAX_PACKET_SIZE: usize = 16 << 20;
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+enum AdnlError {
+    #[error("Invalid server key")]
+    InvalidServerKey,
+    #[error("Handshake failed")]
+    HandshakeFailed,
+    #[error("Invalid packet")]
+    InvalidPacket,
+    #[error("Query timeout")]
+    Timeout,
+}
+
+#[cfg(test)]
+mod tests {
+    use nekoton::external::AdnlConnection;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn query_roundtrip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        let server_key = public.to_bytes();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let mut handshake = [0u8; 256];
+            stream.read_exact(&mut handshake).await.unwrap();
+            assert_eq!(handshake[..32], server_key_id(&public));
+
+            let client_key = ed25519_dalek::PublicKey::from_bytes(&handshake[32..64]).unwrap();
+            let shared = nekoton::crypto::x25519::compute_shared(&secret, &client_key);
+            let hash: [u8; 32] = handshake[64..96].try_into().unwrap();
+
+            let mut params = handshake[96..].to_vec();
+            handshake_cipher(&shared, &hash).apply_keystream(&mut params);
+            assert_eq!(Sha256::digest(&params).as_slice(), hash);
+
+            let mut connection = Connection {
+                stream,
+                send_cipher: make_cipher(&params[0..32], &params[64..80]),
+                recv_cipher: make_cipher(&params[32..64], &params[80..96]),
+            };
+            connection.send_packet(&[]).await.unwrap();
+
+            let packet = connection.recv_packet().await.unwrap();
+            let mut reader = TlReader::new(&packet);
+            assert_eq!(reader.read_u32(), Some(ADNL_MESSAGE_QUERY));
+            let query_id = reader.read_int256().unwrap();
+            let mut reader = TlReader::new(reader.read_bytes().unwrap());
+            assert_eq!(reader.read_u32(), Some(LITE_SERVER_QUERY));
+            let request = reader.read_bytes().unwrap().to_vec();
+
+            // Answer to another query must be skipped
+            for (id, answer) in [(UInt256::default(), b"other".to_vec()), (query_id, request)] {
+                let mut message = TlWriter::new(ADNL_MESSAGE_ANSWER);
+                message.write_int256(&id);
+                message.write_bytes(&answer);
+                connection.send_packet(&message.finish()).await.unwrap();
+            }
+        });
+
+        let client = AdnlClient::new(AdnlConfig::new(address, server_key)).unwrap();
+        let answer = client.query(b"request".to_vec()).await.unwrap();
+        assert_eq!(answer, b"request");
+
+        server.await.unwrap();
+    }
+}
@@ -272,7 +272,7 @@ impl Endpoint {
     }
 }
 
-fn expand_address(base_url: &str) -> String {
+pub(crate) fn expand_address(base_url: &str) -> String {
     match base_url.trim_end_matches('/') {
         url if base_url.starts_with("http://") || base_url.starts_with("https://") => {
             format!("{}/graphql", url)
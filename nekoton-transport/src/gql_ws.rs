@@ -0,0 +1,740 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use nekoton::abi::GenTimings;
+use nekoton::core::models::NetworkCapabilities;
+use nekoton::crypto::SignatureId;
+use nekoton::transport::models::{PollContractState, RawContractState, RawTransaction};
+use nekoton::transport::proofs::AccountStateProof;
+use nekoton::transport::{Transport, TransportInfo};
+use nekoton_utils::Clock;
+use parking_lot::Mutex;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use ton_block::MsgAddressInt;
+use ton_types::{Cell, UInt256};
+
+type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// GraphQL subscriptions client (`graphql-transport-ws` protocol)
+#[derive(Debug, Clone)]
+pub struct GqlWsClient {
+    endpoint: Url,
+    connection_timeout: Duration,
+}
+
+impl GqlWsClient {
+    pub fn new(endpoint: &str) -> Result<Self> {
+        let endpoint = expand_ws_address(endpoint)
+            .as_str()
+            .try_into()
+            .with_context(|| format!("failed to parse endpoint: {}", endpoint))?;
+
+        Ok(Self {
+            endpoint,
+            connection_timeout: Duration::from_secs(10),
+        })
+    }
+
+    pub fn with_connection_timeout(mut self, timeout: Duration) -> Self {
+        self.connection_timeout = timeout;
+        self
+    }
+
+    /// Subscribes to new transactions of the specified account
+    pub async fn subscribe_transactions(
+        &self,
+        address: &MsgAddressInt,
+    ) -> Result<TransactionsSubscription> {
+        self.subscribe(
+            "subscription($address:String!){transactions(filter:{account_addr:{eq:$address}}){id lt}}",
+            address,
+        )
+        .await
+    }
+
+    /// Subscribes to new inbound messages of the specified account
+    pub async fn subscribe_messages(
+        &self,
+        address: &MsgAddressInt,
+    ) -> Result<MessagesSubscription> {
+        self.subscribe(
+            "subscription($address:String!){messages(filter:{dst:{eq:$address}}){id}}",
+            address,
+        )
+        .await
+    }
+
+    async fn subscribe<T>(
+        &self,
+        query: &str,
+        address: &MsgAddressInt,
+    ) -> Result<GqlSubscription<T>> {
+        let variables = serde_json::json!({ "address": address.to_string() });
+        let stream = tokio::time::timeout(self.connection_timeout, self.connect(query, variables))
+            .await
+            .map_err(|_| GqlWsError::ConnectionTimeout)??;
+
+        Ok(GqlSubscription {
+            stream: Some(stream),
+            _marker: PhantomData,
+        })
+    }
+
+    async fn connect(&self, query: &str, variables: serde_json::Value) -> Result<WsStream> {
+        let mut request = self.endpoint.as_str().into_client_request()?;
+        request.headers_mut().insert(
+            "Sec-WebSocket-Protocol",
+            HeaderValue::from_static("graphql-transport-ws"),
+        );
+
+        let (mut stream, _) = tokio_tungstenite::connect_async(request).await?;
+
+        stream
+            .send(Message::Text(r#"{"type":"connection_init"}"#.to_owned()))
+            .await?;
+
+        match read_server_message(&mut stream).await? {
+            Some(ServerMessage::ConnectionAck) => {}
+            _ => return Err(GqlWsError::ConnectionRejected.into()),
+        }
+
+        let subscribe = serde_json::json!({
+            "id": SUBSCRIPTION_ID,
+            "type": "subscribe",
+            "payload": { "query": query, "variables": variables },
+        });
+        stream.send(Message::Text(subscribe.to_string())).await?;
+
+        Ok(stream)
+    }
+}
+
+/// Stream of account transactions notifications
+pub type TransactionsSubscription = GqlSubscription<TransactionNotification>;
+
+/// Stream of account inbound messages notifications
+pub type MessagesSubscription = GqlSubscription<MessageNotification>;
+
+/// Stream of GraphQL subscription notifications
+pub struct GqlSubscription<T> {
+    stream: Option<WsStream>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Notification> GqlSubscription<T> {
+    /// Whether the underlying connection is still alive
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Waits for the next transaction notification.
+    ///
+    /// Returns `None` when the connection was closed
+    pub async fn recv(&mut self) -> Option<Result<T>> {
+        let stream = self.stream.as_mut()?;
+
+        let result = loop {
+            match read_server_message(stream).await {
+                Ok(Some(ServerMessage::Next { payload })) => {
+                    break parse_notification(payload);
+                }
+                Ok(Some(ServerMessage::Ping)) => {
+                    let pong = Message::Text(r#"{"type":"pong"}"#.to_owned());
+                    if stream.send(pong).await.is_err() {
+                        self.stream = None;
+                        return None;
+                    }
+                }
+                Ok(Some(ServerMessage::Pong | ServerMessage::ConnectionAck)) => {}
+                Ok(Some(ServerMessage::Error { payload })) => {
+                    self.stream = None;
+                    return Some(Err(
+                        GqlWsError::SubscriptionError(payload.to_string()).into()
+                    ));
+                }
+                Ok(Some(ServerMessage::Complete) | None) => {
+                    self.stream = None;
+                    return None;
+                }
+                Err(e) => {
+                    self.stream = None;
+                    return Some(Err(e));
+                }
+            }
+        };
+
+        Some(result)
+    }
+
+    /// Waits for the next notification, but no longer than `poll_interval`.
+    ///
+    /// After disconnect it just sleeps for `poll_interval`, so the caller can
+    /// transparently fall back to polling
+    pub async fn next_or_poll(&mut self, poll_interval: Duration) -> UpdateReason<T> {
+        if !self.is_connected() {
+            tokio::time::sleep(poll_interval).await;
+            return UpdateReason::Poll;
+        }
+
+        match tokio::time::timeout(poll_interval, self.recv()).await {
+            Ok(Some(Ok(notification))) => UpdateReason::Notification(notification),
+            Ok(Some(Err(e))) => {
+                log::warn!("GQL subscription error: {:?}", e);
+                UpdateReason::Poll
+            }
+            Ok(None) => UpdateReason::Disconnected,
+            Err(_) => UpdateReason::Poll,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum UpdateReason<T> {
+    /// New item was found
+    Notification(T),
+    /// Poll interval elapsed
+    Poll,
+    /// Connection was closed, all subsequent updates will be polled
+    Disconnected,
+}
+
+pub trait Notification: Sized {
+    /// Name of the subscription field
+    const FIELD: &'static str;
+    type Raw: DeserializeOwned;
+
+    fn parse(raw: Self::Raw) -> Result<Self>;
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TransactionNotification {
+    pub hash: [u8; 32],
+    pub lt: u64,
+}
+
+impl Notification for TransactionNotification {
+    const FIELD: &'static str = "transactions";
+    type Raw = RawTransactionNotification;
+
+    fn parse(raw: Self::Raw) -> Result<Self> {
+        let mut hash = [0; 32];
+        hex::decode_to_slice(&raw.id, &mut hash).map_err(|_| GqlWsError::InvalidNotification)?;
+
+        // NOTE: lt is returned as a hex string with `0x` prefix
+        let lt = match raw.lt.strip_prefix("0x") {
+            Some(lt) => u64::from_str_radix(lt, 16),
+            None => raw.lt.parse(),
+        }
+        .map_err(|_| GqlWsError::InvalidNotification)?;
+
+        Ok(Self { hash, lt })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MessageNotification {
+    pub hash: [u8; 32],
+}
+
+impl Notification for MessageNotification {
+    const FIELD: &'static str = "messages";
+    type Raw = RawMessageNotification;
+
+    fn parse(raw: Self::Raw) -> Result<Self> {
+        let mut hash = [0; 32];
+        hex::decode_to_slice(&raw.id, &mut hash).map_err(|_| GqlWsError::InvalidNotification)?;
+        Ok(Self { hash })
+    }
+}
+
+fn parse_notification<T: Notification>(payload: NextPayload) -> Result<T> {
+    let raw = payload
+        .data
+        .and_then(|mut data| data.get_mut(T::FIELD).map(serde_json::Value::take))
+        .ok_or(GqlWsError::InvalidNotification)?;
+    T::parse(serde_json::from_value(raw)?)
+}
+
+async fn read_server_message(stream: &mut WsStream) -> Result<Option<ServerMessage>> {
+    loop {
+        let message = match stream.next().await {
+            Some(message) => message?,
+            None => return Ok(None),
+        };
+
+        match message {
+            Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+            Message::Close(_) => return Ok(None),
+            _ => continue,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    ConnectionAck,
+    Next { payload: NextPayload },
+    Error { payload: serde_json::Value },
+    Complete,
+    Ping,
+    Pong,
+}
+
+#[derive(Deserialize)]
+struct NextPayload {
+    data: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+pub struct RawTransactionNotification {
+    id: String,
+    lt: String,
+}
+
+#[derive(Deserialize)]
+pub struct RawMessageNotification {
+    id: String,
+}
+
+/// Same as the HTTP endpoint, but with the websocket scheme
+fn expand_ws_address(base_url: &str) -> String {
+    if base_url.starts_with("ws://") || base_url.starts_with("wss://") {
+        return format!("{}/graphql", base_url.trim_end_matches('/'));
+    }
+
+    let url = crate::gql::expand_address(base_url);
+    match url.strip_prefix("https://") {
+        Some(url) => format!("wss://{}", url),
+        None => url.replacen("http://", "ws://", 1),
+    }
+}
+
+/// Transport which answers contract state polls locally while the
+/// websocket subscription reports no new transactions.
+///
+/// All other requests (and polls of accounts without a live subscription)
+/// are forwarded to the inner transport, so `ContractSubscription::refresh`
+/// can be called often without loading the endpoint
+pub struct GqlWsTransport {
+    inner: Arc<dyn Transport>,
+    client: GqlWsClient,
+    accounts: Arc<Mutex<HashMap<MsgAddressInt, WatchedAccount>>>,
+}
+
+impl GqlWsTransport {
+    /// Delay before the next subscription attempt after disconnect
+    pub const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+
+    pub fn new(inner: Arc<dyn Transport>, client: GqlWsClient) -> Self {
+        Self {
+            inner,
+            client,
+            accounts: Default::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &Arc<dyn Transport> {
+        &self.inner
+    }
+
+    pub fn client(&self) -> &GqlWsClient {
+        &self.client
+    }
+
+    /// Stops the subscription of the account
+    pub fn unwatch(&self, address: &MsgAddressInt) {
+        if let Some(account) = self.accounts.lock().remove(address) {
+            account.task.abort();
+        }
+    }
+
+    /// Returns `true` if the account definitely has no transactions since `last_trans_lt`.
+    /// Otherwise starts the subscription if needed
+    fn is_unchanged(&self, address: &MsgAddressInt, last_trans_lt: u64) -> bool {
+        let mut accounts = self.accounts.lock();
+        if let Some(account) = accounts.get_mut(address) {
+            if let Some(unchanged) = account.state.poll(last_trans_lt) {
+                return unchanged;
+            }
+        }
+
+        let task = tokio::spawn(watch_account(
+            self.client.clone(),
+            address.clone(),
+            Arc::downgrade(&self.accounts),
+        ));
+        accounts.insert(
+            address.clone(),
+            WatchedAccount {
+                state: WatchState::Connecting,
+                task,
+            },
+        );
+        false
+    }
+
+    /// Returns the start of the current subscription connection
+    fn connected_since(&self, address: &MsgAddressInt) -> Option<Instant> {
+        match self.accounts.lock().get(address) {
+            Some(WatchedAccount {
+                state: WatchState::Connected { since, .. },
+                ..
+            }) => Some(*since),
+            _ => None,
+        }
+    }
+
+    /// Marks the subscription as synced if it wasn't restarted
+    fn mark_synced(&self, address: &MsgAddressInt, connected_since: Instant) {
+        if let Some(account) = self.accounts.lock().get_mut(address) {
+            account.state.mark_synced(connected_since);
+        }
+    }
+}
+
+impl Drop for GqlWsTransport {
+    fn drop(&mut self) {
+        for account in self.accounts.lock().values() {
+            account.task.abort();
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Transport for GqlWsTransport {
+    fn info(&self) -> TransportInfo {
+        self.inner.info()
+    }
+
+    async fn send_message(&self, message: &ton_block::Message) -> Result<()> {
+        self.inner.send_message(message).await
+    }
+
+    async fn get_contract_state(&self, address: &MsgAddressInt) -> Result<RawContractState> {
+        self.inner.get_contract_state(address).await
+    }
+
+    async fn get_library_cell(&self, hash: &UInt256) -> Result<Option<Cell>> {
+        self.inner.get_library_cell(hash).await
+    }
+
+    async fn poll_contract_state(
+        &self,
+        address: &MsgAddressInt,
+        last_trans_lt: u64,
+    ) -> Result<PollContractState> {
+        if self.is_unchanged(address, last_trans_lt) {
+            // NOTE: unknown timings make the subscription use the local clock
+            return Ok(PollContractState::Unchanged {
+                timings: GenTimings::Unknown,
+            });
+        }
+
+        // NOTE: the connection is captured before the request so that
+        // a reconnect during it doesn't mark the new subscription as synced
+        let connected_since = self.connected_since(address);
+        let state = self
+            .inner
+            .poll_contract_state(address, last_trans_lt)
+            .await?;
+        if let Some(since) = connected_since {
+            self.mark_synced(address, since);
+        }
+        Ok(state)
+    }
+
+    async fn get_accounts_by_code_hash(
+        &self,
+        code_hash: &UInt256,
+        limit: u8,
+        continuation: &Option<MsgAddressInt>,
+    ) -> Result<Vec<MsgAddressInt>> {
+        self.inner
+            .get_accounts_by_code_hash(code_hash, limit, continuation)
+            .await
+    }
+
+    async fn get_transactions(
+        &self,
+        address: &MsgAddressInt,
+        from_lt: u64,
+        count: u8,
+    ) -> Result<Vec<RawTransaction>> {
+        self.inner.get_transactions(address, from_lt, count).await
+    }
+
+    async fn get_transaction(&self, id: &UInt256) -> Result<Option<RawTransaction>> {
+        self.inner.get_transaction(id).await
+    }
+
+    async fn get_dst_transaction(&self, message_hash: &UInt256) -> Result<Option<RawTransaction>> {
+        self.inner.get_dst_transaction(message_hash).await
+    }
+
+    async fn get_latest_key_block(&self) -> Result<ton_block::Block> {
+        self.inner.get_latest_key_block().await
+    }
+
+    async fn get_capabilities(&self, clock: &dyn Clock) -> Result<NetworkCapabilities> {
+        self.inner.get_capabilities(clock).await
+    }
+
+    async fn get_signature_id(&self, clock: &dyn Clock) -> Result<Option<SignatureId>> {
+        self.inner.get_signature_id(clock).await
+    }
+
+    async fn get_blockchain_config(
+        &self,
+        clock: &dyn Clock,
+        force: bool,
+    ) -> Result<ton_executor::BlockchainConfig> {
+        self.inner.get_blockchain_config(clock, force).await
+    }
+
+    async fn get_contract_state_proof(
+        &self,
+        address: &MsgAddressInt,
+        block_hash: &UInt256,
+    ) -> Result<AccountStateProof> {
+        self.inner
+            .get_contract_state_proof(address, block_hash)
+            .await
+    }
+}
+
+struct WatchedAccount {
+    state: WatchState,
+    task: tokio::task::JoinHandle<()>,
+}
+
+enum WatchState {
+    Connecting,
+    Connected {
+        /// When the subscription was established
+        since: Instant,
+        /// Whether the state was successfully polled after the connection
+        synced: bool,
+        /// Lt of the latest notified transaction
+        latest_lt: Option<u64>,
+    },
+    Disconnected {
+        since: Instant,
+    },
+}
+
+impl WatchState {
+    /// Returns whether the account is unchanged since `last_trans_lt`,
+    /// or `None` if the subscription must be restarted
+    fn poll(&mut self, last_trans_lt: u64) -> Option<bool> {
+        match self {
+            // NOTE: polls are forwarded until the first successful one after
+            // connection to catch transactions which appeared before it
+            Self::Connected {
+                synced, latest_lt, ..
+            } => Some(*synced && !matches!(latest_lt, Some(lt) if *lt >= last_trans_lt)),
+            Self::Connecting => Some(false),
+            Self::Disconnected { since } => {
+                (since.elapsed() < GqlWsTransport::RECONNECT_INTERVAL).then_some(false)
+            }
+        }
+    }
+
+    fn mark_synced(&mut self, connected_since: Instant) {
+        if let Self::Connected { since, synced, .. } = self {
+            if *since == connected_since {
+                *synced = true;
+            }
+        }
+    }
+}
+
+type AccountsMap = Mutex<HashMap<MsgAddressInt, WatchedAccount>>;
+
+async fn watch_account(
+    client: GqlWsClient,
+    address: MsgAddressInt,
+    accounts: std::sync::Weak<AccountsMap>,
+) {
+    let update = |state: WatchState| {
+        if let Some(accounts) = accounts.upgrade() {
+            if let Some(account) = accounts.lock().get_mut(&address) {
+                account.state = state;
+            }
+        }
+    };
+
+    match client.subscribe_transactions(&address).await {
+        Ok(mut subscription) => {
+            update(WatchState::Connected {
+                since: Instant::now(),
+                synced: false,
+                latest_lt: None,
+            });
+
+            while let Some(notification) = subscription.recv().await {
+                let notification = match notification {
+                    Ok(notification) => notification,
+                    Err(e) => {
+                        log::warn!("GQL subscription error: {:?}", e);
+                        break;
+                    }
+                };
+
+                let accounts = match accounts.upgrade() {
+                    Some(accounts) => accounts,
+                    None => return,
+                };
+                let mut accounts = accounts.lock();
+                if let Some(WatchedAccount {
+                    state: WatchState::Connected { latest_lt, .. },
+                    ..
+                }) = accounts.get_mut(&address)
+                {
+                    *latest_lt = Some(latest_lt.unwrap_or_default().max(notification.lt));
+                }
+            }
+        }
+        Err(e) => log::warn!("Failed to subscribe to {address}: {e:?}"),
+    }
+
+    update(WatchState::Disconnected {
+        since: Instant::now(),
+    });
+}
+
+const SUBSCRIPTION_ID: &str = "1";
+
+#[derive(thiserror::Error, Debug)]
+enum GqlWsError {
+    #[error("connection timeout")]
+    ConnectionTimeout,
+    #[error("connection rejected")]
+    ConnectionRejected,
+    #[error("subscription error: {0}")]
+    SubscriptionError(String),
+    #[error("invalid notification")]
+    InvalidNotification,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn correct_endpoint_expansion() {
+        assert_eq!(expand_ws_address("localhost"), "ws://localhost/graphql");
+        assert_eq!(
+            expand_ws_address("https://mainnet.evercloud.dev/"),
+            "wss://mainnet.evercloud.dev/graphql"
+        );
+        assert_eq!(
+            expand_ws_address("mainnet.evercloud.dev"),
+            "wss://mainnet.evercloud.dev/graphql"
+        );
+        assert_eq!(
+            expand_ws_address("wss://mainnet.evercloud.dev/"),
+            "wss://mainnet.evercloud.dev/graphql"
+        );
+    }
+
+    #[test]
+    fn watch_state_polling() {
+        let mut state = WatchState::Connecting;
+        assert_eq!(state.poll(100), Some(false));
+
+        let since = Instant::now();
+        let mut state = WatchState::Connected {
+            since,
+            synced: false,
+            latest_lt: None,
+        };
+        // Not synced until the forwarded poll succeeds
+        assert_eq!(state.poll(100), Some(false));
+        assert_eq!(state.poll(100), Some(false));
+
+        // Sync of the previous connection is ignored
+        state.mark_synced(since - Duration::from_secs(1));
+        assert_eq!(state.poll(100), Some(false));
+
+        state.mark_synced(since);
+        assert_eq!(state.poll(100), Some(true));
+
+        if let WatchState::Connected { latest_lt, .. } = &mut state {
+            *latest_lt = Some(150);
+        }
+        assert_eq!(state.poll(100), Some(false));
+        assert_eq!(state.poll(151), Some(true));
+
+        let mut state = WatchState::Disconnected {
+            since: Instant::now(),
+        };
+        assert_eq!(state.poll(100), Some(false));
+
+        let mut state = WatchState::Disconnected {
+            since: Instant::now() - GqlWsTransport::RECONNECT_INTERVAL,
+        };
+        assert_eq!(state.poll(100), None);
+    }
+
+    #[test]
+    fn parse_notifications() {
+        let message: ServerMessage = serde_json::from_str(
+            r#"{
+                "id": "1",
+                "type": "next",
+                "payload": {
+                    "data": {
+                        "transactions": {
+                            "id": "4a0a06bfbfaba4da8fcc7f5ad617fdee5344d954a1794e35618df2a4b349d15c",
+                            "lt": "0x1a2b"
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let notification = match message {
+            ServerMessage::Next { payload } => {
+                parse_notification::<TransactionNotification>(payload).unwrap()
+            }
+            _ => panic!("unexpected message"),
+        };
+        assert_eq!(notification.lt, 0x1a2b);
+        assert_eq!(notification.hash[0], 0x4a);
+
+        let message: ServerMessage = serde_json::from_str(
+            r#"{
+                "id": "1",
+                "type": "next",
+                "payload": {
+                    "data": {
+                        "messages": {
+                            "id": "4a0a06bfbfaba4da8fcc7f5ad617fdee5344d954a1794e35618df2a4b349d15c"
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let notification = match message {
+            ServerMessage::Next { payload } => {
+                parse_notification::<MessageNotification>(payload).unwrap()
+            }
+            _ => panic!("unexpected message"),
+        };
+        assert_eq!(notification.hash[0], 0x4a);
+    }
+}
@@ -1,12 +1,33 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
+use parking_lot::Mutex;
 use reqwest::{IntoUrl, Url};
+use tokio::sync::oneshot;
 
 pub struct JrpcClient {
     client: reqwest::Client,
     base_url: Url,
     alternative_url: Option<Url>,
+    batching: Option<Batching>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct JrpcBatchSettings {
+    /// Max number of requests in one batch. Default: `50`
+    pub max_batch_size: usize,
+    /// How long to wait for other requests before sending a batch. Default: `10 ms`
+    pub flush_interval: Duration,
+}
+
+impl Default for JrpcBatchSettings {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 50,
+            flush_interval: Duration::from_millis(10),
+        }
+    }
 }
 
 impl JrpcClient {
@@ -29,6 +50,7 @@ impl JrpcClient {
             client,
             base_url: url,
             alternative_url: None,
+            batching: None,
         }))
     }
 
@@ -37,22 +59,180 @@ impl JrpcClient {
         self.alternative_url = Some(endpoint.into_url()?);
         Ok(())
     }
+
+    /// Coalesce concurrent `getContractState` and `getTransactions` requests
+    /// into JSON-RPC batches
+    pub fn set_batching(&mut self, settings: JrpcBatchSettings) {
+        self.batching = Some(Batching {
+            settings,
+            queues: Default::default(),
+        });
+    }
+
+    /// Queues the request and waits for the response.
+    ///
+    /// Batches are sent on detached tasks, so cancelling
+    /// one request doesn't affect the others
+    async fn post_batched(
+        &self,
+        batching: &Batching,
+        url: &Url,
+        req_idx: usize,
+        data: String,
+    ) -> Result<String> {
+        let (tx, rx) = oneshot::channel();
+        let queue = &batching.queues[req_idx];
+
+        let (full_batch, schedule_flush) = {
+            let mut requests = queue.lock();
+            requests.push(PendingRequest { data, tx });
+            if requests.len() >= batching.settings.max_batch_size {
+                (Some(std::mem::take(&mut *requests)), false)
+            } else {
+                // The first request in the queue schedules the flush
+                (None, requests.len() == 1)
+            }
+        };
+
+        if let Some(batch) = full_batch {
+            tokio::spawn(send_batch(self.client.clone(), url.clone(), batch));
+        } else if schedule_flush {
+            let client = self.client.clone();
+            let url = url.clone();
+            let queue = queue.clone();
+            let flush_interval = batching.settings.flush_interval;
+            tokio::spawn(async move {
+                tokio::time::sleep(flush_interval).await;
+                let batch = std::mem::take(&mut *queue.lock());
+                if !batch.is_empty() {
+                    send_batch(client, url, batch).await;
+                }
+            });
+        }
+
+        rx.await.map_err(|_| JrpcClientError::RequestDropped)?
+    }
+}
+
+async fn send_batch(client: reqwest::Client, url: Url, batch: Vec<PendingRequest>) {
+    let (requests, mut senders) = prepare_batch(batch);
+
+    let result = async {
+        let response = client.post(url).json(&requests).send().await?;
+        Ok::<_, anyhow::Error>(response.json::<Vec<serde_json::Value>>().await?)
+    }
+    .await;
+
+    match result {
+        Ok(responses) => dispatch_responses(responses, &mut senders),
+        Err(e) => {
+            let error = e.to_string();
+            for (tx, _) in senders.into_iter().flatten() {
+                tx.send(Err(JrpcClientError::BatchFailed(error.clone()).into()))
+                    .ok();
+            }
+        }
+    }
+}
+
+/// Assigns unique ids to the batched requests.
+///
+/// Returns requests with the senders and the original request ids
+fn prepare_batch(batch: Vec<PendingRequest>) -> (Vec<serde_json::Value>, Vec<Option<BatchSender>>) {
+    let mut requests = Vec::with_capacity(batch.len());
+    let mut senders = Vec::with_capacity(batch.len());
+    for PendingRequest { data, tx } in batch {
+        match serde_json::from_str::<serde_json::Value>(&data) {
+            Ok(mut request) => {
+                let original_id = std::mem::replace(&mut request["id"], senders.len().into());
+                requests.push(request);
+                senders.push(Some((tx, original_id)));
+            }
+            Err(e) => {
+                tx.send(Err(e.into())).ok();
+                senders.push(None);
+            }
+        }
+    }
+    (requests, senders)
+}
+
+/// Sends responses to the callers, restoring the original request ids
+fn dispatch_responses(responses: Vec<serde_json::Value>, senders: &mut [Option<BatchSender>]) {
+    for mut response in responses {
+        let sender = response["id"]
+            .as_u64()
+            .and_then(|id| senders.get_mut(id as usize))
+            .and_then(Option::take);
+        if let Some((tx, original_id)) = sender {
+            response["id"] = original_id;
+            tx.send(Ok(response.to_string())).ok();
+        }
+    }
+    for (tx, _) in senders.iter_mut().filter_map(Option::take) {
+        tx.send(Err(JrpcClientError::MissingResponse.into())).ok();
+    }
+}
+
+struct Batching {
+    settings: JrpcBatchSettings,
+    /// Separate queues for the base and alternative urls
+    queues: [Arc<Mutex<Vec<PendingRequest>>>; 2],
+}
+
+struct PendingRequest {
+    data: String,
+    tx: oneshot::Sender<Result<String>>,
+}
+
+type BatchSender = (oneshot::Sender<Result<String>>, serde_json::Value);
+
+fn is_batchable(data: &str) -> bool {
+    #[derive(serde::Deserialize)]
+    struct Request<'a> {
+        #[serde(borrow)]
+        method: &'a str,
+    }
+
+    matches!(
+        serde_json::from_str::<Request<'_>>(data),
+        Ok(Request {
+            method: "getContractState" | "getTransactions"
+        })
+    )
 }
 
 #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
 #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
 impl nekoton::external::JrpcConnection for JrpcClient {
     async fn post(&self, req: nekoton::external::JrpcRequest) -> Result<String> {
-        let url = if req.requires_db {
-            self.alternative_url.as_ref().unwrap_or(&self.base_url)
+        let (url, req_idx) = if req.requires_db {
+            (self.alternative_url.as_ref().unwrap_or(&self.base_url), 1)
         } else {
-            &self.base_url
+            (&self.base_url, 0)
         };
+
+        if let Some(batching) = &self.batching {
+            if is_batchable(&req.data) {
+                return self.post_batched(batching, url, req_idx, req.data).await;
+            }
+        }
+
         let response = self.client.post(url.clone()).body(req.data).send().await?;
         Ok(response.text().await?)
     }
 }
 
+#[derive(thiserror::Error, Debug)]
+enum JrpcClientError {
+    #[error("request dropped")]
+    RequestDropped,
+    #[error("missing response in batch")]
+    MissingResponse,
+    #[error("batch request failed: {0}")]
+    BatchFailed(String),
+}
+
 #[cfg(test)]
 mod tests {
     use nekoton::external::{JrpcConnection, JrpcRequest};
@@ -81,4 +261,65 @@ mod tests {
             .unwrap();
         println!("{}", response);
     }
+
+    #[test]
+    fn batchable_requests() {
+        assert!(is_batchable(
+            r#"{"jsonrpc":"2.0","id":1,"method":"getContractState","params":{}}"#
+        ));
+        assert!(is_batchable(
+            r#"{"jsonrpc":"2.0","id":1,"method":"getTransactions","params":{}}"#
+        ));
+        assert!(!is_batchable(
+            r#"{"jsonrpc":"2.0","id":1,"method":"sendMessage","params":{}}"#
+        ));
+        assert!(!is_batchable("not a json"));
+    }
+
+    #[test]
+    fn batch_keeps_request_ids() {
+        let mut receivers = Vec::new();
+        let batch = ["1", "\"abc\"", "1"]
+            .iter()
+            .map(|id| {
+                let (tx, rx) = oneshot::channel();
+                receivers.push(rx);
+                PendingRequest {
+                    data: format!(
+                        r#"{{"jsonrpc":"2.0","id":{id},"method":"getContractState","params":{{}}}}"#
+                    ),
+                    tx,
+                }
+            })
+            .collect();
+
+        let (requests, mut senders) = prepare_batch(batch);
+        let ids = requests
+            .iter()
+            .map(|item| item["id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [0, 1, 2]);
+
+        // Responses can be reordered, the last one is missing
+        let responses = [1, 0]
+            .iter()
+            .map(|id| serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": id }))
+            .collect();
+        dispatch_responses(responses, &mut senders);
+
+        let mut results = receivers
+            .into_iter()
+            .map(|mut rx| rx.try_recv().unwrap())
+            .collect::<Vec<_>>();
+        assert!(results.pop().unwrap().is_err());
+
+        let results = results
+            .into_iter()
+            .map(|item| serde_json::from_str::<serde_json::Value>(&item.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(results[0]["id"], 1);
+        assert_eq!(results[0]["result"], 0);
+        assert_eq!(results[1]["id"], "abc");
+        assert_eq!(results[1]["result"], 1);
+    }
 }
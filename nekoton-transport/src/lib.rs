@@ -52,8 +52,12 @@
     clippy::dbg_macro
 )]
 
+#[cfg(feature = "adnl_transport")]
+pub mod adnl;
 #[cfg(feature = "gql_transport")]
 pub mod gql;
+#[cfg(feature = "gql_ws_transport")]
+pub mod gql_ws;
 #[cfg(feature = "jrpc_transport")]
 pub mod jrpc;
 #[cfg(feature = "proto_transport")]
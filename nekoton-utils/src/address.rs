@@ -2,6 +2,7 @@ use std::str::FromStr;
 
 use anyhow::Result;
 use base64::URL_SAFE;
+use serde::{Deserialize, Serialize};
 use ton_block::{MsgAddrStd, MsgAddressInt};
 use ton_types::AccountId;
 
@@ -15,6 +16,18 @@ pub fn pack_std_smc_addr(
     addr: &MsgAddressInt,
     bounceable: bool,
 ) -> Result<String> {
+    pack_std_smc_addr_ext(
+        addr,
+        PackedAddressFlags {
+            url_safe: base64_url,
+            bounceable,
+            testnet: false,
+        },
+    )
+}
+
+/// Packs std address to base64 format with the specified flags
+pub fn pack_std_smc_addr_ext(addr: &MsgAddressInt, flags: PackedAddressFlags) -> Result<String> {
     let addr = match addr {
         MsgAddressInt::AddrStd(addr) => addr,
         MsgAddressInt::AddrVar(_) => {
@@ -22,15 +35,14 @@ pub fn pack_std_smc_addr(
         }
     };
 
-    let testnet = false;
     let mut buffer = [0u8; 36];
-    buffer[0] = (0x51 - (bounceable as i32) * 0x40 + (testnet as i32) * 0x80) as u8;
+    buffer[0] = flags.tag();
     buffer[1] = addr.workchain_id as u8;
     buffer[2..34].copy_from_slice(&addr.address.storage()[0..32]);
     let crc = crc_16(&buffer[..34]);
     buffer[34] = (crc >> 8) as u8;
     buffer[35] = (crc & 0xff) as u8;
-    let b64_enc = if base64_url {
+    let b64_enc = if flags.url_safe {
         base64::encode_config(buffer, URL_SAFE)
     } else {
         base64::encode(buffer)
@@ -42,6 +54,14 @@ pub fn pack_std_smc_addr(
 /// # Arguments
 /// `base64_url` - encode with url friendly charset or not
 pub fn unpack_std_smc_addr(packed: &str, base64_url: bool) -> Result<MsgAddressInt> {
+    unpack_std_smc_addr_ext(packed, base64_url).map(|(address, _)| address)
+}
+
+/// Unpacks base64 encoded address to std address and its flags
+pub fn unpack_std_smc_addr_ext(
+    packed: &str,
+    base64_url: bool,
+) -> Result<(MsgAddressInt, PackedAddressFlags)> {
     let unpacked = if base64_url {
         base64::decode_config(packed, URL_SAFE)
     } else {
@@ -58,16 +78,122 @@ pub fn unpack_std_smc_addr(packed: &str, base64_url: bool) -> Result<MsgAddressI
         return Err(AddressConversionError::InvalidChecksum.into());
     }
 
+    let flags = PackedAddressFlags::from_tag(unpacked[0], base64_url)
+        .ok_or(AddressConversionError::InvalidTag)?;
+
     let wc = unpacked[1];
     let address = &unpacked[2..34];
     let address = AccountId::from_raw(address.to_vec(), address.len() * 8);
-    Ok(MsgAddressInt::AddrStd(MsgAddrStd {
+    let address = MsgAddressInt::AddrStd(MsgAddrStd {
         workchain_id: wc as i8,
         anycast: None,
         address,
-    }))
+    });
+    Ok((address, flags))
+}
+
+/// Flags of the packed (user-friendly) address representation
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackedAddressFlags {
+    /// Use url friendly base64 charset
+    pub url_safe: bool,
+    pub bounceable: bool,
+    /// Address is intended for the test network only
+    pub testnet: bool,
+}
+
+impl PackedAddressFlags {
+    fn tag(&self) -> u8 {
+        let mut tag = if self.bounceable {
+            BOUNCEABLE_TAG
+        } else {
+            NON_BOUNCEABLE_TAG
+        };
+        if self.testnet {
+            tag |= TESTNET_FLAG;
+        }
+        tag
+    }
+
+    fn from_tag(tag: u8, url_safe: bool) -> Option<Self> {
+        let testnet = tag & TESTNET_FLAG != 0;
+        let bounceable = match tag & !TESTNET_FLAG {
+            BOUNCEABLE_TAG => true,
+            NON_BOUNCEABLE_TAG => false,
+            _ => return None,
+        };
+        Some(Self {
+            url_safe,
+            bounceable,
+            testnet,
+        })
+    }
+}
+
+const BOUNCEABLE_TAG: u8 = 0x11;
+const NON_BOUNCEABLE_TAG: u8 = 0x51;
+const TESTNET_FLAG: u8 = 0x80;
+
+/// Textual representation of the address
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "flags")]
+pub enum AddressFormat {
+    /// `workchain:hex`
+    Raw,
+    /// 48 chars of base64 with flags and checksum
+    Packed(PackedAddressFlags),
+}
+
+impl AddressFormat {
+    /// Packed url-safe format, used by TON wallets for user accounts
+    pub const USER_FRIENDLY: Self = Self::Packed(PackedAddressFlags {
+        url_safe: true,
+        bounceable: false,
+        testnet: false,
+    });
+
+    /// Packed url-safe format, used by TON wallets for smart contracts
+    pub const USER_FRIENDLY_BOUNCEABLE: Self = Self::Packed(PackedAddressFlags {
+        url_safe: true,
+        bounceable: true,
+        testnet: false,
+    });
+}
+
+/// Parses the address in any supported format, detecting the format automatically
+pub fn parse_address(address: &str) -> Result<(MsgAddressInt, AddressFormat)> {
+    if let Ok(a) = MsgAddressInt::from_str(address) {
+        return Ok((a, AddressFormat::Raw));
+    }
+
+    if address.len() != PACKED_ADDRESS_LEN {
+        return Err(AddressConversionError::InvalidAddress.into());
+    }
+
+    // NOTE: addresses without `+/-_` chars are valid in both charsets,
+    // url-safe is preferred as the most common one
+    let url_safe = !address.contains(|c| c == '+' || c == '/');
+    let (address, flags) = unpack_std_smc_addr_ext(address, url_safe)?;
+    Ok((address, AddressFormat::Packed(flags)))
 }
 
+/// Renders the address in the specified format
+pub fn format_address(address: &MsgAddressInt, format: AddressFormat) -> Result<String> {
+    match format {
+        AddressFormat::Raw => Ok(address.to_string()),
+        AddressFormat::Packed(flags) => pack_std_smc_addr_ext(address, flags),
+    }
+}
+
+/// Converts the address from any supported format to the specified one
+pub fn convert_address(address: &str, format: AddressFormat) -> Result<String> {
+    let (address, _) = parse_address(address)?;
+    format_address(&address, format)
+}
+
+const PACKED_ADDRESS_LEN: usize = 48;
+
 pub fn validate_address(address: &str) -> bool {
     MsgAddressInt::from_str(address).is_ok()
         || unpack_std_smc_addr(address, false).is_ok()
@@ -98,6 +224,8 @@ enum AddressConversionError {
     InvalidPackedLength,
     #[error("Invalid checksum")]
     InvalidChecksum,
+    #[error("Invalid address tag")]
+    InvalidTag,
     #[error("Invalid address")]
     InvalidAddress,
 }
@@ -203,6 +331,60 @@ mod tests {
         )
     }
 
+    #[test]
+    fn parse_any_format() {
+        let addr = test_addr();
+
+        let (parsed, format) = super::parse_address(
+            "0:02e3f2284e68a8106b823ab9f2404f33cc43fccad8e1de835bdd96789254686c",
+        )
+        .unwrap();
+        assert_eq!(parsed, addr);
+        assert_eq!(format, super::AddressFormat::Raw);
+
+        let (parsed, format) =
+            super::parse_address("EQAC4/IoTmioEGuCOrnyQE8zzEP8ytjh3oNb3ZZ4klRobFz0").unwrap();
+        assert_eq!(parsed, addr);
+        assert_eq!(
+            format,
+            super::AddressFormat::Packed(super::PackedAddressFlags {
+                url_safe: false,
+                bounceable: true,
+                testnet: false,
+            })
+        );
+
+        let (parsed, format) =
+            super::parse_address("UQAC4_IoTmioEGuCOrnyQE8zzEP8ytjh3oNb3ZZ4klRobAEx").unwrap();
+        assert_eq!(parsed, addr);
+        assert_eq!(format, super::AddressFormat::USER_FRIENDLY);
+
+        // Corrupted checksum
+        assert!(super::parse_address("UQAC4_IoTmioEGuCOrnyQE8zzEP8ytjh3oNb3ZZ4klRobAEy").is_err());
+    }
+
+    #[test]
+    fn convert_formats() {
+        let testnet = super::AddressFormat::Packed(super::PackedAddressFlags {
+            url_safe: true,
+            bounceable: true,
+            testnet: true,
+        });
+
+        let packed = super::convert_address(
+            "0:02e3f2284e68a8106b823ab9f2404f33cc43fccad8e1de835bdd96789254686c",
+            testnet,
+        )
+        .unwrap();
+        let (_, format) = super::parse_address(&packed).unwrap();
+        assert_eq!(format, testnet);
+
+        assert_eq!(
+            super::convert_address(&packed, super::AddressFormat::Raw).unwrap(),
+            "0:02e3f2284e68a8106b823ab9f2404f33cc43fccad8e1de835bdd96789254686c"
+        );
+    }
+
     #[test]
     pub fn repack_bad() {
         let res = super::repack_address(
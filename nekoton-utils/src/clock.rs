@@ -1,10 +1,18 @@
 use std::convert::TryInto;
-use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
 
 pub trait Clock: Send + Sync {
     fn now_sec_u64(&self) -> u64;
     fn now_ms_f64(&self) -> f64;
     fn now_ms_u64(&self) -> u64;
+
+    /// Called with the generation time of the latest known block (e.g. from transport responses).
+    ///
+    /// Clocks which correct the local time use it to learn the skew
+    fn observe_block_time(&self, gen_utime: u32) {
+        let _ = gen_utime;
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -27,21 +35,34 @@ impl Clock for SimpleClock {
     }
 }
 
+/// Clock with a constant offset from the local time.
+///
+/// The default clock learns the offset from the observed block times,
+/// until it is set explicitly
 #[derive(Default)]
 pub struct ClockWithOffset {
     offset_as_sec: AtomicI64,
     offset_as_ms: AtomicI64,
+    explicit_offset: AtomicBool,
 }
 
 impl ClockWithOffset {
+    /// Creates a clock with an explicit offset, which is not affected by block times
     pub fn new(offset_ms: i64) -> Self {
         Self {
             offset_as_sec: AtomicI64::new(offset_ms / 1000),
             offset_as_ms: AtomicI64::new(offset_ms),
+            explicit_offset: AtomicBool::new(true),
         }
     }
 
+    /// Sets an explicit offset, which is no longer adjusted by block times
     pub fn update_offset(&self, offset_ms: i64) {
+        self.explicit_offset.store(true, Ordering::Release);
+        self.store_offset(offset_ms);
+    }
+
+    fn store_offset(&self, offset_ms: i64) {
         self.offset_as_sec
             .store(offset_ms / 1000, Ordering::Release);
         self.offset_as_ms.store(offset_ms, Ordering::Release);
@@ -50,6 +71,12 @@ impl ClockWithOffset {
     pub fn offset_ms(&self) -> i64 {
         self.offset_as_ms.load(Ordering::Acquire)
     }
+
+    /// Max difference between the corrected time and the block time
+    /// which is not treated as a clock skew.
+    ///
+    /// Blocks are always a bit behind the real time, so small differences are ignored
+    pub const MAX_BLOCK_TIME_LAG_MS: i64 = 10_000;
 }
 
 impl Clock for ClockWithOffset {
@@ -75,6 +102,22 @@ impl Clock for ClockWithOffset {
             .try_into()
             .unwrap_or_default()
     }
+
+    /// Moves the clock forward when the local time is behind the block time.
+    ///
+    /// NOTE: block time behind the local time is ignored, because it is
+    /// indistinguishable from a lagging node. Use [`ClockWithOffset::update_offset`]
+    /// to correct a local clock which is ahead
+    fn observe_block_time(&self, gen_utime: u32) {
+        if self.explicit_offset.load(Ordering::Acquire) {
+            return;
+        }
+
+        let offset_ms = gen_utime as i64 * 1000 - now_ms_u64() as i64;
+        if offset_ms - self.offset_ms() > Self::MAX_BLOCK_TIME_LAG_MS {
+            self.store_offset(offset_ms);
+        }
+    }
 }
 
 #[derive(Copy, Clone, Default)]
@@ -118,6 +161,47 @@ impl Clock for ConstClock {
     }
 }
 
+/// Manually controlled clock.
+///
+/// Clones share the same time
+#[derive(Clone, Default)]
+pub struct MockClock {
+    now_ms: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            now_ms: Arc::new(AtomicU64::new(now_ms)),
+        }
+    }
+
+    pub fn set_ms(&self, now_ms: u64) {
+        self.now_ms.store(now_ms, Ordering::Release);
+    }
+
+    pub fn advance_ms(&self, ms: u64) {
+        self.now_ms.fetch_add(ms, Ordering::AcqRel);
+    }
+}
+
+impl Clock for MockClock {
+    #[inline]
+    fn now_sec_u64(&self) -> u64 {
+        self.now_ms_u64() / 1000
+    }
+
+    #[inline]
+    fn now_ms_f64(&self) -> f64 {
+        self.now_ms_u64() as f64
+    }
+
+    #[inline]
+    fn now_ms_u64(&self) -> u64 {
+        self.now_ms.load(Ordering::Acquire)
+    }
+}
+
 #[cfg(all(target_arch = "wasm32", feature = "web"))]
 pub fn now_sec_u64() -> u64 {
     (js_sys::Date::now() / 1000.0) as u64
@@ -173,4 +257,60 @@ mod tests {
         println!("{}", now_ms_f64());
         println!("{}", now_ms_u64());
     }
+
+    #[test]
+    fn mock_clock() {
+        let clock = MockClock::new(1_500);
+        assert_eq!(clock.now_sec_u64(), 1);
+
+        // Clones share the same time
+        let shared = clock.clone();
+        shared.advance_ms(1_000);
+        assert_eq!(clock.now_ms_u64(), 2_500);
+
+        clock.set_ms(10_000);
+        assert_eq!(clock.now_sec_u64(), 10);
+    }
+
+    #[test]
+    fn clock_with_offset_learns_skew() {
+        let clock = ClockWithOffset::default();
+        let now = now_sec_u64() as u32;
+
+        // Small difference is ignored
+        clock.observe_block_time(now + 3);
+        assert_eq!(clock.offset_ms(), 0);
+
+        // Local clock is one hour behind
+        clock.observe_block_time(now + 3600);
+        assert!((clock.offset_ms() - 3_600_000).abs() < 2_000);
+        assert!(clock.now_sec_u64().abs_diff(now as u64 + 3600) <= 2);
+
+        // Older blocks don't move the clock back
+        clock.observe_block_time(now);
+        assert!((clock.offset_ms() - 3_600_000).abs() < 2_000);
+    }
+
+    #[test]
+    fn clock_with_offset_ignores_lagging_node() {
+        let clock = ClockWithOffset::default();
+        let now = now_sec_u64() as u32;
+
+        clock.observe_block_time(now - 3600);
+        assert_eq!(clock.offset_ms(), 0);
+    }
+
+    #[test]
+    fn clock_with_offset_keeps_explicit_offset() {
+        let clock = ClockWithOffset::new(5_000);
+        let now = now_sec_u64() as u32;
+
+        clock.observe_block_time(now + 3600);
+        assert_eq!(clock.offset_ms(), 5_000);
+
+        let clock = ClockWithOffset::default();
+        clock.update_offset(-5_000);
+        clock.observe_block_time(now + 3600);
+        assert_eq!(clock.offset_ms(), -5_000);
+    }
 }
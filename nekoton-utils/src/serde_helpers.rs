@@ -672,6 +672,35 @@ pub mod serde_cell {
     }
 }
 
+pub mod serde_optional_cell {
+    use super::*;
+
+    pub fn serialize<S>(data: &Option<Cell>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(serde::Serialize)]
+        #[serde(transparent)]
+        struct Wrapper<'a>(#[serde(with = "serde_cell")] &'a Cell);
+
+        match data {
+            Some(data) => serializer.serialize_some(&Wrapper(data)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Cell>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(transparent)]
+        struct Wrapper(#[serde(with = "serde_cell")] Cell);
+
+        Option::<Wrapper>::deserialize(deserializer).map(|wrapper| wrapper.map(|data| data.0))
+    }
+}
+
 pub mod serde_ton_block {
     use ton_block::{Deserializable, Serializable};
 
@@ -56,6 +56,63 @@ pub fn compute_total_transaction_fees(
     total_fees
 }
 
+/// Fees charged from the account during the transaction
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct TransactionFees {
+    /// Fees collected in the storage phase
+    pub storage_fee: u128,
+    /// Fees for the computation in the compute phase
+    pub gas_fee: u128,
+    /// Fees for the actions (without forwarding fees)
+    pub action_fees: u128,
+    /// Fees for forwarding outgoing and bounced messages
+    pub fwd_fees: u128,
+    /// Total value attached to the outgoing internal messages
+    pub forwarded_value: u128,
+    /// Total fee, see [`compute_total_transaction_fees`]
+    pub total: u128,
+}
+
+/// Splits transaction fees by phases
+pub fn compute_transaction_fees(
+    transaction: &ton_block::Transaction,
+    description: &ton_block::TransactionDescrOrdinary,
+) -> TransactionFees {
+    let mut fees = TransactionFees {
+        total: compute_total_transaction_fees(transaction, description),
+        ..Default::default()
+    };
+
+    if let Some(phase) = &description.storage_ph {
+        fees.storage_fee = phase.storage_fees_collected.as_u128();
+    }
+    if let ton_block::TrComputePhase::Vm(phase) = &description.compute_ph {
+        fees.gas_fee = phase.gas_fees.as_u128();
+    }
+    if let Some(phase) = &description.action {
+        let grams = |grams: &Option<ton_block::Grams>| {
+            grams
+                .as_ref()
+                .map(|grams| grams.as_u128())
+                .unwrap_or_default()
+        };
+        fees.fwd_fees = grams(&phase.total_fwd_fees);
+        fees.action_fees = grams(&phase.total_action_fees);
+    }
+    if let Some(ton_block::TrBouncePhase::Ok(phase)) = &description.bounce {
+        fees.fwd_fees += phase.fwd_fees.as_u128();
+    }
+
+    let _ = transaction.out_msgs.iterate(|out_msg| {
+        if let ton_block::CommonMsgInfo::IntMsgInfo(header) = out_msg.0.header() {
+            fees.forwarded_value += header.value.grams.as_u128();
+        }
+        Ok(true)
+    });
+
+    fees
+}
+
 #[cfg(test)]
 mod tests {
     use ton_block::Deserializable;
@@ -68,4 +125,23 @@ mod tests {
         let balance_change = compute_balance_change(&tx);
         assert_eq!(balance_change, 0);
     }
+
+    #[test]
+    fn fees_for_bounce_tx() {
+        let tx = ton_block::Transaction::construct_from_base64("te6ccgECBwEAAXgAA7V7I6v9Bo6UZTcpUTDMPNHomt63V2qkcrrjqlh+9STZH1AAArX2P2tMMAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAZfQohAAD5gUWEIAwIBAB8ECQ7kyjgBwDAosIMFFhhAAIJykK7Illr6uxbrw8ubQI665xthjXh4i8gNCYQ1k8rJjaSQrsiWWvq7FuvDy5tAjrrnG2GNeHiLyA0JhDWTysmNpAIB4AYEAQHfBQC5WAFkdX+g0dKMpuUqJhmHmj0TW9bq7VSOV1x1Sw/epJsj6wAmfD7CYutxv9bl0y1a1XmYfSoPdQXCpsr6XdmJS4KcONDuLh8ABgosMAAAVr7H7WmIy+hRCH/////AALFoATPh9hMXW43+ty6ZatarzMPpUHuoLhU2V9LuzEpcFOHHACyOr/QaOlGU3KVEwzDzR6Jret1dqpHK646pYfvUk2R9UO5Mo4AGCiwwAABWvsftaYTL6FEIQA==").unwrap();
+        let description = match tx.description.read_struct().unwrap() {
+            ton_block::TransactionDescr::Ordinary(description) => description,
+            _ => panic!("ordinary transaction expected"),
+        };
+
+        let fees = compute_transaction_fees(&tx, &description);
+        assert_eq!(
+            fees.total,
+            compute_total_transaction_fees(&tx, &description)
+        );
+        assert_eq!(
+            fees.total,
+            tx.total_fees.grams.as_u128() + fees.fwd_fees - fees.action_fees
+        );
+    }
 }
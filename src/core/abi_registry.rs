@@ -0,0 +1,510 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use nekoton_abi::{make_abi_tokens, read_function_id};
+use nekoton_contracts::wallets;
+use ton_block::MsgAddressInt;
+use ton_types::{SliceData, UInt256};
+
+use crate::core::models::{CustomPayload, KnownPayload, Message, Transaction};
+use crate::core::parsing::parse_payload;
+use crate::core::ton_wallet::{ever_wallet, MultisigType};
+
+/// Functions and events of the contract, used to decode its messages
+#[derive(Default, Clone)]
+pub struct ContractAbi {
+    functions: Vec<ton_abi::Function>,
+    events: Vec<ton_abi::Event>,
+}
+
+impl ContractAbi {
+    pub fn new<F, E>(functions: F, events: E) -> Self
+    where
+        F: IntoIterator<Item = ton_abi::Function>,
+        E: IntoIterator<Item = ton_abi::Event>,
+    {
+        Self {
+            functions: functions.into_iter().collect(),
+            events: events.into_iter().collect(),
+        }
+    }
+
+    pub fn function_by_id(&self, id: u32) -> Option<&ton_abi::Function> {
+        self.functions
+            .iter()
+            .find(|function| function.input_id == id)
+    }
+
+    pub fn event_by_id(&self, id: u32) -> Option<&ton_abi::Event> {
+        self.events.iter().find(|event| event.id == id)
+    }
+
+    /// Adds the function, replacing the existing one with the same id
+    pub fn add_function(&mut self, function: ton_abi::Function) -> &mut Self {
+        self.functions
+            .retain(|item| item.input_id != function.input_id);
+        self.functions.push(function);
+        self
+    }
+
+    /// Decodes the function call from the message body
+    pub fn decode_call(&self, body: SliceData, internal: bool) -> Option<DecodedAbiMessage> {
+        let function = self.function_by_id(read_function_id(&body).ok()?)?;
+        let tokens = function.decode_input(body, internal, false).ok()?;
+        Some(DecodedAbiMessage {
+            name: function.name.clone(),
+            tokens,
+        })
+    }
+
+    /// Decodes the event from the external outgoing message body
+    pub fn decode_event(&self, body: SliceData) -> Option<DecodedAbiMessage> {
+        let event = self.event_by_id(read_function_id(&body).ok()?)?;
+        let tokens = event.decode_input(body).ok()?;
+        Some(DecodedAbiMessage {
+            name: event.name.clone(),
+            tokens,
+        })
+    }
+}
+
+impl From<&ton_abi::Contract> for ContractAbi {
+    fn from(contract: &ton_abi::Contract) -> Self {
+        Self::new(
+            contract.functions.values().cloned(),
+            contract.events.values().cloned(),
+        )
+    }
+}
+
+/// Contract ABIs, keyed by the code hash.
+///
+/// Address overrides have priority over the code hash, e.g. for the contracts
+/// with a shared code but a different interface.
+///
+/// Messages to unknown contracts are decoded with the first ABI which has the
+/// same function id, in the following order:
+/// 1. address overrides, in the order they were added;
+/// 2. functions, registered with [`AbiRegistry::register_function`];
+/// 3. code hash ABIs, ordered by the code hash.
+#[derive(Default, Clone)]
+pub struct AbiRegistry {
+    by_code_hash: BTreeMap<UInt256, Arc<ContractAbi>>,
+    by_address: Vec<(MsgAddressInt, Arc<ContractAbi>)>,
+    functions: ContractAbi,
+}
+
+impl AbiRegistry {
+    /// Creates the registry with the well-known wallet contracts
+    pub fn with_known_contracts() -> Self {
+        let mut registry = Self::default();
+
+        registry.add_code_hash(
+            UInt256::from(*ever_wallet::CODE_HASH),
+            ContractAbi::new(
+                [
+                    wallets::ever_wallet::send_transaction(),
+                    wallets::ever_wallet::send_transaction_raw_0(),
+                    wallets::ever_wallet::send_transaction_raw_1(),
+                    wallets::ever_wallet::send_transaction_raw_2(),
+                    wallets::ever_wallet::send_transaction_raw_3(),
+                    wallets::ever_wallet::send_transaction_raw_4(),
+                ]
+                .into_iter()
+                .cloned(),
+                [],
+            ),
+        );
+
+        let multisig = Arc::new(ContractAbi::new(
+            [
+                wallets::multisig::send_transaction(),
+                wallets::multisig::submit_transaction(),
+                wallets::multisig::confirm_transaction(),
+            ]
+            .into_iter()
+            .cloned(),
+            [],
+        ));
+        let multisig2 = Arc::new(ContractAbi::new(
+            [
+                wallets::multisig2::send_transaction(),
+                wallets::multisig2::submit_transaction(),
+                wallets::multisig2::confirm_transaction(),
+                wallets::multisig2::submit_update(),
+                wallets::multisig2::confirm_update(),
+                wallets::multisig2::execute_update(),
+            ]
+            .into_iter()
+            .cloned(),
+            [],
+        ));
+
+        for multisig_type in [
+            MultisigType::SafeMultisigWallet,
+            MultisigType::SafeMultisigWallet24h,
+            MultisigType::SetcodeMultisigWallet,
+            MultisigType::SetcodeMultisigWallet24h,
+            MultisigType::BridgeMultisigWallet,
+            MultisigType::SurfWallet,
+            MultisigType::Multisig2,
+            MultisigType::Multisig2_1,
+        ] {
+            let abi = match multisig_type.is_multisig2() {
+                true => multisig2.clone(),
+                false => multisig.clone(),
+            };
+            registry
+                .by_code_hash
+                .insert(UInt256::from(*multisig_type.code_hash()), abi);
+        }
+
+        registry
+    }
+
+    pub fn add_code_hash(&mut self, code_hash: UInt256, abi: ContractAbi) -> &mut Self {
+        self.by_code_hash.insert(code_hash, Arc::new(abi));
+        self
+    }
+
+    pub fn add_address(&mut self, address: MsgAddressInt, abi: ContractAbi) -> &mut Self {
+        let abi = Arc::new(abi);
+        match self
+            .by_address
+            .iter_mut()
+            .find(|(item, _)| item == &address)
+        {
+            Some((_, existing)) => *existing = abi,
+            None => self.by_address.push((address, abi)),
+        }
+        self
+    }
+
+    /// Registers function to decode messages to any contract with.
+    ///
+    /// Replaces the previously registered function with the same id
+    pub fn register_function(&mut self, function: ton_abi::Function) -> &mut Self {
+        self.functions.add_function(function);
+        self
+    }
+
+    /// Registers all contract functions, see [`AbiRegistry::register_function`]
+    pub fn register_contract(&mut self, contract: &ton_abi::Contract) -> &mut Self {
+        for function in contract.functions.values() {
+            self.functions.add_function(function.clone());
+        }
+        self
+    }
+
+    /// Finds the ABI of the contract by its address or code hash
+    pub fn get(
+        &self,
+        address: &MsgAddressInt,
+        code_hash: Option<&UInt256>,
+    ) -> Option<&ContractAbi> {
+        if let Some(abi) = self.get_by_address(address) {
+            return Some(abi);
+        }
+        code_hash
+            .and_then(|code_hash| self.by_code_hash.get(code_hash))
+            .map(Arc::as_ref)
+    }
+
+    fn get_by_address(&self, address: &MsgAddressInt) -> Option<&ContractAbi> {
+        self.by_address
+            .iter()
+            .find(|(item, _)| item == address)
+            .map(|(_, abi)| abi.as_ref())
+    }
+
+    /// Decodes the function call with the first known ABI which has the same function id
+    /// and accepts the body.
+    /// See [`AbiRegistry`] for the lookup order
+    pub fn decode_any_call(&self, body: SliceData, internal: bool) -> Option<DecodedAbiMessage> {
+        let function_id = read_function_id(&body).ok()?;
+        self.by_address
+            .iter()
+            .map(|(_, abi)| abi.as_ref())
+            .chain(std::iter::once(&self.functions))
+            .chain(self.by_code_hash.values().map(Arc::as_ref))
+            .filter(|abi| abi.function_by_id(function_id).is_some())
+            .find_map(|abi| abi.decode_call(body.clone(), internal))
+    }
+
+    /// Decodes the internal message body.
+    ///
+    /// Built-in payloads (comments, token transfers) have priority over the registered ABIs
+    pub fn parse_payload(&self, body: SliceData) -> Option<KnownPayload> {
+        if let Some(known_payload) = parse_payload(body.clone()) {
+            return Some(known_payload);
+        }
+
+        let DecodedAbiMessage { name, tokens } = self.decode_any_call(body, true)?;
+        Some(KnownPayload::Custom(CustomPayload {
+            name,
+            data: make_abi_tokens(&tokens).ok()?,
+            tokens,
+        }))
+    }
+
+    /// Decodes bodies of all outgoing messages of the transaction
+    pub fn parse_out_messages(&self, transaction: &Transaction) -> Vec<Option<KnownPayload>> {
+        transaction
+            .out_msgs
+            .iter()
+            .map(|message| self.parse_payload(message_body(message)?))
+            .collect()
+    }
+
+    fn decode_out_call(&self, dst: &MsgAddressInt, body: SliceData) -> Option<DecodedAbiMessage> {
+        match self.get_by_address(dst) {
+            Some(abi) => abi.decode_call(body, true),
+            None => self.decode_any_call(body, true),
+        }
+    }
+
+    /// Decodes all messages of the transaction.
+    ///
+    /// * `code_hash` - code hash of the transaction account
+    pub fn decode_transaction(
+        &self,
+        address: &MsgAddressInt,
+        code_hash: Option<&UInt256>,
+        transaction: &Transaction,
+    ) -> DecodedAbiTransaction {
+        let abi = self.get(address, code_hash);
+
+        let input = abi.and_then(|abi| {
+            let body = message_body(&transaction.in_msg)?;
+            abi.decode_call(body, transaction.in_msg.src.is_some())
+        });
+
+        let mut out_calls = Vec::with_capacity(transaction.out_msgs.len());
+        let mut events = Vec::new();
+        for message in &transaction.out_msgs {
+            let body = message_body(message);
+            match (&message.dst, body) {
+                (Some(dst), Some(body)) => out_calls.push(self.decode_out_call(dst, body)),
+                (None, Some(body)) => {
+                    out_calls.push(None);
+                    events.extend(abi.and_then(|abi| abi.decode_event(body)));
+                }
+                (_, None) => out_calls.push(None),
+            }
+        }
+
+        DecodedAbiTransaction {
+            input,
+            out_calls,
+            events,
+        }
+    }
+}
+
+/// Registry, bound to the subscribed contract
+pub(crate) struct AbiDecoder {
+    pub registry: Arc<AbiRegistry>,
+    pub address: MsgAddressInt,
+    pub code_hash: Option<UInt256>,
+}
+
+impl AbiDecoder {
+    pub fn decode(&self, transaction: &Transaction) -> DecodedAbiTransaction {
+        self.registry
+            .decode_transaction(&self.address, self.code_hash.as_ref(), transaction)
+    }
+}
+
+fn message_body(message: &Message) -> Option<SliceData> {
+    let body = message.body.as_ref()?;
+    SliceData::load_cell_ref(&body.data).ok()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedAbiMessage {
+    /// Function or event name
+    pub name: String,
+    pub tokens: Vec<ton_abi::Token>,
+}
+
+/// Transaction messages, decoded with the [`AbiRegistry`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodedAbiTransaction {
+    /// Function call of the incoming message
+    pub input: Option<DecodedAbiMessage>,
+    /// Function calls of the outgoing internal messages (one item per outgoing message)
+    pub out_calls: Vec<Option<DecodedAbiMessage>>,
+    /// Events, emitted by the contract
+    pub events: Vec<DecodedAbiMessage>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use nekoton_abi::{FunctionBuilder, TransactionId};
+    use ton_abi::{Token, TokenValue, Uint};
+
+    use super::*;
+    use crate::core::models::{AccountStatus, MessageBody};
+
+    fn make_function(name: &str, id: u32) -> ton_abi::Function {
+        FunctionBuilder::new(name)
+            .abi_version(ton_abi::contract::ABI_VERSION_2_2)
+            .id(id)
+            .input("value", ton_abi::ParamType::Uint(32))
+            .build()
+    }
+
+    fn make_body(function: &ton_abi::Function) -> ton_types::Cell {
+        function
+            .encode_internal_input(&[Token::new("value", TokenValue::Uint(Uint::new(123, 32)))])
+            .and_then(|builder| builder.into_cell())
+            .unwrap()
+    }
+
+    fn make_message(
+        src: Option<MsgAddressInt>,
+        dst: Option<MsgAddressInt>,
+        function: &ton_abi::Function,
+    ) -> Message {
+        let data = make_body(function);
+        Message {
+            src,
+            dst,
+            body: Some(MessageBody {
+                hash: data.repr_hash(),
+                data,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn make_address(byte: u8) -> MsgAddressInt {
+        MsgAddressInt::from_str(&format!("0:{}", hex::encode([byte; 32]))).unwrap()
+    }
+
+    #[test]
+    fn known_contracts() {
+        let registry = AbiRegistry::with_known_contracts();
+        let address = MsgAddressInt::default();
+
+        let ever_wallet = UInt256::from(*ever_wallet::CODE_HASH);
+        let abi = registry.get(&address, Some(&ever_wallet)).unwrap();
+        assert!(abi
+            .function_by_id(wallets::ever_wallet::send_transaction().input_id)
+            .is_some());
+
+        let multisig2 = UInt256::from(*MultisigType::Multisig2.code_hash());
+        let abi = registry.get(&address, Some(&multisig2)).unwrap();
+        assert!(abi
+            .function_by_id(wallets::multisig2::submit_update().input_id)
+            .is_some());
+
+        assert!(registry.get(&address, None).is_none());
+
+        // Address overrides have priority
+        let mut registry = registry;
+        registry.add_address(address.clone(), ContractAbi::default());
+        let abi = registry.get(&address, Some(&ever_wallet)).unwrap();
+        assert!(abi
+            .function_by_id(wallets::ever_wallet::send_transaction().input_id)
+            .is_none());
+    }
+
+    #[test]
+    fn parse_custom_payload() {
+        let function = make_function("setValue", 1);
+        let payload = SliceData::load_cell(make_body(&function)).unwrap();
+
+        let mut registry = AbiRegistry::default();
+        assert!(registry.parse_payload(payload.clone()).is_none());
+
+        registry.register_function(function);
+        let payload = match registry.parse_payload(payload) {
+            Some(KnownPayload::Custom(payload)) => payload,
+            _ => panic!("unexpected payload"),
+        };
+        assert_eq!(payload.name, "setValue");
+        assert_eq!(payload.data["value"], "123");
+        assert_eq!(payload.unpack::<(u32,)>().unwrap(), (123,));
+    }
+
+    #[test]
+    fn decode_transaction() {
+        let account = make_address(1);
+        let code_hash = UInt256::from([1; 32]);
+        let overridden = make_address(2);
+        let unknown = make_address(3);
+
+        let on_call = make_function("onCall", 1);
+        let by_address = make_function("byAddress", 2);
+        let early = make_function("early", 3);
+        let late = make_function("late", 3);
+        let custom = make_function("custom", 4);
+
+        let mut registry = AbiRegistry::default();
+        registry
+            .add_code_hash(code_hash, ContractAbi::new([on_call.clone()], []))
+            // NOTE: added first, but has a greater code hash
+            .add_code_hash(UInt256::from([3; 32]), ContractAbi::new([late], []))
+            .add_code_hash(
+                UInt256::from([2; 32]),
+                ContractAbi::new([early.clone()], []),
+            )
+            .add_address(
+                overridden.clone(),
+                ContractAbi::new([by_address.clone()], []),
+            )
+            .register_function(custom.clone());
+
+        let transaction = Transaction {
+            id: TransactionId {
+                lt: 1,
+                hash: Default::default(),
+            },
+            prev_trans_id: None,
+            created_at: 0,
+            aborted: false,
+            exit_code: Some(0),
+            result_code: Some(0),
+            orig_status: AccountStatus::Active,
+            end_status: AccountStatus::Active,
+            total_fees: 0,
+            in_msg: make_message(Some(unknown.clone()), Some(account.clone()), &on_call),
+            out_msgs: vec![
+                make_message(Some(account.clone()), Some(overridden), &by_address),
+                make_message(Some(account.clone()), Some(unknown.clone()), &early),
+                make_message(Some(account.clone()), Some(unknown), &custom),
+                Message {
+                    src: Some(account.clone()),
+                    ..Default::default()
+                },
+            ],
+            #[cfg(feature = "extended_models")]
+            raw: Default::default(),
+        };
+
+        let names = |decoded: &[Option<DecodedAbiMessage>]| {
+            decoded
+                .iter()
+                .map(|item| item.as_ref().map(|item| item.name.as_str()))
+                .collect::<Vec<_>>()
+        };
+
+        for _ in 0..10 {
+            let decoded = registry.decode_transaction(&account, Some(&code_hash), &transaction);
+            assert_eq!(decoded.input.unwrap().name, "onCall");
+            assert_eq!(
+                names(&decoded.out_calls),
+                [Some("byAddress"), Some("early"), Some("custom"), None]
+            );
+            assert!(decoded.events.is_empty());
+        }
+
+        // Unknown account
+        let decoded = registry.decode_transaction(&make_address(4), None, &transaction);
+        assert!(decoded.input.is_none());
+        assert_eq!(decoded.out_calls.len(), 4);
+    }
+}
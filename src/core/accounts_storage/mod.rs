@@ -9,11 +9,25 @@ use ton_block::MsgAddressInt;
 
 use nekoton_utils::*;
 
+use crate::core::storage_migrator::{set_version, MigrationStep, StorageSchema};
 use crate::core::ton_wallet;
 use crate::external::Storage;
+use crate::transport::Transport;
 
 pub const ACCOUNTS_STORAGE_KEY: &str = "__core__accounts";
 
+/// Versions of the stored accounts:
+/// - `0`: `{ "assets": { <address>: <assets list json> } }`
+/// - `1`: same with explicit `version`
+pub const ACCOUNTS_STORAGE_SCHEMA: StorageSchema = StorageSchema {
+    key: ACCOUNTS_STORAGE_KEY,
+    steps: &[migrate_accounts_v0 as MigrationStep],
+};
+
+fn migrate_accounts_v0(data: serde_json::Value) -> Result<serde_json::Value> {
+    set_version(data, 1)
+}
+
 const DEFAULT_NETWORK_GROUP: &str = "mainnet";
 
 pub struct AccountsStorage {
@@ -91,6 +105,7 @@ impl AccountsStorage {
                         contract: new_account.contract,
                     },
                     additional_assets: Default::default(),
+                    watch_only: new_account.watch_only,
                 })
                 .clone(),
         };
@@ -128,6 +143,7 @@ impl AccountsStorage {
                             contract: new_account.contract,
                         },
                         additional_assets: Default::default(),
+                        watch_only: new_account.watch_only,
                     })
                     .clone(),
             };
@@ -280,10 +296,12 @@ impl AccountsStorage {
 
         #[derive(Serialize)]
         struct StoredData<'a> {
+            version: u32,
             assets: StoredAssetsMap<'a>,
         }
 
         let data = serde_json::to_string(&StoredData {
+            version: ACCOUNTS_STORAGE_SCHEMA.current_version(),
             assets: StoredAssetsMap(assets),
         })
         .trust_me();
@@ -319,7 +337,8 @@ fn parse_assets_map(data: &str) -> Result<AssetsMap> {
         assets: StoredAssetsMap,
     }
 
-    Ok(serde_json::from_str::<StoredData>(data)?.assets.0)
+    let data = ACCOUNTS_STORAGE_SCHEMA.migrate(data)?;
+    Ok(serde_json::from_value::<StoredData>(data)?.assets.0)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -335,6 +354,37 @@ pub struct AccountToAdd {
         skip_serializing_if = "Option::is_none"
     )]
     pub explicit_address: Option<MsgAddressInt>,
+    /// Account without keystore entry, which can only be observed
+    #[serde(default)]
+    pub watch_only: bool,
+}
+
+impl AccountToAdd {
+    /// Creates a watch-only account for the existing wallet.
+    ///
+    /// See [`ton_wallet::get_existing_wallet_info`] to resolve it by address
+    pub fn watch_only(name: String, wallet: &ton_wallet::ExistingWalletInfo) -> Self {
+        Self {
+            name,
+            public_key: wallet.public_key,
+            contract: wallet.wallet_type,
+            workchain: wallet.address.workchain_id() as i8,
+            explicit_address: Some(wallet.address.clone()),
+            watch_only: true,
+        }
+    }
+
+    /// Creates a watch-only account from the address only.
+    ///
+    /// The public key and the wallet type are resolved from the deployed contract
+    pub async fn watch_only_by_address(
+        transport: &dyn Transport,
+        name: String,
+        address: MsgAddressInt,
+    ) -> Result<Self> {
+        let wallet = ton_wallet::get_existing_wallet_info(transport, address).await?;
+        Ok(Self::watch_only(name, &wallet))
+    }
 }
 
 #[derive(Debug)]
@@ -355,6 +405,9 @@ pub struct AssetsList {
 
     /// Additional assets, grouped by network group
     pub additional_assets: HashMap<NetworkGroup, AdditionalAssets>,
+
+    /// Account without keystore entry, which can only be observed
+    pub watch_only: bool,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -423,6 +476,8 @@ impl<'de> serde::Deserialize<'de> for AssetsList {
                 name: String,
                 ton_wallet: TonWalletAsset,
                 additional_assets: HashMap<String, AdditionalAssets>,
+                #[serde(default)]
+                watch_only: bool,
             },
         }
 
@@ -446,16 +501,19 @@ impl<'de> serde::Deserialize<'de> for AssetsList {
                     name,
                     ton_wallet,
                     additional_assets,
+                    watch_only: false,
                 }
             }
             ParsedAssetsList::New {
                 name,
                 ton_wallet,
                 additional_assets,
+                watch_only,
             } => AssetsList {
                 name,
                 ton_wallet,
                 additional_assets,
+                watch_only,
             },
         })
     }
@@ -489,3 +547,41 @@ enum AccountsStorageError {
     #[error("Account not found")]
     AccountNotFound,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_watch_only_flag() {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public_key = ed25519_dalek::PublicKey::from(&secret);
+        let wallet = serde_json::to_string(&TonWalletAsset {
+            address: ton_wallet::compute_address(
+                &public_key,
+                ton_wallet::WalletType::EverWallet,
+                0,
+            ),
+            public_key,
+            contract: ton_wallet::WalletType::EverWallet,
+        })
+        .unwrap();
+
+        let assets = format!(r#"{{"name":"test","ton_wallet":{wallet},"additional_assets":{{}}}}"#);
+        let assets = serde_json::from_str::<AssetsList>(&assets).unwrap();
+        assert!(!assets.watch_only);
+
+        let assets = format!(
+            r#"{{"name":"test","ton_wallet":{wallet},"additional_assets":{{}},"watch_only":true}}"#
+        );
+        let assets = serde_json::from_str::<AssetsList>(&assets).unwrap();
+        assert!(assets.watch_only);
+
+        let assets = serde_json::to_string(&assets).unwrap();
+        assert!(
+            serde_json::from_str::<AssetsList>(&assets)
+                .unwrap()
+                .watch_only
+        );
+    }
+}
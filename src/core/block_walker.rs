@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use ton_block::MsgAddressInt;
+use ton_types::UInt256;
+
+use super::utils::block_touched_accounts;
+use crate::transport::gql::GqlTransport;
+
+/// Walks the shard chain which contains the specified address
+/// and reports accounts touched in each new block.
+///
+/// Can be used instead of interval polling to refresh only
+/// subscriptions with new transactions, see [`SubscriptionManager::refresh_touched`]
+///
+/// [`SubscriptionManager::refresh_touched`]: super::subscription_manager::SubscriptionManager::refresh_touched
+pub struct BlockWalker {
+    transport: Arc<GqlTransport>,
+    address: MsgAddressInt,
+    current_block_id: String,
+}
+
+impl BlockWalker {
+    pub async fn new(transport: Arc<GqlTransport>, address: MsgAddressInt) -> Result<Self> {
+        let latest_block = transport.get_latest_block(&address).await?;
+        Ok(Self {
+            transport,
+            address,
+            current_block_id: latest_block.id,
+        })
+    }
+
+    pub fn current_block_id(&self) -> &str {
+        &self.current_block_id
+    }
+
+    /// Waits for the next block in the shard chain
+    pub async fn next_block(&mut self, timeout: Duration) -> Result<WalkedBlock> {
+        let block_id = self
+            .transport
+            .wait_for_next_block(&self.current_block_id, &self.address, timeout)
+            .await?;
+        let block = self.transport.get_block(&block_id).await?;
+        let workchain_id = block.read_info()?.shard().workchain_id();
+        let touched_accounts = block_touched_accounts(&block)?.into_iter().collect();
+
+        self.current_block_id = block_id;
+
+        Ok(WalkedBlock {
+            block,
+            workchain_id,
+            touched_accounts,
+        })
+    }
+}
+
+pub struct WalkedBlock {
+    pub block: ton_block::Block,
+    /// Workchain of the block shard
+    pub workchain_id: i32,
+    pub touched_accounts: HashSet<UInt256>,
+}
+
+impl WalkedBlock {
+    /// Whether the block contains transactions of the specified account
+    pub fn is_touched(&self, address: &MsgAddressInt) -> bool {
+        address.workchain_id() == self.workchain_id
+            && self.touched_accounts.contains(&UInt256::from_be_bytes(
+                &address.address().get_bytestring(0),
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn touched_accounts_in_workchain() {
+        let account = [0x11; 32];
+        let block = WalkedBlock {
+            block: Default::default(),
+            workchain_id: 0,
+            touched_accounts: [UInt256::from(account)].into_iter().collect(),
+        };
+
+        let address = |workchain: i32, account: [u8; 32]| {
+            MsgAddressInt::from_str(&format!("{workchain}:{}", hex::encode(account))).unwrap()
+        };
+        assert!(block.is_touched(&address(0, account)));
+        assert!(!block.is_touched(&address(-1, account)));
+        assert!(!block.is_touched(&address(0, [0x22; 32])));
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::Result;
+use ton_block::StoragePrices;
+use ton_executor::BlockchainConfig;
+
+use super::network_params::NetworkParams;
+
+/// Typed view of the commonly used blockchain config params.
+///
+/// Use [`CachedTransport::get_network_config`] to avoid fetching the config every time.
+/// Software capabilities are provided by [`Transport::get_capabilities`]
+///
+/// [`CachedTransport::get_network_config`]: crate::transport::cached::CachedTransport::get_network_config
+/// [`Transport::get_capabilities`]: crate::transport::Transport::get_capabilities
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    elector_params: ElectorParams,
+    storage_prices: Vec<StoragePrices>,
+    prices: NetworkParams,
+}
+
+impl NetworkConfig {
+    pub fn from_config(config: &BlockchainConfig) -> Result<Self> {
+        let raw_config = config.raw_config();
+
+        let elector_params = raw_config.elector_params()?;
+
+        let mut storage_prices = Vec::new();
+        raw_config.storage_prices()?.map.iterate(|price| {
+            storage_prices.push(price);
+            Ok(true)
+        })?;
+        storage_prices.sort_by_key(|price| price.utime_since);
+
+        Ok(Self {
+            elector_params: ElectorParams {
+                validators_elected_for: elector_params.validators_elected_for,
+                elections_start_before: elector_params.elections_start_before,
+                elections_end_before: elector_params.elections_end_before,
+                stake_held_for: elector_params.stake_held_for,
+            },
+            storage_prices,
+            prices: NetworkParams::from_config(config),
+        })
+    }
+
+    /// Elections timings (param 15)
+    pub fn elector_params(&self) -> &ElectorParams {
+        &self.elector_params
+    }
+
+    /// All storage prices (param 18), ordered by the activation time
+    pub fn storage_prices(&self) -> &[StoragePrices] {
+        &self.storage_prices
+    }
+
+    /// Storage prices which are active at the specified time
+    pub fn storage_prices_at(&self, utime: u32) -> Option<&StoragePrices> {
+        self.storage_prices
+            .iter()
+            .rev()
+            .find(|prices| prices.utime_since <= utime)
+    }
+
+    /// Gas prices (params 20/21) and message forwarding prices (params 24/25)
+    pub fn prices(&self) -> &NetworkParams {
+        &self.prices
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ElectorParams {
+    pub validators_elected_for: u32,
+    pub elections_start_before: u32,
+    pub elections_end_before: u32,
+    pub stake_held_for: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_default_config() {
+        let config = NetworkConfig::from_config(&BlockchainConfig::default()).unwrap();
+
+        assert!(config.elector_params().validators_elected_for > 0);
+        assert!(!config.storage_prices().is_empty());
+        assert_eq!(
+            config
+                .storage_prices_at(u32::MAX)
+                .map(|prices| prices.utime_since),
+            config
+                .storage_prices()
+                .last()
+                .map(|prices| prices.utime_since)
+        );
+    }
+}
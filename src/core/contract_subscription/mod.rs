@@ -1,18 +1,22 @@
 use std::sync::Arc;
 
 use super::models::{
-    ContractState, PendingTransaction, ReliableBehavior, TransactionsBatchInfo,
-    TransactionsBatchType,
+    BackfillState, BackfillStopCondition, ContractState, ContractUpgrade, NetworkCapabilities,
+    PendingTransaction, ReliableBehavior, TransactionsBatchInfo, TransactionsBatchType,
 };
 use super::{utils, PollingMethod};
 use anyhow::Result;
-use futures_util::StreamExt;
-use nekoton_abi::{Executor, LastTransactionId};
+use futures_util::{StreamExt, TryStreamExt};
+use nekoton_abi::{Executor, GenTimings, LastTransactionId};
 use nekoton_utils::*;
 use serde::{Deserialize, Serialize};
 use ton_block::{AccountStuff, MsgAddressInt};
+use ton_types::UInt256;
 
+use crate::core::models::Transaction;
+use crate::core::transactions_storage::TransactionsStorage;
 use crate::core::utils::{MessageContext, PendingTransactionsExt};
+use crate::crypto::SignatureId;
 use crate::transport::models::{RawContractState, RawTransaction};
 use crate::transport::Transport;
 
@@ -25,6 +29,10 @@ pub struct ContractSubscription {
     latest_known_lt: Option<u64>,
     pending_transactions: Vec<PendingTransaction>,
     transactions_synced: bool,
+    contract_upgrade: Option<ContractUpgrade>,
+    transactions_storage: Option<Arc<dyn TransactionsStorage>>,
+    /// Transactions from handled blocks, which will be stored on the next refresh
+    unsaved_transactions: Vec<RawTransaction>,
 }
 
 impl ContractSubscription {
@@ -43,6 +51,9 @@ impl ContractSubscription {
             latest_known_lt: None,
             pending_transactions: Vec::new(),
             transactions_synced: false,
+            contract_upgrade: None,
+            transactions_storage: None,
+            unsaved_transactions: Vec::new(),
         };
 
         result.transactions_synced = !result
@@ -73,6 +84,80 @@ impl ContractSubscription {
         Ok(result)
     }
 
+    /// Restores the subscription from the snapshot.
+    ///
+    /// Only the current contract state is requested, transactions
+    /// will be fetched starting from the latest known one
+    pub async fn restore(
+        clock: Arc<dyn Clock>,
+        transport: Arc<dyn Transport>,
+        snapshot: ContractSubscriptionSnapshot,
+        on_contract_state: OnContractState<'_>,
+    ) -> Result<Self> {
+        let mut contract_state = snapshot.contract_state;
+        contract_state.last_lt = snapshot.last_lt;
+
+        let mut result = Self {
+            clock,
+            transport,
+            address: snapshot.address,
+            contract_state,
+            latest_known_lt: snapshot.latest_known_lt,
+            pending_transactions: snapshot.pending_transactions,
+            transactions_synced: false,
+            contract_upgrade: None,
+            transactions_storage: None,
+            unsaved_transactions: Vec::new(),
+        };
+
+        result
+            .refresh_contract_state_impl(None, on_contract_state)
+            .await?;
+
+        // NOTE: there is nothing to fetch if no transactions appeared since the snapshot
+        result.transactions_synced = match result.contract_state.last_transaction_id {
+            Some(LastTransactionId::Exact(id)) => Some(id.lt) == result.latest_known_lt,
+            Some(LastTransactionId::Inexact { .. }) => false,
+            None => true,
+        };
+
+        Ok(result)
+    }
+
+    /// Returns the state, required to restore the subscription later
+    pub fn snapshot(&self) -> ContractSubscriptionSnapshot {
+        ContractSubscriptionSnapshot {
+            address: self.address.clone(),
+            contract_state: self.contract_state,
+            last_lt: self.contract_state.last_lt,
+            latest_known_lt: self.latest_known_lt,
+            pending_transactions: self.pending_transactions.clone(),
+        }
+    }
+
+    /// Returns the latest detected code hash change.
+    ///
+    /// NOTE: code changes are not visible in blocks, so they are
+    /// only detected when the full contract state is refreshed
+    pub fn contract_upgrade(&self) -> Option<&ContractUpgrade> {
+        self.contract_upgrade.as_ref()
+    }
+
+    /// Returns the latest detected code hash change and resets it.
+    ///
+    /// Owners should call it after each refresh or handled block
+    /// to re-derive the contract-specific data (e.g. wallet type or token version).
+    /// Use [`ContractSubscription::contract_upgrade`] if handling the upgrade can fail
+    pub fn take_contract_upgrade(&mut self) -> Option<ContractUpgrade> {
+        self.contract_upgrade.take()
+    }
+
+    /// Sets the storage for all transactions found by this subscription
+    pub fn set_transactions_storage(&mut self, storage: Option<Arc<dyn TransactionsStorage>>) {
+        self.transactions_storage = storage;
+        self.unsaved_transactions.clear();
+    }
+
     pub fn transport(&self) -> &Arc<dyn Transport> {
         &self.transport
     }
@@ -113,6 +198,20 @@ impl ContractSubscription {
         }
     }
 
+    /// Software capabilities of the network.
+    ///
+    /// NOTE: message builders don't use them, see [`TonWallet::get_capabilities`]
+    ///
+    /// [`TonWallet::get_capabilities`]: crate::core::ton_wallet::TonWallet::get_capabilities
+    pub async fn get_capabilities(&self) -> Result<NetworkCapabilities> {
+        self.transport.get_capabilities(self.clock.as_ref()).await
+    }
+
+    /// Signature id which must be used to sign external messages
+    pub async fn get_signature_id(&self) -> Result<Option<SignatureId>> {
+        self.transport.get_signature_id(self.clock.as_ref()).await
+    }
+
     pub fn add_pending_transaction(&mut self, pending_transaction: PendingTransaction) {
         self.pending_transactions.push(pending_transaction);
     }
@@ -156,6 +255,14 @@ impl ContractSubscription {
         // optimistic prediction, that there were at most N new transactions
         const INITIAL_TRANSACTION_COUNT: u8 = 4;
 
+        if !self.unsaved_transactions.is_empty() {
+            let unsaved = std::mem::take(&mut self.unsaved_transactions);
+            if let Err(e) = self.store_transactions(&unsaved).await {
+                self.unsaved_transactions = unsaved;
+                return Err(e);
+            }
+        }
+
         // NOTE: refresh transactions every time state changes, or there are
         // new transactions, which we still need to receive (e.g. state has new
         // last_transaction_id, but the last known transaction is not equal to id)
@@ -200,6 +307,10 @@ impl ContractSubscription {
 
         let mut new_account_state = None;
         if let Some((account_state, new_transactions)) = block.data {
+            // NOTE: `last_lt` is kept, so the next refresh will poll the full state
+            // and notify the owner (the block contains only transactions)
+            self.contract_state.balance = account_state.balance;
+            self.contract_state.gen_timings = account_state.gen_timings;
             new_account_state = Some(account_state);
 
             if let Some((mut new_transactions, batch_info)) = new_transactions {
@@ -208,6 +319,10 @@ impl ContractSubscription {
 
                 if let Some(first) = new_transactions.first() {
                     self.latest_known_lt = Some(first.data.lt);
+                    if self.transactions_storage.is_some() {
+                        self.unsaved_transactions
+                            .extend(new_transactions.iter().cloned());
+                    }
                     on_transactions_found(new_transactions, batch_info);
                 }
             }
@@ -238,6 +353,34 @@ impl ContractSubscription {
         )
     }
 
+    /// Emulates the message execution and splits the fees by phases
+    pub async fn estimate_fees_detailed(
+        &self,
+        message: &ton_block::Message,
+    ) -> Result<TransactionFees> {
+        let transaction = self
+            .execute_transaction_locally(
+                message,
+                TransactionExecutionOptions {
+                    disable_signature_check: true,
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        Ok(
+            if let ton_block::TransactionDescr::Ordinary(descr) = transaction.read_description()? {
+                compute_transaction_fees(&transaction, &descr)
+            } else {
+                let total = transaction.total_fees.grams.as_u128();
+                TransactionFees {
+                    total,
+                    ..Default::default()
+                }
+            },
+        )
+    }
+
     pub async fn execute_transaction_locally(
         &self,
         message: &ton_block::Message,
@@ -319,6 +462,8 @@ impl ContractSubscription {
         }
         drop(transactions);
 
+        self.store_transactions(&new_transactions).await?;
+
         if let (Some(first), Some(last)) = (new_transactions.first(), new_transactions.last()) {
             // Transactions in response are in descending order
             let max_lt = first.data.lt;
@@ -376,6 +521,8 @@ impl ContractSubscription {
             )
             .await?;
 
+        self.store_transactions(&transactions).await?;
+
         if let (Some(first), Some(last)) = (transactions.first(), transactions.last()) {
             let batch_info = TransactionsBatchInfo {
                 min_lt: last.data.lt, // transactions in response are in descending order
@@ -389,6 +536,89 @@ impl ContractSubscription {
         Ok(())
     }
 
+    /// Loads older transactions page by page until one of the conditions is met
+    /// or the first transaction of the account is reached.
+    ///
+    /// The `state` is updated after each page, so the backfill can be interrupted
+    /// (e.g. by dropping the future) and resumed later with the same state.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_match` - filter for the transactions counted by [`BackfillStopCondition::Found`]
+    /// * `on_progress` - called after each page with the updated state
+    pub async fn backfill_transactions(
+        &self,
+        state: &mut BackfillState,
+        conditions: &[BackfillStopCondition],
+        is_match: &mut (dyn FnMut(&RawTransaction) -> bool + Send + Sync),
+        on_transactions_found: OnTransactionsFound<'_>,
+        on_progress: &mut (dyn FnMut(&BackfillState) + Send + Sync),
+    ) -> Result<()> {
+        if is_enough_found(state.found, conditions) {
+            state.next_lt = None;
+        }
+
+        while let Some(from_lt) = state.next_lt {
+            let transactions = self
+                .transport
+                .get_transactions(
+                    &self.address,
+                    from_lt,
+                    self.transport.info().max_transactions_per_fetch,
+                )
+                .await?;
+
+            let transactions = apply_backfill_page(state, conditions, transactions, is_match);
+            self.store_transactions(&transactions).await?;
+
+            if let (Some(first), Some(last)) = (transactions.first(), transactions.last()) {
+                let batch_info = TransactionsBatchInfo {
+                    min_lt: last.data.lt,
+                    max_lt: first.data.lt,
+                    batch_type: TransactionsBatchType::Old,
+                };
+                on_transactions_found(transactions, batch_info);
+            }
+
+            on_progress(state);
+        }
+
+        Ok(())
+    }
+
+    /// Streams transactions since the specified lt in descending order.
+    ///
+    /// Pages of `page_size` transactions are requested only when the previous one
+    /// was consumed, so the history can be rendered incrementally.
+    /// Dropping the stream cancels the loading
+    ///
+    /// # Arguments
+    ///
+    /// * `from_lt` - lt of the first transaction to load (inclusive)
+    /// * `until_lt` - lt of the last known transaction (exclusive)
+    /// * `page_size` - number of transactions requested at once
+    pub fn transactions_stream(
+        &self,
+        from_lt: u64,
+        until_lt: Option<u64>,
+        page_size: u8,
+    ) -> TransactionsStream<RawTransaction> {
+        let transactions = utils::request_transactions_owned(
+            self.transport.clone(),
+            self.address.clone(),
+            from_lt,
+            until_lt,
+            page_size,
+        )
+        .map_ok(|page| futures_util::stream::iter(page.into_iter().map(Ok)))
+        .try_flatten();
+
+        #[cfg(not(feature = "non_threadsafe"))]
+        return transactions.boxed();
+        #[cfg(feature = "non_threadsafe")]
+        return transactions.boxed_local();
+    }
+
     async fn refresh_contract_state_impl(
         &mut self,
         prev_trans_lt: Option<u64>,
@@ -403,6 +633,7 @@ impl ContractSubscription {
                 match poll.to_changed() {
                     Ok(new_state) => new_state,
                     Err(timings) => {
+                        self.observe_timings(&timings);
                         self.contract_state.gen_timings = timings;
                         return Ok(false);
                     }
@@ -411,6 +642,7 @@ impl ContractSubscription {
             None => self.transport.get_contract_state(&self.address).await?,
         };
         let new_contract_state = contract_state.brief();
+        self.observe_timings(&new_contract_state.gen_timings);
 
         let updated = if let Some(last_lt) = prev_trans_lt {
             new_contract_state.last_lt > last_lt
@@ -428,6 +660,7 @@ impl ContractSubscription {
             }
 
             on_contract_state(&mut contract_state);
+            self.detect_contract_upgrade(new_contract_state.code_hash);
             self.contract_state = new_contract_state;
             self.transactions_synced = false;
         } else {
@@ -438,6 +671,40 @@ impl ContractSubscription {
         Ok(updated)
     }
 
+    async fn store_transactions(&self, transactions: &[RawTransaction]) -> Result<()> {
+        let storage = match &self.transactions_storage {
+            Some(storage) if !transactions.is_empty() => storage,
+            _ => return Ok(()),
+        };
+
+        // NOTE: transactions with unsupported descriptions (e.g. tick-tock) are skipped
+        let transactions = transactions
+            .iter()
+            .filter_map(|transaction| {
+                Transaction::try_from((transaction.hash, transaction.data.clone())).ok()
+            })
+            .collect::<Vec<_>>();
+
+        storage
+            .add_transactions(&self.address, &transactions)
+            .await?;
+        Ok(())
+    }
+
+    fn detect_contract_upgrade(&mut self, new_code_hash: Option<UInt256>) {
+        if let Some(upgrade) = ContractUpgrade::detect(self.contract_state.code_hash, new_code_hash)
+        {
+            self.contract_upgrade = Some(upgrade);
+        }
+    }
+
+    /// Lets the clock learn its skew from the block time
+    fn observe_timings(&self, timings: &GenTimings) {
+        if let GenTimings::Known { gen_utime, .. } = timings {
+            self.clock.observe_block_time(*gen_utime);
+        }
+    }
+
     /// Searches executed pending transactions and notifies the handler if some were found
     fn check_executed_transactions(
         &mut self,
@@ -467,6 +734,13 @@ impl ContractSubscription {
         self.pending_transactions.retain(|pending| {
             let expired = current_utime > pending.expire_at;
             if expired {
+                #[cfg(feature = "instrumentation")]
+                {
+                    tracing::info!(address = %self.address, expire_at = pending.expire_at, "message expired");
+                    if let Some(metrics) = crate::transport::metrics::transport_metrics() {
+                        metrics.on_message_expired(&self.address);
+                    }
+                }
                 on_message_expired(pending.clone());
             }
             !expired
@@ -474,12 +748,88 @@ impl ContractSubscription {
     }
 }
 
+/// Lazily loaded transactions, see [`ContractSubscription::transactions_stream`]
+#[cfg(not(feature = "non_threadsafe"))]
+pub type TransactionsStream<T> = futures_util::stream::BoxStream<'static, Result<T>>;
+/// Lazily loaded transactions, see [`ContractSubscription::transactions_stream`]
+#[cfg(feature = "non_threadsafe")]
+pub type TransactionsStream<T> = futures_util::stream::LocalBoxStream<'static, Result<T>>;
+
 type OnContractState<'a> = &'a mut (dyn FnMut(&RawContractState) + Send + Sync);
+/// Filters the loaded page by the stop conditions and advances the backfill state
+fn apply_backfill_page(
+    state: &mut BackfillState,
+    conditions: &[BackfillStopCondition],
+    mut transactions: Vec<RawTransaction>,
+    is_match: &mut (dyn FnMut(&RawTransaction) -> bool + Send + Sync),
+) -> Vec<RawTransaction> {
+    let from_lt = state.next_lt.unwrap_or_default();
+
+    transactions.sort_by_key(|tx| std::cmp::Reverse(tx.data.lt));
+    transactions.retain(|tx| tx.data.lt <= from_lt);
+
+    let mut next_lt = transactions
+        .last()
+        .map(|tx| tx.data.prev_trans_lt)
+        .filter(|&lt| lt != 0);
+
+    let mut len = 0;
+    for tx in &transactions {
+        let reached = conditions.iter().any(|condition| match condition {
+            BackfillStopCondition::UntilLt(lt) => tx.data.lt <= *lt,
+            BackfillStopCondition::UntilUtime(utime) => tx.data.now < *utime,
+            BackfillStopCondition::Found(_) => false,
+        });
+        if reached {
+            next_lt = None;
+            break;
+        }
+
+        len += 1;
+        if is_match(tx) {
+            state.found += 1;
+            if is_enough_found(state.found, conditions) {
+                next_lt = None;
+                break;
+            }
+        }
+    }
+    transactions.truncate(len);
+
+    state.next_lt = next_lt;
+    state.loaded += len;
+    if let Some(last) = transactions.last() {
+        state.oldest_utime = Some(last.data.now);
+    }
+
+    transactions
+}
+
+fn is_enough_found(found: usize, conditions: &[BackfillStopCondition]) -> bool {
+    conditions.iter().any(|condition| match condition {
+        BackfillStopCondition::Found(count) => found >= *count,
+        _ => false,
+    })
+}
+
 type OnTransactionsFound<'a> =
     &'a mut (dyn FnMut(Vec<RawTransaction>, TransactionsBatchInfo) + Send + Sync);
 type OnMessageSent<'a> = &'a mut (dyn FnMut(PendingTransaction, RawTransaction) + Send + Sync);
 type OnMessageExpired<'a> = &'a mut (dyn FnMut(PendingTransaction) + Send + Sync);
 
+/// Serializable state of the [`ContractSubscription`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractSubscriptionSnapshot {
+    #[serde(with = "serde_address")]
+    pub address: MsgAddressInt,
+    pub contract_state: ContractState,
+    /// Lt of the contract state (not serialized as a part of it)
+    pub last_lt: u64,
+    pub latest_known_lt: Option<u64>,
+    pub pending_transactions: Vec<PendingTransaction>,
+}
+
 #[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(default, rename_all = "camelCase")]
 pub struct TransactionExecutionOptions {
@@ -492,6 +842,112 @@ pub struct TransactionExecutionOptions {
 mod tests {
     use super::*;
 
+    fn make_transaction(lt: u64, prev_trans_lt: u64, now: u32) -> RawTransaction {
+        let mut data = ton_block::Transaction::default();
+        data.lt = lt;
+        data.prev_trans_lt = prev_trans_lt;
+        data.now = now;
+        RawTransaction {
+            hash: Default::default(),
+            data,
+        }
+    }
+
+    #[test]
+    fn backfill_stop_conditions() {
+        let page = || {
+            vec![
+                make_transaction(30, 20, 300),
+                make_transaction(40, 30, 400),
+                make_transaction(20, 10, 200),
+            ]
+        };
+
+        // Continues from the oldest transaction of the page
+        let mut state = BackfillState::new(40);
+        let loaded = apply_backfill_page(&mut state, &[], page(), &mut |_| true);
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[0].data.lt, 40);
+        assert_eq!(state.next_lt, Some(10));
+        assert_eq!(state.oldest_utime, Some(200));
+
+        // Stops before the old transactions
+        let mut state = BackfillState::new(40);
+        let loaded = apply_backfill_page(
+            &mut state,
+            &[BackfillStopCondition::UntilUtime(250)],
+            page(),
+            &mut |_| true,
+        );
+        assert_eq!(loaded.len(), 2);
+        assert!(state.is_finished());
+
+        let mut state = BackfillState::new(40);
+        let loaded = apply_backfill_page(
+            &mut state,
+            &[BackfillStopCondition::UntilLt(30)],
+            page(),
+            &mut |_| true,
+        );
+        assert_eq!(loaded.len(), 1);
+        assert!(state.is_finished());
+
+        // Stops after the required number of matching transactions
+        let mut state = BackfillState::new(40);
+        let loaded = apply_backfill_page(
+            &mut state,
+            &[BackfillStopCondition::Found(1)],
+            page(),
+            &mut |tx| tx.data.lt == 30,
+        );
+        assert_eq!(loaded.len(), 2);
+        assert_eq!((state.loaded, state.found), (2, 1));
+        assert!(state.is_finished());
+
+        // The first transaction of the account
+        let mut state = BackfillState::new(10);
+        let loaded = apply_backfill_page(
+            &mut state,
+            &[],
+            vec![make_transaction(10, 0, 100)],
+            &mut |_| true,
+        );
+        assert_eq!(loaded.len(), 1);
+        assert!(state.is_finished());
+    }
+
+    #[test]
+    fn snapshot_serialization() {
+        let snapshot = ContractSubscriptionSnapshot {
+            address: MsgAddressInt::default(),
+            contract_state: ContractState {
+                balance: 100,
+                last_transaction_id: Some(LastTransactionId::Exact(nekoton_abi::TransactionId {
+                    lt: 123,
+                    hash: Default::default(),
+                })),
+                is_deployed: true,
+                ..Default::default()
+            },
+            last_lt: 124,
+            latest_known_lt: Some(123),
+            pending_transactions: vec![PendingTransaction {
+                message_hash: Default::default(),
+                src: None,
+                latest_lt: 123,
+                created_at: 1000,
+                expire_at: 1060,
+            }],
+        };
+
+        let serialized = serde_json::to_string(&snapshot).unwrap();
+        let restored: ContractSubscriptionSnapshot = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(restored.contract_state, snapshot.contract_state);
+        assert_eq!(restored.last_lt, 124);
+        assert_eq!(restored.pending_transactions, snapshot.pending_transactions);
+        assert_eq!(serde_json::to_string(&restored).unwrap(), serialized);
+    }
+
     #[test]
     fn executor_params_serialization() {
         assert_eq!(
@@ -528,4 +984,344 @@ mod tests {
             }
         );
     }
+
+    #[tokio::test]
+    async fn found_transactions_are_stored() {
+        use crate::core::transactions_storage::{InMemoryTransactionsStorage, TransactionsFilter};
+        use crate::transport::tests::MockTransport;
+        use ton_block::Deserializable;
+
+        let address = MsgAddressInt::default();
+        let data = ton_block::Transaction::construct_from_base64("te6ccgECCAEAAa0AA7V6khRTRyNmt/7uwVMjqWtdzxcZfIjcDUV436UpALijPLAAALi67rDsNGkd3DyaHde6qGSNyU7rxIrKKUFCg2XCiOWm8qj/wgcwAAC4uu6w7BYG3S5AABRh6EgIBQQBAhUECQ7msoAYYehIEQMCAFvAAAAAAAAAAAAAAAABLUUtpEnlC4z33SeGHxRhIq/htUa7i3D8ghbwxhQTn44EAJwnzD0JAAAAAAAAAAAAAwAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAgnI6oOa5hnaAmb92gCQ5BlaDDjAmDoH5UvSJNuK95TfvcajtZ/eBLgOpVpjmuIPSjgFKd0RzU/MXZ1uRuop7DU5HAQGgBgG5aAFSQopo5GzW/93YKmR1LWu54uMvkRuBqK8b9KUgFxRnlwAqSFFNHI2a3/u7BUyOpa13PFxl8iNwNRXjfpSkAuKM8tDuaygABhnMOAAAFxdd1h2EwNulyAAAAABABwAqaSBsb3ZlIG1lbWVzIGFuZCDwn6aA").unwrap();
+        let lt = data.lt;
+
+        let transport = Arc::new(MockTransport::new());
+        transport.add_transaction(
+            address.clone(),
+            RawTransaction {
+                hash: UInt256::from_be_bytes(&lt.to_be_bytes()),
+                data,
+            },
+        );
+
+        let mut subscription = ContractSubscription::subscribe(
+            Arc::new(SimpleClock),
+            transport,
+            address.clone(),
+            &mut |_| {},
+            None,
+        )
+        .await
+        .unwrap();
+
+        let storage = Arc::new(InMemoryTransactionsStorage::default());
+        subscription.set_transactions_storage(Some(storage.clone()));
+
+        let mut found = 0;
+        subscription
+            .preload_transactions(u64::MAX, &mut |transactions, _| found += transactions.len())
+            .await
+            .unwrap();
+        assert_eq!(found, 1);
+
+        let stored = storage
+            .get_transactions(&address, &TransactionsFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id.lt, lt);
+    }
+
+    #[tokio::test]
+    async fn refresh_detects_contract_upgrade() {
+        use crate::transport::models::ExistingContract;
+        use crate::transport::tests::MockTransport;
+
+        fn make_state(code: u32, lt: u64) -> RawContractState {
+            let mut builder = ton_types::BuilderData::new();
+            builder.append_u32(code).unwrap();
+
+            RawContractState::Exists(ExistingContract {
+                account: AccountStuff {
+                    storage: ton_block::AccountStorage {
+                        last_trans_lt: lt,
+                        state: ton_block::AccountState::AccountActive {
+                            state_init: ton_block::StateInit {
+                                code: Some(builder.into_cell().unwrap()),
+                                ..Default::default()
+                            },
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                timings: GenTimings::Unknown,
+                last_transaction_id: LastTransactionId::Inexact { latest_lt: lt },
+            })
+        }
+
+        let address = MsgAddressInt::default();
+        let transport = Arc::new(MockTransport::new());
+        transport.set_contract_state(address.clone(), make_state(1, 10));
+
+        let mut subscription = ContractSubscription::subscribe(
+            Arc::new(SimpleClock),
+            transport.clone(),
+            address.clone(),
+            &mut |_| {},
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(subscription.contract_upgrade().is_none());
+
+        let old_code_hash = subscription.contract_state().code_hash.unwrap();
+        let new_state = make_state(2, 20);
+        let new_code_hash = new_state.brief().code_hash.unwrap();
+        transport.set_contract_state(address, new_state);
+
+        subscription
+            .refresh(&mut |_| {}, &mut |_, _| {}, &mut |_, _| {}, &mut |_| {})
+            .await
+            .unwrap();
+
+        // The upgrade is kept until it is taken
+        let upgrade = *subscription.contract_upgrade().unwrap();
+        assert_eq!(upgrade.old_code_hash, old_code_hash);
+        assert_eq!(upgrade.new_code_hash, new_code_hash);
+        assert_eq!(subscription.take_contract_upgrade(), Some(upgrade));
+        assert!(subscription.contract_upgrade().is_none());
+
+        // Nothing changes without a new code
+        subscription
+            .refresh(&mut |_| {}, &mut |_, _| {}, &mut |_, _| {}, &mut |_| {})
+            .await
+            .unwrap();
+        assert!(subscription.take_contract_upgrade().is_none());
+    }
+
+    #[tokio::test]
+    async fn transactions_stream_pages() {
+        use crate::transport::tests::MockTransport;
+
+        let address = MsgAddressInt::default();
+        let transport = Arc::new(MockTransport::new());
+        for lt in (10..=50).step_by(10) {
+            transport.add_transaction(address.clone(), make_transaction(lt, lt - 10, 0));
+        }
+
+        let subscription = ContractSubscription::subscribe(
+            Arc::new(SimpleClock),
+            transport,
+            address,
+            &mut |_| {},
+            None,
+        )
+        .await
+        .unwrap();
+
+        let load = |from_lt: u64, until_lt: Option<u64>| {
+            subscription
+                .transactions_stream(from_lt, until_lt, 2)
+                .map_ok(|transaction| transaction.data.lt)
+                .try_collect::<Vec<_>>()
+        };
+
+        assert_eq!(load(40, Some(10)).await.unwrap(), [40, 30, 20]);
+        assert_eq!(load(u64::MAX, None).await.unwrap(), [50, 40, 30, 20, 10]);
+        assert!(load(10, Some(10)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn refresh_resolves_pending_messages() {
+        use crate::transport::models::ExistingContract;
+        use crate::transport::tests::MockTransport;
+        use ton_block::Serializable;
+
+        fn make_state(lt: u64) -> RawContractState {
+            RawContractState::Exists(ExistingContract {
+                account: AccountStuff {
+                    storage: ton_block::AccountStorage {
+                        last_trans_lt: lt,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                timings: GenTimings::Unknown,
+                last_transaction_id: LastTransactionId::Inexact { latest_lt: lt },
+            })
+        }
+
+        let clock = MockClock::new(1_000_000_000);
+        let address = MsgAddressInt::default();
+        let transport = Arc::new(MockTransport::new());
+        transport.set_contract_state(address.clone(), make_state(10));
+
+        let mut subscription = ContractSubscription::subscribe(
+            Arc::new(clock.clone()),
+            transport.clone(),
+            address.clone(),
+            &mut |_| {},
+            None,
+        )
+        .await
+        .unwrap();
+
+        let make_message = |created_at: u32| {
+            let mut message =
+                ton_block::Message::with_ext_in_header(ton_block::ExternalInboundMessageHeader {
+                    dst: address.clone(),
+                    ..Default::default()
+                });
+            let mut body = ton_types::BuilderData::new();
+            body.append_u32(created_at).unwrap();
+            message.set_body(ton_types::SliceData::load_builder(body).unwrap());
+            message
+        };
+
+        let now = clock.now_sec_u64() as u32;
+        let delivered = make_message(now);
+        let expired = make_message(now + 1);
+        subscription.send(&delivered, now + 60).await.unwrap();
+        subscription.send(&expired, now + 60).await.unwrap();
+        assert_eq!(transport.take_sent_messages().len(), 2);
+        assert_eq!(subscription.polling_method(), PollingMethod::Reliable);
+
+        // The first message is included into the new transaction
+        let mut data = ton_block::Transaction::default();
+        data.lt = 20;
+        data.now = now + 5;
+        data.write_in_msg(Some(&delivered)).unwrap();
+        transport.add_transaction(
+            address.clone(),
+            RawTransaction {
+                hash: UInt256::from([20; 32]),
+                data,
+            },
+        );
+        transport.set_contract_state(address.clone(), make_state(21));
+
+        let mut found = Vec::new();
+        let mut sent = Vec::new();
+        let mut expired_messages = Vec::new();
+        subscription
+            .refresh(
+                &mut |_| {},
+                &mut |transactions, batch_info| {
+                    assert_eq!(batch_info.batch_type, TransactionsBatchType::New);
+                    found.extend(transactions.into_iter().map(|tx| tx.data.lt));
+                },
+                &mut |pending, _| sent.push(pending.message_hash),
+                &mut |pending| expired_messages.push(pending.message_hash),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(found, [20]);
+        assert_eq!(sent, [delivered.serialize().unwrap().repr_hash()]);
+        assert!(expired_messages.is_empty());
+        assert_eq!(subscription.pending_transactions().len(), 1);
+
+        // The second message expires without new transactions
+        clock.advance_ms(120_000);
+        subscription
+            .refresh(
+                &mut |_| {},
+                &mut |_, _| panic!("no new transactions"),
+                &mut |_, _| panic!("no sent messages"),
+                &mut |pending| expired_messages.push(pending.message_hash),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(expired_messages, [expired.serialize().unwrap().repr_hash()]);
+        assert!(subscription.pending_transactions().is_empty());
+        assert_eq!(subscription.polling_method(), PollingMethod::Manual);
+    }
+
+    #[tokio::test]
+    async fn refresh_after_block_updates_state() {
+        use ton_block::{Serializable, ShardAccountBlocks};
+
+        use crate::transport::models::ExistingContract;
+        use crate::transport::tests::MockTransport;
+
+        fn make_state(lt: u64) -> RawContractState {
+            RawContractState::Exists(ExistingContract {
+                account: AccountStuff {
+                    storage: ton_block::AccountStorage {
+                        last_trans_lt: lt,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                timings: GenTimings::Unknown,
+                last_transaction_id: LastTransactionId::Inexact { latest_lt: lt },
+            })
+        }
+
+        let address = MsgAddressInt::default();
+        let transport = Arc::new(MockTransport::new());
+        transport.set_contract_state(address.clone(), make_state(10));
+
+        let mut subscription = ContractSubscription::subscribe(
+            Arc::new(SimpleClock),
+            transport.clone(),
+            address.clone(),
+            &mut |_| {},
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Block with the new account transaction
+        let mut transaction = ton_block::Transaction::default();
+        transaction.account_addr = address.address();
+        transaction.lt = 20;
+        transaction.prev_trans_lt = 10;
+
+        let mut account_blocks = ShardAccountBlocks::default();
+        account_blocks
+            .add_serialized_transaction(&transaction, &transaction.serialize().unwrap())
+            .unwrap();
+        let mut extra = ton_block::BlockExtra::default();
+        extra.write_account_blocks(&account_blocks).unwrap();
+        let block = ton_block::Block::with_params(
+            0,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            extra,
+        )
+        .unwrap();
+
+        let mut found = 0;
+        let new_state = subscription
+            .handle_block(
+                &block,
+                &mut |txs, _| found += txs.len(),
+                &mut |_, _| {},
+                &mut |_| {},
+            )
+            .unwrap();
+        assert_eq!(found, 1);
+        assert_eq!(new_state.unwrap().last_lt, 21);
+        assert_eq!(subscription.contract_state().last_lt, 10);
+
+        // The next refresh still receives the full state
+        transport.set_contract_state(address, make_state(21));
+
+        let mut state_handled = false;
+        subscription
+            .refresh(
+                &mut |_| state_handled = true,
+                &mut |_, _| {},
+                &mut |_, _| {},
+                &mut |_| {},
+            )
+            .await
+            .unwrap();
+        assert!(state_handled);
+        assert_eq!(subscription.contract_state().last_lt, 21);
+    }
 }
@@ -157,33 +157,65 @@ impl DensTld {
 
     pub async fn try_resolve_contract_address(&self, path: &str) -> Result<Option<MsgAddressInt>> {
         match self.get_domain_state(path).await? {
-            Some(domain) => dens::DomainContract(domain.as_context(self.clock.as_ref()))
-                .query::<dens::TargetAddressRecord>(),
-            None => Ok(None),
+            DomainState::Active(domain) => {
+                dens::DomainContract(domain.as_context(self.clock.as_ref()))
+                    .query::<dens::TargetAddressRecord>()
+            }
+            DomainState::Expired | DomainState::NotFound => Ok(None),
         }
     }
 
     pub async fn try_resolve_adnl_address(&self, path: &str) -> Result<Option<ton_types::UInt256>> {
         match self.get_domain_state(path).await? {
-            Some(domain) => dens::DomainContract(domain.as_context(self.clock.as_ref()))
-                .query::<dens::AdnlAddressRecord>(),
-            None => Ok(None),
+            DomainState::Active(domain) => {
+                dens::DomainContract(domain.as_context(self.clock.as_ref()))
+                    .query::<dens::AdnlAddressRecord>()
+            }
+            DomainState::Expired | DomainState::NotFound => Ok(None),
         }
     }
 
     pub async fn try_resolve(&self, path: &str, record: u32) -> Result<ResolvedValue> {
         match self.get_domain_state(path).await? {
-            Some(domain) => match dens::DomainContract(domain.as_context(self.clock.as_ref()))
-                .query_raw(record)?
-            {
-                Some(value) => Ok(ResolvedValue::Found(value)),
-                None => Ok(ResolvedValue::RecordNotFound),
-            },
-            None => Ok(ResolvedValue::DomainNotFound),
+            DomainState::Active(domain) => {
+                match dens::DomainContract(domain.as_context(self.clock.as_ref()))
+                    .query_raw(record)?
+                {
+                    Some(value) => Ok(ResolvedValue::Found(value)),
+                    None => Ok(ResolvedValue::RecordNotFound),
+                }
+            }
+            DomainState::Expired => Ok(ResolvedValue::DomainExpired),
+            DomainState::NotFound => Ok(ResolvedValue::DomainNotFound),
         }
     }
 
-    async fn get_domain_state(&self, path: &str) -> Result<Option<ExistingContract>> {
+    /// Returns domain expiration timestamp (in seconds), or `None` if domain was not found
+    pub async fn get_expiration(&self, path: &str) -> Result<Option<u32>> {
+        match self.get_domain_contract(path).await? {
+            Some(domain) => dens::DomainContract(domain.as_context(self.clock.as_ref()))
+                .expires_at()
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_domain_state(&self, path: &str) -> Result<DomainState> {
+        let domain = match self.get_domain_contract(path).await? {
+            Some(domain) => domain,
+            None => return Ok(DomainState::NotFound),
+        };
+
+        let expires_at =
+            dens::DomainContract(domain.as_context(self.clock.as_ref())).expires_at()?;
+        if is_expired(expires_at, self.clock.now_sec_u64()) {
+            return Ok(DomainState::Expired);
+        }
+
+        Ok(DomainState::Active(domain))
+    }
+
+    async fn get_domain_contract(&self, path: &str) -> Result<Option<ExistingContract>> {
         let address =
             dens::RootContract(self.state.as_context(self.clock.as_ref())).resolve(path)?;
         Ok(self
@@ -194,6 +226,17 @@ impl DensTld {
     }
 }
 
+enum DomainState {
+    Active(ExistingContract),
+    Expired,
+    NotFound,
+}
+
+/// Domain is considered expired starting from its expiration timestamp
+fn is_expired(expires_at: u32, now_sec: u64) -> bool {
+    expires_at as u64 <= now_sec
+}
+
 pub fn validate_address(path: &str) -> bool {
     let mut segment_start = 0;
     let mut segment_end = 0;
@@ -216,11 +259,13 @@ pub fn validate_address(path: &str) -> bool {
     segment_start != segment_end
 }
 
+#[non_exhaustive]
 #[derive(Debug, Clone)]
 pub enum ResolvedValue {
     Found(ton_types::Cell),
     RecordNotFound,
     DomainNotFound,
+    DomainExpired,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -234,3 +279,66 @@ enum DensError {
     #[error("Invalid path")]
     InvalidPath,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use nekoton_utils::SimpleClock;
+
+    use super::*;
+    use crate::transport::tests::MockTransport;
+
+    #[test]
+    fn domain_expiration() {
+        assert!(!is_expired(1_700_000_001, 1_700_000_000));
+        assert!(is_expired(1_700_000_000, 1_700_000_000));
+        assert!(is_expired(1_699_999_999, 1_700_000_000));
+        assert!(is_expired(0, 0));
+    }
+
+    #[test]
+    fn address_validation() {
+        assert!(validate_address("alice.ever"));
+        assert!(validate_address("sub-domain.alice.ever"));
+        assert!(validate_address("ever"));
+
+        assert!(!validate_address(""));
+        assert!(!validate_address(".ever"));
+        assert!(!validate_address("alice..ever"));
+        assert!(!validate_address("alice.ever."));
+        assert!(!validate_address("alice_bob.ever"));
+    }
+
+    #[tokio::test]
+    async fn resolve_requires_known_tld() {
+        let transport = Arc::new(MockTransport::new());
+        let dens = Dens::builder(Arc::new(SimpleClock), transport.clone()).build();
+
+        let err = dens.try_resolve("alice.ever", 0).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DensError>(),
+            Some(DensError::TldNotFound)
+        ));
+
+        let err = dens.try_resolve("alice..ever", 0).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DensError>(),
+            Some(DensError::InvalidPath)
+        ));
+
+        // TLD contract must exist
+        let address = MsgAddressInt::from_str(
+            "0:0000000000000000000000000000000000000000000000000000000000000001",
+        )
+        .unwrap();
+        let err = DensTld::new(Arc::new(SimpleClock), transport, &address)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<DensError>(),
+            Some(DensError::InvalidTldContract)
+        ));
+    }
+}
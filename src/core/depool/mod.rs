@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use anyhow::Result;
+use nekoton_abi::*;
+use nekoton_contracts::depool::{
+    depool_contract, DePoolContract, DePoolInfo, ParticipantInfo, TruncatedRound,
+};
+use nekoton_utils::*;
+use ton_block::MsgAddressInt;
+
+use crate::core::models::*;
+use crate::core::parsing::*;
+use crate::transport::models::{RawContractState, RawTransaction};
+use crate::transport::Transport;
+
+use super::{ContractSubscription, InternalMessage};
+
+/// DePool subscription from the point of view of one participant
+pub struct DePool {
+    clock: Arc<dyn Clock>,
+    contract_subscription: ContractSubscription,
+    handler: Arc<dyn DePoolSubscriptionHandler>,
+    participant: MsgAddressInt,
+    info: DePoolInfo,
+    participant_info: Option<ParticipantInfo>,
+}
+
+impl DePool {
+    pub async fn subscribe(
+        clock: Arc<dyn Clock>,
+        transport: Arc<dyn Transport>,
+        address: MsgAddressInt,
+        participant: MsgAddressInt,
+        handler: Arc<dyn DePoolSubscriptionHandler>,
+        preload_transactions: bool,
+    ) -> Result<DePool> {
+        let state = match transport.get_contract_state(&address).await? {
+            RawContractState::Exists(state) => state,
+            RawContractState::NotExists { .. } => {
+                return Err(DePoolError::InvalidDePoolContract.into())
+            }
+        };
+        let info = DePoolContract(state.as_context(clock.as_ref())).get_info()?;
+
+        let mut participant_info = None;
+
+        let contract_subscription = {
+            let handler = handler.as_ref();
+
+            // NOTE: create handler beforehead to prevent lifetime issues
+            let mut on_transactions_found = match preload_transactions {
+                true => Some(make_transactions_handler(handler, &participant)),
+                false => None,
+            };
+
+            // Manual map is used here due to unsoundness
+            // See issue: https://github.com/rust-lang/rust/issues/84305
+            #[allow(trivial_casts)]
+            #[allow(clippy::manual_map)]
+            let on_transactions_found = match &mut on_transactions_found {
+                Some(handler) => Some(handler as _),
+                None => None,
+            };
+
+            ContractSubscription::subscribe(
+                clock.clone(),
+                transport,
+                address,
+                &mut make_contract_state_handler(
+                    clock.clone(),
+                    &participant,
+                    &mut participant_info,
+                ),
+                on_transactions_found,
+            )
+            .await?
+        };
+
+        handler.on_participant_info_changed(participant_info.clone());
+
+        Ok(Self {
+            clock,
+            contract_subscription,
+            handler,
+            participant,
+            info,
+            participant_info,
+        })
+    }
+
+    pub fn contract_subscription(&self) -> &ContractSubscription {
+        &self.contract_subscription
+    }
+
+    pub fn address(&self) -> &MsgAddressInt {
+        self.contract_subscription.address()
+    }
+
+    pub fn participant(&self) -> &MsgAddressInt {
+        &self.participant
+    }
+
+    /// DePool parameters at the moment of subscription
+    pub fn info(&self) -> &DePoolInfo {
+        &self.info
+    }
+
+    /// Participant stakes, `None` if the address is not a participant yet
+    pub fn participant_info(&self) -> Option<&ParticipantInfo> {
+        self.participant_info.as_ref()
+    }
+
+    pub fn contract_state(&self) -> &ContractState {
+        self.contract_subscription.contract_state()
+    }
+
+    /// Fetches current DePool rounds
+    pub async fn get_rounds(&self) -> Result<BTreeMap<u64, TruncatedRound>> {
+        let state = match self
+            .contract_subscription
+            .transport()
+            .get_contract_state(self.address())
+            .await?
+        {
+            RawContractState::Exists(state) => state,
+            RawContractState::NotExists { .. } => {
+                return Err(DePoolError::InvalidDePoolContract.into())
+            }
+        };
+        DePoolContract(state.as_context(self.clock.as_ref())).get_rounds()
+    }
+
+    /// Prepares a message which adds an ordinary stake.
+    ///
+    /// The stake fee is attached in addition to the stake
+    pub fn prepare_add_ordinary_stake(&self, stake: u64) -> Result<InternalMessage> {
+        if self.info.pool_closed {
+            return Err(DePoolError::PoolClosed.into());
+        }
+        if stake < self.info.min_stake {
+            return Err(DePoolError::StakeTooSmall.into());
+        }
+
+        let (function, input) = MessageBuilder::new(depool_contract::add_ordinary_stake())
+            .arg(stake) // stake
+            .build();
+
+        self.make_internal_message(function, input, stake as u128 + self.info.stake_fee as u128)
+    }
+
+    /// Prepares a message which requests a partial withdrawal
+    /// after the round completion
+    pub fn prepare_withdraw_part(&self, amount: u64) -> Result<InternalMessage> {
+        let (function, input) = MessageBuilder::new(depool_contract::withdraw_part())
+            .arg(amount) // withdrawValue
+            .build();
+
+        self.make_internal_message(function, input, self.info.stake_fee as u128)
+    }
+
+    /// Prepares a message which requests a withdrawal of the whole stake
+    /// after the round completion
+    pub fn prepare_withdraw_all(&self) -> Result<InternalMessage> {
+        let (function, input) = MessageBuilder::new(depool_contract::withdraw_all()).build();
+
+        self.make_internal_message(function, input, self.info.stake_fee as u128)
+    }
+
+    /// Prepares a message which cancels the pending withdrawal request
+    pub fn prepare_cancel_withdrawal(&self) -> Result<InternalMessage> {
+        let (function, input) = MessageBuilder::new(depool_contract::cancel_withdrawal()).build();
+
+        self.make_internal_message(function, input, self.info.stake_fee as u128)
+    }
+
+    fn make_internal_message(
+        &self,
+        function: &ton_abi::Function,
+        input: Vec<ton_abi::Token>,
+        amount: u128,
+    ) -> Result<InternalMessage> {
+        let body = function
+            .encode_internal_input(&input)
+            .and_then(ton_types::SliceData::load_builder)?;
+
+        Ok(InternalMessage {
+            source: Some(self.participant.clone()),
+            destination: self.address().clone(),
+            amount,
+            bounce: true,
+            body,
+        })
+    }
+
+    pub async fn refresh(&mut self) -> Result<()> {
+        let mut participant_info = self.participant_info.clone();
+
+        let handler = self.handler.as_ref();
+        self.contract_subscription
+            .refresh(
+                &mut make_contract_state_handler(
+                    self.clock.clone(),
+                    &self.participant,
+                    &mut participant_info,
+                ),
+                &mut make_transactions_handler(handler, &self.participant),
+                &mut |_, _| {},
+                &mut |_| {},
+            )
+            .await?;
+
+        if participant_info != self.participant_info {
+            self.participant_info = participant_info;
+            handler.on_participant_info_changed(self.participant_info.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Handles transactions from the block.
+    ///
+    /// NOTE: participant info is updated only on [`DePool::refresh`]
+    pub async fn handle_block(&mut self, block: &ton_block::Block) -> Result<()> {
+        let handler = self.handler.as_ref();
+        self.contract_subscription.handle_block(
+            block,
+            &mut make_transactions_handler(handler, &self.participant),
+            &mut |_, _| {},
+            &mut |_| {},
+        )?;
+
+        Ok(())
+    }
+
+    pub async fn preload_transactions(&mut self, from_lt: u64) -> Result<()> {
+        let handler = self.handler.as_ref();
+        self.contract_subscription
+            .preload_transactions(
+                from_lt,
+                &mut make_transactions_handler(handler, &self.participant),
+            )
+            .await
+    }
+}
+
+pub trait DePoolSubscriptionHandler: Send + Sync {
+    fn on_participant_info_changed(&self, participant_info: Option<ParticipantInfo>);
+
+    /// Called every time new transactions are detected.
+    /// - When new block found
+    /// - When manually requesting the latest transactions (can be called several times)
+    /// - When preloading transactions
+    ///
+    /// Only transactions related to the participant have parsed data
+    fn on_transactions_found(
+        &self,
+        transactions: Vec<TransactionWithData<DePoolTransaction>>,
+        batch_info: TransactionsBatchInfo,
+    );
+}
+
+fn make_contract_state_handler<'a>(
+    clock: Arc<dyn Clock>,
+    participant: &'a MsgAddressInt,
+    participant_info: &'a mut Option<ParticipantInfo>,
+) -> impl FnMut(&RawContractState) + 'a {
+    move |contract_state| {
+        if let RawContractState::Exists(state) = contract_state {
+            // NOTE: getter fails for addresses which are not participants
+            *participant_info = DePoolContract(state.as_context(clock.as_ref()))
+                .get_participant_info(participant)
+                .ok();
+        }
+    }
+}
+
+fn make_transactions_handler<'a>(
+    handler: &'a dyn DePoolSubscriptionHandler,
+    participant: &'a MsgAddressInt,
+) -> impl FnMut(Vec<RawTransaction>, TransactionsBatchInfo) + 'a {
+    move |transactions, batch_info| {
+        let transactions = transactions
+            .into_iter()
+            .filter_map(
+                |transaction| match transaction.data.description.read_struct().ok()? {
+                    ton_block::TransactionDescr::Ordinary(description) => {
+                        let data =
+                            parse_depool_transaction(&transaction.data, &description, participant);
+
+                        let transaction =
+                            Transaction::try_from((transaction.hash, transaction.data)).ok()?;
+
+                        Some(TransactionWithData { transaction, data })
+                    }
+                    _ => None,
+                },
+            )
+            .collect();
+
+        handler.on_transactions_found(transactions, batch_info)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum DePoolError {
+    #[error("Invalid DePool contract")]
+    InvalidDePoolContract,
+    #[error("DePool is closed")]
+    PoolClosed,
+    #[error("Stake is less than the DePool minimal stake")]
+    StakeTooSmall,
+}
@@ -2,19 +2,31 @@ use std::convert::TryFrom;
 use std::sync::Arc;
 
 use anyhow::Result;
+use nekoton_abi::{unpack_from_cell, UnpackAbi};
 use ton_block::{GetRepresentationHash, MsgAddressInt};
+use ton_types::{SliceData, UInt256};
 
-use nekoton_utils::Clock;
+use nekoton_utils::{Clock, TransactionFees};
 
-use super::models::{ContractState, PendingTransaction, Transaction, TransactionsBatchInfo};
+use super::abi_registry::{AbiDecoder, AbiRegistry, DecodedAbiTransaction};
+use super::models::{
+    BalanceChange, ContractState, ContractUpgrade, PendingTransaction, Transaction,
+    TransactionsBatchInfo,
+};
+use super::state_diff::{ContractStateDiff, StateDiffTracker};
 use super::{ContractSubscription, PollingMethod, TransactionExecutionOptions};
 use crate::core::utils;
+use crate::crypto::SignatureId;
 use crate::transport::models::{RawContractState, RawTransaction};
 use crate::transport::Transport;
 
 pub struct GenericContract {
+    clock: Arc<dyn Clock>,
     contract_subscription: ContractSubscription,
     handler: Arc<dyn GenericContractSubscriptionHandler>,
+    state_diff: StateDiffTracker,
+    replay_protection: Option<ReplayProtection>,
+    abi_registry: Option<Arc<AbiRegistry>>,
 }
 
 impl GenericContract {
@@ -25,12 +37,14 @@ impl GenericContract {
         handler: Arc<dyn GenericContractSubscriptionHandler>,
         preload_transactions: bool,
     ) -> Result<Self> {
+        let mut state_diff = StateDiffTracker::default();
+
         let contract_subscription = {
             let handler = handler.as_ref();
 
             // NOTE: create handler beforehead to prevent lifetime issues
             let mut on_transactions_found = match preload_transactions {
-                true => Some(make_transactions_handler(handler)),
+                true => Some(make_transactions_handler(handler, None)),
                 false => None,
             };
 
@@ -44,18 +58,22 @@ impl GenericContract {
             };
 
             ContractSubscription::subscribe(
-                clock,
+                clock.clone(),
                 transport,
                 address,
-                &mut make_contract_state_handler(handler),
+                &mut make_contract_state_handler(handler, &mut state_diff, &mut None),
                 on_transactions_found,
             )
             .await?
         };
 
         Ok(Self {
+            clock,
             contract_subscription,
             handler,
+            state_diff,
+            replay_protection: None,
+            abi_registry: None,
         })
     }
 
@@ -75,48 +93,157 @@ impl GenericContract {
         self.contract_subscription.polling_method()
     }
 
+    /// Sets the contract ABI which is used to compare data fields
+    /// in [`GenericContractSubscriptionHandler::on_state_diff`]
+    pub fn set_abi(&mut self, abi: Option<Arc<ton_abi::Contract>>) {
+        self.state_diff.set_abi(abi);
+    }
+
+    /// Sets the registry which is used to decode transactions in
+    /// [`GenericContractSubscriptionHandler::on_transactions_decoded`]
+    pub fn set_abi_registry(&mut self, abi_registry: Option<Arc<AbiRegistry>>) {
+        self.abi_registry = abi_registry;
+    }
+
+    /// Decodes messages of the transaction with the ABI registry
+    pub fn decode_transaction(&self, transaction: &Transaction) -> Option<DecodedAbiTransaction> {
+        self.abi_decoder()
+            .map(|decoder| decoder.decode(transaction))
+    }
+
+    fn abi_decoder(&self) -> Option<AbiDecoder> {
+        Some(AbiDecoder {
+            registry: self.abi_registry.clone()?,
+            address: self.address().clone(),
+            code_hash: self.contract_state().code_hash,
+        })
+    }
+
+    /// Enables tracking of the replay protection field (e.g. seqno or timestamp).
+    ///
+    /// See [`GenericContract::reserve_replay_protection`]
+    pub fn set_replay_protection(&mut self, extractor: Option<Arc<dyn ReplayProtectionExtractor>>) {
+        self.replay_protection = extractor.map(|extractor| ReplayProtection {
+            extractor,
+            current: None,
+            outdated: true,
+            reserved: Vec::new(),
+        });
+    }
+
+    /// Reserves the current value of the replay protection field for a new message.
+    ///
+    /// Must be called before preparing the unsigned message with the same `expire_at`.
+    /// Fails while there is an unresolved message with the same value.
+    /// The contract state is only requested if it has changed since the last refresh
+    pub async fn reserve_replay_protection(&mut self, expire_at: u32) -> Result<Option<u64>> {
+        let current_utime = self
+            .contract_state()
+            .gen_timings
+            .current_utime(self.clock.as_ref());
+        let pending_transactions = self.contract_subscription.pending_transactions();
+        let replay_protection = match &mut self.replay_protection {
+            Some(replay_protection) => replay_protection,
+            None => return Ok(None),
+        };
+
+        replay_protection.forget_resolved(pending_transactions, current_utime);
+
+        if replay_protection.outdated {
+            let state = self
+                .contract_subscription
+                .transport()
+                .get_contract_state(self.contract_subscription.address())
+                .await?;
+            replay_protection.update(&state)?;
+        }
+
+        let value = match replay_protection.current {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        replay_protection.reserve(value, expire_at)?;
+        Ok(Some(value))
+    }
+
+    /// Signature id which must be used to sign messages for this contract
+    pub async fn get_signature_id(&self) -> Result<Option<SignatureId>> {
+        self.contract_subscription.get_signature_id().await
+    }
+
     pub async fn send(
         &mut self,
         message: &ton_block::Message,
         expire_at: u32,
     ) -> Result<PendingTransaction> {
-        self.contract_subscription.send(message, expire_at).await
+        let pending_transaction = self.contract_subscription.send(message, expire_at).await?;
+        if let Some(replay_protection) = &mut self.replay_protection {
+            replay_protection.bind(expire_at, pending_transaction.message_hash);
+        }
+        Ok(pending_transaction)
     }
 
     pub async fn refresh(&mut self) -> Result<()> {
         let handler = self.handler.as_ref();
+        let abi_decoder = self.abi_decoder();
         self.contract_subscription
             .refresh(
-                &mut make_contract_state_handler(handler),
-                &mut make_transactions_handler(handler),
+                &mut make_contract_state_handler(
+                    handler,
+                    &mut self.state_diff,
+                    &mut self.replay_protection,
+                ),
+                &mut make_transactions_handler(handler, abi_decoder),
                 &mut make_message_sent_handler(handler),
                 &mut make_message_expired_handler(handler),
             )
             .await?;
 
+        self.handle_contract_upgrade();
         Ok(())
     }
 
     pub async fn handle_block(&mut self, block: &ton_block::Block) -> Result<()> {
         let handler = self.handler.as_ref();
+        let abi_decoder = self.abi_decoder();
         let new_account_state = self.contract_subscription.handle_block(
             block,
-            &mut make_transactions_handler(handler),
+            &mut make_transactions_handler(handler, abi_decoder),
             &mut make_message_sent_handler(handler),
             &mut make_message_expired_handler(handler),
         )?;
 
         if let Some(account_state) = new_account_state {
+            // NOTE: blocks don't contain the account data
+            if let Some(replay_protection) = &mut self.replay_protection {
+                replay_protection.outdated = true;
+            }
+
+            let diff = self.state_diff.update_brief(&account_state);
             handler.on_state_changed(account_state);
+            if let Some(diff) = diff {
+                handler.on_state_diff(diff);
+            }
         }
 
+        self.handle_contract_upgrade();
         Ok(())
     }
 
+    fn handle_contract_upgrade(&mut self) {
+        if let Some(upgrade) = self.contract_subscription.take_contract_upgrade() {
+            self.handler.on_contract_upgraded(upgrade);
+        }
+    }
+
     pub async fn preload_transactions(&mut self, from_lt: u64) -> Result<()> {
         let handler = self.handler.as_ref();
+        let abi_decoder = self.abi_decoder();
         self.contract_subscription
-            .preload_transactions(from_lt, &mut make_transactions_handler(handler))
+            .preload_transactions(
+                from_lt,
+                &mut make_transactions_handler(handler, abi_decoder),
+            )
             .await
     }
 
@@ -124,6 +251,15 @@ impl GenericContract {
         self.contract_subscription.estimate_fees(message).await
     }
 
+    pub async fn estimate_fees_detailed(
+        &mut self,
+        message: &ton_block::Message,
+    ) -> Result<TransactionFees> {
+        self.contract_subscription
+            .estimate_fees_detailed(message)
+            .await
+    }
+
     pub async fn execute_transaction_locally(
         &mut self,
         message: &ton_block::Message,
@@ -139,18 +275,169 @@ impl GenericContract {
     }
 }
 
-fn make_contract_state_handler(
-    handler: &dyn GenericContractSubscriptionHandler,
-) -> impl FnMut(&RawContractState) + '_ {
-    move |contract_state| handler.on_state_changed(contract_state.brief())
+struct ReplayProtection {
+    extractor: Arc<dyn ReplayProtectionExtractor>,
+    /// Value from the latest known contract state
+    current: Option<u64>,
+    /// Whether the contract data has changed since the value was extracted
+    outdated: bool,
+    /// Values of the prepared messages
+    reserved: Vec<ReservedValue>,
+}
+
+impl ReplayProtection {
+    fn update(&mut self, contract_state: &RawContractState) -> Result<()> {
+        self.current = None;
+        self.outdated = true;
+        if let RawContractState::Exists(contract) = contract_state {
+            self.current = Some(self.extractor.extract(&contract.account)?);
+        }
+        self.outdated = false;
+        Ok(())
+    }
+
+    /// Removes values of the resolved, expired or never sent messages
+    fn forget_resolved(&mut self, pending_transactions: &[PendingTransaction], current_utime: u32) {
+        self.reserved.retain(|item| match &item.message_hash {
+            Some(message_hash) => pending_transactions
+                .iter()
+                .any(|pending| &pending.message_hash == message_hash),
+            None => current_utime <= item.expire_at,
+        });
+    }
+
+    fn reserve(&mut self, value: u64, expire_at: u32) -> Result<(), ReplayProtectionError> {
+        if self.reserved.iter().any(|item| item.value == value) {
+            return Err(ReplayProtectionError::MessageInFlight { value });
+        }
+        self.reserved.push(ReservedValue {
+            value,
+            expire_at,
+            message_hash: None,
+        });
+        Ok(())
+    }
+
+    /// Binds the sent message to the value reserved with the same `expire_at`
+    fn bind(&mut self, expire_at: u32, message_hash: UInt256) {
+        if let Some(item) = self
+            .reserved
+            .iter_mut()
+            .find(|item| item.message_hash.is_none() && item.expire_at == expire_at)
+        {
+            item.message_hash = Some(message_hash);
+        }
+    }
+}
+
+struct ReservedValue {
+    value: u64,
+    expire_at: u32,
+    /// Hash of the sent message
+    message_hash: Option<UInt256>,
+}
+
+/// Reads the replay protection field (e.g. seqno or timestamp) from the account state
+pub trait ReplayProtectionExtractor: Send + Sync {
+    fn extract(&self, account: &ton_block::AccountStuff) -> Result<u64>;
+}
+
+impl<F> ReplayProtectionExtractor for F
+where
+    F: Fn(&ton_block::AccountStuff) -> Result<u64> + Send + Sync,
+{
+    fn extract(&self, account: &ton_block::AccountStuff) -> Result<u64> {
+        self(account)
+    }
+}
+
+/// Reads the integer field of the contract data, described in the ABI
+pub struct AbiFieldExtractor {
+    abi: Arc<ton_abi::Contract>,
+    field: String,
+}
+
+impl AbiFieldExtractor {
+    pub fn new(abi: Arc<ton_abi::Contract>, field: impl Into<String>) -> Self {
+        Self {
+            abi,
+            field: field.into(),
+        }
+    }
+}
+
+impl ReplayProtectionExtractor for AbiFieldExtractor {
+    fn extract(&self, account: &ton_block::AccountStuff) -> Result<u64> {
+        let data = match &account.storage.state {
+            ton_block::AccountState::AccountActive { state_init } => state_init.data.clone(),
+            _ => None,
+        }
+        .ok_or(ReplayProtectionError::NoContractData)?;
+
+        let token = unpack_from_cell(
+            &self.abi.fields,
+            SliceData::load_cell(data)?,
+            true,
+            self.abi.abi_version,
+        )?
+        .into_iter()
+        .find(|token| token.name == self.field)
+        .ok_or(ReplayProtectionError::FieldNotFound)?;
+
+        Ok(token.value.unpack()?)
+    }
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum ReplayProtectionError {
+    #[error("Message with the same replay protection value ({value}) is not resolved yet")]
+    MessageInFlight { value: u64 },
+    #[error("Contract has no data")]
+    NoContractData,
+    #[error("Replay protection field not found")]
+    FieldNotFound,
+}
+
+fn make_contract_state_handler<'a>(
+    handler: &'a dyn GenericContractSubscriptionHandler,
+    state_diff: &'a mut StateDiffTracker,
+    replay_protection: &'a mut Option<ReplayProtection>,
+) -> impl FnMut(&RawContractState) + 'a {
+    move |contract_state| {
+        if let Some(replay_protection) = replay_protection.as_mut() {
+            if let Err(e) = replay_protection.update(contract_state) {
+                log::error!("{e}");
+            }
+        }
+
+        let diff = state_diff.update(contract_state);
+        handler.on_state_changed(contract_state.brief());
+        if let Some(diff) = diff {
+            handler.on_state_diff(diff);
+        }
+    }
 }
 
 fn make_transactions_handler(
     handler: &dyn GenericContractSubscriptionHandler,
+    abi_decoder: Option<AbiDecoder>,
 ) -> impl FnMut(Vec<RawTransaction>, TransactionsBatchInfo) + '_ {
     move |transactions, batch_info| {
-        let transactions = utils::convert_transactions(transactions).collect();
-        handler.on_transactions_found(transactions, batch_info)
+        let balance_changes = transactions
+            .iter()
+            .map(|transaction| BalanceChange::new(transaction.hash, &transaction.data))
+            .collect();
+
+        let transactions: Vec<Transaction> = utils::convert_transactions(transactions).collect();
+        if let Some(abi_decoder) = &abi_decoder {
+            let decoded = transactions
+                .iter()
+                .map(|transaction| abi_decoder.decode(transaction))
+                .collect();
+            handler.on_transactions_decoded(&transactions, decoded);
+        }
+        handler.on_transactions_found(transactions, batch_info);
+        handler.on_balance_changes(balance_changes, batch_info);
     }
 }
 
@@ -183,6 +470,12 @@ pub trait GenericContractSubscriptionHandler: Send + Sync {
     /// Called every time a new state is detected
     fn on_state_changed(&self, new_state: ContractState);
 
+    /// Called after [`GenericContractSubscriptionHandler::on_state_changed`]
+    /// with the changes since the previous state
+    fn on_state_diff(&self, diff: ContractStateDiff) {
+        let _ = diff;
+    }
+
     /// Called every time new transactions are detected.
     /// - When new block found
     /// - When manually requesting the latest transactions (can be called several times)
@@ -192,4 +485,191 @@ pub trait GenericContractSubscriptionHandler: Send + Sync {
         transactions: Vec<Transaction>,
         batch_info: TransactionsBatchInfo,
     );
+
+    /// Called before [`GenericContractSubscriptionHandler::on_transactions_found`]
+    /// if the ABI registry is set. Decoded items have the same order as transactions
+    fn on_transactions_decoded(
+        &self,
+        transactions: &[Transaction],
+        decoded: Vec<DecodedAbiTransaction>,
+    ) {
+        let _ = (transactions, decoded);
+    }
+
+    /// Called after [`GenericContractSubscriptionHandler::on_transactions_found`]
+    /// with the balance change of each transaction
+    fn on_balance_changes(&self, changes: Vec<BalanceChange>, batch_info: TransactionsBatchInfo) {
+        let _ = (changes, batch_info);
+    }
+
+    /// Called when the contract code hash changed
+    fn on_contract_upgraded(&self, upgrade: ContractUpgrade) {
+        let _ = upgrade;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nekoton_abi::{pack_into_cell, BuildTokenValue, TokenValueExt};
+
+    use super::*;
+
+    #[test]
+    fn abi_field_extractor() {
+        let abi = ton_abi::Contract::load(
+            r#"{
+                "ABI version": 2,
+                "version": "2.2",
+                "header": [],
+                "functions": [],
+                "events": [],
+                "data": [],
+                "fields": [
+                    {"name": "owner", "type": "uint256"},
+                    {"name": "seqno", "type": "uint32"}
+                ]
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let data = pack_into_cell(
+            &[
+                UInt256::default().token_value().named("owner"),
+                42u32.token_value().named("seqno"),
+            ],
+            abi.abi_version,
+        )
+        .unwrap();
+
+        let account = ton_block::AccountStuff {
+            storage: ton_block::AccountStorage {
+                state: ton_block::AccountState::AccountActive {
+                    state_init: ton_block::StateInit {
+                        data: Some(data),
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let abi = Arc::new(abi);
+        assert_eq!(
+            AbiFieldExtractor::new(abi.clone(), "seqno")
+                .extract(&account)
+                .unwrap(),
+            42
+        );
+        assert!(AbiFieldExtractor::new(abi, "nonce")
+            .extract(&account)
+            .is_err());
+    }
+
+    struct NoopHandler;
+
+    impl GenericContractSubscriptionHandler for NoopHandler {
+        fn on_message_sent(&self, _: PendingTransaction, _: Option<Transaction>) {}
+
+        fn on_message_expired(&self, _: PendingTransaction) {}
+
+        fn on_state_changed(&self, _: ContractState) {}
+
+        fn on_transactions_found(&self, _: Vec<Transaction>, _: TransactionsBatchInfo) {}
+    }
+
+    fn make_state(lt: u64) -> RawContractState {
+        RawContractState::Exists(crate::transport::models::ExistingContract {
+            account: ton_block::AccountStuff {
+                storage: ton_block::AccountStorage {
+                    last_trans_lt: lt,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            timings: nekoton_abi::GenTimings::Unknown,
+            last_transaction_id: nekoton_abi::LastTransactionId::Inexact { latest_lt: lt },
+        })
+    }
+
+    #[tokio::test]
+    async fn replay_protection_reservations() {
+        use nekoton_utils::MockClock;
+
+        use crate::transport::tests::MockTransport;
+
+        let clock = MockClock::new(1_000_000_000);
+        let now = clock.now_sec_u64() as u32;
+
+        let address = MsgAddressInt::default();
+        let transport = Arc::new(MockTransport::new());
+        transport.set_contract_state(address.clone(), make_state(10));
+
+        let mut contract = GenericContract::subscribe(
+            Arc::new(clock.clone()),
+            transport.clone(),
+            address.clone(),
+            Arc::new(NoopHandler),
+            false,
+        )
+        .await
+        .unwrap();
+
+        // Disabled by default
+        assert_eq!(
+            contract.reserve_replay_protection(now + 60).await.unwrap(),
+            None
+        );
+
+        let extractor = |account: &ton_block::AccountStuff| {
+            Ok::<_, anyhow::Error>(account.storage.last_trans_lt)
+        };
+        contract.set_replay_protection(Some(Arc::new(extractor)));
+
+        // The second message with the same value can't be prepared
+        assert_eq!(
+            contract.reserve_replay_protection(now + 60).await.unwrap(),
+            Some(10)
+        );
+        assert!(contract.reserve_replay_protection(now + 61).await.is_err());
+
+        // Never sent messages are forgotten after expiration
+        clock.advance_ms(120_000);
+        let now = clock.now_sec_u64() as u32;
+        assert_eq!(
+            contract.reserve_replay_protection(now + 60).await.unwrap(),
+            Some(10)
+        );
+
+        let message =
+            ton_block::Message::with_ext_in_header(ton_block::ExternalInboundMessageHeader {
+                dst: address.clone(),
+                ..Default::default()
+            });
+        contract.send(&message, now + 60).await.unwrap();
+
+        // Sent message stays in flight until it is resolved
+        clock.advance_ms(60_000);
+        let now = clock.now_sec_u64() as u32;
+        assert!(contract.reserve_replay_protection(now + 60).await.is_err());
+
+        // Resolved by expiration
+        clock.advance_ms(120_000);
+        contract.refresh().await.unwrap();
+        assert!(contract.pending_transactions().is_empty());
+        let now = clock.now_sec_u64() as u32;
+        assert_eq!(
+            contract.reserve_replay_protection(now + 60).await.unwrap(),
+            Some(10)
+        );
+
+        // The value is updated on refresh
+        transport.set_contract_state(address, make_state(20));
+        contract.refresh().await.unwrap();
+        assert_eq!(
+            contract.reserve_replay_protection(now + 120).await.unwrap(),
+            Some(20)
+        );
+    }
 }
@@ -0,0 +1,467 @@
+//! CSV and JSON export of the decoded transaction history.
+//!
+//! Both formats share the same set of columns. All amounts are exported as signed
+//! integers in the smallest units, from the point of view of the exported account
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use nekoton_utils::*;
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+use ton_block::MsgAddressInt;
+use ton_types::UInt256;
+
+use crate::core::models::{
+    describe_transaction, Symbol, TokenIncomingTransfer, TokenOutgoingTransfer,
+    TokenWalletTransaction, Transaction, TransactionAdditionalInfo, TransactionDescription,
+    TransactionWithData, TransferRecipient,
+};
+
+define_string_enum!(
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub enum ExportColumn {
+        /// Transaction unix timestamp
+        Timestamp,
+        /// Transaction logical time
+        Lt,
+        /// Transaction hash
+        Hash,
+        /// Type of the decoded transaction, see [`TransactionDescription`]
+        Kind,
+        /// Sender or recipient address
+        Counterparty,
+        /// Signed change of the native balance excluding fees
+        Amount,
+        /// Signed change of the token balance
+        TokenAmount,
+        /// Token symbol name
+        Token,
+        /// Address of the root token contract
+        TokenRoot,
+        /// Total transaction fees
+        Fees,
+        /// Transfer comment
+        Comment,
+        /// Whether the transaction was aborted
+        Aborted,
+    }
+);
+
+impl ExportColumn {
+    pub const ALL: [Self; 12] = [
+        Self::Timestamp,
+        Self::Lt,
+        Self::Hash,
+        Self::Kind,
+        Self::Counterparty,
+        Self::Amount,
+        Self::TokenAmount,
+        Self::Token,
+        Self::TokenRoot,
+        Self::Fees,
+        Self::Comment,
+        Self::Aborted,
+    ];
+
+    /// Whether the column contains text, which can be controlled by third parties
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Self::Kind | Self::Counterparty | Self::Token | Self::TokenRoot | Self::Comment
+        )
+    }
+
+    /// Column name in the CSV header and the JSON object key
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Timestamp => "timestamp",
+            Self::Lt => "lt",
+            Self::Hash => "hash",
+            Self::Kind => "kind",
+            Self::Counterparty => "counterparty",
+            Self::Amount => "amount",
+            Self::TokenAmount => "tokenAmount",
+            Self::Token => "token",
+            Self::TokenRoot => "tokenRoot",
+            Self::Fees => "fees",
+            Self::Comment => "comment",
+            Self::Aborted => "aborted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    /// Exported columns in the output order
+    pub columns: Vec<ExportColumn>,
+    /// Inclusive lower bound of the transaction timestamp
+    #[serde(default)]
+    pub since: Option<u32>,
+    /// Exclusive upper bound of the transaction timestamp
+    #[serde(default)]
+    pub until: Option<u32>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            columns: ExportColumn::ALL.to_vec(),
+            since: None,
+            until: None,
+        }
+    }
+}
+
+impl ExportOptions {
+    pub fn contains(&self, created_at: u32) -> bool {
+        self.since.map_or(true, |since| created_at >= since)
+            && self.until.map_or(true, |until| created_at < until)
+    }
+}
+
+/// Single row of the exported history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryRecord {
+    pub created_at: u32,
+    pub lt: u64,
+    pub hash: UInt256,
+    pub kind: &'static str,
+    pub counterparty: Option<MsgAddressInt>,
+    pub amount: i128,
+    pub token_amount: Option<BigInt>,
+    pub token: Option<String>,
+    pub token_root: Option<MsgAddressInt>,
+    pub fees: u64,
+    pub comment: Option<String>,
+    pub aborted: bool,
+}
+
+impl HistoryRecord {
+    /// Creates a record from the wallet transaction, decoding it with [`describe_transaction`]
+    pub fn from_wallet_transaction(
+        transaction: &TransactionWithData<TransactionAdditionalInfo>,
+    ) -> Self {
+        let description = describe_transaction(&transaction.transaction, transaction.data.as_ref());
+        let mut record = Self::from_transaction(&transaction.transaction, &description);
+
+        match description {
+            TransactionDescription::IncomingTransfer {
+                sender, comment, ..
+            } => {
+                record.counterparty = sender;
+                record.comment = comment;
+            }
+            TransactionDescription::OutgoingTransfer {
+                recipient, comment, ..
+            } => {
+                record.counterparty = recipient;
+                record.comment = comment;
+            }
+            TransactionDescription::TokenTransfer { recipient, tokens } => {
+                record.counterparty = Some(transfer_recipient_address(recipient));
+                record.token_amount = Some(-BigInt::from(tokens));
+            }
+            TransactionDescription::TokenSwapBack {
+                tokens,
+                callback_address,
+            } => {
+                record.counterparty = Some(callback_address);
+                record.token_amount = Some(-BigInt::from(tokens));
+            }
+            TransactionDescription::TokenWalletDeployed {
+                root_token_contract,
+            } => record.token_root = Some(root_token_contract),
+            TransactionDescription::JettonTransfer { recipient, tokens } => {
+                record.counterparty = Some(recipient);
+                record.token_amount = Some(-BigInt::from(tokens));
+            }
+            TransactionDescription::JettonNotification { sender, tokens } => {
+                record.counterparty = Some(sender);
+                record.token_amount = Some(BigInt::from(tokens));
+            }
+            TransactionDescription::MultisigSubmit { dest, .. } => {
+                record.counterparty = Some(dest);
+            }
+            _ => {}
+        }
+
+        record
+    }
+
+    /// Creates a record from the token wallet transaction
+    pub fn from_token_transaction(
+        transaction: &TransactionWithData<TokenWalletTransaction>,
+        symbol: &Symbol,
+    ) -> Self {
+        let mut record =
+            Self::from_transaction(&transaction.transaction, &TransactionDescription::Unknown);
+        record.token = Some(symbol.name.clone());
+        record.token_root = Some(symbol.root_token_contract.clone());
+
+        if let Some(data) = &transaction.data {
+            record.kind = token_transaction_kind(data);
+            record.token_amount = Some(data.balance_delta());
+            record.counterparty = match data {
+                TokenWalletTransaction::IncomingTransfer(TokenIncomingTransfer {
+                    sender_address,
+                    ..
+                }) => Some(sender_address.clone()),
+                TokenWalletTransaction::OutgoingTransfer(TokenOutgoingTransfer { to, .. }) => {
+                    Some(transfer_recipient_address(to.clone()))
+                }
+                _ => None,
+            };
+        }
+
+        record
+    }
+
+    fn from_transaction(transaction: &Transaction, description: &TransactionDescription) -> Self {
+        let incoming = if transaction.in_msg.src.is_some() {
+            transaction.in_msg.value as i128
+        } else {
+            0
+        };
+        let outgoing = transaction
+            .out_msgs
+            .iter()
+            .map(|msg| msg.value as i128)
+            .sum::<i128>();
+
+        Self {
+            created_at: transaction.created_at,
+            lt: transaction.id.lt,
+            hash: transaction.id.hash,
+            kind: description_kind(description),
+            counterparty: None,
+            amount: incoming - outgoing,
+            token_amount: None,
+            token: None,
+            token_root: None,
+            fees: transaction.total_fees,
+            comment: None,
+            aborted: transaction.aborted,
+        }
+    }
+
+    fn cell(&self, column: ExportColumn) -> Option<String> {
+        match column {
+            ExportColumn::Timestamp => Some(self.created_at.to_string()),
+            ExportColumn::Lt => Some(self.lt.to_string()),
+            ExportColumn::Hash => Some(self.hash.to_hex_string()),
+            ExportColumn::Kind => Some(self.kind.to_owned()),
+            ExportColumn::Counterparty => self.counterparty.as_ref().map(ToString::to_string),
+            ExportColumn::Amount => Some(self.amount.to_string()),
+            ExportColumn::TokenAmount => self.token_amount.as_ref().map(ToString::to_string),
+            ExportColumn::Token => self.token.clone(),
+            ExportColumn::TokenRoot => self.token_root.as_ref().map(ToString::to_string),
+            ExportColumn::Fees => Some(self.fees.to_string()),
+            ExportColumn::Comment => self.comment.clone(),
+            ExportColumn::Aborted => Some(self.aborted.to_string()),
+        }
+    }
+}
+
+/// Writes records within the date range as RFC 4180 CSV with the header row.
+///
+/// Text values which start with a formula character (`=`, `+`, `-`, `@`, tab or
+/// carriage return) are prefixed with `'`, so spreadsheets don't evaluate them
+pub fn export_csv(records: &[HistoryRecord], options: &ExportOptions) -> String {
+    let mut result = String::new();
+
+    let header = options.columns.iter().map(|column| column.name());
+    write_csv_row(&mut result, header);
+
+    for record in records
+        .iter()
+        .filter(|record| options.contains(record.created_at))
+    {
+        let row = options.columns.iter().map(|column| {
+            let value = record.cell(*column).unwrap_or_default();
+            if column.is_text() {
+                neutralize_formula(value)
+            } else {
+                value
+            }
+        });
+        write_csv_row(&mut result, row);
+    }
+
+    result
+}
+
+/// Writes records within the date range as JSON array of objects.
+///
+/// Object keys follow the order of the columns. Missing values are exported as `null`,
+/// numbers are exported as strings
+pub fn export_json(records: &[HistoryRecord], options: &ExportOptions) -> Result<String> {
+    let rows = records
+        .iter()
+        .filter(|record| options.contains(record.created_at))
+        .map(|record| JsonRow {
+            record,
+            columns: &options.columns,
+        })
+        .collect::<Vec<_>>();
+
+    Ok(serde_json::to_string(&rows)?)
+}
+
+/// Serializes the record as a map with keys in the columns order
+/// (`serde_json::Map` sorts keys without the `preserve_order` feature)
+struct JsonRow<'a> {
+    record: &'a HistoryRecord,
+    columns: &'a [ExportColumn],
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for column in self.columns {
+            match column {
+                ExportColumn::Aborted => {
+                    map.serialize_entry(column.name(), &self.record.aborted)?
+                }
+                _ => map.serialize_entry(column.name(), &self.record.cell(*column))?,
+            }
+        }
+        map.end()
+    }
+}
+
+fn neutralize_formula(value: String) -> String {
+    if value.starts_with(|c| matches!(c, '=' | '+' | '-' | '@' | '\t' | '\r')) {
+        format!("'{value}")
+    } else {
+        value
+    }
+}
+
+fn write_csv_row<I, T>(result: &mut String, row: I)
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<str>,
+{
+    for (i, field) in row.into_iter().enumerate() {
+        if i > 0 {
+            result.push(',');
+        }
+
+        let field = field.as_ref();
+        if field.contains(|c| matches!(c, ',' | '"' | '\r' | '\n')) {
+            let _ = write!(result, "\"{}\"", field.replace('"', "\"\""));
+        } else {
+            result.push_str(field);
+        }
+    }
+    result.push_str("\r\n");
+}
+
+fn transfer_recipient_address(recipient: TransferRecipient) -> MsgAddressInt {
+    match recipient {
+        TransferRecipient::OwnerWallet(address) | TransferRecipient::TokenWallet(address) => {
+            address
+        }
+    }
+}
+
+fn description_kind(description: &TransactionDescription) -> &'static str {
+    match description {
+        TransactionDescription::IncomingTransfer { .. } => "incoming_transfer",
+        TransactionDescription::OutgoingTransfer { .. } => "outgoing_transfer",
+        TransactionDescription::TokenTransfer { .. } => "token_transfer",
+        TransactionDescription::TokenSwapBack { .. } => "token_swap_back",
+        TransactionDescription::TokenWalletDeployed { .. } => "token_wallet_deployed",
+        TransactionDescription::JettonTransfer { .. } => "jetton_transfer",
+        TransactionDescription::JettonNotification { .. } => "jetton_notification",
+        TransactionDescription::MultisigSubmit { .. } => "multisig_submit",
+        TransactionDescription::MultisigConfirm { .. } => "multisig_confirm",
+        TransactionDescription::MultisigUpdate => "multisig_update",
+        TransactionDescription::DePoolReward { .. } => "de_pool_reward",
+        TransactionDescription::DePoolAnswer { .. } => "de_pool_answer",
+        TransactionDescription::Deploy => "deploy",
+        TransactionDescription::Bounced { .. } => "bounced",
+        TransactionDescription::Aborted { .. } => "aborted",
+        TransactionDescription::Unknown => "unknown",
+    }
+}
+
+fn token_transaction_kind(transaction: &TokenWalletTransaction) -> &'static str {
+    match transaction {
+        TokenWalletTransaction::IncomingTransfer(_) => "incoming_transfer",
+        TokenWalletTransaction::OutgoingTransfer(_) => "outgoing_transfer",
+        TokenWalletTransaction::SwapBack(_) => "swap_back",
+        TokenWalletTransaction::Accept(_) => "accept",
+        TokenWalletTransaction::TransferBounced(_) => "transfer_bounced",
+        TokenWalletTransaction::SwapBackBounced(_) => "swap_back_bounced",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_record(created_at: u32, comment: Option<&str>) -> HistoryRecord {
+        HistoryRecord {
+            created_at,
+            lt: created_at as u64 * 1000,
+            hash: UInt256::default(),
+            kind: "incoming_transfer",
+            counterparty: None,
+            amount: -1_000_000_000,
+            token_amount: Some(BigInt::from(-5)),
+            token: None,
+            token_root: None,
+            fees: 10,
+            comment: comment.map(ToOwned::to_owned),
+            aborted: false,
+        }
+    }
+
+    #[test]
+    fn history_export() {
+        let records = [
+            make_record(100, Some("hello, \"world\"")),
+            make_record(200, Some("=HYPERLINK(\"http://example.com\")")),
+            make_record(300, None),
+        ];
+        let options = ExportOptions {
+            columns: vec![
+                ExportColumn::Timestamp,
+                ExportColumn::Amount,
+                ExportColumn::TokenAmount,
+                ExportColumn::Token,
+                ExportColumn::Comment,
+                ExportColumn::Aborted,
+            ],
+            since: Some(100),
+            until: Some(300),
+        };
+
+        assert_eq!(
+            export_csv(&records, &options),
+            "timestamp,amount,tokenAmount,token,comment,aborted\r\n\
+             100,-1000000000,-5,,\"hello, \"\"world\"\"\",false\r\n\
+             200,-1000000000,-5,,\"'=HYPERLINK(\"\"http://example.com\"\")\",false\r\n"
+        );
+
+        let json = export_json(&records, &options).unwrap();
+        assert!(json.starts_with(
+            r#"[{"timestamp":"100","amount":"-1000000000","tokenAmount":"-5","token":null,"#
+        ));
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["amount"], "-1000000000");
+        assert_eq!(rows[0]["comment"], "hello, \"world\"");
+        assert_eq!(rows[1]["comment"], "=HYPERLINK(\"http://example.com\")");
+        assert_eq!(rows[1]["aborted"], false);
+    }
+}
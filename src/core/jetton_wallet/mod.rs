@@ -18,6 +18,7 @@ use super::{utils, ContractSubscription, InternalMessage};
 
 pub const JETTON_TRANSFER_OPCODE: u32 = 0x0f8a7ea5;
 pub const JETTON_INTERNAL_TRANSFER_OPCODE: u32 = 0x178d4519;
+pub const JETTON_BURN_OPCODE: u32 = 0x595f07bc;
 
 pub struct JettonWallet {
     clock: Arc<dyn Clock>,
@@ -200,6 +201,54 @@ impl JettonWallet {
         })
     }
 
+    /// Prepares a message which burns the specified amount of jettons.
+    ///
+    /// The jetton master will send the excess to `remaining_gas_to`
+    pub fn prepare_burn(
+        &self,
+        amount: BigUint,
+        remaining_gas_to: MsgAddressInt,
+        custom_payload: Option<ton_types::Cell>,
+        attached_amount: u128,
+    ) -> Result<InternalMessage> {
+        let mut builder = BuilderData::new();
+
+        // Opcode
+        builder.append_u32(JETTON_BURN_OPCODE)?;
+
+        // Query id
+        builder.append_u64(self.clock.now_ms_u64())?;
+
+        // Amount
+        let grams =
+            ton_block::Grams::new(amount.to_u128().ok_or(JettonWalletError::TryFromGrams)?)?;
+        grams.write_to(&mut builder)?;
+
+        // Response destination
+        remaining_gas_to.write_to(&mut builder)?;
+
+        // Optional(TvmCell)
+        match custom_payload {
+            Some(payload) => {
+                builder.append_bit_one()?;
+                builder.checked_append_reference(payload)?;
+            }
+            None => {
+                builder.append_bit_zero()?;
+            }
+        }
+
+        let body = builder.into_cell().map(SliceData::load_cell)??;
+
+        Ok(InternalMessage {
+            source: Some(self.owner.clone()),
+            destination: self.address().clone(),
+            amount: attached_amount,
+            bounce: true,
+            body,
+        })
+    }
+
     pub async fn refresh(&mut self) -> Result<()> {
         let mut balance = self.balance.clone();
 
@@ -243,6 +292,9 @@ impl JettonWallet {
                                 JettonWalletTransaction::Transfer(transfer) => {
                                     balance -= transfer.tokens.clone().to_bigint().trust_me();
                                 }
+                                JettonWalletTransaction::Burn(burn) => {
+                                    balance -= burn.tokens.clone().to_bigint().trust_me();
+                                }
                                 JettonWalletTransaction::InternalTransfer(transfer) => {
                                     balance += transfer.tokens.clone().to_bigint().trust_me();
                                 }
@@ -636,4 +688,116 @@ mod tests {
 
         Ok(())
     }
+
+    struct NoopHandler;
+
+    impl super::JettonWalletSubscriptionHandler for NoopHandler {
+        fn on_balance_changed(&self, _: BigUint) {}
+
+        fn on_transactions_found(
+            &self,
+            _: Vec<crate::core::models::TransactionWithData<super::JettonWalletTransaction>>,
+            _: crate::core::models::TransactionsBatchInfo,
+        ) {
+        }
+    }
+
+    /// Subscription to the missing wallet, which skips the root contract lookup
+    async fn make_wallet(owner: MsgAddressInt) -> super::JettonWallet {
+        use std::sync::Arc;
+
+        use crate::core::ContractSubscription;
+        use crate::transport::tests::MockTransport;
+
+        let clock: Arc<dyn nekoton_utils::Clock> = Arc::new(SimpleClock);
+        let contract_subscription = ContractSubscription::subscribe(
+            clock.clone(),
+            Arc::new(MockTransport::new()),
+            MsgAddressInt::from_str(
+                "0:0c6a835483369275c9ae76e7e31d9eda0845368045a8ec2ed78609d96bb0a087",
+            )
+            .unwrap(),
+            &mut |_| {},
+            None,
+        )
+        .await
+        .unwrap();
+
+        super::JettonWallet {
+            clock,
+            contract_subscription,
+            handler: Arc::new(NoopHandler),
+            root: MsgAddressInt::default(),
+            owner,
+            balance: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn prepare_and_parse_burn() -> anyhow::Result<()> {
+        use ton_block::{Message, Transaction, TransactionDescrOrdinary};
+
+        use crate::core::parsing::parse_jetton_transaction;
+
+        let owner = MsgAddressInt::from_str(
+            "0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb",
+        )?;
+        let wallet = make_wallet(owner.clone()).await;
+
+        let message =
+            wallet.prepare_burn(BigUint::from(1_000_000u32), owner.clone(), None, 50_000_000)?;
+        assert_eq!(message.source.as_ref(), Some(&owner));
+        assert_eq!(&message.destination, wallet.address());
+        assert_eq!(message.amount, 50_000_000);
+        assert!(message.bounce);
+
+        let mut body = message.body.clone();
+        assert_eq!(body.get_next_u32()?, super::JETTON_BURN_OPCODE);
+        assert_eq!(body.remaining_references(), 0);
+
+        // Burn is parsed from the incoming message of the wallet transaction
+        let mut in_msg = Message::with_int_header(ton_block::InternalMessageHeader {
+            src: ton_block::MsgAddressIntOrNone::Some(owner.clone()),
+            dst: message.destination.clone(),
+            ..Default::default()
+        });
+        in_msg.set_body(message.body);
+
+        let mut tx = Transaction::default();
+        tx.write_in_msg(Some(&in_msg))?;
+
+        let parsed = parse_jetton_transaction(&tx, &TransactionDescrOrdinary::default());
+        match parsed {
+            Some(super::JettonWalletTransaction::Burn(burn)) => {
+                assert_eq!(burn.tokens, BigUint::from(1_000_000u32));
+                assert_eq!(burn.response_destination, owner);
+            }
+            _ => panic!("burn not parsed"),
+        }
+
+        // Aborted burns are ignored
+        let aborted = TransactionDescrOrdinary {
+            aborted: true,
+            ..Default::default()
+        };
+        assert!(parse_jetton_transaction(&tx, &aborted).is_none());
+
+        // Custom payload is stored in the reference
+        let payload = ton_types::Cell::default();
+        let message =
+            wallet.prepare_burn(BigUint::from(1u32), owner.clone(), Some(payload.clone()), 0)?;
+        assert_eq!(message.body.remaining_references(), 1);
+        assert_eq!(message.body.reference(0)?.repr_hash(), payload.repr_hash());
+
+        // Amount must fit into grams
+        let err = wallet
+            .prepare_burn(BigUint::from(u128::MAX) + 1u32, owner, None, 0)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<super::JettonWalletError>(),
+            Some(super::JettonWalletError::TryFromGrams)
+        ));
+
+        Ok(())
+    }
 }
@@ -8,23 +8,45 @@ use anyhow::Result;
 use ed25519_dalek::PublicKey;
 use futures_util::future;
 use rand::Rng;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 use tokio::sync::RwLock;
+use zeroize::Zeroizing;
 
 use nekoton_utils::*;
 
+use crate::core::storage_migrator::{MigrationError, MigrationStep, StorageSchema};
 use crate::crypto::{
-    EncryptedData, EncryptionAlgorithm, PasswordCache, SharedSecret, Signature, SignatureId,
-    Signer, SignerContext, SignerEntry, SignerStorage,
+    EncryptedData, EncryptionAlgorithm, KdfParams, KeyAlgorithm, PasswordCache, SealedBox,
+    Secp256k1PublicKey, Secp256k1Signature, Secp256k1Signer, SharedSecret, Signature, SignatureId,
+    SignedMessage, Signer, SignerContext, SignerEntry, SignerStorage, UnsignedMessage,
 };
 use crate::external::Storage;
 
 pub const KEYSTORE_STORAGE_KEY: &str = "__core__keystore";
 
+/// Versions of the stored keystore:
+/// - `0`: `[[<signer name>, <signer state>], ...]`
+/// - `1`: `{ "version": 1, "signers": [[<signer name>, <signer state>], ...] }`
+pub const KEYSTORE_SCHEMA: StorageSchema = StorageSchema {
+    key: KEYSTORE_STORAGE_KEY,
+    steps: &[migrate_keystore_v0 as MigrationStep],
+};
+
+fn migrate_keystore_v0(data: serde_json::Value) -> Result<serde_json::Value> {
+    match data {
+        serde_json::Value::Array(signers) => Ok(serde_json::json!({
+            "version": 1,
+            "signers": signers,
+        })),
+        _ => Err(MigrationError::InvalidData.into()),
+    }
+}
+
 pub struct KeyStore {
     state: RwLock<KeyStoreState>,
     storage: Arc<dyn Storage>,
     password_cache: PasswordCache,
+    kdf_params: KdfParams,
 }
 
 impl KeyStore {
@@ -32,6 +54,7 @@ impl KeyStore {
         KeyStoreBuilder {
             signers: Default::default(),
             signer_types: Default::default(),
+            kdf_params: Default::default(),
         }
     }
 
@@ -44,6 +67,11 @@ impl KeyStore {
         self.password_cache.contains(id, duration)
     }
 
+    /// Params, used for the new (or re-encrypted) keys
+    pub fn kdf_params(&self) -> KdfParams {
+        self.kdf_params
+    }
+
     pub async fn reload(&self) -> Result<()> {
         let data = KeyStoreBuilder::load_stored_data(&self.storage).await?;
 
@@ -75,11 +103,19 @@ impl KeyStore {
         Ok(())
     }
 
+    /// Returns all ed25519 entries, which can be used for wallets.
+    ///
+    /// See [`KeyStore::get_entries_by_algorithm`] for other keys
     pub async fn get_entries(&self) -> Vec<KeyStoreEntry> {
+        self.get_entries_by_algorithm(KeyAlgorithm::Ed25519).await
+    }
+
+    pub async fn get_entries_by_algorithm(&self, algorithm: KeyAlgorithm) -> Vec<KeyStoreEntry> {
         let state = self.state.read().await;
         state
             .entries
             .values()
+            .filter(|(_, signer_entry)| signer_entry.algorithm == algorithm)
             .filter_map(|(type_id, signer_entry)| {
                 Some(KeyStoreEntry::from_signer_entry(
                     state.signers.get(type_id)?.0.clone(),
@@ -163,6 +199,25 @@ impl KeyStore {
         Ok(KeyStoreEntry::from_signer_entry(signer_name, signer_entry))
     }
 
+    /// Re-encrypts the key with the keystore KDF params if it uses different ones
+    /// (e.g. legacy entries)
+    async fn upgrade_key<T>(&self, input: &T::SignInput) -> Result<()>
+    where
+        T: Signer,
+    {
+        let mut state = self.state.write().await;
+
+        let (_, signer): (_, &mut T) = state.get_signer_mut::<T>()?;
+
+        let ctx = SignerContext {
+            password_cache: &self.password_cache,
+        };
+        if signer.upgrade_key(ctx, input)? {
+            self.save(&state.signers).await?;
+        }
+        Ok(())
+    }
+
     pub async fn export_seed<T>(&self, input: T::ExportSeedInput) -> Result<T::ExportSeedOutput>
     where
         T: Signer,
@@ -218,6 +273,8 @@ impl KeyStore {
     where
         T: Signer,
     {
+        ensure_algorithm::<T>(KeyAlgorithm::Ed25519)?;
+        self.upgrade_key::<T>(&input).await?;
         let state = self.state.read().await;
 
         let ctx = SignerContext {
@@ -234,23 +291,14 @@ impl KeyStore {
             .compute_shared_secrets(ctx, public_keys, input)
             .await?
         {
-            match algorithm {
-                EncryptionAlgorithm::ChaCha20Poly1305 => {
-                    use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
-
-                    let nonce = Nonce::from(rand::thread_rng().gen::<[u8; 12]>());
-                    let encryptor =
-                        ChaCha20Poly1305::new(&Key::clone_from_slice(secret.as_slice()));
-                    let data = encrypt(&encryptor, &nonce, data)?;
-                    result.push(EncryptedData {
-                        algorithm,
-                        source_public_key,
-                        recipient_public_key,
-                        data,
-                        nonce: nonce.to_vec(),
-                    })
-                }
-            }
+            let (data, nonce) = algorithm.encrypt(&secret, data)?;
+            result.push(EncryptedData {
+                algorithm,
+                source_public_key,
+                recipient_public_key,
+                data,
+                nonce,
+            });
         }
         Ok(result)
     }
@@ -259,6 +307,8 @@ impl KeyStore {
     where
         T: Signer,
     {
+        ensure_algorithm::<T>(KeyAlgorithm::Ed25519)?;
+        self.upgrade_key::<T>(&input).await?;
         let state = self.state.read().await;
 
         let ctx = SignerContext {
@@ -272,19 +322,35 @@ impl KeyStore {
             .next()
             .ok_or(KeyStoreError::SharedSecretError)?;
 
-        match data.algorithm {
-            EncryptionAlgorithm::ChaCha20Poly1305 => {
-                use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+        data.algorithm.decrypt(&secret, &data.data, &data.nonce)
+    }
 
-                let nonce = <[u8; 12]>::try_from(data.nonce.as_slice())
-                    .map(Nonce::from)
-                    .map_err(|_| KeyStoreError::InvalidNonce)?;
-                let decryptor = ChaCha20Poly1305::new(&Key::clone_from_slice(secret.as_slice()));
-                let data = decrypt(&decryptor, &nonce, &data.data)?;
+    /// Decrypts the sealed box (see [`crate::crypto::seal`]) with the keystore entry
+    pub async fn open_sealed<T>(&self, data: &[u8], input: T::SignInput) -> Result<Vec<u8>>
+    where
+        T: Signer,
+    {
+        ensure_algorithm::<T>(KeyAlgorithm::Ed25519)?;
+        self.upgrade_key::<T>(&input).await?;
+        let state = self.state.read().await;
 
-                Ok(data)
-            }
-        }
+        let ctx = SignerContext {
+            password_cache: &self.password_cache,
+        };
+        let sealed = SealedBox::parse(data)?;
+        let SharedSecret {
+            source_public_key,
+            secret,
+            ..
+        } = state
+            .get_signer_ref::<T>()?
+            .compute_shared_secrets(ctx, &[sealed.source_public_key()?], input)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(KeyStoreError::SharedSecretError)?;
+
+        sealed.open(&secret, &source_public_key)
     }
 
     pub async fn sign<T>(
@@ -296,6 +362,8 @@ impl KeyStore {
     where
         T: Signer,
     {
+        ensure_algorithm::<T>(KeyAlgorithm::Ed25519)?;
+        self.upgrade_key::<T>(&input).await?;
         let state = self.state.read().await;
 
         let ctx = SignerContext {
@@ -307,6 +375,168 @@ impl KeyStore {
             .await
     }
 
+    pub async fn get_secp256k1_public_key<T>(
+        &self,
+        public_key: &PublicKey,
+    ) -> Result<Secp256k1PublicKey>
+    where
+        T: Secp256k1Signer,
+    {
+        ensure_algorithm::<T>(KeyAlgorithm::Secp256k1)?;
+        let state = self.state.read().await;
+
+        let ctx = SignerContext {
+            password_cache: &self.password_cache,
+        };
+        state
+            .get_signer_ref::<T>()?
+            .get_secp256k1_public_key(ctx, public_key)
+            .await
+    }
+
+    /// Signs the prehashed message with the secp256k1 key
+    pub async fn sign_secp256k1<T>(
+        &self,
+        hash: &[u8; 32],
+        input: T::SignInput,
+    ) -> Result<Secp256k1Signature>
+    where
+        T: Secp256k1Signer,
+    {
+        ensure_algorithm::<T>(KeyAlgorithm::Secp256k1)?;
+        self.upgrade_key::<T>(&input).await?;
+        let state = self.state.read().await;
+
+        let ctx = SignerContext {
+            password_cache: &self.password_cache,
+        };
+        state
+            .get_signer_ref::<T>()?
+            .sign_secp256k1(ctx, hash, input)
+            .await
+    }
+
+    /// Exports entries of all signers (with their metadata) into a single
+    /// password-protected container, suitable for a full backup.
+    ///
+    /// The container key is derived with the configured KDF params
+    pub async fn export_encrypted(&self, password: &str) -> Result<String> {
+        use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce};
+
+        let state = self.state.read().await;
+        let data = Zeroizing::new(serde_json::to_vec(&StoredData(&state.signers))?);
+
+        let mut rng = rand::thread_rng();
+        let kdf = self.kdf_params;
+        let salt = rng.gen::<[u8; EXPORT_SALT_LEN]>().to_vec();
+        let nonce = Nonce::from(rng.gen::<[u8; 12]>());
+
+        let key = kdf.derive_key(password, &salt)?;
+        let encryptor = ChaCha20Poly1305::new(&key);
+        let data = encrypt(&encryptor, &nonce, &data)?;
+
+        let container = ExportedKeyStore {
+            version: EXPORT_VERSION,
+            kdf,
+            salt,
+            nonce: nonce.to_vec(),
+            data,
+        };
+        Ok(serde_json::to_string(&container)?)
+    }
+
+    /// Replaces the state of signers with the entries from the container,
+    /// produced by [`KeyStore::export_encrypted`].
+    ///
+    /// Signers which are not present in the container are left untouched
+    pub async fn import_encrypted(&self, data: &str, password: &str) -> Result<Vec<KeyStoreEntry>> {
+        use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce};
+
+        let container: ExportedKeyStore = serde_json::from_str(data)?;
+        if container.version != EXPORT_VERSION {
+            return Err(KeyStoreError::UnsupportedContainerVersion.into());
+        }
+        // NOTE: params are checked before the key derivation,
+        // so crafted containers can't exhaust the memory or hang the wallet
+        if container.kdf.validate().is_err() || container.salt.len() != EXPORT_SALT_LEN {
+            return Err(KeyStoreError::InvalidContainerParams.into());
+        }
+
+        let nonce = <[u8; 12]>::try_from(container.nonce.as_slice())
+            .map(Nonce::from)
+            .map_err(|_| KeyStoreError::InvalidNonce)?;
+        let key = container.kdf.derive_key(password, &container.salt)?;
+        let decryptor = ChaCha20Poly1305::new(&key);
+        let data = Zeroizing::new(decrypt(&decryptor, &nonce, &container.data)?);
+        let data = serde_json::from_slice::<Vec<(String, String)>>(&data)?;
+
+        let mut state = self.state.write().await;
+
+        // Keep the previous state to restore it if some signer fails to load
+        let backup = state
+            .signers
+            .iter()
+            .map(|(type_id, (_, signer))| (*type_id, signer.store_state()))
+            .collect::<Vec<_>>();
+
+        for (name, data) in &data {
+            let signer = state
+                .signers
+                .values_mut()
+                .find(|(signer_name, _)| signer_name == name);
+
+            if let Some((_, signer)) = signer {
+                if let Err(e) = signer.load_state(data) {
+                    for (type_id, data) in &backup {
+                        if let Some((_, signer)) = state.signers.get_mut(type_id) {
+                            signer.load_state(data).ok();
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut entries = HashMap::new();
+        for (type_id, (_, signer)) in &state.signers {
+            entries.extend(
+                signer
+                    .get_entries()
+                    .into_iter()
+                    .map(|entry| entry.into_plain(*type_id)),
+            );
+        }
+        state.entries = entries;
+
+        self.password_cache.reset();
+        self.save(&state.signers).await?;
+
+        Ok(state
+            .entries
+            .values()
+            .filter_map(|(type_id, signer_entry)| {
+                Some(KeyStoreEntry::from_signer_entry(
+                    state.signers.get(type_id)?.0.clone(),
+                    signer_entry.clone(),
+                ))
+            })
+            .collect())
+    }
+
+    /// Signs the message hash (extended with `signature_id`) and creates the signed message
+    pub async fn sign_message<T>(
+        &self,
+        message: &dyn UnsignedMessage,
+        signature_id: Option<SignatureId>,
+        input: T::SignInput,
+    ) -> Result<SignedMessage>
+    where
+        T: Signer,
+    {
+        let signature = self.sign::<T>(message.hash(), signature_id, input).await?;
+        message.sign(&signature)
+    }
+
     pub async fn remove_key(&self, public_key: &PublicKey) -> Result<Option<KeyStoreEntry>> {
         let mut state = self.state.write().await;
 
@@ -366,28 +596,37 @@ impl KeyStore {
     }
 
     async fn save(&self, signers: &SignersMap) -> Result<()> {
-        use serde::ser::SerializeSeq;
+        #[derive(Serialize)]
+        struct VersionedStoredData<'a> {
+            version: u32,
+            signers: StoredData<'a>,
+        }
+
+        let data = serde_json::to_string(&VersionedStoredData {
+            version: KEYSTORE_SCHEMA.current_version(),
+            signers: StoredData(signers),
+        })?;
+        self.storage.set(KEYSTORE_STORAGE_KEY, &data).await
+    }
+}
+
+struct StoredData<'a>(&'a SignersMap);
 
-        struct StoredData<'a>(&'a SignersMap);
+impl Serialize for StoredData<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeSeq;
 
         #[derive(Serialize)]
         struct StoredDataItem<'a>(&'a str, &'a str);
 
-        impl Serialize for StoredData<'_> {
-            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-            where
-                S: Serializer,
-            {
-                let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
-                for (name, signer) in self.0.values() {
-                    seq.serialize_element(&StoredDataItem(name.as_str(), &signer.store_state()))?;
-                }
-                seq.end()
-            }
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for (name, signer) in self.0.values() {
+            seq.serialize_element(&StoredDataItem(name.as_str(), &signer.store_state()))?;
         }
-
-        let data = serde_json::to_string(&StoredData(signers))?;
-        self.storage.set(KEYSTORE_STORAGE_KEY, &data).await
+        seq.end()
     }
 }
 
@@ -447,6 +686,7 @@ pub struct KeyStoreEntry {
     #[serde(with = "nekoton_utils::serde_public_key")]
     pub master_key: PublicKey,
     pub account_id: u16,
+    pub algorithm: KeyAlgorithm,
 }
 
 impl KeyStoreEntry {
@@ -457,6 +697,7 @@ impl KeyStoreEntry {
             public_key: signer_entry.public_key,
             master_key: signer_entry.master_key,
             account_id: signer_entry.account_id,
+            algorithm: signer_entry.algorithm,
         }
     }
 }
@@ -464,6 +705,7 @@ impl KeyStoreEntry {
 pub struct KeyStoreBuilder {
     signers: HashMap<String, (Box<dyn SignerStorage>, TypeId)>,
     signer_types: HashSet<TypeId>,
+    kdf_params: KdfParams,
 }
 
 type BuilderSignersMap = HashMap<String, (Box<dyn SignerStorage>, TypeId)>;
@@ -488,8 +730,17 @@ impl KeyStoreBuilder {
         Ok(self)
     }
 
+    /// Sets params for the new (or re-encrypted) keys.
+    ///
+    /// Existing keys are upgraded to them on the next unlock
+    pub fn with_kdf_params(mut self, kdf_params: KdfParams) -> Result<Self> {
+        kdf_params.validate()?;
+        self.kdf_params = kdf_params;
+        Ok(self)
+    }
+
     pub fn verify(mut self, data: &str) -> Result<()> {
-        let data = serde_json::from_str::<Vec<(String, String)>>(data)?;
+        let data = parse_stored_data(data)?;
         for (name, data) in data {
             if let Some((storage, _)) = self.signers.get_mut(&name) {
                 storage.load_state(&data)?;
@@ -516,6 +767,10 @@ impl KeyStoreBuilder {
             }
         }
 
+        for (storage, _) in self.signers.values_mut() {
+            storage.set_kdf_params(self.kdf_params);
+        }
+
         Ok(KeyStore {
             state: RwLock::new(KeyStoreState {
                 signers: transpose_signers(self.signers),
@@ -523,6 +778,7 @@ impl KeyStoreBuilder {
             }),
             storage,
             password_cache: PasswordCache::new(),
+            kdf_params: self.kdf_params,
         })
     }
 
@@ -544,6 +800,10 @@ impl KeyStoreBuilder {
             }
         }
 
+        for (storage, _) in self.signers.values_mut() {
+            storage.set_kdf_params(self.kdf_params);
+        }
+
         KeyStore {
             state: RwLock::new(KeyStoreState {
                 signers: transpose_signers(self.signers),
@@ -551,20 +811,57 @@ impl KeyStoreBuilder {
             }),
             storage,
             password_cache: PasswordCache::new(),
+            kdf_params: self.kdf_params,
         }
     }
 
     async fn load_stored_data(storage: &Arc<dyn Storage>) -> Result<Vec<(String, String)>> {
         match storage.get(KEYSTORE_STORAGE_KEY).await? {
-            Some(data) => {
-                let data = serde_json::from_str(&data)?;
-                Ok(data)
-            }
+            Some(data) => parse_stored_data(&data),
             None => Ok(Default::default()),
         }
     }
 }
 
+/// Parses stored signers states (of any known version)
+fn parse_stored_data(data: &str) -> Result<Vec<(String, String)>> {
+    #[derive(Deserialize)]
+    struct VersionedStoredData {
+        signers: Vec<(String, String)>,
+    }
+
+    let data = KEYSTORE_SCHEMA.migrate(data)?;
+    Ok(serde_json::from_value::<VersionedStoredData>(data)?.signers)
+}
+
+const EXPORT_VERSION: u8 = 1;
+const EXPORT_SALT_LEN: usize = 32;
+
+/// Versioned password-protected keystore backup
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedKeyStore {
+    version: u8,
+    kdf: KdfParams,
+    #[serde(with = "serde_bytes_base64")]
+    salt: Vec<u8>,
+    #[serde(with = "serde_bytes_base64")]
+    nonce: Vec<u8>,
+    #[serde(with = "serde_bytes_base64")]
+    data: Vec<u8>,
+}
+
+fn ensure_algorithm<T: Signer>(expected: KeyAlgorithm) -> Result<(), KeyStoreError> {
+    if T::ALGORITHM == expected {
+        Ok(())
+    } else {
+        Err(KeyStoreError::AlgorithmMismatch {
+            expected,
+            found: T::ALGORITHM,
+        })
+    }
+}
+
 fn transpose_signers(signers: BuilderSignersMap) -> SignersMap {
     signers
         .into_iter()
@@ -586,6 +883,15 @@ pub enum KeyStoreError {
     SharedSecretError,
     #[error("Invalid nonce")]
     InvalidNonce,
+    #[error("Unsupported container version")]
+    UnsupportedContainerVersion,
+    #[error("Invalid container params")]
+    InvalidContainerParams,
+    #[error("Key algorithm mismatch: expected {expected}, found {found}")]
+    AlgorithmMismatch {
+        expected: KeyAlgorithm,
+        found: KeyAlgorithm,
+    },
 }
 
 #[cfg(test)]
@@ -595,39 +901,10 @@ mod tests {
         EncryptedKeyCreateInput, EncryptedKeyPassword, EncryptedKeySigner, MnemonicType, Password,
         PasswordCacheBehavior,
     };
-    use std::collections::HashMap;
+    use crate::external::tests::TestStorage;
 
     use super::*;
 
-    #[derive(Default)]
-    struct TestStorage(parking_lot::Mutex<HashMap<String, String>>);
-
-    #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
-    #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
-    impl Storage for TestStorage {
-        async fn get(&self, key: &str) -> Result<Option<String>> {
-            Ok(self.0.lock().get(key).cloned())
-        }
-
-        async fn set(&self, key: &str, value: &str) -> Result<()> {
-            self.set_unchecked(key, value);
-            Ok(())
-        }
-
-        fn set_unchecked(&self, key: &str, value: &str) {
-            self.0.lock().insert(key.to_string(), value.to_string());
-        }
-
-        async fn remove(&self, key: &str) -> Result<()> {
-            self.remove_unchecked(key);
-            Ok(())
-        }
-
-        fn remove_unchecked(&self, key: &str) {
-            self.0.lock().remove(key);
-        }
-    }
-
     const TEST_MNEMONICS: [&str; 2] = [
         "admit cheap engage ancient audit drink mammal mobile fashion aspect rapid else",
         "stuff chuckle dirt pig health refuse foam liquid around cream undo forum",
@@ -653,6 +930,7 @@ mod tests {
 
         let first_key = keystore
             .add_key::<DerivedKeySigner>(DerivedKeyCreateInput::Import {
+                passphrase: None,
                 key_name: None,
                 phrase: TEST_MNEMONICS[0].into(),
                 password: useless_password.clone(),
@@ -679,6 +957,7 @@ mod tests {
                 &[second_key.public_key],
                 EncryptionAlgorithm::ChaCha20Poly1305,
                 DerivedKeyPassword::ByPublicKey {
+                    passphrase: None,
                     master_key: first_key.master_key,
                     public_key: first_key.public_key,
                     password: Password::FromCache,
@@ -709,5 +988,280 @@ mod tests {
             .await
             .unwrap();
         assert_eq!(data, TEST_DATA);
+
+        // Check ephemeral key encryption (ephemeral -> first)
+        let encrypted_data = crate::crypto::encrypt_with_ephemeral_key(
+            TEST_DATA,
+            &first_key.public_key,
+            EncryptionAlgorithm::XSalsa20Poly1305,
+        )
+        .unwrap();
+        assert_ne!(encrypted_data.source_public_key, first_key.public_key);
+        assert_eq!(encrypted_data.nonce.len(), 24);
+
+        let data = keystore
+            .decrypt::<DerivedKeySigner>(
+                &encrypted_data,
+                DerivedKeyPassword::ByPublicKey {
+                    passphrase: None,
+                    master_key: first_key.master_key,
+                    public_key: first_key.public_key,
+                    password: Password::FromCache,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(data, TEST_DATA);
+
+        // Check sealed box (ephemeral -> second)
+        let sealed = crate::crypto::seal(TEST_DATA, &second_key.public_key).unwrap();
+        let data = keystore
+            .open_sealed::<EncryptedKeySigner>(
+                &sealed,
+                EncryptedKeyPassword {
+                    public_key: second_key.public_key,
+                    password: Password::FromCache,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(data, TEST_DATA);
+    }
+
+    #[tokio::test]
+    async fn export_import_encrypted() {
+        const KDF_PARAMS: KdfParams = KdfParams::Argon2id {
+            memory_cost: 8 * 1024,
+            time_cost: 1,
+            parallelism: 1,
+        };
+
+        async fn make_keystore() -> KeyStore {
+            KeyStore::builder()
+                .with_signer("master_key", DerivedKeySigner::new())
+                .unwrap()
+                .with_signer("encrypted_key", EncryptedKeySigner::new())
+                .unwrap()
+                .with_kdf_params(KDF_PARAMS)
+                .unwrap()
+                .load(Arc::new(TestStorage::default()))
+                .await
+                .unwrap()
+        }
+
+        let password = Password::Explicit {
+            password: "test".into(),
+            cache_behavior: PasswordCacheBehavior::Nop,
+        };
+
+        let keystore = make_keystore().await;
+        keystore
+            .add_key::<DerivedKeySigner>(DerivedKeyCreateInput::Import {
+                passphrase: None,
+                key_name: Some("first".to_owned()),
+                phrase: TEST_MNEMONICS[0].into(),
+                password: password.clone(),
+            })
+            .await
+            .unwrap();
+        keystore
+            .add_key::<EncryptedKeySigner>(EncryptedKeyCreateInput {
+                name: Some("second".to_owned()),
+                phrase: TEST_MNEMONICS[1].into(),
+                mnemonic_type: MnemonicType::Bip39(Bip39MnemonicData::labs_old(0)),
+                password,
+            })
+            .await
+            .unwrap();
+
+        let exported = keystore.export_encrypted("backup password").await.unwrap();
+        assert!(!exported.contains("first"));
+
+        // Configured KDF params are used for the container
+        let container: ExportedKeyStore = serde_json::from_str(&exported).unwrap();
+        assert_eq!(container.kdf, KDF_PARAMS);
+
+        let other = make_keystore().await;
+        assert!(other.import_encrypted(&exported, "wrong").await.is_err());
+        assert!(other.get_entries().await.is_empty());
+
+        // Crafted KDF params are rejected before the key derivation
+        let craft = |field: &str, value: serde_json::Value| {
+            let mut crafted: serde_json::Value = serde_json::from_str(&exported).unwrap();
+            crafted["kdf"][field] = value;
+            crafted.to_string()
+        };
+        for crafted in [
+            craft("memory_cost", u32::MAX.into()),
+            craft("time_cost", u32::MAX.into()),
+            craft("memory_cost", 1.into()),
+        ] {
+            let err = other
+                .import_encrypted(&crafted, "backup password")
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<KeyStoreError>(),
+                Some(KeyStoreError::InvalidContainerParams)
+            ));
+        }
+
+        let mut imported = other
+            .import_encrypted(&exported, "backup password")
+            .await
+            .unwrap();
+        imported.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut expected = keystore.get_entries().await;
+        expected.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(imported.len(), 2);
+        for (imported, expected) in imported.iter().zip(&expected) {
+            assert_eq!(imported.name, expected.name);
+            assert_eq!(imported.signer_name, expected.signer_name);
+            assert_eq!(imported.public_key, expected.public_key);
+        }
+        assert_eq!(other.get_entries().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn upgrade_kdf_params() {
+        const KDF_PARAMS: KdfParams = KdfParams::Argon2id {
+            memory_cost: 8 * 1024,
+            time_cost: 1,
+            parallelism: 1,
+        };
+
+        let storage = Arc::new(TestStorage::default());
+        let password = Password::Explicit {
+            password: "test".into(),
+            cache_behavior: PasswordCacheBehavior::Nop,
+        };
+
+        let legacy = KeyStore::builder()
+            .with_signer("master_key", DerivedKeySigner::new())
+            .unwrap()
+            .load(storage.clone())
+            .await
+            .unwrap();
+        let entry = legacy
+            .add_key::<DerivedKeySigner>(DerivedKeyCreateInput::Import {
+                passphrase: None,
+                key_name: None,
+                phrase: TEST_MNEMONICS[0].into(),
+                password: password.clone(),
+            })
+            .await
+            .unwrap();
+
+        assert!(KeyStore::builder()
+            .with_kdf_params(KdfParams::Pbkdf2 { iterations: 1 })
+            .is_err());
+
+        let keystore = KeyStore::builder()
+            .with_signer("master_key", DerivedKeySigner::new())
+            .unwrap()
+            .with_kdf_params(KDF_PARAMS)
+            .unwrap()
+            .load(storage.clone())
+            .await
+            .unwrap();
+
+        let input = || DerivedKeyPassword::ByPublicKey {
+            passphrase: None,
+            master_key: entry.master_key,
+            public_key: entry.public_key,
+            password: password.clone(),
+        };
+
+        let data = storage.get(KEYSTORE_STORAGE_KEY).await.unwrap().unwrap();
+        assert!(!data.contains("argon2id"));
+
+        // Unlocking the key upgrades it
+        keystore
+            .sign::<DerivedKeySigner>(b"test", None, input())
+            .await
+            .unwrap();
+
+        let data = storage.get(KEYSTORE_STORAGE_KEY).await.unwrap().unwrap();
+        assert!(data.contains("argon2id"));
+
+        keystore
+            .sign::<DerivedKeySigner>(b"test", None, input())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn secp256k1_entries_are_separated() {
+        use crate::crypto::{Secp256k1KeyCreateInput, Secp256k1KeyPassword, Secp256k1KeySigner};
+
+        let keystore = KeyStore::builder()
+            .with_signer("master_key", DerivedKeySigner::new())
+            .unwrap()
+            .with_signer("secp256k1_key", Secp256k1KeySigner::new())
+            .unwrap()
+            .load(Arc::new(TestStorage::default()))
+            .await
+            .unwrap();
+
+        let password = Password::Explicit {
+            password: "test".into(),
+            cache_behavior: PasswordCacheBehavior::Store(Duration::from_secs(1000)),
+        };
+
+        keystore
+            .add_key::<DerivedKeySigner>(DerivedKeyCreateInput::Import {
+                passphrase: None,
+                key_name: None,
+                phrase: TEST_MNEMONICS[0].into(),
+                password: password.clone(),
+            })
+            .await
+            .unwrap();
+        let entry = keystore
+            .add_key::<Secp256k1KeySigner>(Secp256k1KeyCreateInput {
+                name: None,
+                secret: None,
+                password: password.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(entry.algorithm, KeyAlgorithm::Secp256k1);
+
+        // Only ed25519 keys can be used for wallets
+        let entries = keystore.get_entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].algorithm, KeyAlgorithm::Ed25519);
+
+        let entries = keystore
+            .get_entries_by_algorithm(KeyAlgorithm::Secp256k1)
+            .await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].public_key, entry.public_key);
+
+        keystore
+            .get_secp256k1_public_key::<Secp256k1KeySigner>(&entry.public_key)
+            .await
+            .unwrap();
+
+        let err = keystore
+            .sign::<Secp256k1KeySigner>(
+                b"test",
+                None,
+                Secp256k1KeyPassword {
+                    public_key: entry.public_key,
+                    password,
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<KeyStoreError>(),
+            Some(KeyStoreError::AlgorithmMismatch {
+                expected: KeyAlgorithm::Ed25519,
+                found: KeyAlgorithm::Secp256k1,
+            })
+        ));
     }
 }
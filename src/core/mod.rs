@@ -2,22 +2,38 @@ use anyhow::Result;
 use nekoton_utils::*;
 use serde::{Deserialize, Serialize};
 
-pub use self::contract_subscription::{ContractSubscription, TransactionExecutionOptions};
+pub use self::contract_subscription::{
+    ContractSubscription, ContractSubscriptionSnapshot, TransactionExecutionOptions,
+};
 use self::models::PollingMethod;
 use crate::transport::Transport;
 
+pub mod abi_registry;
 pub mod accounts_storage;
+#[cfg(feature = "gql_transport")]
+pub mod block_walker;
+pub mod config;
 pub mod contract_subscription;
 pub mod dens;
+pub mod depool;
 pub mod generic_contract;
+pub mod history_export;
 pub mod keystore;
 pub use super::models;
 pub mod jetton_wallet;
+pub mod network_params;
 pub mod nft_wallet;
 pub mod owners_cache;
 pub mod parsing;
+pub mod payment_uri;
+pub mod pending_messages;
+pub mod state_diff;
+pub mod stever;
+pub mod storage_migrator;
+pub mod subscription_manager;
 pub mod token_wallet;
 pub mod ton_wallet;
+pub mod transactions_storage;
 pub mod transactions_tree;
 pub mod utils;
 
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use nekoton_utils::Clock;
+use ton_block::{GasLimitsPrices, MsgAddressInt, MsgForwardPrices};
+use ton_executor::BlockchainConfig;
+
+use crate::transport::Transport;
+
+/// Gas and forwarding prices of the network.
+///
+/// Built from the blockchain config params 20/21 (gas prices) and 24/25 (message forwarding prices)
+#[derive(Debug, Clone)]
+pub struct NetworkParams {
+    mc_gas_prices: GasLimitsPrices,
+    gas_prices: GasLimitsPrices,
+    mc_fwd_prices: MsgForwardPrices,
+    fwd_prices: MsgForwardPrices,
+}
+
+impl NetworkParams {
+    pub fn from_config(config: &BlockchainConfig) -> Self {
+        Self {
+            mc_gas_prices: config.get_gas_config(true).clone(),
+            gas_prices: config.get_gas_config(false).clone(),
+            mc_fwd_prices: config.get_fwd_prices(true).clone(),
+            fwd_prices: config.get_fwd_prices(false).clone(),
+        }
+    }
+
+    /// Fetches the params using the config, cached by the transport
+    pub async fn fetch(transport: &dyn Transport, clock: &dyn Clock) -> Result<Self> {
+        let config = transport.get_blockchain_config(clock, false).await?;
+        Ok(Self::from_config(&config))
+    }
+
+    pub fn gas_prices(&self, is_masterchain: bool) -> &GasLimitsPrices {
+        if is_masterchain {
+            &self.mc_gas_prices
+        } else {
+            &self.gas_prices
+        }
+    }
+
+    pub fn fwd_prices(&self, is_masterchain: bool) -> &MsgForwardPrices {
+        if is_masterchain {
+            &self.mc_fwd_prices
+        } else {
+            &self.fwd_prices
+        }
+    }
+
+    /// Computes the fee for the specified amount of gas
+    pub fn compute_gas_fee(&self, is_masterchain: bool, gas_used: u64) -> u128 {
+        let prices = self.gas_prices(is_masterchain);
+        let flat_gas_price = prices.flat_gas_price as u128;
+        if gas_used <= prices.flat_gas_limit {
+            return flat_gas_price;
+        }
+
+        let gas = (gas_used - prices.flat_gas_limit) as u128;
+        flat_gas_price + ((gas * prices.gas_price as u128 + 0xffff) >> 16)
+    }
+
+    /// Computes the forwarding fee for the message of the specified size
+    pub fn compute_fwd_fee(&self, is_masterchain: bool, cells: u64, bits: u64) -> u128 {
+        let prices = self.fwd_prices(is_masterchain);
+        let price =
+            prices.bit_price as u128 * bits as u128 + prices.cell_price as u128 * cells as u128;
+        prices.lump_price as u128 + ((price + 0xffff) >> 16)
+    }
+
+    /// Scales the attached amount, chosen for the Everscale reference gas price,
+    /// to the current gas price of the workchain of the destination.
+    ///
+    /// NOTE: must not be used for amounts, chosen for other networks (e.g. TON jettons)
+    pub fn scale_attached_amount(&self, destination: &MsgAddressInt, amount: u128) -> u128 {
+        let is_masterchain = destination.is_masterchain();
+        let reference = if is_masterchain {
+            REFERENCE_MC_GAS_PRICE
+        } else {
+            REFERENCE_GAS_PRICE
+        };
+
+        let gas_price = self.gas_prices(is_masterchain).gas_price as u128;
+        if gas_price == 0 {
+            return amount;
+        }
+        amount.saturating_mul(gas_price) / reference as u128
+    }
+}
+
+/// Basechain gas price (1000 nano per gas unit), for which hardcoded attached amounts were chosen
+const REFERENCE_GAS_PRICE: u64 = 1000 << 16;
+/// Masterchain gas price (10000 nano per gas unit), for which hardcoded attached amounts were chosen
+const REFERENCE_MC_GAS_PRICE: u64 = 10000 << 16;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_fees() {
+        let mut params = NetworkParams::from_config(&BlockchainConfig::default());
+        params.gas_prices = GasLimitsPrices {
+            gas_price: REFERENCE_GAS_PRICE,
+            flat_gas_limit: 100,
+            flat_gas_price: 100_000,
+            ..params.gas_prices
+        };
+        params.fwd_prices = MsgForwardPrices {
+            lump_price: 1_000_000,
+            bit_price: 1 << 16,
+            cell_price: 100 << 16,
+            ..params.fwd_prices
+        };
+
+        assert_eq!(params.compute_gas_fee(false, 50), 100_000);
+        assert_eq!(params.compute_gas_fee(false, 1100), 100_000 + 1_000_000);
+        assert_eq!(params.compute_fwd_fee(false, 2, 100), 1_000_000 + 300);
+
+        let address = MsgAddressInt::default();
+        assert_eq!(params.scale_attached_amount(&address, 1_000), 1_000);
+
+        params.gas_prices.gas_price = REFERENCE_GAS_PRICE * 2;
+        assert_eq!(params.scale_attached_amount(&address, 1_000), 2_000);
+    }
+}
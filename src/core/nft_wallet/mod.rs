@@ -7,19 +7,18 @@ use nekoton_contracts::tip4_1::nft_contract::{self, GetInfoOutputs, NftCallbackP
 use nekoton_contracts::tip4_3::index_contract::IndexGetInfoOutputs;
 use nekoton_contracts::*;
 use nekoton_utils::Clock;
-use ton_block::{MsgAddressInt, Serializable};
-use ton_types::{BuilderData, Cell, UInt256};
+use ton_block::MsgAddressInt;
+use ton_types::{Cell, UInt256};
 
 use crate::core::models::{
     NftTransaction, PendingTransaction, Transaction, TransactionWithData, TransactionsBatchInfo,
 };
+use crate::core::network_params::NetworkParams;
 use crate::core::parsing::parse_nft_transaction;
 use crate::core::{ContractSubscription, InternalMessage};
 use crate::transport::models::{ExistingContract, RawContractState, RawTransaction};
 use crate::transport::Transport;
 
-const NFT_STAMP: &[u8; 3] = b"nft";
-
 pub struct NftCollection {
     transport: Arc<dyn Transport>,
     collection_address: MsgAddressInt,
@@ -100,6 +99,8 @@ pub struct Nft {
     manager: MsgAddressInt,
     interfaces: NftInterfaces,
     json_info: Option<String>,
+    /// `None` if the config was not fetched yet, unscaled amounts are used then
+    network_params: Option<NetworkParams>,
     contract_subscription: ContractSubscription,
     handler: Arc<dyn NftSubscriptionHandler>,
 }
@@ -155,6 +156,10 @@ impl Nft {
             None
         };
 
+        let network_params = NetworkParams::fetch(transport.as_ref(), clock.as_ref())
+            .await
+            .ok();
+
         let contract_subscription = ContractSubscription::subscribe(
             clock.clone(),
             transport,
@@ -180,6 +185,7 @@ impl Nft {
             manager: info.manager,
             interfaces,
             json_info,
+            network_params,
             contract_subscription,
             handler,
         })
@@ -219,7 +225,6 @@ impl Nft {
         send_gas_to: MsgAddressInt,
         callbacks: BTreeMap<MsgAddressInt, NftCallbackPayload>,
     ) -> Result<InternalMessage> {
-        const ATTACHED_AMOUNT: u128 = 1_000_000_000; // 1 TON
         let (function, input) = MessageBuilder::new(nft_contract::transfer())
             .arg(to)
             .arg(send_gas_to)
@@ -233,7 +238,7 @@ impl Nft {
         Ok(InternalMessage {
             source: Some(self.owner.clone()),
             destination: self.address().clone(),
-            amount: ATTACHED_AMOUNT,
+            amount: self.attached_amount(),
             bounce: true,
             body,
         })
@@ -245,7 +250,6 @@ impl Nft {
         send_gas_to: MsgAddressInt,
         callbacks: BTreeMap<MsgAddressInt, NftCallbackPayload>,
     ) -> Result<InternalMessage> {
-        const ATTACHED_AMOUNT: u128 = 1_000_000_000; // 1 TON
         let (function, input) = MessageBuilder::new(nft_contract::change_manager())
             .arg(new_manager)
             .arg(send_gas_to)
@@ -259,7 +263,7 @@ impl Nft {
         Ok(InternalMessage {
             source: Some(self.owner.clone()),
             destination: self.address().clone(),
-            amount: ATTACHED_AMOUNT,
+            amount: self.attached_amount(),
             bounce: true,
             body,
         })
@@ -271,7 +275,6 @@ impl Nft {
         send_gas_to: MsgAddressInt,
         callbacks: BTreeMap<MsgAddressInt, NftCallbackPayload>,
     ) -> Result<InternalMessage> {
-        const ATTACHED_AMOUNT: u128 = 1_000_000_000; // 1 TON
         let (function, input) = MessageBuilder::new(nft_contract::change_owner())
             .arg(new_owner)
             .arg(send_gas_to)
@@ -285,12 +288,22 @@ impl Nft {
         Ok(InternalMessage {
             source: Some(self.owner.clone()),
             destination: self.address().clone(),
-            amount: ATTACHED_AMOUNT,
+            amount: self.attached_amount(),
             bounce: true,
             body,
         })
     }
 
+    /// Amount attached to the NFT management messages (1 EVER at the reference gas price)
+    fn attached_amount(&self) -> u128 {
+        const ATTACHED_AMOUNT: u128 = 1_000_000_000;
+        self.network_params
+            .as_ref()
+            .map_or(ATTACHED_AMOUNT, |params| {
+                params.scale_attached_amount(&self.address, ATTACHED_AMOUNT)
+            })
+    }
+
     pub async fn send(
         &mut self,
         message: &ton_block::Message,
@@ -300,6 +313,12 @@ impl Nft {
     }
 
     pub async fn refresh(&mut self) -> Result<()> {
+        let transport = self.contract_subscription.transport();
+        // NOTE: previous params are kept if the config is unavailable
+        if let Ok(params) = NetworkParams::fetch(transport.as_ref(), self.clock.as_ref()).await {
+            self.network_params = Some(params);
+        }
+
         let handler = self.handler.as_ref();
 
         self.contract_subscription
@@ -464,23 +483,7 @@ impl CollectionContractState<'_> {
         owner: &MsgAddressInt,
         code_index: Cell,
     ) -> Result<UInt256> {
-        let mut builder = BuilderData::new();
-
-        let owner_cell = owner.serialize()?;
-        let collection_cell = self.0.account.addr.serialize()?;
-
-        builder.append_raw(collection_cell.data(), collection_cell.bit_length())?;
-        builder.append_raw(owner_cell.data(), owner_cell.bit_length())?;
-
-        let mut nft = BuilderData::new();
-        nft.append_raw(NFT_STAMP, 24)?;
-
-        builder.checked_append_reference(nft.into_cell()?)?;
-
-        let salt = builder.into_cell()?;
-
-        let cell = nekoton_abi::set_code_salt(code_index, salt)?;
-        Ok(cell.hash(0))
+        tip4_3::compute_index_code_hash(code_index, &self.0.account.addr, owner)
     }
 }
 
@@ -10,7 +10,10 @@ use nekoton_abi::*;
 use nekoton_contracts::tip4_1::nft_contract;
 use nekoton_contracts::{old_tip3, tip3_1};
 
-use crate::core::jetton_wallet::{JETTON_INTERNAL_TRANSFER_OPCODE, JETTON_TRANSFER_OPCODE};
+use crate::core::abi_registry::AbiRegistry;
+use crate::core::jetton_wallet::{
+    JETTON_BURN_OPCODE, JETTON_INTERNAL_TRANSFER_OPCODE, JETTON_TRANSFER_OPCODE,
+};
 use crate::core::models::*;
 use crate::core::ton_wallet::{MultisigType, WalletType};
 
@@ -65,6 +68,92 @@ pub fn parse_payload(payload: ton_types::SliceData) -> Option<KnownPayload> {
     None
 }
 
+/// Transaction with all known data, see [`decode_transactions`]
+#[derive(Debug, Clone)]
+pub struct DecodedTransaction {
+    pub transaction: Transaction,
+    pub info: Option<TransactionAdditionalInfo>,
+    pub token_transaction: Option<TokenWalletTransaction>,
+    pub jetton_transaction: Option<JettonWalletTransaction>,
+    pub nft_transaction: Option<NftTransaction>,
+    /// Decoded bodies of the outgoing messages
+    pub out_payloads: Vec<Option<KnownPayload>>,
+    pub description: TransactionDescription,
+}
+
+/// Decodes raw transaction BOCs (e.g. dumped from an indexer) without any transport.
+///
+/// External messages are parsed as wallet calls only if `wallet_type` is specified.
+/// Each BOC is decoded independently, so one invalid item doesn't affect the others
+pub fn decode_transactions<I, B>(
+    raw_bocs: I,
+    registry: &AbiRegistry,
+    wallet_type: Option<WalletType>,
+) -> Vec<Result<DecodedTransaction>>
+where
+    I: IntoIterator<Item = B>,
+    B: AsRef<[u8]>,
+{
+    raw_bocs
+        .into_iter()
+        .map(|boc| decode_transaction(boc.as_ref(), registry, wallet_type))
+        .collect()
+}
+
+fn decode_transaction(
+    boc: &[u8],
+    registry: &AbiRegistry,
+    wallet_type: Option<WalletType>,
+) -> Result<DecodedTransaction> {
+    let cell = ton_types::deserialize_tree_of_cells(&mut &*boc)?;
+    let hash = cell.repr_hash();
+    let raw = ton_block::Transaction::construct_from_cell(cell)?;
+
+    let is_external = raw
+        .in_msg
+        .as_ref()
+        .and_then(|msg| msg.read_struct().ok())
+        .map(|msg| matches!(msg.header(), ton_block::CommonMsgInfo::ExtInMsgInfo(_)))
+        .unwrap_or_default();
+    let info = match (is_external, wallet_type) {
+        (true, Some(wallet_type)) => {
+            parse_transaction_additional_info_with_registry(&raw, wallet_type, Some(registry))
+        }
+        (true, None) => None,
+        // NOTE: wallet type is only used for external messages
+        (false, _) => parse_transaction_additional_info_with_registry(
+            &raw,
+            WalletType::EverWallet,
+            Some(registry),
+        ),
+    };
+
+    let (token_transaction, jetton_transaction, nft_transaction) = match raw.read_description()? {
+        ton_block::TransactionDescr::Ordinary(description) => (
+            [TokenWalletVersion::Tip3, TokenWalletVersion::OldTip3v4]
+                .into_iter()
+                .find_map(|version| parse_token_transaction(&raw, &description, version)),
+            parse_jetton_transaction(&raw, &description),
+            parse_nft_transaction(&raw, &description),
+        ),
+        _ => (None, None, None),
+    };
+
+    let transaction = Transaction::try_from((hash, raw))?;
+    let out_payloads = registry.parse_out_messages(&transaction);
+    let description = describe_transaction(&transaction, info.as_ref());
+
+    Ok(DecodedTransaction {
+        transaction,
+        info,
+        token_transaction,
+        jetton_transaction,
+        nft_transaction,
+        out_payloads,
+        description,
+    })
+}
+
 pub fn parse_jetton_payload(payload: ton_types::SliceData) -> Option<KnownPayload> {
     let mut payload = payload;
 
@@ -93,6 +182,22 @@ pub fn parse_transaction_additional_info(
     tx: &ton_block::Transaction,
     wallet_type: WalletType,
 ) -> Option<TransactionAdditionalInfo> {
+    parse_transaction_additional_info_with_registry(tx, wallet_type, None)
+}
+
+/// Same as [`parse_transaction_additional_info`], but payloads of wallet transfers
+/// are also decoded with the functions registered in the `registry`
+/// (see [`AbiRegistry::parse_payload`])
+pub fn parse_transaction_additional_info_with_registry(
+    tx: &ton_block::Transaction,
+    wallet_type: WalletType,
+    registry: Option<&AbiRegistry>,
+) -> Option<TransactionAdditionalInfo> {
+    let parse_known_payload = |body: ton_types::SliceData| match registry {
+        Some(registry) => registry.parse_payload(body),
+        None => parse_payload(body),
+    };
+
     let in_msg = tx.in_msg.as_ref()?.read_struct().ok()?;
 
     let int_header = match in_msg.header() {
@@ -113,7 +218,7 @@ pub fn parse_transaction_additional_info(
                         _ => return None,
                     };
 
-                    let known_payload = out_msg.body().and_then(parse_payload);
+                    let known_payload = out_msg.body().and_then(parse_known_payload);
 
                     (
                         Some(recipient.clone()),
@@ -140,7 +245,9 @@ pub fn parse_transaction_additional_info(
                         _ => return None,
                     };
 
-                    let known_payload = out_msg.body().and_then(parse_jetton_payload);
+                    let known_payload = out_msg.body().and_then(|body| {
+                        parse_jetton_payload(body.clone()).or_else(|| registry?.parse_payload(body))
+                    });
 
                     (
                         Some(recipient.clone()),
@@ -162,7 +269,7 @@ pub fn parse_transaction_additional_info(
                             ..
                         }) => (
                             Some(dest.clone()),
-                            parse_payload(ton_types::SliceData::load_cell_ref(payload).ok()?),
+                            parse_known_payload(ton_types::SliceData::load_cell_ref(payload).ok()?),
                         ),
                         _ => (None, None),
                     };
@@ -706,7 +813,10 @@ pub fn parse_jetton_transaction(
 
     let opcode = body.get_next_u32().ok()?;
 
-    if opcode != JETTON_TRANSFER_OPCODE && opcode != JETTON_INTERNAL_TRANSFER_OPCODE {
+    if opcode != JETTON_TRANSFER_OPCODE
+        && opcode != JETTON_INTERNAL_TRANSFER_OPCODE
+        && opcode != JETTON_BURN_OPCODE
+    {
         return None;
     }
 
@@ -732,6 +842,10 @@ pub fn parse_jetton_transaction(
                 tokens: amount,
             },
         )),
+        JETTON_BURN_OPCODE => Some(JettonWalletTransaction::Burn(JettonBurn {
+            response_destination: addr,
+            tokens: amount,
+        })),
         _ => None,
     }
 }
@@ -799,6 +913,123 @@ pub fn parse_nft_transaction(
     }
 }
 
+struct DePoolFunctions {
+    add_ordinary_stake: &'static ton_abi::Function,
+    withdraw_part: &'static ton_abi::Function,
+    withdraw_all: &'static ton_abi::Function,
+    cancel_withdrawal: &'static ton_abi::Function,
+}
+
+impl DePoolFunctions {
+    fn instance() -> &'static Self {
+        use nekoton_contracts::depool::depool_contract;
+
+        static IDS: OnceBox<DePoolFunctions> = OnceBox::new();
+        IDS.get_or_init(|| {
+            Box::new(Self {
+                add_ordinary_stake: depool_contract::add_ordinary_stake(),
+                withdraw_part: depool_contract::withdraw_part(),
+                withdraw_all: depool_contract::withdraw_all(),
+                cancel_withdrawal: depool_contract::cancel_withdrawal(),
+            })
+        })
+    }
+}
+
+/// Parses DePool transaction, related to the specified participant.
+///
+/// Incoming participant requests are parsed first, then DePool notifications
+/// sent to the participant
+pub fn parse_depool_transaction(
+    tx: &ton_block::Transaction,
+    description: &ton_block::TransactionDescrOrdinary,
+    participant: &MsgAddressInt,
+) -> Option<DePoolTransaction> {
+    if description.aborted {
+        return None;
+    }
+
+    let in_msg = tx.in_msg.as_ref()?.read_struct().ok()?;
+    if let Some(header) = in_msg.int_header() {
+        if !header.bounced && in_msg.src_ref() == Some(participant) {
+            return parse_depool_request(in_msg.body()?);
+        }
+    }
+
+    let mut result = None;
+    tx.out_msgs
+        .iterate(|item| {
+            let message = item.0;
+            match message.int_header() {
+                Some(header) if &header.dst == participant => {}
+                _ => return Ok(true),
+            }
+
+            result = message.body().and_then(parse_depool_notification);
+            Ok(result.is_none())
+        })
+        .ok()?;
+    result
+}
+
+fn parse_depool_request(body: SliceData) -> Option<DePoolTransaction> {
+    let function_id = read_function_id(&body).ok()?;
+    let functions = DePoolFunctions::instance();
+
+    if function_id == functions.add_ordinary_stake.input_id {
+        let inputs = functions
+            .add_ordinary_stake
+            .decode_input(body, true, false)
+            .ok()?;
+        inputs
+            .unpack_first()
+            .map(DePoolTransaction::OrdinaryStake)
+            .ok()
+    } else if function_id == functions.withdraw_part.input_id {
+        let inputs = functions
+            .withdraw_part
+            .decode_input(body, true, false)
+            .ok()?;
+        inputs
+            .unpack_first()
+            .map(DePoolTransaction::WithdrawPart)
+            .ok()
+    } else if function_id == functions.withdraw_all.input_id {
+        Some(DePoolTransaction::WithdrawAll)
+    } else if function_id == functions.cancel_withdrawal.input_id {
+        Some(DePoolTransaction::CancelWithdrawal)
+    } else {
+        None
+    }
+}
+
+fn parse_depool_notification(body: SliceData) -> Option<DePoolTransaction> {
+    let function_id = read_function_id(&body).ok()?;
+    let functions = DePoolParticipantFunctions::instance();
+
+    if function_id == functions.on_round_complete.input_id {
+        let inputs = functions
+            .on_round_complete
+            .decode_input(body, true, false)
+            .ok()?;
+
+        DePoolOnRoundCompleteNotification::try_from(InputMessage(inputs))
+            .map(DePoolTransaction::RoundComplete)
+            .ok()
+    } else if function_id == functions.receive_answer.input_id {
+        let inputs = functions
+            .receive_answer
+            .decode_input(body, true, false)
+            .ok()?;
+
+        DePoolReceiveAnswerNotification::try_from(InputMessage(inputs))
+            .map(DePoolTransaction::ReceiveAnswer)
+            .ok()
+    } else {
+        None
+    }
+}
+
 struct TokenWalletFunctions {
     // Incoming
     accept_mint: &'static ton_abi::Function,
@@ -1049,6 +1280,95 @@ mod tests {
         (tx, description)
     }
 
+    #[test]
+    fn test_decode_transactions() {
+        let boc = base64::decode("te6ccgECCAEAAa0AA7V6khRTRyNmt/7uwVMjqWtdzxcZfIjcDUV436UpALijPLAAALi67rDsNGkd3DyaHde6qGSNyU7rxIrKKUFCg2XCiOWm8qj/wgcwAAC4uu6w7BYG3S5AABRh6EgIBQQBAhUECQ7msoAYYehIEQMCAFvAAAAAAAAAAAAAAAABLUUtpEnlC4z33SeGHxRhIq/htUa7i3D8ghbwxhQTn44EAJwnzD0JAAAAAAAAAAAAAwAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAgnI6oOa5hnaAmb92gCQ5BlaDDjAmDoH5UvSJNuK95TfvcajtZ/eBLgOpVpjmuIPSjgFKd0RzU/MXZ1uRuop7DU5HAQGgBgG5aAFSQopo5GzW/93YKmR1LWu54uMvkRuBqK8b9KUgFxRnlwAqSFFNHI2a3/u7BUyOpa13PFxl8iNwNRXjfpSkAuKM8tDuaygABhnMOAAAFxdd1h2EwNulyAAAAABABwAqaSBsb3ZlIG1lbWVzIGFuZCDwn6aA").unwrap();
+
+        let mut decoded = decode_transactions([boc, vec![0; 4]], &AbiRegistry::default(), None);
+        assert_eq!(decoded.len(), 2);
+        assert!(decoded.pop().unwrap().is_err());
+
+        let decoded = decoded.pop().unwrap().unwrap();
+        assert!(matches!(
+            decoded.info,
+            Some(TransactionAdditionalInfo::Comment(_))
+        ));
+        assert!(matches!(
+            decoded.description,
+            TransactionDescription::IncomingTransfer {
+                comment: Some(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn decode_registered_wallet_payload() {
+        use ton_block::Serializable;
+
+        let function = nekoton_abi::FunctionBuilder::new("setValue")
+            .abi_version(ton_abi::contract::ABI_VERSION_2_2)
+            .id(1)
+            .input("value", ton_abi::ParamType::Uint(32))
+            .build();
+        let body = function
+            .encode_internal_input(&[ton_abi::Token::new(
+                "value",
+                ton_abi::TokenValue::Uint(ton_abi::Uint::new(123, 32)),
+            )])
+            .and_then(SliceData::load_builder)
+            .unwrap();
+
+        let mut out_msg = ton_block::Message::with_int_header(Default::default());
+        out_msg.set_body(body);
+
+        let mut tx = Transaction::default();
+        tx.write_in_msg(Some(&ton_block::Message::with_ext_in_header(
+            Default::default(),
+        )))
+        .unwrap();
+        tx.add_out_message(&out_msg).unwrap();
+        tx.write_description(&ton_block::TransactionDescr::Ordinary(Default::default()))
+            .unwrap();
+
+        let known_payload = |info: Option<TransactionAdditionalInfo>| match info {
+            Some(TransactionAdditionalInfo::WalletInteraction(info)) => info.known_payload,
+            _ => panic!("unexpected info"),
+        };
+
+        assert!(known_payload(parse_transaction_additional_info(
+            &tx,
+            WalletType::EverWallet
+        ))
+        .is_none());
+
+        let mut registry = AbiRegistry::default();
+        registry.register_function(function);
+
+        let payload = known_payload(parse_transaction_additional_info_with_registry(
+            &tx,
+            WalletType::EverWallet,
+            Some(&registry),
+        ));
+        assert!(
+            matches!(payload, Some(KnownPayload::Custom(payload)) if payload.name == "setValue")
+        );
+
+        let boc = ton_types::serialize_toc(&tx.serialize().unwrap()).unwrap();
+        let decoded = decode_transactions([boc], &registry, Some(WalletType::EverWallet))
+            .pop()
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            known_payload(decoded.info),
+            Some(KnownPayload::Custom(_))
+        ));
+        assert!(matches!(
+            decoded.out_payloads.as_slice(),
+            [Some(KnownPayload::Custom(_))]
+        ));
+    }
+
     #[test]
     fn test_transaction_with_comment() {
         let tx = Transaction::construct_from_base64("te6ccgECCAEAAa0AA7V6khRTRyNmt/7uwVMjqWtdzxcZfIjcDUV436UpALijPLAAALi67rDsNGkd3DyaHde6qGSNyU7rxIrKKUFCg2XCiOWm8qj/wgcwAAC4uu6w7BYG3S5AABRh6EgIBQQBAhUECQ7msoAYYehIEQMCAFvAAAAAAAAAAAAAAAABLUUtpEnlC4z33SeGHxRhIq/htUa7i3D8ghbwxhQTn44EAJwnzD0JAAAAAAAAAAAAAwAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAgnI6oOa5hnaAmb92gCQ5BlaDDjAmDoH5UvSJNuK95TfvcajtZ/eBLgOpVpjmuIPSjgFKd0RzU/MXZ1uRuop7DU5HAQGgBgG5aAFSQopo5GzW/93YKmR1LWu54uMvkRuBqK8b9KUgFxRnlwAqSFFNHI2a3/u7BUyOpa13PFxl8iNwNRXjfpSkAuKM8tDuaygABhnMOAAAFxdd1h2EwNulyAAAAABABwAqaSBsb3ZlIG1lbWVzIGFuZCDwn6aA").unwrap();
@@ -1059,6 +1379,51 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parse_depool_requests() {
+        use nekoton_contracts::depool::depool_contract;
+
+        let encode = |(function, input): (&ton_abi::Function, Vec<ton_abi::Token>)| {
+            function
+                .encode_internal_input(&input)
+                .and_then(SliceData::load_builder)
+                .unwrap()
+        };
+
+        let body = encode(
+            MessageBuilder::new(depool_contract::add_ordinary_stake())
+                .arg(10_000_000_000u64)
+                .build(),
+        );
+        assert!(matches!(
+            parse_depool_request(body),
+            Some(DePoolTransaction::OrdinaryStake(10_000_000_000))
+        ));
+
+        let body = encode(
+            MessageBuilder::new(depool_contract::withdraw_part())
+                .arg(1_000_000_000u64)
+                .build(),
+        );
+        assert!(matches!(
+            parse_depool_request(body),
+            Some(DePoolTransaction::WithdrawPart(1_000_000_000))
+        ));
+
+        let body = encode(MessageBuilder::new(depool_contract::withdraw_all()).build());
+        assert!(matches!(
+            parse_depool_request(body),
+            Some(DePoolTransaction::WithdrawAll)
+        ));
+
+        let body = encode(MessageBuilder::new(depool_contract::cancel_withdrawal()).build());
+        assert!(matches!(
+            parse_depool_request(body.clone()),
+            Some(DePoolTransaction::CancelWithdrawal)
+        ));
+        assert!(parse_depool_notification(body).is_none());
+    }
+
     #[test]
     fn parse_depool_on_round_complete() {
         let tx = Transaction::construct_from_base64("te6ccgECBgEAATYAA7F6khRTRyNmt/7uwVMjqWtdzxcZfIjcDUV436UpALijPLAAAMhcEbrIEikkn05Ku83ZEENvShriMSDo3Wrh+PZVqEZZFR73UDNgAADIW3VTuCYJRG2wABQiKAMCAQALDERIQEkgAIJy0W3rZziZMBhdOhOSsj9f2V3MqUZWvD39kx9ersOjLDlIhSvIB0KaWZELIJ7zw+I+Sy9Ykv6tSJbSpB49hDcHiQEBoAQBq0gBd5fv1pbeJAd0Wp/qbrvGFKgRUCYX7z8OHfefqT2CydMAKkhRTRyNmt/7uwVMjqWtdzxcZfIjcDUV436UpALijPLEBAYduXAAABkLgdvLrMEojabABQBbRE1D9QAAAAAAAAB5AAAAAES7c48AAATFd7ZSmgAAAAAAAAAAAAAAAAAAAACCwA==").unwrap();
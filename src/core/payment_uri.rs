@@ -0,0 +1,244 @@
+//! `ton://transfer/<address>?amount=..&text=..&bin=..&jetton=..` payment links
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use nekoton_utils::*;
+use ton_block::MsgAddressInt;
+use ton_types::Cell;
+
+const SCHEME: &str = "ton://transfer/";
+
+/// Parsed payment link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentRequest {
+    pub address: MsgAddressInt,
+    /// Format of the address in the link (contains bounce and testnet flags for packed addresses)
+    pub address_format: AddressFormat,
+    /// Amount in nano EVERs or in the smallest token units if `token_root` is specified
+    pub amount: Option<u128>,
+    /// Text comment, mutually exclusive with `payload`
+    pub comment: Option<String>,
+    /// Token root contract, the transfer is made in native currency if not specified
+    pub token_root: Option<MsgAddressInt>,
+    /// Raw message body, mutually exclusive with `comment`
+    pub payload: Option<Cell>,
+}
+
+impl PaymentRequest {
+    pub fn new(address: MsgAddressInt) -> Self {
+        Self {
+            address,
+            address_format: AddressFormat::USER_FRIENDLY_BOUNCEABLE,
+            amount: None,
+            comment: None,
+            token_root: None,
+            payload: None,
+        }
+    }
+
+    /// Parses and validates the payment link
+    pub fn parse(uri: &str) -> Result<Self> {
+        let uri = uri.trim();
+        let rest = match uri.get(..SCHEME.len()) {
+            Some(scheme) if scheme.eq_ignore_ascii_case(SCHEME) => &uri[SCHEME.len()..],
+            _ => return Err(PaymentUriError::InvalidScheme.into()),
+        };
+
+        let (address, query) = match rest.split_once('?') {
+            Some((address, query)) => (address, Some(query)),
+            None => (rest, None),
+        };
+        let (address, address_format) =
+            parse_address(address).map_err(|_| PaymentUriError::InvalidAddress)?;
+
+        let mut request = Self {
+            address_format,
+            ..Self::new(address)
+        };
+
+        for param in query.into_iter().flat_map(|query| query.split('&')) {
+            if param.is_empty() {
+                continue;
+            }
+
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode(value)?;
+
+            let duplicate = match name {
+                "amount" => request.amount.replace(parse_amount(&value)?).is_some(),
+                "text" => request.comment.replace(value).is_some(),
+                "jetton" => {
+                    let (token_root, _) =
+                        parse_address(&value).map_err(|_| PaymentUriError::InvalidAddress)?;
+                    request.token_root.replace(token_root).is_some()
+                }
+                "bin" => request.payload.replace(parse_payload(&value)?).is_some(),
+                // NOTE: unknown params are skipped for forward compatibility
+                _ => false,
+            };
+            if duplicate {
+                return Err(PaymentUriError::DuplicateParam.into());
+            }
+        }
+
+        request.validate()?;
+        Ok(request)
+    }
+
+    /// Renders the payment link
+    pub fn to_uri(&self) -> Result<String> {
+        self.validate()?;
+
+        let mut uri = SCHEME.to_owned();
+        uri.push_str(&format_address(&self.address, self.address_format)?);
+
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            params.push(("amount", amount.to_string()));
+        }
+        if let Some(token_root) = &self.token_root {
+            params.push(("jetton", format_address(token_root, self.address_format)?));
+        }
+        if let Some(comment) = &self.comment {
+            params.push(("text", comment.clone()));
+        }
+        if let Some(payload) = &self.payload {
+            let boc = ton_types::serialize_toc(payload)?;
+            params.push(("bin", base64::encode_config(boc, base64::URL_SAFE)));
+        }
+
+        for (i, (name, value)) in params.into_iter().enumerate() {
+            uri.push(if i == 0 { '?' } else { '&' });
+            uri.push_str(name);
+            uri.push('=');
+            percent_encode(&value, &mut uri);
+        }
+
+        Ok(uri)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.comment.is_some() && self.payload.is_some() {
+            return Err(PaymentUriError::CommentWithPayload.into());
+        }
+        if let Some(amount) = self.amount {
+            // NOTE: native amounts are limited by the `Grams` size
+            if self.token_root.is_none() && amount > u64::MAX as u128 {
+                return Err(PaymentUriError::InvalidAmount.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_amount(value: &str) -> Result<u128> {
+    if value.is_empty() || !value.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(PaymentUriError::InvalidAmount.into());
+    }
+    value
+        .parse()
+        .map_err(|_| PaymentUriError::InvalidAmount.into())
+}
+
+fn parse_payload(value: &str) -> Result<Cell> {
+    let boc = base64::decode_config(value, base64::URL_SAFE)
+        .or_else(|_| base64::decode(value))
+        .map_err(|_| PaymentUriError::InvalidPayload)?;
+    ton_types::deserialize_tree_of_cells(&mut boc.as_slice())
+        .map_err(|_| PaymentUriError::InvalidPayload.into())
+}
+
+fn percent_encode(value: &str, target: &mut String) {
+    for c in value.bytes() {
+        match c {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                target.push(c as char)
+            }
+            _ => {
+                let _ = write!(target, "%{c:02X}");
+            }
+        }
+    }
+}
+
+fn percent_decode(value: &str) -> Result<String> {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or(PaymentUriError::InvalidEncoding)?;
+            result.push(byte);
+            i += 3;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(result).map_err(|_| PaymentUriError::InvalidEncoding.into())
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum PaymentUriError {
+    #[error("Invalid payment link scheme")]
+    InvalidScheme,
+    #[error("Invalid address")]
+    InvalidAddress,
+    #[error("Invalid amount")]
+    InvalidAmount,
+    #[error("Invalid payload")]
+    InvalidPayload,
+    #[error("Invalid percent encoding")]
+    InvalidEncoding,
+    #[error("Duplicate param")]
+    DuplicateParam,
+    #[error("Comment and payload can't be specified simultaneously")]
+    CommentWithPayload,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn payment_uri_roundtrip() {
+        let address = MsgAddressInt::from_str(
+            "0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb",
+        )
+        .unwrap();
+
+        let mut request = PaymentRequest::new(address.clone());
+        request.amount = Some(1_000_000_000);
+        request.comment = Some("Coffee & cake".to_owned());
+
+        let uri = request.to_uri().unwrap();
+        assert!(uri.ends_with("?amount=1000000000&text=Coffee%20%26%20cake"));
+        assert_eq!(PaymentRequest::parse(&uri).unwrap(), request);
+
+        let parsed =
+            PaymentRequest::parse(&format!("ton://transfer/{address}?amount=10&foo=bar")).unwrap();
+        assert_eq!(parsed.address, address);
+        assert_eq!(parsed.address_format, AddressFormat::Raw);
+        assert_eq!(parsed.amount, Some(10));
+
+        for invalid in [
+            "https://transfer/0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb",
+            "ton://transfer/invalid",
+            "ton://transfer/0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb?amount=-1",
+            "ton://transfer/0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb?amount=1&amount=2",
+            "ton://transfer/0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb?amount=18446744073709551616",
+            "ton://transfer/0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb?text=%ZZ",
+            "ton://transfer/0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb?text=a&bin=te6ccgEBAQEAAgAAAA==",
+        ] {
+            assert!(PaymentRequest::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use nekoton_utils::*;
+use ton_block::{MsgAddressInt, Serializable};
+use ton_types::UInt256;
+
+use super::models::PendingTransaction;
+use crate::transport::models::RawTransaction;
+
+/// Tracks sent external messages until they are either executed or expired.
+///
+/// Unlike pending transactions of [`ContractSubscription`](super::ContractSubscription),
+/// it is not bound to a single account
+pub struct PendingMessagesQueue {
+    clock: Arc<dyn Clock>,
+    messages: Vec<(MsgAddressInt, PendingTransaction)>,
+}
+
+impl PendingMessagesQueue {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            messages: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Pending messages for the specified account
+    pub fn pending_for<'a>(
+        &'a self,
+        account: &'a MsgAddressInt,
+    ) -> impl Iterator<Item = &'a PendingTransaction> + 'a {
+        self.messages
+            .iter()
+            .filter(move |(dst, _)| dst == account)
+            .map(|(_, pending)| pending)
+    }
+
+    pub fn find(&self, message_hash: &UInt256) -> Option<&PendingTransaction> {
+        self.messages
+            .iter()
+            .map(|(_, pending)| pending)
+            .find(|pending| &pending.message_hash == message_hash)
+    }
+
+    /// Starts tracking the external message
+    pub fn add(
+        &mut self,
+        message: &ton_block::Message,
+        latest_lt: u64,
+        expire_at: u32,
+    ) -> Result<PendingTransaction> {
+        let dst = match message.header() {
+            ton_block::CommonMsgInfo::ExtInMsgInfo(header) => header.dst.clone(),
+            _ => return Err(PendingMessagesError::InvalidMessageType.into()),
+        };
+
+        let pending = self.make_pending(message, latest_lt, expire_at)?;
+        self.messages.push((dst, pending.clone()));
+
+        Ok(pending)
+    }
+
+    /// Replaces the pending message with a new one with the same destination
+    /// and a later expiration time
+    pub fn replace(
+        &mut self,
+        old_message_hash: &UInt256,
+        message: &ton_block::Message,
+        expire_at: u32,
+    ) -> Result<PendingTransaction> {
+        let index = self
+            .messages
+            .iter()
+            .position(|(_, pending)| &pending.message_hash == old_message_hash)
+            .ok_or(PendingMessagesError::MessageNotFound)?;
+
+        let (dst, old) = &self.messages[index];
+        match message.header() {
+            ton_block::CommonMsgInfo::ExtInMsgInfo(header) if &header.dst == dst => {}
+            ton_block::CommonMsgInfo::ExtInMsgInfo(_) => {
+                return Err(PendingMessagesError::DestinationMismatch.into())
+            }
+            _ => return Err(PendingMessagesError::InvalidMessageType.into()),
+        }
+        if expire_at <= old.expire_at {
+            return Err(PendingMessagesError::ExpirationNotIncreased.into());
+        }
+
+        let pending = self.make_pending(message, old.latest_lt, expire_at)?;
+        self.messages[index].1 = pending.clone();
+
+        Ok(pending)
+    }
+
+    fn make_pending(
+        &self,
+        message: &ton_block::Message,
+        latest_lt: u64,
+        expire_at: u32,
+    ) -> Result<PendingTransaction> {
+        let message_hash = message.serialize()?.repr_hash();
+        if self.find(&message_hash).is_some() {
+            return Err(PendingMessagesError::AlreadyTracked.into());
+        }
+
+        Ok(PendingTransaction {
+            message_hash,
+            src: None,
+            latest_lt,
+            created_at: self.clock.now_sec_u64() as u32,
+            expire_at,
+        })
+    }
+
+    /// Resolves pending messages with the new account transactions
+    pub fn handle_transactions(
+        &mut self,
+        transactions: &[RawTransaction],
+    ) -> Vec<PendingMessageEvent> {
+        let mut events = Vec::new();
+        self.messages.retain(|(_, pending)| {
+            match transactions
+                .iter()
+                .find(|transaction| pending.eq(*transaction))
+            {
+                Some(transaction) => {
+                    events.push(PendingMessageEvent::Delivered {
+                        pending: pending.clone(),
+                        transaction: transaction.clone(),
+                    });
+                    false
+                }
+                None => true,
+            }
+        });
+        events
+    }
+
+    /// Removes expired messages.
+    ///
+    /// `current_utime` is a timestamp of the latest known block,
+    /// clock is used if it is not specified
+    pub fn check_expired(&mut self, current_utime: Option<u32>) -> Vec<PendingMessageEvent> {
+        let current_utime = current_utime.unwrap_or_else(|| self.clock.now_sec_u64() as u32);
+
+        let mut events = Vec::new();
+        self.messages.retain(|(_, pending)| {
+            let expired = current_utime > pending.expire_at;
+            if expired {
+                events.push(PendingMessageEvent::Expired(pending.clone()));
+            }
+            !expired
+        });
+        events
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PendingMessageEvent {
+    /// Message was executed in the transaction
+    Delivered {
+        pending: PendingTransaction,
+        transaction: RawTransaction,
+    },
+    /// Message expired and will not be executed
+    Expired(PendingTransaction),
+}
+
+#[derive(thiserror::Error, Debug)]
+enum PendingMessagesError {
+    #[error("Only external inbound messages can be tracked")]
+    InvalidMessageType,
+    #[error("Message is already tracked")]
+    AlreadyTracked,
+    #[error("Pending message not found")]
+    MessageNotFound,
+    #[error("Replacement message has different destination")]
+    DestinationMismatch,
+    #[error("Replacement message must expire later")]
+    ExpirationNotIncreased,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ton_types::IBitstring;
+
+    use super::*;
+
+    fn make_message(dst: &MsgAddressInt, body: u8) -> ton_block::Message {
+        let mut message =
+            ton_block::Message::with_ext_in_header(ton_block::ExternalInboundMessageHeader {
+                dst: dst.clone(),
+                ..Default::default()
+            });
+        let mut builder = ton_types::BuilderData::new();
+        builder.append_u8(body).unwrap();
+        message.set_body(ton_types::SliceData::load_builder(builder).unwrap());
+        message
+    }
+
+    #[test]
+    fn pending_messages_queue() {
+        let clock = Arc::new(ConstClock::from_secs(1000));
+        let mut queue = PendingMessagesQueue::new(clock);
+
+        let first = MsgAddressInt::from_str(
+            "0:1111111111111111111111111111111111111111111111111111111111111111",
+        )
+        .unwrap();
+        let second = MsgAddressInt::from_str(
+            "0:2222222222222222222222222222222222222222222222222222222222222222",
+        )
+        .unwrap();
+
+        let message = make_message(&first, 1);
+        let pending = queue.add(&message, 10, 1060).unwrap();
+        assert_eq!(pending.created_at, 1000);
+        assert!(queue.add(&message, 10, 1060).is_err());
+
+        queue.add(&make_message(&second, 1), 20, 1030).unwrap();
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pending_for(&first).count(), 1);
+
+        // Replacement must have the same destination and later expiration
+        let hash = pending.message_hash;
+        assert!(queue
+            .replace(&hash, &make_message(&second, 2), 1120)
+            .is_err());
+        assert!(queue
+            .replace(&hash, &make_message(&first, 2), 1060)
+            .is_err());
+        let replaced = queue
+            .replace(&hash, &make_message(&first, 2), 1120)
+            .unwrap();
+        assert_eq!(replaced.latest_lt, 10);
+        assert!(queue.find(&hash).is_none());
+        assert_eq!(queue.len(), 2);
+
+        let expired = queue.check_expired(Some(1100));
+        assert!(matches!(
+            expired.as_slice(),
+            [PendingMessageEvent::Expired(pending)] if pending.latest_lt == 20
+        ));
+        assert!(queue.check_expired(None).is_empty());
+
+        assert_eq!(queue.check_expired(Some(1121)).len(), 1);
+        assert!(queue.is_empty());
+    }
+}
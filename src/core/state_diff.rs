@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use nekoton_abi::unpack_from_cell;
+use ton_abi::TokenValue;
+use ton_types::{Cell, SliceData, UInt256};
+
+use super::models::ContractState;
+use crate::transport::models::RawContractState;
+
+/// Changes between two consecutive contract states
+#[derive(Debug, Clone)]
+pub struct ContractStateDiff {
+    pub old_state: ContractState,
+    pub new_state: ContractState,
+    /// Balance change in nano EVERs
+    pub balance_delta: i128,
+    /// Whether the contract was deployed or deleted
+    pub deployment_changed: bool,
+    /// Contract code hash change (e.g. after code upgrade)
+    pub code_hash_changed: Option<CodeHashChange>,
+    /// Changed data fields, `None` if the data layout or one of the states is unknown
+    pub fields: Option<Vec<FieldChange>>,
+}
+
+impl ContractStateDiff {
+    /// Compares brief states without data fields
+    pub fn new(old_state: &ContractState, new_state: &ContractState) -> Self {
+        let code_hash_changed =
+            (old_state.code_hash != new_state.code_hash).then_some(CodeHashChange {
+                old: old_state.code_hash,
+                new: new_state.code_hash,
+            });
+
+        Self {
+            old_state: *old_state,
+            new_state: *new_state,
+            balance_delta: new_state.balance as i128 - old_state.balance as i128,
+            deployment_changed: old_state.is_deployed != new_state.is_deployed,
+            code_hash_changed,
+            fields: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.balance_delta == 0
+            && !self.deployment_changed
+            && self.code_hash_changed.is_none()
+            && self
+                .fields
+                .as_ref()
+                .map_or(true, |fields| fields.is_empty())
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CodeHashChange {
+    pub old: Option<UInt256>,
+    pub new: Option<UInt256>,
+}
+
+/// Change of the ABI-described data field
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub name: String,
+    /// `None` if the contract had no data
+    pub old: Option<TokenValue>,
+    /// `None` if the contract has no data
+    pub new: Option<TokenValue>,
+}
+
+/// Compares ABI-described data fields of two states
+pub fn diff_data_fields(
+    abi: &ton_abi::Contract,
+    old_data: Option<&Cell>,
+    new_data: Option<&Cell>,
+) -> Result<Vec<FieldChange>> {
+    let unpack = |data: Option<&Cell>| -> Result<Vec<Option<TokenValue>>> {
+        Ok(match data {
+            Some(data) => unpack_from_cell(
+                &abi.fields,
+                SliceData::load_cell(data.clone())?,
+                true,
+                abi.abi_version,
+            )?
+            .into_iter()
+            .map(|token| Some(token.value))
+            .collect(),
+            None => Vec::new(),
+        })
+    };
+
+    let old = unpack(old_data)?;
+    let new = unpack(new_data)?;
+
+    Ok(abi
+        .fields
+        .iter()
+        .enumerate()
+        .filter_map(|(i, field)| {
+            let old = old.get(i).cloned().flatten();
+            let new = new.get(i).cloned().flatten();
+            if old == new {
+                return None;
+            }
+            Some(FieldChange {
+                name: field.name.clone(),
+                old,
+                new,
+            })
+        })
+        .collect())
+}
+
+/// Remembers the previous contract state to produce diffs
+#[derive(Default, Clone)]
+pub struct StateDiffTracker {
+    abi: Option<Arc<ton_abi::Contract>>,
+    state: Option<ContractState>,
+    data: StateData,
+}
+
+impl StateDiffTracker {
+    pub fn new(abi: Option<Arc<ton_abi::Contract>>) -> Self {
+        Self {
+            abi,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the contract ABI, used to compare data fields
+    pub fn set_abi(&mut self, abi: Option<Arc<ton_abi::Contract>>) {
+        self.abi = abi;
+    }
+
+    /// Remembers the full state. Returns `None` for the first state
+    pub fn update(&mut self, state: &RawContractState) -> Option<ContractStateDiff> {
+        let new_data = match state {
+            RawContractState::Exists(contract) => match &contract.account.storage.state {
+                ton_block::AccountState::AccountActive { state_init } => state_init.data.clone(),
+                _ => None,
+            },
+            RawContractState::NotExists { .. } => None,
+        };
+
+        let old_data = std::mem::take(&mut self.data);
+        let mut diff = self.update_brief(&state.brief());
+        if let (Some(diff), Some(abi), StateData::Known(old_data)) =
+            (&mut diff, &self.abi, &old_data)
+        {
+            // NOTE: data of some states may not match the ABI (e.g. before the constructor call)
+            diff.fields = diff_data_fields(abi, old_data.as_ref(), new_data.as_ref()).ok();
+        }
+
+        self.data = StateData::Known(new_data);
+        diff
+    }
+
+    /// Remembers the brief state, data fields are not compared
+    pub fn update_brief(&mut self, state: &ContractState) -> Option<ContractStateDiff> {
+        let diff = self
+            .state
+            .as_ref()
+            .map(|old_state| ContractStateDiff::new(old_state, state));
+        self.state = Some(*state);
+        self.data = StateData::Unknown;
+        diff
+    }
+}
+
+/// Data of the previous state
+#[derive(Default, Clone)]
+enum StateData {
+    /// State was found in a block
+    #[default]
+    Unknown,
+    Known(Option<Cell>),
+}
+
+#[cfg(test)]
+mod tests {
+    use nekoton_abi::{pack_into_cell, BuildTokenValue, TokenValueExt};
+
+    use super::*;
+
+    const ABI: &str = r#"{
+        "ABI version": 2,
+        "version": "2.2",
+        "header": [],
+        "functions": [],
+        "events": [],
+        "data": [],
+        "fields": [
+            {"name": "counter", "type": "uint32"},
+            {"name": "paused", "type": "bool"}
+        ]
+    }"#;
+
+    #[test]
+    fn state_diff() {
+        let old_state = ContractState {
+            balance: 100,
+            is_deployed: true,
+            ..Default::default()
+        };
+        let new_state = ContractState {
+            balance: 40,
+            is_deployed: true,
+            code_hash: Some(UInt256::from([1; 32])),
+            ..Default::default()
+        };
+
+        let diff = ContractStateDiff::new(&old_state, &new_state);
+        assert_eq!(diff.balance_delta, -60);
+        assert!(!diff.deployment_changed);
+        assert_eq!(
+            diff.code_hash_changed,
+            Some(CodeHashChange {
+                old: None,
+                new: new_state.code_hash,
+            })
+        );
+        assert!(ContractStateDiff::new(&old_state, &old_state).is_empty());
+
+        let abi = ton_abi::Contract::load(ABI.as_bytes()).unwrap();
+        let data = |counter: u32| {
+            pack_into_cell(
+                &[
+                    counter.token_value().named("counter"),
+                    false.token_value().named("paused"),
+                ],
+                abi.abi_version,
+            )
+            .unwrap()
+        };
+
+        let fields = diff_data_fields(&abi, Some(&data(1)), Some(&data(2))).unwrap();
+        assert_eq!(
+            fields,
+            [FieldChange {
+                name: "counter".to_owned(),
+                old: Some(1u32.token_value()),
+                new: Some(2u32.token_value()),
+            }]
+        );
+
+        let fields = diff_data_fields(&abi, None, Some(&data(1))).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert!(fields.iter().all(|field| field.old.is_none()));
+    }
+}
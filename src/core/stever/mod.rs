@@ -0,0 +1,510 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use nekoton_abi::*;
+use nekoton_contracts::stever::{
+    self, vault_contract, StEverAccountContract, StEverVaultContract, WithdrawRequest,
+};
+use nekoton_utils::*;
+use num_bigint::BigUint;
+use ton_block::MsgAddressInt;
+
+use crate::core::models::*;
+use crate::core::token_wallet::TokenWallet;
+use crate::core::transactions_tree::TransactionsTreeStream;
+use crate::transport::models::{ExistingContract, RawContractState};
+use crate::transport::Transport;
+
+use super::{ContractSubscription, InternalMessage};
+
+/// StEver vault subscription from the point of view of one user.
+///
+/// Pending withdrawal requests are stored in the separate user account contract,
+/// deployed by the vault on the first withdrawal
+pub struct StEverVault {
+    clock: Arc<dyn Clock>,
+    contract_subscription: ContractSubscription,
+    handler: Arc<dyn StEverVaultSubscriptionHandler>,
+    owner: MsgAddressInt,
+    account: MsgAddressInt,
+    rate: StEverRate,
+    withdraw_requests: BTreeMap<u64, WithdrawRequest>,
+}
+
+impl StEverVault {
+    pub async fn subscribe(
+        clock: Arc<dyn Clock>,
+        transport: Arc<dyn Transport>,
+        vault: MsgAddressInt,
+        owner: MsgAddressInt,
+        handler: Arc<dyn StEverVaultSubscriptionHandler>,
+    ) -> Result<StEverVault> {
+        let state = match transport.get_contract_state(&vault).await? {
+            RawContractState::Exists(state) => state,
+            RawContractState::NotExists { .. } => {
+                return Err(StEverVaultError::InvalidVaultContract.into())
+            }
+        };
+        let account =
+            StEverVaultContract(state.as_context(clock.as_ref())).get_account_address(&owner)?;
+
+        let mut rate = StEverRate::default();
+        let contract_subscription = ContractSubscription::subscribe(
+            clock.clone(),
+            transport.clone(),
+            vault,
+            &mut make_contract_state_handler(clock.clone(), &mut rate),
+            None,
+        )
+        .await?;
+
+        let withdraw_requests =
+            get_withdraw_requests(clock.as_ref(), transport.as_ref(), &account).await?;
+
+        handler.on_rate_changed(rate);
+        handler.on_withdraw_requests_changed(withdraw_requests.clone());
+
+        Ok(Self {
+            clock,
+            contract_subscription,
+            handler,
+            owner,
+            account,
+            rate,
+            withdraw_requests,
+        })
+    }
+
+    pub fn contract_subscription(&self) -> &ContractSubscription {
+        &self.contract_subscription
+    }
+
+    pub fn address(&self) -> &MsgAddressInt {
+        self.contract_subscription.address()
+    }
+
+    pub fn owner(&self) -> &MsgAddressInt {
+        &self.owner
+    }
+
+    /// Address of the user account with pending withdrawal requests
+    pub fn account(&self) -> &MsgAddressInt {
+        &self.account
+    }
+
+    pub fn rate(&self) -> StEverRate {
+        self.rate
+    }
+
+    /// Pending withdrawal requests by nonce
+    pub fn withdraw_requests(&self) -> &BTreeMap<u64, WithdrawRequest> {
+        &self.withdraw_requests
+    }
+
+    pub fn contract_state(&self) -> &ContractState {
+        self.contract_subscription.contract_state()
+    }
+
+    /// Computes the amount of stEVER, minted for the deposit, using the latest vault state
+    pub async fn get_deposit_stever_amount(&self, amount: u128) -> Result<u128> {
+        let state = self.get_vault_state().await?;
+        StEverVaultContract(state.as_context(self.clock.as_ref())).get_deposit_stever_amount(amount)
+    }
+
+    /// Computes the amount of EVER, returned for the withdrawal, using the latest vault state
+    pub async fn get_withdraw_ever_amount(&self, amount: u128) -> Result<u128> {
+        let state = self.get_vault_state().await?;
+        StEverVaultContract(state.as_context(self.clock.as_ref())).get_withdraw_ever_amount(amount)
+    }
+
+    /// Prepares a message which deposits EVER to the vault.
+    ///
+    /// stEVER are minted to the owner token wallet. The estimated fee
+    /// is attached in addition to the deposit amount
+    pub async fn prepare_deposit(&self, amount: u128, nonce: u64) -> Result<InternalMessage> {
+        let (function, input) = MessageBuilder::new(vault_contract::deposit())
+            .arg(amount) // _amount
+            .arg(nonce) // _nonce
+            .build();
+
+        let body = function
+            .encode_internal_input(&input)
+            .and_then(ton_types::SliceData::load_builder)?;
+
+        let mut message = InternalMessage {
+            source: Some(self.owner.clone()),
+            destination: self.address().clone(),
+            amount,
+            bounce: true,
+            body,
+        };
+        message.amount += self.estimate_fee(&message).await?;
+        Ok(message)
+    }
+
+    /// Prepares a stEVER transfer to the vault, which creates a withdrawal request
+    pub async fn prepare_withdraw(
+        &self,
+        token_wallet: &TokenWallet,
+        tokens: BigUint,
+        nonce: u64,
+    ) -> Result<InternalMessage> {
+        if token_wallet.owner() != &self.owner {
+            return Err(StEverVaultError::InvalidTokenWallet.into());
+        }
+
+        let mut message = token_wallet
+            .prepare_transfer(
+                TransferRecipient::OwnerWallet(self.address().clone()),
+                tokens,
+                true,
+                stever::make_withdraw_payload(nonce)?,
+                0,
+            )
+            .await?;
+        message.amount += self.estimate_fee(&message).await?;
+        Ok(message)
+    }
+
+    /// Prepares a message which cancels the pending withdrawal request
+    pub async fn prepare_remove_pending_withdraw(&self, nonce: u64) -> Result<InternalMessage> {
+        if !self.withdraw_requests.contains_key(&nonce) {
+            return Err(StEverVaultError::WithdrawRequestNotFound.into());
+        }
+
+        let (function, input) = MessageBuilder::new(vault_contract::remove_pending_withdraw())
+            .arg(nonce) // _nonce
+            .build();
+
+        let body = function
+            .encode_internal_input(&input)
+            .and_then(ton_types::SliceData::load_builder)?;
+
+        let mut message = InternalMessage {
+            source: Some(self.owner.clone()),
+            destination: self.address().clone(),
+            amount: 0,
+            bounce: true,
+            body,
+        };
+        message.amount = self.estimate_fee(&message).await?;
+        Ok(message)
+    }
+
+    /// Estimates the value which must be attached to the message
+    /// in addition to the transferred amount.
+    ///
+    /// Executes the whole transactions tree, produced by the message,
+    /// and sums the fees of all its transactions
+    pub async fn estimate_fee(&self, message: &InternalMessage) -> Result<u128> {
+        const FEE_MULTIPLIER: u128 = 2;
+
+        let mut header = ton_block::InternalMessageHeader {
+            src: ton_block::MsgAddressIntOrNone::Some(
+                message.source.clone().unwrap_or_else(|| self.owner.clone()),
+            ),
+            dst: message.destination.clone(),
+            bounce: message.bounce,
+            ..Default::default()
+        };
+        header.value.grams = ton_block::Grams::new(message.amount)?;
+
+        let mut tree_message = ton_block::Message::with_int_header(header);
+        tree_message.set_body(message.body.clone());
+
+        let transport = self.contract_subscription.transport().clone();
+        let config = transport
+            .get_blockchain_config(self.clock.as_ref(), true)
+            .await?;
+
+        let mut tree =
+            TransactionsTreeStream::new(tree_message, config, transport, self.clock.clone());
+        tree.unlimited_account_balance();
+        tree.unlimited_message_balance();
+
+        let mut total_fees: u128 = 0;
+        while let Some(tx) = tree.next().await? {
+            total_fees += tx.total_fees.grams.as_u128();
+            if let Some(exit_code) = aborted_exit_code(&tx)? {
+                return Err(StEverVaultError::SimulationFailed(exit_code).into());
+            }
+        }
+
+        Ok(total_fees * FEE_MULTIPLIER)
+    }
+
+    pub async fn refresh(&mut self) -> Result<()> {
+        let mut rate = self.rate;
+
+        self.contract_subscription
+            .refresh_contract_state(&mut make_contract_state_handler(
+                self.clock.clone(),
+                &mut rate,
+            ))
+            .await?;
+
+        if rate != self.rate {
+            self.rate = rate;
+            self.handler.on_rate_changed(rate);
+        }
+
+        let withdraw_requests = get_withdraw_requests(
+            self.clock.as_ref(),
+            self.contract_subscription.transport().as_ref(),
+            &self.account,
+        )
+        .await?;
+
+        if withdraw_requests != self.withdraw_requests {
+            self.withdraw_requests = withdraw_requests;
+            self.handler
+                .on_withdraw_requests_changed(self.withdraw_requests.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn get_vault_state(&self) -> Result<ExistingContract> {
+        match self
+            .contract_subscription
+            .transport()
+            .get_contract_state(self.address())
+            .await?
+        {
+            RawContractState::Exists(state) => Ok(state),
+            RawContractState::NotExists { .. } => {
+                Err(StEverVaultError::InvalidVaultContract.into())
+            }
+        }
+    }
+}
+
+pub trait StEverVaultSubscriptionHandler: Send + Sync {
+    fn on_rate_changed(&self, rate: StEverRate);
+
+    fn on_withdraw_requests_changed(&self, withdraw_requests: BTreeMap<u64, WithdrawRequest>);
+}
+
+/// Exchange rate of the vault
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct StEverRate {
+    /// Amount of stEVER, minted for 1 EVER deposit
+    pub deposit: u128,
+    /// Amount of EVER, returned for 1 stEVER withdrawal
+    pub withdraw: u128,
+}
+
+/// Fetches pending withdrawal requests from the user account state.
+///
+/// Returns an empty map if the account is not deployed yet
+pub async fn get_withdraw_requests(
+    clock: &dyn Clock,
+    transport: &dyn Transport,
+    account: &MsgAddressInt,
+) -> Result<BTreeMap<u64, WithdrawRequest>> {
+    match transport.get_contract_state(account).await? {
+        RawContractState::Exists(state) => {
+            StEverAccountContract(state.as_context(clock)).withdraw_requests()
+        }
+        RawContractState::NotExists { .. } => Ok(Default::default()),
+    }
+}
+
+fn make_contract_state_handler(
+    clock: Arc<dyn Clock>,
+    rate: &'_ mut StEverRate,
+) -> impl FnMut(&RawContractState) + '_ {
+    move |contract_state| {
+        if let RawContractState::Exists(state) = contract_state {
+            let vault = StEverVaultContract(state.as_context(clock.as_ref()));
+            if let (Ok(deposit), Ok(withdraw)) = (
+                vault.get_deposit_stever_amount(ONE_EVER),
+                vault.get_withdraw_ever_amount(ONE_EVER),
+            ) {
+                *rate = StEverRate { deposit, withdraw };
+            }
+        }
+    }
+}
+
+/// Returns `Some` with the compute phase exit code if the transaction was aborted
+fn aborted_exit_code(tx: &ton_block::Transaction) -> Result<Option<Option<i32>>> {
+    let descr = tx.read_description()?;
+    if !descr.is_aborted() {
+        return Ok(None);
+    }
+    let exit_code = match descr.compute_phase_ref() {
+        Some(ton_block::TrComputePhase::Vm(phase)) => Some(phase.exit_code),
+        _ => None,
+    };
+    Ok(Some(exit_code))
+}
+
+const ONE_EVER: u128 = 1_000_000_000;
+
+#[derive(thiserror::Error, Debug)]
+enum StEverVaultError {
+    #[error("Invalid StEver vault contract")]
+    InvalidVaultContract,
+    #[error("Token wallet owner doesn't match")]
+    InvalidTokenWallet,
+    #[error("Withdraw request not found")]
+    WithdrawRequestNotFound,
+    #[error("Simulated transaction failed with exit code {0:?}")]
+    SimulationFailed(Option<i32>),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ton_types::Cell;
+
+    use super::*;
+    use crate::transport::models::LastTransactionId;
+    use crate::transport::tests::MockTransport;
+
+    struct NoopHandler;
+
+    impl StEverVaultSubscriptionHandler for NoopHandler {
+        fn on_rate_changed(&self, _: StEverRate) {}
+
+        fn on_withdraw_requests_changed(&self, _: BTreeMap<u64, WithdrawRequest>) {}
+    }
+
+    fn vault_address() -> MsgAddressInt {
+        MsgAddressInt::from_str(
+            "0:675a6d63f27e3f24d41d286043a9286b2e3eb6b84fa4c3308cc2833ef6f54d68",
+        )
+        .unwrap()
+    }
+
+    fn owner_address() -> MsgAddressInt {
+        MsgAddressInt::from_str(
+            "0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb",
+        )
+        .unwrap()
+    }
+
+    /// Active account with an empty code, which accepts any internal message
+    fn make_vault_state(address: MsgAddressInt) -> RawContractState {
+        RawContractState::Exists(ExistingContract {
+            account: ton_block::AccountStuff {
+                addr: address,
+                storage: ton_block::AccountStorage {
+                    last_trans_lt: 10,
+                    balance: ton_block::CurrencyCollection::with_grams(ONE_EVER as u64),
+                    state: ton_block::AccountState::AccountActive {
+                        state_init: ton_block::StateInit {
+                            code: Some(Cell::default()),
+                            data: Some(Cell::default()),
+                            ..Default::default()
+                        },
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            timings: GenTimings::Unknown,
+            last_transaction_id: LastTransactionId::Inexact { latest_lt: 10 },
+        })
+    }
+
+    /// Vault subscription, which skips the user account lookup
+    async fn make_vault(transport: Arc<MockTransport>) -> StEverVault {
+        let clock: Arc<dyn Clock> = Arc::new(SimpleClock);
+        let contract_subscription = ContractSubscription::subscribe(
+            clock.clone(),
+            transport,
+            vault_address(),
+            &mut |_| {},
+            None,
+        )
+        .await
+        .unwrap();
+
+        StEverVault {
+            clock,
+            contract_subscription,
+            handler: Arc::new(NoopHandler),
+            owner: owner_address(),
+            account: owner_address(),
+            rate: Default::default(),
+            withdraw_requests: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_requires_vault() {
+        let err = StEverVault::subscribe(
+            Arc::new(SimpleClock),
+            Arc::new(MockTransport::new()),
+            vault_address(),
+            owner_address(),
+            Arc::new(NoopHandler),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<StEverVaultError>(),
+            Some(StEverVaultError::InvalidVaultContract)
+        ));
+    }
+
+    #[tokio::test]
+    async fn deposit_fee_is_estimated() {
+        let transport = Arc::new(MockTransport::new());
+        transport.set_contract_state(vault_address(), make_vault_state(vault_address()));
+        let vault = make_vault(transport).await;
+
+        let amount = 10 * ONE_EVER;
+        let message = vault.prepare_deposit(amount, 1).await.unwrap();
+        assert_eq!(message.destination, vault_address());
+        assert_eq!(message.source, Some(owner_address()));
+
+        assert!(message.amount > amount);
+    }
+
+    #[tokio::test]
+    async fn failed_simulation_is_reported() {
+        // Bounceable message to the missing vault is aborted
+        let vault = make_vault(Arc::new(MockTransport::new())).await;
+
+        let err = vault.prepare_deposit(ONE_EVER, 1).await.err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<StEverVaultError>(),
+            Some(StEverVaultError::SimulationFailed(None))
+        ));
+    }
+
+    #[tokio::test]
+    async fn remove_pending_withdraw_requires_request() {
+        let transport = Arc::new(MockTransport::new());
+        transport.set_contract_state(vault_address(), make_vault_state(vault_address()));
+        let mut vault = make_vault(transport).await;
+
+        let err = vault
+            .prepare_remove_pending_withdraw(1)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<StEverVaultError>(),
+            Some(StEverVaultError::WithdrawRequestNotFound)
+        ));
+
+        vault.withdraw_requests.insert(
+            1,
+            WithdrawRequest {
+                amount: ONE_EVER,
+                timestamp: 0,
+                unlock_time: 0,
+            },
+        );
+        let message = vault.prepare_remove_pending_withdraw(1).await.unwrap();
+        assert!(message.amount > 0);
+        assert!(message.bounce);
+    }
+}
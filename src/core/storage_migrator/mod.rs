@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::core::accounts_storage::ACCOUNTS_STORAGE_SCHEMA;
+use crate::core::keystore::KEYSTORE_SCHEMA;
+use crate::external::{Storage, StorageBatch};
+
+/// Upgrades data from the previous version of the schema
+pub type MigrationStep = fn(Value) -> Result<Value>;
+
+/// Versioned format of the data, stored under a single storage key
+pub struct StorageSchema {
+    pub key: &'static str,
+    /// Step `i` upgrades data from version `i` to version `i + 1`
+    pub steps: &'static [MigrationStep],
+}
+
+impl StorageSchema {
+    /// Version of the data, produced by this library
+    pub fn current_version(&self) -> u32 {
+        self.steps.len() as u32
+    }
+
+    /// Parses and upgrades stored data to the current version
+    pub fn migrate(&self, data: &str) -> Result<Value> {
+        self.migrate_value(serde_json::from_str(data)?)
+    }
+
+    /// Upgrades parsed data to the current version
+    pub fn migrate_value(&self, mut value: Value) -> Result<Value> {
+        let version = stored_version(&value);
+        if version > self.current_version() {
+            return Err(MigrationError::UnsupportedVersion {
+                key: self.key,
+                version,
+            }
+            .into());
+        }
+
+        for step in &self.steps[version as usize..] {
+            value = step(value)?;
+        }
+        Ok(value)
+    }
+}
+
+/// Returns the version of the stored data.
+///
+/// Data without explicit version (created before versioning was introduced) has version `0`
+pub fn stored_version(value: &Value) -> u32 {
+    match value.get(VERSION_FIELD).and_then(Value::as_u64) {
+        Some(version) => u32::try_from(version).unwrap_or(u32::MAX),
+        None => 0,
+    }
+}
+
+/// Sets the version of the object-like data
+pub fn set_version(mut value: Value, version: u32) -> Result<Value> {
+    value
+        .as_object_mut()
+        .ok_or(MigrationError::InvalidData)?
+        .insert(VERSION_FIELD.to_owned(), Value::from(version));
+    Ok(value)
+}
+
+/// Upgrades all known data in the storage to the current versions.
+///
+/// [`AccountsStorage`] and [`KeyStore`] migrate data on load without
+/// persisting it, so running the migrator is only required to upgrade
+/// the storage explicitly (e.g. before the backup)
+///
+/// [`AccountsStorage`]: crate::core::accounts_storage::AccountsStorage
+/// [`KeyStore`]: crate::core::keystore::KeyStore
+pub struct StorageMigrator {
+    storage: Arc<dyn Storage>,
+    schemas: Vec<&'static StorageSchema>,
+}
+
+impl StorageMigrator {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            schemas: vec![&ACCOUNTS_STORAGE_SCHEMA, &KEYSTORE_SCHEMA],
+        }
+    }
+
+    /// Adds a custom schema (e.g. for the application data in the same storage)
+    pub fn with_schema(mut self, schema: &'static StorageSchema) -> Self {
+        self.schemas.push(schema);
+        self
+    }
+
+    /// Returns stored versions of all known keys (`None` if the key is empty)
+    pub async fn stored_versions(&self) -> Result<Vec<(&'static str, Option<u32>)>> {
+        let mut result = Vec::with_capacity(self.schemas.len());
+        for schema in &self.schemas {
+            let version = match self.storage.get(schema.key).await? {
+                Some(data) => Some(stored_version(&serde_json::from_str(&data)?)),
+                None => None,
+            };
+            result.push((schema.key, version));
+        }
+        Ok(result)
+    }
+
+    /// Upgrades stored data to the current versions. All keys are updated
+    /// in a single batch (atomically if the storage supports it).
+    ///
+    /// Returns keys of the updated entries
+    pub async fn migrate(&self) -> Result<Vec<&'static str>> {
+        let mut batch = StorageBatch::default();
+        let mut updated = Vec::new();
+        for schema in &self.schemas {
+            let data = match self.storage.get(schema.key).await? {
+                Some(data) => data,
+                None => continue,
+            };
+
+            let value = serde_json::from_str::<Value>(&data)?;
+            if stored_version(&value) == schema.current_version() {
+                continue;
+            }
+
+            let value = schema.migrate_value(value)?;
+            if stored_version(&value) != schema.current_version() {
+                return Err(MigrationError::InvalidMigration { key: schema.key }.into());
+            }
+
+            batch.set(schema.key, &serde_json::to_string(&value)?);
+            updated.push(schema.key);
+        }
+
+        if !batch.is_empty() {
+            self.storage.apply_batch(batch).await?;
+        }
+        Ok(updated)
+    }
+}
+
+const VERSION_FIELD: &str = "version";
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum MigrationError {
+    #[error("Unsupported version {version} of `{key}`")]
+    UnsupportedVersion { key: &'static str, version: u32 },
+    #[error("Migration of `{key}` produced invalid version")]
+    InvalidMigration { key: &'static str },
+    #[error("Invalid data")]
+    InvalidData,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::accounts_storage::{AccountsStorage, ACCOUNTS_STORAGE_KEY};
+    use crate::core::keystore::KEYSTORE_STORAGE_KEY;
+    use crate::external::tests::TestStorage;
+
+    const OLD_ACCOUNTS: &str = r#"{"assets":{"0:9a3ad35dc2b5f3ab4c2a7de1b0d1e26d3b0b10f6d2a86e6bb0fd0a89a8da5a0c":"{\"name\":\"Main\",\"ton_wallet\":{\"address\":\"0:9a3ad35dc2b5f3ab4c2a7de1b0d1e26d3b0b10f6d2a86e6bb0fd0a89a8da5a0c\",\"public_key\":\"122a6ca3f3785aeab4d2944cb5c49cf30efca1cf8f818faa8a8e7a17593751e0\",\"contract\":\"WalletV3\"},\"token_wallets\":[],\"depools\":[]}"}}"#;
+
+    #[tokio::test]
+    async fn migrate_storage() {
+        let storage = Arc::new(TestStorage::default());
+        storage.set_unchecked(ACCOUNTS_STORAGE_KEY, OLD_ACCOUNTS);
+        storage.set_unchecked(KEYSTORE_STORAGE_KEY, "[]");
+
+        // Old data is readable without migration
+        let accounts = AccountsStorage::load(storage.clone()).await.unwrap();
+        assert_eq!(accounts.stored_data().await.accounts().len(), 1);
+
+        let migrator = StorageMigrator::new(storage.clone());
+        assert_eq!(
+            migrator.stored_versions().await.unwrap(),
+            [
+                (ACCOUNTS_STORAGE_KEY, Some(0)),
+                (KEYSTORE_STORAGE_KEY, Some(0))
+            ]
+        );
+
+        let updated = migrator.migrate().await.unwrap();
+        assert_eq!(updated, [ACCOUNTS_STORAGE_KEY, KEYSTORE_STORAGE_KEY]);
+        assert!(migrator.migrate().await.unwrap().is_empty());
+
+        assert_eq!(
+            migrator.stored_versions().await.unwrap(),
+            [
+                (
+                    ACCOUNTS_STORAGE_KEY,
+                    Some(ACCOUNTS_STORAGE_SCHEMA.current_version())
+                ),
+                (
+                    KEYSTORE_STORAGE_KEY,
+                    Some(KEYSTORE_SCHEMA.current_version())
+                )
+            ]
+        );
+
+        let accounts = AccountsStorage::load(storage.clone()).await.unwrap();
+        assert_eq!(accounts.stored_data().await.accounts().len(), 1);
+
+        // Data from the newer library version is rejected
+        storage.set_unchecked(ACCOUNTS_STORAGE_KEY, r#"{"version":1000,"assets":{}}"#);
+        assert!(AccountsStorage::load(storage).await.is_err());
+    }
+}
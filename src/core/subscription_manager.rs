@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use nekoton_utils::Clock;
+use ton_block::MsgAddressInt;
+
+#[cfg(feature = "gql_transport")]
+use super::block_walker::WalkedBlock;
+use super::depool::DePool;
+use super::generic_contract::GenericContract;
+use super::jetton_wallet::JettonWallet;
+use super::models::PollingMethod;
+use super::nft_wallet::Nft;
+use super::stever::StEverVault;
+use super::token_wallet::TokenWallet;
+use super::ton_wallet::TonWallet;
+
+/// Subscription which can be driven by the [`SubscriptionManager`]
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+pub trait ManagedSubscription: Send + Sync {
+    fn address(&self) -> &MsgAddressInt;
+
+    fn polling_method(&self) -> PollingMethod;
+
+    async fn refresh(&mut self) -> Result<()>;
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl<T: ManagedSubscription + ?Sized> ManagedSubscription for Box<T> {
+    fn address(&self) -> &MsgAddressInt {
+        T::address(self)
+    }
+
+    fn polling_method(&self) -> PollingMethod {
+        T::polling_method(self)
+    }
+
+    async fn refresh(&mut self) -> Result<()> {
+        T::refresh(self).await
+    }
+}
+
+macro_rules! impl_managed_subscription {
+    ($($ty:ty),*$(,)?) => {$(
+        #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+        #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+        impl ManagedSubscription for $ty {
+            fn address(&self) -> &MsgAddressInt {
+                <$ty>::address(self)
+            }
+
+            fn polling_method(&self) -> PollingMethod {
+                self.contract_subscription().polling_method()
+            }
+
+            async fn refresh(&mut self) -> Result<()> {
+                <$ty>::refresh(self).await
+            }
+        }
+    )*};
+}
+
+impl_managed_subscription!(
+    TonWallet,
+    TokenWallet,
+    JettonWallet,
+    Nft,
+    DePool,
+    StEverVault
+);
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl ManagedSubscription for GenericContract {
+    fn address(&self) -> &MsgAddressInt {
+        GenericContract::address(self)
+    }
+
+    fn polling_method(&self) -> PollingMethod {
+        GenericContract::polling_method(self)
+    }
+
+    async fn refresh(&mut self) -> Result<()> {
+        GenericContract::refresh(self).await
+    }
+}
+
+pub trait SubscriptionManagerHandler: Send + Sync {
+    /// Called after each successful refresh
+    fn on_refreshed(
+        &self,
+        id: SubscriptionId,
+        address: &MsgAddressInt,
+        polling_method: PollingMethod,
+    );
+
+    /// Called when the refresh failed. The subscription will be retried after its interval
+    fn on_refresh_failed(&self, id: SubscriptionId, address: &MsgAddressInt, error: &anyhow::Error);
+}
+
+pub type SubscriptionId = u32;
+
+#[derive(Debug, Copy, Clone)]
+pub struct PollingParams {
+    /// Refresh interval of the active subscription
+    pub active_interval: Duration,
+    /// Refresh interval of all other subscriptions
+    pub background_interval: Duration,
+    /// Refresh interval of subscriptions with pending messages
+    pub reliable_interval: Duration,
+    /// Max number of refreshes during one [`SubscriptionManager::poll`]
+    pub max_refreshes_per_poll: usize,
+}
+
+impl Default for PollingParams {
+    fn default() -> Self {
+        Self {
+            active_interval: Duration::from_secs(10),
+            background_interval: Duration::from_secs(60),
+            reliable_interval: Duration::from_secs(1),
+            max_refreshes_per_poll: 4,
+        }
+    }
+}
+
+/// Drives many subscriptions with a shared polling budget.
+///
+/// Instead of running a separate loop for each subscription, call [`SubscriptionManager::poll`]
+/// in a single loop and sleep for the returned duration
+pub struct SubscriptionManager<S = Box<dyn ManagedSubscription>> {
+    clock: Arc<dyn Clock>,
+    handler: Arc<dyn SubscriptionManagerHandler>,
+    params: PollingParams,
+    subscriptions: HashMap<SubscriptionId, Entry<S>>,
+    active: Option<SubscriptionId>,
+    next_id: SubscriptionId,
+}
+
+impl<S: ManagedSubscription> SubscriptionManager<S> {
+    pub fn new(
+        clock: Arc<dyn Clock>,
+        handler: Arc<dyn SubscriptionManagerHandler>,
+        params: PollingParams,
+    ) -> Self {
+        Self {
+            clock,
+            handler,
+            params,
+            subscriptions: Default::default(),
+            active: None,
+            next_id: 0,
+        }
+    }
+
+    /// Adds an already refreshed subscription, it will be polled after its interval
+    pub fn add(&mut self, subscription: S) -> SubscriptionId {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let next_refresh_at = self.clock.now_ms_u64()
+            + self.interval(id, subscription.polling_method()).as_millis() as u64;
+        self.subscriptions.insert(
+            id,
+            Entry {
+                subscription,
+                next_refresh_at,
+            },
+        );
+        id
+    }
+
+    pub fn remove(&mut self, id: SubscriptionId) -> Option<S> {
+        if self.active == Some(id) {
+            self.active = None;
+        }
+        self.subscriptions
+            .remove(&id)
+            .map(|entry| entry.subscription)
+    }
+
+    pub fn get(&self, id: SubscriptionId) -> Option<&S> {
+        self.subscriptions.get(&id).map(|entry| &entry.subscription)
+    }
+
+    /// NOTE: reschedule the subscription with [`SubscriptionManager::refresh_now`]
+    /// if its polling method could have changed (e.g. after sending a message)
+    pub fn get_mut(&mut self, id: SubscriptionId) -> Option<&mut S> {
+        self.subscriptions
+            .get_mut(&id)
+            .map(|entry| &mut entry.subscription)
+    }
+
+    pub fn active(&self) -> Option<SubscriptionId> {
+        self.active
+    }
+
+    /// Marks the subscription as active (e.g. currently shown to the user).
+    /// It will be refreshed during the next poll
+    pub fn set_active(&mut self, id: Option<SubscriptionId>) {
+        self.active = id;
+        if let Some(id) = id {
+            self.refresh_now(id);
+        }
+    }
+
+    /// Schedules the subscription refresh during the next poll
+    pub fn refresh_now(&mut self, id: SubscriptionId) {
+        if let Some(entry) = self.subscriptions.get_mut(&id) {
+            entry.next_refresh_at = 0;
+        }
+    }
+
+    /// Schedules the refresh of all subscriptions with transactions in the block.
+    ///
+    /// Returns the number of scheduled subscriptions
+    #[cfg(feature = "gql_transport")]
+    pub fn refresh_touched(&mut self, block: &WalkedBlock) -> usize {
+        let mut count = 0;
+        for entry in self.subscriptions.values_mut() {
+            if block.is_touched(entry.subscription.address()) {
+                entry.next_refresh_at = 0;
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Refreshes the most prioritized subscriptions which are due.
+    ///
+    /// Returns the delay until the next poll is needed
+    pub async fn poll(&mut self) -> Duration {
+        let now = self.clock.now_ms_u64();
+        #[cfg(feature = "instrumentation")]
+        let (started_at, mut refreshed, mut failed) = (now, 0, 0);
+
+        let mut due = self
+            .subscriptions
+            .iter()
+            .filter(|(_, entry)| entry.next_refresh_at <= now)
+            .map(|(id, entry)| {
+                let priority = match (
+                    Some(*id) == self.active,
+                    entry.subscription.polling_method(),
+                ) {
+                    (true, _) => 0,
+                    (false, PollingMethod::Reliable) => 1,
+                    (false, PollingMethod::Manual) => 2,
+                };
+                (priority, entry.next_refresh_at, *id)
+            })
+            .collect::<Vec<_>>();
+        due.sort_unstable();
+        due.truncate(self.params.max_refreshes_per_poll);
+
+        for (_, _, id) in due {
+            let entry = match self.subscriptions.get_mut(&id) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            #[cfg(feature = "instrumentation")]
+            let span = tracing::debug_span!(
+                "subscription_refresh",
+                id,
+                address = %entry.subscription.address()
+            );
+
+            let refresh = entry.subscription.refresh();
+            #[cfg(feature = "instrumentation")]
+            let refresh = tracing::Instrument::instrument(refresh, span);
+
+            let result = refresh.await;
+            let address = entry.subscription.address();
+            let polling_method = entry.subscription.polling_method();
+            match &result {
+                Ok(()) => self.handler.on_refreshed(id, address, polling_method),
+                Err(e) => self.handler.on_refresh_failed(id, address, e),
+            }
+
+            #[cfg(feature = "instrumentation")]
+            match &result {
+                Ok(()) => refreshed += 1,
+                Err(e) => {
+                    tracing::warn!(id, address = %address, error = %e, "subscription refresh failed");
+                    failed += 1;
+                }
+            }
+
+            let interval = self.interval(id, polling_method);
+            if let Some(entry) = self.subscriptions.get_mut(&id) {
+                entry.next_refresh_at = self.clock.now_ms_u64() + interval.as_millis() as u64;
+            }
+        }
+
+        let now = self.clock.now_ms_u64();
+
+        #[cfg(feature = "instrumentation")]
+        if let Some(metrics) = crate::transport::metrics::transport_metrics() {
+            metrics.on_poll_cycle(
+                refreshed,
+                failed,
+                Duration::from_millis(now.saturating_sub(started_at)),
+            );
+        }
+
+        self.subscriptions
+            .values()
+            .map(|entry| entry.next_refresh_at.saturating_sub(now))
+            .min()
+            .map_or(self.params.background_interval, Duration::from_millis)
+    }
+
+    fn interval(&self, id: SubscriptionId, polling_method: PollingMethod) -> Duration {
+        match polling_method {
+            PollingMethod::Reliable => self.params.reliable_interval,
+            PollingMethod::Manual if self.active == Some(id) => self.params.active_interval,
+            PollingMethod::Manual => self.params.background_interval,
+        }
+    }
+}
+
+struct Entry<S> {
+    subscription: S,
+    next_refresh_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use nekoton_utils::MockClock;
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    struct TestSubscription {
+        address: MsgAddressInt,
+        polling_method: PollingMethod,
+    }
+
+    #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+    #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+    impl ManagedSubscription for TestSubscription {
+        fn address(&self) -> &MsgAddressInt {
+            &self.address
+        }
+
+        fn polling_method(&self) -> PollingMethod {
+            self.polling_method
+        }
+
+        async fn refresh(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct TestHandler(Mutex<Vec<SubscriptionId>>);
+
+    impl SubscriptionManagerHandler for TestHandler {
+        fn on_refreshed(&self, id: SubscriptionId, _: &MsgAddressInt, _: PollingMethod) {
+            self.0.lock().push(id);
+        }
+
+        fn on_refresh_failed(&self, _: SubscriptionId, _: &MsgAddressInt, _: &anyhow::Error) {}
+    }
+
+    #[tokio::test]
+    async fn prioritized_polling() {
+        let clock = Arc::new(MockClock::default());
+        let handler = Arc::new(TestHandler::default());
+        let mut manager = SubscriptionManager::new(
+            clock.clone(),
+            handler.clone(),
+            PollingParams {
+                active_interval: Duration::from_secs(10),
+                background_interval: Duration::from_secs(60),
+                reliable_interval: Duration::from_secs(1),
+                max_refreshes_per_poll: 2,
+            },
+        );
+
+        let subscription = |polling_method| TestSubscription {
+            address: Default::default(),
+            polling_method,
+        };
+        let background = manager.add(subscription(PollingMethod::Manual));
+        let reliable = manager.add(subscription(PollingMethod::Reliable));
+        let active = manager.add(subscription(PollingMethod::Manual));
+        manager.set_active(Some(active));
+
+        assert_eq!(manager.poll().await, Duration::from_secs(1));
+        assert_eq!(*handler.0.lock(), [active]);
+
+        clock.set_ms(1000);
+        assert_eq!(manager.poll().await, Duration::from_secs(1));
+        assert_eq!(*handler.0.lock(), [active, reliable]);
+
+        handler.0.lock().clear();
+        clock.set_ms(60_000);
+        manager.poll().await;
+        assert_eq!(*handler.0.lock(), [active, reliable]);
+
+        manager.poll().await;
+        assert_eq!(*handler.0.lock(), [active, reliable, background]);
+    }
+
+    #[cfg(feature = "gql_transport")]
+    #[tokio::test]
+    async fn walked_block_refreshes_touched() {
+        use std::str::FromStr;
+
+        let clock = Arc::new(MockClock::default());
+        let handler = Arc::new(TestHandler::default());
+        let mut manager = SubscriptionManager::new(clock, handler.clone(), Default::default());
+
+        let address = |workchain: i8, account: &str| {
+            MsgAddressInt::from_str(&format!("{workchain}:{}", account.repeat(32))).unwrap()
+        };
+        let subscription = |address| TestSubscription {
+            address,
+            polling_method: PollingMethod::Manual,
+        };
+        let touched = manager.add(subscription(address(0, "11")));
+        manager.add(subscription(address(0, "22")));
+        manager.add(subscription(address(-1, "11")));
+
+        // Subscriptions are not due yet
+        manager.poll().await;
+        assert!(handler.0.lock().is_empty());
+
+        let block = WalkedBlock {
+            block: Default::default(),
+            workchain_id: 0,
+            touched_accounts: [ton_types::UInt256::from([0x11; 32])].into_iter().collect(),
+        };
+        assert_eq!(manager.refresh_touched(&block), 1);
+
+        manager.poll().await;
+        assert_eq!(*handler.0.lock(), [touched]);
+    }
+}
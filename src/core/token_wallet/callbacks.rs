@@ -0,0 +1,154 @@
+//! Bodies of the TIP-3.1 token callbacks, e.g. to target token receivers
+//! (DEX pairs, vaults) or to test them locally
+
+use anyhow::Result;
+use nekoton_abi::*;
+use nekoton_contracts::tip3_1::{callbacks, token_wallet_contract};
+use ton_types::{Cell, SliceData};
+
+use crate::core::utils::MessageLimits;
+
+/// Cells between the message root and the payload cell:
+/// message -> body -> ABI continuation cell -> payload
+const PAYLOAD_OFFSET: u16 = 3;
+
+/// `acceptTransfer` body, sent by the token wallet to the recipient token wallet
+pub fn accept_transfer_body(
+    inputs: token_wallet_contract::AcceptTransferInputs,
+) -> Result<SliceData> {
+    check_payload_depth(&inputs.payload)?;
+    encode_body(token_wallet_contract::accept_transfer(), inputs.pack())
+}
+
+/// `acceptMint` body, sent by the root token contract to the recipient token wallet
+pub fn accept_mint_body(inputs: token_wallet_contract::AcceptMintInputs) -> Result<SliceData> {
+    check_payload_depth(&inputs.payload)?;
+    encode_body(token_wallet_contract::accept_mint(), inputs.pack())
+}
+
+/// `onAcceptTokensTransfer` body, sent by the recipient token wallet to its owner
+pub fn on_accept_tokens_transfer_body(
+    inputs: callbacks::OnAcceptTokensTransferInputs,
+) -> Result<SliceData> {
+    check_payload_depth(&inputs.payload)?;
+    encode_body(callbacks::on_accept_tokens_transfer(), inputs.pack())
+}
+
+/// `onAcceptTokensMint` body, sent by the recipient token wallet to its owner
+pub fn on_accept_tokens_mint_body(
+    inputs: callbacks::OnAcceptTokensMintInputs,
+) -> Result<SliceData> {
+    check_payload_depth(&inputs.payload)?;
+    encode_body(callbacks::on_accept_tokens_mint(), inputs.pack())
+}
+
+/// Packs the fields followed by the inner payload into a new payload cell.
+///
+/// Used for the payloads which are passed through several contracts
+/// (e.g. a DEX payload with the user payload inside). Each nesting level
+/// makes the cell tree deeper, so the result is checked against the message limits
+pub fn wrap_payload(mut fields: Vec<ton_abi::Token>, inner: Cell) -> Result<Cell> {
+    fields.push(inner.token_value().named("payload"));
+    let payload = pack_into_cell(&fields, ton_abi::contract::ABI_VERSION_2_2)?;
+    check_payload_depth(&payload)?;
+    Ok(payload)
+}
+
+fn check_payload_depth(payload: &Cell) -> Result<()> {
+    let max_depth = MessageLimits::default().max_depth - PAYLOAD_OFFSET;
+    let depth = payload.repr_depth();
+    if depth > max_depth {
+        return Err(CallbackPayloadError::TooDeep { depth, max_depth }.into());
+    }
+    Ok(())
+}
+
+fn encode_body(function: &ton_abi::Function, tokens: Vec<ton_abi::Token>) -> Result<SliceData> {
+    function
+        .encode_internal_input(&tokens)
+        .and_then(SliceData::load_builder)
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum CallbackPayloadError {
+    #[error("Payload cell tree is too deep ({depth} > {max_depth})")]
+    TooDeep { depth: u16, max_depth: u16 },
+}
+
+#[cfg(test)]
+mod tests {
+    use nekoton_abi::num_bigint::BigUint;
+    use ton_block::MsgAddressInt;
+    use ton_types::BuilderData;
+
+    use super::*;
+
+    fn make_deep_cell(depth: u16) -> Cell {
+        let mut cell = Cell::default();
+        for _ in 0..depth {
+            let mut builder = BuilderData::new();
+            builder.checked_append_reference(cell).unwrap();
+            cell = builder.into_cell().unwrap();
+        }
+        cell
+    }
+
+    #[test]
+    fn callback_bodies() {
+        let inner = pack_into_cell(
+            &[42u32.token_value().named("value")],
+            ton_abi::contract::ABI_VERSION_2_2,
+        )
+        .unwrap();
+        let payload = wrap_payload(vec![true.token_value().named("flag")], inner).unwrap();
+
+        let body = on_accept_tokens_transfer_body(callbacks::OnAcceptTokensTransferInputs {
+            token_root: MsgAddressInt::default(),
+            amount: BigUint::from(100u32),
+            sender: MsgAddressInt::default(),
+            sender_wallet: MsgAddressInt::default(),
+            remaining_gas_to: MsgAddressInt::default(),
+            payload: payload.clone(),
+        })
+        .unwrap();
+
+        let function = callbacks::on_accept_tokens_transfer();
+        assert_eq!(read_function_id(&body).unwrap(), function.input_id);
+        let inputs: callbacks::OnAcceptTokensTransferInputs = function
+            .decode_input(body, true, false)
+            .unwrap()
+            .unpack()
+            .unwrap();
+        assert_eq!(inputs.amount, BigUint::from(100u32));
+        assert_eq!(inputs.payload, payload);
+
+        let body = accept_mint_body(token_wallet_contract::AcceptMintInputs {
+            amount: BigUint::from(1u32),
+            remaining_gas_to: MsgAddressInt::default(),
+            notify: true,
+            payload: Cell::default(),
+        })
+        .unwrap();
+        assert_eq!(
+            read_function_id(&body).unwrap(),
+            token_wallet_contract::accept_mint().input_id
+        );
+    }
+
+    #[test]
+    fn too_deep_payload() {
+        let max_depth = MessageLimits::default().max_depth - PAYLOAD_OFFSET;
+
+        let payload = make_deep_cell(max_depth);
+        assert!(wrap_payload(Vec::new(), payload.clone()).is_err());
+
+        let result = accept_transfer_body(token_wallet_contract::AcceptTransferInputs {
+            amount: BigUint::from(1u32),
+            sender: MsgAddressInt::default(),
+            remaining_gas_to: MsgAddressInt::default(),
+            notify: false,
+            payload: make_deep_cell(max_depth + 1),
+        });
+        assert!(result.is_err());
+    }
+}
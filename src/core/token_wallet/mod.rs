@@ -2,9 +2,11 @@ use std::convert::TryFrom;
 use std::ops::Shr;
 use std::sync::Arc;
 
+use crate::core::abi_registry::{AbiDecoder, AbiRegistry, DecodedAbiTransaction};
 use crate::core::models::*;
 use crate::core::parsing::*;
 use crate::core::transactions_tree::*;
+use crate::crypto::SignatureId;
 use crate::transport::models::{RawContractState, RawTransaction};
 use crate::transport::Transport;
 use anyhow::Result;
@@ -12,11 +14,14 @@ use nekoton_abi::*;
 use nekoton_contracts::tip3_any::{RootTokenContractState, TokenWalletContractState};
 use nekoton_contracts::{old_tip3, tip3_1};
 use nekoton_utils::*;
-use num_bigint::{BigInt, BigUint, ToBigInt};
+use num_bigint::{BigInt, BigUint};
+use serde::{Deserialize, Serialize};
 use ton_block::MsgAddressInt;
 use ton_executor::BlockchainConfig;
 
-use super::{ContractSubscription, InternalMessage};
+use super::{ContractSubscription, ContractSubscriptionSnapshot, InternalMessage};
+
+pub mod callbacks;
 
 pub struct TokenWallet {
     clock: Arc<dyn Clock>,
@@ -26,6 +31,9 @@ pub struct TokenWallet {
     symbol: Symbol,
     version: TokenWalletVersion,
     balance: BigUint,
+    transfer_relay: Option<TransferRelay>,
+    abi_registry: Option<Arc<AbiRegistry>>,
+    watch_only: bool,
 }
 
 impl TokenWallet {
@@ -60,7 +68,12 @@ impl TokenWallet {
 
             // NOTE: create handler beforehead to prevent lifetime issues
             let mut on_transactions_found = match preload_transactions {
-                true => Some(make_transactions_handler(handler, version)),
+                true => Some(make_transactions_handler(
+                    handler,
+                    version,
+                    &root_token_contract,
+                    None,
+                )),
                 false => None,
             };
 
@@ -100,13 +113,68 @@ impl TokenWallet {
             symbol,
             version,
             balance,
+            transfer_relay: None,
+            abi_registry: None,
+            watch_only: false,
         })
     }
 
+    /// Restores the wallet from the snapshot without requesting
+    /// the root contract and reloading the transactions history
+    pub async fn restore(
+        clock: Arc<dyn Clock>,
+        transport: Arc<dyn Transport>,
+        snapshot: TokenWalletSnapshot,
+        handler: Arc<dyn TokenWalletSubscriptionHandler>,
+    ) -> Result<TokenWallet> {
+        let version = snapshot.version;
+        let mut balance = Default::default();
+
+        let contract_subscription = ContractSubscription::restore(
+            clock.clone(),
+            transport,
+            snapshot.subscription,
+            &mut make_contract_state_handler(clock.clone(), version, &mut balance),
+        )
+        .await?;
+
+        handler.on_balance_changed(balance.clone());
+
+        Ok(Self {
+            clock,
+            contract_subscription,
+            handler,
+            owner: snapshot.owner,
+            symbol: snapshot.symbol,
+            version,
+            balance,
+            transfer_relay: snapshot.transfer_relay,
+            abi_registry: None,
+            watch_only: snapshot.watch_only,
+        })
+    }
+
+    /// Returns the state, required to restore the wallet later
+    pub fn snapshot(&self) -> TokenWalletSnapshot {
+        TokenWalletSnapshot {
+            owner: self.owner.clone(),
+            symbol: self.symbol.clone(),
+            version: self.version,
+            subscription: self.contract_subscription.snapshot(),
+            transfer_relay: self.transfer_relay.clone(),
+            watch_only: self.watch_only,
+        }
+    }
+
     pub fn contract_subscription(&self) -> &ContractSubscription {
         &self.contract_subscription
     }
 
+    /// Signature id which must be used to sign messages of the owner wallet
+    pub async fn get_signature_id(&self) -> Result<Option<SignatureId>> {
+        self.contract_subscription.get_signature_id().await
+    }
+
     pub fn owner(&self) -> &MsgAddressInt {
         &self.owner
     }
@@ -131,6 +199,47 @@ impl TokenWallet {
         self.contract_subscription.contract_state()
     }
 
+    /// Sets the registry which is used to decode transactions in
+    /// [`TokenWalletSubscriptionHandler::on_transactions_decoded`]
+    pub fn set_abi_registry(&mut self, abi_registry: Option<Arc<AbiRegistry>>) {
+        self.abi_registry = abi_registry;
+    }
+
+    /// Decodes messages of the transaction with the ABI registry
+    pub fn decode_transaction(&self, transaction: &Transaction) -> Option<DecodedAbiTransaction> {
+        self.abi_decoder()
+            .map(|decoder| decoder.decode(transaction))
+    }
+
+    fn abi_decoder(&self) -> Option<AbiDecoder> {
+        Some(AbiDecoder {
+            registry: self.abi_registry.clone()?,
+            address: self.address().clone(),
+            code_hash: self.contract_state().code_hash,
+        })
+    }
+
+    /// Whether the owner has no keystore entry and can't prepare messages
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only
+    }
+
+    pub fn set_watch_only(&mut self, watch_only: bool) {
+        self.watch_only = watch_only;
+    }
+
+    pub fn transfer_relay(&self) -> Option<&TransferRelay> {
+        self.transfer_relay.as_ref()
+    }
+
+    /// Routes all further transfers through the relay contract.
+    ///
+    /// The relay receives `tokens + fee` with the payload describing
+    /// the original transfer, keeps the fee and sends the rest to the recipient
+    pub fn set_transfer_relay(&mut self, relay: Option<TransferRelay>) {
+        self.transfer_relay = relay;
+    }
+
     pub async fn estimate_min_attached_amount(
         &self,
         destination: TransferRecipient,
@@ -236,6 +345,28 @@ impl TokenWallet {
             }
         }
 
+        self.ensure_not_watch_only()?;
+
+        let (destination, tokens, notify_receiver, payload) = match &self.transfer_relay {
+            Some(relay) => {
+                let total = &tokens + &relay.fee;
+                self.ensure_enough_tokens(&total)?;
+
+                let payload =
+                    make_relay_payload(&destination, tokens, &relay.fee, notify_receiver, payload)?;
+                (
+                    TransferRecipient::TokenWallet(relay.token_wallet.clone()),
+                    total,
+                    true,
+                    payload,
+                )
+            }
+            None => {
+                self.ensure_enough_tokens(&tokens)?;
+                (destination, tokens, notify_receiver, payload)
+            }
+        };
+
         let initial_balance = match &destination {
             TransferRecipient::OwnerWallet(address) => {
                 let transport = self.contract_subscription.transport();
@@ -311,14 +442,128 @@ impl TokenWallet {
         })
     }
 
+    /// Prepares a message which burns the specified amount of tokens.
+    ///
+    /// The root token contract will notify `callback_to` with the `payload`
+    pub fn prepare_burn(
+        &self,
+        tokens: BigUint,
+        callback_to: MsgAddressInt,
+        payload: ton_types::Cell,
+        attached_amount: u128,
+    ) -> Result<InternalMessage> {
+        self.ensure_not_watch_only()?;
+        self.ensure_enough_tokens(&tokens)?;
+
+        let (function, input) = match self.version {
+            TokenWalletVersion::OldTip3v4 => {
+                MessageBuilder::new(old_tip3::token_wallet_contract::burn_by_owner())
+                    .arg(BigUint128(tokens)) // tokens
+                    .arg(BigUint128(Default::default())) // grams
+                    .arg(&self.owner) // send_gas_to
+                    .arg(callback_to) // callback_address
+                    .arg(payload) // callback_payload
+                    .build()
+            }
+            TokenWalletVersion::Tip3 => {
+                MessageBuilder::new(tip3_1::token_wallet_contract::burnable::burn())
+                    .arg(BigUint128(tokens)) // amount
+                    .arg(&self.owner) // remainingGasTo
+                    .arg(callback_to) // callbackTo
+                    .arg(payload) // payload
+                    .build()
+            }
+        };
+
+        let body = function
+            .encode_internal_input(&input)
+            .and_then(ton_types::SliceData::load_builder)?;
+
+        Ok(InternalMessage {
+            source: Some(self.owner.clone()),
+            destination: self.address().clone(),
+            amount: attached_amount,
+            bounce: true,
+            body,
+        })
+    }
+
+    fn ensure_not_watch_only(&self) -> Result<()> {
+        if self.watch_only {
+            return Err(TokenWalletError::WatchOnlyAccount.into());
+        }
+        Ok(())
+    }
+
+    /// Checks that the wallet is deployed and has at least the specified amount of tokens
+    fn ensure_enough_tokens(&self, required: &BigUint) -> Result<()> {
+        if !self.contract_state().is_deployed {
+            return Err(TokenWalletError::AccountNotDeployed.into());
+        }
+        if *required > self.balance {
+            return Err(TokenWalletError::InsufficientBalance {
+                available: self.balance.clone(),
+                required: required.clone(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Prepares a message to the root token contract which mints new tokens.
+    ///
+    /// NOTE: only the root owner is allowed to mint tokens, so this wallet
+    /// owner must be the root owner
+    pub fn prepare_mint(
+        &self,
+        tokens: BigUint,
+        recipient: MsgAddressInt,
+        deploy_wallet_value: u128,
+        notify: bool,
+        payload: ton_types::Cell,
+        attached_amount: u128,
+    ) -> Result<InternalMessage> {
+        self.ensure_not_watch_only()?;
+        if self.version != TokenWalletVersion::Tip3 {
+            return Err(TokenWalletError::MintNotSupported.into());
+        }
+
+        let (function, input) = MessageBuilder::new(tip3_1::root_token_contract::mint())
+            .arg(BigUint128(tokens)) // amount
+            .arg(recipient) // recipient
+            .arg(BigUint128(deploy_wallet_value.into())) // deployWalletValue
+            .arg(&self.owner) // remainingGasTo
+            .arg(notify) // notify
+            .arg(payload) // payload
+            .build();
+
+        let body = function
+            .encode_internal_input(&input)
+            .and_then(ton_types::SliceData::load_builder)?;
+
+        Ok(InternalMessage {
+            source: Some(self.owner.clone()),
+            destination: self.symbol.root_token_contract.clone(),
+            amount: attached_amount,
+            bounce: true,
+            body,
+        })
+    }
+
     pub async fn refresh(&mut self) -> Result<()> {
         let mut balance = self.balance.clone();
 
+        let abi_decoder = self.abi_decoder();
         let handler = self.handler.as_ref();
         self.contract_subscription
             .refresh(
                 &mut make_contract_state_handler(self.clock.clone(), self.version, &mut balance),
-                &mut make_transactions_handler(handler, self.version),
+                &mut make_transactions_handler(
+                    handler,
+                    self.version,
+                    &self.symbol.root_token_contract,
+                    abi_decoder,
+                ),
                 &mut |_, _| {},
                 &mut |_| {},
             )
@@ -329,57 +574,31 @@ impl TokenWallet {
             handler.on_balance_changed(self.balance.clone());
         }
 
-        Ok(())
+        self.handle_contract_upgrade().await
     }
 
     pub async fn handle_block(&mut self, block: &ton_block::Block) -> Result<()> {
         let version = self.version;
+        let root_token_contract = &self.symbol.root_token_contract;
         let mut balance: BigInt = self.balance.clone().into();
 
+        let abi_decoder = self.abi_decoder();
         let handler = self.handler.as_ref();
         self.contract_subscription.handle_block(
             block,
             &mut |transactions, batch_info| {
-                let transactions = transactions
-                    .into_iter()
-                    .filter_map(|transaction| {
-                        let description = match transaction.data.description.read_struct().ok()? {
-                            ton_block::TransactionDescr::Ordinary(description) => description,
-                            _ => return None,
-                        };
-
-                        let data =
-                            parse_token_transaction(&transaction.data, &description, version);
-
-                        if let Some(data) = &data {
-                            match data {
-                                TokenWalletTransaction::IncomingTransfer(
-                                    TokenIncomingTransfer { tokens, .. },
-                                )
-                                | TokenWalletTransaction::Accept(tokens)
-                                | TokenWalletTransaction::SwapBackBounced(tokens)
-                                | TokenWalletTransaction::TransferBounced(tokens) => {
-                                    balance += tokens.clone().to_bigint().trust_me();
-                                }
-                                TokenWalletTransaction::OutgoingTransfer(
-                                    TokenOutgoingTransfer { tokens, .. },
-                                )
-                                | TokenWalletTransaction::SwapBack(TokenSwapBack {
-                                    tokens, ..
-                                }) => {
-                                    balance -= tokens.clone().to_bigint().trust_me();
-                                }
-                            }
-                        }
-
-                        let transaction =
-                            Transaction::try_from((transaction.hash, transaction.data)).ok()?;
-
-                        Some(TransactionWithData { transaction, data })
-                    })
-                    .collect();
-
-                handler.on_transactions_found(transactions, batch_info)
+                let (transactions, balance_changes) =
+                    parse_transactions(transactions, version, root_token_contract);
+
+                for change in &balance_changes {
+                    for token_delta in &change.token_deltas {
+                        balance += &token_delta.delta;
+                    }
+                }
+
+                notify_decoded_transactions(handler, abi_decoder.as_ref(), &transactions);
+                handler.on_transactions_found(transactions, batch_info);
+                handler.on_balance_changes(balance_changes, batch_info);
             },
             &mut |_, _| {},
             &mut |_| {},
@@ -391,18 +610,103 @@ impl TokenWallet {
             handler.on_balance_changed(self.balance.clone());
         }
 
+        self.handle_contract_upgrade().await
+    }
+
+    /// Re-derives the token wallet version if the wallet code was changed
+    async fn handle_contract_upgrade(&mut self) -> Result<()> {
+        // NOTE: the upgrade is taken only after it was handled, so it will
+        // be handled again on the next refresh if the state request fails
+        let upgrade = match self.contract_subscription.contract_upgrade() {
+            Some(upgrade) => *upgrade,
+            None => return Ok(()),
+        };
+
+        let state = self
+            .contract_subscription
+            .transport()
+            .get_contract_state(self.address())
+            .await?;
+
+        if let RawContractState::Exists(state) = state {
+            let contract = TokenWalletContractState(state.as_context(self.clock.as_ref()));
+            match contract.get_version() {
+                Ok(version) => {
+                    self.version = version;
+                    let balance = contract.get_balance(version)?;
+                    if balance != self.balance {
+                        self.balance = balance;
+                        self.handler.on_balance_changed(self.balance.clone());
+                    }
+                }
+                // NOTE: keep the previous version for the unknown code
+                Err(e) => log::error!("{e}"),
+            }
+        }
+
+        self.contract_subscription.take_contract_upgrade();
+        self.handler.on_contract_upgraded(upgrade);
         Ok(())
     }
 
     pub async fn preload_transactions(&mut self, from_lt: u64) -> Result<()> {
+        let abi_decoder = self.abi_decoder();
         let handler = self.handler.as_ref();
         self.contract_subscription
             .preload_transactions(
                 from_lt,
-                &mut make_transactions_handler(handler, self.version),
+                &mut make_transactions_handler(
+                    handler,
+                    self.version,
+                    &self.symbol.root_token_contract,
+                    abi_decoder,
+                ),
             )
             .await
     }
+
+    /// Loads older transactions until one of the conditions is met.
+    ///
+    /// [`BackfillStopCondition::Found`] counts incoming and outgoing token transfers
+    pub async fn backfill_transactions(
+        &mut self,
+        state: &mut BackfillState,
+        conditions: &[BackfillStopCondition],
+        on_progress: &mut (dyn FnMut(&BackfillState) + Send + Sync),
+    ) -> Result<()> {
+        let abi_decoder = self.abi_decoder();
+        let handler = self.handler.as_ref();
+        let version = self.version;
+        self.contract_subscription
+            .backfill_transactions(
+                state,
+                conditions,
+                &mut |transaction| is_token_transfer(transaction, version),
+                &mut make_transactions_handler(
+                    handler,
+                    version,
+                    &self.symbol.root_token_contract,
+                    abi_decoder,
+                ),
+                on_progress,
+            )
+            .await
+    }
+}
+
+/// Serializable state of the [`TokenWallet`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenWalletSnapshot {
+    #[serde(with = "serde_address")]
+    pub owner: MsgAddressInt,
+    pub symbol: Symbol,
+    pub version: TokenWalletVersion,
+    pub subscription: ContractSubscriptionSnapshot,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer_relay: Option<TransferRelay>,
+    #[serde(default)]
+    pub watch_only: bool,
 }
 
 pub trait TokenWalletSubscriptionHandler: Send + Sync {
@@ -417,6 +721,28 @@ pub trait TokenWalletSubscriptionHandler: Send + Sync {
         transactions: Vec<TransactionWithData<TokenWalletTransaction>>,
         batch_info: TransactionsBatchInfo,
     );
+
+    /// Called before [`TokenWalletSubscriptionHandler::on_transactions_found`]
+    /// if the ABI registry is set. Decoded items have the same order as transactions
+    fn on_transactions_decoded(
+        &self,
+        transactions: &[TransactionWithData<TokenWalletTransaction>],
+        decoded: Vec<DecodedAbiTransaction>,
+    ) {
+        let _ = (transactions, decoded);
+    }
+
+    /// Called after [`TokenWalletSubscriptionHandler::on_transactions_found`]
+    /// with the native and token balance changes of each transaction
+    fn on_balance_changes(&self, changes: Vec<BalanceChange>, batch_info: TransactionsBatchInfo) {
+        let _ = (changes, batch_info);
+    }
+
+    /// Called when the token wallet code hash changed.
+    /// [`TokenWallet::version`] is already updated at this point
+    fn on_contract_upgraded(&self, upgrade: ContractUpgrade) {
+        let _ = upgrade;
+    }
 }
 
 pub async fn get_token_root_details(
@@ -464,6 +790,87 @@ pub async fn get_token_wallet_details(
     Ok((token_wallet_details, root_contract_details))
 }
 
+/// Token wallet of the owner, found by [`find_owned_wallets`]
+#[derive(Debug, Clone)]
+pub struct OwnedTokenWallet {
+    pub address: MsgAddressInt,
+    pub symbol: Symbol,
+    pub version: TokenWalletVersion,
+    pub balance: BigUint,
+}
+
+/// Computes token wallet addresses of the owner for each of the known root contracts
+/// and returns deployed ones with their balances, in the order of `root_token_contracts`.
+///
+/// Root contracts which don't exist or can't be parsed are skipped
+pub async fn find_owned_wallets(
+    clock: &dyn Clock,
+    transport: &dyn Transport,
+    owner: &MsgAddressInt,
+    root_token_contracts: &[MsgAddressInt],
+) -> Result<Vec<OwnedTokenWallet>> {
+    use futures_util::stream::{StreamExt, TryStreamExt};
+
+    const MAX_CONCURRENT_REQUESTS: usize = 10;
+
+    let wallets = futures_util::stream::iter(root_token_contracts)
+        .map(|root_token_contract| find_owned_wallet(clock, transport, owner, root_token_contract))
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    Ok(wallets.into_iter().flatten().collect())
+}
+
+async fn find_owned_wallet(
+    clock: &dyn Clock,
+    transport: &dyn Transport,
+    owner: &MsgAddressInt,
+    root_token_contract: &MsgAddressInt,
+) -> Result<Option<OwnedTokenWallet>> {
+    let root_state = match transport.get_contract_state(root_token_contract).await? {
+        RawContractState::Exists(state) => state,
+        RawContractState::NotExists { .. } => return Ok(None),
+    };
+    let root_state = RootTokenContractState(root_state.as_context(clock));
+    let (details, address) = match root_state.guess_details().and_then(|details| {
+        let address = root_state.get_wallet_address(details.version, owner)?;
+        Ok((details, address))
+    }) {
+        Ok(details) => details,
+        Err(e) => {
+            log::warn!("Skipping invalid root token contract {root_token_contract}: {e:?}");
+            return Ok(None);
+        }
+    };
+
+    let wallet_state = match transport.get_contract_state(&address).await? {
+        RawContractState::Exists(state) => state,
+        RawContractState::NotExists { .. } => return Ok(None),
+    };
+    let balance = match TokenWalletContractState(wallet_state.as_context(clock))
+        .get_balance(details.version)
+    {
+        Ok(balance) => balance,
+        Err(e) => {
+            log::warn!("Skipping invalid token wallet {address}: {e:?}");
+            return Ok(None);
+        }
+    };
+
+    Ok(Some(OwnedTokenWallet {
+        address,
+        symbol: Symbol {
+            name: details.symbol,
+            full_name: details.name,
+            decimals: details.decimals,
+            root_token_contract: root_token_contract.clone(),
+        },
+        version: details.version,
+        balance,
+    }))
+}
+
 pub async fn get_token_root_details_from_token_wallet(
     clock: &dyn Clock,
     transport: &dyn Transport,
@@ -472,7 +879,7 @@ pub async fn get_token_root_details_from_token_wallet(
     let state = match transport.get_contract_state(token_wallet_address).await? {
         RawContractState::Exists(state) => state,
         RawContractState::NotExists { .. } => {
-            return Err(TokenWalletError::WalletNotDeployed.into())
+            return Err(TokenWalletError::AccountNotDeployed.into())
         }
     };
     let state = TokenWalletContractState(state.as_context(clock));
@@ -541,41 +948,125 @@ fn calculate_initial_balance(config: &BlockchainConfig, address: &MsgAddressInt)
     Ok(fees as u128)
 }
 
-fn make_transactions_handler(
-    handler: &'_ dyn TokenWalletSubscriptionHandler,
+fn make_transactions_handler<'a>(
+    handler: &'a dyn TokenWalletSubscriptionHandler,
     version: TokenWalletVersion,
-) -> impl FnMut(Vec<RawTransaction>, TransactionsBatchInfo) + '_ {
+    root_token_contract: &'a MsgAddressInt,
+    abi_decoder: Option<AbiDecoder>,
+) -> impl FnMut(Vec<RawTransaction>, TransactionsBatchInfo) + 'a {
     move |transactions, batch_info| {
-        let transactions = transactions
-            .into_iter()
-            .filter_map(
-                |transaction| match transaction.data.description.read_struct().ok()? {
-                    ton_block::TransactionDescr::Ordinary(description) => {
-                        let data =
-                            parse_token_transaction(&transaction.data, &description, version);
-
-                        let transaction =
-                            Transaction::try_from((transaction.hash, transaction.data)).ok()?;
-
-                        Some(TransactionWithData { transaction, data })
-                    }
-                    _ => None,
-                },
-            )
+        let (transactions, balance_changes) =
+            parse_transactions(transactions, version, root_token_contract);
+
+        notify_decoded_transactions(handler, abi_decoder.as_ref(), &transactions);
+        handler.on_transactions_found(transactions, batch_info);
+        handler.on_balance_changes(balance_changes, batch_info);
+    }
+}
+
+fn notify_decoded_transactions(
+    handler: &dyn TokenWalletSubscriptionHandler,
+    abi_decoder: Option<&AbiDecoder>,
+    transactions: &[TransactionWithData<TokenWalletTransaction>],
+) {
+    if let Some(abi_decoder) = abi_decoder {
+        let decoded = transactions
+            .iter()
+            .map(|item| abi_decoder.decode(&item.transaction))
             .collect();
+        handler.on_transactions_decoded(transactions, decoded);
+    }
+}
+
+/// Parses token transactions and their balance changes (one item per ordinary transaction)
+fn parse_transactions(
+    transactions: Vec<RawTransaction>,
+    version: TokenWalletVersion,
+    root_token_contract: &MsgAddressInt,
+) -> (
+    Vec<TransactionWithData<TokenWalletTransaction>>,
+    Vec<BalanceChange>,
+) {
+    let mut balance_changes = Vec::with_capacity(transactions.len());
+    let transactions = transactions
+        .into_iter()
+        .filter_map(|transaction| {
+            let description = match transaction.data.description.read_struct().ok()? {
+                ton_block::TransactionDescr::Ordinary(description) => description,
+                _ => return None,
+            };
+
+            let data = parse_token_transaction(&transaction.data, &description, version);
+
+            let mut balance_change = BalanceChange::new(transaction.hash, &transaction.data);
+            if let Some(data) = &data {
+                balance_change.token_deltas.push(TokenBalanceDelta {
+                    root_token_contract: root_token_contract.clone(),
+                    delta: data.balance_delta(),
+                });
+            }
+            balance_changes.push(balance_change);
 
-        handler.on_transactions_found(transactions, batch_info)
+            let transaction = Transaction::try_from((transaction.hash, transaction.data)).ok()?;
+
+            Some(TransactionWithData { transaction, data })
+        })
+        .collect();
+
+    (transactions, balance_changes)
+}
+
+fn is_token_transfer(transaction: &RawTransaction, version: TokenWalletVersion) -> bool {
+    match transaction.data.description.read_struct() {
+        Ok(ton_block::TransactionDescr::Ordinary(description)) => matches!(
+            parse_token_transaction(&transaction.data, &description, version),
+            Some(
+                TokenWalletTransaction::IncomingTransfer(_)
+                    | TokenWalletTransaction::OutgoingTransfer(_)
+            )
+        ),
+        _ => false,
     }
 }
 
+/// Payload for the relay contract with the original transfer.
+///
+/// Layout (ABI 2.2): `recipient: address, deployWallet: bool, amount: uint128,
+/// fee: uint128, notify: bool, payload: cell`
+fn make_relay_payload(
+    destination: &TransferRecipient,
+    tokens: BigUint,
+    fee: &BigUint,
+    notify_receiver: bool,
+    payload: ton_types::Cell,
+) -> Result<ton_types::Cell> {
+    let (recipient, deploy) = match destination {
+        TransferRecipient::OwnerWallet(address) => (address.clone(), true),
+        TransferRecipient::TokenWallet(address) => (address.clone(), false),
+    };
+
+    pack_into_cell(
+        &[
+            recipient.token_value().named("recipient"),
+            deploy.token_value().named("deployWallet"),
+            BigUint128(tokens).token_value().named("amount"),
+            BigUint128(fee.clone()).token_value().named("fee"),
+            notify_receiver.token_value().named("notify"),
+            payload.token_value().named("payload"),
+        ],
+        ton_abi::contract::ABI_VERSION_2_2,
+    )
+}
+
+#[non_exhaustive]
 #[derive(thiserror::Error, Debug)]
-enum TokenWalletError {
+pub enum TokenWalletError {
     #[error("Invalid root token contract")]
     InvalidRootTokenContract,
     #[error("Invalid token wallet contract")]
     InvalidTokenWalletContract,
     #[error("Wallet not deployed")]
-    WalletNotDeployed,
+    AccountNotDeployed,
     #[error("No source transaction produced")]
     NoSourceTx,
     #[error("No destination transaction produced")]
@@ -584,6 +1075,15 @@ enum TokenWalletError {
     SourceTxFailed(Option<i32>),
     #[error("Destination transaction failed with exit code {0:?}")]
     DestinationTxFailed(Option<i32>),
+    #[error("Mint is not supported by this token version")]
+    MintNotSupported,
+    #[error("Watch-only account can't prepare messages")]
+    WatchOnlyAccount,
+    #[error("Insufficient token balance: available {available}, required {required}")]
+    InsufficientBalance {
+        available: BigUint,
+        required: BigUint,
+    },
 }
 
 #[cfg(test)]
@@ -650,6 +1150,86 @@ mod tests {
         }
     }
 
+    #[test]
+    fn snapshot_keeps_transfer_relay() {
+        let address =
+            convert_address("0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb");
+        let mut snapshot = TokenWalletSnapshot {
+            owner: address.clone(),
+            symbol: Symbol {
+                name: "USDT".to_owned(),
+                full_name: "Tether USD".to_owned(),
+                decimals: 6,
+                root_token_contract: address.clone(),
+            },
+            version: TokenWalletVersion::Tip3,
+            subscription: ContractSubscriptionSnapshot {
+                address: address.clone(),
+                contract_state: Default::default(),
+                last_lt: 0,
+                latest_known_lt: None,
+                pending_transactions: Vec::new(),
+            },
+            transfer_relay: None,
+            watch_only: false,
+        };
+
+        // Snapshots without the relay are still accepted
+        let serialized = serde_json::to_string(&snapshot).unwrap();
+        assert!(!serialized.contains("transferRelay"));
+        let restored: TokenWalletSnapshot = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(restored.transfer_relay, None);
+
+        let relay = TransferRelay {
+            token_wallet: address,
+            fee: BigUint::from(5u32),
+        };
+        snapshot.transfer_relay = Some(relay.clone());
+
+        let serialized = serde_json::to_string(&snapshot).unwrap();
+        let restored: TokenWalletSnapshot = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(restored.transfer_relay, Some(relay));
+    }
+
+    #[test]
+    fn relay_payload() {
+        let recipient =
+            convert_address("0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb");
+        let payload = make_relay_payload(
+            &TransferRecipient::OwnerWallet(recipient.clone()),
+            BigUint::from(100u32),
+            &BigUint::from(5u32),
+            false,
+            Default::default(),
+        )
+        .unwrap();
+
+        let params = [
+            ton_abi::Param::new("recipient", ton_abi::ParamType::Address),
+            ton_abi::Param::new("deployWallet", ton_abi::ParamType::Bool),
+            ton_abi::Param::new("amount", ton_abi::ParamType::Uint(128)),
+            ton_abi::Param::new("fee", ton_abi::ParamType::Uint(128)),
+            ton_abi::Param::new("notify", ton_abi::ParamType::Bool),
+            ton_abi::Param::new("payload", ton_abi::ParamType::Cell),
+        ];
+        let tokens = unpack_from_cell(
+            &params,
+            ton_types::SliceData::load_cell(payload).unwrap(),
+            false,
+            ton_abi::contract::ABI_VERSION_2_2,
+        )
+        .unwrap();
+
+        let address: MsgAddressInt = tokens[0].value.clone().unpack().unwrap();
+        assert_eq!(address, recipient);
+        let deploy: bool = tokens[1].value.clone().unpack().unwrap();
+        assert!(deploy);
+        let amount: BigUint = tokens[2].value.clone().unpack().unwrap();
+        assert_eq!(amount, BigUint::from(100u32));
+        let fee: BigUint = tokens[3].value.clone().unpack().unwrap();
+        assert_eq!(fee, BigUint::from(5u32));
+    }
+
     #[test]
     fn compute_token_wallet_address() {
         let owner_address = "0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb";
@@ -682,6 +1262,280 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compute_tip3_wallet_address_locally() {
+        let owner =
+            convert_address("0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb");
+        let expected =
+            convert_address("0:a3d6926fc25c9ac9a93b1f0afab9dabe63798290ccdc318c1940b99b1c1dade6");
+
+        let root = root_token_contract(TokenWalletVersion::Tip3);
+        let wallet = token_wallet_contract(TokenWalletVersion::Tip3);
+        assert_eq!(wallet.account.addr, expected);
+
+        let wallet_code = match &wallet.account.storage.state {
+            ton_block::AccountState::AccountActive { state_init } => {
+                state_init.code.clone().unwrap()
+            }
+            _ => panic!("token wallet is not deployed"),
+        };
+
+        let address = tip3_1::compute_wallet_address(
+            wallet_code,
+            &root.account.addr,
+            &owner,
+            ton_abi::contract::ABI_VERSION_2_2,
+        )
+        .unwrap();
+        assert_eq!(address, expected);
+    }
+
+    struct TestHandler;
+
+    impl TokenWalletSubscriptionHandler for TestHandler {
+        fn on_balance_changed(&self, _: BigUint) {}
+
+        fn on_transactions_found(
+            &self,
+            _: Vec<TransactionWithData<TokenWalletTransaction>>,
+            _: TransactionsBatchInfo,
+        ) {
+        }
+    }
+
+    /// Owner of the test token wallets
+    fn test_owner() -> MsgAddressInt {
+        convert_address("0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb")
+    }
+
+    /// Subscribes to the test owner wallet of the token of the specified version
+    async fn subscribe_test_wallet(version: TokenWalletVersion, deployed: bool) -> TokenWallet {
+        use crate::transport::tests::MockTransport;
+
+        let root = root_token_contract(version);
+        let root_address = root.account.addr.clone();
+
+        let transport = Arc::new(MockTransport::new());
+        transport.set_contract_state(root_address.clone(), RawContractState::Exists(root));
+        if deployed {
+            let wallet = token_wallet_contract(version);
+            transport.set_contract_state(
+                wallet.account.addr.clone(),
+                RawContractState::Exists(wallet),
+            );
+        }
+
+        TokenWallet::subscribe(
+            Arc::new(SimpleClock),
+            transport,
+            test_owner(),
+            root_address,
+            Arc::new(TestHandler),
+            false,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn watch_only_wallet_cant_prepare_messages() {
+        let owner = test_owner();
+        let mut token_wallet = subscribe_test_wallet(TokenWalletVersion::Tip3, true).await;
+
+        let burn = |token_wallet: &TokenWallet| {
+            token_wallet.prepare_burn(
+                BigUint::default(),
+                owner.clone(),
+                Default::default(),
+                100_000_000,
+            )
+        };
+        burn(&token_wallet).unwrap();
+
+        token_wallet.set_watch_only(true);
+        assert!(token_wallet.snapshot().watch_only);
+
+        let err = burn(&token_wallet).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TokenWalletError>(),
+            Some(TokenWalletError::WatchOnlyAccount)
+        ));
+
+        let err = token_wallet
+            .prepare_transfer(
+                TransferRecipient::OwnerWallet(owner.clone()),
+                BigUint::default(),
+                false,
+                Default::default(),
+                100_000_000,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TokenWalletError>(),
+            Some(TokenWalletError::WatchOnlyAccount)
+        ));
+    }
+
+    #[tokio::test]
+    async fn burn_requires_enough_tokens() {
+        let owner = test_owner();
+        let burn = |token_wallet: &TokenWallet, tokens: BigUint| {
+            token_wallet.prepare_burn(tokens, owner.clone(), Default::default(), 100_000_000)
+        };
+
+        // Wallet is not deployed yet
+        let token_wallet = subscribe_test_wallet(TokenWalletVersion::Tip3, false).await;
+        let err = burn(&token_wallet, BigUint::default()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TokenWalletError>(),
+            Some(TokenWalletError::AccountNotDeployed)
+        ));
+
+        let token_wallet = subscribe_test_wallet(TokenWalletVersion::Tip3, true).await;
+        let balance = token_wallet.balance().clone();
+        burn(&token_wallet, balance.clone()).unwrap();
+
+        let required = balance.clone() + 1u32;
+        let err = burn(&token_wallet, required.clone()).unwrap_err();
+        match err.downcast_ref::<TokenWalletError>() {
+            Some(TokenWalletError::InsufficientBalance {
+                available,
+                required: actual_required,
+            }) => {
+                assert_eq!(*available, balance);
+                assert_eq!(*actual_required, required);
+            }
+            _ => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn prepare_burn_and_mint_messages() {
+        let owner = test_owner();
+        let recipient =
+            convert_address("0:0000000000000000000000000000000000000000000000000000000000000001");
+
+        let token_wallet = subscribe_test_wallet(TokenWalletVersion::Tip3, true).await;
+        let tokens = token_wallet.balance().clone();
+
+        let burn = token_wallet
+            .prepare_burn(tokens.clone(), recipient.clone(), Default::default(), 100)
+            .unwrap();
+        assert_eq!(burn.source.as_ref(), Some(&owner));
+        assert_eq!(&burn.destination, token_wallet.address());
+        assert_eq!(burn.amount, 100);
+
+        let inputs = tip3_1::token_wallet_contract::burnable::burn()
+            .decode_input(burn.body, true, false)
+            .unwrap();
+        let (amount, remaining_gas_to, callback_to): (BigUint, MsgAddressInt, MsgAddressInt) = (
+            inputs[0].value.clone().unpack().unwrap(),
+            inputs[1].value.clone().unpack().unwrap(),
+            inputs[2].value.clone().unpack().unwrap(),
+        );
+        assert_eq!(amount, tokens);
+        assert_eq!(remaining_gas_to, owner);
+        assert_eq!(callback_to, recipient);
+
+        let mint = token_wallet
+            .prepare_mint(
+                BigUint::from(10u32),
+                recipient.clone(),
+                50,
+                true,
+                Default::default(),
+                100,
+            )
+            .unwrap();
+        assert_eq!(
+            &mint.destination,
+            &token_wallet.symbol().root_token_contract
+        );
+
+        let inputs = tip3_1::root_token_contract::mint()
+            .decode_input(mint.body, true, false)
+            .unwrap();
+        let (amount, mint_recipient, deploy_wallet_value, notify): (
+            BigUint,
+            MsgAddressInt,
+            BigUint,
+            bool,
+        ) = (
+            inputs[0].value.clone().unpack().unwrap(),
+            inputs[1].value.clone().unpack().unwrap(),
+            inputs[2].value.clone().unpack().unwrap(),
+            inputs[4].value.clone().unpack().unwrap(),
+        );
+        assert_eq!(amount, BigUint::from(10u32));
+        assert_eq!(mint_recipient, recipient);
+        assert_eq!(deploy_wallet_value, BigUint::from(50u32));
+        assert!(notify);
+
+        // Old tokens can't be minted by the owner wallet
+        let token_wallet = subscribe_test_wallet(TokenWalletVersion::OldTip3v4, false).await;
+        let err = token_wallet
+            .prepare_mint(
+                BigUint::from(10u32),
+                recipient,
+                50,
+                false,
+                Default::default(),
+                100,
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TokenWalletError>(),
+            Some(TokenWalletError::MintNotSupported)
+        ));
+    }
+
+    #[tokio::test]
+    async fn find_owned_wallets_skips_invalid_roots() {
+        use crate::transport::models::ExistingContract;
+        use crate::transport::tests::MockTransport;
+
+        let owner =
+            convert_address("0:a921453472366b7feeec15323a96b5dcf17197c88dc0d4578dfa52900b8a33cb");
+        let root = root_token_contract(TokenWalletVersion::Tip3);
+        let wallet = token_wallet_contract(TokenWalletVersion::Tip3);
+        let root_address = root.account.addr.clone();
+        let wallet_address = wallet.account.addr.clone();
+
+        let invalid_root_address = convert_address(&format!("0:{}", "11".repeat(32)));
+        let missing_root_address = convert_address(&format!("0:{}", "22".repeat(32)));
+
+        let transport = MockTransport::new();
+        transport.set_contract_state(root_address.clone(), RawContractState::Exists(root));
+        transport.set_contract_state(wallet_address.clone(), RawContractState::Exists(wallet));
+        transport.set_contract_state(
+            invalid_root_address.clone(),
+            RawContractState::Exists(ExistingContract {
+                account: Default::default(),
+                timings: nekoton_abi::GenTimings::Unknown,
+                last_transaction_id: nekoton_abi::LastTransactionId::Inexact { latest_lt: 0 },
+            }),
+        );
+
+        let wallets = find_owned_wallets(
+            &SimpleClock,
+            &transport,
+            &owner,
+            &[
+                invalid_root_address,
+                root_address.clone(),
+                missing_root_address,
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(wallets.len(), 1);
+        assert_eq!(wallets[0].address, wallet_address);
+        assert_eq!(wallets[0].symbol.root_token_contract, root_address);
+        assert_eq!(wallets[0].version, TokenWalletVersion::Tip3);
+    }
+
     #[test]
     fn get_root_contract_details() {
         // Old
@@ -711,6 +1565,39 @@ mod tests {
         assert_eq!(details.symbol, "TST1");
     }
 
+    #[test]
+    fn run_getters_with_same_state() {
+        use old_tip3::root_token_contract::{get_details, get_version, RootTokenContractDetails};
+
+        let root_state = root_token_contract(TokenWalletVersion::OldTip3v4);
+        let context = root_state.as_context(&SimpleClock);
+
+        let inputs = vec![0u32.token_value().named("answerId")];
+        let outputs = context
+            .run_getters(
+                &[
+                    (get_version().clone(), inputs.clone()),
+                    (get_details().clone(), inputs),
+                ],
+                &BriefBlockchainConfig::default(),
+            )
+            .unwrap();
+        assert_eq!(outputs.len(), 2);
+
+        let mut outputs = outputs.into_iter().map(|output| output.tokens.unwrap());
+
+        let version: u32 = outputs.next().unwrap().unpack_first().unwrap();
+        let expected = old_tip3::RootTokenContract(context).get_version().unwrap();
+        assert_eq!(version, expected);
+
+        let details: RootTokenContractDetails = outputs.next().unwrap().unpack_first().unwrap();
+        assert_eq!(details.symbol, "WTON");
+        assert_eq!(
+            details.total_supply,
+            BigUint::from_str("22000000000").unwrap()
+        );
+    }
+
     #[test]
     fn get_strange_root_contract_details() {
         let root_state = r#"{"account":"te6ccgECpgEALrQAAnKAHh5eeOtBZE9N4jZNP+Kf124tRZChZNcAVHqtbpKGWoYGmYCwsgYPfoTgAAB4cmmGFjKAl0UXiSZgAQTzAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAIAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAwe+zxf4AAAAAAAAAAAAONfmkrtgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACBfUVECAhD0pCCK7VP0oANhAgEgBwQBAv8FAv5/jQhgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAE+Gkh2zzTAAGOHYECANcYIPkBAdMAAZTT/wMBkwL4QuIg+GX5EPKoldMAAfJ64tM/AY4d+EMhuSCfMCD4I4ED6KiCCBt3QKC53pMg+GPg8jTYMNMfAfgjvPK5EQYCFtMfAds8+EdujoDeCggDbt9wItDTA/pAMPhpqTgA+ER/b3GCCJiWgG9ybW9zcG90+GSOgOAhxwDcIdMfId0B2zz4R26OgN5ICggBBlvbPAkCDvhBbuMA2zxQSQRYIIIQDC/yDbuOgOAgghApxIl+u46A4CCCEEvxYOK7joDgIIIQebJe4buOgOA8KBQLBFAgghBotV8/uuMCIIIQce7odbrjAiCCEHVszfe64wIgghB5sl7huuMCEA8ODALqMPhBbuMA0x/4RFhvdfhk0fhEcG9ycG9xgEBvdPhk+Er4TPhN+E74UPhR+FJvByHA/45CI9DTAfpAMDHIz4cgzoBgz0DPgc+DyM+T5sl7hiJvJ1UGJ88WJs8L/yXPFiTPC3/IJM8WI88WIs8KAGxyzc3JcPsAUA0Bvo5W+EQgbxMhbxL4SVUCbxHIcs9AygBzz0DOAfoC9ACAaM9Az4HPg8j4RG8VzwsfIm8nVQYnzxYmzwv/Jc8WJM8Lf8gkzxYjzxYizwoAbHLNzcn4RG8U+wDiMOMAf/hnSQPiMPhBbuMA0fhN+kJvE9cL/8MAIJcw+E34SccF3iCOFDD4TMMAIJww+Ez4RSBukjBw3rre3/LgZPhN+kJvE9cL/8MAjoCS+ADibfhv+E36Qm8T1wv/jhX4ScjPhYjOgG3PQM+Bz4HJgQCA+wDe2zx/+GdQRUkCsDD4QW7jAPpBldTR0PpA39cMAJXU0dDSAN/R+E36Qm8T1wv/wwAglzD4TfhJxwXeII4UMPhMwwAgnDD4TPhFIG6SMHDeut7f8uBk+AAh+HAg+HJb2zx/+GdQSQLiMPhBbuMA+Ebyc3H4ZtH4TPhCuiCOFDD4TfpCbxPXC//AACCVMPhMwADf3vLgZPgAf/hy+E36Qm8T1wv/ji34TcjPhYjOjQPInEAAAAAAAAAAAAAAAAABzxbPgc+Bz5EhTuze+ErPFslx+wDe2zx/+GcRSQGS7UTQINdJwgGOPNP/0z/TANX6QPpA+HH4cPht+kDU0//Tf/QEASBuldDTf28C3/hv1woA+HL4bvhs+Gv4an/4Yfhm+GP4Yo6A4hIB/vQFcSGAQPQOjiSNCGAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAATf+GpyIYBA9A+SyMnf+GtzIYBA9A6T1wv/kXDi+Gx0IYBA9A6OJI0IYAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABN/4bXD4bm0TAM74b40IYAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABPhwjQhgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAE+HFw+HJwAYBA9A7yvdcL//hicPhjcPhmf/hhA0AgghA/ENGru46A4CCCEElpWH+7joDgIIIQS/Fg4rrjAiAZFQL+MPhBbuMA+kGV1NHQ+kDf1w1/ldTR0NN/39cNf5XU0dDTf9/6QZXU0dD6QN/XDACV1NHQ0gDf1NH4TfpCbxPXC//DACCXMPhN+EnHBd4gjhQw+EzDACCcMPhM+EUgbpIwcN663t/y4GQkwgDy4GQk+E678uBlJfpCbxPXC//DAFAWAjLy4G8l+CjHBbPy4G/4TfpCbxPXC//DAI6AGBcB5I5o+CdvECS88uBuI4IK+vCAvPLgbvgAJPhOAaG1f/huIyZ/yM+FgMoAc89AzgH6AoBpz0DPgc+DyM+QY0hcCibPC3/4TM8L//hNzxYk+kJvE9cL/8MAkSSS+CjizxYjzwoAIs8Uzclx+wDiXwbbPH/4Z0kB7oIK+vCA+CdvENs8obV/tgn4J28QIYIK+vCAoLV/vPLgbiBy+wIl+E4BobV/+G4mf8jPhYDKAHPPQM6Abc9Az4HPg8jPkGNIXAonzwt/+EzPC//4Tc8WJfpCbxPXC//DAJElkvhN4s8WJM8KACPPFM3JgQCB+wAwmwIoIIIQP1Z5UbrjAiCCEElpWH+64wIcGgKQMPhBbuMA0x/4RFhvdfhk0fhEcG9ycG9xgEBvdPhk+E4hwP+OIyPQ0wH6QDAxyM+HIM6AYM9Az4HPgc+TJaVh/iHPC3/JcPsAUBsBgI43+EQgbxMhbxL4SVUCbxHIcs9AygBzz0DOAfoC9ACAaM9Az4HPgfhEbxXPCx8hzwt/yfhEbxT7AOIw4wB/+GdJBPww+EFu4wD6QZXU0dD6QN/XDX+V1NHQ03/f+kGV1NHQ+kDf1wwAldTR0NIA39TR+E9us/Lga/hJ+E8gbvJ/bxHHBfLgbCP4TyBu8n9vELvy4G0j+E678uBlI8IA8uBkJPgoxwWz8uBv+E36Qm8T1wv/wwCOgI6A4iP4TgGhtX9QHx4dAbT4bvhPIG7yf28QJKG1f/hPIG7yf28RbwL4byR/yM+FgMoAc89AzoBtz0DPgc+DyM+QY0hcCiXPC3/4TM8L//hNzxYkzxYjzwoAIs8UzcmBAIH7AF8F2zx/+GdJAi7bPIIK+vCAvPLgbvgnbxDbPKG1f3L7ApubAnKCCvrwgPgnbxDbPKG1f7YJ+CdvECGCCvrwgKC1f7zy4G4gcvsCggr68ID4J28Q2zyhtX+2CXL7AjCbmwIoIIIQLalNL7rjAiCCED8Q0au64wInIQL+MPhBbuMA1w3/ldTR0NP/3/pBldTR0PpA39cNf5XU0dDTf9/XDX+V1NHQ03/f1w1/ldTR0NN/3/pBldTR0PpA39cMAJXU0dDSAN/U0fhN+kJvE9cL/8MAIJcw+E34SccF3iCOFDD4TMMAIJww+Ez4RSBukjBw3rre3/LgZCXCAFAiAvzy4GQl+E678uBlJvpCbxPXC//AACCUMCfAAN/y4G/4TfpCbxPXC//DAI6AjiD4J28QJSWgtX+88uBuI4IK+vCAvPLgbif4TL3y4GT4AOJtKMjL/3BYgED0Q/hKcViAQPQW+EtyWIBA9BcoyMv/c1iAQPRDJ3RYgED0Fsj0AMkmIwH8+EvIz4SA9AD0AM+ByY0IYAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABCbCAI43ISD5APgo+kJvEsjPhkDKB8v/ydAoIcjPhYjOAfoCgGnPQM+Dz4MizxTPgc+RotV8/slx+wAxMZ0h+QDIz4oAQMv/ydAx4vhNJAG4+kJvE9cL/8MAjlEn+E4BobV/+G4gf8jPhYDKAHPPQM6Abc9Az4HPg8jPkGNIXAopzwt/+EzPC//4Tc8WJvpCbxPXC//DAJEmkvhN4s8WJc8KACTPFM3JgQCB+wAlAbyOUyf4TgGhtX/4biUhf8jPhYDKAHPPQM4B+gKAac9Az4HPg8jPkGNIXAopzwt/+EzPC//4Tc8WJvpCbxPXC//DAJEmkvgo4s8WJc8KACTPFM3JcfsA4ltfCNs8f/hnSQFmggr68ID4J28Q2zyhtX+2CfgnbxAhggr68ICgtX8noLV/vPLgbif4TccFs/LgbyBy+wIwmwHoMNMf+ERYb3X4ZNF0IcD/jiMj0NMB+kAwMcjPhyDOgGDPQM+Bz4HPkralNL4hzwsfyXD7AI43+EQgbxMhbxL4SVUCbxHIcs9AygBzz0DOAfoC9ACAaM9Az4HPgfhEbxXPCx8hzwsfyfhEbxT7AOIw4wB/+GdJA0AgghAQR8kEu46A4CCCEBjSFwK7joDgIIIQKcSJfrrjAjQsKQL+MPhBbuMA+kGV1NHQ+kDf+kGV1NHQ+kDf1w1/ldTR0NN/39cNf5XU0dDTf9/6QZXU0dD6QN/XDACV1NHQ0gDf1NH4TfpCbxPXC//DACCXMPhN+EnHBd4gjhQw+EzDACCcMPhM+EUgbpIwcN663t/y4GQl+kJvE9cL/8MA8uBvJFAqAvbCAPLgZCYmxwWz8uBv+E36Qm8T1wv/wwCOgI5X+CdvECS88uBuI4IK+vCAcqi1f7zy4G74ACMnyM+FiM4B+gKAac9Az4HPg8jPkP1Z5UYnzxYmzwt/JPpCbxPXC//DAJEkkvgo4s8WI88KACLPFM3JcfsA4l8H2zx/+GcrSQHMggr68ID4J28Q2zyhtX+2CfgnbxAhggr68IByqLV/oLV/vPLgbiBy+wInyM+FiM6Abc9Az4HPg8jPkP1Z5UYozxYnzwt/JfpCbxPXC//DAJElkvhN4s8WJM8KACPPFM3JgQCB+wAwmwIoIIIQGG1zvLrjAiCCEBjSFwK64wIyLQL+MPhBbuMA1w1/ldTR0NN/39cN/5XU0dDT/9/6QZXU0dD6QN/6QZXU0dD6QN/XDACV1NHQ0gDf1NEh+FKxIJww+FD6Qm8T1wv/wADf8uBwJCRtIsjL/3BYgED0Q/hKcViAQPQW+EtyWIBA9BciyMv/c1iAQPRDIXRYgED0Fsj0AFAuA77J+EvIz4SA9AD0AM+BySD5AMjPigBAy//J0DFsIfhJIccF8uBnJPhNxwWzIJUwJfhMvd/y4G/4TfpCbxPXC//DAI6AjoDiJvhOAaC1f/huIiCcMPhQ+kJvE9cL/8MA3jEwLwHIjkP4UMjPhYjOgG3PQM+Bz4PIz5FlBH7m+CjPFvhKzxYozwt/J88L/8gnzxb4Sc8WJs8WyPhOzwt/Jc8Uzc3NyYEAgPsAjhQjyM+FiM6Abc9Az4HPgcmBAID7AOIwXwbbPH/4Z0kBGPgnbxDbPKG1f3L7ApsBPIIK+vCA+CdvENs8obV/tgn4J28QIbzy4G4gcvsCMJsCrDD4QW7jANMf+ERYb3X4ZNH4RHBvcnBvcYBAb3T4ZPhPbrOW+E8gbvJ/jidwjQhgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAEbwLiIcD/UDMB7o4sI9DTAfpAMDHIz4cgzoBgz0DPgc+Bz5Jhtc7yIW8iWCLPC38hzxZsIclw+wCOQPhEIG8TIW8S+ElVAm8RyHLPQMoAc89AzgH6AvQAgGjPQM+Bz4H4RG8VzwsfIW8iWCLPC38hzxZsIcn4RG8U+wDiMOMAf/hnSQIoIIIQDwJYqrrjAiCCEBBHyQS64wI6NQP2MPhBbuMA1w1/ldTR0NN/39cNf5XU0dDTf9/6QZXU0dD6QN/6QZXU0dD6QN/U0fhN+kJvE9cL/8MAIJcw+E34SccF3iCOFDD4TMMAIJww+Ez4RSBukjBw3rre3/LgZCTCAPLgZCT4Trvy4GX4TfpCbxPXC//DACCOgN4gUDk2AmCOHTD4TfpCbxPXC//AACCeMCP4J28QuyCUMCPCAN7e3/LgbvhN+kJvE9cL/8MAjoA4NwHCjlf4ACT4TgGhtX/4biP4Sn/Iz4WAygBzz0DOAfoCgGnPQM+Bz4PIz5C4oiKqJs8Lf/hMzwv/+E3PFiT6Qm8T1wv/wwCRJJL4KOLPFsgkzxYjzxTNzclw+wDiXwXbPH/4Z0kBzIIK+vCA+CdvENs8obV/tgly+wIk+E4BobV/+G74Sn/Iz4WAygBzz0DOgG3PQM+Bz4PIz5C4oiKqJs8Lf/hMzwv/+E3PFiT6Qm8T1wv/wwCRJJL4TeLPFsgkzxYjzxTNzcmBAID7AJsBCjDbPMIAmwMuMPhBbuMA+kGV1NHQ+kDf0ds82zx/+GdQO0kAvPhN+kJvE9cL/8MAIJcw+E34SccF3iCOFDD4TMMAIJww+Ez4RSBukjBw3rre3/LgZPhOwADy4GT4ACDIz4UIzo0DyA+gAAAAAAAAAAAAAAAAAc8Wz4HPgcmBAKD7ADADPiCCCyHRc7uOgOAgghALP89Xu46A4CCCEAwv8g264wJCPz0D/jD4QW7jANcNf5XU0dDTf9/6QZXU0dD6QN/6QZXU0dD6QN/U0fhK+EnHBfLgZiPCAPLgZCP4Trvy4GX4J28Q2zyhtX9y+wIj+E4BobV/+G74Sn/Iz4WAygBzz0DOgG3PQM+Bz4PIz5C4oiKqJc8Lf/hMzwv/+E3PFiTPFsgkzxZQmz4BJCPPFM3NyYEAgPsAXwTbPH/4Z0kCKCCCEAXFAA+64wIgghALP89XuuMCQUACVjD4QW7jANcNf5XU0dDTf9/R+Er4SccF8uBm+AAg+E4BoLV/+G4w2zx/+GdQSQKWMPhBbuMA+kGV1NHQ+kDf0fhN+kJvE9cL/8MAIJcw+E34SccF3iCOFDD4TMMAIJww+Ez4RSBukjBw3rre3/LgZPgAIPhxMNs8f/hnUEkCJCCCCXwzWbrjAiCCCyHRc7rjAkZDA/Aw+EFu4wD6QZXU0dD6QN/XDX+V1NHQ03/f1w1/ldTR0NN/39H4TfpCbxPXC//DACCXMPhN+EnHBd4gjhQw+EzDACCcMPhM+EUgbpIwcN663t/y4GQhwAAgljD4T26zs9/y4Gr4TfpCbxPXC//DAI6AkvgA4vhPbrNQRUQBiI4S+E8gbvJ/bxAiupYgI28C+G/eliAjbwL4b+L4TfpCbxPXC/+OFfhJyM+FiM6Abc9Az4HPgcmBAID7AN5fA9s8f/hnSQEmggr68ID4J28Q2zyhtX+2CXL7ApsC/jD4QW7jANMf+ERYb3X4ZNH4RHBvcnBvcYBAb3T4ZPhLIcD/jiIj0NMB+kAwMcjPhyDOgGDPQM+Bz4HPkgXwzWYhzxTJcPsAjjb4RCBvEyFvEvhJVQJvEchyz0DKAHPPQM4B+gL0AIBoz0DPgc+B+ERvFc8LHyHPFMn4RG8U+wBQRwEO4jDjAH/4Z0kEQCHWHzH4QW7jAPgAINMfMiCCEBjSFwK6joCOgOIwMNs8UExKSQCs+ELIy//4Q88LP/hGzwsAyPhN+FD4UV4gzs7O+Er4S/hM+E74T/hSXmDPEc7My//LfwEgbrOOFcgBbyLIIs8LfyHPFmwhzxcBz4PPEZMwz4HiygDJ7VQBFiCCEC4oiKq6joDeSwEwIdN/M/hOAaC1f/hu+E36Qm8T1wv/joDeTgI8IdN/MyD4TgGgtX/4bvhR+kJvE9cL/8MAjoCOgOIwT00BGPhN+kJvE9cL/46A3k4BUIIK+vCA+CdvENs8obV/tgly+wL4TcjPhYjOgG3PQM+Bz4HJgQCA+wCbAYD4J28Q2zyhtX9y+wL4UcjPhYjOgG3PQM+Bz4PIz5DqFdlC+CjPFvhKzxYizwt/yPhJzxb4Ts8Lf83NyYEAgPsAmwB+7UTQ0//TP9MA1fpA+kD4cfhw+G36QNTT/9N/9AQBIG6V0NN/bwLf+G/XCgD4cvhu+Gz4a/hqf/hh+Gb4Y/hiAf6qTG9yZW0gaXBzdW0gZG9sb3Igc2l0IGFtZXQsIGNvbnNlY3RldHVyIGFkaXBpc2NpbmcgZWxpdC4gTWF1cmlzIGV0IGR1aSBlZ2V0IG5pYmggdGVtcHVzIHB1bHZpbmFyLiBRdWlzcXVlIHVsbGFtY29ycGVyLCBkb2xvciBzUgH+ZWQgdm9sdXRwYXQgdm9sdXRwYXQsIGV4IG1hdXJpcyBwb3N1ZXJlIGV4LCBldSB1bHRyaWNpZXMgZGlhbSBuZXF1ZSBtb2xlc3RpZSBtaS4gTWFlY2VuYXMgYSBibGFuZGl0IG1hc3NhLiBGdXNjZSBhdCB2ZWxpdCB0b3J0b1MB/nIuIExvcmVtIGlwc3VtIGRvbG9yIHNpdCBhbWV0LCBjb25zZWN0ZXR1ciBhZGlwaXNjaW5nIGVsaXQuIEFlbmVhbiBzY2VsZXJpc3F1ZSBkaWN0dW0gcmlzdXMgcXVpcyBoZW5kcmVyaXQuIER1aXMgaW50ZXJkdW0gZWxlaWZUAf5lbmQgZW5pbS4gRnVzY2UgdmVsIGZlbGlzIGNvbW1vZG8sIGZlcm1lbnR1bSB2ZWxpdCBzdXNjaXBpdCwgaW1wZXJkaWV0IGxlby4gTWFlY2VuYXMgdml0YWUgcHVydXMgdml0YWUgbWV0dXMgbWFsZXN1YWRhIGZldWdpYXQuVQH+IFZlc3RpYnVsdW0gYW50ZSBpcHN1bSBwcmltaXMgaW4gZmF1Y2lidXMgb3JjaSBsdWN0dXMgZXQgdWx0cmljZXMgcG9zdWVyZSBjdWJpbGlhIGN1cmFlOyBWZXN0aWJ1bHVtIHNlZCBkaWFtIHZpdGFlIGxhY3VzIG1vbGVzdFYB/mllIHZhcml1cyBldSBhdCBlcmF0LiBOdWxsYSBsdWN0dXMgcGVsbGVudGVzcXVlIG5pYmggdmVsIHNvZGFsZXMuIFZlc3RpYnVsdW0gYW50ZSBvcmNpLCBwbGFjZXJhdCBhYyBydXRydW0gZWdldCwgYmliZW5kdW0gaW4gYW5XAf50ZS4gTWF1cmlzIG1hdHRpcyBtYXNzYSBldCB0b3J0b3IgbW9sbGlzIGZpbmlidXMuqlBoYXNlbGx1cyBiaWJlbmR1bSBsaWd1bGEgdG9ydG9yLCBpZCB2ZWhpY3VsYSBhcmN1IGNvbnNlcXVhdCBlbGVtZW50dW0uIEFsaXF1WAH+YW0gdnVscHV0YXRlIGhlbmRyZXJpdCBhcmN1IGlkIGZldWdpYXQuIEV0aWFtIGNvbW1vZG8gbG9ib3J0aXMgZWdlc3Rhcy4gSW4gYSBudWxsYSB0ZW1wb3IsIGZyaW5naWxsYSBkdWkgZXUsIGxvYm9ydGlzIHVybmEuIE1hZVkB/mNlbmFzIG9ybmFyZSwgbWkgYWMgdml2ZXJyYSBhdWN0b3IsIGV4IHNlbSBzb2RhbGVzIG9yY2ksIHF1aXMgcnV0cnVtIG1pIHZlbGl0IGEgbmliaC4gRHVpcyBpYWN1bGlzLCBzZW0gZ3JhdmlkYSBpbXBlcmRpZXQgY29uZGlaAf5tZW50dW0sIGlwc3VtIGRpYW0gdWxsYW1jb3JwZXIgZHVpLCBhdCBlbGVtZW50dW0gc2VtIHB1cnVzIHNlZCBhcmN1LiBEb25lYyB2ZWwgb3JuYXJlIG1hZ25hLiBDdXJhYml0dXIgY29uc2VjdGV0dXIgbmVjIG1hZ25hIGF0WwH+IHVsdHJpY2VzLiBJbnRlZ2VyIG5lYyBmaW5pYnVzIHRlbGx1cy4gSW50ZWdlciB2aXRhZSBtYXR0aXMgZHVpLCB2aXRhZSB0cmlzdGlxdWUgbWkuIFNlZCBuaWJoIG9yY2ksIGVsZW1lbnR1bSBub24gbmlzaSBhYywgZmFjaVwB/mxpc2lzIHBvc3VlcmUgbG9yZW0uIFV0IHNhcGllbiBmZWxpcywgdWxsYW1jb3JwZXIgaWQgbGFjdXMgZXQsIGF1Y3RvciBkaWduaXNzaW0gZGlhbS4gVml2YW11cyBuZWMgdXJuYSBuZXF1ZS4gTnVuYyBtYXVyaXMgb3JjaSxdAf4gZGljdHVtIG5vbiB1cm5hIHZlbCwgdmFyaXVzIHRpbmNpZHVudCBtaS4gU3VzcGVuZGlzc2UgdWx0cmljaWVzIG51bGxhIG1pLCBpZCBzZW1wZXIgbWFzc2Egc2FnaXR0aXMgdXQuIFNlZCBmYWNpbGlzaXMgdGVsbHVzIHV0XgA2IG1ldHVzIGZlcm1lbnR1bSBpbnRlcmR1bS4gAGOAE1v3A5m4uXTpo3Ifu8Brbi0ngv0nlmNbRglcPELhQ3NAAAAAAAAAAAAAAAAO5rKAEAIQ9KQgiu1T9KBjYQEK9KQg9KFiAAACASBnZAEC/2UC/n+NCGAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAT4aSHbPNMAAY4dgQIA1xgg+QEB0wABlNP/AwGTAvhC4iD4ZfkQ8qiV0wAB8nri0z8Bjh34QyG5IJ8wIPgjgQPoqIIIG3dAoLnekyD4Y+DyNNgw0x8B+CO88rmeZgIW0x8B2zz4R26OgN5qaANu33Ai0NMD+kAw+GmpOAD4RH9vcYIImJaAb3Jtb3Nwb3T4ZI6A4CHHANwh0x8h3QHbPPhHbo6A3qNqaAEGW9s8aQIO+EFu4wDbPKWkBFggghAVAFsHu46A4CCCEDMfUaS7joDgIIIQcj3EzruOgOAgghB/96R8u46A4JKEcGsDPCCCEHJuk3+64wIgghB5hbP0uuMCIIIQf/ekfLrjAm9ubALcMPhBbuMA0x/4RFhvdfhk0fhEcG9ycG9xgEBvdPhk+Ev4TPhN+FD4UfhPbwYhwP+OPSPQ0wH6QDAxyM+HIM6AYM9Az4HPg8jPk//ekfIibyZVBSbPFCXPFCTPCwcjzwv/Is8WIc8Lf2xhzclw+wClbQG0jlH4RCBvEyFvEvhJVQJvEchyz0DKAHPPQM4B+gL0AIBoz0DPgc+DyPhEbxXPCx8ibyZVBSbPFCXPFCTPCwcjzwv/Is8WIc8Lf2xhzcn4RG8U+wDiMOMAf/hnpAFmMNHbPCDA/44l+EvIi9wAAAAAAAAAAAAAAAAgzxbPgc+Bz5PmFs/SIc8UyXD7AN4wf/hnpQFoMNHbPCDA/44m+FLIi9wAAAAAAAAAAAAAAAAgzxbPgc+Bz5PJuk3+Ic8Lf8lw+wDeMH/4Z6UDQiCCEEWzvf27joDgIIIQVbOp+7uOgOAgghByPcTOu46A4IB6cQIoIIIQZiEcb7rjAiCCEHI9xM664wJ0cgL8MPhBbuMA1w1/ldTR0NN/3/pBldTR0PpA39H4UfpCbxPXC//DACCXMPhR+EnHBd4gjhQw+FDDACCcMPhQ+EUgbpIwcN663t/y4GT4ACDIz4WIzo0EDmJaAAAAAAAAAAAAAAAAAAHPFs+Bz4HPkCz/PV4izwt/yXD7ACH4TwGgpXMBFLV/+G9b2zx/+GekAuIw+EFu4wDXDX+V1NHQ03/f1w1/ldTR0NN/39cN/5XU0dDT/9/6QZXU0dD6QN/6QZXU0dD6QN/RjQhgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAE+FH6Qm8T1wv/wwAglzD4UfhJxwXeIKV1AvyOFDD4UMMAIJww+FD4RSBukjBw3rre3/LgZCVwvvLgZCL6Qm8T1wv/wwAglDAjwADeII4SMCL6Qm8T1wv/wAAglDAjwwDe3/LgZ/hR+kJvE9cL/8AAkvgAjoDibSTIy/9wWIBA9EP4KHFYgED0FvhOcliAQPQXJMjL/3NYgEB5dgH09EMjdFiAQPQWyPQAyfhOyM+EgPQA9ADPgcmNCGAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQmwgCONyEg+QD4KPpCbxLIz4ZAygfL/8nQKCHIz4WIzgH6AoBpz0DPg8+DIs8Uz4HPkaLVfP7JcfsAMTF3AZydIfkAyM+KAEDL/8nQMeIgyM+FiM6NBA5iWgAAAAAAAAAAAAAAAAABzxbPgc+Bz5As/z1eKM8Lf8lw+wAn+E8BoLV/+G/4UfpCbxPXC/94AeCOOCP6Qm8T1wv/wwCOFCPIz4WIzoBtz0DPgc+ByYEAgPsAjhX4ScjPhYjOgG3PQM+Bz4HJgQCA+wDi3iBsE1lbbFEhwP+OIiPQ0wH6QDAxyM+HIM6AYM9Az4HPgc+TmIRxviHPFslw+wDeMNs8f/hnpAEg+FL4J28Q2zyhtX+2CXL7ApsCKCCCEFQrFnK64wIgghBVs6n7uuMCfXsD/jD4QW7jANcN/5XU0dDT/9/6QZXU0dD6QN/6QZXU0dD6QN/R+CdvENs8obV/cvsCIiJtIsjL/3BYgED0Q/gocViAQPQW+E5yWIBA9BciyMv/c1iAQPRDIXRYgED0Fsj0AMn4TsjPhID0APQAz4HJIPkAyM+KAEDL/8nQMWwhIcilm3wBWM+FiM6Abc9Az4HPg8jPkEXN5XIizxYlzwv/JM8WzcmBAID7ADBfA9s8f/hnpAP+MPhBbuMA1w1/ldTR0NN/39cN/5XU0dDT/9/6QZXU0dD6QN/6QZXU0dD6QN/RIfpCbxPXC//DACCUMCLAAN4gjhIwIfpCbxPXC//AACCUMCLDAN7f8uBn+CdvENs8obV/cvsCbSPIy/9wWIBA9EP4KHFYgED0FvhOcliAQPQXI6WbfgHeyMv/c1iAQPRDInRYgED0Fsj0AMn4TsjPhID0APQAz4HJIPkAyM+KAEDL/8nQJSHIz4WIzgH6AoBpz0DPg8+DIs8Uz4HPkaLVfP7JcfsAMSH6Qm8T1wv/wwCOFCHIz4WIzoBtz0DPgc+ByYEAgPsAfwGUjhX4ScjPhYjOgG3PQM+Bz4HJgQCA+wDiIDFsQSHA/44iI9DTAfpAMDHIz4cgzoBgz0DPgc+Bz5NQrFnKIc8WyXD7AN4w2zx/+GekAiggghA4KCYauuMCIIIQRbO9/brjAoKBAWYw0ds8IMD/jiX4TMiL3AAAAAAAAAAAAAAAACDPFs+Bz4HPkxbO9/YhzxTJcPsA3jB/+GelA/4w+EFu4wDXDf+V1NHQ0//f+kGV1NHQ+kDf0fhR+kJvE9cL/8MAIJcw+FH4SccF3iCOFDD4UMMAIJww+FD4RSBukjBw3rre3/LgZCHDACCbMCD6Qm8T1wv/wADeII4SMCHAACCbMCD6Qm8T1wv/wwDe3/LgZ/gAIfhwIPhxW9s8paSDAAZ/+GcDQiCCECDrx227joDgIIIQLiiIqruOgOAgghAzH1Gku46A4I6JhQIoIIIQMI1m0brjAiCCEDMfUaS64wKIhgKQMPhBbuMA0x/4RFhvdfhk0fhEcG9ycG9xgEBvdPhk+E8hwP+OIyPQ0wH6QDAxyM+HIM6AYM9Az4HPgc+SzH1GkiHPC3/JcPsApYcBgI43+EQgbxMhbxL4SVUCbxHIcs9AygBzz0DOAfoC9ACAaM9Az4HPgfhEbxXPCx8hzwt/yfhEbxT7AOIw4wB/+GekAWgw0ds8IMD/jib4U8iL3AAAAAAAAAAAAAAAACDPFs+Bz4HPksI1m0YhzwoAyXD7AN4wf/hnpQIoIIIQLalNL7rjAiCCEC4oiKq64wKNigL+MPhBbuMA1w1/ldTR0NN/39cN/5XU0dDT/9/6QZXU0dD6QN/6QZXU0dD6QN/6QZXU0dD6QN/U0fhTs/LgaCQkbSLIy/9wWIBA9EP4KHFYgED0FvhOcliAQPQXIsjL/3NYgED0QyF0WIBA9BbI9ADJ+E7Iz4SA9AD0AM+BySD5AKWLAv7Iz4oAQMv/ydAxbCH4SSHHBfLgZvgnbxDbPKG1f3L7Aib4TwGhtX/4byL6Qm8T1wv/wACOFCPIz4WIzoBtz0DPgc+ByYEAgPsAjjIiyM+FiM6Abc9Az4HPg8jPkPMkQPoozwt/I88UJ88L/ybPFiLPFsgmzxbNzcmBAID7AOIwm4wBDl8G2zx/+GekAegw0x/4RFhvdfhk0XQhwP+OIyPQ0wH6QDAxyM+HIM6AYM9Az4HPgc+StqU0viHPCx/JcPsAjjf4RCBvEyFvEvhJVQJvEchyz0DKAHPPQM4B+gL0AIBoz0DPgc+B+ERvFc8LHyHPCx/J+ERvFPsA4jDjAH/4Z6QCKCCCEB34aKm64wIgghAg68dtuuMCkI8CmjD4QW7jAPpBldTR0PpA39H4UfpCbxPXC//DACCXMPhR+EnHBd7y4GT4UnL7AiDIz4WIzoBtz0DPgc+Bz5A7trPyyYEAgPsAMNs8f/hnpaQD/DD4QW7jANcNf5XU0dDTf9/6QZXU0dD6QN/6QZXU0dD6QN/6QZXU0dD6QN/U0fhR+kJvE9cL/8MAIJcw+FH4SccF3vLgZPgnbxDbPKG1f3L7AiJwJW0iyMv/cFiAQPRD+ChxWIBA9Bb4TnJYgED0FyLIy/9zWIBA9EMhdFiAQKWbkQG+9BbI9ADJ+E7Iz4SA9AD0AM+BySD5AMjPigBAy//J0DFsIST6Qm8T1wv/kiUy3yDIz4WIzoBtz0DPgc+DyM+QML/INijPC38jzxYlzxYkzxTNyYEAgPsAW18F2zx/+GekA0AgggnVPR27joDgIIIQBpoI+LuOgOAgghAVAFsHu46A4JyWkwIoIIIQDVr8crrjAiCCEBUAWwe64wKVlAFoMNHbPCDA/44m+E3Ii9wAAAAAAAAAAAAAAAAgzxbPgc+Bz5JUAWweIc8LB8lw+wDeMH/4Z6UCiDD4QW7jANIA0fhR+kJvE9cL/8MAIJcw+FH4SccF3iCOFDD4UMMAIJww+FD4RSBukjBw3rre3/LgZPgAIPhzMNs8f/hnpaQCJiCCCfUaZrrjAiCCEAaaCPi64wKalwL8MPhBbuMA0x/4RFhvdfhk1w3/ldTR0NP/3/pBldTR0PpA39Eg+kJvE9cL/8MAIJQwIcAA3iCOEjAg+kJvE9cL/8AAIJQwIcMA3t/y4Gf4RHBvcnBvcYBAb3T4ZCEhbSLIy/9wWIBA9EP4KHFYgED0FvhOcliAQPQXIsjL/3NYpZgBqIBA9EMhdFiAQPQWyPQAyfhOyM+EgPQA9ADPgckg+QDIz4oAQMv/ydAxbCFsISHA/44iI9DTAfpAMDHIz4cgzoBgz0DPgc+Bz5IaaCPiIc8WyXD7AJkBfo42+EQgbxMhbxL4SVUCbxHIcs9AygBzz0DOAfoC9ACAaM9Az4HPgfhEbxXPCx8hzxbJ+ERvFPsA4jDjAH/4Z6QDjjD4QW7jANM/+kGV1NHQ+kDf0fgnbxDbPKG1f3L7AiDIz4WIzoBtz0DPgc+Bz5HOG8OiIs8LP/hTzwoAyYEAgPsAW9s8f/hnpZukABhwaKb7YJVopv5gMd8CJCCCCXwzWbrjAiCCCdU9HbrjAqGdAsow+EFu4wD4RvJzcfhm1w3/ldTR0NP/3/pBldTR0PpA39EhwwAgmzAg+kJvE9cL/8AA3iCOEjAhwAAgmzAg+kJvE9cL/8MA3t/y4Gf4ACH4cCD4cXD4b3D4c/gnbxD4clvbPH/4Z56kAYjtRNAg10nCAY430//TP9MA1fpA1wt/+HL4cdP/1NTTB9TTf9P/1woA+HP4cPhv+G74bfhs+Gv4an/4Yfhm+GP4Yo6A4p8B/PQFcSGAQPQOk9cL/5Fw4vhqciGAQPQPksjJ3/hrcyGAQPQPksjJ3/hsdCGAQPQOk9cLB5Fw4vhtdSGAQPQPksjJ3/hucPhvcPhwjQhgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAE+HFw+HJw+HNwAYBA9A7yvaAAHNcL//hicPhjcPhmf/hhAv4w+EFu4wDTH/hEWG91+GTR+ERwb3Jwb3GAQG90+GT4TiHA/44iI9DTAfpAMDHIz4cgzoBgz0DPgc+Bz5IF8M1mIc8UyXD7AI42+EQgbxMhbxL4SVUCbxHIcs9AygBzz0DOAfoC9ACAaM9Az4HPgfhEbxXPCx8hzxTJ+ERvFPsApaIBDuIw4wB/+GekAlYh1h8x+EFu4wD4ACDTHzIgghALP89Xup4h038zIPhPAaG1f/hvMN4wMNs8paQAePhCyMv/+EPPCz/4Rs8LAMj4UfhSAs7Lf/hK+Ev4TPhN+E74T/hQ+FNegM8Ry//MzMsHzMt/y//KAMntVAB07UTQ0//TP9MA1fpA1wt/+HL4cdP/1NTTB9TTf9P/1woA+HP4cPhv+G74bfhs+Gv4an/4Yfhm+GP4Yg==","timings":{"genLt":"16558098000001","genUtime":1626868952},"lastTransactionId":{"isExact":true,"lt":"16554099000005","hash":"a73789af4437ff5a58f33a5b29a347d01b6b99088009437b8e47d73751f51741"}}"#;
@@ -0,0 +1,347 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use nekoton_abi::{CellLayoutError, SliceReader};
+use nekoton_utils::*;
+use serde::{Deserialize, Serialize};
+use ton_block::{Deserializable, MsgAddressInt};
+use ton_types::{HashmapE, HashmapType, UInt256};
+
+use super::WalletType;
+
+/// Unlock schedule of the vesting or lockup wallet.
+///
+/// Both contracts use the wallet v3 external message layout, so they can be
+/// operated as [`WalletType::WalletV3`] family wallets
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "data")]
+pub enum LockupDetails {
+    /// Standard vesting wallet, funds are unlocked by periods after the cliff
+    Vesting(VestingSchedule),
+    /// Lockup wallet, funds are unlocked at the specified times
+    Lockup(LockupSchedule),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VestingSchedule {
+    pub start_time: u64,
+    pub total_duration: u32,
+    pub unlock_period: u32,
+    pub cliff_duration: u32,
+    #[serde(with = "serde_string")]
+    pub total_amount: u128,
+    #[serde(with = "serde_address")]
+    pub sender: MsgAddressInt,
+    #[serde(with = "serde_address")]
+    pub owner: MsgAddressInt,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockupSchedule {
+    /// Amounts which are unlocked at the specified time
+    pub locked: BTreeMap<u32, u128>,
+    /// Amounts which can only be sent to the allowed destinations
+    /// until the specified time
+    pub restricted: BTreeMap<u32, u128>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LockupType {
+    Vesting,
+    Lockup,
+}
+
+/// Code hashes of the vesting and lockup contracts.
+///
+/// Contract data is decoded only for accounts with one of the registered
+/// code hashes, so plain wallets are never mistaken for locked ones
+#[derive(Clone, Debug, Default)]
+pub struct LockupCodeHashes {
+    code_hashes: BTreeMap<UInt256, LockupType>,
+}
+
+impl LockupCodeHashes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, code_hash: UInt256, lockup_type: LockupType) -> &mut Self {
+        self.code_hashes.insert(code_hash, lockup_type);
+        self
+    }
+
+    pub fn guess_lockup_type(&self, code_hash: &UInt256) -> Option<LockupType> {
+        self.code_hashes.get(code_hash).copied()
+    }
+}
+
+impl LockupDetails {
+    /// Decodes the unlock schedule from the contract data.
+    ///
+    /// Returns `None` for wallets without lockup
+    pub fn from_account(
+        code_hashes: &LockupCodeHashes,
+        wallet_type: WalletType,
+        account: &ton_block::AccountStuff,
+    ) -> Option<Self> {
+        if !matches!(
+            wallet_type,
+            WalletType::WalletV3 | WalletType::WalletV3R1 | WalletType::WalletV3R2
+        ) {
+            return None;
+        }
+
+        let state_init = match &account.storage.state {
+            ton_block::AccountState::AccountActive { state_init } => state_init,
+            _ => return None,
+        };
+
+        let code_hash = state_init.code.as_ref()?.repr_hash();
+        let parser = match code_hashes.guess_lockup_type(&code_hash)? {
+            LockupType::Vesting => parse_vesting_data,
+            LockupType::Lockup => parse_lockup_data,
+        };
+
+        let data = state_init.data.as_ref()?;
+        parser(&mut SliceReader::from_cell(data).ok()?).ok()
+    }
+
+    /// Amount which is still locked at the specified time
+    pub fn locked_amount(&self, now: u64) -> u128 {
+        match self {
+            Self::Vesting(schedule) => schedule.locked_amount(now),
+            Self::Lockup(schedule) => schedule.locked_amount(now),
+        }
+    }
+
+    /// Part of the balance which can be spent right now
+    pub fn available_balance(&self, balance: u128, clock: &dyn Clock) -> u128 {
+        balance.saturating_sub(self.locked_amount(clock.now_sec_u64()))
+    }
+}
+
+impl VestingSchedule {
+    pub fn locked_amount(&self, now: u64) -> u128 {
+        let elapsed = match now.checked_sub(self.start_time) {
+            Some(elapsed) => elapsed,
+            None => return self.total_amount,
+        };
+
+        if elapsed >= self.total_duration as u64 {
+            0
+        } else if elapsed < self.cliff_duration as u64 {
+            self.total_amount
+        } else {
+            let unlock_period = self.unlock_period as u64;
+            let periods_passed = (elapsed / unlock_period) as u128;
+            let total_periods = (self.total_duration as u64 / unlock_period) as u128;
+
+            // NOTE: `total_amount * periods_passed` may not fit into u128,
+            // so the quotient and the remainder are scaled separately
+            let unlocked = self.total_amount / total_periods * periods_passed
+                + self.total_amount % total_periods * periods_passed / total_periods;
+            self.total_amount - unlocked
+        }
+    }
+}
+
+impl LockupSchedule {
+    pub fn locked_amount(&self, now: u64) -> u128 {
+        self.locked
+            .iter()
+            .chain(self.restricted.iter())
+            .filter(|(unlock_at, _)| **unlock_at as u64 > now)
+            .map(|(_, amount)| *amount)
+            .sum()
+    }
+}
+
+fn parse_vesting_data(reader: &mut SliceReader) -> Result<LockupDetails, CellLayoutError> {
+    reader.read_u32("seqno")?;
+    reader.read_u32("subwallet_id")?;
+    reader.read_u256("public_key")?;
+    reader.read_maybe_ref("whitelist")?;
+
+    let schedule = reader.read_ref_with("vesting_params", |params| {
+        Ok(VestingSchedule {
+            start_time: params.read_u64("vesting_start_time")?,
+            total_duration: params.read_u32("vesting_total_duration")?,
+            unlock_period: params.read_u32("unlock_period")?,
+            cliff_duration: params.read_u32("cliff_duration")?,
+            total_amount: params.read_grams("vesting_total_amount")?,
+            sender: params.read_address("vesting_sender_address")?,
+            owner: params.read_address("owner_address")?,
+        })
+    })?;
+    reader.finish()?;
+
+    if schedule.unlock_period == 0 || schedule.total_duration < schedule.unlock_period {
+        return Err(CellLayoutError::InvalidValue {
+            field: "vesting_params.unlock_period".to_owned(),
+            offset: 0,
+            reason: "invalid vesting period".to_owned(),
+        });
+    }
+
+    Ok(LockupDetails::Vesting(schedule))
+}
+
+fn parse_lockup_data(reader: &mut SliceReader) -> Result<LockupDetails, CellLayoutError> {
+    reader.read_u32("seqno")?;
+    reader.read_u32("subwallet_id")?;
+    reader.read_u256("public_key")?;
+    reader.read_u256("config_public_key")?;
+    reader.read_maybe_ref("allowed_destinations")?;
+    reader.read_grams("total_locked_value")?;
+    let locked = reader.read_dict("locked", 32)?;
+    reader.read_grams("total_restricted_value")?;
+    let restricted = reader.read_dict("restricted", 32)?;
+    reader.finish()?;
+
+    Ok(LockupDetails::Lockup(LockupSchedule {
+        locked: read_unlock_times("locked", &locked)?,
+        restricted: read_unlock_times("restricted", &restricted)?,
+    }))
+}
+
+fn read_unlock_times(field: &str, dict: &HashmapE) -> Result<BTreeMap<u32, u128>, CellLayoutError> {
+    let mut result = BTreeMap::new();
+    dict.iterate_slices(|mut key, mut value| {
+        let unlock_at = key.get_next_u32()?;
+        let amount = ton_block::Grams::construct_from(&mut value)?;
+        result.insert(unlock_at, amount.as_u128());
+        Ok(true)
+    })
+    .map_err(|e| CellLayoutError::InvalidValue {
+        field: field.to_owned(),
+        offset: 0,
+        reason: e.to_string(),
+    })?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use nekoton_abi::CellWriter;
+
+    use super::*;
+
+    fn make_code(tag: u32) -> ton_types::Cell {
+        let mut writer = CellWriter::new();
+        writer.write_u32("tag", tag).unwrap();
+        writer.build().unwrap()
+    }
+
+    fn make_account(code: ton_types::Cell, data: ton_types::Cell) -> ton_block::AccountStuff {
+        ton_block::AccountStuff {
+            storage: ton_block::AccountStorage {
+                state: ton_block::AccountState::AccountActive {
+                    state_init: ton_block::StateInit {
+                        code: Some(code),
+                        data: Some(data),
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn vesting_schedule() {
+        let owner = MsgAddressInt::from_str(
+            "0:9a3ad35dc2b5f3ab4c2a7de1b0d1e26d3b0b10f6d2a86e6bb0fd0a89a8da5a0c",
+        )
+        .unwrap();
+
+        let mut writer = CellWriter::new();
+        writer
+            .write_u32("seqno", 0)
+            .unwrap()
+            .write_u32("subwallet_id", 698983191)
+            .unwrap()
+            .write_u256("public_key", &Default::default())
+            .unwrap()
+            .write_maybe_ref("whitelist", None)
+            .unwrap()
+            .write_ref_with("vesting_params", |params| {
+                params
+                    .write_u64("vesting_start_time", 1000)?
+                    .write_u32("vesting_total_duration", 400)?
+                    .write_u32("unlock_period", 100)?
+                    .write_u32("cliff_duration", 200)?
+                    .write_grams("vesting_total_amount", 4000)?
+                    .write_address("vesting_sender_address", &owner)?
+                    .write_address("owner_address", &owner)?;
+                Ok(())
+            })
+            .unwrap();
+        let vesting_code = make_code(1);
+        let account = make_account(vesting_code.clone(), writer.build().unwrap());
+
+        let mut code_hashes = LockupCodeHashes::new();
+        assert!(
+            LockupDetails::from_account(&code_hashes, WalletType::WalletV3, &account).is_none()
+        );
+
+        code_hashes.register(vesting_code.repr_hash(), LockupType::Vesting);
+        assert!(
+            LockupDetails::from_account(&code_hashes, WalletType::EverWallet, &account).is_none()
+        );
+
+        let details =
+            LockupDetails::from_account(&code_hashes, WalletType::WalletV3, &account).unwrap();
+        assert_eq!(details.locked_amount(900), 4000);
+        assert_eq!(details.locked_amount(1150), 4000);
+        assert_eq!(details.locked_amount(1200), 2000);
+        assert_eq!(details.locked_amount(1399), 1000);
+        assert_eq!(details.locked_amount(1400), 0);
+
+        let clock = ConstClock::from_secs(1250);
+        assert_eq!(details.available_balance(5000, &clock), 3000);
+        assert_eq!(details.available_balance(1000, &clock), 0);
+    }
+
+    #[test]
+    fn plain_wallet_has_no_lockup() {
+        let mut writer = CellWriter::new();
+        writer
+            .write_u32("seqno", 0)
+            .unwrap()
+            .write_u32("subwallet_id", 0)
+            .unwrap()
+            .write_u256("public_key", &Default::default())
+            .unwrap();
+        let code = make_code(1);
+        let account = make_account(code.clone(), writer.build().unwrap());
+
+        let mut code_hashes = LockupCodeHashes::new();
+        code_hashes.register(code.repr_hash(), LockupType::Vesting);
+        assert!(
+            LockupDetails::from_account(&code_hashes, WalletType::WalletV3, &account).is_none()
+        );
+    }
+
+    #[test]
+    fn vesting_schedule_large_amount() {
+        let schedule = VestingSchedule {
+            start_time: 0,
+            total_duration: 400,
+            unlock_period: 100,
+            cliff_duration: 0,
+            total_amount: u128::MAX - 1,
+            sender: Default::default(),
+            owner: Default::default(),
+        };
+
+        assert_eq!(schedule.locked_amount(0), u128::MAX - 1);
+        assert_eq!(schedule.locked_amount(100), (3 << 126) - 1);
+        assert_eq!(schedule.locked_amount(399), 1 << 126);
+        assert_eq!(schedule.locked_amount(400), 0);
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use ed25519_dalek::PublicKey;
+use nekoton_abi::*;
+use ton_types::{BuilderData, SliceData};
+
+use crate::crypto::{EncryptedData, EncryptionAlgorithm};
+
+/// Opcode of the message with an encrypted comment in the nekoton format.
+///
+/// NOTE: differs from the TON encrypted comment opcode (`0x2167da4b`),
+/// because the layout is not compatible with it
+pub const ENCRYPTED_COMMENT_OPCODE: u32 = 0x6e6b6563;
+
+/// Body of the outgoing message
+#[derive(Clone, Debug)]
+pub enum MessageBody {
+    /// ABI-encoded internal function call
+    Call {
+        function: ton_abi::Function,
+        tokens: Vec<ton_abi::Token>,
+    },
+    /// Plain text comment in the snake format
+    Comment(String),
+    /// Comment, encrypted for the recipient
+    EncryptedComment(EncryptedData),
+    /// Prebuilt body
+    Raw(SliceData),
+}
+
+impl MessageBody {
+    pub fn build(self) -> Result<SliceData> {
+        match self {
+            Self::Call { function, tokens } => function
+                .encode_internal_input(&tokens)
+                .and_then(SliceData::load_builder),
+            Self::Comment(comment) => create_snake_comment_payload(&comment),
+            Self::EncryptedComment(data) => create_encrypted_comment_payload(&data),
+            Self::Raw(body) => Ok(body),
+        }
+    }
+}
+
+/// Encodes the encrypted comment.
+///
+/// Layout: opcode, algorithm (8 bits), source and recipient public keys,
+/// nonce and encrypted data. Everything after the opcode is stored in the snake format
+pub fn create_encrypted_comment_payload(data: &EncryptedData) -> Result<SliceData> {
+    let mut root = BuilderData::new();
+    root.append_u32(ENCRYPTED_COMMENT_OPCODE)?;
+
+    let mut bytes = Vec::with_capacity(1 + 32 + 32 + data.nonce.len() + data.data.len());
+    bytes.push(match data.algorithm {
+        EncryptionAlgorithm::ChaCha20Poly1305 => 0,
+        EncryptionAlgorithm::XSalsa20Poly1305 => 1,
+    });
+    bytes.extend_from_slice(data.source_public_key.as_bytes());
+    bytes.extend_from_slice(data.recipient_public_key.as_bytes());
+    bytes.extend_from_slice(&data.nonce);
+    bytes.extend_from_slice(&data.data);
+
+    create_snake_payload(root, &bytes)
+}
+
+/// Parses the encrypted comment, created with [`create_encrypted_comment_payload`]
+pub fn parse_encrypted_comment_payload(mut payload: SliceData) -> Option<EncryptedData> {
+    if payload.get_next_u32().ok()? != ENCRYPTED_COMMENT_OPCODE {
+        return None;
+    }
+
+    let bytes = read_snake_bytes(&payload)?;
+    let (&algorithm, bytes) = bytes.split_first()?;
+    let (algorithm, nonce_len) = match algorithm {
+        0 => (EncryptionAlgorithm::ChaCha20Poly1305, 12),
+        1 => (EncryptionAlgorithm::XSalsa20Poly1305, 24),
+        _ => return None,
+    };
+
+    if bytes.len() < 64 + nonce_len {
+        return None;
+    }
+    let (source_public_key, bytes) = bytes.split_at(32);
+    let (recipient_public_key, bytes) = bytes.split_at(32);
+    let (nonce, data) = bytes.split_at(nonce_len);
+
+    Some(EncryptedData {
+        algorithm,
+        source_public_key: PublicKey::from_bytes(source_public_key).ok()?,
+        recipient_public_key: PublicKey::from_bytes(recipient_public_key).ok()?,
+        data: data.to_vec(),
+        nonce: nonce.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ton_wallet::{IntoGift, Transfer};
+
+    #[test]
+    fn transfer_body() {
+        let gift = Transfer {
+            flags: 3,
+            bounce: false,
+            destination: Default::default(),
+            amount: 1_000_000_000,
+            body: Some(MessageBody::Comment("hello".to_owned())),
+            state_init: None,
+        }
+        .into_gift()
+        .unwrap();
+
+        assert_eq!(parse_comment_payload(gift.body.unwrap()).unwrap(), "hello");
+    }
+
+    #[test]
+    fn encrypted_comment() {
+        let recipient = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let recipient = PublicKey::from(&recipient);
+
+        let text = "very secret comment ".repeat(20);
+        let data = crate::crypto::encrypt_with_ephemeral_key(
+            text.as_bytes(),
+            &recipient,
+            EncryptionAlgorithm::ChaCha20Poly1305,
+        )
+        .unwrap();
+
+        let body = MessageBody::EncryptedComment(data.clone()).build().unwrap();
+        assert!(parse_comment_payload(body.clone()).is_none());
+        assert_ne!(body.clone().get_next_u32().unwrap(), 0x2167da4b);
+
+        let parsed = parse_encrypted_comment_payload(body).unwrap();
+        assert_eq!(parsed.algorithm, data.algorithm);
+        assert_eq!(parsed.source_public_key, data.source_public_key);
+        assert_eq!(parsed.recipient_public_key, recipient);
+        assert_eq!(parsed.nonce, data.nonce);
+        assert_eq!(parsed.data, data.data);
+    }
+}
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::num::NonZeroU8;
 use std::str::FromStr;
@@ -13,22 +14,32 @@ use ton_types::{BuilderData, SliceData, UInt256};
 use nekoton_abi::*;
 use nekoton_utils::*;
 
+pub use self::lockup::{LockupCodeHashes, LockupDetails, LockupType};
+pub use self::message_body::MessageBody;
 pub use self::multisig::MultisigType;
+use super::abi_registry::{AbiDecoder, AbiRegistry, DecodedAbiTransaction};
+use super::contract_subscription::TransactionsStream;
 use super::models::{
-    ContractState, Expiration, MessageFlags, MultisigPendingTransaction, MultisigPendingUpdate,
-    PendingTransaction, Transaction, TransactionAdditionalInfo, TransactionWithData,
-    TransactionsBatchInfo,
+    BackfillState, BackfillStopCondition, BalanceChange, ContractState, ContractUpgrade,
+    Expiration, MessageFlags, MultisigPendingTransaction, MultisigPendingUpdate,
+    NetworkCapabilities, PendingTransaction, Transaction, TransactionAdditionalInfo,
+    TransactionWithData, TransactionsBatchInfo,
 };
-use super::{ContractSubscription, PollingMethod};
+use super::state_diff::{ContractStateDiff, StateDiffTracker};
+use super::{ContractSubscription, ContractSubscriptionSnapshot, PollingMethod};
+use crate::core::accounts_storage::{AssetsList, TonWalletAsset};
+use crate::core::keystore::KeyStore;
 use crate::core::parsing::*;
 use crate::core::InternalMessage;
-use crate::crypto::UnsignedMessage;
+use crate::crypto::{DerivedKeyGetPublicKeys, DerivedKeySigner, SignatureId, UnsignedMessage};
 use crate::models::ExpireAt;
 use crate::transport::models::{ExistingContract, RawContractState, RawTransaction};
 use crate::transport::Transport;
 
 pub mod ever_wallet;
 pub mod highload_wallet_v2;
+pub mod lockup;
+pub mod message_body;
 pub mod multisig;
 pub mod wallet_v3;
 pub mod wallet_v3v4;
@@ -36,6 +47,9 @@ pub mod wallet_v5r1;
 
 pub const DEFAULT_WORKCHAIN: i8 = 0;
 
+/// How long before the expiration a pending multisig transaction is considered expiring
+pub const PENDING_TRANSACTION_EXPIRING_THRESHOLD: u32 = 600;
+
 pub struct TonWallet {
     clock: Arc<dyn Clock>,
     public_key: PublicKey,
@@ -43,6 +57,8 @@ pub struct TonWallet {
     contract_subscription: ContractSubscription,
     handler: Arc<dyn TonWalletSubscriptionHandler>,
     wallet_data: WalletData,
+    watch_only: bool,
+    abi_registry: Option<Arc<AbiRegistry>>,
 }
 
 impl TonWallet {
@@ -72,6 +88,7 @@ impl TonWallet {
             Some(&mut make_transactions_handler(
                 handler.as_ref(),
                 wallet_type,
+                None,
             )),
         )
         .await?;
@@ -83,6 +100,8 @@ impl TonWallet {
             contract_subscription,
             handler,
             wallet_data,
+            watch_only: false,
+            abi_registry: None,
         })
     }
 
@@ -115,6 +134,7 @@ impl TonWallet {
             Some(&mut make_transactions_handler(
                 handler.as_ref(),
                 wallet_type,
+                None,
             )),
         )
         .await?;
@@ -126,6 +146,8 @@ impl TonWallet {
             contract_subscription,
             handler,
             wallet_data,
+            watch_only: false,
+            abi_registry: None,
         })
     }
 
@@ -151,6 +173,7 @@ impl TonWallet {
             Some(&mut make_transactions_handler(
                 handler.as_ref(),
                 existing_wallet.wallet_type,
+                None,
             )),
         )
         .await?;
@@ -162,9 +185,102 @@ impl TonWallet {
             contract_subscription,
             handler,
             wallet_data,
+            watch_only: false,
+            abi_registry: None,
         })
     }
 
+    /// Subscribes to the wallet of the stored account, keeping its watch-only flag
+    pub async fn subscribe_by_assets(
+        clock: Arc<dyn Clock>,
+        transport: Arc<dyn Transport>,
+        assets: &AssetsList,
+        handler: Arc<dyn TonWalletSubscriptionHandler>,
+    ) -> Result<Self> {
+        let TonWalletAsset {
+            address,
+            public_key,
+            contract: wallet_type,
+        } = assets.ton_wallet.clone();
+
+        let mut wallet_data = WalletData::default();
+
+        let contract_subscription = ContractSubscription::subscribe(
+            clock.clone(),
+            transport,
+            address,
+            &mut make_contract_state_handler(
+                clock.as_ref(),
+                handler.as_ref(),
+                &public_key,
+                wallet_type,
+                &mut wallet_data,
+            ),
+            Some(&mut make_transactions_handler(
+                handler.as_ref(),
+                wallet_type,
+                None,
+            )),
+        )
+        .await?;
+
+        Ok(Self {
+            clock,
+            public_key,
+            wallet_type,
+            contract_subscription,
+            handler,
+            wallet_data,
+            watch_only: assets.watch_only,
+            abi_registry: None,
+        })
+    }
+
+    /// Restores the wallet from the snapshot without reloading the transactions history
+    pub async fn restore(
+        clock: Arc<dyn Clock>,
+        transport: Arc<dyn Transport>,
+        snapshot: TonWalletSnapshot,
+        handler: Arc<dyn TonWalletSubscriptionHandler>,
+    ) -> Result<Self> {
+        let mut wallet_data = WalletData::default();
+
+        let contract_subscription = ContractSubscription::restore(
+            clock.clone(),
+            transport,
+            snapshot.subscription,
+            &mut make_contract_state_handler(
+                clock.as_ref(),
+                handler.as_ref(),
+                &snapshot.public_key,
+                snapshot.wallet_type,
+                &mut wallet_data,
+            ),
+        )
+        .await?;
+
+        Ok(Self {
+            clock,
+            public_key: snapshot.public_key,
+            wallet_type: snapshot.wallet_type,
+            contract_subscription,
+            handler,
+            wallet_data,
+            watch_only: snapshot.watch_only,
+            abi_registry: None,
+        })
+    }
+
+    /// Returns the state, required to restore the wallet later
+    pub fn snapshot(&self) -> TonWalletSnapshot {
+        TonWalletSnapshot {
+            public_key: self.public_key,
+            wallet_type: self.wallet_type,
+            watch_only: self.watch_only,
+            subscription: self.contract_subscription.snapshot(),
+        }
+    }
+
     pub fn make_state_init(&self) -> Result<ton_block::StateInit> {
         match self.wallet_type {
             WalletType::Multisig(multisig_type) => Ok(multisig::prepare_state_init(
@@ -212,6 +328,35 @@ impl TonWallet {
         self.wallet_type
     }
 
+    /// Whether the wallet has no keystore entry and can't prepare messages
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only
+    }
+
+    pub fn set_watch_only(&mut self, watch_only: bool) {
+        self.watch_only = watch_only;
+    }
+
+    /// Sets the registry which is used to decode transactions in
+    /// [`TonWalletSubscriptionHandler::on_transactions_decoded`]
+    pub fn set_abi_registry(&mut self, abi_registry: Option<Arc<AbiRegistry>>) {
+        self.abi_registry = abi_registry;
+    }
+
+    /// Decodes messages of the transaction with the ABI registry
+    pub fn decode_transaction(&self, transaction: &Transaction) -> Option<DecodedAbiTransaction> {
+        self.abi_decoder()
+            .map(|decoder| decoder.decode(transaction))
+    }
+
+    fn abi_decoder(&self) -> Option<AbiDecoder> {
+        Some(AbiDecoder {
+            registry: self.abi_registry.clone()?,
+            address: self.address().clone(),
+            code_hash: self.contract_state().code_hash,
+        })
+    }
+
     pub fn contract_state(&self) -> &ContractState {
         self.contract_subscription.contract_state()
     }
@@ -224,6 +369,21 @@ impl TonWallet {
         self.contract_subscription.polling_method()
     }
 
+    /// Software capabilities of the network.
+    ///
+    /// NOTE: `prepare_*` builders don't depend on them and stay synchronous.
+    /// The signature id only affects signing, so it must be passed to the signer
+    /// (see [`TonWallet::get_signature_id`]). Bounce flags are always explicit,
+    /// because no capability defines the default one
+    pub async fn get_capabilities(&self) -> Result<NetworkCapabilities> {
+        self.contract_subscription.get_capabilities().await
+    }
+
+    /// Signature id which must be used to sign messages for this wallet
+    pub async fn get_signature_id(&self) -> Result<Option<SignatureId>> {
+        self.contract_subscription.get_signature_id().await
+    }
+
     pub fn details(&self) -> TonWalletDetails {
         self.wallet_data
             .details
@@ -242,7 +402,30 @@ impl TonWallet {
         &self.wallet_data.custodians
     }
 
+    /// Sets code hashes of the vesting and lockup contracts.
+    ///
+    /// The unlock schedule is refreshed on the next contract state update
+    pub fn set_lockup_code_hashes(&mut self, code_hashes: Arc<LockupCodeHashes>) {
+        self.wallet_data.lockup_code_hashes = code_hashes;
+    }
+
+    /// Unlock schedule for vesting and lockup wallets
+    pub fn lockup_details(&self) -> Option<&LockupDetails> {
+        self.wallet_data.lockup.as_ref()
+    }
+
+    /// Part of the balance which can be spent right now
+    pub fn available_balance(&self) -> u128 {
+        let balance = self.contract_state().balance;
+        match &self.wallet_data.lockup {
+            Some(lockup) => lockup.available_balance(balance, self.clock.as_ref()),
+            None => balance,
+        }
+    }
+
     pub fn prepare_deploy(&self, expiration: Expiration) -> Result<Box<dyn UnsignedMessage>> {
+        self.ensure_not_watch_only()?;
+
         match self.wallet_type {
             WalletType::Multisig(multisig_type) => multisig::prepare_deploy(
                 self.clock.as_ref(),
@@ -314,6 +497,8 @@ impl TonWallet {
         req_confirms: u8,
         expiration_time: Option<u32>,
     ) -> Result<Box<dyn UnsignedMessage>> {
+        self.ensure_not_watch_only()?;
+
         match self.wallet_type {
             WalletType::Multisig(multisig_type) => multisig::prepare_deploy(
                 self.clock.as_ref(),
@@ -382,19 +567,33 @@ impl TonWallet {
         Ok(init_data.seqno)
     }
 
-    pub fn prepare_transfer(
+    /// Prepares the transfer with either prebuilt [`Gift`]s or [`Transfer`]s,
+    /// which bodies are built here
+    pub fn prepare_transfer<G>(
         &mut self,
         current_state: &ton_block::AccountStuff,
         public_key: &PublicKey,
-        gifts: Vec<Gift>,
+        gifts: Vec<G>,
         expiration: Expiration,
-    ) -> Result<TransferAction> {
+    ) -> Result<TransferAction>
+    where
+        G: IntoGift,
+    {
+        let gifts = gifts
+            .into_iter()
+            .map(IntoGift::into_gift)
+            .collect::<Result<Vec<_>>>()?;
+
+        self.ensure_not_watch_only()?;
+        self.ensure_actual_state(current_state)?;
+        self.ensure_enough_balance(current_state, &gifts)?;
+        self.ensure_unlocked(current_state, &gifts)?;
+
         match self.wallet_type {
             WalletType::Multisig(multisig_type) => {
-                anyhow::ensure!(
-                    gifts.len() == 1,
-                    "Multiple outgoing messages are not supported by multisig contract"
-                );
+                if gifts.len() != 1 {
+                    return Err(TonWalletError::TooManyMessages { max: 1 }.into());
+                }
                 let gift = gifts.into_iter().next().unwrap();
 
                 match &current_state.storage.state {
@@ -416,7 +615,12 @@ impl TonWallet {
                 )?;
 
                 let has_multiple_owners = match &self.wallet_data.custodians {
-                    Some(custodians) => custodians.len() > 1,
+                    Some(custodians) => {
+                        if !is_custodian(custodians, public_key) {
+                            return Err(TonWalletError::InvalidCustodian.into());
+                        }
+                        custodians.len() > 1
+                    }
                     None => return Err(TonWalletError::CustodiansNotFound.into()),
                 };
 
@@ -507,8 +711,12 @@ impl TonWallet {
         transaction_id: u64,
         expiration: Expiration,
     ) -> Result<Box<dyn UnsignedMessage>> {
+        self.ensure_not_watch_only()?;
+
         match self.wallet_type {
             WalletType::Multisig(multisig_type) => {
+                self.ensure_custodian(current_state, multisig_type, public_key)?;
+
                 let has_pending_transaction = multisig::find_pending_transaction(
                     self.clock.as_ref(),
                     multisig_type,
@@ -539,6 +747,8 @@ impl TonWallet {
         new_code_hash: &[u8; 32],
         expiration: Expiration,
     ) -> Result<Box<dyn UnsignedMessage>> {
+        self.ensure_not_watch_only()?;
+
         match self.wallet_type {
             WalletType::Multisig(multisig_type) if multisig_type.is_multisig2() => {
                 multisig::prepare_code_update(
@@ -554,6 +764,47 @@ impl TonWallet {
         }
     }
 
+    /// Prepares `submitUpdate` which replaces the custodians set.
+    ///
+    /// The new set is validated against the contract params from the current state
+    pub fn prepare_custodians_update(
+        &self,
+        current_state: &ton_block::AccountStuff,
+        public_key: &PublicKey,
+        owners: &[PublicKey],
+        req_confirms: u8,
+        expiration: Expiration,
+    ) -> Result<Box<dyn UnsignedMessage>> {
+        self.ensure_not_watch_only()?;
+
+        match self.wallet_type {
+            WalletType::Multisig(multisig_type) if multisig_type.is_multisig2() => {
+                self.ensure_custodian(current_state, multisig_type, public_key)?;
+
+                let params = multisig::get_params(
+                    self.clock.as_ref(),
+                    multisig_type,
+                    Cow::Borrowed(current_state),
+                )?;
+                multisig::validate_custodians(owners, req_confirms, params.max_custodian_count)?;
+
+                multisig::prepare_update(
+                    self.clock.as_ref(),
+                    multisig_type,
+                    public_key,
+                    self.address().clone(),
+                    multisig::UpdateParams {
+                        owners: Some(owners),
+                        req_confirms: Some(req_confirms),
+                        ..Default::default()
+                    },
+                    expiration,
+                )
+            }
+            _ => Err(TonWalletError::UpdateNotSupported.into()),
+        }
+    }
+
     pub fn prepare_confirm_update(
         &self,
         current_state: &ton_block::AccountStuff,
@@ -561,8 +812,12 @@ impl TonWallet {
         update_id: u64,
         expiration: Expiration,
     ) -> Result<Box<dyn UnsignedMessage>> {
+        self.ensure_not_watch_only()?;
+
         match self.wallet_type {
             WalletType::Multisig(multisig_type) => {
+                self.ensure_custodian(current_state, multisig_type, public_key)?;
+
                 let pending_update = multisig::find_pending_update(
                     self.clock.as_ref(),
                     multisig_type,
@@ -594,8 +849,32 @@ impl TonWallet {
         new_code: ton_types::Cell,
         expiration: Expiration,
     ) -> Result<Box<dyn UnsignedMessage>> {
+        self.prepare_execute_update(
+            current_state,
+            public_key,
+            update_id,
+            Some(new_code),
+            expiration,
+        )
+    }
+
+    /// Prepares `executeUpdate` for the confirmed pending update.
+    ///
+    /// `new_code` must be provided only if the update changes the contract code
+    pub fn prepare_execute_update(
+        &self,
+        current_state: &ton_block::AccountStuff,
+        public_key: &PublicKey,
+        update_id: u64,
+        new_code: Option<ton_types::Cell>,
+        expiration: Expiration,
+    ) -> Result<Box<dyn UnsignedMessage>> {
+        self.ensure_not_watch_only()?;
+
         match self.wallet_type {
             WalletType::Multisig(multisig_type) => {
+                self.ensure_custodian(current_state, multisig_type, public_key)?;
+
                 let update = match multisig::find_pending_update(
                     self.clock.as_ref(),
                     multisig_type,
@@ -606,7 +885,12 @@ impl TonWallet {
                     None => return Err(TonWalletError::PendingUpdateNotFound.into()),
                 };
 
-                if !matches!(update.new_code_hash, Some(hash) if new_code.repr_hash() == hash) {
+                let code_matches = match (&update.new_code_hash, &new_code) {
+                    (Some(hash), Some(code)) => code.repr_hash() == *hash,
+                    (None, None) => true,
+                    _ => false,
+                };
+                if !code_matches {
                     return Err(TonWalletError::UpdatedDataMismatch.into());
                 }
 
@@ -616,7 +900,7 @@ impl TonWallet {
                     public_key,
                     self.address().clone(),
                     update_id,
-                    Some(new_code),
+                    new_code,
                     expiration,
                 )
             }
@@ -624,6 +908,113 @@ impl TonWallet {
         }
     }
 
+    fn ensure_not_watch_only(&self) -> Result<()> {
+        if self.watch_only {
+            return Err(TonWalletError::WatchOnlyAccount.into());
+        }
+        Ok(())
+    }
+
+    /// Checks that the provided state is not older than the latest known one
+    fn ensure_actual_state(&self, current_state: &ton_block::AccountStuff) -> Result<()> {
+        let latest_lt = self.contract_state().last_lt;
+        if current_state.storage.last_trans_lt < latest_lt {
+            return Err(TonWalletError::ExpiredState.into());
+        }
+        Ok(())
+    }
+
+    /// Checks that the wallet is deployed and the key is one of its custodians
+    fn ensure_custodian(
+        &self,
+        current_state: &ton_block::AccountStuff,
+        multisig_type: MultisigType,
+        public_key: &PublicKey,
+    ) -> Result<()> {
+        self.ensure_actual_state(current_state)?;
+        match &current_state.storage.state {
+            ton_block::AccountState::AccountActive { .. } => {}
+            ton_block::AccountState::AccountFrozen { .. } => {
+                return Err(TonWalletError::AccountIsFrozen.into())
+            }
+            ton_block::AccountState::AccountUninit => {
+                return Err(TonWalletError::AccountNotDeployed.into())
+            }
+        }
+
+        let custodians = multisig::get_custodians(
+            self.clock.as_ref(),
+            multisig_type,
+            Cow::Borrowed(current_state),
+        )?;
+        if !is_custodian(&custodians, public_key) {
+            return Err(TonWalletError::InvalidCustodian.into());
+        }
+        Ok(())
+    }
+
+    fn ensure_enough_balance(
+        &self,
+        current_state: &ton_block::AccountStuff,
+        gifts: &[Gift],
+    ) -> Result<()> {
+        // NOTE: messages with the `AllBalance` flag never exceed the balance
+        let available = current_state.storage.balance.grams.as_u128();
+        let required = gifts
+            .iter()
+            .filter(|gift| gift.flags & u8::from(MessageFlags::AllBalance) == 0)
+            .fold(0u128, |total, gift| total.saturating_add(gift.amount));
+
+        if required > available {
+            return Err(TonWalletError::InsufficientBalance {
+                available,
+                required,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn ensure_unlocked(
+        &self,
+        current_state: &ton_block::AccountStuff,
+        gifts: &[Gift],
+    ) -> Result<()> {
+        let lockup = match LockupDetails::from_account(
+            &self.wallet_data.lockup_code_hashes,
+            self.wallet_type,
+            current_state,
+        ) {
+            Some(lockup) => lockup,
+            None => return Ok(()),
+        };
+
+        if let LockupDetails::Vesting(_) = &lockup {
+            if gifts.len() != 1 {
+                return Err(TonWalletError::TooManyMessages { max: 1 }.into());
+            }
+        }
+
+        let balance = current_state.storage.balance.grams.as_u128();
+        let amount = gifts.iter().fold(0u128, |total, gift| {
+            if gift.flags & u8::from(MessageFlags::AllBalance) != 0 {
+                total.saturating_add(balance)
+            } else {
+                total.saturating_add(gift.amount)
+            }
+        });
+
+        let available = lockup.available_balance(balance, self.clock.as_ref());
+        if amount > available {
+            return Err(TonWalletError::InsufficientUnlockedBalance {
+                available,
+                required: amount,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
     pub async fn send(
         &mut self,
         message: &ton_block::Message,
@@ -633,6 +1024,7 @@ impl TonWallet {
     }
 
     pub async fn refresh(&mut self) -> Result<()> {
+        let abi_decoder = self.abi_decoder();
         let handler = self.handler.as_ref();
         self.contract_subscription
             .refresh(
@@ -643,37 +1035,144 @@ impl TonWallet {
                     self.wallet_type,
                     &mut self.wallet_data,
                 ),
-                &mut make_transactions_handler(handler, self.wallet_type),
+                &mut make_transactions_handler(handler, self.wallet_type, abi_decoder),
                 &mut make_message_sent_handler(handler),
                 &mut make_message_expired_handler(handler),
             )
-            .await
+            .await?;
+
+        self.wallet_data
+            .check_pending_transactions_expiration(self.clock.as_ref(), self.handler.as_ref());
+        self.handle_contract_upgrade().await
     }
 
     pub async fn handle_block(&mut self, block: &ton_block::Block) -> Result<()> {
         // TODO: update wallet data here
 
+        let abi_decoder = self.abi_decoder();
         let handler = self.handler.as_ref();
         let new_account_state = self.contract_subscription.handle_block(
             block,
-            &mut make_transactions_handler(handler, self.wallet_type),
+            &mut make_transactions_handler(handler, self.wallet_type, abi_decoder),
             &mut make_message_sent_handler(handler),
             &mut make_message_expired_handler(handler),
         )?;
 
         if let Some(account_state) = new_account_state {
+            let diff = self.wallet_data.state_diff.update_brief(&account_state);
             handler.on_state_changed(account_state);
+            if let Some(diff) = diff {
+                handler.on_state_diff(diff);
+            }
         }
 
+        self.wallet_data
+            .check_pending_transactions_expiration(self.clock.as_ref(), handler);
+        self.handle_contract_upgrade().await
+    }
+
+    /// Re-derives the wallet type if the wallet code was changed
+    async fn handle_contract_upgrade(&mut self) -> Result<()> {
+        // NOTE: the upgrade is taken only after it was handled, so it will
+        // be handled again on the next refresh if the state request fails
+        let upgrade = match self.contract_subscription.contract_upgrade() {
+            Some(upgrade) => *upgrade,
+            None => return Ok(()),
+        };
+
+        let state = self
+            .contract_subscription
+            .transport()
+            .get_contract_state(self.address())
+            .await?;
+
+        if let RawContractState::Exists(state) = state {
+            match extract_wallet_init_data(&state) {
+                Ok((_, wallet_type)) if wallet_type != self.wallet_type => {
+                    self.wallet_type = wallet_type;
+                    // Details depend on the wallet type and must be extracted again
+                    self.wallet_data.details = None;
+                    self.wallet_data.update(
+                        self.clock.as_ref(),
+                        &self.public_key,
+                        wallet_type,
+                        &state.account,
+                        self.handler.as_ref(),
+                    )?;
+                }
+                Ok(_) => {}
+                // NOTE: keep the previous wallet type for the unknown code
+                Err(e) => log::error!("{e}"),
+            }
+        }
+
+        self.contract_subscription.take_contract_upgrade();
+        self.handler.on_contract_upgraded(upgrade);
         Ok(())
     }
 
+    /// Streams parsed transactions since the specified lt in descending order.
+    /// See [`ContractSubscription::transactions_stream`]
+    pub fn transactions_stream(
+        &self,
+        from_lt: u64,
+        until_lt: Option<u64>,
+        page_size: u8,
+    ) -> TransactionsStream<TransactionWithData<TransactionAdditionalInfo>> {
+        use futures_util::{StreamExt, TryStreamExt};
+
+        let wallet_type = self.wallet_type;
+        let abi_registry = self.abi_registry.clone();
+        let transactions = self
+            .contract_subscription
+            .transactions_stream(from_lt, until_lt, page_size)
+            .try_filter_map(move |transaction| {
+                let data = parse_transaction_additional_info_with_registry(
+                    &transaction.data,
+                    wallet_type,
+                    abi_registry.as_deref(),
+                );
+                let transaction = Transaction::try_from((transaction.hash, transaction.data))
+                    .ok()
+                    .map(|transaction| TransactionWithData { transaction, data });
+                futures_util::future::ready(Ok(transaction))
+            });
+
+        #[cfg(not(feature = "non_threadsafe"))]
+        return transactions.boxed();
+        #[cfg(feature = "non_threadsafe")]
+        return transactions.boxed_local();
+    }
+
     pub async fn preload_transactions(&mut self, from_lt: u64) -> Result<()> {
+        let abi_decoder = self.abi_decoder();
         let handler = self.handler.as_ref();
         self.contract_subscription
             .preload_transactions(
                 from_lt,
-                &mut make_transactions_handler(handler, self.wallet_type),
+                &mut make_transactions_handler(handler, self.wallet_type, abi_decoder),
+            )
+            .await
+    }
+
+    /// Loads older transactions until one of the conditions is met.
+    ///
+    /// [`BackfillStopCondition::Found`] counts all transactions
+    pub async fn backfill_transactions(
+        &mut self,
+        state: &mut BackfillState,
+        conditions: &[BackfillStopCondition],
+        on_progress: &mut (dyn FnMut(&BackfillState) + Send + Sync),
+    ) -> Result<()> {
+        let abi_decoder = self.abi_decoder();
+        let handler = self.handler.as_ref();
+        self.contract_subscription
+            .backfill_transactions(
+                state,
+                conditions,
+                &mut |_| true,
+                &mut make_transactions_handler(handler, self.wallet_type, abi_decoder),
+                on_progress,
             )
             .await
     }
@@ -681,6 +1180,15 @@ impl TonWallet {
     pub async fn estimate_fees(&mut self, message: &ton_block::Message) -> Result<u128> {
         self.contract_subscription.estimate_fees(message).await
     }
+
+    pub async fn estimate_fees_detailed(
+        &mut self,
+        message: &ton_block::Message,
+    ) -> Result<TransactionFees> {
+        self.contract_subscription
+            .estimate_fees_detailed(message)
+            .await
+    }
 }
 
 #[derive(Default)]
@@ -689,6 +1197,12 @@ struct WalletData {
     unconfirmed_transactions: Vec<MultisigPendingTransaction>,
     unconfirmed_updates: Vec<MultisigPendingUpdate>,
     details: Option<TonWalletDetails>,
+    lockup: Option<LockupDetails>,
+    lockup_code_hashes: Arc<LockupCodeHashes>,
+    state_diff: StateDiffTracker,
+    /// Ids of pending transactions for which the expiration events were emitted
+    expiring_notified: HashSet<u64>,
+    expired_notified: HashSet<u64>,
 }
 
 impl WalletData {
@@ -716,6 +1230,14 @@ impl WalletData {
             handler.on_details_changed(details);
         }
 
+        // Extract unlock schedule
+        let lockup =
+            LockupDetails::from_account(&self.lockup_code_hashes, wallet_type, account_stuff);
+        if lockup != self.lockup {
+            self.lockup = lockup;
+            handler.on_lockup_details_changed(self.lockup.as_ref());
+        }
+
         // Extract custodians
         let multisig_type = match wallet_type {
             WalletType::Multisig(multisig_type) => multisig_type,
@@ -762,11 +1284,13 @@ impl WalletData {
         }
 
         // Extract pending transactions
+        let expiration_time = self.details.map_or(0, |details| details.expiration_time);
         let pending_transactions = multisig::get_pending_transactions(
             clock,
             multisig_type,
             Cow::Borrowed(account_stuff),
             custodians,
+            expiration_time,
         )?;
 
         if self.unconfirmed_transactions != pending_transactions {
@@ -776,6 +1300,38 @@ impl WalletData {
 
         Ok(())
     }
+
+    /// Emits expiration events for pending transactions, at most once per transaction.
+    ///
+    /// Depends only on the current time, so it is checked on each refresh
+    fn check_pending_transactions_expiration(
+        &mut self,
+        clock: &dyn Clock,
+        handler: &dyn TonWalletSubscriptionHandler,
+    ) {
+        let now = clock.now_sec_u64() as u32;
+
+        // Forget about resolved transactions
+        let ids = self
+            .unconfirmed_transactions
+            .iter()
+            .map(|transaction| transaction.id)
+            .collect::<HashSet<_>>();
+        self.expiring_notified.retain(|id| ids.contains(id));
+        self.expired_notified.retain(|id| ids.contains(id));
+
+        for transaction in &self.unconfirmed_transactions {
+            if transaction.is_expired(now) {
+                if self.expired_notified.insert(transaction.id) {
+                    handler.on_unconfirmed_transaction_expired(transaction);
+                }
+            } else if transaction.expires_at - now <= PENDING_TRANSACTION_EXPIRING_THRESHOLD
+                && self.expiring_notified.insert(transaction.id)
+            {
+                handler.on_unconfirmed_transaction_expiring(transaction);
+            }
+        }
+    }
 }
 
 pub fn extract_wallet_init_data(contract: &ExistingContract) -> Result<(PublicKey, WalletType)> {
@@ -850,6 +1406,41 @@ pub const WALLET_TYPES_BY_POPULARITY: [WalletType; 10] = [
     WalletType::HighloadWalletV2,
 ];
 
+/// All supported wallet types, ordered by their popularity
+pub const ALL_WALLET_TYPES: [WalletType; 16] = [
+    WalletType::Multisig(MultisigType::SafeMultisigWallet),
+    WalletType::Multisig(MultisigType::SurfWallet),
+    WalletType::WalletV3,
+    WalletType::EverWallet,
+    WalletType::Multisig(MultisigType::Multisig2_1),
+    WalletType::Multisig(MultisigType::Multisig2),
+    WalletType::Multisig(MultisigType::SetcodeMultisigWallet),
+    WalletType::Multisig(MultisigType::SafeMultisigWallet24h),
+    WalletType::Multisig(MultisigType::BridgeMultisigWallet),
+    WalletType::HighloadWalletV2,
+    WalletType::WalletV4R2,
+    WalletType::WalletV5R1,
+    WalletType::WalletV3R2,
+    WalletType::WalletV4R1,
+    WalletType::WalletV3R1,
+    WalletType::Multisig(MultisigType::SetcodeMultisigWallet24h),
+];
+
+/// Finds wallet types which have the specified address for the public key.
+///
+/// Candidates are ranked by likelihood, see [`ALL_WALLET_TYPES`]
+pub fn guess_wallet_types(address: &MsgAddressInt, public_key: &PublicKey) -> Vec<WalletType> {
+    let workchain_id = match i8::try_from(address.workchain_id()) {
+        Ok(workchain_id) => workchain_id,
+        Err(_) => return Vec::new(),
+    };
+
+    ALL_WALLET_TYPES
+        .into_iter()
+        .filter(|&wallet_type| compute_address(public_key, wallet_type, workchain_id) == *address)
+        .collect()
+}
+
 pub async fn find_existing_wallets(
     transport: &dyn Transport,
     public_key: &PublicKey,
@@ -877,6 +1468,108 @@ pub async fn find_existing_wallets(
         .await
 }
 
+/// Derives accounts of the master key on the standard path and finds
+/// their wallets which are deployed or have a non-zero balance.
+///
+/// Accounts without such wallets are skipped
+pub async fn discover_accounts(
+    keystore: &KeyStore,
+    transport: &dyn Transport,
+    input: DerivedKeyGetPublicKeys,
+    workchain_id: i8,
+    wallet_types: &[WalletType],
+) -> Result<Vec<DiscoveredAccount>> {
+    use futures_util::stream::{StreamExt, TryStreamExt};
+
+    const MAX_CONCURRENT_REQUESTS: usize = 10;
+
+    let offset = input.offset;
+    let public_keys = keystore.get_public_keys::<DerivedKeySigner>(input).await?;
+
+    let requests = public_keys.iter().flat_map(|public_key| {
+        wallet_types
+            .iter()
+            .map(move |&wallet_type| (public_key, wallet_type))
+    });
+
+    let wallets = futures_util::stream::iter(requests)
+        .map(|(public_key, wallet_type)| async move {
+            let address = compute_address(public_key, wallet_type, workchain_id);
+            let contract_state = transport.get_contract_state(&address).await?;
+
+            Ok::<_, anyhow::Error>(ExistingWalletInfo {
+                address,
+                public_key: *public_key,
+                wallet_type,
+                contract_state: contract_state.brief(),
+            })
+        })
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    // NOTE: `buffered` keeps the order, so wallets are grouped by public keys
+    let mut wallets = wallets.into_iter();
+    Ok(public_keys
+        .into_iter()
+        .zip(offset..=u16::MAX)
+        .filter_map(|(public_key, account_id)| {
+            let wallets = wallets
+                .by_ref()
+                .take(wallet_types.len())
+                .filter(|wallet| {
+                    wallet.contract_state.is_deployed || wallet.contract_state.balance > 0
+                })
+                .collect::<Vec<_>>();
+
+            (!wallets.is_empty()).then(|| DiscoveredAccount {
+                account_id,
+                public_key,
+                wallets,
+            })
+        })
+        .collect())
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredAccount {
+    pub account_id: u16,
+    #[serde(with = "serde_public_key")]
+    pub public_key: PublicKey,
+    /// Deployed or funded wallets of this account
+    pub wallets: Vec<ExistingWalletInfo>,
+}
+
+impl DiscoveredAccount {
+    /// Total balance of all wallets
+    pub fn balance(&self) -> u128 {
+        self.wallets
+            .iter()
+            .map(|wallet| wallet.contract_state.balance)
+            .sum()
+    }
+}
+
+/// Resolves the public key and the wallet type of the deployed wallet
+pub async fn get_existing_wallet_info(
+    transport: &dyn Transport,
+    address: MsgAddressInt,
+) -> Result<ExistingWalletInfo> {
+    let contract = match transport.get_contract_state(&address).await? {
+        RawContractState::Exists(contract) => contract,
+        RawContractState::NotExists { .. } => return Err(TonWalletError::AccountNotExists.into()),
+    };
+    let (public_key, wallet_type) = extract_wallet_init_data(&contract)?;
+
+    Ok(ExistingWalletInfo {
+        address,
+        public_key,
+        wallet_type,
+        contract_state: contract.brief(),
+    })
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExistingWalletInfo {
@@ -930,7 +1623,8 @@ enum InternalMessageSenderError {
 }
 
 #[derive(thiserror::Error, Debug)]
-enum TonWalletError {
+#[non_exhaustive]
+pub enum TonWalletError {
     #[error("Account not exists")]
     AccountNotExists,
     #[error("Account is frozen")]
@@ -947,6 +1641,26 @@ enum TonWalletError {
     PendingUpdateNotFound,
     #[error("Updated data mismatch")]
     UpdatedDataMismatch,
+    #[error("Watch-only account can't prepare messages")]
+    WatchOnlyAccount,
+    #[error("Insufficient balance: available {available}, required {required}")]
+    InsufficientBalance { available: u128, required: u128 },
+    #[error("Insufficient unlocked balance: available {available}, required {required}")]
+    InsufficientUnlockedBalance { available: u128, required: u128 },
+    #[error("Account not deployed")]
+    AccountNotDeployed,
+    #[error("Public key is not a wallet custodian")]
+    InvalidCustodian,
+    #[error("Account state is older than the latest known one")]
+    ExpiredState,
+    #[error("Too many outgoing messages: at most {max} supported")]
+    TooManyMessages { max: usize },
+}
+
+fn is_custodian(custodians: &[UInt256], public_key: &PublicKey) -> bool {
+    custodians
+        .iter()
+        .any(|custodian| custodian.as_slice() == public_key.as_bytes())
 }
 
 fn make_contract_state_handler<'a>(
@@ -968,26 +1682,52 @@ fn make_contract_state_handler<'a>(
                 log::error!("{e}");
             }
         }
-        handler.on_state_changed(contract_state.brief())
+
+        let diff = wallet_data.state_diff.update(contract_state);
+        handler.on_state_changed(contract_state.brief());
+        if let Some(diff) = diff {
+            handler.on_state_diff(diff);
+        }
     }
 }
 
 fn make_transactions_handler(
     handler: &'_ dyn TonWalletSubscriptionHandler,
     wallet_type: WalletType,
+    abi_decoder: Option<AbiDecoder>,
 ) -> impl FnMut(Vec<RawTransaction>, TransactionsBatchInfo) + '_ {
     move |transactions, batch_info| {
+        let balance_changes = transactions
+            .iter()
+            .map(|transaction| BalanceChange::new(transaction.hash, &transaction.data))
+            .collect();
+
+        let registry = abi_decoder
+            .as_ref()
+            .map(|abi_decoder| abi_decoder.registry.as_ref());
         let transactions = transactions
             .into_iter()
             .filter_map(move |transaction| {
-                let data = parse_transaction_additional_info(&transaction.data, wallet_type);
+                let data = parse_transaction_additional_info_with_registry(
+                    &transaction.data,
+                    wallet_type,
+                    registry,
+                );
                 let transaction =
                     Transaction::try_from((transaction.hash, transaction.data)).ok()?;
                 Some(TransactionWithData { transaction, data })
             })
-            .collect();
-
-        handler.on_transactions_found(transactions, batch_info)
+            .collect::<Vec<_>>();
+
+        if let Some(abi_decoder) = &abi_decoder {
+            let decoded = transactions
+                .iter()
+                .map(|item| abi_decoder.decode(&item.transaction))
+                .collect();
+            handler.on_transactions_decoded(&transactions, decoded);
+        }
+        handler.on_transactions_found(transactions, batch_info);
+        handler.on_balance_changes(balance_changes, batch_info);
     }
 }
 
@@ -1020,6 +1760,17 @@ pub struct TonWalletDetails {
     pub required_confirmations: Option<NonZeroU8>,
 }
 
+/// Serializable state of the [`TonWallet`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TonWalletSnapshot {
+    #[serde(with = "serde_public_key")]
+    pub public_key: PublicKey,
+    pub wallet_type: WalletType,
+    pub watch_only: bool,
+    pub subscription: ContractSubscriptionSnapshot,
+}
+
 /// Message info
 #[derive(Clone)]
 pub struct Gift {
@@ -1031,6 +1782,49 @@ pub struct Gift {
     pub state_init: Option<ton_block::StateInit>,
 }
 
+impl Gift {
+    /// Builds and sets the message body
+    pub fn with_body(mut self, body: MessageBody) -> Result<Self> {
+        self.body = Some(body.build()?);
+        Ok(self)
+    }
+}
+
+/// Message info with the body which is not built yet
+#[derive(Clone, Debug)]
+pub struct Transfer {
+    pub flags: u8,
+    pub bounce: bool,
+    pub destination: MsgAddressInt,
+    pub amount: u128,
+    pub body: Option<MessageBody>,
+    pub state_init: Option<ton_block::StateInit>,
+}
+
+/// Outgoing message, accepted by [`TonWallet::prepare_transfer`]
+pub trait IntoGift {
+    fn into_gift(self) -> Result<Gift>;
+}
+
+impl IntoGift for Gift {
+    fn into_gift(self) -> Result<Gift> {
+        Ok(self)
+    }
+}
+
+impl IntoGift for Transfer {
+    fn into_gift(self) -> Result<Gift> {
+        Ok(Gift {
+            flags: self.flags,
+            bounce: self.bounce,
+            destination: self.destination,
+            amount: self.amount,
+            body: self.body.map(MessageBody::build).transpose()?,
+            state_init: self.state_init,
+        })
+    }
+}
+
 #[derive(Clone)]
 pub enum TransferAction {
     DeployFirst,
@@ -1064,6 +1858,23 @@ impl WalletType {
         }
     }
 
+    /// Address format, which is shown to the user by default.
+    ///
+    /// TON wallets use the non-bounceable packed format, other wallets
+    /// use the raw format
+    pub fn default_address_format(&self) -> AddressFormat {
+        match self {
+            Self::WalletV3R1
+            | Self::WalletV3R2
+            | Self::WalletV4R1
+            | Self::WalletV4R2
+            | Self::WalletV5R1 => AddressFormat::USER_FRIENDLY,
+            Self::Multisig(_) | Self::WalletV3 | Self::HighloadWalletV2 | Self::EverWallet => {
+                AddressFormat::Raw
+            }
+        }
+    }
+
     pub fn possible_updates(&self) -> &'static [Self] {
         const MULTISIG2_UPDATES: &[WalletType] = &[WalletType::Multisig(MultisigType::Multisig2_1)];
 
@@ -1215,6 +2026,12 @@ pub trait TonWalletSubscriptionHandler: Send + Sync {
         let _ = new_state;
     }
 
+    /// Called after [`TonWalletSubscriptionHandler::on_state_changed`]
+    /// with the changes since the previous state
+    fn on_state_diff(&self, diff: ContractStateDiff) {
+        let _ = diff;
+    }
+
     /// Called every time new transactions are detected.
     /// - When new block found
     /// - When manually requesting the latest transactions (can be called several times)
@@ -1228,6 +2045,22 @@ pub trait TonWalletSubscriptionHandler: Send + Sync {
         let _ = batch_info;
     }
 
+    /// Called before [`TonWalletSubscriptionHandler::on_transactions_found`]
+    /// if the ABI registry is set. Decoded items have the same order as transactions
+    fn on_transactions_decoded(
+        &self,
+        transactions: &[TransactionWithData<TransactionAdditionalInfo>],
+        decoded: Vec<DecodedAbiTransaction>,
+    ) {
+        let _ = (transactions, decoded);
+    }
+
+    /// Called after [`TonWalletSubscriptionHandler::on_transactions_found`]
+    /// with the balance change of each transaction
+    fn on_balance_changes(&self, changes: Vec<BalanceChange>, batch_info: TransactionsBatchInfo) {
+        let _ = (changes, batch_info);
+    }
+
     /// Called when wallet details changed (e.g. expiration time or required confirms)
     fn on_details_changed(&self, details: TonWalletDetails) {
         let _ = details;
@@ -1238,6 +2071,12 @@ pub trait TonWalletSubscriptionHandler: Send + Sync {
         let _ = custodians;
     }
 
+    /// Called when the wallet code hash changed.
+    /// [`TonWallet::wallet_type`] is already updated at this point
+    fn on_contract_upgraded(&self, upgrade: ContractUpgrade) {
+        let _ = upgrade;
+    }
+
     /// Called when wallet has new pending transactions set
     fn on_unconfirmed_transactions_changed(
         &self,
@@ -1246,8 +2085,442 @@ pub trait TonWalletSubscriptionHandler: Send + Sync {
         let _ = unconfirmed_transactions;
     }
 
+    /// Called once when the pending transaction will expire in less than
+    /// [`PENDING_TRANSACTION_EXPIRING_THRESHOLD`] seconds
+    fn on_unconfirmed_transaction_expiring(&self, transaction: &MultisigPendingTransaction) {
+        let _ = transaction;
+    }
+
+    /// Called once when the pending transaction has expired without enough confirmations
+    fn on_unconfirmed_transaction_expired(&self, transaction: &MultisigPendingTransaction) {
+        let _ = transaction;
+    }
+
     /// Called when wallet has new pending updates set
     fn on_unconfirmed_updates_changed(&self, unconfirmed_updates: &[MultisigPendingUpdate]) {
         let _ = unconfirmed_updates;
     }
+
+    /// Called when the unlock schedule of vesting or lockup wallet changed
+    fn on_lockup_details_changed(&self, lockup_details: Option<&LockupDetails>) {
+        let _ = lockup_details;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guess_wallet_type_by_address() {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public_key = PublicKey::from(&secret);
+
+        for wallet_type in ALL_WALLET_TYPES {
+            for workchain_id in [0, -1] {
+                let address = compute_address(&public_key, wallet_type, workchain_id);
+                assert!(guess_wallet_types(&address, &public_key).contains(&wallet_type));
+            }
+        }
+
+        let other_secret = ed25519_dalek::SecretKey::from_bytes(&[2; 32]).unwrap();
+        let other_public_key = PublicKey::from(&other_secret);
+        let address = compute_address(&other_public_key, WalletType::EverWallet, 0);
+        assert!(guess_wallet_types(&address, &public_key).is_empty());
+    }
+
+    #[derive(Default)]
+    struct ExpirationHandler {
+        expiring: parking_lot::Mutex<Vec<u64>>,
+        expired: parking_lot::Mutex<Vec<u64>>,
+    }
+
+    impl TonWalletSubscriptionHandler for ExpirationHandler {
+        fn on_message_sent(&self, _: PendingTransaction, _: Option<Transaction>) {}
+
+        fn on_message_expired(&self, _: PendingTransaction) {}
+
+        fn on_unconfirmed_transaction_expiring(&self, transaction: &MultisigPendingTransaction) {
+            self.expiring.lock().push(transaction.id);
+        }
+
+        fn on_unconfirmed_transaction_expired(&self, transaction: &MultisigPendingTransaction) {
+            self.expired.lock().push(transaction.id);
+        }
+    }
+
+    #[test]
+    fn pending_transactions_expiration() {
+        let make_transaction = |id: u64, expires_at: u32| MultisigPendingTransaction {
+            id,
+            confirmations: Vec::new(),
+            signs_required: 2,
+            signs_received: 1,
+            creator: Default::default(),
+            index: 0,
+            dest: Default::default(),
+            value: Default::default(),
+            send_flags: 3,
+            payload: Default::default(),
+            bounce: false,
+            expires_at,
+        };
+
+        let handler = ExpirationHandler::default();
+        let mut wallet_data = WalletData {
+            unconfirmed_transactions: vec![make_transaction(1, 1000), make_transaction(2, 5000)],
+            ..Default::default()
+        };
+
+        let check = |wallet_data: &mut WalletData, now: u64| {
+            wallet_data.check_pending_transactions_expiration(&ConstClock::from_secs(now), &handler)
+        };
+
+        check(&mut wallet_data, 500);
+        assert_eq!(*handler.expiring.lock(), [1]);
+        assert!(handler.expired.lock().is_empty());
+
+        // Events are emitted only once
+        check(&mut wallet_data, 600);
+        assert_eq!(*handler.expiring.lock(), [1]);
+
+        check(&mut wallet_data, 1000);
+        assert_eq!(*handler.expired.lock(), [1]);
+
+        check(&mut wallet_data, 4500);
+        assert_eq!(*handler.expiring.lock(), [1, 2]);
+        assert_eq!(*handler.expired.lock(), [1]);
+
+        // Resolved transactions are forgotten
+        wallet_data.unconfirmed_transactions.clear();
+        check(&mut wallet_data, 6000);
+        assert!(wallet_data.expiring_notified.is_empty());
+        assert!(wallet_data.expired_notified.is_empty());
+    }
+
+    #[tokio::test]
+    async fn watch_only_wallet_from_assets() {
+        use crate::transport::tests::MockTransport;
+
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public_key = PublicKey::from(&secret);
+        let address = compute_address(&public_key, WalletType::EverWallet, 0);
+
+        let mut assets = AssetsList {
+            name: "test".to_owned(),
+            ton_wallet: TonWalletAsset {
+                address: address.clone(),
+                public_key,
+                contract: WalletType::EverWallet,
+            },
+            additional_assets: Default::default(),
+            watch_only: true,
+        };
+
+        let subscribe = |assets: AssetsList| async move {
+            TonWallet::subscribe_by_assets(
+                Arc::new(SimpleClock),
+                Arc::new(MockTransport::new()),
+                &assets,
+                Arc::new(ExpirationHandler::default()),
+            )
+            .await
+            .unwrap()
+        };
+
+        let mut wallet = subscribe(assets.clone()).await;
+        assert!(wallet.is_watch_only());
+        assert_eq!(wallet.address(), &address);
+        assert!(wallet.snapshot().watch_only);
+
+        let err = wallet
+            .prepare_transfer(
+                &Default::default(),
+                &public_key,
+                Vec::<Gift>::new(),
+                Expiration::Timeout(60),
+            )
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<TonWalletError>(),
+            Some(TonWalletError::WatchOnlyAccount)
+        ));
+
+        assets.watch_only = false;
+        let wallet = subscribe(assets).await;
+        assert!(!wallet.is_watch_only());
+    }
+
+    fn make_account(
+        last_trans_lt: u64,
+        balance: u64,
+        state: ton_block::AccountState,
+    ) -> ton_block::AccountStuff {
+        ton_block::AccountStuff {
+            storage: ton_block::AccountStorage {
+                last_trans_lt,
+                balance: ton_block::CurrencyCollection::with_grams(balance),
+                state,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn transfer_checks_balance_and_state() {
+        use crate::transport::tests::MockTransport;
+
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public_key = PublicKey::from(&secret);
+        let address = compute_address(&public_key, WalletType::EverWallet, 0);
+
+        let transport = Arc::new(MockTransport::new());
+        transport.set_contract_state(
+            address.clone(),
+            RawContractState::Exists(ExistingContract {
+                account: make_account(10, 1_000, ton_block::AccountState::AccountUninit),
+                timings: GenTimings::Unknown,
+                last_transaction_id: LastTransactionId::Inexact { latest_lt: 10 },
+            }),
+        );
+
+        let mut wallet = TonWallet::subscribe(
+            Arc::new(SimpleClock),
+            transport,
+            0,
+            public_key,
+            WalletType::EverWallet,
+            Arc::new(ExpirationHandler::default()),
+        )
+        .await
+        .unwrap();
+
+        let gift = |amount: u128, flags: MessageFlags| Gift {
+            flags: flags.into(),
+            bounce: false,
+            destination: address.clone(),
+            amount,
+            body: None,
+            state_init: None,
+        };
+        let mut transfer = |state: &ton_block::AccountStuff, gifts: Vec<Gift>| {
+            wallet.prepare_transfer(state, &public_key, gifts, Expiration::Timeout(60))
+        };
+
+        let state = make_account(10, 1_000, ton_block::AccountState::AccountUninit);
+
+        let err = transfer(&state, vec![gift(1_001, MessageFlags::Normal)])
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<TonWalletError>(),
+            Some(TonWalletError::InsufficientBalance {
+                available: 1_000,
+                required: 1_001
+            })
+        ));
+
+        // Amounts of all messages are summed up
+        let gifts = vec![
+            gift(600, MessageFlags::Normal),
+            gift(600, MessageFlags::Normal),
+        ];
+        let err = transfer(&state, gifts).err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<TonWalletError>(),
+            Some(TonWalletError::InsufficientBalance {
+                available: 1_000,
+                required: 1_200
+            })
+        ));
+
+        // Messages with all balance never exceed it
+        transfer(&state, vec![gift(5_000, MessageFlags::AllBalance)]).unwrap();
+        transfer(&state, vec![gift(1_000, MessageFlags::Normal)]).unwrap();
+
+        // State must not be older than the subscription state
+        let outdated = make_account(5, 1_000, ton_block::AccountState::AccountUninit);
+        let err = transfer(&outdated, vec![gift(1, MessageFlags::Normal)])
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<TonWalletError>(),
+            Some(TonWalletError::ExpiredState)
+        ));
+    }
+
+    #[tokio::test]
+    async fn multisig_transfer_requires_single_message() {
+        use crate::transport::tests::MockTransport;
+
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public_key = PublicKey::from(&secret);
+
+        let mut wallet = TonWallet::subscribe(
+            Arc::new(SimpleClock),
+            Arc::new(MockTransport::new()),
+            0,
+            public_key,
+            WalletType::Multisig(MultisigType::SafeMultisigWallet),
+            Arc::new(ExpirationHandler::default()),
+        )
+        .await
+        .unwrap();
+
+        let gift = Gift {
+            flags: MessageFlags::Normal.into(),
+            bounce: false,
+            destination: wallet.address().clone(),
+            amount: 1,
+            body: None,
+            state_init: None,
+        };
+
+        let state = make_account(0, 1_000, ton_block::AccountState::AccountUninit);
+        let err = wallet
+            .prepare_transfer(
+                &state,
+                &public_key,
+                vec![gift.clone(), gift],
+                Expiration::Timeout(60),
+            )
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<TonWalletError>(),
+            Some(TonWalletError::TooManyMessages { max: 1 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn multisig_actions_check_custodian() {
+        use crate::transport::tests::MockTransport;
+
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public_key = PublicKey::from(&secret);
+        let other_secret = ed25519_dalek::SecretKey::from_bytes(&[2; 32]).unwrap();
+        let other_public_key = PublicKey::from(&other_secret);
+
+        let multisig_type = MultisigType::SafeMultisigWallet;
+        let wallet = TonWallet::subscribe(
+            Arc::new(SimpleClock),
+            Arc::new(MockTransport::new()),
+            0,
+            public_key,
+            WalletType::Multisig(multisig_type),
+            Arc::new(ExpirationHandler::default()),
+        )
+        .await
+        .unwrap();
+
+        let confirm = |state: &ton_block::AccountStuff| {
+            wallet
+                .prepare_confirm_transaction(state, &public_key, 1, Expiration::Timeout(60))
+                .err()
+                .unwrap()
+        };
+
+        let err = confirm(&make_account(0, 0, ton_block::AccountState::AccountUninit));
+        assert!(matches!(
+            err.downcast_ref::<TonWalletError>(),
+            Some(TonWalletError::AccountNotDeployed)
+        ));
+
+        // The initial state of the other wallet has no custodians
+        let state = make_account(
+            0,
+            0,
+            ton_block::AccountState::AccountActive {
+                state_init: multisig::prepare_state_init(&other_public_key, multisig_type),
+            },
+        );
+        let err = confirm(&state);
+        assert!(matches!(
+            err.downcast_ref::<TonWalletError>(),
+            Some(TonWalletError::InvalidCustodian)
+        ));
+    }
+
+    #[tokio::test]
+    async fn discover_funded_accounts() {
+        use crate::crypto::{DerivedKeyCreateInput, Password, PasswordCacheBehavior};
+        use crate::external::tests::TestStorage;
+        use crate::transport::tests::MockTransport;
+
+        let keystore = KeyStore::builder()
+            .with_signer("master_key", DerivedKeySigner::new())
+            .unwrap()
+            .load(Arc::new(TestStorage::default()))
+            .await
+            .unwrap();
+
+        let password = Password::Explicit {
+            password: "test".into(),
+            cache_behavior: PasswordCacheBehavior::Nop,
+        };
+        let entry = keystore
+            .add_key::<DerivedKeySigner>(DerivedKeyCreateInput::Import {
+                passphrase: None,
+                key_name: None,
+                phrase:
+                    "admit cheap engage ancient audit drink mammal mobile fashion aspect rapid else"
+                        .into(),
+                password: password.clone(),
+            })
+            .await
+            .unwrap();
+
+        let input = || DerivedKeyGetPublicKeys {
+            master_key: entry.master_key,
+            password: password.clone(),
+            passphrase: None,
+            limit: 3,
+            offset: 0,
+        };
+        let public_keys = keystore
+            .get_public_keys::<DerivedKeySigner>(input())
+            .await
+            .unwrap();
+        assert_eq!(public_keys.len(), 3);
+
+        let transport = MockTransport::new();
+        let fund = |public_key: &PublicKey, wallet_type: WalletType| {
+            transport.set_contract_state(
+                compute_address(public_key, wallet_type, 0),
+                RawContractState::Exists(ExistingContract {
+                    account: make_account(10, 1_000, ton_block::AccountState::AccountUninit),
+                    timings: GenTimings::Unknown,
+                    last_transaction_id: LastTransactionId::Inexact { latest_lt: 10 },
+                }),
+            );
+        };
+        fund(&public_keys[0], WalletType::EverWallet);
+        fund(&public_keys[2], WalletType::WalletV3);
+
+        let wallet_types = [WalletType::EverWallet, WalletType::WalletV3];
+        let accounts = discover_accounts(&keystore, &transport, input(), 0, &wallet_types)
+            .await
+            .unwrap();
+
+        let found = accounts
+            .iter()
+            .map(|account| {
+                let wallet_types = account
+                    .wallets
+                    .iter()
+                    .map(|wallet| wallet.wallet_type)
+                    .collect::<Vec<_>>();
+                (account.account_id, account.public_key, wallet_types)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (0, public_keys[0], vec![WalletType::EverWallet]),
+                (2, public_keys[2], vec![WalletType::WalletV3]),
+            ]
+        );
+    }
 }
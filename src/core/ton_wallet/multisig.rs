@@ -58,6 +58,8 @@ pub fn prepare_deploy(
 
     message.set_state_init(state_init);
 
+    validate_custodians(params.owners, params.req_confirms, MAX_CUSTODIAN_COUNT)?;
+
     let owners = params
         .owners
         .iter()
@@ -179,6 +181,39 @@ pub fn prepare_code_update(
     address: MsgAddressInt,
     new_code_hash: &[u8; 32],
     expiration: Expiration,
+) -> Result<Box<dyn UnsignedMessage>> {
+    prepare_update(
+        clock,
+        multisig_type,
+        public_key,
+        address,
+        UpdateParams {
+            new_code_hash: Some(new_code_hash),
+            ..Default::default()
+        },
+        expiration,
+    )
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct UpdateParams<'a> {
+    pub new_code_hash: Option<&'a [u8; 32]>,
+    pub owners: Option<&'a [PublicKey]>,
+    pub req_confirms: Option<u8>,
+    pub lifetime: Option<u64>,
+}
+
+/// Prepares `submitUpdate` with the arbitrary set of changed params.
+///
+/// NOTE: custodians are validated only against the default contract limits,
+/// use [`validate_custodians`] with the actual contract params for a precise check
+pub fn prepare_update(
+    clock: &dyn Clock,
+    multisig_type: MultisigType,
+    public_key: &PublicKey,
+    address: MsgAddressInt,
+    params: UpdateParams<'_>,
+    expiration: Expiration,
 ) -> Result<Box<dyn UnsignedMessage>> {
     use nekoton_contracts::wallets::multisig2;
 
@@ -186,6 +221,13 @@ pub fn prepare_code_update(
         return Err(MultisigError::UnsupportedUpdate.into());
     }
 
+    if let Some(owners) = params.owners {
+        let req_confirms = params
+            .req_confirms
+            .ok_or(MultisigError::ReqConfirmsNotSpecified)?;
+        validate_custodians(owners, req_confirms, MAX_CUSTODIAN_COUNT)?;
+    }
+
     make_ext_message(
         clock,
         public_key,
@@ -193,15 +235,46 @@ pub fn prepare_code_update(
         expiration,
         multisig2::submit_update(),
         multisig2::SubmitUpdateParams {
-            code_hash: Some(ton_types::UInt256::from(*new_code_hash)),
-            owners: None,
-            req_confirms: None,
-            lifetime: None,
+            code_hash: params
+                .new_code_hash
+                .map(|hash| ton_types::UInt256::from(*hash)),
+            owners: params.owners.map(|owners| {
+                owners
+                    .iter()
+                    .map(|public_key| UInt256::from(public_key.as_bytes()))
+                    .collect()
+            }),
+            req_confirms: params.req_confirms,
+            lifetime: params.lifetime,
         }
         .pack(),
     )
 }
 
+/// Checks that the custodians set can be used in multisig contract
+pub fn validate_custodians(
+    owners: &[PublicKey],
+    req_confirms: u8,
+    max_custodian_count: u8,
+) -> Result<()> {
+    if owners.is_empty() {
+        return Err(MultisigError::EmptyCustodians.into());
+    }
+    if owners.len() > max_custodian_count as usize {
+        return Err(MultisigError::TooManyCustodians(max_custodian_count).into());
+    }
+    if req_confirms == 0 || req_confirms as usize > owners.len() {
+        return Err(MultisigError::InvalidReqConfirms(req_confirms).into());
+    }
+
+    let mut unique = std::collections::HashSet::with_capacity(owners.len());
+    if !owners.iter().all(|owner| unique.insert(owner.as_bytes())) {
+        return Err(MultisigError::DuplicateCustodians.into());
+    }
+
+    Ok(())
+}
+
 pub fn prepare_confirm_update(
     clock: &dyn Clock,
     multisig_type: MultisigType,
@@ -430,6 +503,7 @@ fn run_local(
     let ExecutionOutput {
         tokens,
         result_code,
+        ..
     } = function.run_local(clock, account_stuff, &[])?;
     tokens.ok_or_else(|| MultisigError::NonZeroResultCode(result_code).into())
 }
@@ -474,6 +548,28 @@ pub fn get_params(
     Ok(output)
 }
 
+#[derive(Debug, Clone)]
+pub struct MultisigCustodiansInfo {
+    pub custodians: Vec<UInt256>,
+    pub required_confirms: u8,
+    pub max_custodian_count: u8,
+}
+
+/// Returns custodians sorted by their index with the confirmation params
+pub fn get_custodians_info(
+    clock: &dyn Clock,
+    multisig_type: MultisigType,
+    account_stuff: Cow<'_, ton_block::AccountStuff>,
+) -> Result<MultisigCustodiansInfo> {
+    let params = get_params(clock, multisig_type, Cow::Borrowed(account_stuff.as_ref()))?;
+    let custodians = get_custodians(clock, multisig_type, account_stuff)?;
+    Ok(MultisigCustodiansInfo {
+        custodians,
+        required_confirms: params.required_confirms,
+        max_custodian_count: params.max_custodian_count,
+    })
+}
+
 pub fn get_custodians(
     clock: &dyn Clock,
     multisig_type: MultisigType,
@@ -587,6 +683,7 @@ pub fn get_pending_transactions(
     multisig_type: MultisigType,
     account_stuff: Cow<'_, ton_block::AccountStuff>,
     custodians: &[UInt256],
+    expiration_time: u32,
 ) -> Result<Vec<MultisigPendingTransaction>> {
     let function = if multisig_type.is_multisig2() {
         nekoton_contracts::wallets::multisig2::get_transactions()
@@ -601,7 +698,13 @@ pub fn get_pending_transactions(
 
         let transactions = array
             .into_iter()
-            .map(|item| Ok(extend_pending_transaction(item.unpack()?, custodians)))
+            .map(|item| {
+                Ok(extend_pending_transaction(
+                    item.unpack()?,
+                    custodians,
+                    expiration_time,
+                ))
+            })
             .collect::<UnpackerResult<Vec<MultisigPendingTransaction>>>()?;
 
         Ok(transactions)
@@ -640,6 +743,7 @@ pub fn get_pending_updates(
 fn extend_pending_transaction(
     tx: nekoton_contracts::wallets::multisig::MultisigTransaction,
     custodians: &[UInt256],
+    expiration_time: u32,
 ) -> MultisigPendingTransaction {
     let confirmations = custodians
         .iter()
@@ -648,6 +752,9 @@ fn extend_pending_transaction(
         .map(|(_, item)| *item)
         .collect::<Vec<UInt256>>();
 
+    // NOTE: the upper 32 bits of the transaction id contain its creation time
+    let created_at = (tx.id >> 32) as u32;
+
     MultisigPendingTransaction {
         id: tx.id,
         confirmations,
@@ -660,6 +767,7 @@ fn extend_pending_transaction(
         send_flags: tx.send_flags,
         payload: tx.payload,
         bounce: tx.bounce,
+        expires_at: created_at.saturating_add(expiration_time),
     }
 }
 
@@ -711,6 +819,7 @@ fn make_ext_message(
 }
 
 const DEFAULT_LIFETIME: u32 = 3600;
+const MAX_CUSTODIAN_COUNT: u8 = 32;
 
 #[derive(thiserror::Error, Debug)]
 enum MultisigError {
@@ -722,6 +831,16 @@ enum MultisigError {
     CustomExpirationTimeNotSupported,
     #[error("Update is not supported or not implemented for this contract type")]
     UnsupportedUpdate,
+    #[error("Custodians list is empty")]
+    EmptyCustodians,
+    #[error("Too many custodians. Max: {}", .0)]
+    TooManyCustodians(u8),
+    #[error("Invalid required confirmations count: {}", .0)]
+    InvalidReqConfirms(u8),
+    #[error("Duplicate custodians")]
+    DuplicateCustodians,
+    #[error("Required confirmations count must be specified with custodians")]
+    ReqConfirmsNotSpecified,
 }
 
 #[cfg(test)]
@@ -741,4 +860,42 @@ mod tests {
             "0:3de70f9212154344a3158768b3fed731fc865ca15948b0d6d0d34daf4c6a7a0a"
         );
     }
+
+    #[test]
+    fn custodians_validation() {
+        let keys = (1..=3u8)
+            .map(|i| {
+                let secret = ed25519_dalek::SecretKey::from_bytes(&[i; 32]).unwrap();
+                PublicKey::from(&secret)
+            })
+            .collect::<Vec<_>>();
+
+        assert!(validate_custodians(&keys, 2, MAX_CUSTODIAN_COUNT).is_ok());
+        assert!(validate_custodians(&keys, 3, MAX_CUSTODIAN_COUNT).is_ok());
+
+        assert!(validate_custodians(&[], 1, MAX_CUSTODIAN_COUNT).is_err());
+        assert!(validate_custodians(&keys, 0, MAX_CUSTODIAN_COUNT).is_err());
+        assert!(validate_custodians(&keys, 4, MAX_CUSTODIAN_COUNT).is_err());
+        assert!(validate_custodians(&keys, 1, 2).is_err());
+
+        let duplicates = [keys[0], keys[1], keys[0]];
+        assert!(validate_custodians(&duplicates, 1, MAX_CUSTODIAN_COUNT).is_err());
+
+        let update = |req_confirms| {
+            prepare_update(
+                &SimpleClock,
+                MultisigType::Multisig2,
+                &keys[0],
+                MsgAddressInt::default(),
+                UpdateParams {
+                    owners: Some(keys.as_slice()),
+                    req_confirms,
+                    ..Default::default()
+                },
+                Expiration::Timeout(60),
+            )
+        };
+        assert!(update(Some(2)).is_ok());
+        assert!(update(None).is_err());
+    }
 }
@@ -9,7 +9,7 @@ use nekoton_utils::*;
 
 use super::{Gift, TonWalletDetails, TransferAction};
 use crate::core::models::{Expiration, ExpireAt};
-use crate::crypto::{SignedMessage, UnsignedMessage};
+use crate::crypto::{signed_data_cell, SignedMessage, UnsignedMessage};
 
 pub fn prepare_deploy(
     clock: &dyn Clock,
@@ -137,6 +137,10 @@ impl UnsignedMessage for UnsignedWallet {
         self.hash.as_slice()
     }
 
+    fn signed_data(&self) -> Option<Cell> {
+        signed_data_cell(self.payload.clone(), &self.hash)
+    }
+
     fn sign(&self, signature: &[u8; ed25519_dalek::SIGNATURE_LENGTH]) -> Result<SignedMessage> {
         let mut payload = self.payload.clone();
         payload.prepend_raw(signature, signature.len() * 8)?;
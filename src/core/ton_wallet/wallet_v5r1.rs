@@ -9,7 +9,7 @@ use nekoton_utils::*;
 
 use super::{Gift, TonWalletDetails, TransferAction};
 use crate::core::models::{Expiration, ExpireAt};
-use crate::crypto::{SignedMessage, UnsignedMessage};
+use crate::crypto::{signed_data_cell, SignedMessage, UnsignedMessage};
 
 const SIGNED_EXTERNAL_PREFIX: u32 = 0x7369676E;
 const SIGNED_INTERNAL_PREFIX: u32 = 0x73696E74;
@@ -147,6 +147,10 @@ impl UnsignedMessage for UnsignedWalletV5 {
         self.hash.as_slice()
     }
 
+    fn signed_data(&self) -> Option<Cell> {
+        signed_data_cell(self.payload.clone(), &self.hash)
+    }
+
     fn sign(&self, signature: &[u8; ed25519_dalek::SIGNATURE_LENGTH]) -> Result<SignedMessage> {
         let mut payload = self.payload.clone();
         payload.append_raw(signature, signature.len() * 8)?;
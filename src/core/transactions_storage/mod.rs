@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+use anyhow::Result;
+use nekoton_abi::TransactionId;
+use parking_lot::RwLock;
+use ton_block::MsgAddressInt;
+
+use super::models::Transaction;
+
+/// Persistent storage for the account transactions
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+pub trait TransactionsStorage: Send + Sync {
+    /// Inserts transactions, skipping already stored ones.
+    ///
+    /// Returns the number of newly inserted transactions
+    async fn add_transactions(
+        &self,
+        address: &MsgAddressInt,
+        transactions: &[Transaction],
+    ) -> Result<usize>;
+
+    /// Returns transactions which satisfy the filter, sorted by lt in descending order
+    async fn get_transactions(
+        &self,
+        address: &MsgAddressInt,
+        filter: &TransactionsFilter,
+    ) -> Result<Vec<Transaction>>;
+
+    /// Returns the id of the latest stored transaction
+    async fn get_latest_transaction_id(
+        &self,
+        address: &MsgAddressInt,
+    ) -> Result<Option<TransactionId>>;
+
+    /// Removes all transactions with lt less than `before_lt`.
+    ///
+    /// Returns the number of removed transactions
+    async fn prune(&self, address: &MsgAddressInt, before_lt: u64) -> Result<usize>;
+
+    /// Removes all account transactions
+    async fn remove_account(&self, address: &MsgAddressInt) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TransactionsFilter {
+    /// Logical time range
+    pub lt: Option<Range<u64>>,
+    /// Transaction unix timestamp range
+    pub utime: Option<Range<u32>>,
+    /// Max number of returned transactions
+    pub limit: Option<usize>,
+}
+
+impl TransactionsFilter {
+    fn matches(&self, transaction: &Transaction) -> bool {
+        let lt_matches = match &self.lt {
+            Some(range) => range.contains(&transaction.id.lt),
+            None => true,
+        };
+        let utime_matches = match &self.utime {
+            Some(range) => range.contains(&transaction.created_at),
+            None => true,
+        };
+        lt_matches && utime_matches
+    }
+}
+
+/// Simple storage which keeps all transactions in memory
+#[derive(Default)]
+pub struct InMemoryTransactionsStorage {
+    accounts: RwLock<HashMap<MsgAddressInt, BTreeMap<u64, Transaction>>>,
+    max_transactions_per_account: Option<usize>,
+}
+
+impl InMemoryTransactionsStorage {
+    /// Creates storage which keeps at most `max_transactions` latest transactions per account
+    pub fn with_capacity_limit(max_transactions: usize) -> Self {
+        Self {
+            accounts: Default::default(),
+            max_transactions_per_account: Some(max_transactions),
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl TransactionsStorage for InMemoryTransactionsStorage {
+    async fn add_transactions(
+        &self,
+        address: &MsgAddressInt,
+        transactions: &[Transaction],
+    ) -> Result<usize> {
+        let mut accounts = self.accounts.write();
+        let account = accounts.entry(address.clone()).or_default();
+
+        let mut inserted = 0;
+        for transaction in transactions {
+            if account.contains_key(&transaction.id.lt) {
+                continue;
+            }
+            account.insert(transaction.id.lt, transaction.clone());
+            inserted += 1;
+        }
+
+        if let Some(max_transactions) = self.max_transactions_per_account {
+            while account.len() > max_transactions {
+                let oldest = match account.keys().next() {
+                    Some(lt) => *lt,
+                    None => break,
+                };
+                account.remove(&oldest);
+            }
+        }
+
+        Ok(inserted)
+    }
+
+    async fn get_transactions(
+        &self,
+        address: &MsgAddressInt,
+        filter: &TransactionsFilter,
+    ) -> Result<Vec<Transaction>> {
+        let accounts = self.accounts.read();
+        let account = match accounts.get(address) {
+            Some(account) => account,
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(account
+            .values()
+            .rev()
+            .filter(|transaction| filter.matches(transaction))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_latest_transaction_id(
+        &self,
+        address: &MsgAddressInt,
+    ) -> Result<Option<TransactionId>> {
+        Ok(self
+            .accounts
+            .read()
+            .get(address)
+            .and_then(|account| account.values().next_back())
+            .map(|transaction| transaction.id))
+    }
+
+    async fn prune(&self, address: &MsgAddressInt, before_lt: u64) -> Result<usize> {
+        let mut accounts = self.accounts.write();
+        let account = match accounts.get_mut(address) {
+            Some(account) => account,
+            None => return Ok(0),
+        };
+
+        let retained = account.split_off(&before_lt);
+        let removed = account.len();
+        *account = retained;
+
+        Ok(removed)
+    }
+
+    async fn remove_account(&self, address: &MsgAddressInt) -> Result<()> {
+        self.accounts.write().remove(address);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use ton_block::Deserializable;
+    use ton_types::UInt256;
+
+    use super::*;
+
+    fn make_transaction(lt: u64, now: u32) -> Transaction {
+        let mut tx = ton_block::Transaction::construct_from_base64("te6ccgECCAEAAa0AA7V6khRTRyNmt/7uwVMjqWtdzxcZfIjcDUV436UpALijPLAAALi67rDsNGkd3DyaHde6qGSNyU7rxIrKKUFCg2XCiOWm8qj/wgcwAAC4uu6w7BYG3S5AABRh6EgIBQQBAhUECQ7msoAYYehIEQMCAFvAAAAAAAAAAAAAAAABLUUtpEnlC4z33SeGHxRhIq/htUa7i3D8ghbwxhQTn44EAJwnzD0JAAAAAAAAAAAAAwAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAgnI6oOa5hnaAmb92gCQ5BlaDDjAmDoH5UvSJNuK95TfvcajtZ/eBLgOpVpjmuIPSjgFKd0RzU/MXZ1uRuop7DU5HAQGgBgG5aAFSQopo5GzW/93YKmR1LWu54uMvkRuBqK8b9KUgFxRnlwAqSFFNHI2a3/u7BUyOpa13PFxl8iNwNRXjfpSkAuKM8tDuaygABhnMOAAAFxdd1h2EwNulyAAAAABABwAqaSBsb3ZlIG1lbWVzIGFuZCDwn6aA").unwrap();
+        tx.lt = lt;
+        tx.now = now;
+        Transaction::try_from((UInt256::from_be_bytes(&lt.to_be_bytes()), tx)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn in_memory_storage() {
+        let storage = InMemoryTransactionsStorage::with_capacity_limit(4);
+        let address = MsgAddressInt::default();
+
+        let transactions = (1..=3u64)
+            .map(|i| make_transaction(i * 10, i as u32 * 100))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            storage
+                .add_transactions(&address, &transactions)
+                .await
+                .unwrap(),
+            3
+        );
+
+        // Duplicates are skipped
+        let more = [make_transaction(30, 300), make_transaction(40, 400)];
+        assert_eq!(storage.add_transactions(&address, &more).await.unwrap(), 1);
+
+        let latest = storage.get_latest_transaction_id(&address).await.unwrap();
+        assert_eq!(latest.map(|id| id.lt), Some(40));
+
+        let all = storage
+            .get_transactions(&address, &Default::default())
+            .await
+            .unwrap();
+        let lts = all.iter().map(|tx| tx.id.lt).collect::<Vec<_>>();
+        assert_eq!(lts, [40, 30, 20, 10]);
+
+        let filter = TransactionsFilter {
+            utime: Some(150..350),
+            limit: Some(1),
+            ..Default::default()
+        };
+        let filtered = storage.get_transactions(&address, &filter).await.unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id.lt, 30);
+
+        // Oldest transactions are removed after reaching the limit
+        storage
+            .add_transactions(&address, &[make_transaction(50, 500)])
+            .await
+            .unwrap();
+        let filter = TransactionsFilter {
+            lt: Some(0..15),
+            ..Default::default()
+        };
+        assert!(storage
+            .get_transactions(&address, &filter)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(storage.prune(&address, 40).await.unwrap(), 2);
+        let all = storage
+            .get_transactions(&address, &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 2);
+
+        storage.remove_account(&address).await.unwrap();
+        assert!(storage
+            .get_latest_transaction_id(&address)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
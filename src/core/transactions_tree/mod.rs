@@ -14,6 +14,8 @@ pub struct TransactionsTreeStream {
     disable_signature_check: bool,
     unlimited_message_balance: bool,
     unlimited_account_balance: bool,
+    max_depth: Option<usize>,
+    max_message_value: Option<u128>,
     transport: Arc<dyn Transport>,
     clock: Arc<dyn Clock>,
 }
@@ -32,6 +34,8 @@ impl TransactionsTreeStream {
             disable_signature_check: false,
             unlimited_message_balance: false,
             unlimited_account_balance: false,
+            max_depth: None,
+            max_message_value: None,
             transport,
             clock,
         }
@@ -52,6 +56,18 @@ impl TransactionsTreeStream {
         self
     }
 
+    /// Limits the depth of the simulated tree. Used only in [`Self::simulate`]
+    pub fn max_depth(&mut self, depth: usize) -> &mut Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Fails the simulation if some message carries more than the specified value
+    pub fn max_message_value(&mut self, value: u128) -> &mut Self {
+        self.max_message_value = Some(value);
+        self
+    }
+
     pub fn message_queue(&self) -> &VecDeque<ton_block::Message> {
         &self.messages
     }
@@ -74,9 +90,84 @@ impl TransactionsTreeStream {
         self.messages.front()
     }
 
-    async fn step(&mut self, mut message: Message) -> TransactionTreeResult<Transaction> {
+    /// Executes all messages in the queue and returns the transactions tree.
+    ///
+    /// Messages deeper than `max_depth` are not executed and are returned
+    /// as `pending_messages` of the corresponding node
+    pub async fn simulate(mut self) -> TransactionTreeResult<Vec<TransactionTreeNode>> {
+        let mut nodes = Vec::<(Option<usize>, TransactionTreeNode)>::new();
+        let mut queue = self
+            .messages
+            .drain(..)
+            .map(|message| (None, 0, message))
+            .collect::<VecDeque<_>>();
+
+        while let Some((parent, depth, message)) = queue.pop_front() {
+            let transaction = self.execute(message).await?;
+
+            let index = nodes.len();
+            let mut pending_messages = Vec::new();
+            transaction
+                .iterate_out_msgs(|message| {
+                    if !message.is_internal() {
+                        return Ok(true);
+                    }
+                    match self.max_depth {
+                        Some(max_depth) if depth >= max_depth => pending_messages.push(message),
+                        _ => queue.push_back((Some(index), depth + 1, message)),
+                    }
+                    Ok(true)
+                })
+                .map_err(TransactionTreeError::ExecutionError)?;
+
+            nodes.push((
+                parent,
+                TransactionTreeNode {
+                    transaction,
+                    pending_messages,
+                    children: Vec::new(),
+                },
+            ));
+        }
+
+        // Children always follow their parents, so the tree can be assembled from the end
+        let mut roots = Vec::new();
+        while let Some((parent, mut node)) = nodes.pop() {
+            node.children.reverse();
+            match parent {
+                Some(parent) => nodes[parent].1.children.push(node),
+                None => roots.push(node),
+            }
+        }
+        roots.reverse();
+
+        Ok(roots)
+    }
+
+    async fn step(&mut self, message: Message) -> TransactionTreeResult<Transaction> {
+        let tx = self.execute(message).await?;
+
+        tx.iterate_out_msgs(|x| {
+            if x.is_internal() {
+                self.messages.push_back(x);
+            }
+            Ok(true)
+        })
+        .map_err(TransactionTreeError::ExecutionError)?;
+
+        Ok(tx)
+    }
+
+    async fn execute(&mut self, mut message: Message) -> TransactionTreeResult<Transaction> {
         const A_LOT: u64 = 1_000_000_000_000_000; // 1'000'000 ever
 
+        if let (Some(max_value), Some(header)) = (self.max_message_value, message.int_header()) {
+            let value = header.value.grams.as_u128();
+            if value > max_value {
+                return Err(TransactionTreeError::MessageValueLimitExceeded(value));
+            }
+        }
+
         if self.unlimited_message_balance {
             if let Some(header) = message.int_header_mut() {
                 header.value.grams = ton_block::Grams::from(A_LOT);
@@ -130,14 +221,6 @@ impl TransactionsTreeStream {
             },
         );
 
-        tx.iterate_out_msgs(|x| {
-            if x.is_internal() {
-                self.messages.push_back(x);
-            }
-            Ok(true)
-        })
-        .map_err(TransactionTreeError::ExecutionError)?;
-
         Ok(tx)
     }
 
@@ -170,6 +253,35 @@ impl TransactionsTreeStream {
     }
 }
 
+/// Simulated transaction with the transactions produced by its outgoing messages
+#[derive(Clone)]
+pub struct TransactionTreeNode {
+    pub transaction: Transaction,
+    /// Outgoing internal messages which were not executed due to the depth limit
+    pub pending_messages: Vec<Message>,
+    pub children: Vec<TransactionTreeNode>,
+}
+
+impl TransactionTreeNode {
+    /// Compute phase exit code, `None` if the compute phase was skipped
+    pub fn exit_code(&self) -> Option<i32> {
+        let descr = self.transaction.read_description().ok()?;
+        match descr.compute_phase_ref()? {
+            ton_block::TrComputePhase::Vm(phase) => Some(phase.exit_code),
+            ton_block::TrComputePhase::Skipped(_) => None,
+        }
+    }
+
+    /// Whether this transaction or any of its descendants was aborted
+    pub fn has_errors(&self) -> bool {
+        let aborted = match self.transaction.read_description() {
+            Ok(descr) => descr.is_aborted(),
+            Err(_) => true,
+        };
+        aborted || self.children.iter().any(Self::has_errors)
+    }
+}
+
 #[derive(Clone)]
 struct StoredAccount {
     account: Account,
@@ -183,6 +295,8 @@ type TransactionTreeResult<T> = Result<T, TransactionTreeError>;
 pub enum TransactionTreeError {
     #[error("External out message")]
     ExternalOutMessage,
+    #[error("Message value limit exceeded: {0}")]
+    MessageValueLimitExceeded(u128),
     #[error("Transport error: {0}")]
     TransportError(anyhow::Error),
     #[error("Execution error: {0}")]
@@ -2,6 +2,7 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use anyhow::Result;
@@ -14,7 +15,7 @@ use ton_types::{CellType, SliceData, UInt256};
 
 use crate::core::models::*;
 #[cfg(feature = "wallet_core")]
-use crate::crypto::{SignedMessage, UnsignedMessage};
+use crate::crypto::{signed_data_cell, SignedMessage, UnsignedMessage};
 use crate::transport::models::RawTransaction;
 use crate::transport::Transport;
 
@@ -34,19 +35,43 @@ pub fn request_transactions<'a>(
     initial_count: u8,
     limit: Option<usize>,
 ) -> impl Stream<Item = Result<Vec<RawTransaction>>> + 'a {
-    let initial_count = u8::min(initial_count, transport.info().max_transactions_per_fetch);
-    let fut = transport.get_transactions(address, from_lt, initial_count);
-
-    LatestTransactions {
-        address,
+    LatestTransactions::new(
+        transport.info().max_transactions_per_fetch,
+        Box::new(move |from_lt: u64, count: u8| -> TransactionsFut<'a> {
+            transport.get_transactions(address, from_lt, count)
+        }),
         from_lt,
         until_lt,
-        transport,
-        fut: Some(fut),
         initial_count,
-        total_fetched: 0,
         limit,
-    }
+    )
+}
+
+/// Same as [`request_transactions`], but the stream owns the transport
+/// and all pages contain at most `page_size` transactions
+pub fn request_transactions_owned(
+    transport: Arc<dyn Transport>,
+    address: MsgAddressInt,
+    from_lt: u64,
+    until_lt: Option<u64>,
+    page_size: u8,
+) -> impl Stream<Item = Result<Vec<RawTransaction>>> + 'static {
+    let page_size = page_size
+        .min(transport.info().max_transactions_per_fetch)
+        .max(1);
+
+    LatestTransactions::new(
+        page_size,
+        Box::new(move |from_lt: u64, count: u8| -> TransactionsFut<'static> {
+            let transport = transport.clone();
+            let address = address.clone();
+            Box::pin(async move { transport.get_transactions(&address, from_lt, count).await })
+        }),
+        from_lt,
+        until_lt,
+        page_size,
+        None,
+    )
 }
 
 #[derive(Debug)]
@@ -77,6 +102,27 @@ impl ParsedBlock {
     }
 }
 
+/// Returns account ids of all accounts which have transactions in the block
+pub fn block_touched_accounts(block: &ton_block::Block) -> Result<Vec<UInt256>> {
+    use ton_block::HashmapAugType;
+
+    let account_blocks = block
+        .extra
+        .read_struct()
+        .and_then(|extra| extra.read_account_blocks())
+        .map_err(|_| BlockParsingError::InvalidBlockStructure)?;
+
+    let mut accounts = Vec::new();
+    account_blocks
+        .iterate_with_keys(|account, _| {
+            accounts.push(account);
+            Ok(true)
+        })
+        .map_err(|_| BlockParsingError::InvalidBlockStructure)?;
+
+    Ok(accounts)
+}
+
 pub fn parse_block(
     address: &MsgAddressInt,
     contract_state: &ContractState,
@@ -180,16 +226,44 @@ pub enum BlockParsingError {
 type NewTransactions = (Vec<RawTransaction>, TransactionsBatchInfo);
 
 struct LatestTransactions<'a> {
-    address: &'a MsgAddressInt,
     from_lt: u64,
     until_lt: Option<u64>,
-    transport: &'a dyn Transport,
+    max_transactions_per_fetch: u8,
+    fetch: TransactionsFetch<'a>,
     fut: Option<TransactionsFut<'a>>,
     initial_count: u8,
     total_fetched: usize,
     limit: Option<usize>,
 }
 
+impl<'a> LatestTransactions<'a> {
+    fn new(
+        max_transactions_per_fetch: u8,
+        fetch: TransactionsFetch<'a>,
+        from_lt: u64,
+        until_lt: Option<u64>,
+        initial_count: u8,
+        limit: Option<usize>,
+    ) -> Self {
+        let initial_count = u8::min(initial_count, max_transactions_per_fetch);
+        let fut = fetch(from_lt, initial_count);
+
+        Self {
+            from_lt,
+            until_lt,
+            max_transactions_per_fetch,
+            fetch,
+            fut: Some(fut),
+            initial_count,
+            total_fetched: 0,
+            limit,
+        }
+    }
+}
+
+/// Requests transactions starting from the specified lt
+type TransactionsFetch<'a> = Box<dyn Fn(u64, u8) -> TransactionsFut<'a> + Send + Sync + 'a>;
+
 #[cfg(not(feature = "non_threadsafe"))]
 type TransactionsFut<'a> = Pin<Box<dyn Future<Output = Result<Vec<RawTransaction>>> + Send + 'a>>;
 #[cfg(feature = "non_threadsafe")]
@@ -262,11 +336,7 @@ impl Stream for LatestTransactions<'_> {
             let last = match new_transactions.last() {
                 Some(last) => last,
                 None if possibly_has_more => {
-                    self.fut = Some(self.transport.get_transactions(
-                        self.address,
-                        next_lt_from_response,
-                        self.initial_count,
-                    ));
+                    self.fut = Some((self.fetch)(next_lt_from_response, self.initial_count));
                     continue;
                 }
                 None => return Poll::Ready(None),
@@ -291,17 +361,13 @@ impl Stream for LatestTransactions<'_> {
                 }
                 Some(limit) => usize::min(
                     limit - self.total_fetched,
-                    self.transport.info().max_transactions_per_fetch as usize,
+                    self.max_transactions_per_fetch as usize,
                 ) as u8,
-                None => self.transport.info().max_transactions_per_fetch,
+                None => self.max_transactions_per_fetch,
             };
 
             // If there are some unprocessed transactions left we should request remaining
-            self.fut = Some(self.transport.get_transactions(
-                self.address,
-                last.data.prev_trans_lt,
-                next_count,
-            ));
+            self.fut = Some((self.fetch)(last.data.prev_trans_lt, next_count));
 
             // Return result
             return Poll::Ready(Some(Ok(new_transactions)));
@@ -427,6 +493,16 @@ impl UnsignedMessage for LabsUnsignedMessage {
         self.hash.as_slice()
     }
 
+    fn signed_data(&self) -> Option<ton_types::Cell> {
+        signed_data_cell(self.payload.clone(), &self.hash).or_else(|| {
+            // Since ABI 2.3 the destination address is signed together with the payload
+            let mut data = ton_types::BuilderData::new();
+            self.message.dst()?.write_to(&mut data).ok()?;
+            data.append_builder(&self.payload).ok()?;
+            signed_data_cell(data, &self.hash)
+        })
+    }
+
     fn sign(&self, signature: &[u8; ed25519_dalek::SIGNATURE_LENGTH]) -> Result<SignedMessage> {
         let payload = self.payload.clone();
         let payload = ton_abi::Function::fill_sign(
@@ -520,3 +596,159 @@ pub async fn update_library_cell(
 
     Ok(())
 }
+
+/// Network limits for the messages, see blockchain config param 43
+#[derive(Debug, Copy, Clone)]
+pub struct MessageLimits {
+    /// Max depth of the message cell tree
+    pub max_depth: u16,
+    /// Max number of unique cells in the message
+    pub max_cells: usize,
+    /// Max number of bits in unique cells of the message
+    pub max_bits: usize,
+    /// Max size of the serialized external message in bytes
+    pub max_ext_msg_size: usize,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 512,
+            max_cells: 1 << 13,
+            max_bits: 1 << 21,
+            max_ext_msg_size: 65535,
+        }
+    }
+}
+
+/// Checks the message against the network limits.
+///
+/// Nodes silently drop messages which exceed them, so it's better to check before broadcast
+pub fn validate_message(message: &ton_block::Message, limits: &MessageLimits) -> Result<()> {
+    let dst = match message.header() {
+        ton_block::CommonMsgInfo::IntMsgInfo(header) => &header.dst,
+        ton_block::CommonMsgInfo::ExtInMsgInfo(header) => &header.dst,
+        ton_block::CommonMsgInfo::ExtOutMsgInfo(_) => {
+            return Err(MessageValidationError::UnsupportedMessageType.into())
+        }
+    };
+    let has_anycast = match dst {
+        MsgAddressInt::AddrStd(addr) => addr.anycast.is_some(),
+        MsgAddressInt::AddrVar(addr) => addr.anycast.is_some(),
+    };
+    if has_anycast {
+        return Err(MessageValidationError::AnycastAddress.into());
+    }
+
+    let cell = message.serialize()?;
+
+    let depth = cell.repr_depth();
+    if depth > limits.max_depth {
+        return Err(MessageValidationError::TooDeep {
+            depth,
+            max_depth: limits.max_depth,
+        }
+        .into());
+    }
+
+    let mut visited = std::collections::HashSet::new();
+    let mut stack = vec![cell.clone()];
+    let (mut cells, mut bits) = (0, 0);
+    while let Some(cell) = stack.pop() {
+        if !visited.insert(cell.repr_hash()) {
+            continue;
+        }
+        cells += 1;
+        bits += cell.bit_length();
+        for i in 0..cell.references_count() {
+            stack.push(cell.reference(i)?);
+        }
+    }
+
+    if cells > limits.max_cells {
+        return Err(MessageValidationError::TooManyCells {
+            cells,
+            max_cells: limits.max_cells,
+        }
+        .into());
+    }
+    if bits > limits.max_bits {
+        return Err(MessageValidationError::TooManyBits {
+            bits,
+            max_bits: limits.max_bits,
+        }
+        .into());
+    }
+
+    if matches!(message.header(), ton_block::CommonMsgInfo::ExtInMsgInfo(_)) {
+        let size = ton_types::serialize_toc(&cell)?.len();
+        if size > limits.max_ext_msg_size {
+            return Err(MessageValidationError::TooLarge {
+                size,
+                max_size: limits.max_ext_msg_size,
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum MessageValidationError {
+    #[error("Only internal and external inbound messages can be sent")]
+    UnsupportedMessageType,
+    #[error("Anycast destination addresses are not supported")]
+    AnycastAddress,
+    #[error(
+        "Message cell tree is too deep ({depth} > {max_depth}), reduce the nesting of references"
+    )]
+    TooDeep { depth: u16, max_depth: u16 },
+    #[error("Message has too many cells ({cells} > {max_cells}), split the payload into several messages")]
+    TooManyCells { cells: usize, max_cells: usize },
+    #[error(
+        "Message has too many bits ({bits} > {max_bits}), split the payload into several messages"
+    )]
+    TooManyBits { bits: usize, max_bits: usize },
+    #[error("External message is too large ({size} > {max_size} bytes), move data into an internal message")]
+    TooLarge { size: usize, max_size: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use ton_types::BuilderData;
+
+    use super::*;
+
+    #[test]
+    fn validate_message_limits() {
+        let dst = MsgAddressInt::default();
+        let message = |body: Option<SliceData>| {
+            let mut message =
+                ton_block::Message::with_ext_in_header(ton_block::ExternalInboundMessageHeader {
+                    dst: dst.clone(),
+                    ..Default::default()
+                });
+            if let Some(body) = body {
+                message.set_body(body);
+            }
+            message
+        };
+
+        let limits = MessageLimits::default();
+        validate_message(&message(None), &limits).unwrap();
+
+        let mut deep = BuilderData::new().into_cell().unwrap();
+        for _ in 0..600 {
+            let mut builder = BuilderData::new();
+            builder.checked_append_reference(deep).unwrap();
+            deep = builder.into_cell().unwrap();
+        }
+        let err = validate_message(&message(Some(SliceData::load_cell(deep).unwrap())), &limits)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<MessageValidationError>(),
+            Some(MessageValidationError::TooDeep { .. })
+        ));
+    }
+}
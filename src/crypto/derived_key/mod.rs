@@ -3,21 +3,22 @@ use std::collections::hash_map::{self, HashMap};
 use anyhow::Result;
 use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce};
 use ed25519_dalek::{Keypair, PublicKey, Signer};
-use secstr::SecUtf8;
+use secstr::{SecUtf8, SecVec};
 use serde::{Deserialize, Serialize, Serializer};
 
 use nekoton_utils::*;
 
 use super::mnemonic::*;
 use super::{
-    default_key_name, extend_with_signature_id, Password, PasswordCache, PasswordCacheTransaction,
-    PubKey, SharedSecret, SignatureId, Signer as StoreSigner, SignerContext, SignerEntry,
-    SignerStorage,
+    default_key_name, extend_with_signature_id, KdfParams, KeyAlgorithm, Password, PasswordCache,
+    PasswordCacheTransaction, PubKey, SharedSecret, SignatureId, Signer as StoreSigner,
+    SignerContext, SignerEntry, SignerStorage,
 };
 
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
 pub struct DerivedKeySigner {
     master_keys: HashMap<[u8; 32], MasterKey>,
+    kdf_params: KdfParams,
 }
 
 impl DerivedKeySigner {
@@ -37,20 +38,27 @@ impl DerivedKeySigner {
         password_cache: &PasswordCache,
         input: DerivedKeyPassword,
     ) -> Result<Keypair> {
-        let (master_key, account_id, password) = match input {
+        let (master_key, account_id, password, passphrase) = match input {
             DerivedKeyPassword::ByAccountId {
                 master_key,
                 account_id,
                 password,
-            } => (self.get_master_key(&master_key)?, account_id, password),
+                passphrase,
+            } => (
+                self.get_master_key(&master_key)?,
+                account_id,
+                password,
+                passphrase,
+            ),
             DerivedKeyPassword::ByPublicKey {
                 master_key,
                 public_key,
                 password,
+                passphrase,
             } => {
                 let master_key = self.get_master_key(&master_key)?;
                 match master_key.accounts_map.get(public_key.as_bytes()) {
-                    Some(account) => (master_key, account.account_id, password),
+                    Some(account) => (master_key, account.account_id, password, passphrase),
                     None => return Err(MasterKeyError::DerivedKeyNotFound.into()),
                 }
             }
@@ -59,16 +67,7 @@ impl DerivedKeySigner {
         let password =
             password_cache.process_password(master_key.public_key.to_bytes(), password)?;
 
-        let decrypter = ChaCha20Poly1305::new(&symmetric_key_from_password(
-            password.as_ref(),
-            &master_key.salt,
-        ));
-
-        let master = decrypt_secure(
-            &decrypter,
-            &master_key.entropy_nonce,
-            &master_key.enc_entropy,
-        )?;
+        let master = master_key.decrypt_seed(password.as_ref(), passphrase.as_ref())?;
 
         let signer = derive_from_master(account_id, master.unsecure())?;
 
@@ -98,10 +97,17 @@ impl StoreSigner for DerivedKeySigner {
             DerivedKeyCreateInput::Import {
                 phrase,
                 password,
+                passphrase,
                 key_name,
             } => {
-                let (master_key, key_name, password) =
-                    MasterKey::new(ctx.password_cache, password, phrase, key_name)?;
+                let (master_key, key_name, password) = MasterKey::new(
+                    ctx.password_cache,
+                    password,
+                    phrase,
+                    passphrase,
+                    key_name,
+                    self.kdf_params,
+                )?;
                 let public_key = master_key.public_key;
 
                 match self.master_keys.entry(public_key.to_bytes()) {
@@ -115,6 +121,8 @@ impl StoreSigner for DerivedKeySigner {
                         existing.entropy_nonce = master_key.entropy_nonce;
                         existing.enc_phrase = master_key.enc_phrase;
                         existing.phrase_nonce = master_key.phrase_nonce;
+                        existing.has_passphrase = master_key.has_passphrase;
+                        existing.kdf = master_key.kdf;
 
                         match existing.accounts_map.entry(public_key.to_bytes()) {
                             hash_map::Entry::Vacant(entry) => {
@@ -136,12 +144,14 @@ impl StoreSigner for DerivedKeySigner {
                     public_key,
                     master_key: public_key,
                     account_id: 0,
+                    algorithm: KeyAlgorithm::Ed25519,
                 }
             }
             DerivedKeyCreateInput::Derive {
                 master_key,
                 account_id,
                 password,
+                passphrase,
                 key_name,
             } => {
                 let master_key = match self.master_keys.get_mut(master_key.as_bytes()) {
@@ -161,16 +171,7 @@ impl StoreSigner for DerivedKeySigner {
                     .password_cache
                     .process_password(master_key.public_key.to_bytes(), password)?;
 
-                let decrypter = ChaCha20Poly1305::new(&symmetric_key_from_password(
-                    password.as_ref(),
-                    &master_key.salt,
-                ));
-
-                let master = decrypt_secure(
-                    &decrypter,
-                    &master_key.entropy_nonce,
-                    &master_key.enc_entropy,
-                )?;
+                let master = master_key.decrypt_seed(password.as_ref(), passphrase.as_ref())?;
 
                 let public_key = derive_from_master(account_id, master.unsecure())?.public;
 
@@ -190,6 +191,7 @@ impl StoreSigner for DerivedKeySigner {
                     public_key,
                     master_key: master_key.public_key,
                     account_id,
+                    algorithm: KeyAlgorithm::Ed25519,
                 }
             }
         })
@@ -223,6 +225,7 @@ impl StoreSigner for DerivedKeySigner {
                     public_key,
                     master_key,
                     account_id: entry.account_id,
+                    algorithm: KeyAlgorithm::Ed25519,
                 })
             }
             Self::UpdateKeyInput::ChangePassword {
@@ -242,7 +245,11 @@ impl StoreSigner for DerivedKeySigner {
                     .password_cache
                     .process_password(master_key.to_bytes(), new_password)?;
 
-                entry.change_password(old_password.as_ref(), new_password.as_ref())?;
+                entry.change_password(
+                    old_password.as_ref(),
+                    new_password.as_ref(),
+                    self.kdf_params,
+                )?;
 
                 let name = entry
                     .accounts_map
@@ -256,6 +263,7 @@ impl StoreSigner for DerivedKeySigner {
                     public_key: entry.public_key,
                     master_key: entry.public_key,
                     account_id: 0,
+                    algorithm: KeyAlgorithm::Ed25519,
                 })
             }
         }
@@ -275,10 +283,7 @@ impl StoreSigner for DerivedKeySigner {
             .password_cache
             .process_password(master_key.public_key.to_bytes(), input.password)?;
 
-        let decrypter = ChaCha20Poly1305::new(&symmetric_key_from_password(
-            password.as_ref(),
-            &master_key.salt,
-        ));
+        let decrypter = ChaCha20Poly1305::new(&master_key.derive_key(password.as_ref())?);
 
         let phrase = decrypt_secure(&decrypter, &master_key.phrase_nonce, &master_key.enc_phrase)?;
 
@@ -310,16 +315,7 @@ impl StoreSigner for DerivedKeySigner {
             .password_cache
             .process_password(master_key.public_key.to_bytes(), input.password)?;
 
-        let decrypter = ChaCha20Poly1305::new(&symmetric_key_from_password(
-            password.as_ref(),
-            &master_key.salt,
-        ));
-
-        let master = decrypt_secure(
-            &decrypter,
-            &master_key.entropy_nonce,
-            &master_key.enc_entropy,
-        )?;
+        let master = master_key.decrypt_seed(password.as_ref(), input.passphrase.as_ref())?;
 
         let public_keys = (input.offset..input.offset.saturating_add(input.limit))
             .map(|account_id| {
@@ -362,6 +358,38 @@ impl StoreSigner for DerivedKeySigner {
         let data = extend_with_signature_id(data, signature_id);
         Ok(keypair.sign(&data).to_bytes())
     }
+
+    fn upgrade_key(&mut self, ctx: SignerContext<'_>, input: &Self::SignInput) -> Result<bool> {
+        let (master_key, password) = match input {
+            DerivedKeyPassword::ByAccountId {
+                master_key,
+                password,
+                ..
+            }
+            | DerivedKeyPassword::ByPublicKey {
+                master_key,
+                password,
+                ..
+            } => (master_key, password),
+        };
+
+        let master_key = match self.master_keys.get_mut(master_key.as_bytes()) {
+            Some(key) => key,
+            None => return Err(MasterKeyError::MasterKeyNotFound.into()),
+        };
+        if master_key.kdf == self.kdf_params {
+            return Ok(false);
+        }
+
+        let password = ctx
+            .password_cache
+            .process_password(master_key.public_key.to_bytes(), password.clone())?;
+
+        master_key.change_password(password.as_ref(), password.as_ref(), self.kdf_params)?;
+
+        password.proceed();
+        Ok(true)
+    }
 }
 
 #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
@@ -394,6 +422,10 @@ impl SignerStorage for DerivedKeySigner {
         Ok(())
     }
 
+    fn set_kdf_params(&mut self, kdf_params: KdfParams) {
+        self.kdf_params = kdf_params;
+    }
+
     fn store_state(&self) -> String {
         struct StoredMasterKeys<'a>(&'a HashMap<[u8; 32], MasterKey>);
 
@@ -436,6 +468,7 @@ impl SignerStorage for DerivedKeySigner {
                         public_key: PublicKey::from_bytes(public_key).trust_me(),
                         master_key,
                         account_id: account_id.account_id,
+                        algorithm: KeyAlgorithm::Ed25519,
                     })
             })
             .collect()
@@ -449,6 +482,7 @@ impl SignerStorage for DerivedKeySigner {
                     public_key: *public_key,
                     master_key: master_key.public_key,
                     account_id: account.account_id,
+                    algorithm: KeyAlgorithm::Ed25519,
                 });
             }
         }
@@ -484,6 +518,16 @@ struct MasterKey {
     #[serde(with = "serde_nonce")]
     phrase_nonce: Nonce,
 
+    /// Whether the seed is derived with a BIP-39 passphrase.
+    ///
+    /// Such seed is not stored and is derived from the phrase on each use
+    #[serde(default)]
+    has_passphrase: bool,
+
+    /// Params, used to derive the encryption key from the password
+    #[serde(default)]
+    kdf: KdfParams,
+
     #[serde(with = "serde_accounts_map")]
     accounts_map: AccountsMap,
 }
@@ -493,32 +537,44 @@ impl MasterKey {
         password_cache: &'_ PasswordCache,
         password: Password,
         phrase: SecUtf8,
+        passphrase: Option<SecUtf8>,
         key_name: Option<String>,
+        kdf: KdfParams,
     ) -> Result<(Self, String, PasswordCacheTransaction<'_>)> {
         use zeroize::Zeroize;
 
         let mut phrase = phrase.unsecure().to_string();
+        let passphrase = passphrase.filter(|passphrase| !passphrase.unsecure().is_empty());
+        let has_passphrase = passphrase.is_some();
+
+        let mut seed = labs::derive_master_key_with_passphrase(
+            &phrase,
+            passphrase
+                .as_ref()
+                .map(SecUtf8::unsecure)
+                .unwrap_or_default(),
+        )?;
 
         // SECURITY: private key will be zeroized here
-        let public_key =
-            derive_from_phrase(&phrase, MnemonicType::Bip39(Bip39MnemonicData::labs_old(0)))?
-                .public;
+        let public_key = derive_from_master(0, &seed)?.public;
 
         let key_name = key_name.unwrap_or_else(|| default_key_name(public_key.as_bytes()));
 
         let password = password_cache.process_password(public_key.to_bytes(), password)?;
 
-        let mut entropy = labs::derive_master_key(&phrase)?;
+        // NOTE: seed with passphrase must not be usable without it
+        let entropy: &[u8] = if has_passphrase { &[] } else { &seed };
+
         let EncryptedPart {
             salt,
             enc_entropy,
             entropy_nonce,
             enc_phrase,
             phrase_nonce,
-        } = compute_encrypted_part(&entropy, phrase.as_bytes(), password.as_ref())?;
+        } = compute_encrypted_part(entropy, phrase.as_bytes(), password.as_ref(), kdf)?;
 
         phrase.zeroize();
-        entropy.zeroize();
+        seed.zeroize();
 
         let mut accounts_map = AccountsMap::new();
         accounts_map.insert(
@@ -537,6 +593,8 @@ impl MasterKey {
                 entropy_nonce,
                 enc_phrase,
                 phrase_nonce,
+                has_passphrase,
+                kdf,
                 accounts_map,
             },
             key_name,
@@ -544,26 +602,74 @@ impl MasterKey {
         ))
     }
 
-    fn change_password(&mut self, old_password: &str, new_password: &str) -> Result<()> {
-        let decrypter =
-            ChaCha20Poly1305::new(&symmetric_key_from_password(old_password, &self.salt));
+    fn derive_key(&self, password: &str) -> Result<chacha20poly1305::Key> {
+        self.kdf.derive_key(password, &self.salt)
+    }
+
+    /// Decrypts the master seed (or derives it from the phrase and the passphrase)
+    fn decrypt_seed(&self, password: &str, passphrase: Option<&SecUtf8>) -> Result<SecVec<u8>> {
+        let decrypter = ChaCha20Poly1305::new(&self.derive_key(password)?);
+
+        if !self.has_passphrase {
+            if matches!(passphrase, Some(passphrase) if !passphrase.unsecure().is_empty()) {
+                return Err(MasterKeyError::UnexpectedPassphrase.into());
+            }
+            return Ok(decrypt_secure(
+                &decrypter,
+                &self.entropy_nonce,
+                &self.enc_entropy,
+            )?);
+        }
+
+        let passphrase = match passphrase {
+            Some(passphrase) if !passphrase.unsecure().is_empty() => passphrase,
+            _ => return Err(MasterKeyError::PassphraseRequired.into()),
+        };
+
+        let phrase = decrypt_secure_str(&decrypter, &self.phrase_nonce, &self.enc_phrase)?;
+        let seed = SecVec::new(
+            labs::derive_master_key_with_passphrase(phrase.unsecure(), passphrase.unsecure())?
+                .to_vec(),
+        );
+
+        // Any passphrase produces a valid seed, so it is checked by the master key
+        if derive_from_master(0, seed.unsecure())?.public != self.public_key {
+            return Err(MasterKeyError::InvalidPassphrase.into());
+        }
+
+        Ok(seed)
+    }
+
+    fn change_password(
+        &mut self,
+        old_password: &str,
+        new_password: &str,
+        kdf: KdfParams,
+    ) -> Result<()> {
+        let decrypter = ChaCha20Poly1305::new(&self.derive_key(old_password)?);
 
         let entropy = decrypt_secure(&decrypter, &self.entropy_nonce, &self.enc_entropy)?;
         let phrase = decrypt_secure(&decrypter, &self.phrase_nonce, &self.enc_phrase)?;
 
         let encrypted_part =
-            compute_encrypted_part(entropy.unsecure(), phrase.unsecure(), new_password)?;
+            compute_encrypted_part(entropy.unsecure(), phrase.unsecure(), new_password, kdf)?;
         self.salt = encrypted_part.salt;
         self.enc_entropy = encrypted_part.enc_entropy;
         self.entropy_nonce = encrypted_part.entropy_nonce;
         self.enc_phrase = encrypted_part.enc_phrase;
         self.phrase_nonce = encrypted_part.phrase_nonce;
+        self.kdf = kdf;
 
         Ok(())
     }
 }
 
-fn compute_encrypted_part(entropy: &[u8], phrase: &[u8], password: &str) -> Result<EncryptedPart> {
+fn compute_encrypted_part(
+    entropy: &[u8],
+    phrase: &[u8],
+    password: &str,
+    kdf: KdfParams,
+) -> Result<EncryptedPart> {
     use rand::Rng;
 
     let rng = &mut rand::thread_rng();
@@ -580,7 +686,7 @@ fn compute_encrypted_part(entropy: &[u8], phrase: &[u8], password: &str) -> Resu
     let entropy_nonce = Nonce::from(entropy_nonce);
     let phrase_nonce = Nonce::from(phrase_nonce);
 
-    let encryptor = ChaCha20Poly1305::new(&symmetric_key_from_password(password, &salt));
+    let encryptor = ChaCha20Poly1305::new(&kdf.derive_key(password, &salt)?);
     let enc_entropy = encrypt(&encryptor, &entropy_nonce, entropy)?;
     let enc_phrase = encrypt(&encryptor, &phrase_nonce, phrase)?;
 
@@ -617,6 +723,9 @@ pub enum DerivedKeyPassword {
         master_key: PublicKey,
         account_id: u16,
         password: Password,
+        /// BIP-39 passphrase, required if the key was imported with it
+        #[serde(default)]
+        passphrase: Option<SecUtf8>,
     },
     ByPublicKey {
         #[serde(with = "serde_public_key")]
@@ -624,6 +733,9 @@ pub enum DerivedKeyPassword {
         #[serde(with = "serde_public_key")]
         public_key: PublicKey,
         password: Password,
+        /// BIP-39 passphrase, required if the key was imported with it
+        #[serde(default)]
+        passphrase: Option<SecUtf8>,
     },
 }
 
@@ -644,6 +756,9 @@ pub struct DerivedKeyGetPublicKeys {
     #[serde(with = "serde_public_key")]
     pub master_key: PublicKey,
     pub password: Password,
+    /// BIP-39 passphrase, required if the key was imported with it
+    #[serde(default)]
+    pub passphrase: Option<SecUtf8>,
     pub limit: u16,
     pub offset: u16,
 }
@@ -673,6 +788,11 @@ pub enum DerivedKeyCreateInput {
         key_name: Option<String>,
         phrase: SecUtf8,
         password: Password,
+        /// Optional BIP-39 passphrase ("25th word").
+        ///
+        /// It is not stored and must be specified for each operation with this key
+        #[serde(default)]
+        passphrase: Option<SecUtf8>,
     },
     Derive {
         key_name: Option<String>,
@@ -680,6 +800,9 @@ pub enum DerivedKeyCreateInput {
         master_key: PublicKey,
         account_id: u16,
         password: Password,
+        /// BIP-39 passphrase, required if the key was imported with it
+        #[serde(default)]
+        passphrase: Option<SecUtf8>,
     },
 }
 
@@ -771,6 +894,12 @@ enum MasterKeyError {
     DerivationError,
     #[error("Derived key already exists")]
     DerivedKeyExists,
+    #[error("BIP-39 passphrase is required for this key")]
+    PassphraseRequired,
+    #[error("Invalid BIP-39 passphrase")]
+    InvalidPassphrase,
+    #[error("This key doesn't use a BIP-39 passphrase")]
+    UnexpectedPassphrase,
 }
 
 #[cfg(test)]
@@ -797,6 +926,7 @@ mod tests {
             .add_key(
                 ctx,
                 DerivedKeyCreateInput::Import {
+                    passphrase: None,
                     key_name: Some("Key".to_owned()),
                     phrase: SecUtf8::from(TEST_PHRASE),
                     password: Password::Explicit {
@@ -824,6 +954,7 @@ mod tests {
             .export_keypair(
                 ctx,
                 DerivedKeyPassword::ByAccountId {
+                    passphrase: None,
                     master_key: entry.master_key,
                     account_id: 0,
                     password: Password::FromCache,
@@ -837,6 +968,7 @@ mod tests {
             .add_key(
                 ctx,
                 DerivedKeyCreateInput::Import {
+                    passphrase: None,
                     key_name: Some("Key 2".to_owned()),
                     phrase: SecUtf8::from(TEST_PHRASE),
                     password: Password::Explicit {
@@ -879,6 +1011,7 @@ mod tests {
             .add_key(
                 ctx,
                 DerivedKeyCreateInput::Import {
+                    passphrase: None,
                     key_name: Some("Key".to_owned()),
                     phrase: SecUtf8::from(TEST_PHRASE),
                     password: Password::Explicit {
@@ -927,6 +1060,90 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn bip39_passphrase() {
+        let mut signer = DerivedKeySigner::new();
+
+        let cache = PasswordCache::new();
+        let ctx = SignerContext {
+            password_cache: &cache,
+        };
+
+        let password = || Password::Explicit {
+            password: SecUtf8::from("123"),
+            cache_behavior: Default::default(),
+        };
+        let sign_input = |master_key, passphrase: Option<&str>| DerivedKeyPassword::ByAccountId {
+            master_key,
+            account_id: 0,
+            password: password(),
+            passphrase: passphrase.map(SecUtf8::from),
+        };
+
+        let plain = signer
+            .add_key(
+                ctx,
+                DerivedKeyCreateInput::Import {
+                    key_name: None,
+                    phrase: SecUtf8::from(TEST_PHRASE),
+                    password: password(),
+                    passphrase: None,
+                },
+            )
+            .await
+            .unwrap();
+        let protected = signer
+            .add_key(
+                ctx,
+                DerivedKeyCreateInput::Import {
+                    key_name: None,
+                    phrase: SecUtf8::from(TEST_PHRASE),
+                    password: password(),
+                    passphrase: Some(SecUtf8::from("25th word")),
+                },
+            )
+            .await
+            .unwrap();
+        assert_ne!(plain.public_key, protected.public_key);
+
+        let seed = labs::derive_master_key_with_passphrase(TEST_PHRASE, "25th word").unwrap();
+        assert_eq!(
+            protected.public_key,
+            derive_from_master(0, &seed).unwrap().public
+        );
+
+        // Passphrase is not stored
+        let err = signer
+            .export_keypair(ctx, sign_input(protected.master_key, None))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "BIP-39 passphrase is required for this key"
+        );
+
+        let err = signer
+            .export_keypair(ctx, sign_input(protected.master_key, Some("wrong")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid BIP-39 passphrase");
+
+        let keypair = signer
+            .export_keypair(ctx, sign_input(protected.master_key, Some("25th word")))
+            .await
+            .unwrap();
+        assert_eq!(keypair.public, protected.public_key);
+
+        assert!(signer
+            .export_keypair(ctx, sign_input(plain.master_key, Some("25th word")))
+            .await
+            .is_err());
+        signer
+            .export_keypair(ctx, sign_input(plain.master_key, None))
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn migrate_accounts_map() {
         use serde::{Deserialize, Serialize};
@@ -958,6 +1175,7 @@ mod tests {
             .add_key(
                 ctx,
                 DerivedKeyCreateInput::Import {
+                    passphrase: None,
                     key_name: Some("from giver".into()),
                     phrase: TEST_PHRASE.into(),
                     password: Password::Explicit {
@@ -972,6 +1190,7 @@ mod tests {
         key.add_key(
             ctx,
             DerivedKeyCreateInput::Derive {
+                passphrase: None,
                 key_name: Some("all my money 🤑".into()),
                 master_key: master,
                 password: Password::Explicit {
@@ -986,6 +1205,7 @@ mod tests {
         key.add_key(
             ctx,
             DerivedKeyCreateInput::Derive {
+                passphrase: None,
                 key_name: Some("史萊克的模因.".into()),
                 master_key: master,
                 password: Password::Explicit {
@@ -13,14 +13,15 @@ use nekoton_utils::*;
 
 use super::mnemonic::*;
 use super::{
-    default_key_name, extend_with_signature_id, Password, PasswordCache, PasswordCacheTransaction,
-    PubKey, SharedSecret, SignatureId, Signer as StoreSigner, SignerContext, SignerEntry,
-    SignerStorage,
+    default_key_name, extend_with_signature_id, KdfParams, KeyAlgorithm, Password, PasswordCache,
+    PasswordCacheTransaction, PubKey, SharedSecret, SignatureId, Signer as StoreSigner,
+    SignerContext, SignerEntry, SignerStorage,
 };
 
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
 pub struct EncryptedKeySigner {
     keys: KeysMap,
+    kdf_params: KdfParams,
 }
 
 type KeysMap = HashMap<PubKey, EncryptedKey>;
@@ -68,6 +69,7 @@ impl StoreSigner for EncryptedKeySigner {
             input.mnemonic_type,
             input.phrase,
             input.name,
+            self.kdf_params,
         )?;
 
         let public_key = *key.public_key();
@@ -81,6 +83,7 @@ impl StoreSigner for EncryptedKeySigner {
             public_key,
             master_key: public_key,
             account_id: input.mnemonic_type.account_id(),
+            algorithm: KeyAlgorithm::Ed25519,
         })
     }
 
@@ -98,6 +101,7 @@ impl StoreSigner for EncryptedKeySigner {
                     public_key,
                     master_key: public_key,
                     account_id: key.mnemonic_type().account_id(),
+                    algorithm: KeyAlgorithm::Ed25519,
                 })
             }
             Self::UpdateKeyInput::ChangePassword {
@@ -112,8 +116,9 @@ impl StoreSigner for EncryptedKeySigner {
                     .password_cache
                     .process_password(public_key.to_bytes(), new_password)?;
 
+                let kdf_params = self.kdf_params;
                 let key = self.get_key_mut(&public_key)?;
-                key.change_password(old_password.as_ref(), new_password.as_ref())?;
+                key.change_password(old_password.as_ref(), new_password.as_ref(), kdf_params)?;
 
                 new_password.proceed();
 
@@ -122,6 +127,7 @@ impl StoreSigner for EncryptedKeySigner {
                     public_key,
                     master_key: public_key,
                     account_id: key.mnemonic_type().account_id(),
+                    algorithm: KeyAlgorithm::Ed25519,
                 })
             }
         }
@@ -208,6 +214,23 @@ impl StoreSigner for EncryptedKeySigner {
         password.proceed();
         Ok(signature)
     }
+
+    fn upgrade_key(&mut self, ctx: SignerContext<'_>, input: &Self::SignInput) -> Result<bool> {
+        let kdf_params = self.kdf_params;
+        let key = self.get_key_mut(&input.public_key)?;
+        if key.kdf_params() == kdf_params {
+            return Ok(false);
+        }
+
+        let password = ctx
+            .password_cache
+            .process_password(input.public_key.to_bytes(), input.password.clone())?;
+
+        key.change_password(password.as_ref(), password.as_ref(), kdf_params)?;
+
+        password.proceed();
+        Ok(true)
+    }
 }
 
 #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
@@ -230,6 +253,10 @@ impl SignerStorage for EncryptedKeySigner {
         Ok(())
     }
 
+    fn set_kdf_params(&mut self, kdf_params: KdfParams) {
+        self.kdf_params = kdf_params;
+    }
+
     fn store_state(&self) -> String {
         use serde::ser::SerializeSeq;
 
@@ -263,6 +290,7 @@ impl SignerStorage for EncryptedKeySigner {
                 public_key: *key.public_key(),
                 master_key: *key.public_key(),
                 account_id: key.inner.mnemonic_type.account_id(),
+                algorithm: KeyAlgorithm::Ed25519,
             })
             .collect()
     }
@@ -274,6 +302,7 @@ impl SignerStorage for EncryptedKeySigner {
             public_key: entry.inner.pubkey,
             master_key: entry.inner.pubkey,
             account_id: entry.inner.mnemonic_type.account_id(),
+            algorithm: KeyAlgorithm::Ed25519,
         })
     }
 
@@ -339,6 +368,7 @@ impl EncryptedKey {
         mnemonic_type: MnemonicType,
         phrase: SecUtf8,
         name: Option<String>,
+        kdf_params: KdfParams,
     ) -> Result<(Self, PasswordCacheTransaction<'_>)> {
         let rng = &mut rand::thread_rng();
 
@@ -357,8 +387,7 @@ impl EncryptedKey {
         let password = password_cache.process_password(keypair.public.to_bytes(), password)?;
 
         // prepare encryptor
-        let encryptor =
-            ChaCha20Poly1305::new(&symmetric_key_from_password(password.as_ref(), &salt));
+        let encryptor = ChaCha20Poly1305::new(&kdf_params.derive_key(password.as_ref(), &salt)?);
 
         // encrypt private key
         let pubkey = keypair.public;
@@ -379,6 +408,7 @@ impl EncryptedKey {
                     encrypted_seed_phrase,
                     seed_phrase_nonce,
                     salt,
+                    kdf: kdf_params,
                 },
             },
             password,
@@ -386,8 +416,7 @@ impl EncryptedKey {
     }
 
     pub fn get_mnemonic(&self, password: &str) -> Result<SecUtf8, EncryptedKeyError> {
-        let salt = &self.inner.salt;
-        let password = symmetric_key_from_password(password, salt);
+        let password = self.inner.derive_key(password)?;
         let dec = ChaCha20Poly1305::new(&password);
         let data = decrypt_secure(
             &dec,
@@ -402,7 +431,7 @@ impl EncryptedKey {
     }
 
     pub fn get_key_pair(&self, password: &str) -> Result<Keypair, EncryptedKeyError> {
-        let password = symmetric_key_from_password(password, &self.inner.salt);
+        let password = self.inner.derive_key(password)?;
         decrypt_key_pair(
             &self.inner.encrypted_private_key,
             &password,
@@ -418,7 +447,12 @@ impl EncryptedKey {
         Ok(EncryptedKey { inner: crypto_data })
     }
 
-    pub fn change_password(&mut self, old_password: &str, new_password: &str) -> Result<()> {
+    pub fn change_password(
+        &mut self,
+        old_password: &str,
+        new_password: &str,
+        kdf_params: KdfParams,
+    ) -> Result<()> {
         let rng = &mut rand::thread_rng();
 
         // prepare nonce
@@ -429,8 +463,8 @@ impl EncryptedKey {
         rng.fill(new_salt.as_mut_slice());
 
         // prepare encryptor/decrypter pair
-        let old_key = symmetric_key_from_password(old_password, &self.inner.salt);
-        let new_key = symmetric_key_from_password(new_password, &new_salt);
+        let old_key = self.inner.derive_key(old_password)?;
+        let new_key = kdf_params.derive_key(new_password, &new_salt)?;
 
         let decrypter = ChaCha20Poly1305::new(&old_key);
         let encryptor = ChaCha20Poly1305::new(&new_key);
@@ -457,6 +491,7 @@ impl EncryptedKey {
 
         // save new data
         self.inner.salt = new_salt;
+        self.inner.kdf = kdf_params;
 
         self.inner.encrypted_private_key = new_encrypted_private_key;
         self.inner.private_key_nonce = new_private_key_nonce;
@@ -493,6 +528,11 @@ impl EncryptedKey {
         self.inner.mnemonic_type
     }
 
+    /// Params, used to derive the encryption key from the password
+    pub fn kdf_params(&self) -> KdfParams {
+        self.inner.kdf
+    }
+
     pub fn as_json(&self) -> String {
         serde_json::to_string(&self.inner).trust_me()
     }
@@ -526,6 +566,8 @@ struct CryptoData {
 
     #[serde(with = "serde_bytes")]
     salt: Vec<u8>,
+
+    kdf: KdfParams,
 }
 
 impl CryptoData {
@@ -564,8 +606,14 @@ impl CryptoData {
             .collect())
     }
 
+    fn derive_key(&self, password: &str) -> Result<Key, EncryptedKeyError> {
+        self.kdf
+            .derive_key(password, &self.salt)
+            .map_err(|_| EncryptedKeyError::InvalidKdfParams)
+    }
+
     fn decrypt_secret(&self, password: &str) -> Result<ed25519_dalek::SecretKey> {
-        let key = symmetric_key_from_password(password, &self.salt);
+        let key = self.derive_key(password)?;
         let decrypter = ChaCha20Poly1305::new(&key);
 
         let bytes = decrypt_secure(
@@ -603,6 +651,8 @@ impl<'de> Deserialize<'de> for CryptoData {
             seed_phrase_nonce: Nonce,
             #[serde(with = "serde_bytes")]
             salt: Vec<u8>,
+            #[serde(default)]
+            kdf: KdfParams,
         }
 
         let data = StoredCryptoData::deserialize(deserializer)?;
@@ -620,6 +670,7 @@ impl<'de> Deserialize<'de> for CryptoData {
             encrypted_seed_phrase: data.encrypted_seed_phrase,
             seed_phrase_nonce: data.seed_phrase_nonce,
             salt: data.salt,
+            kdf: data.kdf,
         })
     }
 }
@@ -648,6 +699,8 @@ pub enum EncryptedKeyError {
     KeyNotFound,
     #[error("Invalid public key")]
     InvalidPublicKey,
+    #[error("Invalid key derivation params")]
+    InvalidKdfParams,
 }
 
 impl From<SymmetricCryptoError> for EncryptedKeyError {
@@ -683,6 +736,7 @@ mod tests {
             MnemonicType::Legacy,
             TEST_MNEMONIC.into(),
             Some("Test".to_owned()),
+            Default::default(),
         )
         .unwrap();
     }
@@ -701,6 +755,7 @@ mod tests {
             MnemonicType::Legacy,
             TEST_MNEMONIC.into(),
             Some("Test".to_owned()),
+            Default::default(),
         )
         .unwrap();
 
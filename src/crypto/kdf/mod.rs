@@ -0,0 +1,201 @@
+use anyhow::Result;
+use chacha20poly1305::Key;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Parameters of the key derivation function, used to encrypt signer entries
+/// with the user password.
+///
+/// Parameters are stored in the entry metadata, so entries encrypted
+/// with different parameters can coexist in one keystore
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum KdfParams {
+    Pbkdf2 {
+        /// Number of iterations
+        iterations: u32,
+    },
+    Argon2id {
+        /// Memory size in KiB
+        memory_cost: u32,
+        /// Number of iterations
+        time_cost: u32,
+        /// Degree of parallelism
+        parallelism: u32,
+    },
+}
+
+impl KdfParams {
+    /// Parameters of all entries, created before they were configurable
+    pub const LEGACY: Self = Self::Pbkdf2 {
+        iterations: 100_000,
+    };
+
+    /// Recommended parameters for mobile devices
+    pub const MOBILE: Self = Self::Argon2id {
+        memory_cost: 19 * 1024,
+        time_cost: 2,
+        parallelism: 1,
+    };
+
+    /// Recommended parameters for desktop devices
+    pub const DESKTOP: Self = Self::Argon2id {
+        memory_cost: 64 * 1024,
+        time_cost: 3,
+        parallelism: 4,
+    };
+
+    /// Checks that parameters are not weaker than the minimal policy
+    pub fn validate(&self) -> Result<()> {
+        self.check_limits()?;
+        match *self {
+            Self::Pbkdf2 { iterations } if iterations < MIN_PBKDF2_ITERATIONS => {
+                Err(KdfError::TooWeakParams.into())
+            }
+            Self::Argon2id { memory_cost, .. } if memory_cost < MIN_ARGON2_MEMORY_COST => {
+                Err(KdfError::TooWeakParams.into())
+            }
+            Self::Argon2id {
+                memory_cost,
+                time_cost,
+                parallelism,
+            } => {
+                argon2::Params::new(memory_cost, time_cost, parallelism, Some(KEY_LEN))
+                    .map_err(|_| KdfError::InvalidParams)?;
+                Ok(())
+            }
+            Self::Pbkdf2 { .. } => Ok(()),
+        }
+    }
+
+    /// Checks that parameters are not too expensive to compute.
+    ///
+    /// Parameters are read from stored entries and imported backups,
+    /// so crafted ones must not exhaust the memory or hang the wallet
+    pub fn check_limits(&self) -> Result<()> {
+        let within_limits = match *self {
+            Self::Pbkdf2 { iterations } => iterations <= MAX_PBKDF2_ITERATIONS,
+            Self::Argon2id {
+                memory_cost,
+                time_cost,
+                parallelism,
+            } => {
+                memory_cost <= MAX_ARGON2_MEMORY_COST
+                    && time_cost <= MAX_ARGON2_TIME_COST
+                    && parallelism <= MAX_ARGON2_PARALLELISM
+            }
+        };
+
+        if within_limits {
+            Ok(())
+        } else {
+            Err(KdfError::TooExpensiveParams.into())
+        }
+    }
+
+    /// Computes symmetric key from the password
+    pub fn derive_key(&self, password: &str, salt: &[u8]) -> Result<Key> {
+        self.check_limits()?;
+
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        match *self {
+            Self::Pbkdf2 { iterations } => pbkdf2::pbkdf2_hmac::<sha2::Sha256>(
+                password.as_bytes(),
+                salt,
+                iterations,
+                &mut *key,
+            ),
+            Self::Argon2id {
+                memory_cost,
+                time_cost,
+                parallelism,
+            } => {
+                let params =
+                    argon2::Params::new(memory_cost, time_cost, parallelism, Some(KEY_LEN))
+                        .map_err(|_| KdfError::InvalidParams)?;
+                argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into(password.as_bytes(), salt, &mut *key)
+                    .map_err(|_| KdfError::InvalidParams)?;
+            }
+        }
+        Ok(Key::clone_from_slice(key.as_slice()))
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self::LEGACY
+    }
+}
+
+const KEY_LEN: usize = 32;
+
+const MIN_PBKDF2_ITERATIONS: u32 = 10_000;
+const MIN_ARGON2_MEMORY_COST: u32 = 8 * 1024;
+
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+const MAX_ARGON2_MEMORY_COST: u32 = 1024 * 1024;
+const MAX_ARGON2_TIME_COST: u32 = 16;
+const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+enum KdfError {
+    #[error("Invalid key derivation params")]
+    InvalidParams,
+    #[error("Key derivation params are too weak")]
+    TooWeakParams,
+    #[error("Key derivation params are too expensive")]
+    TooExpensiveParams,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_params_are_compatible() {
+        let salt = [0x11; 32];
+        assert_eq!(
+            KdfParams::LEGACY.derive_key("test", &salt).unwrap(),
+            nekoton_utils::symmetric_key_from_password("test", &salt)
+        );
+    }
+
+    #[test]
+    fn validate_params() {
+        KdfParams::LEGACY.validate().unwrap();
+        KdfParams::MOBILE.validate().unwrap();
+        KdfParams::DESKTOP.validate().unwrap();
+
+        assert!(KdfParams::Pbkdf2 { iterations: 1 }.validate().is_err());
+        assert!(KdfParams::Pbkdf2 {
+            iterations: u32::MAX
+        }
+        .validate()
+        .is_err());
+        assert!(KdfParams::Argon2id {
+            memory_cost: 19 * 1024,
+            time_cost: 0,
+            parallelism: 1
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn expensive_params_are_rejected() {
+        let params = KdfParams::Argon2id {
+            memory_cost: u32::MAX,
+            time_cost: 2,
+            parallelism: 1,
+        };
+        assert!(params.derive_key("test", &[0; 32]).is_err());
+
+        let params = KdfParams::Argon2id {
+            memory_cost: 19 * 1024,
+            time_cost: u32::MAX,
+            parallelism: 1,
+        };
+        assert!(params.derive_key("test", &[0; 32]).is_err());
+    }
+}
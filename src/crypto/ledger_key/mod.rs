@@ -10,8 +10,8 @@ use serde::{Deserialize, Serialize};
 use nekoton_utils::*;
 
 use super::{
-    default_key_name, SharedSecret, SignatureId, Signer as StoreSigner, SignerContext, SignerEntry,
-    SignerStorage,
+    default_key_name, KeyAlgorithm, SharedSecret, SignatureId, Signer as StoreSigner,
+    SignerContext, SignerEntry, SignerStorage,
 };
 use crate::core::ton_wallet::WalletType;
 use crate::external::{LedgerConnection, LedgerSignatureContext};
@@ -82,6 +82,7 @@ impl StoreSigner for LedgerKeySigner {
                     public_key,
                     master_key,
                     account_id: input.account_id,
+                    algorithm: KeyAlgorithm::Ed25519,
                 })
             }
             hash_map::Entry::Occupied(_) => return Err(LedgerKeyError::KeyAlreadyExists.into()),
@@ -102,6 +103,7 @@ impl StoreSigner for LedgerKeySigner {
                     public_key,
                     master_key: key.master_key,
                     account_id: key.account_id,
+                    algorithm: KeyAlgorithm::Ed25519,
                 })
             }
         }
@@ -230,6 +232,7 @@ impl SignerStorage for LedgerKeySigner {
                 public_key: key.public_key,
                 master_key: key.master_key,
                 account_id: key.account_id,
+                algorithm: KeyAlgorithm::Ed25519,
             })
             .collect()
     }
@@ -241,6 +244,7 @@ impl SignerStorage for LedgerKeySigner {
             public_key: key.public_key,
             master_key: key.master_key,
             account_id: key.account_id,
+            algorithm: KeyAlgorithm::Ed25519,
         })
     }
 
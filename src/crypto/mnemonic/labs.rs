@@ -6,8 +6,13 @@ use nekoton_utils::TrustMe;
 use super::{Bip39MnemonicData, LANGUAGE};
 
 pub fn derive_master_key(phrase: &str) -> Result<[u8; 64]> {
+    derive_master_key_with_passphrase(phrase, "")
+}
+
+/// Derives BIP-39 seed with the optional passphrase (empty string if not used)
+pub fn derive_master_key_with_passphrase(phrase: &str, passphrase: &str) -> Result<[u8; 64]> {
     let mnemonic = bip39::Mnemonic::from_phrase(phrase, LANGUAGE)?;
-    let hd = bip39::Seed::new(&mnemonic, "");
+    let hd = bip39::Seed::new(&mnemonic, passphrase);
     Ok(hd.as_bytes().try_into().trust_me())
 }
 
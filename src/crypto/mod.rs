@@ -12,21 +12,38 @@ use nekoton_utils::*;
 
 pub use derived_key::*;
 pub use encrypted_key::*;
+pub use kdf::*;
 pub use ledger_key::*;
 pub use mnemonic::*;
+pub use offline_message::*;
 pub use password_cache::*;
+pub use remote_signing::*;
+pub use secp256k1_key::*;
+pub use sign_data::*;
+pub use signing_session::*;
 
 mod derived_key;
 mod encrypted_key;
+mod kdf;
 mod ledger_key;
 mod mnemonic;
+mod offline_message;
 mod password_cache;
+mod remote_signing;
+mod secp256k1_key;
+mod sign_data;
+mod signing_session;
 
 pub type Signature = [u8; ed25519_dalek::SIGNATURE_LENGTH];
 pub type PubKey = [u8; ed25519_dalek::PUBLIC_KEY_LENGTH];
 
 pub type SignatureId = i32;
 
+/// Compressed SEC1 public key
+pub type Secp256k1PublicKey = [u8; 33];
+/// Recoverable ECDSA signature (`r || s || v`)
+pub type Secp256k1Signature = [u8; 65];
+
 pub trait UnsignedMessage: DynClone + Send + Sync {
     /// Adjust expiration timestamp from now
     fn refresh_timeout(&mut self, clock: &dyn Clock);
@@ -34,9 +51,20 @@ pub trait UnsignedMessage: DynClone + Send + Sync {
     /// Current expiration timestamp
     fn expire_at(&self) -> u32;
 
-    /// Message body hash
+    /// Message body hash.
+    ///
+    /// NOTE: it doesn't depend on the network capabilities, the signature id
+    /// is only prepended to it while signing
     fn hash(&self) -> &[u8];
 
+    /// Cell which representation hash is [`UnsignedMessage::hash`].
+    ///
+    /// Allows to check what is actually signed without trusting the hash.
+    /// `None` if the message doesn't support it
+    fn signed_data(&self) -> Option<ton_types::Cell> {
+        None
+    }
+
     /// Create signed message from prepared inputs
     /// # Arguments
     /// `signature` - signature, received from [`UnsignedMessage::hash`]
@@ -48,6 +76,19 @@ pub trait UnsignedMessage: DynClone + Send + Sync {
         signature: &Signature,
         prune_after_depth: u16,
     ) -> Result<SignedMessage>;
+
+    /// Create signed message, checking the signature received from an external signer
+    /// # Arguments
+    /// `signature` - signature of the [`UnsignedMessage::hash`], extended with `signature_id`
+    fn sign_verified(
+        &self,
+        public_key: &PublicKey,
+        signature_id: Option<SignatureId>,
+        signature: &Signature,
+    ) -> Result<SignedMessage> {
+        verify_signature(public_key, self.hash(), signature_id, signature)?;
+        self.sign(signature)
+    }
 }
 
 dyn_clone::clone_trait_object!(UnsignedMessage);
@@ -125,6 +166,9 @@ pub trait Signer: SignerStorage {
     type UpdateKeyInput;
     type SignInput;
 
+    /// Algorithm of all keys of this signer
+    const ALGORITHM: KeyAlgorithm = KeyAlgorithm::Ed25519;
+
     async fn add_key(
         &mut self,
         ctx: SignerContext<'_>,
@@ -169,6 +213,36 @@ pub trait Signer: SignerStorage {
         signature_id: Option<SignatureId>,
         input: Self::SignInput,
     ) -> Result<Signature>;
+
+    /// Re-encrypts the key if it uses outdated key derivation params.
+    /// Called by the keystore right before the key is unlocked.
+    ///
+    /// Returns `true` if the key was updated
+    fn upgrade_key(&mut self, ctx: SignerContext<'_>, input: &Self::SignInput) -> Result<bool> {
+        let _ = (ctx, input);
+        Ok(false)
+    }
+}
+
+/// Signer which holds secp256k1 keys (e.g. for EVM payloads).
+///
+/// Entries are still identified by the ed25519 public key in the keystore
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+pub trait Secp256k1Signer: Signer {
+    async fn get_secp256k1_public_key(
+        &self,
+        ctx: SignerContext<'_>,
+        public_key: &PublicKey,
+    ) -> Result<Secp256k1PublicKey>;
+
+    /// Signs the prehashed message (e.g. keccak256 of the EIP-191 payload)
+    async fn sign_secp256k1(
+        &self,
+        ctx: SignerContext<'_>,
+        hash: &[u8; 32],
+        input: Self::SignInput,
+    ) -> Result<Secp256k1Signature>;
 }
 
 #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
@@ -180,6 +254,11 @@ pub trait SignerStorage: Downcast + Send + Sync {
     fn get_entries(&self) -> Vec<SignerEntry>;
     async fn remove_key(&mut self, public_key: &PublicKey) -> Option<SignerEntry>;
     async fn clear(&mut self);
+
+    /// Sets params for the new (or re-encrypted) keys
+    fn set_kdf_params(&mut self, kdf_params: KdfParams) {
+        let _ = kdf_params;
+    }
 }
 
 impl_downcast!(SignerStorage);
@@ -196,13 +275,209 @@ pub struct SharedSecret {
     pub secret: Zeroizing<[u8; 32]>,
 }
 
+define_string_enum!(
+    /// Algorithm of the keystore entry
+    #[derive(Debug, Default, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+    pub enum KeyAlgorithm {
+        #[default]
+        Ed25519,
+        Secp256k1,
+    }
+);
+
 define_string_enum!(
     #[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
     pub enum EncryptionAlgorithm {
         ChaCha20Poly1305,
+        /// NaCl `crypto_box` (X25519 + HSalsa20 + XSalsa20Poly1305)
+        XSalsa20Poly1305,
     }
 );
 
+impl EncryptionAlgorithm {
+    /// Encrypts data with the shared secret. Returns encrypted data and nonce
+    pub fn encrypt(&self, secret: &[u8; 32], data: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        match self {
+            Self::ChaCha20Poly1305 => {
+                use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+
+                let nonce = Nonce::from(rng.gen::<[u8; 12]>());
+                let encryptor = ChaCha20Poly1305::new(Key::from_slice(secret));
+                let data = encrypt(&encryptor, &nonce, data)?;
+                Ok((data, nonce.to_vec()))
+            }
+            Self::XSalsa20Poly1305 => {
+                use xsalsa20poly1305::aead::Aead;
+                use xsalsa20poly1305::{KeyInit, Nonce, XSalsa20Poly1305};
+
+                let nonce = Nonce::from(rng.gen::<[u8; 24]>());
+                let data = XSalsa20Poly1305::new(&nacl_box_key(secret))
+                    .encrypt(&nonce, data)
+                    .map_err(|_| SymmetricCryptoError::FailedToEncryptData)?;
+                Ok((data, nonce.to_vec()))
+            }
+        }
+    }
+
+    /// Decrypts data with the shared secret
+    pub fn decrypt(&self, secret: &[u8; 32], data: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::ChaCha20Poly1305 => {
+                use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+
+                let nonce = <[u8; 12]>::try_from(nonce)
+                    .map(Nonce::from)
+                    .map_err(|_| CryptoError::InvalidNonce)?;
+                let decryptor = ChaCha20Poly1305::new(Key::from_slice(secret));
+                Ok(decrypt(&decryptor, &nonce, data)?)
+            }
+            Self::XSalsa20Poly1305 => {
+                use xsalsa20poly1305::aead::Aead;
+                use xsalsa20poly1305::{KeyInit, Nonce, XSalsa20Poly1305};
+
+                let nonce = <[u8; 24]>::try_from(nonce)
+                    .map(Nonce::from)
+                    .map_err(|_| CryptoError::InvalidNonce)?;
+                XSalsa20Poly1305::new(&nacl_box_key(secret))
+                    .decrypt(&nonce, data)
+                    .map_err(|_| SymmetricCryptoError::FailedToDecryptData.into())
+            }
+        }
+    }
+}
+
+/// Derives the `crypto_box` key from the X25519 shared secret (same as `crypto_box_beforenm`)
+fn nacl_box_key(secret: &[u8; 32]) -> xsalsa20poly1305::Key {
+    use salsa20::cipher::consts::U10;
+
+    salsa20::hsalsa::<U10>(secret.into(), &Default::default())
+}
+
+/// Encrypts data for the recipient with the new ephemeral key.
+///
+/// Doesn't require any keystore entry, the result can only be decrypted
+/// by the owner of the recipient key.
+///
+/// NOTE: this is not a libsodium sealed box, see [`seal`] for it. The nonce
+/// is random and the ephemeral public key is returned separately
+/// in [`EncryptedData::source_public_key`]
+pub fn encrypt_with_ephemeral_key(
+    data: &[u8],
+    recipient_public_key: &PublicKey,
+    algorithm: EncryptionAlgorithm,
+) -> Result<EncryptedData> {
+    use rand::Rng;
+
+    let ephemeral_secret = Zeroizing::new(rand::thread_rng().gen::<[u8; 32]>());
+    let ephemeral_secret = ed25519_dalek::SecretKey::from_bytes(ephemeral_secret.as_slice())?;
+    let source_public_key = PublicKey::from(&ephemeral_secret);
+
+    let secret = x25519::compute_shared(&ephemeral_secret, recipient_public_key);
+    let (data, nonce) = algorithm.encrypt(&secret, data)?;
+
+    Ok(EncryptedData {
+        algorithm,
+        source_public_key,
+        recipient_public_key: *recipient_public_key,
+        data,
+        nonce,
+    })
+}
+
+/// Size of the ephemeral public key and MAC in a sealed box
+pub const SEALED_BOX_OVERHEAD: usize = 32 + 16;
+
+/// Encrypts data for the recipient as a libsodium sealed box (`crypto_box_seal`).
+///
+/// The result is `ephemeral_pk || box` with the nonce `blake2b(ephemeral_pk || recipient_pk)`.
+/// All keys are in the x25519 form, so the box can also be opened by libsodium
+/// with the secret key from `crypto_sign_ed25519_sk_to_curve25519`.
+pub fn seal(data: &[u8], recipient_public_key: &PublicKey) -> Result<Vec<u8>> {
+    use rand::Rng;
+    use xsalsa20poly1305::aead::Aead;
+    use xsalsa20poly1305::{KeyInit, XSalsa20Poly1305};
+
+    let ephemeral_secret = Zeroizing::new(rand::thread_rng().gen::<[u8; 32]>());
+    let ephemeral_secret = ed25519_dalek::SecretKey::from_bytes(ephemeral_secret.as_slice())?;
+    let ephemeral_public_key = x25519::to_montgomery(&PublicKey::from(&ephemeral_secret));
+
+    let secret = x25519::compute_shared(&ephemeral_secret, recipient_public_key);
+    let nonce = sealed_box_nonce(&ephemeral_public_key, recipient_public_key);
+    let data = XSalsa20Poly1305::new(&nacl_box_key(&secret))
+        .encrypt(&nonce, data)
+        .map_err(|_| SymmetricCryptoError::FailedToEncryptData)?;
+
+    let mut result = Vec::with_capacity(32 + data.len());
+    result.extend_from_slice(&ephemeral_public_key);
+    result.extend_from_slice(&data);
+    Ok(result)
+}
+
+/// Decrypts the sealed box (`crypto_box_seal_open`) with the recipient secret key
+pub fn open_sealed(
+    data: &[u8],
+    recipient_secret_key: &ed25519_dalek::SecretKey,
+) -> Result<Vec<u8>> {
+    let sealed = SealedBox::parse(data)?;
+    let secret = x25519::compute_shared(recipient_secret_key, &sealed.source_public_key()?);
+    sealed.open(&secret, &PublicKey::from(recipient_secret_key))
+}
+
+/// Parsed libsodium sealed box
+#[derive(Debug, Clone, Copy)]
+pub struct SealedBox<'a> {
+    ephemeral_public_key: [u8; 32],
+    data: &'a [u8],
+}
+
+impl<'a> SealedBox<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.len() < SEALED_BOX_OVERHEAD {
+            return Err(CryptoError::InvalidSealedBox.into());
+        }
+        let (ephemeral_public_key, data) = data.split_at(32);
+        Ok(Self {
+            ephemeral_public_key: ephemeral_public_key.try_into().unwrap(),
+            data,
+        })
+    }
+
+    /// Ephemeral key in the ed25519 form, suitable for [`Signer::compute_shared_secrets`]
+    pub fn source_public_key(&self) -> Result<PublicKey> {
+        // NOTE: the sign of the point is lost in the x25519 form, but it doesn't
+        // affect the shared secret
+        x25519::to_edwards(&self.ephemeral_public_key)
+            .ok_or_else(|| CryptoError::InvalidSealedBox.into())
+    }
+
+    /// Decrypts the box with the shared secret of the recipient key and the ephemeral key
+    pub fn open(&self, secret: &[u8; 32], recipient_public_key: &PublicKey) -> Result<Vec<u8>> {
+        use xsalsa20poly1305::aead::Aead;
+        use xsalsa20poly1305::{KeyInit, XSalsa20Poly1305};
+
+        let nonce = sealed_box_nonce(&self.ephemeral_public_key, recipient_public_key);
+        XSalsa20Poly1305::new(&nacl_box_key(secret))
+            .decrypt(&nonce, self.data)
+            .map_err(|_| SymmetricCryptoError::FailedToDecryptData.into())
+    }
+}
+
+fn sealed_box_nonce(
+    ephemeral_public_key: &[u8; 32],
+    recipient_public_key: &PublicKey,
+) -> xsalsa20poly1305::Nonce {
+    use blake2::digest::consts::U24;
+    use blake2::{Blake2b, Digest};
+
+    Blake2b::<U24>::new()
+        .chain_update(ephemeral_public_key)
+        .chain_update(x25519::to_montgomery(recipient_public_key))
+        .finalize()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EncryptedData {
@@ -230,6 +505,8 @@ pub struct SignerEntry {
     #[serde(with = "serde_public_key")]
     pub master_key: PublicKey,
     pub account_id: u16,
+    #[serde(default)]
+    pub algorithm: KeyAlgorithm,
 }
 
 pub fn default_key_name(public_key: &PubKey) -> String {
@@ -240,6 +517,15 @@ pub fn default_key_name(public_key: &PubKey) -> String {
     )
 }
 
+/// Returns the cell only if its representation hash matches the expected one
+pub fn signed_data_cell(
+    data: ton_types::BuilderData,
+    hash: &ton_types::UInt256,
+) -> Option<ton_types::Cell> {
+    let cell = data.into_cell().ok()?;
+    (cell.repr_hash() == *hash).then_some(cell)
+}
+
 pub fn extend_with_signature_id(data: &[u8], signature_id: Option<SignatureId>) -> Cow<'_, [u8]> {
     match signature_id {
         Some(signature_id) => {
@@ -252,7 +538,44 @@ pub fn extend_with_signature_id(data: &[u8], signature_id: Option<SignatureId>)
     }
 }
 
+/// Checks the signature of the data, extended with `signature_id`
+pub fn verify_signature(
+    public_key: &PublicKey,
+    data: &[u8],
+    signature_id: Option<SignatureId>,
+    signature: &Signature,
+) -> Result<()> {
+    use ed25519_dalek::Verifier;
+
+    let data = extend_with_signature_id(data, signature_id);
+    let signature = ed25519_dalek::Signature::from_bytes(signature)?;
+    public_key
+        .verify(&data, &signature)
+        .map_err(|_| CryptoError::InvalidSignature.into())
+}
+
+/// Checks the recoverable secp256k1 signature of the prehashed message
+pub fn verify_secp256k1_signature(
+    public_key: &Secp256k1PublicKey,
+    hash: &[u8; 32],
+    signature: &Secp256k1Signature,
+) -> Result<()> {
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+    let recovery_id = RecoveryId::from_byte(signature[64]).ok_or(CryptoError::InvalidSignature)?;
+    let signature =
+        Signature::from_slice(&signature[..64]).map_err(|_| CryptoError::InvalidSignature)?;
+    let recovered = VerifyingKey::recover_from_prehash(hash, &signature, recovery_id)
+        .map_err(|_| CryptoError::InvalidSignature)?;
+
+    if recovered.to_encoded_point(true).as_bytes() != public_key.as_slice() {
+        return Err(CryptoError::InvalidSignature.into());
+    }
+    Ok(())
+}
+
 pub mod x25519 {
+    use curve25519_dalek_ng::montgomery::MontgomeryPoint;
     use curve25519_dalek_ng::scalar::Scalar;
     use zeroize::Zeroizing;
 
@@ -266,11 +589,203 @@ pub mod x25519 {
         k[31] &= 127;
         k[31] |= 64;
 
-        let u = curve25519_dalek_ng::edwards::CompressedEdwardsY(u.to_bytes())
+        let u = MontgomeryPoint(to_montgomery(u));
+
+        Zeroizing::new((Scalar::from_bits(k) * u).to_bytes())
+    }
+
+    /// Converts the ed25519 public key to the x25519 public key
+    pub fn to_montgomery(u: &ed25519_dalek::PublicKey) -> [u8; 32] {
+        curve25519_dalek_ng::edwards::CompressedEdwardsY(u.to_bytes())
             .decompress()
             .unwrap() // shouldn't fail because bytes were extracted from public key
-            .to_montgomery();
+            .to_montgomery()
+            .to_bytes()
+    }
 
-        Zeroizing::new((Scalar::from_bits(k) * u).to_bytes())
+    /// Converts the x25519 public key to the ed25519 public key with the positive sign
+    pub fn to_edwards(u: &[u8; 32]) -> Option<ed25519_dalek::PublicKey> {
+        let point = MontgomeryPoint(*u).to_edwards(0)?;
+        ed25519_dalek::PublicKey::from_bytes(point.compress().as_bytes()).ok()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum CryptoError {
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Invalid nonce")]
+    InvalidNonce,
+    #[error("Invalid sealed box")]
+    InvalidSealedBox,
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Signer;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct TestMessage {
+        hash: [u8; 32],
+    }
+
+    impl UnsignedMessage for TestMessage {
+        fn refresh_timeout(&mut self, _: &dyn Clock) {}
+
+        fn expire_at(&self) -> u32 {
+            0
+        }
+
+        fn hash(&self) -> &[u8] {
+            &self.hash
+        }
+
+        fn sign(&self, _: &Signature) -> Result<SignedMessage> {
+            Ok(SignedMessage {
+                message: ton_block::Message::default(),
+                expire_at: 0,
+            })
+        }
+
+        fn sign_with_pruned_payload(&self, signature: &Signature, _: u16) -> Result<SignedMessage> {
+            self.sign(signature)
+        }
+    }
+
+    fn make_keypair(seed: u8) -> ed25519_dalek::Keypair {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        ed25519_dalek::Keypair { secret, public }
+    }
+
+    fn sign(
+        keypair: &ed25519_dalek::Keypair,
+        data: &[u8],
+        signature_id: Option<SignatureId>,
+    ) -> Signature {
+        keypair
+            .sign(&extend_with_signature_id(data, signature_id))
+            .to_bytes()
+    }
+
+    fn is_invalid_signature(result: Result<SignedMessage>) -> bool {
+        matches!(
+            result.unwrap_err().downcast_ref::<CryptoError>(),
+            Some(CryptoError::InvalidSignature)
+        )
+    }
+
+    #[test]
+    fn sign_verified_accepts_valid_signature() {
+        let keypair = make_keypair(1);
+        let message = TestMessage { hash: [1; 32] };
+
+        let signature = sign(&keypair, message.hash(), None);
+        message
+            .sign_verified(&keypair.public, None, &signature)
+            .unwrap();
+
+        let signature = sign(&keypair, message.hash(), Some(42));
+        message
+            .sign_verified(&keypair.public, Some(42), &signature)
+            .unwrap();
+    }
+
+    #[test]
+    fn sign_verified_rejects_wrong_key_or_data() {
+        let keypair = make_keypair(1);
+        let other_keypair = make_keypair(2);
+        let message = TestMessage { hash: [1; 32] };
+
+        let signature = sign(&keypair, message.hash(), None);
+        assert!(is_invalid_signature(message.sign_verified(
+            &other_keypair.public,
+            None,
+            &signature
+        )));
+
+        let tampered = TestMessage { hash: [2; 32] };
+        assert!(is_invalid_signature(tampered.sign_verified(
+            &keypair.public,
+            None,
+            &signature
+        )));
+    }
+
+    #[test]
+    fn sign_verified_rejects_signature_id_mismatch() {
+        let keypair = make_keypair(1);
+        let message = TestMessage { hash: [1; 32] };
+
+        let signature = sign(&keypair, message.hash(), Some(42));
+        for signature_id in [None, Some(43)] {
+            assert!(is_invalid_signature(message.sign_verified(
+                &keypair.public,
+                signature_id,
+                &signature
+            )));
+        }
+
+        let signature = sign(&keypair, message.hash(), None);
+        assert!(is_invalid_signature(message.sign_verified(
+            &keypair.public,
+            Some(42),
+            &signature
+        )));
+    }
+
+    #[test]
+    fn sealed_box_roundtrip() {
+        let recipient = make_keypair(1);
+        let other = make_keypair(2);
+
+        let sealed = seal(b"hello", &recipient.public).unwrap();
+        assert_eq!(sealed.len(), 5 + SEALED_BOX_OVERHEAD);
+        assert_eq!(open_sealed(&sealed, &recipient.secret).unwrap(), b"hello");
+        assert!(open_sealed(&sealed, &other.secret).is_err());
+
+        let sealed = seal(&[], &recipient.public).unwrap();
+        assert!(open_sealed(&sealed, &recipient.secret).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sealed_box_rejects_tampered_data() {
+        let recipient = make_keypair(1);
+        let sealed = seal(b"hello", &recipient.public).unwrap();
+
+        // Ephemeral key, MAC and ciphertext
+        for i in [0, 31, 32, 47, 48, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 1;
+            assert!(open_sealed(&tampered, &recipient.secret).is_err());
+        }
+
+        let err = open_sealed(&sealed[..SEALED_BOX_OVERHEAD - 1], &recipient.secret).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CryptoError>(),
+            Some(CryptoError::InvalidSealedBox)
+        ));
+    }
+
+    #[test]
+    fn sealed_box_is_libsodium_compatible() {
+        // Sealed with `crypto_box_seal` for the x25519 form of the recipient key
+        let sealed = hex::decode(
+            "57db4b359f23ae5e146e4e2512056704722506348c150c14753d0c933d04d421\
+             f6c324908a44fe48185b1dd3462b71bd881f7aa3e03982c0c937f2f72d2c99",
+        )
+        .unwrap();
+
+        let recipient = make_keypair(7);
+        assert_eq!(
+            hex::encode(x25519::to_montgomery(&recipient.public)),
+            "761d88ec830413919dfe9d4d1d56f17e653c8c994082df5b137b90a0ae6edf74"
+        );
+        assert_eq!(
+            open_sealed(&sealed, &recipient.secret).unwrap(),
+            b"sealed box test"
+        );
     }
 }
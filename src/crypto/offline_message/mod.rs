@@ -0,0 +1,535 @@
+use anyhow::Result;
+use ed25519_dalek::PublicKey;
+use serde::{Deserialize, Serialize};
+use ton_block::{Deserializable, MsgAddressInt, Serializable};
+use ton_types::{Cell, UInt256};
+
+use nekoton_utils::*;
+
+use super::{Signature, SignatureId, SignedMessage, Signer, UnsignedMessage};
+use crate::core::keystore::KeyStore;
+use crate::models::TokenAmount;
+
+const OFFLINE_FORMAT_VERSION: u8 = 3;
+
+/// Keeps the unsigned message on the online device while its hash is signed
+/// on the offline (air-gapped) one.
+///
+/// The message itself never leaves the online device, only the
+/// [`OfflineSigningRequest`] is exported and only the [`OfflineSignature`] is imported back
+#[derive(Clone)]
+pub struct OfflineSigningSession {
+    message: Box<dyn UnsignedMessage>,
+    public_key: PublicKey,
+    signature_id: Option<SignatureId>,
+    function: Option<OfflineFunction>,
+    description: Option<String>,
+}
+
+impl OfflineSigningSession {
+    pub fn new(
+        message: Box<dyn UnsignedMessage>,
+        public_key: PublicKey,
+        signature_id: Option<SignatureId>,
+    ) -> Self {
+        Self {
+            message,
+            public_key,
+            signature_id,
+            function: None,
+            description: None,
+        }
+    }
+
+    /// Sets the ABI function which is called by the message
+    pub fn with_function(mut self, function: &ton_abi::Function) -> Self {
+        self.function = Some(OfflineFunction::from(function));
+        self
+    }
+
+    /// Sets the human-readable description which is shown on the offline device
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn expire_at(&self) -> u32 {
+        self.message.expire_at()
+    }
+
+    pub fn is_expired(&self, clock: &dyn Clock) -> bool {
+        self.expire_at() <= clock.now_sec_u64() as u32
+    }
+
+    /// Adjusts the expiration timestamp from now.
+    ///
+    /// NOTE: the hash changes, so all previously exported requests become invalid
+    pub fn refresh_timeout(&mut self, clock: &dyn Clock) {
+        self.message.refresh_timeout(clock);
+    }
+
+    /// Refreshes the timeout if the message expires in less than `min_lifetime` seconds.
+    ///
+    /// Returns `true` if the timeout was refreshed
+    pub fn refresh_if_needed(&mut self, clock: &dyn Clock, min_lifetime: u32) -> bool {
+        let now = clock.now_sec_u64() as u32;
+        if self.expire_at() >= now.saturating_add(min_lifetime) {
+            return false;
+        }
+        self.refresh_timeout(clock);
+        true
+    }
+
+    /// Creates the request which must be transferred to the offline device
+    pub fn request(&self, clock: &dyn Clock) -> Result<OfflineSigningRequest> {
+        if self.is_expired(clock) {
+            return Err(OfflineMessageError::MessageExpired.into());
+        }
+
+        let signed_data = self
+            .message
+            .signed_data()
+            .ok_or(OfflineMessageError::UnsupportedMessage)?;
+
+        // Message with an empty signature is only used to inspect its contents
+        let boc = self
+            .message
+            .sign(&[0; ed25519_dalek::SIGNATURE_LENGTH])?
+            .message
+            .serialize()?;
+
+        Ok(OfflineSigningRequest {
+            version: OFFLINE_FORMAT_VERSION,
+            boc,
+            signed_data,
+            hash: self.message.hash().to_vec(),
+            expire_at: self.expire_at(),
+            signature_id: self.signature_id,
+            public_key: self.public_key,
+            function: self.function.clone(),
+            description: self.description.clone(),
+        })
+    }
+
+    /// Creates the signed message from the signature, received from the offline device
+    pub fn finalize(
+        &self,
+        clock: &dyn Clock,
+        signature: &OfflineSignature,
+    ) -> Result<SignedMessage> {
+        if signature.hash != self.message.hash() || signature.expire_at != self.expire_at() {
+            return Err(OfflineMessageError::StaleSignature.into());
+        }
+        if self.is_expired(clock) {
+            return Err(OfflineMessageError::MessageExpired.into());
+        }
+
+        self.message
+            .sign_verified(&self.public_key, self.signature_id, &signature.signature)
+    }
+}
+
+/// Data which is required to sign the message on the offline device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineSigningRequest {
+    pub version: u8,
+    /// Message with an empty signature
+    #[serde(with = "serde_cell")]
+    pub boc: Cell,
+    /// Cell which hash is signed
+    #[serde(with = "serde_cell")]
+    pub signed_data: Cell,
+    /// Hash which must be signed.
+    ///
+    /// NOTE: must not be trusted on the offline device, see [`OfflineSigningRequest::verified_hash`]
+    #[serde(with = "serde_bytes")]
+    pub hash: Vec<u8>,
+    pub expire_at: u32,
+    pub signature_id: Option<SignatureId>,
+    #[serde(with = "serde_public_key")]
+    pub public_key: PublicKey,
+    /// ABI function which is called by the message
+    pub function: Option<OfflineFunction>,
+    /// Human-readable description from the online device
+    pub description: Option<String>,
+}
+
+/// ABI function which is called by the message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineFunction {
+    pub name: String,
+    /// Function id of the input message
+    pub input_id: u32,
+}
+
+impl From<&ton_abi::Function> for OfflineFunction {
+    fn from(function: &ton_abi::Function) -> Self {
+        Self {
+            name: function.name.clone(),
+            input_id: function.input_id,
+        }
+    }
+}
+
+/// Description of the request, derived from the signed data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfflineSigningSummary {
+    /// Destination of the external message
+    pub destination: Option<MsgAddressInt>,
+    /// Called ABI function (informational, not verified)
+    pub function: Option<OfflineFunction>,
+    /// Description from the online device (informational, not verified)
+    pub description: Option<String>,
+    /// Internal messages which are signed
+    pub transfers: Vec<OfflineTransfer>,
+}
+
+impl std::fmt::Display for OfflineSigningSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.function, &self.destination) {
+            (Some(function), Some(destination)) => writeln!(
+                f,
+                "Call {} (0x{:08x}) on {destination}",
+                function.name, function.input_id
+            )?,
+            (Some(function), None) => {
+                writeln!(f, "Call {} (0x{:08x})", function.name, function.input_id)?
+            }
+            (None, Some(destination)) => writeln!(f, "Message to {destination}")?,
+            (None, None) => {}
+        }
+        for transfer in &self.transfers {
+            write!(
+                f,
+                "Transfer {} to {}",
+                TokenAmount::new(transfer.amount, 9),
+                transfer.destination
+            )?;
+            match (transfer.bounce, transfer.has_body) {
+                (true, true) => writeln!(f, " (bounce, with payload)")?,
+                (true, false) => writeln!(f, " (bounce)")?,
+                (false, true) => writeln!(f, " (with payload)")?,
+                (false, false) => writeln!(f)?,
+            }
+        }
+        if let Some(description) = &self.description {
+            writeln!(f, "Description: {description}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfflineTransfer {
+    pub destination: MsgAddressInt,
+    pub amount: u128,
+    pub bounce: bool,
+    pub has_body: bool,
+}
+
+impl OfflineSigningRequest {
+    pub fn is_expired(&self, clock: &dyn Clock) -> bool {
+        self.expire_at <= clock.now_sec_u64() as u32
+    }
+
+    /// Recomputes the hash from the signed data and checks that it matches the request
+    pub fn verified_hash(&self) -> Result<UInt256> {
+        let hash = self.signed_data.repr_hash();
+        if self.hash != hash.as_slice() {
+            return Err(OfflineMessageError::HashMismatch.into());
+        }
+        Ok(hash)
+    }
+
+    /// Describes the request using only the data which is covered by the signature.
+    ///
+    /// Internal messages are taken from the direct references of the signed data
+    /// (e.g. wallet transfers). The external message destination, function and
+    /// description are informational, because they are not always signed
+    pub fn summary(&self) -> Result<OfflineSigningSummary> {
+        self.verified_hash()?;
+
+        let destination = ton_block::Message::construct_from_cell(self.boc.clone())
+            .map_err(|_| OfflineMessageError::InvalidContainer)?
+            .dst();
+
+        let mut transfers = Vec::new();
+        for i in 0..self.signed_data.references_count() {
+            let cell = self.signed_data.reference(i)?;
+            let message = match ton_block::Message::construct_from_cell(cell) {
+                Ok(message) => message,
+                Err(_) => continue,
+            };
+            if let Some(header) = message.int_header() {
+                transfers.push(OfflineTransfer {
+                    destination: header.dst.clone(),
+                    amount: header.value.grams.as_u128(),
+                    bounce: header.bounce,
+                    has_body: message.body().is_some(),
+                });
+            }
+        }
+
+        Ok(OfflineSigningSummary {
+            destination,
+            function: self.function.clone(),
+            description: self.description.clone(),
+            transfers,
+        })
+    }
+
+    /// Signs the request with the keystore entry on the offline device.
+    ///
+    /// Only the hash of the signed data is signed, the `hash` field is only compared with it
+    pub async fn sign_with<T>(
+        &self,
+        keystore: &KeyStore,
+        input: T::SignInput,
+    ) -> Result<OfflineSignature>
+    where
+        T: Signer,
+    {
+        let hash = self.verified_hash()?;
+        let signature = keystore
+            .sign::<T>(hash.as_slice(), self.signature_id, input)
+            .await?;
+        Ok(OfflineSignature {
+            version: OFFLINE_FORMAT_VERSION,
+            hash: hash.to_vec(),
+            expire_at: self.expire_at,
+            signature,
+        })
+    }
+
+    /// Encodes the request into a compact string (e.g. for a QR code)
+    pub fn encode(&self) -> Result<String> {
+        encode_container(self)
+    }
+
+    pub fn decode(data: &str) -> Result<Self> {
+        let request: Self = decode_container(data)?;
+        check_version(request.version)?;
+        Ok(request)
+    }
+}
+
+/// Signature which is transferred back from the offline device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineSignature {
+    pub version: u8,
+    /// Signed hash, used to match the signature with the session
+    #[serde(with = "serde_bytes")]
+    pub hash: Vec<u8>,
+    pub expire_at: u32,
+    #[serde(with = "serde_hex_array")]
+    pub signature: Signature,
+}
+
+impl OfflineSignature {
+    /// Encodes the signature into a compact string (e.g. for a QR code)
+    pub fn encode(&self) -> Result<String> {
+        encode_container(self)
+    }
+
+    pub fn decode(data: &str) -> Result<Self> {
+        let signature: Self = decode_container(data)?;
+        check_version(signature.version)?;
+        Ok(signature)
+    }
+}
+
+fn encode_container<T: Serialize>(container: &T) -> Result<String> {
+    let json = serde_json::to_vec(container)?;
+    Ok(base64::encode_config(json, base64::URL_SAFE_NO_PAD))
+}
+
+fn decode_container<T: for<'de> Deserialize<'de>>(data: &str) -> Result<T> {
+    let json = base64::decode_config(data.trim(), base64::URL_SAFE_NO_PAD)
+        .map_err(|_| OfflineMessageError::InvalidContainer)?;
+    serde_json::from_slice(&json).map_err(|_| OfflineMessageError::InvalidContainer.into())
+}
+
+fn check_version(version: u8) -> Result<()> {
+    if version != OFFLINE_FORMAT_VERSION {
+        return Err(OfflineMessageError::UnsupportedVersion.into());
+    }
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum OfflineMessageError {
+    #[error("Message expired")]
+    MessageExpired,
+    #[error("Signature doesn't match the current message")]
+    StaleSignature,
+    #[error("Invalid container")]
+    InvalidContainer,
+    #[error("Unsupported container version")]
+    UnsupportedVersion,
+    #[error("Message can't be signed offline")]
+    UnsupportedMessage,
+    #[error("Hash doesn't match the signed data")]
+    HashMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Signer as _;
+    use ton_types::IBitstring;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct TestMessage {
+        expire_at: u32,
+        data: Cell,
+        hash: UInt256,
+    }
+
+    impl TestMessage {
+        fn new(expire_at: u32) -> Self {
+            let transfer = ton_block::Message::with_int_header(ton_block::InternalMessageHeader {
+                dst: MsgAddressInt::default(),
+                value: ton_block::CurrencyCollection::with_grams(1_000_000_000),
+                bounce: true,
+                ..Default::default()
+            });
+
+            let mut data = ton_types::BuilderData::new();
+            data.append_u32(expire_at).unwrap();
+            data.checked_append_reference(transfer.serialize().unwrap())
+                .unwrap();
+            let data = data.into_cell().unwrap();
+
+            Self {
+                expire_at,
+                hash: data.repr_hash(),
+                data,
+            }
+        }
+    }
+
+    impl UnsignedMessage for TestMessage {
+        fn refresh_timeout(&mut self, clock: &dyn Clock) {
+            *self = Self::new(clock.now_sec_u64() as u32 + 60);
+        }
+
+        fn expire_at(&self) -> u32 {
+            self.expire_at
+        }
+
+        fn hash(&self) -> &[u8] {
+            self.hash.as_slice()
+        }
+
+        fn signed_data(&self) -> Option<Cell> {
+            Some(self.data.clone())
+        }
+
+        fn sign(&self, _: &Signature) -> Result<SignedMessage> {
+            Ok(SignedMessage {
+                message: ton_block::Message::default(),
+                expire_at: self.expire_at,
+            })
+        }
+
+        fn sign_with_pruned_payload(&self, signature: &Signature, _: u16) -> Result<SignedMessage> {
+            self.sign(signature)
+        }
+    }
+
+    #[test]
+    fn offline_signing_roundtrip() {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let keypair = ed25519_dalek::Keypair { secret, public };
+
+        let abi_function = nekoton_contracts::wallets::ever_wallet::send_transaction();
+        let function = OfflineFunction {
+            name: abi_function.name.clone(),
+            input_id: abi_function.input_id,
+        };
+
+        let message = TestMessage::new(100);
+        let mut session = OfflineSigningSession::new(Box::new(message), public, Some(42))
+            .with_function(abi_function)
+            .with_description("Pay for coffee");
+
+        assert!(session.request(&ConstClock::from_secs(100)).is_err());
+        assert!(!session.refresh_if_needed(&ConstClock::from_secs(10), 30));
+        assert!(session.refresh_if_needed(&ConstClock::from_secs(80), 30));
+        assert_eq!(session.expire_at(), 140);
+
+        let clock = ConstClock::from_secs(90);
+        let encoded = session.request(&clock).unwrap().encode().unwrap();
+
+        // Offline device
+        let request = OfflineSigningRequest::decode(&encoded).unwrap();
+        assert!(!request.is_expired(&clock));
+
+        assert_eq!(request.version, OFFLINE_FORMAT_VERSION);
+        assert_eq!(request.function.as_ref(), Some(&function));
+        assert_eq!(request.description.as_deref(), Some("Pay for coffee"));
+
+        let summary = request.summary().unwrap();
+        assert_eq!(summary.function.as_ref(), Some(&function));
+        assert_eq!(summary.transfers.len(), 1);
+        assert_eq!(summary.transfers[0].amount, 1_000_000_000);
+        assert!(summary.transfers[0].bounce);
+
+        let text = summary.to_string();
+        assert!(text.starts_with(&format!(
+            "Call sendTransaction (0x{:08x})",
+            function.input_id
+        )));
+        assert!(text.contains(&format!(
+            "Transfer 1 to {} (bounce)\n",
+            MsgAddressInt::default()
+        )));
+        assert!(text.ends_with("Description: Pay for coffee\n"));
+
+        let hash = request.verified_hash().unwrap();
+        let data = crate::crypto::extend_with_signature_id(hash.as_slice(), request.signature_id);
+        let signature = OfflineSignature {
+            version: request.version,
+            hash: hash.to_vec(),
+            expire_at: request.expire_at,
+            signature: keypair.sign(&data).to_bytes(),
+        };
+        let encoded = signature.encode().unwrap();
+
+        // Online device
+        let signature = OfflineSignature::decode(&encoded).unwrap();
+        let signed = session.finalize(&clock, &signature).unwrap();
+        assert_eq!(signed.expire_at, 140);
+
+        // Signature becomes stale after the refresh
+        session.refresh_timeout(&ConstClock::from_secs(100));
+        assert!(session.finalize(&clock, &signature).is_err());
+    }
+
+    #[test]
+    fn tampered_request() {
+        let public = PublicKey::from(&ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap());
+        let session = OfflineSigningSession::new(Box::new(TestMessage::new(100)), public, None);
+        let request = session.request(&ConstClock::from_secs(10)).unwrap();
+
+        // Online device substitutes the hash of another message
+        let mut tampered = request.clone();
+        tampered.hash = TestMessage::new(200).hash.to_vec();
+        assert!(tampered.verified_hash().is_err());
+        assert!(tampered.summary().is_err());
+
+        // Consistent hash and signed data of another message are shown as they are
+        let other = TestMessage::new(200);
+        let tampered = OfflineSigningRequest {
+            signed_data: other.data.clone(),
+            hash: other.hash.to_vec(),
+            ..request
+        };
+        assert_eq!(tampered.verified_hash().unwrap(), other.hash);
+    }
+}
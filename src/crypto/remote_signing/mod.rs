@@ -0,0 +1,335 @@
+use anyhow::Result;
+use ed25519_dalek::PublicKey;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use ton_types::Cell;
+
+use nekoton_utils::*;
+
+use super::{
+    extend_with_signature_id, EncryptedData, EncryptionAlgorithm, Signature, SignatureId,
+    SignedMessage,
+};
+
+pub const REMOTE_SIGNING_PROTOCOL_VERSION: u16 = 2;
+
+/// Request from the dApp to the wallet which holds the keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSigningRequest {
+    pub version: u16,
+    /// Request id, unique within the session
+    pub id: String,
+    #[serde(flatten)]
+    pub payload: RemoteSigningPayload,
+    /// Commitment to the data which is signed or processed, see [`RemoteSigningPayload::commitment`].
+    ///
+    /// Both sides compute it independently, so it can be shown to the user on both
+    /// devices and is echoed in the response
+    #[serde(with = "serde_hex_array")]
+    pub commitment: [u8; 32],
+}
+
+impl RemoteSigningRequest {
+    pub fn new(id: impl Into<String>, payload: RemoteSigningPayload) -> Result<Self> {
+        let commitment = payload.commitment()?;
+        Ok(Self {
+            version: REMOTE_SIGNING_PROTOCOL_VERSION,
+            id: id.into(),
+            payload,
+            commitment,
+        })
+    }
+
+    /// Checks the protocol version and recomputes the commitment from the payload.
+    ///
+    /// For [`RemoteSigningPayload::SignMessage`] it also checks that the hash matches the signed data
+    pub fn verify(&self) -> Result<()> {
+        if self.version != REMOTE_SIGNING_PROTOCOL_VERSION {
+            return Err(RemoteSigningError::UnsupportedVersion.into());
+        }
+        if self.payload.commitment()? != self.commitment {
+            return Err(RemoteSigningError::CommitmentMismatch.into());
+        }
+        Ok(())
+    }
+}
+
+/// What is requested from the wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "method", content = "params")]
+pub enum RemoteSigningPayload {
+    SignMessage(SignMessageParams),
+    SignData(SignDataParams),
+    EncryptData(EncryptDataParams),
+    DecodePayload(DecodePayloadParams),
+}
+
+impl RemoteSigningPayload {
+    /// SHA-256 of the canonical binary encoding of the request:
+    ///
+    /// - `signMessage`: `0x01 || public_key || signed_bytes`
+    /// - `signData`: `0x02 || public_key || signed_bytes`
+    /// - `encryptData`: `0x03 || public_key || algorithm || u32 count || recipients || data`
+    /// - `decodePayload`: `0x04 || payload_hash || abi`
+    ///
+    /// Where `signed_bytes` are the exact bytes which are signed (including the signature id),
+    /// variable length fields are prefixed with their length as big-endian `u32`
+    /// and the message hash is recomputed from the signed data
+    pub fn commitment(&self) -> Result<[u8; 32]> {
+        let mut hasher = sha2::Sha256::new();
+        match self {
+            Self::SignMessage(params) => {
+                let hash = params.verified_hash()?;
+                hasher.update([0x01]);
+                hasher.update(params.public_key.as_bytes());
+                hasher.update(extend_with_signature_id(
+                    hash.as_slice(),
+                    params.signature_id,
+                ));
+            }
+            Self::SignData(params) => {
+                hasher.update([0x02]);
+                hasher.update(params.public_key.as_bytes());
+                hasher.update(extend_with_signature_id(&params.data, params.signature_id));
+            }
+            Self::EncryptData(params) => {
+                hasher.update([0x03]);
+                hasher.update(params.public_key.as_bytes());
+                update_with_bytes(&mut hasher, params.algorithm.as_str().as_bytes());
+                hasher.update((params.recipient_public_keys.len() as u32).to_be_bytes());
+                for public_key in &params.recipient_public_keys {
+                    hasher.update(public_key.as_bytes());
+                }
+                update_with_bytes(&mut hasher, &params.data);
+            }
+            Self::DecodePayload(params) => {
+                hasher.update([0x04]);
+                hasher.update(params.payload.repr_hash().as_slice());
+                update_with_bytes(
+                    &mut hasher,
+                    params.abi.as_deref().unwrap_or_default().as_bytes(),
+                );
+            }
+        }
+        Ok(hasher.finalize().into())
+    }
+}
+
+fn update_with_bytes(hasher: &mut sha2::Sha256, data: &[u8]) {
+    hasher.update((data.len() as u32).to_be_bytes());
+    hasher.update(data);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignMessageParams {
+    #[serde(with = "serde_public_key")]
+    pub public_key: PublicKey,
+    /// Message with an empty signature, used to show its contents
+    #[serde(with = "serde_cell")]
+    pub boc: Cell,
+    /// Cell which hash is signed, see [`UnsignedMessage::signed_data`]
+    ///
+    /// [`UnsignedMessage::signed_data`]: crate::crypto::UnsignedMessage::signed_data
+    #[serde(with = "serde_cell")]
+    pub signed_data: Cell,
+    /// Hash which must be signed
+    #[serde(with = "serde_bytes")]
+    pub hash: Vec<u8>,
+    pub expire_at: u32,
+    pub signature_id: Option<SignatureId>,
+}
+
+impl SignMessageParams {
+    /// Recomputes the hash from the signed data and checks that it matches the params
+    pub fn verified_hash(&self) -> Result<ton_types::UInt256> {
+        let hash = self.signed_data.repr_hash();
+        if self.hash != hash.as_slice() {
+            return Err(RemoteSigningError::HashMismatch.into());
+        }
+        Ok(hash)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignDataParams {
+    #[serde(with = "serde_public_key")]
+    pub public_key: PublicKey,
+    #[serde(with = "serde_bytes_base64")]
+    pub data: Vec<u8>,
+    pub signature_id: Option<SignatureId>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptDataParams {
+    /// Sender key in the wallet
+    #[serde(with = "serde_public_key")]
+    pub public_key: PublicKey,
+    #[serde(with = "serde_vec_public_key")]
+    pub recipient_public_keys: Vec<PublicKey>,
+    pub algorithm: EncryptionAlgorithm,
+    #[serde(with = "serde_bytes_base64")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodePayloadParams {
+    #[serde(with = "serde_cell")]
+    pub payload: Cell,
+    /// Contract ABI JSON, known payloads are decoded without it
+    pub abi: Option<String>,
+}
+
+/// Response of the wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSigningResponse {
+    pub version: u16,
+    pub id: String,
+    /// Commitment of the request
+    #[serde(with = "serde_hex_array")]
+    pub commitment: [u8; 32],
+    pub result: RemoteSigningResult,
+}
+
+impl RemoteSigningResponse {
+    pub fn new(request: &RemoteSigningRequest, result: RemoteSigningResult) -> Self {
+        Self {
+            version: REMOTE_SIGNING_PROTOCOL_VERSION,
+            id: request.id.clone(),
+            commitment: request.commitment,
+            result,
+        }
+    }
+
+    /// Checks that the response was produced for the specified request
+    pub fn verify(&self, request: &RemoteSigningRequest) -> Result<()> {
+        if self.version != REMOTE_SIGNING_PROTOCOL_VERSION {
+            return Err(RemoteSigningError::UnsupportedVersion.into());
+        }
+        if self.id != request.id || self.commitment != request.commitment {
+            return Err(RemoteSigningError::ResponseMismatch.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "data")]
+pub enum RemoteSigningResult {
+    /// Result of [`RemoteSigningPayload::SignMessage`]
+    SignedMessage(SignedMessage),
+    /// Result of [`RemoteSigningPayload::SignData`]
+    Signature(#[serde(with = "serde_hex_array")] Signature),
+    /// Result of [`RemoteSigningPayload::EncryptData`]
+    EncryptedData(Vec<EncryptedData>),
+    /// Result of [`RemoteSigningPayload::DecodePayload`]
+    DecodedPayload {
+        method: String,
+        /// Decoded tokens in JSON format
+        params: serde_json::Value,
+    },
+    /// Request was rejected by the user or failed
+    Rejected { reason: String },
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum RemoteSigningError {
+    #[error("Unsupported protocol version")]
+    UnsupportedVersion,
+    #[error("Payload doesn't match the commitment")]
+    CommitmentMismatch,
+    #[error("Response doesn't match the request")]
+    ResponseMismatch,
+    #[error("Hash doesn't match the signed data")]
+    HashMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_commitment() {
+        let public_key = PublicKey::from(&ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap());
+
+        let request = RemoteSigningRequest::new(
+            "1",
+            RemoteSigningPayload::SignData(SignDataParams {
+                public_key,
+                data: b"hello".to_vec(),
+                signature_id: Some(42),
+            }),
+        )
+        .unwrap();
+        request.verify().unwrap();
+
+        let json = serde_json::to_string(&request).unwrap();
+        assert!(json.contains(r#""method":"signData""#));
+
+        let mut parsed: RemoteSigningRequest = serde_json::from_str(&json).unwrap();
+        parsed.verify().unwrap();
+
+        // Tampered payload
+        if let RemoteSigningPayload::SignData(params) = &mut parsed.payload {
+            params.data = b"bye".to_vec();
+        }
+        assert!(parsed.verify().is_err());
+
+        let response =
+            RemoteSigningResponse::new(&request, RemoteSigningResult::Signature([0; 64]));
+        let json = serde_json::to_string(&response).unwrap();
+        let response: RemoteSigningResponse = serde_json::from_str(&json).unwrap();
+        response.verify(&request).unwrap();
+        assert!(response.verify(&parsed).is_err());
+    }
+
+    #[test]
+    fn canonical_commitment() {
+        let public_key = PublicKey::from(&ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap());
+        let payload = RemoteSigningPayload::SignData(SignDataParams {
+            public_key,
+            data: b"hello".to_vec(),
+            signature_id: Some(42),
+        });
+
+        // Commitment doesn't depend on the JSON representation
+        let mut expected = sha2::Sha256::new();
+        expected.update([0x02]);
+        expected.update(public_key.as_bytes());
+        expected.update(42i32.to_be_bytes());
+        expected.update(b"hello");
+        let expected: [u8; 32] = expected.finalize().into();
+        assert_eq!(payload.commitment().unwrap(), expected);
+    }
+
+    #[test]
+    fn message_hash_binding() {
+        let public_key = PublicKey::from(&ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap());
+        let signed_data = ton_types::BuilderData::with_raw(vec![0x55; 4], 32)
+            .unwrap()
+            .into_cell()
+            .unwrap();
+
+        let mut params = SignMessageParams {
+            public_key,
+            boc: Cell::default(),
+            hash: signed_data.repr_hash().to_vec(),
+            signed_data,
+            expire_at: 100,
+            signature_id: None,
+        };
+        let request =
+            RemoteSigningRequest::new("1", RemoteSigningPayload::SignMessage(params.clone()))
+                .unwrap();
+        request.verify().unwrap();
+
+        // Hash of another message
+        params.hash = [0x11; 32].to_vec();
+        assert!(RemoteSigningRequest::new("2", RemoteSigningPayload::SignMessage(params)).is_err());
+    }
+}
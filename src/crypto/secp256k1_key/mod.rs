@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+use anyhow::Result;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use ed25519_dalek::PublicKey;
+use rand::Rng;
+use secstr::SecUtf8;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use zeroize::Zeroizing;
+
+use nekoton_utils::*;
+
+use super::{
+    default_key_name, KdfParams, KeyAlgorithm, Password, PubKey, Secp256k1PublicKey,
+    Secp256k1Signature, Secp256k1Signer, SharedSecret, Signature, SignatureId,
+    Signer as StoreSigner, SignerContext, SignerEntry, SignerStorage,
+};
+
+/// Domain separator of the ed25519 key which identifies the entry in the keystore
+const IDENTITY_KEY_PREFIX: &[u8] = b"nekoton-secp256k1-identity";
+
+const CREDENTIAL_LEN: usize = 32;
+
+#[derive(Default, Clone, Debug, Eq, PartialEq)]
+pub struct Secp256k1KeySigner {
+    keys: KeysMap,
+    kdf_params: KdfParams,
+}
+
+type KeysMap = HashMap<PubKey, Secp256k1Key>;
+
+impl Secp256k1KeySigner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get_key(&self, public_key: &PublicKey) -> Result<&Secp256k1Key> {
+        match self.keys.get(public_key.as_bytes()) {
+            Some(key) => Ok(key),
+            None => Err(Secp256k1KeyError::KeyNotFound.into()),
+        }
+    }
+
+    fn get_key_mut(&mut self, public_key: &PublicKey) -> Result<&mut Secp256k1Key> {
+        match self.keys.get_mut(public_key.as_bytes()) {
+            Some(key) => Ok(key),
+            None => Err(Secp256k1KeyError::KeyNotFound.into()),
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl StoreSigner for Secp256k1KeySigner {
+    type CreateKeyInput = Secp256k1KeyCreateInput;
+    type ExportSeedInput = Secp256k1KeyPassword;
+    type ExportSeedOutput = Secp256k1KeyExportOutput;
+    type ExportKeypairInput = Secp256k1KeyPassword;
+    type ExportKeypairOutput = Secp256k1KeyExportOutput;
+    type GetPublicKeys = Secp256k1KeyGetPublicKeys;
+    type UpdateKeyInput = Secp256k1KeyUpdateParams;
+    type SignInput = Secp256k1KeyPassword;
+
+    const ALGORITHM: KeyAlgorithm = KeyAlgorithm::Secp256k1;
+
+    async fn add_key(
+        &mut self,
+        ctx: SignerContext<'_>,
+        input: Self::CreateKeyInput,
+    ) -> Result<SignerEntry> {
+        let secret = match input.secret {
+            Some(secret) => parse_secret(secret.unsecure())?,
+            None => k256::ecdsa::SigningKey::random(&mut rand::thread_rng()),
+        };
+        let identity = identity_key(&secret);
+
+        if self.keys.contains_key(identity.as_bytes()) {
+            return Err(Secp256k1KeyError::KeyAlreadyExists.into());
+        }
+
+        let password = ctx
+            .password_cache
+            .process_password(identity.to_bytes(), input.password)?;
+
+        let name = input
+            .name
+            .unwrap_or_else(|| default_key_name(identity.as_bytes()));
+        let key = Secp256k1Key::new(name, identity, &secret, password.as_ref(), self.kdf_params)?;
+
+        let entry = key.entry();
+        self.keys.insert(identity.to_bytes(), key);
+
+        password.proceed();
+        Ok(entry)
+    }
+
+    async fn update_key(
+        &mut self,
+        ctx: SignerContext<'_>,
+        input: Self::UpdateKeyInput,
+    ) -> Result<SignerEntry> {
+        match input {
+            Self::UpdateKeyInput::Rename { public_key, name } => {
+                let key = self.get_key_mut(&public_key)?;
+                key.name = name;
+                Ok(key.entry())
+            }
+            Self::UpdateKeyInput::ChangePassword {
+                public_key,
+                old_password,
+                new_password,
+            } => {
+                let old_password = ctx
+                    .password_cache
+                    .process_password(public_key.to_bytes(), old_password)?;
+                let new_password = ctx
+                    .password_cache
+                    .process_password(public_key.to_bytes(), new_password)?;
+
+                let kdf_params = self.kdf_params;
+                let key = self.get_key_mut(&public_key)?;
+                key.change_password(old_password.as_ref(), new_password.as_ref(), kdf_params)?;
+
+                new_password.proceed();
+                Ok(key.entry())
+            }
+        }
+    }
+
+    async fn export_seed(
+        &self,
+        ctx: SignerContext<'_>,
+        input: Self::ExportSeedInput,
+    ) -> Result<Self::ExportSeedOutput> {
+        self.export_keypair(ctx, input).await
+    }
+
+    async fn export_keypair(
+        &self,
+        ctx: SignerContext<'_>,
+        input: Self::ExportKeypairInput,
+    ) -> Result<Self::ExportKeypairOutput> {
+        let key = self.get_key(&input.public_key)?;
+        let password = ctx
+            .password_cache
+            .process_password(input.public_key.to_bytes(), input.password)?;
+
+        let secret = key.decrypt_secret(password.as_ref())?;
+
+        password.proceed();
+        Ok(Secp256k1KeyExportOutput {
+            secret: SecUtf8::from(hex::encode(secret.to_bytes())),
+            public_key: key.secp256k1_public_key,
+        })
+    }
+
+    /// Does nothing useful, only exists for compatibility with other signers
+    async fn get_public_keys(
+        &self,
+        _: SignerContext<'_>,
+        input: Self::GetPublicKeys,
+    ) -> Result<Vec<PublicKey>> {
+        let _key = self.get_key(&input.public_key)?;
+        Ok(vec![input.public_key])
+    }
+
+    /// Not supported, secp256k1 keys can't be used for the ed25519 key exchange
+    async fn compute_shared_secrets(
+        &self,
+        _: SignerContext<'_>,
+        _: &[PublicKey],
+        _: Self::SignInput,
+    ) -> Result<Vec<SharedSecret>> {
+        Err(Secp256k1KeyError::ALGORITHM_MISMATCH.into())
+    }
+
+    /// Not supported, use [`Secp256k1Signer::sign_secp256k1`] instead
+    async fn sign(
+        &self,
+        _: SignerContext<'_>,
+        _: &[u8],
+        _: Option<SignatureId>,
+        _: Self::SignInput,
+    ) -> Result<Signature> {
+        Err(Secp256k1KeyError::ALGORITHM_MISMATCH.into())
+    }
+
+    fn upgrade_key(&mut self, ctx: SignerContext<'_>, input: &Self::SignInput) -> Result<bool> {
+        let kdf_params = self.kdf_params;
+        let key = self.get_key_mut(&input.public_key)?;
+        if key.kdf == kdf_params {
+            return Ok(false);
+        }
+
+        let password = ctx
+            .password_cache
+            .process_password(input.public_key.to_bytes(), input.password.clone())?;
+
+        key.change_password(password.as_ref(), password.as_ref(), kdf_params)?;
+
+        password.proceed();
+        Ok(true)
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Secp256k1Signer for Secp256k1KeySigner {
+    async fn get_secp256k1_public_key(
+        &self,
+        _: SignerContext<'_>,
+        public_key: &PublicKey,
+    ) -> Result<Secp256k1PublicKey> {
+        Ok(self.get_key(public_key)?.secp256k1_public_key)
+    }
+
+    async fn sign_secp256k1(
+        &self,
+        ctx: SignerContext<'_>,
+        hash: &[u8; 32],
+        input: Self::SignInput,
+    ) -> Result<Secp256k1Signature> {
+        let key = self.get_key(&input.public_key)?;
+
+        let password = ctx
+            .password_cache
+            .process_password(input.public_key.to_bytes(), input.password)?;
+
+        let secret = key.decrypt_secret(password.as_ref())?;
+        let (signature, recovery_id) = secret
+            .sign_prehash_recoverable(hash)
+            .map_err(|_| Secp256k1KeyError::FailedToSign)?;
+
+        let mut result = [0; 65];
+        result[..64].copy_from_slice(&signature.to_bytes());
+        result[64] = recovery_id.to_byte();
+
+        password.proceed();
+        Ok(result)
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl SignerStorage for Secp256k1KeySigner {
+    fn load_state(&mut self, data: &str) -> Result<()> {
+        let data = serde_json::from_str::<Vec<(String, Secp256k1Key)>>(data)?;
+
+        self.keys = data
+            .into_iter()
+            .map(|(public_key, data)| {
+                let public_key = hex::decode(public_key)?
+                    .try_into()
+                    .map_err(|_| Secp256k1KeyError::InvalidPublicKey)?;
+                Ok((public_key, data))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(())
+    }
+
+    fn set_kdf_params(&mut self, kdf_params: KdfParams) {
+        self.kdf_params = kdf_params;
+    }
+
+    fn store_state(&self) -> String {
+        let data = self
+            .keys
+            .iter()
+            .map(|(public_key, key)| (hex::encode(public_key), key))
+            .collect::<Vec<_>>();
+
+        serde_json::to_string(&data).trust_me()
+    }
+
+    fn get_entries(&self) -> Vec<SignerEntry> {
+        self.keys.values().map(Secp256k1Key::entry).collect()
+    }
+
+    async fn remove_key(&mut self, public_key: &PublicKey) -> Option<SignerEntry> {
+        let key = self.keys.remove(public_key.as_bytes())?;
+        Some(key.entry())
+    }
+
+    async fn clear(&mut self) {
+        self.keys.clear();
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Secp256k1KeyCreateInput {
+    pub name: Option<String>,
+    /// Hex encoded secret key, the new one is generated if not specified
+    pub secret: Option<SecUtf8>,
+    pub password: Password,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Secp256k1KeyPassword {
+    #[serde(with = "serde_public_key")]
+    pub public_key: PublicKey,
+    pub password: Password,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Secp256k1KeyExportOutput {
+    /// Hex encoded secret key
+    pub secret: SecUtf8,
+    #[serde(with = "serde_hex_array")]
+    pub public_key: Secp256k1PublicKey,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Secp256k1KeyGetPublicKeys {
+    #[serde(with = "serde_public_key")]
+    pub public_key: PublicKey,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "data")]
+pub enum Secp256k1KeyUpdateParams {
+    Rename {
+        #[serde(with = "serde_public_key")]
+        public_key: PublicKey,
+        name: String,
+    },
+    ChangePassword {
+        #[serde(with = "serde_public_key")]
+        public_key: PublicKey,
+        old_password: Password,
+        new_password: Password,
+    },
+}
+
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct Secp256k1Key {
+    name: String,
+    /// Ed25519 key which identifies the entry
+    #[serde(with = "serde_public_key")]
+    pubkey: PublicKey,
+    #[serde(with = "serde_hex_array")]
+    secp256k1_public_key: Secp256k1PublicKey,
+    #[serde(with = "serde_bytes")]
+    encrypted_secret: Vec<u8>,
+    #[serde(with = "serde_nonce")]
+    secret_nonce: Nonce,
+    #[serde(with = "serde_bytes")]
+    salt: Vec<u8>,
+    kdf: KdfParams,
+}
+
+impl Secp256k1Key {
+    fn new(
+        name: String,
+        pubkey: PublicKey,
+        secret: &k256::ecdsa::SigningKey,
+        password: &str,
+        kdf: KdfParams,
+    ) -> Result<Self> {
+        let rng = &mut rand::thread_rng();
+
+        let secret_nonce = Nonce::from(rng.gen::<[u8; NONCE_LENGTH]>());
+        let mut salt = vec![0u8; CREDENTIAL_LEN];
+        rng.fill(salt.as_mut_slice());
+
+        let encryptor = ChaCha20Poly1305::new(&kdf.derive_key(password, &salt)?);
+        let encrypted_secret = encrypt(&encryptor, &secret_nonce, &secret.to_bytes())?;
+
+        Ok(Self {
+            name,
+            pubkey,
+            secp256k1_public_key: compressed_public_key(secret),
+            encrypted_secret,
+            secret_nonce,
+            salt,
+            kdf,
+        })
+    }
+
+    fn entry(&self) -> SignerEntry {
+        SignerEntry {
+            name: self.name.clone(),
+            public_key: self.pubkey,
+            master_key: self.pubkey,
+            account_id: 0,
+            algorithm: KeyAlgorithm::Secp256k1,
+        }
+    }
+
+    fn change_password(
+        &mut self,
+        old_password: &str,
+        new_password: &str,
+        kdf: KdfParams,
+    ) -> Result<()> {
+        let secret = self.decrypt_secret(old_password)?;
+        *self = Self::new(self.name.clone(), self.pubkey, &secret, new_password, kdf)?;
+        Ok(())
+    }
+
+    fn derive_key(&self, password: &str) -> Result<Key, Secp256k1KeyError> {
+        self.kdf
+            .derive_key(password, &self.salt)
+            .map_err(|_| Secp256k1KeyError::InvalidKdfParams)
+    }
+
+    fn decrypt_secret(&self, password: &str) -> Result<k256::ecdsa::SigningKey> {
+        let key = self.derive_key(password)?;
+        let decrypter = ChaCha20Poly1305::new(&key);
+
+        let bytes = decrypt_secure(&decrypter, &self.secret_nonce, &self.encrypted_secret)
+            .map_err(|_| Secp256k1KeyError::FailedToDecryptData)?;
+
+        k256::ecdsa::SigningKey::from_slice(bytes.unsecure())
+            .map_err(|_| Secp256k1KeyError::InvalidPrivateKey.into())
+    }
+}
+
+impl std::fmt::Debug for Secp256k1Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.pubkey)
+    }
+}
+
+fn parse_secret(secret: &str) -> Result<k256::ecdsa::SigningKey> {
+    let bytes = Zeroizing::new(
+        hex::decode(secret.trim_start_matches("0x"))
+            .map_err(|_| Secp256k1KeyError::InvalidPrivateKey)?,
+    );
+    k256::ecdsa::SigningKey::from_slice(&bytes)
+        .map_err(|_| Secp256k1KeyError::InvalidPrivateKey.into())
+}
+
+/// Deterministically derives the ed25519 public key, used as the keystore entry id
+fn identity_key(secret: &k256::ecdsa::SigningKey) -> PublicKey {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(IDENTITY_KEY_PREFIX);
+    hasher.update(secret.to_bytes());
+    let seed = Zeroizing::new(<[u8; 32]>::from(hasher.finalize()));
+
+    let secret = ed25519_dalek::SecretKey::from_bytes(seed.as_slice()).trust_me();
+    PublicKey::from(&secret)
+}
+
+fn compressed_public_key(secret: &k256::ecdsa::SigningKey) -> Secp256k1PublicKey {
+    secret
+        .verifying_key()
+        .to_encoded_point(true)
+        .as_bytes()
+        .try_into()
+        .trust_me()
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum Secp256k1KeyError {
+    #[error("Invalid private key")]
+    InvalidPrivateKey,
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+    #[error("Failed to decrypt data")]
+    FailedToDecryptData,
+    #[error("Failed to sign data")]
+    FailedToSign,
+    #[error("Key not found")]
+    KeyNotFound,
+    #[error("Key already exists")]
+    KeyAlreadyExists,
+    #[error("Invalid key derivation params")]
+    InvalidKdfParams,
+    #[error("Key algorithm mismatch: expected {expected}, found {found}")]
+    AlgorithmMismatch {
+        expected: KeyAlgorithm,
+        found: KeyAlgorithm,
+    },
+}
+
+impl Secp256k1KeyError {
+    const ALGORITHM_MISMATCH: Self = Self::AlgorithmMismatch {
+        expected: KeyAlgorithm::Ed25519,
+        found: KeyAlgorithm::Secp256k1,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{verify_secp256k1_signature, PasswordCache, PasswordCacheBehavior};
+    use super::*;
+
+    #[tokio::test]
+    async fn sign_secp256k1() {
+        let password_cache = PasswordCache::new();
+        let ctx = SignerContext {
+            password_cache: &password_cache,
+        };
+        let password = || Password::Explicit {
+            password: SecUtf8::from("supasecret"),
+            cache_behavior: PasswordCacheBehavior::Store(std::time::Duration::from_secs(1)),
+        };
+
+        let mut signer = Secp256k1KeySigner::new();
+        let entry = signer
+            .add_key(
+                ctx,
+                Secp256k1KeyCreateInput {
+                    name: None,
+                    secret: Some(SecUtf8::from("01".repeat(32))),
+                    password: password(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(entry.algorithm, KeyAlgorithm::Secp256k1);
+
+        let public_key = signer
+            .get_secp256k1_public_key(ctx, &entry.public_key)
+            .await
+            .unwrap();
+
+        let input = || Secp256k1KeyPassword {
+            public_key: entry.public_key,
+            password: password(),
+        };
+
+        let hash = [42; 32];
+        let signature = signer.sign_secp256k1(ctx, &hash, input()).await.unwrap();
+        verify_secp256k1_signature(&public_key, &hash, &signature).unwrap();
+        assert!(verify_secp256k1_signature(&public_key, &[0; 32], &signature).is_err());
+
+        // Ed25519 operations are rejected
+        assert!(signer.sign(ctx, &hash, None, input()).await.is_err());
+
+        // State roundtrip
+        let mut restored = Secp256k1KeySigner::new();
+        restored.load_state(&signer.store_state()).unwrap();
+        assert_eq!(restored, signer);
+    }
+}
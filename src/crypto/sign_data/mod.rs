@@ -0,0 +1,230 @@
+use anyhow::Result;
+use ed25519_dalek::PublicKey;
+use serde::{Deserialize, Serialize};
+use ton_block::{MsgAddressInt, Serializable};
+use ton_types::{BuilderData, Cell, UInt256};
+
+use nekoton_utils::*;
+
+use super::{verify_signature, Signature, Signer};
+use crate::core::keystore::KeyStore;
+
+/// Prefix of the signed data cell
+pub const SIGN_DATA_PREFIX: u32 = 0x75569022;
+
+/// Off-chain data signed by the wallet owner (e.g. a dApp login challenge).
+///
+/// The hash of the cell is signed:
+/// ```text
+/// sign_data#75569022 schema_crc:uint32 timestamp:uint64 signer:MsgAddress
+///     domain:^SnakeData payload:^Cell = SignData;
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignDataPayload {
+    /// CRC32 of the payload TL-B schema
+    pub schema_crc: u32,
+    /// Unix time of signing
+    pub timestamp: u64,
+    /// Wallet of the signer
+    #[serde(with = "serde_address")]
+    pub signer: MsgAddressInt,
+    /// Domain of the dApp which requested the signature
+    pub domain: String,
+    #[serde(with = "serde_cell")]
+    pub payload: Cell,
+}
+
+impl SignDataPayload {
+    pub fn new(
+        clock: &dyn Clock,
+        schema_crc: u32,
+        signer: MsgAddressInt,
+        domain: String,
+        payload: Cell,
+    ) -> Self {
+        Self {
+            schema_crc,
+            timestamp: clock.now_sec_u64(),
+            signer,
+            domain,
+            payload,
+        }
+    }
+
+    pub fn to_cell(&self) -> Result<Cell> {
+        let domain = nekoton_abi::create_snake_payload(BuilderData::new(), self.domain.as_bytes())?
+            .into_cell();
+
+        let mut builder = BuilderData::new();
+        builder
+            .append_u32(SIGN_DATA_PREFIX)?
+            .append_u32(self.schema_crc)?
+            .append_u64(self.timestamp)?;
+        self.signer.write_to(&mut builder)?;
+        builder.checked_append_reference(domain)?;
+        builder.checked_append_reference(self.payload.clone())?;
+        builder.into_cell()
+    }
+
+    /// Hash which is signed
+    pub fn hash(&self) -> Result<UInt256> {
+        Ok(self.to_cell()?.repr_hash())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedData {
+    #[serde(flatten)]
+    pub data: SignDataPayload,
+    #[serde(with = "serde_hex_array")]
+    pub signature: Signature,
+}
+
+/// Signs the data with the keystore entry
+pub async fn sign_data<T>(
+    keystore: &KeyStore,
+    data: SignDataPayload,
+    input: T::SignInput,
+) -> Result<SignedData>
+where
+    T: Signer,
+{
+    let hash = data.hash()?;
+    // NOTE: signature id is not used, the data is bound to the domain instead
+    let signature = keystore.sign::<T>(hash.as_slice(), None, input).await?;
+    Ok(SignedData { data, signature })
+}
+
+/// Checks the signature and the replay protection fields.
+///
+/// # Arguments
+///
+/// * `domain` - domain of the current dApp
+/// * `max_age` - max number of seconds since signing
+pub fn verify_signed_data(
+    public_key: &PublicKey,
+    signed: &SignedData,
+    domain: &str,
+    clock: &dyn Clock,
+    max_age: u64,
+) -> Result<()> {
+    const MAX_CLOCK_SKEW: u64 = 60;
+
+    if signed.data.domain != domain {
+        return Err(SignDataError::DomainMismatch.into());
+    }
+
+    let now = clock.now_sec_u64();
+    if signed.data.timestamp > now.saturating_add(MAX_CLOCK_SKEW) {
+        return Err(SignDataError::InvalidTimestamp.into());
+    }
+    if signed.data.timestamp.saturating_add(max_age) < now {
+        return Err(SignDataError::Expired.into());
+    }
+
+    let hash = signed.data.hash()?;
+    verify_signature(public_key, hash.as_slice(), None, &signed.signature)
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum SignDataError {
+    #[error("Signed data is for another domain")]
+    DomainMismatch,
+    #[error("Signed data timestamp is in the future")]
+    InvalidTimestamp,
+    #[error("Signed data expired")]
+    Expired,
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Signer as _;
+
+    use super::*;
+
+    #[test]
+    fn signed_data() {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let keypair = ed25519_dalek::Keypair { secret, public };
+
+        let clock = ConstClock::from_secs(1000);
+        let data = SignDataPayload::new(
+            &clock,
+            0x12345678,
+            MsgAddressInt::default(),
+            "example.com".to_owned(),
+            Cell::default(),
+        );
+
+        let hash = data.hash().unwrap();
+        let signed = SignedData {
+            data,
+            signature: keypair.sign(hash.as_slice()).to_bytes(),
+        };
+
+        let json = serde_json::to_string(&signed).unwrap();
+        let signed: SignedData = serde_json::from_str(&json).unwrap();
+
+        verify_signed_data(&public, &signed, "example.com", &clock, 300).unwrap();
+        assert!(verify_signed_data(&public, &signed, "evil.com", &clock, 300).is_err());
+
+        let later = ConstClock::from_secs(2000);
+        assert!(verify_signed_data(&public, &signed, "example.com", &later, 300).is_err());
+
+        let mut tampered = signed;
+        tampered.data.timestamp += 1;
+        assert!(verify_signed_data(&public, &tampered, "example.com", &clock, 300).is_err());
+    }
+
+    #[test]
+    fn signed_data_age_boundary() {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let keypair = ed25519_dalek::Keypair { secret, public };
+
+        let sign = |timestamp: u64| {
+            let data = SignDataPayload {
+                schema_crc: 0x12345678,
+                timestamp,
+                signer: MsgAddressInt::default(),
+                domain: "example.com".to_owned(),
+                payload: Cell::default(),
+            };
+            let hash = data.hash().unwrap();
+            SignedData {
+                data,
+                signature: keypair.sign(hash.as_slice()).to_bytes(),
+            }
+        };
+        let verify = |signed: &SignedData, now: u64, max_age: u64| {
+            verify_signed_data(
+                &public,
+                signed,
+                "example.com",
+                &ConstClock::from_secs(now),
+                max_age,
+            )
+            .map_err(|e| *e.downcast_ref::<SignDataError>().unwrap())
+        };
+
+        let signed = sign(1000);
+        verify(&signed, 1300, 300).unwrap();
+        assert!(matches!(
+            verify(&signed, 1301, 300),
+            Err(SignDataError::Expired)
+        ));
+
+        // Huge max age doesn't overflow
+        verify(&signed, 1301, u64::MAX).unwrap();
+
+        // Far future timestamp is rejected without overflow
+        let signed = sign(u64::MAX);
+        assert!(matches!(
+            verify(&signed, 1000, 300),
+            Err(SignDataError::InvalidTimestamp)
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use ed25519_dalek::PublicKey;
+
+use nekoton_utils::*;
+
+use super::{
+    verify_signature, PubKey, Signature, SignatureId, SignedMessage, Signer, UnsignedMessage,
+};
+use crate::core::keystore::KeyStore;
+
+/// Collects signatures of one unsigned message from multiple custodians.
+///
+/// Signatures can be produced by the local keystore or received from external
+/// co-signers (e.g. gathered offline), each of them is verified before it is accepted
+pub struct SigningSession {
+    message: Box<dyn UnsignedMessage>,
+    signature_id: Option<SignatureId>,
+    custodians: Vec<PublicKey>,
+    required_signatures: usize,
+    signatures: HashMap<PubKey, Signature>,
+}
+
+impl SigningSession {
+    pub fn new(
+        message: Box<dyn UnsignedMessage>,
+        signature_id: Option<SignatureId>,
+        custodians: Vec<PublicKey>,
+        required_signatures: usize,
+    ) -> Result<Self> {
+        if required_signatures == 0 || required_signatures > custodians.len() {
+            return Err(SigningSessionError::InvalidThreshold.into());
+        }
+
+        Ok(Self {
+            message,
+            signature_id,
+            custodians,
+            required_signatures,
+            signatures: Default::default(),
+        })
+    }
+
+    /// Hash which must be signed by custodians
+    pub fn hash(&self) -> &[u8] {
+        self.message.hash()
+    }
+
+    pub fn signature_id(&self) -> Option<SignatureId> {
+        self.signature_id
+    }
+
+    pub fn expire_at(&self) -> u32 {
+        self.message.expire_at()
+    }
+
+    pub fn is_expired(&self, clock: &dyn Clock) -> bool {
+        self.expire_at() <= clock.now_sec_u64() as u32
+    }
+
+    pub fn custodians(&self) -> &[PublicKey] {
+        &self.custodians
+    }
+
+    pub fn required_signatures(&self) -> usize {
+        self.required_signatures
+    }
+
+    /// Custodians which have already signed the message
+    pub fn signed(&self) -> Vec<PublicKey> {
+        self.custodians
+            .iter()
+            .filter(|custodian| self.signatures.contains_key(custodian.as_bytes()))
+            .copied()
+            .collect()
+    }
+
+    /// Custodians which have not signed the message yet
+    pub fn pending(&self) -> Vec<PublicKey> {
+        self.custodians
+            .iter()
+            .filter(|custodian| !self.signatures.contains_key(custodian.as_bytes()))
+            .copied()
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.signatures.len() >= self.required_signatures
+    }
+
+    /// Adds the signature received from an external co-signer
+    pub fn add_signature(&mut self, public_key: &PublicKey, signature: Signature) -> Result<()> {
+        if !self.custodians.contains(public_key) {
+            return Err(SigningSessionError::UnknownCustodian.into());
+        }
+        if self.signatures.contains_key(public_key.as_bytes()) {
+            return Err(SigningSessionError::AlreadySigned.into());
+        }
+
+        verify_signature(public_key, self.hash(), self.signature_id, &signature)?;
+        self.signatures.insert(public_key.to_bytes(), signature);
+        Ok(())
+    }
+
+    /// Signs the message with the keystore entry
+    pub async fn sign_with<T>(
+        &mut self,
+        keystore: &KeyStore,
+        public_key: &PublicKey,
+        input: T::SignInput,
+    ) -> Result<()>
+    where
+        T: Signer,
+    {
+        let signature = keystore
+            .sign::<T>(self.hash(), self.signature_id, input)
+            .await?;
+        self.add_signature(public_key, signature)
+    }
+
+    /// Collected signatures in the order of custodians
+    pub fn signatures(&self) -> Vec<(PublicKey, Signature)> {
+        self.custodians
+            .iter()
+            .filter_map(|custodian| {
+                let signature = self.signatures.get(custodian.as_bytes())?;
+                Some((*custodian, *signature))
+            })
+            .collect()
+    }
+
+    /// Creates the final message, signed by the first custodian which has signed it
+    pub fn assemble(&self) -> Result<SignedMessage> {
+        self.assemble_with(|message, signatures| message.sign(&signatures[0].1))
+    }
+
+    /// Creates the final message from all collected signatures
+    pub fn assemble_with<F>(&self, f: F) -> Result<SignedMessage>
+    where
+        F: FnOnce(&dyn UnsignedMessage, &[(PublicKey, Signature)]) -> Result<SignedMessage>,
+    {
+        if !self.is_complete() {
+            return Err(SigningSessionError::NotEnoughSignatures.into());
+        }
+        f(self.message.as_ref(), &self.signatures())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum SigningSessionError {
+    #[error("Invalid number of required signatures")]
+    InvalidThreshold,
+    #[error("Public key is not a custodian")]
+    UnknownCustodian,
+    #[error("Custodian has already signed the message")]
+    AlreadySigned,
+    #[error("Not enough signatures")]
+    NotEnoughSignatures,
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Signer as _;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct TestMessage;
+
+    impl UnsignedMessage for TestMessage {
+        fn refresh_timeout(&mut self, _: &dyn Clock) {}
+
+        fn expire_at(&self) -> u32 {
+            1000
+        }
+
+        fn hash(&self) -> &[u8] {
+            &[0x55; 32]
+        }
+
+        fn sign(&self, _: &Signature) -> Result<SignedMessage> {
+            Ok(SignedMessage {
+                message: ton_block::Message::default(),
+                expire_at: self.expire_at(),
+            })
+        }
+
+        fn sign_with_pruned_payload(&self, signature: &Signature, _: u16) -> Result<SignedMessage> {
+            self.sign(signature)
+        }
+    }
+
+    fn make_keypair(seed: u8) -> ed25519_dalek::Keypair {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        ed25519_dalek::Keypair { secret, public }
+    }
+
+    #[test]
+    fn collect_signatures() {
+        let keys = [make_keypair(1), make_keypair(2), make_keypair(3)];
+        let custodians = keys.iter().map(|key| key.public).collect::<Vec<_>>();
+
+        assert!(SigningSession::new(Box::new(TestMessage), None, custodians.clone(), 4).is_err());
+
+        let signature_id = Some(42);
+        let mut session =
+            SigningSession::new(Box::new(TestMessage), signature_id, custodians, 2).unwrap();
+        let sign = |key: &ed25519_dalek::Keypair| {
+            let data = crate::crypto::extend_with_signature_id(&[0x55; 32], signature_id);
+            key.sign(&data).to_bytes()
+        };
+
+        // Signature without signature id is rejected
+        let invalid = keys[0].sign(&[0x55; 32]).to_bytes();
+        assert!(session.add_signature(&keys[0].public, invalid).is_err());
+
+        session
+            .add_signature(&keys[2].public, sign(&keys[2]))
+            .unwrap();
+        assert!(session
+            .add_signature(&keys[2].public, sign(&keys[2]))
+            .is_err());
+        assert!(session
+            .add_signature(&make_keypair(4).public, sign(&make_keypair(4)))
+            .is_err());
+
+        assert!(!session.is_complete());
+        assert!(session.assemble().is_err());
+        assert_eq!(session.pending(), vec![keys[0].public, keys[1].public]);
+
+        session
+            .add_signature(&keys[0].public, sign(&keys[0]))
+            .unwrap();
+        assert!(session.is_complete());
+        assert_eq!(session.signed(), vec![keys[0].public, keys[2].public]);
+
+        let signed = session.assemble().unwrap();
+        assert_eq!(signed.expire_at, 1000);
+        assert!(session.is_expired(&ConstClock::from_secs(1000)));
+    }
+}
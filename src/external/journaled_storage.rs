@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+
+use super::{Storage, StorageBatch, StorageChange};
+
+/// Storage wrapper which records all successfully applied changes.
+///
+/// Can be used to sync state between several instances (e.g. browser tabs)
+/// or to replay changes to the other storage.
+///
+/// **NOTE:** `set_unchecked` and `remove_unchecked` are not recorded,
+/// because it is unknown whether they were applied
+pub struct JournaledStorage {
+    inner: Arc<dyn Storage>,
+    journal: Mutex<Journal>,
+    /// Keeps the journal in the same order as changes were applied
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl JournaledStorage {
+    /// Wraps the storage, remembering at most `capacity` last changes
+    pub fn new(inner: Arc<dyn Storage>, capacity: usize) -> Self {
+        Self {
+            inner,
+            journal: Mutex::new(Journal {
+                capacity,
+                next_seqno: 0,
+                changes: Default::default(),
+            }),
+            write_lock: Default::default(),
+        }
+    }
+
+    /// Sequence number of the next change
+    pub fn next_seqno(&self) -> u64 {
+        self.journal.lock().next_seqno
+    }
+
+    /// Returns all changes starting from the specified sequence number.
+    ///
+    /// Returns `None` if some of them were already evicted from the journal
+    pub fn changes_since(&self, seqno: u64) -> Option<Vec<(u64, StorageChange)>> {
+        let journal = self.journal.lock();
+
+        let first_seqno = journal.next_seqno - journal.changes.len() as u64;
+        if seqno < first_seqno {
+            return None;
+        }
+
+        Some(
+            journal
+                .changes
+                .iter()
+                .skip((seqno - first_seqno) as usize)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn record<I>(&self, changes: I)
+    where
+        I: IntoIterator<Item = StorageChange>,
+    {
+        let mut journal = self.journal.lock();
+        for change in changes {
+            journal.push(change);
+        }
+    }
+}
+
+struct Journal {
+    capacity: usize,
+    next_seqno: u64,
+    changes: VecDeque<(u64, StorageChange)>,
+}
+
+impl Journal {
+    fn push(&mut self, change: StorageChange) {
+        if self.capacity == 0 {
+            self.next_seqno += 1;
+            return;
+        }
+
+        if self.changes.len() >= self.capacity {
+            self.changes.pop_front();
+        }
+        self.changes.push_back((self.next_seqno, change));
+        self.next_seqno += 1;
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Storage for JournaledStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        self.inner.get(key).await
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        self.inner.set(key, value).await?;
+        self.record([StorageChange::Set {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        }]);
+        Ok(())
+    }
+
+    fn set_unchecked(&self, key: &str, value: &str) {
+        self.inner.set_unchecked(key, value);
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        self.inner.remove(key).await?;
+        self.record([StorageChange::Remove {
+            key: key.to_owned(),
+        }]);
+        Ok(())
+    }
+
+    fn remove_unchecked(&self, key: &str) {
+        self.inner.remove_unchecked(key);
+    }
+
+    async fn apply_batch(&self, batch: StorageBatch) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        self.inner.apply_batch(batch.clone()).await?;
+        self.record(batch.into_changes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external::tests::TestStorage;
+
+    #[tokio::test]
+    async fn journal_changes() {
+        let storage = JournaledStorage::new(Arc::new(TestStorage::default()), 3);
+
+        storage.set("a", "1").await.unwrap();
+        let mut batch = StorageBatch::default();
+        batch.set("b", "2").remove("a");
+        storage.apply_batch(batch).await.unwrap();
+
+        assert_eq!(storage.get("a").await.unwrap(), None);
+        assert_eq!(storage.get("b").await.unwrap().as_deref(), Some("2"));
+        assert_eq!(storage.next_seqno(), 3);
+
+        let changes = storage.changes_since(1).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].0, 1);
+        assert_eq!(changes[1].1.key(), "a");
+
+        storage.remove("b").await.unwrap();
+        assert!(storage.changes_since(0).is_none());
+        assert_eq!(storage.changes_since(1).unwrap().len(), 3);
+        assert!(storage.changes_since(4).unwrap().is_empty());
+    }
+
+    /// Applies writes immediately, but acknowledges them after
+    /// the number of polls, specified in the value
+    #[derive(Default)]
+    struct SlowAckStorage(TestStorage);
+
+    #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+    #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+    impl Storage for SlowAckStorage {
+        async fn get(&self, key: &str) -> Result<Option<String>> {
+            self.0.get(key).await
+        }
+
+        async fn set(&self, key: &str, value: &str) -> Result<()> {
+            self.0.set_unchecked(key, value);
+            for _ in 0..value.parse::<usize>()? {
+                tokio::task::yield_now().await;
+            }
+            Ok(())
+        }
+
+        fn set_unchecked(&self, key: &str, value: &str) {
+            self.0.set_unchecked(key, value);
+        }
+
+        async fn remove(&self, key: &str) -> Result<()> {
+            self.0.remove(key).await
+        }
+
+        fn remove_unchecked(&self, key: &str) {
+            self.0.remove_unchecked(key);
+        }
+    }
+
+    #[tokio::test]
+    async fn journal_keeps_applied_order() {
+        let storage = JournaledStorage::new(Arc::new(SlowAckStorage::default()), 10);
+
+        let (first, second) =
+            futures_util::future::join(storage.set("a", "3"), storage.set("a", "1")).await;
+        first.unwrap();
+        second.unwrap();
+
+        let value = storage.get("a").await.unwrap().unwrap();
+        let changes = storage.changes_since(0).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes.last().unwrap().1,
+            StorageChange::Set {
+                key: "a".to_owned(),
+                value,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn unchecked_writes_are_not_recorded() {
+        let storage = JournaledStorage::new(Arc::new(TestStorage::default()), 3);
+
+        storage.set_unchecked("a", "1");
+        storage.remove_unchecked("b");
+
+        assert_eq!(storage.get("a").await.unwrap().as_deref(), Some("1"));
+        assert_eq!(storage.next_seqno(), 0);
+        assert!(storage.changes_since(0).unwrap().is_empty());
+    }
+}
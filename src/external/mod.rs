@@ -2,6 +2,16 @@ use anyhow::Result;
 use nekoton_utils::serde_optional_hex_array;
 use serde::{Deserialize, Serialize};
 
+pub use self::journaled_storage::JournaledStorage;
+#[cfg(feature = "sled_storage")]
+pub use self::sled_storage::SledStorage;
+
+mod journaled_storage;
+#[cfg(feature = "sled_storage")]
+mod sled_storage;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod tests;
+
 #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
 #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
 pub trait Storage: Sync + Send {
@@ -19,6 +29,70 @@ pub trait Storage: Sync + Send {
 
     /// Remove data without waiting operation result
     fn remove_unchecked(&self, key: &str);
+
+    /// Apply all changes and wait until operation complete.
+    ///
+    /// Default implementation applies changes one by one, so storages
+    /// which support transactions should override it to make it atomic
+    async fn apply_batch(&self, batch: StorageBatch) -> Result<()> {
+        for change in batch.into_changes() {
+            match change {
+                StorageChange::Set { key, value } => self.set(&key, &value).await?,
+                StorageChange::Remove { key } => self.remove(&key).await?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Single storage modification
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum StorageChange {
+    Set { key: String, value: String },
+    Remove { key: String },
+}
+
+impl StorageChange {
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Set { key, .. } | Self::Remove { key } => key,
+        }
+    }
+}
+
+/// Group of changes which are applied together
+#[derive(Debug, Clone, Default)]
+pub struct StorageBatch {
+    changes: Vec<StorageChange>,
+}
+
+impl StorageBatch {
+    pub fn set(&mut self, key: &str, value: &str) -> &mut Self {
+        self.changes.push(StorageChange::Set {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        });
+        self
+    }
+
+    pub fn remove(&mut self, key: &str) -> &mut Self {
+        self.changes.push(StorageChange::Remove {
+            key: key.to_owned(),
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn changes(&self) -> &[StorageChange] {
+        &self.changes
+    }
+
+    pub fn into_changes(self) -> Vec<StorageChange> {
+        self.changes
+    }
 }
 
 #[cfg(feature = "gql_transport")]
@@ -65,6 +139,14 @@ pub trait ProtoConnection: Send + Sync {
     async fn post(&self, req: ProtoRequest) -> Result<Vec<u8>>;
 }
 
+#[cfg(feature = "adnl_transport")]
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+pub trait AdnlConnection: Send + Sync {
+    /// Sends the boxed liteserver function and returns the boxed response
+    async fn query(&self, request: Vec<u8>) -> Result<Vec<u8>>;
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LedgerSignatureContext {
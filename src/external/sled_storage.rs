@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::{Storage, StorageBatch, StorageChange};
+
+/// Native storage, backed by the `sled` tree. Batches are applied atomically
+#[derive(Clone)]
+pub struct SledStorage {
+    tree: sled::Tree,
+}
+
+impl SledStorage {
+    /// Opens the database at the specified path and uses its default tree
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let db = sled::open(path)?;
+        Ok(Self::with_tree((*db).clone()))
+    }
+
+    pub fn with_tree(tree: sled::Tree) -> Self {
+        Self { tree }
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Storage for SledStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        match self.tree.get(key)? {
+            Some(value) => Ok(Some(String::from_utf8(value.to_vec())?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<()> {
+        self.tree.insert(key, value.as_bytes())?;
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+
+    fn set_unchecked(&self, key: &str, value: &str) {
+        if let Err(e) = self.tree.insert(key, value.as_bytes()) {
+            log::error!("Failed to set storage value {key}: {e:?}");
+        }
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.tree.remove(key)?;
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+
+    fn remove_unchecked(&self, key: &str) {
+        if let Err(e) = self.tree.remove(key) {
+            log::error!("Failed to remove storage value {key}: {e:?}");
+        }
+    }
+
+    async fn apply_batch(&self, batch: StorageBatch) -> Result<()> {
+        let mut sled_batch = sled::Batch::default();
+        for change in batch.into_changes() {
+            match change {
+                StorageChange::Set { key, value } => {
+                    sled_batch.insert(key.into_bytes(), value.into_bytes())
+                }
+                StorageChange::Remove { key } => sled_batch.remove(key.into_bytes()),
+            }
+        }
+
+        self.tree.apply_batch(sled_batch)?;
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+}
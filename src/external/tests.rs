@@ -0,0 +1,40 @@
+//! In-memory storage for unit tests.
+//!
+//! Available with the `test-utils` feature
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use parking_lot::Mutex;
+
+use super::Storage;
+
+/// Storage which keeps all values in memory
+#[derive(Default)]
+pub struct TestStorage(Mutex<HashMap<String, String>>);
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Storage for TestStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.0.lock().get(key).cloned())
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<()> {
+        self.set_unchecked(key, value);
+        Ok(())
+    }
+
+    fn set_unchecked(&self, key: &str, value: &str) {
+        self.0.lock().insert(key.to_string(), value.to_string());
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.remove_unchecked(key);
+        Ok(())
+    }
+
+    fn remove_unchecked(&self, key: &str) {
+        self.0.lock().remove(key);
+    }
+}
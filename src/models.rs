@@ -1,7 +1,7 @@
 use std::convert::TryFrom;
 
 use anyhow::Result;
-use num_bigint::BigUint;
+use num_bigint::{BigInt, BigUint};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use ton_block::{Deserializable, MsgAddressInt};
 use ton_types::UInt256;
@@ -56,6 +56,31 @@ pub enum KnownPayload {
     TokenOutgoingTransfer(TokenOutgoingTransfer),
     TokenSwapBack(TokenSwapBack),
     JettonOutgoingTransfer(JettonOutgoingTransfer),
+    Custom(CustomPayload),
+}
+
+/// Payload decoded with the user registered ABI function
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomPayload {
+    /// Function name
+    pub name: String,
+    /// Decoded tokens in JSON representation
+    pub data: serde_json::Value,
+    #[serde(skip)]
+    pub tokens: Vec<ton_abi::Token>,
+}
+
+impl CustomPayload {
+    /// Unpacks decoded tokens into the typed struct.
+    ///
+    /// NOTE: tokens are not serialized, so it will fail for deserialized payloads
+    pub fn unpack<T>(&self) -> UnpackerResult<T>
+    where
+        Vec<ton_abi::Token>: UnpackAbiPlain<T>,
+    {
+        self.tokens.clone().unpack()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -239,6 +264,16 @@ pub struct MultisigPendingTransaction {
     pub payload: ton_types::Cell,
 
     pub bounce: bool,
+
+    /// Creation time plus the contract transactions lifetime
+    #[serde(default)]
+    pub expires_at: u32,
+}
+
+impl MultisigPendingTransaction {
+    pub fn is_expired(&self, now: u32) -> bool {
+        self.expires_at <= now
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -278,11 +313,45 @@ pub enum TokenWalletTransaction {
     SwapBackBounced(BigUint),
 }
 
+impl TokenWalletTransaction {
+    /// Signed change of the token wallet balance
+    pub fn balance_delta(&self) -> BigInt {
+        match self {
+            Self::IncomingTransfer(TokenIncomingTransfer { tokens, .. })
+            | Self::Accept(tokens)
+            | Self::SwapBackBounced(tokens)
+            | Self::TransferBounced(tokens) => BigInt::from(tokens.clone()),
+            Self::OutgoingTransfer(TokenOutgoingTransfer { tokens, .. })
+            | Self::SwapBack(TokenSwapBack { tokens, .. }) => -BigInt::from(tokens.clone()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "data")]
+pub enum DePoolTransaction {
+    /// Participant added an ordinary stake
+    #[serde(with = "serde_string")]
+    OrdinaryStake(u64),
+    /// Participant requested a partial withdrawal
+    #[serde(with = "serde_string")]
+    WithdrawPart(u64),
+    /// Participant requested a withdrawal of the whole stake
+    WithdrawAll,
+    /// Participant cancelled the withdrawal request
+    CancelWithdrawal,
+    /// DePool notified the participant about the round completion
+    RoundComplete(DePoolOnRoundCompleteNotification),
+    /// DePool answered the participant request
+    ReceiveAnswer(DePoolReceiveAnswerNotification),
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type", content = "data")]
 pub enum JettonWalletTransaction {
     Transfer(JettonOutgoingTransfer),
     InternalTransfer(JettonIncomingTransfer),
+    Burn(JettonBurn),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -329,6 +398,14 @@ pub struct JettonOutgoingTransfer {
     pub tokens: BigUint,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JettonBurn {
+    #[serde(with = "serde_string")]
+    pub response_destination: MsgAddressInt,
+    #[serde(with = "serde_string")]
+    pub tokens: BigUint,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IncomingNftTransfer {
@@ -368,6 +445,21 @@ pub enum TransferRecipient {
     TokenWallet(MsgAddressInt),
 }
 
+/// Relay contract which forwards token transfers and takes its fee in the transferred token.
+///
+/// NOTE: this is not a gasless transfer, the owner wallet still attaches
+/// the native currency to the internal message
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferRelay {
+    /// Token wallet of the relay which receives the transfer
+    #[serde(with = "serde_address")]
+    pub token_wallet: MsgAddressInt,
+    /// Fee in tokens, sent on top of the transferred amount
+    #[serde(with = "serde_string")]
+    pub fee: BigUint,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct TokenSwapBack {
     #[serde(with = "serde_string")]
@@ -416,6 +508,16 @@ impl NetworkCapabilities {
     pub fn signature_id(&self) -> Option<i32> {
         (self.raw & Self::CAP_SIGNATURE_WITH_ID != 0).then_some(self.global_id)
     }
+
+    /// Whether the specified software capability is enabled.
+    pub fn has_capability(&self, capability: ton_block::GlobalCapabilities) -> bool {
+        self.raw & (capability as u64) != 0
+    }
+
+    /// Whether bounced messages contain the full body of the original message.
+    pub fn has_full_body_in_bounced(&self) -> bool {
+        self.has_capability(ton_block::GlobalCapabilities::CapFullBodyInBounced)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -500,6 +602,102 @@ pub struct Symbol {
     pub root_token_contract: MsgAddressInt,
 }
 
+/// Token amount with the fixed decimals count
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenAmount {
+    /// Amount in the smallest units
+    #[serde(with = "serde_string")]
+    pub raw: BigUint,
+    /// Fixed decimals count
+    pub decimals: u8,
+}
+
+impl TokenAmount {
+    pub fn new<T: Into<BigUint>>(raw: T, decimals: u8) -> Self {
+        Self {
+            raw: raw.into(),
+            decimals,
+        }
+    }
+
+    /// Unpacks `uint*`, `varuint*` or `token` ABI value
+    pub fn unpack(value: ton_abi::TokenValue, decimals: u8) -> UnpackerResult<Self> {
+        let raw = match value {
+            ton_abi::TokenValue::Uint(value) => value.number,
+            ton_abi::TokenValue::VarUint(_, value) => value,
+            ton_abi::TokenValue::Token(value) => BigUint::from(value.as_u128()),
+            value => return Err(UnpackerError::unexpected::<Self>(&value)),
+        };
+        Ok(Self { raw, decimals })
+    }
+
+    /// Parses decimal string, e.g. `"12.345"`.
+    ///
+    /// Fails if there are more fractional digits than decimals instead of rounding
+    pub fn parse(value: &str, decimals: u8) -> Result<Self> {
+        let (integer, fractional) = match value.split_once('.') {
+            Some((integer, fractional)) => (integer, fractional),
+            None => (value, ""),
+        };
+
+        let is_digits = |s: &str| s.bytes().all(|c| c.is_ascii_digit());
+        if integer.is_empty() || !is_digits(integer) || !is_digits(fractional) {
+            return Err(TokenAmountError::InvalidNumber.into());
+        }
+        if fractional.len() > decimals as usize {
+            return Err(TokenAmountError::TooManyDecimals.into());
+        }
+
+        let mut digits = String::with_capacity(integer.len() + decimals as usize);
+        digits.push_str(integer);
+        digits.push_str(fractional);
+        digits.push_str(&"0".repeat(decimals as usize - fractional.len()));
+
+        let raw = digits
+            .parse::<BigUint>()
+            .map_err(|_| TokenAmountError::InvalidNumber)?;
+        Ok(Self { raw, decimals })
+    }
+
+    /// Formats amount with at most `precision` fractional digits.
+    ///
+    /// Extra digits are truncated (rounded towards zero), trailing zeros are removed
+    pub fn format(&self, precision: u8) -> String {
+        let multiplier = BigUint::from(10u8).pow(self.decimals as u32);
+        let integer = &self.raw / &multiplier;
+        let fractional = &self.raw % &multiplier;
+
+        let mut fractional = format!(
+            "{:0>width$}",
+            fractional.to_string(),
+            width = self.decimals as usize
+        );
+        fractional.truncate(precision.min(self.decimals) as usize);
+        let fractional = fractional.trim_end_matches('0');
+
+        if fractional.is_empty() {
+            integer.to_string()
+        } else {
+            format!("{integer}.{fractional}")
+        }
+    }
+}
+
+impl std::fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.format(self.decimals))
+    }
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+enum TokenAmountError {
+    #[error("Invalid number")]
+    InvalidNumber,
+    #[error("Too many decimals")]
+    TooManyDecimals,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, Copy)]
 #[serde(rename_all = "camelCase")]
 pub struct ContractState {
@@ -529,6 +727,31 @@ impl PartialEq for ContractState {
 
 impl Eq for ContractState {}
 
+/// Code hash change of the already deployed contract
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractUpgrade {
+    #[serde(with = "serde_uint256")]
+    pub old_code_hash: UInt256,
+    #[serde(with = "serde_uint256")]
+    pub new_code_hash: UInt256,
+}
+
+impl ContractUpgrade {
+    /// Returns the upgrade if both code hashes are known and differ
+    pub fn detect(old_code_hash: Option<UInt256>, new_code_hash: Option<UInt256>) -> Option<Self> {
+        match (old_code_hash, new_code_hash) {
+            (Some(old_code_hash), Some(new_code_hash)) if old_code_hash != new_code_hash => {
+                Some(Self {
+                    old_code_hash,
+                    new_code_hash,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PendingTransaction {
@@ -565,6 +788,40 @@ pub enum MessageType {
     External,
 }
 
+/// Balance changes, caused by a single transaction
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceChange {
+    /// Native balance change, including all fees and bounced messages
+    #[serde(with = "serde_string")]
+    pub native_delta: i128,
+    pub token_deltas: Vec<TokenBalanceDelta>,
+    pub caused_by_tx: TransactionId,
+}
+
+impl BalanceChange {
+    /// Native balance change of the transaction account, without token deltas
+    pub fn new(hash: UInt256, transaction: &ton_block::Transaction) -> Self {
+        Self {
+            native_delta: compute_balance_change(transaction),
+            token_deltas: Vec::new(),
+            caused_by_tx: TransactionId {
+                lt: transaction.lt,
+                hash,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBalanceDelta {
+    #[serde(with = "serde_address")]
+    pub root_token_contract: MsgAddressInt,
+    #[serde(with = "serde_string")]
+    pub delta: BigInt,
+}
+
 /// Transaction with additional data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionWithData<T> {
@@ -585,6 +842,48 @@ pub struct TransactionsBatchInfo {
     pub batch_type: TransactionsBatchType,
 }
 
+/// Condition which stops the history backfill
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum BackfillStopCondition {
+    /// Stop before the transaction with the specified lt
+    UntilLt(#[serde(with = "serde_u64")] u64),
+    /// Stop before the first transaction older than the specified unix time
+    UntilUtime(u32),
+    /// Stop when the specified number of matching transactions is found
+    Found(usize),
+}
+
+/// Position of the history backfill, can be stored to resume it later
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillState {
+    /// Lt of the next transaction to load. `None` when the backfill is finished
+    #[serde(with = "serde_optional_u64")]
+    pub next_lt: Option<u64>,
+    /// Number of loaded transactions
+    pub loaded: usize,
+    /// Number of loaded transactions which matched the filter
+    pub found: usize,
+    /// Unix time of the oldest loaded transaction
+    pub oldest_utime: Option<u32>,
+}
+
+impl BackfillState {
+    pub fn new(from_lt: u64) -> Self {
+        Self {
+            next_lt: Some(from_lt),
+            loaded: 0,
+            found: 0,
+            oldest_utime: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_lt.is_none()
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum TransactionsBatchType {
@@ -987,14 +1286,368 @@ pub enum MessageFlagsError {
     UnknownMessageFlags,
 }
 
+/// Structured description of the transaction from the point of view of the wallet.
+///
+/// Contains only the data, so the text can be localized by the application
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "data")]
+pub enum TransactionDescription {
+    /// Incoming transfer of native coins
+    #[serde(rename_all = "camelCase")]
+    IncomingTransfer {
+        #[serde(with = "serde_optional_address")]
+        sender: Option<MsgAddressInt>,
+        #[serde(with = "serde_string")]
+        value: u128,
+        comment: Option<String>,
+    },
+    /// Outgoing transfer of native coins
+    #[serde(rename_all = "camelCase")]
+    OutgoingTransfer {
+        #[serde(with = "serde_optional_address")]
+        recipient: Option<MsgAddressInt>,
+        #[serde(with = "serde_string")]
+        value: u128,
+        comment: Option<String>,
+    },
+    /// Tokens were sent from the wallet
+    #[serde(rename_all = "camelCase")]
+    TokenTransfer {
+        recipient: TransferRecipient,
+        #[serde(with = "serde_string")]
+        tokens: BigUint,
+    },
+    /// Tokens were burned to be swapped to another network
+    #[serde(rename_all = "camelCase")]
+    TokenSwapBack {
+        #[serde(with = "serde_string")]
+        tokens: BigUint,
+        #[serde(with = "serde_address")]
+        callback_address: MsgAddressInt,
+    },
+    /// Token wallet notified the owner about the deployment
+    #[serde(rename_all = "camelCase")]
+    TokenWalletDeployed {
+        #[serde(with = "serde_address")]
+        root_token_contract: MsgAddressInt,
+    },
+    /// Jettons were sent from the wallet
+    #[serde(rename_all = "camelCase")]
+    JettonTransfer {
+        #[serde(with = "serde_address")]
+        recipient: MsgAddressInt,
+        #[serde(with = "serde_string")]
+        tokens: BigUint,
+    },
+    /// Jetton wallet notified the owner about the incoming transfer
+    #[serde(rename_all = "camelCase")]
+    JettonNotification {
+        #[serde(with = "serde_address")]
+        sender: MsgAddressInt,
+        #[serde(with = "serde_string")]
+        tokens: BigUint,
+    },
+    /// Multisig custodian submitted a new transaction
+    #[serde(rename_all = "camelCase")]
+    MultisigSubmit {
+        #[serde(with = "serde_uint256")]
+        custodian: UInt256,
+        #[serde(with = "serde_address")]
+        dest: MsgAddressInt,
+        #[serde(with = "serde_string")]
+        value: BigUint,
+        #[serde(with = "serde_string")]
+        transaction_id: u64,
+    },
+    /// Multisig custodian confirmed the pending transaction
+    #[serde(rename_all = "camelCase")]
+    MultisigConfirm {
+        #[serde(with = "serde_uint256")]
+        custodian: UInt256,
+        #[serde(with = "serde_string")]
+        transaction_id: u64,
+    },
+    /// Multisig code or custodians update was submitted, confirmed or executed
+    MultisigUpdate,
+    /// DePool paid the reward for the completed round
+    #[serde(rename_all = "camelCase")]
+    DePoolReward {
+        #[serde(with = "serde_string")]
+        round_id: u64,
+        #[serde(with = "serde_string")]
+        reward: u64,
+    },
+    /// DePool answered the participant request
+    #[serde(rename_all = "camelCase")]
+    DePoolAnswer { error_code: u32 },
+    /// Contract was deployed
+    Deploy,
+    /// Previously sent message was bounced back
+    #[serde(rename_all = "camelCase")]
+    Bounced {
+        #[serde(with = "serde_string")]
+        value: u128,
+    },
+    /// Transaction was aborted
+    #[serde(rename_all = "camelCase")]
+    Aborted { exit_code: Option<i32> },
+    /// Transaction without any known meaning
+    Unknown,
+}
+
+/// Describes the transaction using its parsed additional info
+pub fn describe_transaction(
+    transaction: &Transaction,
+    info: Option<&TransactionAdditionalInfo>,
+) -> TransactionDescription {
+    if transaction.aborted {
+        return TransactionDescription::Aborted {
+            exit_code: transaction.exit_code,
+        };
+    }
+
+    let outgoing_value = transaction
+        .out_msgs
+        .iter()
+        .map(|msg| msg.value)
+        .sum::<u128>();
+    let first_recipient = || transaction.out_msgs.first().and_then(|msg| msg.dst.clone());
+
+    let info = match info {
+        Some(info) => info,
+        None => return describe_plain_transaction(transaction, outgoing_value),
+    };
+
+    match info {
+        TransactionAdditionalInfo::Comment(comment) => TransactionDescription::IncomingTransfer {
+            sender: transaction.in_msg.src.clone(),
+            value: transaction.in_msg.value,
+            comment: Some(comment.clone()),
+        },
+        TransactionAdditionalInfo::DePoolOnRoundComplete(notification) => {
+            TransactionDescription::DePoolReward {
+                round_id: notification.round_id,
+                reward: notification.reward,
+            }
+        }
+        TransactionAdditionalInfo::DePoolReceiveAnswer(notification) => {
+            TransactionDescription::DePoolAnswer {
+                error_code: notification.error_code,
+            }
+        }
+        TransactionAdditionalInfo::TokenWalletDeployed(notification) => {
+            TransactionDescription::TokenWalletDeployed {
+                root_token_contract: notification.root_token_contract.clone(),
+            }
+        }
+        TransactionAdditionalInfo::JettonNotify(transfer) => {
+            TransactionDescription::JettonNotification {
+                sender: transfer.from.clone(),
+                tokens: transfer.tokens.clone(),
+            }
+        }
+        TransactionAdditionalInfo::WalletInteraction(interaction) => {
+            if let WalletInteractionMethod::Multisig(method) = &interaction.method {
+                match method.as_ref() {
+                    MultisigTransaction::Submit(submit) => {
+                        return TransactionDescription::MultisigSubmit {
+                            custodian: submit.custodian,
+                            dest: submit.dest.clone(),
+                            value: submit.value.clone(),
+                            transaction_id: submit.trans_id,
+                        }
+                    }
+                    MultisigTransaction::Confirm(confirm) => {
+                        return TransactionDescription::MultisigConfirm {
+                            custodian: confirm.custodian,
+                            transaction_id: confirm.transaction_id,
+                        }
+                    }
+                    MultisigTransaction::SubmitUpdate(_)
+                    | MultisigTransaction::ConfirmUpdate(_)
+                    | MultisigTransaction::ExecuteUpdate(_) => {
+                        return TransactionDescription::MultisigUpdate
+                    }
+                    MultisigTransaction::Send(_) => {}
+                }
+            }
+
+            let recipient = interaction.recipient.clone().or_else(first_recipient);
+            match &interaction.known_payload {
+                Some(KnownPayload::TokenOutgoingTransfer(transfer)) => {
+                    TransactionDescription::TokenTransfer {
+                        recipient: transfer.to.clone(),
+                        tokens: transfer.tokens.clone(),
+                    }
+                }
+                Some(KnownPayload::TokenSwapBack(swap_back)) => {
+                    TransactionDescription::TokenSwapBack {
+                        tokens: swap_back.tokens.clone(),
+                        callback_address: swap_back.callback_address.clone(),
+                    }
+                }
+                Some(KnownPayload::JettonOutgoingTransfer(transfer)) => {
+                    TransactionDescription::JettonTransfer {
+                        recipient: transfer.to.clone(),
+                        tokens: transfer.tokens.clone(),
+                    }
+                }
+                Some(KnownPayload::Comment(comment)) => TransactionDescription::OutgoingTransfer {
+                    recipient,
+                    value: outgoing_value,
+                    comment: Some(comment.clone()),
+                },
+                Some(KnownPayload::Custom(_)) | None => TransactionDescription::OutgoingTransfer {
+                    recipient,
+                    value: outgoing_value,
+                    comment: None,
+                },
+            }
+        }
+    }
+}
+
+fn describe_plain_transaction(
+    transaction: &Transaction,
+    outgoing_value: u128,
+) -> TransactionDescription {
+    let in_msg = &transaction.in_msg;
+    if in_msg.src.is_some() {
+        return if in_msg.bounced {
+            TransactionDescription::Bounced {
+                value: in_msg.value,
+            }
+        } else {
+            TransactionDescription::IncomingTransfer {
+                sender: in_msg.src.clone(),
+                value: in_msg.value,
+                comment: None,
+            }
+        };
+    }
+
+    if !transaction.out_msgs.is_empty() {
+        TransactionDescription::OutgoingTransfer {
+            recipient: transaction.out_msgs.first().and_then(|msg| msg.dst.clone()),
+            value: outgoing_value,
+            comment: None,
+        }
+    } else if transaction.orig_status != AccountStatus::Active
+        && transaction.end_status == AccountStatus::Active
+    {
+        TransactionDescription::Deploy
+    } else {
+        TransactionDescription::Unknown
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn token_amount_formatting() {
+        let amount = TokenAmount::parse("12.345", 9).unwrap();
+        assert_eq!(amount.raw, BigUint::from(12_345_000_000u64));
+        assert_eq!(amount.to_string(), "12.345");
+        assert_eq!(amount.format(2), "12.34");
+        assert_eq!(amount.format(0), "12");
+
+        assert_eq!(TokenAmount::new(5u32, 9).to_string(), "0.000000005");
+        assert_eq!(TokenAmount::new(5u32, 9).format(3), "0");
+        assert_eq!(TokenAmount::new(100u32, 0).to_string(), "100");
+        assert_eq!(
+            TokenAmount::parse("7", 6).unwrap().raw,
+            BigUint::from(7_000_000u32)
+        );
+
+        assert!(TokenAmount::parse("1.0000000001", 9).is_err());
+        assert!(TokenAmount::parse(".5", 9).is_err());
+        assert!(TokenAmount::parse("1,5", 9).is_err());
+
+        let value = ton_abi::TokenValue::VarUint(16, BigUint::from(1_500u32));
+        assert_eq!(TokenAmount::unpack(value, 3).unwrap().to_string(), "1.5");
+    }
+
+    #[test]
+    fn detect_contract_upgrade() {
+        let old = UInt256::from([1; 32]);
+        let new = UInt256::from([2; 32]);
+
+        assert_eq!(
+            ContractUpgrade::detect(Some(old), Some(new)),
+            Some(ContractUpgrade {
+                old_code_hash: old,
+                new_code_hash: new,
+            })
+        );
+        assert_eq!(ContractUpgrade::detect(Some(old), Some(old)), None);
+        // Deployment and deletion are not upgrades
+        assert_eq!(ContractUpgrade::detect(None, Some(new)), None);
+        assert_eq!(ContractUpgrade::detect(Some(old), None), None);
+    }
+
     #[test]
     fn test_parse_transaction() {
         let transaction =  ton_block::Transaction::construct_from_base64("te6ccgECCgEAAmIAA7VxDMDpxVKoQf1ESN4flYWnx79MwznjFCnHv2LMYnj5e/AAAMAPptS0HL7tNWkkUnpwkWevWy0v6QllFeZdkxpKd3jABu53GMiwAADABeYcjBYH/izgADRpb9DoBQQBAhEMgEHGGW16hEADAgBvyYehIEwUWEAAAAAAAAIAAAAAAAJdRbUJwB114ymQlNQVCfa9Moy2h4xlzAjFN0wo4BiqckBQGUwAnUF2QxOIAAAAAAAAAAAQgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACAAgnIKnXiVk1PWmbnJrrJ8ZuP6tVF8xWwdT4FzwpwwbcybITXW+aJKu2Ai+6iWudx7E+cmmtyYoMFlMnA6RJvjslElAgHgCAYBAd8HAMtoACGYHTiqVQg/qIkbw/KwtPj36ZhnPGKFOPfsWYxPHy9/AC7y/frS28SA7otT/U3XeMKVAioEwv3n4cO+8/UnsFk6VAnHZSQABhRYYAAAGAH02paEwP/FnAVWDH6AAAABKgXyAEAB34gAIZgdOKpVCD+oiRvD8rC0+PfpmGc8YoU49+xZjE8fL34FEnWHwu7iFVw1r2O1eQN6i3g5Ib9nJIGpQqRtpYG36Pjrmo9/vgPWf5ev1vhedfPUgkaxeInhVroDrGaLYfhoEl1JbFYH/i5IAAADOBwJAIJiAF3l+/Wlt4kB3Ran+puu8YUqBFQJhfvPw4d95+pPYLJ0qBOOykgAAAAAAAAAAAAAAAAAAAqsGP0AAAACVAvkAA==").unwrap();
         let parsed = Transaction::try_from((Default::default(), transaction)).unwrap();
         assert!(parsed.in_msg.body.is_some())
     }
+
+    #[test]
+    fn balance_changes() {
+        let transaction =  ton_block::Transaction::construct_from_base64("te6ccgECCgEAAmIAA7VxDMDpxVKoQf1ESN4flYWnx79MwznjFCnHv2LMYnj5e/AAAMAPptS0HL7tNWkkUnpwkWevWy0v6QllFeZdkxpKd3jABu53GMiwAADABeYcjBYH/izgADRpb9DoBQQBAhEMgEHGGW16hEADAgBvyYehIEwUWEAAAAAAAAIAAAAAAAJdRbUJwB114ymQlNQVCfa9Moy2h4xlzAjFN0wo4BiqckBQGUwAnUF2QxOIAAAAAAAAAAAQgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACAAgnIKnXiVk1PWmbnJrrJ8ZuP6tVF8xWwdT4FzwpwwbcybITXW+aJKu2Ai+6iWudx7E+cmmtyYoMFlMnA6RJvjslElAgHgCAYBAd8HAMtoACGYHTiqVQg/qIkbw/KwtPj36ZhnPGKFOPfsWYxPHy9/AC7y/frS28SA7otT/U3XeMKVAioEwv3n4cO+8/UnsFk6VAnHZSQABhRYYAAAGAH02paEwP/FnAVWDH6AAAABKgXyAEAB34gAIZgdOKpVCD+oiRvD8rC0+PfpmGc8YoU49+xZjE8fL34FEnWHwu7iFVw1r2O1eQN6i3g5Ib9nJIGpQqRtpYG36Pjrmo9/vgPWf5ev1vhedfPUgkaxeInhVroDrGaLYfhoEl1JbFYH/i5IAAADOBwJAIJiAF3l+/Wlt4kB3Ran+puu8YUqBFQJhfvPw4d95+pPYLJ0qBOOykgAAAAAAAAAAAAAAAAAAAqsGP0AAAACVAvkAA==").unwrap();
+        let change = BalanceChange::new(UInt256::default(), &transaction);
+        assert_eq!(change.caused_by_tx.lt, transaction.lt);
+        assert!(change.token_deltas.is_empty());
+
+        // External message with a single outgoing transfer
+        let parsed = Transaction::try_from((Default::default(), transaction)).unwrap();
+        let value = parsed.out_msgs.iter().map(|msg| msg.value).sum::<u128>() as i128;
+        assert!(change.native_delta < -value);
+
+        let tokens = BigUint::from(100u32);
+        assert_eq!(
+            TokenWalletTransaction::Accept(tokens.clone()).balance_delta(),
+            BigInt::from(100)
+        );
+        assert_eq!(
+            TokenWalletTransaction::SwapBack(TokenSwapBack {
+                tokens,
+                callback_address: Default::default(),
+                callback_payload: Default::default(),
+            })
+            .balance_delta(),
+            BigInt::from(-100)
+        );
+    }
+
+    #[test]
+    fn describe_wallet_transactions() {
+        let transaction =  ton_block::Transaction::construct_from_base64("te6ccgECCgEAAmIAA7VxDMDpxVKoQf1ESN4flYWnx79MwznjFCnHv2LMYnj5e/AAAMAPptS0HL7tNWkkUnpwkWevWy0v6QllFeZdkxpKd3jABu53GMiwAADABeYcjBYH/izgADRpb9DoBQQBAhEMgEHGGW16hEADAgBvyYehIEwUWEAAAAAAAAIAAAAAAAJdRbUJwB114ymQlNQVCfa9Moy2h4xlzAjFN0wo4BiqckBQGUwAnUF2QxOIAAAAAAAAAAAQgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACAAgnIKnXiVk1PWmbnJrrJ8ZuP6tVF8xWwdT4FzwpwwbcybITXW+aJKu2Ai+6iWudx7E+cmmtyYoMFlMnA6RJvjslElAgHgCAYBAd8HAMtoACGYHTiqVQg/qIkbw/KwtPj36ZhnPGKFOPfsWYxPHy9/AC7y/frS28SA7otT/U3XeMKVAioEwv3n4cO+8/UnsFk6VAnHZSQABhRYYAAAGAH02paEwP/FnAVWDH6AAAABKgXyAEAB34gAIZgdOKpVCD+oiRvD8rC0+PfpmGc8YoU49+xZjE8fL34FEnWHwu7iFVw1r2O1eQN6i3g5Ib9nJIGpQqRtpYG36Pjrmo9/vgPWf5ev1vhedfPUgkaxeInhVroDrGaLYfhoEl1JbFYH/i5IAAADOBwJAIJiAF3l+/Wlt4kB3Ran+puu8YUqBFQJhfvPw4d95+pPYLJ0qBOOykgAAAAAAAAAAAAAAAAAAAqsGP0AAAACVAvkAA==").unwrap();
+        let parsed = Transaction::try_from((Default::default(), transaction)).unwrap();
+        let value = parsed.out_msgs.iter().map(|msg| msg.value).sum::<u128>();
+
+        match describe_transaction(&parsed, None) {
+            TransactionDescription::OutgoingTransfer {
+                recipient,
+                value: described_value,
+                comment: None,
+            } => {
+                assert_eq!(recipient, parsed.out_msgs[0].dst);
+                assert_eq!(described_value, value);
+            }
+            description => panic!("unexpected description: {description:?}"),
+        }
+
+        let info =
+            TransactionAdditionalInfo::DePoolReceiveAnswer(DePoolReceiveAnswerNotification {
+                error_code: 1,
+                comment: 0,
+            });
+        assert!(matches!(
+            describe_transaction(&parsed, Some(&info)),
+            TransactionDescription::DePoolAnswer { error_code: 1 }
+        ));
+    }
 }
@@ -0,0 +1,518 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use quick_cache::sync::Cache as QuickCache;
+use ton_block::{Block, Deserializable, MsgAddressInt, Serializable};
+use ton_types::{Cell, UInt256};
+
+use nekoton_utils::*;
+
+use crate::core::models::{NetworkCapabilities, ReliableBehavior};
+use crate::external::AdnlConnection;
+
+use super::models::{PollContractState, RawContractState, RawTransaction};
+use super::proofs::{AccountStateProof, ShardBlockProof};
+use super::utils::*;
+use super::{Transport, TransportInfo};
+
+pub mod tl;
+
+/// Transport which talks to liteservers directly.
+///
+/// Account states are verified against the last masterchain block reported by the
+/// liteserver. State proofs are available only for the masterchain blocks which were
+/// already seen by this transport (the last blocks and key blocks).
+///
+/// Liteservers don't index messages or code hashes, so `get_accounts_by_code_hash`,
+/// `get_transaction` and `get_dst_transaction` return [`AdnlClientError::NotSupported`]
+pub struct AdnlTransport {
+    connection: Arc<dyn AdnlConnection>,
+    config_cache: ConfigCache,
+    /// Full ids of the seen masterchain blocks by their root hash
+    known_blocks: QuickCache<UInt256, tl::BlockIdExt>,
+    /// Transaction hashes by account and lt, required to fetch transactions
+    transaction_ids: QuickCache<(MsgAddressInt, u64), UInt256>,
+}
+
+impl AdnlTransport {
+    pub fn new(connection: Arc<dyn AdnlConnection>) -> Self {
+        const KNOWN_BLOCKS_CAPACITY: usize = 100;
+        const TRANSACTION_IDS_CAPACITY: usize = 1000;
+
+        Self {
+            connection,
+            config_cache: ConfigCache::new(false),
+            known_blocks: QuickCache::new(KNOWN_BLOCKS_CAPACITY),
+            transaction_ids: QuickCache::new(TRANSACTION_IDS_CAPACITY),
+        }
+    }
+
+    async fn query<T: tl::Response>(&self, request: Vec<u8>) -> Result<T> {
+        let response = self.connection.query(request).await?;
+        tl::parse_response(&response)
+    }
+
+    async fn get_last_block(&self) -> Result<tl::BlockIdExt> {
+        let info = self
+            .query::<tl::MasterchainInfo>(tl::get_masterchain_info())
+            .await?;
+        self.known_blocks
+            .insert(info.last.root_hash, info.last.clone());
+        Ok(info.last)
+    }
+
+    async fn get_block(&self, id: &tl::BlockIdExt) -> Result<Block> {
+        let block = self.query::<tl::BlockData>(tl::get_block(id)).await?;
+        let cell = ton_types::deserialize_tree_of_cells(&mut block.data.as_slice())?;
+        if block.id != *id || cell.repr_hash() != id.root_hash {
+            return Err(AdnlClientError::InvalidResponse.into());
+        }
+        let block = Block::construct_from_cell(cell)?;
+        if id.workchain == ton_block::MASTERCHAIN_ID {
+            self.known_blocks.insert(id.root_hash, id.clone());
+        }
+        Ok(block)
+    }
+
+    async fn get_account_state_proof(
+        &self,
+        id: &tl::BlockIdExt,
+        address: &MsgAddressInt,
+    ) -> Result<AccountStateProof> {
+        let state = self
+            .query::<tl::AccountState>(tl::get_account_state(id, address))
+            .await?;
+        if state.id != *id {
+            return Err(AdnlClientError::InvalidResponse.into());
+        }
+        parse_account_state_proof(&state)
+    }
+
+    /// Returns the id of the latest transaction with lt not greater than `from_lt`
+    async fn find_transaction_id(
+        &self,
+        address: &MsgAddressInt,
+        from_lt: u64,
+    ) -> Result<Option<(u64, UInt256)>> {
+        if let Some(hash) = self.transaction_ids.get(&(address.clone(), from_lt)) {
+            return Ok(Some((from_lt, hash)));
+        }
+
+        let (mut lt, mut hash) = match self.get_contract_state(address).await? {
+            RawContractState::Exists(contract) => match contract.last_transaction_id {
+                nekoton_abi::LastTransactionId::Exact(id) => (id.lt, id.hash),
+                nekoton_abi::LastTransactionId::Inexact { .. } => {
+                    return Err(AdnlClientError::InvalidResponse.into())
+                }
+            },
+            RawContractState::NotExists { .. } => return Ok(None),
+        };
+
+        // Walk back from the latest transaction
+        while lt > from_lt {
+            let transactions = self
+                .fetch_transactions(address, lt, &hash, MAX_TRANSACTIONS_PER_FETCH)
+                .await?;
+            let last = match transactions.last() {
+                Some(last) => &last.data,
+                None => return Ok(None),
+            };
+            if let Some(tx) = transactions.iter().find(|tx| tx.data.lt <= from_lt) {
+                return Ok(Some((tx.data.lt, tx.hash)));
+            }
+            if last.prev_trans_lt == 0 {
+                return Ok(None);
+            }
+            lt = last.prev_trans_lt;
+            hash = last.prev_trans_hash;
+        }
+
+        Ok(Some((lt, hash)))
+    }
+
+    /// Fetches the chain of transactions which starts from the specified one
+    async fn fetch_transactions(
+        &self,
+        address: &MsgAddressInt,
+        lt: u64,
+        hash: &UInt256,
+        count: u8,
+    ) -> Result<Vec<RawTransaction>> {
+        let list = self
+            .query::<tl::TransactionList>(tl::get_transactions(count as u32, address, lt, hash))
+            .await?;
+        if list.transactions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let cells = ton_types::deserialize_cells_tree(&mut list.transactions.as_slice())?;
+        if cells.len() != list.ids.len() || cells.len() > count as usize {
+            return Err(AdnlClientError::InvalidResponse.into());
+        }
+
+        let mut expected = (lt, *hash);
+        let mut result = Vec::with_capacity(cells.len());
+        for cell in cells {
+            let hash = cell.repr_hash();
+            let data = ton_block::Transaction::construct_from_cell(cell)?;
+
+            // Transactions must form a chain which starts from the requested one
+            if (data.lt, hash) != expected {
+                return Err(AdnlClientError::InvalidResponse.into());
+            }
+            expected = (data.prev_trans_lt, data.prev_trans_hash);
+
+            if data.prev_trans_lt != 0 {
+                self.transaction_ids
+                    .insert((address.clone(), data.prev_trans_lt), data.prev_trans_hash);
+            }
+            result.push(RawTransaction { hash, data });
+        }
+
+        Ok(result)
+    }
+
+    async fn fetch_config(&self) -> Result<ConfigResponse> {
+        let block = self.get_latest_key_block().await?;
+        let seqno = block.info.read_struct()?.seq_no();
+        let extra = block.read_extra()?;
+        let master = extra
+            .read_custom()?
+            .ok_or(AdnlClientError::InvalidResponse)?;
+        let config = master
+            .config()
+            .ok_or(AdnlClientError::InvalidResponse)?
+            .clone();
+
+        Ok(ConfigResponse {
+            global_id: block.global_id,
+            seqno,
+            config,
+        })
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Transport for AdnlTransport {
+    fn info(&self) -> TransportInfo {
+        TransportInfo {
+            max_transactions_per_fetch: MAX_TRANSACTIONS_PER_FETCH,
+            reliable_behavior: ReliableBehavior::IntensivePolling,
+            has_key_blocks: true,
+        }
+    }
+
+    async fn send_message(&self, message: &ton_block::Message) -> Result<()> {
+        let data = message.write_to_bytes()?;
+        self.query::<tl::SendMsgStatus>(tl::send_message(&data))
+            .await?;
+        Ok(())
+    }
+
+    async fn get_contract_state(&self, address: &MsgAddressInt) -> Result<RawContractState> {
+        let last = self.get_last_block().await?;
+        let state = self
+            .get_contract_state_verified(address, &last.root_hash)
+            .await?;
+
+        if let RawContractState::Exists(contract) = &state {
+            if let nekoton_abi::LastTransactionId::Exact(id) = &contract.last_transaction_id {
+                self.transaction_ids
+                    .insert((address.clone(), id.lt), id.hash);
+            }
+        }
+        Ok(state)
+    }
+
+    async fn get_library_cell(&self, hash: &UInt256) -> Result<Option<Cell>> {
+        let libraries = self
+            .query::<tl::LibraryResult>(tl::get_libraries(std::slice::from_ref(hash)))
+            .await?;
+
+        let entry = match libraries
+            .result
+            .into_iter()
+            .find(|entry| entry.hash == *hash)
+        {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let cell = ton_types::deserialize_tree_of_cells(&mut entry.data.as_slice())?;
+        if cell.repr_hash() != *hash {
+            return Err(AdnlClientError::InvalidResponse.into());
+        }
+        Ok(Some(cell))
+    }
+
+    async fn poll_contract_state(
+        &self,
+        address: &MsgAddressInt,
+        last_trans_lt: u64,
+    ) -> Result<PollContractState> {
+        // NOTE: liteservers can't check the last transaction, so the full state is fetched
+        Ok(match self.get_contract_state(address).await? {
+            RawContractState::Exists(contract)
+                if contract.last_transaction_id.lt() == last_trans_lt =>
+            {
+                PollContractState::Unchanged {
+                    timings: contract.timings,
+                }
+            }
+            state => state.into(),
+        })
+    }
+
+    async fn get_accounts_by_code_hash(
+        &self,
+        _code_hash: &UInt256,
+        _limit: u8,
+        _continuation: &Option<MsgAddressInt>,
+    ) -> Result<Vec<MsgAddressInt>> {
+        Err(AdnlClientError::NotSupported.into())
+    }
+
+    async fn get_transactions(
+        &self,
+        address: &MsgAddressInt,
+        from_lt: u64,
+        count: u8,
+    ) -> Result<Vec<RawTransaction>> {
+        let count = count.min(MAX_TRANSACTIONS_PER_FETCH);
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        match self.find_transaction_id(address, from_lt).await? {
+            Some((lt, hash)) => self.fetch_transactions(address, lt, &hash, count).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn get_transaction(&self, _id: &UInt256) -> Result<Option<RawTransaction>> {
+        Err(AdnlClientError::NotSupported.into())
+    }
+
+    async fn get_dst_transaction(&self, _message_hash: &UInt256) -> Result<Option<RawTransaction>> {
+        Err(AdnlClientError::NotSupported.into())
+    }
+
+    async fn get_latest_key_block(&self) -> Result<Block> {
+        let last = self.get_last_block().await?;
+        let block = self.get_block(&last).await?;
+
+        let info = block.info.read_struct()?;
+        if info.key_block() {
+            return Ok(block);
+        }
+
+        let key_block_seqno = info.prev_key_block_seqno();
+        let header = self
+            .query::<tl::BlockHeader>(tl::lookup_block(
+                ton_block::MASTERCHAIN_ID,
+                ton_block::SHARD_FULL,
+                key_block_seqno,
+            ))
+            .await?;
+        if header.id.workchain != ton_block::MASTERCHAIN_ID || header.id.seqno != key_block_seqno {
+            return Err(AdnlClientError::InvalidResponse.into());
+        }
+
+        self.get_block(&header.id).await
+    }
+
+    async fn get_capabilities(&self, clock: &dyn Clock) -> Result<NetworkCapabilities> {
+        let (capabilities, _) = self
+            .config_cache
+            .get_blockchain_config(clock, false, || self.fetch_config())
+            .await?;
+        Ok(capabilities)
+    }
+
+    async fn get_blockchain_config(
+        &self,
+        clock: &dyn Clock,
+        force: bool,
+    ) -> Result<ton_executor::BlockchainConfig> {
+        let (_, config) = self
+            .config_cache
+            .get_blockchain_config(clock, force, || self.fetch_config())
+            .await?;
+        Ok(config)
+    }
+
+    async fn get_contract_state_proof(
+        &self,
+        address: &MsgAddressInt,
+        block_hash: &UInt256,
+    ) -> Result<AccountStateProof> {
+        // NOTE: liteservers require the full block id
+        let id = self
+            .known_blocks
+            .get(block_hash)
+            .ok_or(AdnlClientError::UnknownBlock)?;
+        self.get_account_state_proof(&id, address).await
+    }
+}
+
+/// Liteservers return at most 16 transactions per request
+const MAX_TRANSACTIONS_PER_FETCH: u8 = 16;
+
+/// Converts the liteserver response into the proof.
+///
+/// `proof` contains the block proof and the state proof with the pruned account,
+/// which is returned separately in `state`. For accounts outside of the masterchain
+/// `shard_proof` contains the proofs of the masterchain block and its shard hashes
+fn parse_account_state_proof(state: &tl::AccountState) -> Result<AccountStateProof> {
+    let (block_proof, state_proof) = read_proof_pair(&state.proof)?;
+
+    let account = if state.state.is_empty() {
+        None
+    } else {
+        Some(ton_types::deserialize_tree_of_cells(
+            &mut state.state.as_slice(),
+        )?)
+    };
+
+    let shard_proof = if state.shard_proof.is_empty() {
+        None
+    } else {
+        let (mc_block_proof, mc_state_proof) = read_proof_pair(&state.shard_proof)?;
+        Some(ShardBlockProof {
+            mc_block_proof,
+            mc_state_proof,
+        })
+    };
+
+    Ok(AccountStateProof {
+        block_proof,
+        state_proof,
+        account,
+        shard_proof,
+    })
+}
+
+fn read_proof_pair(boc: &[u8]) -> Result<(Cell, Cell)> {
+    let mut roots = ton_types::deserialize_cells_tree(&mut &*boc)?.into_iter();
+    match (roots.next(), roots.next(), roots.next()) {
+        (Some(block_proof), Some(state_proof), None) => Ok((block_proof, state_proof)),
+        _ => Err(AdnlClientError::InvalidResponse.into()),
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum AdnlClientError {
+    #[error("Failed to parse response")]
+    InvalidResponse,
+    #[error("Liteserver error {code}: {message}")]
+    LiteServer { code: i32, message: String },
+    #[error("Method is not supported by liteservers")]
+    NotSupported,
+    #[error("Unknown masterchain block")]
+    UnknownBlock,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    struct TestConnection {
+        last: tl::BlockIdExt,
+        requests: Mutex<Vec<Vec<u8>>>,
+    }
+
+    #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+    #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+    impl AdnlConnection for TestConnection {
+        async fn query(&self, request: Vec<u8>) -> Result<Vec<u8>> {
+            let response = if request == tl::get_masterchain_info() {
+                let mut writer = tl::TlWriter::new(0x85832881);
+                writer.write_block_id(&self.last);
+                writer.write_int256(&UInt256::default());
+                writer.write_i32(-1);
+                writer.write_int256(&UInt256::default());
+                writer.write_int256(&UInt256::default());
+                writer.finish()
+            } else {
+                let mut writer = tl::TlWriter::new(0xbba9e148);
+                writer.write_i32(0);
+                writer.write_bytes(b"not found");
+                writer.finish()
+            };
+            self.requests.lock().push(request);
+            Ok(response)
+        }
+    }
+
+    #[tokio::test]
+    async fn state_proof_of_known_block() {
+        let last = tl::BlockIdExt {
+            workchain: -1,
+            shard: ton_block::SHARD_FULL,
+            seqno: 100,
+            root_hash: UInt256::from([1; 32]),
+            file_hash: UInt256::from([2; 32]),
+        };
+        let connection = Arc::new(TestConnection {
+            last: last.clone(),
+            requests: Default::default(),
+        });
+        let transport = AdnlTransport::new(connection.clone());
+
+        let address = MsgAddressInt::from_str(
+            "0:0000000000000000000000000000000000000000000000000000000000000000",
+        )
+        .unwrap();
+
+        let err = transport
+            .get_contract_state_proof(&address, &last.root_hash)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AdnlClientError>(),
+            Some(AdnlClientError::UnknownBlock)
+        ));
+        assert!(connection.requests.lock().is_empty());
+
+        transport.get_last_block().await.unwrap();
+
+        let err = transport
+            .get_contract_state_proof(&address, &last.root_hash)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AdnlClientError>(),
+            Some(AdnlClientError::LiteServer { .. })
+        ));
+        assert_eq!(
+            connection.requests.lock().last(),
+            Some(&tl::get_account_state(&last, &address))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_proofs() {
+        let id = tl::BlockIdExt {
+            workchain: -1,
+            shard: ton_block::SHARD_FULL,
+            seqno: 1,
+            root_hash: UInt256::default(),
+            file_hash: UInt256::default(),
+        };
+
+        let single_root = ton_types::serialize_toc(&Cell::default()).unwrap();
+        let state = tl::AccountState {
+            id: id.clone(),
+            shardblk: id,
+            shard_proof: Vec::new(),
+            proof: single_root,
+            state: Vec::new(),
+        };
+        assert!(parse_account_state_proof(&state).is_err());
+    }
+}
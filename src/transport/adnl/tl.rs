@@ -0,0 +1,480 @@
+//! Minimal TL serialization of the liteserver API.
+//!
+//! Only the functions used by [`AdnlTransport`](super::AdnlTransport) are supported.
+//! Requests are boxed liteserver functions, which are wrapped into `liteServer.query`
+//! by the connection.
+
+use anyhow::Result;
+use ton_block::MsgAddressInt;
+use ton_types::UInt256;
+
+use super::AdnlClientError;
+
+/// `tonNode.blockIdExt`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BlockIdExt {
+    pub workchain: i32,
+    pub shard: u64,
+    pub seqno: u32,
+    pub root_hash: UInt256,
+    pub file_hash: UInt256,
+}
+
+/// `liteServer.masterchainInfo`
+#[derive(Debug, Clone)]
+pub struct MasterchainInfo {
+    pub last: BlockIdExt,
+    pub state_root_hash: UInt256,
+}
+
+/// `liteServer.accountState`
+#[derive(Debug, Clone)]
+pub struct AccountState {
+    pub id: BlockIdExt,
+    pub shardblk: BlockIdExt,
+    pub shard_proof: Vec<u8>,
+    pub proof: Vec<u8>,
+    pub state: Vec<u8>,
+}
+
+/// `liteServer.transactionList`
+#[derive(Debug, Clone)]
+pub struct TransactionList {
+    pub ids: Vec<BlockIdExt>,
+    pub transactions: Vec<u8>,
+}
+
+/// `liteServer.blockData`
+#[derive(Debug, Clone)]
+pub struct BlockData {
+    pub id: BlockIdExt,
+    pub data: Vec<u8>,
+}
+
+/// `liteServer.blockHeader`
+#[derive(Debug, Clone)]
+pub struct BlockHeader {
+    pub id: BlockIdExt,
+    pub mode: u32,
+    pub header_proof: Vec<u8>,
+}
+
+/// `liteServer.libraryEntry`
+#[derive(Debug, Clone)]
+pub struct LibraryEntry {
+    pub hash: UInt256,
+    pub data: Vec<u8>,
+}
+
+/// `liteServer.libraryResult`
+#[derive(Debug, Clone)]
+pub struct LibraryResult {
+    pub result: Vec<LibraryEntry>,
+}
+
+/// `liteServer.sendMsgStatus`
+#[derive(Debug, Copy, Clone)]
+pub struct SendMsgStatus {
+    pub status: i32,
+}
+
+pub fn get_masterchain_info() -> Vec<u8> {
+    TlWriter::new(0x89b5e62e).finish()
+}
+
+pub fn send_message(body: &[u8]) -> Vec<u8> {
+    let mut writer = TlWriter::new(0x690ad482);
+    writer.write_bytes(body);
+    writer.finish()
+}
+
+pub fn get_account_state(id: &BlockIdExt, account: &MsgAddressInt) -> Vec<u8> {
+    let mut writer = TlWriter::new(0x6b890e25);
+    writer.write_block_id(id);
+    writer.write_account_id(account);
+    writer.finish()
+}
+
+pub fn get_transactions(count: u32, account: &MsgAddressInt, lt: u64, hash: &UInt256) -> Vec<u8> {
+    let mut writer = TlWriter::new(0x1c40e7a1);
+    writer.write_u32(count);
+    writer.write_account_id(account);
+    writer.write_u64(lt);
+    writer.write_int256(hash);
+    writer.finish()
+}
+
+pub fn get_block(id: &BlockIdExt) -> Vec<u8> {
+    let mut writer = TlWriter::new(0x6377cf0d);
+    writer.write_block_id(id);
+    writer.finish()
+}
+
+/// Looks up the block by its seqno (`mode = 1`)
+pub fn lookup_block(workchain: i32, shard: u64, seqno: u32) -> Vec<u8> {
+    let mut writer = TlWriter::new(0xfac8f71e);
+    writer.write_u32(1);
+    writer.write_i32(workchain);
+    writer.write_u64(shard);
+    writer.write_u32(seqno);
+    writer.finish()
+}
+
+pub fn get_libraries(hashes: &[UInt256]) -> Vec<u8> {
+    let mut writer = TlWriter::new(0x7e1e1899);
+    writer.write_u32(hashes.len() as u32);
+    for hash in hashes {
+        writer.write_int256(hash);
+    }
+    writer.finish()
+}
+
+/// Boxed liteserver response
+pub trait Response: Sized {
+    const ID: u32;
+
+    fn read_from(reader: &mut TlReader<'_>) -> Option<Self>;
+}
+
+/// Parses the boxed response, mapping `liteServer.error` to [`AdnlClientError::LiteServer`]
+pub fn parse_response<T: Response>(data: &[u8]) -> Result<T> {
+    const LITE_SERVER_ERROR: u32 = 0xbba9e148;
+
+    let mut reader = TlReader::new(data);
+    let result = match reader.read_u32() {
+        Some(id) if id == T::ID => T::read_from(&mut reader),
+        Some(LITE_SERVER_ERROR) => {
+            let code = reader.read_i32();
+            let message = reader.read_bytes();
+            return Err(match (code, message) {
+                (Some(code), Some(message)) => AdnlClientError::LiteServer {
+                    code,
+                    message: String::from_utf8_lossy(message).into_owned(),
+                },
+                _ => AdnlClientError::InvalidResponse,
+            }
+            .into());
+        }
+        _ => None,
+    };
+
+    match result {
+        Some(result) if reader.is_empty() => Ok(result),
+        _ => Err(AdnlClientError::InvalidResponse.into()),
+    }
+}
+
+impl Response for MasterchainInfo {
+    const ID: u32 = 0x85832881;
+
+    fn read_from(reader: &mut TlReader<'_>) -> Option<Self> {
+        let last = reader.read_block_id()?;
+        let state_root_hash = reader.read_int256()?;
+        // tonNode.zeroStateIdExt workchain:int root_hash:int256 file_hash:int256
+        reader.skip(4 + 32 + 32)?;
+        Some(Self {
+            last,
+            state_root_hash,
+        })
+    }
+}
+
+impl Response for AccountState {
+    const ID: u32 = 0x7079c751;
+
+    fn read_from(reader: &mut TlReader<'_>) -> Option<Self> {
+        Some(Self {
+            id: reader.read_block_id()?,
+            shardblk: reader.read_block_id()?,
+            shard_proof: reader.read_bytes()?.to_vec(),
+            proof: reader.read_bytes()?.to_vec(),
+            state: reader.read_bytes()?.to_vec(),
+        })
+    }
+}
+
+impl Response for TransactionList {
+    const ID: u32 = 0xb92ed79d;
+
+    fn read_from(reader: &mut TlReader<'_>) -> Option<Self> {
+        let len = reader.read_u32()? as usize;
+        let mut ids = Vec::with_capacity(len.min(256));
+        for _ in 0..len {
+            ids.push(reader.read_block_id()?);
+        }
+        Some(Self {
+            ids,
+            transactions: reader.read_bytes()?.to_vec(),
+        })
+    }
+}
+
+impl Response for BlockData {
+    const ID: u32 = 0xa574ed6c;
+
+    fn read_from(reader: &mut TlReader<'_>) -> Option<Self> {
+        Some(Self {
+            id: reader.read_block_id()?,
+            data: reader.read_bytes()?.to_vec(),
+        })
+    }
+}
+
+impl Response for BlockHeader {
+    const ID: u32 = 0x752d8219;
+
+    fn read_from(reader: &mut TlReader<'_>) -> Option<Self> {
+        Some(Self {
+            id: reader.read_block_id()?,
+            mode: reader.read_u32()?,
+            header_proof: reader.read_bytes()?.to_vec(),
+        })
+    }
+}
+
+impl Response for LibraryResult {
+    const ID: u32 = 0x8b84430c;
+
+    fn read_from(reader: &mut TlReader<'_>) -> Option<Self> {
+        const LIBRARY_ENTRY: u32 = 0x8aff2446;
+
+        let len = reader.read_u32()? as usize;
+        let mut result = Vec::with_capacity(len.min(16));
+        for _ in 0..len {
+            // NOTE: vector elements are boxed here
+            if reader.read_u32()? != LIBRARY_ENTRY {
+                return None;
+            }
+            result.push(LibraryEntry {
+                hash: reader.read_int256()?,
+                data: reader.read_bytes()?.to_vec(),
+            });
+        }
+        Some(Self { result })
+    }
+}
+
+impl Response for SendMsgStatus {
+    const ID: u32 = 0x3950e597;
+
+    fn read_from(reader: &mut TlReader<'_>) -> Option<Self> {
+        Some(Self {
+            status: reader.read_i32()?,
+        })
+    }
+}
+
+pub struct TlWriter {
+    data: Vec<u8>,
+}
+
+impl TlWriter {
+    pub fn new(id: u32) -> Self {
+        let mut writer = Self {
+            data: Vec::with_capacity(128),
+        };
+        writer.write_u32(id);
+        writer
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_int256(&mut self, value: &UInt256) {
+        self.data.extend_from_slice(value.as_slice());
+    }
+
+    pub fn write_bytes(&mut self, value: &[u8]) {
+        let len = value.len();
+        let header_len = if len < 254 {
+            self.data.push(len as u8);
+            1
+        } else {
+            self.data.push(254);
+            self.data
+                .extend_from_slice(&(len as u32).to_le_bytes()[..3]);
+            4
+        };
+        self.data.extend_from_slice(value);
+
+        let padding = (4 - (header_len + len) % 4) % 4;
+        self.data.resize(self.data.len() + padding, 0);
+    }
+
+    pub fn write_block_id(&mut self, id: &BlockIdExt) {
+        self.write_i32(id.workchain);
+        self.write_u64(id.shard);
+        self.write_u32(id.seqno);
+        self.write_int256(&id.root_hash);
+        self.write_int256(&id.file_hash);
+    }
+
+    /// `liteServer.accountId`
+    pub fn write_account_id(&mut self, address: &MsgAddressInt) {
+        self.write_i32(address.workchain_id());
+        self.write_int256(&UInt256::from_be_bytes(
+            &address.address().get_bytestring(0),
+        ));
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+pub struct TlReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TlReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn read_u32(&mut self) -> Option<u32> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    pub fn read_i32(&mut self) -> Option<i32> {
+        self.read_array().map(i32::from_le_bytes)
+    }
+
+    pub fn read_u64(&mut self) -> Option<u64> {
+        self.read_array().map(u64::from_le_bytes)
+    }
+
+    pub fn read_int256(&mut self) -> Option<UInt256> {
+        self.read_array::<32>().map(UInt256::from)
+    }
+
+    pub fn read_bytes(&mut self) -> Option<&'a [u8]> {
+        let (header_len, len) = match *self.data.first()? {
+            254 => {
+                let header = self.read_array::<4>()?;
+                (
+                    4,
+                    u32::from_le_bytes([header[1], header[2], header[3], 0]) as usize,
+                )
+            }
+            255 => return None,
+            len => {
+                self.skip(1)?;
+                (1, len as usize)
+            }
+        };
+
+        let value = self.data.get(..len)?;
+        self.skip(len)?;
+        self.skip((4 - (header_len + len) % 4) % 4)?;
+        Some(value)
+    }
+
+    pub fn read_block_id(&mut self) -> Option<BlockIdExt> {
+        Some(BlockIdExt {
+            workchain: self.read_i32()?,
+            shard: self.read_u64()?,
+            seqno: self.read_u32()?,
+            root_hash: self.read_int256()?,
+            file_hash: self.read_int256()?,
+        })
+    }
+
+    pub fn skip(&mut self, len: usize) -> Option<()> {
+        self.data = self.data.get(len..)?;
+        Some(())
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let value: [u8; N] = self.data.get(..N)?.try_into().ok()?;
+        self.data = &self.data[N..];
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_roundtrip() {
+        for len in [0, 1, 3, 4, 253, 254, 255, 1000] {
+            let value = vec![0xab; len];
+
+            let mut writer = TlWriter::new(0);
+            writer.write_bytes(&value);
+            writer.write_u32(0xdeadbeef);
+            let data = writer.finish();
+            assert_eq!(data.len() % 4, 0);
+
+            let mut reader = TlReader::new(&data);
+            assert_eq!(reader.read_u32(), Some(0));
+            assert_eq!(reader.read_bytes(), Some(value.as_slice()));
+            assert_eq!(reader.read_u32(), Some(0xdeadbeef));
+            assert!(reader.is_empty());
+        }
+    }
+
+    #[test]
+    fn masterchain_info_request() {
+        assert_eq!(get_masterchain_info(), [0x2e, 0xe6, 0xb5, 0x89]);
+    }
+
+    #[test]
+    fn parse_masterchain_info() {
+        let last = BlockIdExt {
+            workchain: -1,
+            shard: 0x8000000000000000,
+            seqno: 123,
+            root_hash: UInt256::from([1; 32]),
+            file_hash: UInt256::from([2; 32]),
+        };
+
+        let mut writer = TlWriter::new(MasterchainInfo::ID);
+        writer.write_block_id(&last);
+        writer.write_int256(&UInt256::from([3; 32]));
+        writer.write_i32(-1);
+        writer.write_int256(&UInt256::from([4; 32]));
+        writer.write_int256(&UInt256::from([5; 32]));
+
+        let info = parse_response::<MasterchainInfo>(&writer.finish()).unwrap();
+        assert_eq!(info.last, last);
+        assert_eq!(info.state_root_hash, UInt256::from([3; 32]));
+    }
+
+    #[test]
+    fn parse_lite_server_error() {
+        let mut writer = TlWriter::new(0xbba9e148);
+        writer.write_i32(651);
+        writer.write_bytes(b"block not found");
+
+        let err = parse_response::<MasterchainInfo>(&writer.finish()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AdnlClientError>(),
+            Some(AdnlClientError::LiteServer { code: 651, message }) if message == "block not found"
+        ));
+    }
+
+    #[test]
+    fn parse_unexpected_response() {
+        let mut writer = TlWriter::new(SendMsgStatus::ID);
+        writer.write_i32(1);
+        let data = writer.finish();
+
+        assert!(parse_response::<MasterchainInfo>(&data).is_err());
+        assert!(parse_response::<SendMsgStatus>(&data[..6]).is_err());
+        assert_eq!(parse_response::<SendMsgStatus>(&data).unwrap().status, 1);
+    }
+}
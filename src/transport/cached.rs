@@ -0,0 +1,265 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures_util::Future;
+use nekoton_utils::Clock;
+use tokio::sync::Mutex;
+use ton_block::MsgAddressInt;
+use ton_types::Cell;
+
+use super::models::{PollContractState, RawContractState, RawTransaction};
+use super::proofs::AccountStateProof;
+use super::{Transport, TransportInfo};
+use crate::core::config::NetworkConfig;
+use crate::core::models::NetworkCapabilities;
+use crate::crypto::SignatureId;
+
+/// Memoizes the latest key block and the blockchain config.
+///
+/// Concurrent requests wait for the single fetch instead of sending their own
+pub struct BlockCache {
+    ttl: u32,
+    key_block: TtlCell<ton_block::Block>,
+    config: TtlCell<ton_executor::BlockchainConfig>,
+}
+
+impl BlockCache {
+    /// Creates an empty cache, values are valid for `ttl` seconds
+    pub fn new(ttl: u32) -> Self {
+        Self {
+            ttl,
+            key_block: Default::default(),
+            config: Default::default(),
+        }
+    }
+
+    pub async fn get_latest_key_block<F, Fut>(
+        &self,
+        clock: &dyn Clock,
+        f: F,
+    ) -> Result<ton_block::Block>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ton_block::Block>>,
+    {
+        self.key_block
+            .get_or_fetch(clock.now_sec_u64() as u32, self.ttl, false, f)
+            .await
+    }
+
+    pub async fn get_blockchain_config<F, Fut>(
+        &self,
+        clock: &dyn Clock,
+        force: bool,
+        f: F,
+    ) -> Result<ton_executor::BlockchainConfig>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ton_executor::BlockchainConfig>>,
+    {
+        self.config
+            .get_or_fetch(clock.now_sec_u64() as u32, self.ttl, force, f)
+            .await
+    }
+}
+
+/// Transport wrapper which shares the [`BlockCache`] between all its users
+pub struct CachedTransport {
+    clock: Arc<dyn Clock>,
+    inner: Arc<dyn Transport>,
+    cache: BlockCache,
+}
+
+impl CachedTransport {
+    pub fn new(clock: Arc<dyn Clock>, inner: Arc<dyn Transport>, ttl: u32) -> Self {
+        Self {
+            clock,
+            inner,
+            cache: BlockCache::new(ttl),
+        }
+    }
+
+    pub fn inner(&self) -> &Arc<dyn Transport> {
+        &self.inner
+    }
+
+    /// Returns the typed view of the cached blockchain config
+    pub async fn get_network_config(&self) -> Result<NetworkConfig> {
+        let config = self
+            .get_blockchain_config(self.clock.as_ref(), false)
+            .await?;
+        NetworkConfig::from_config(&config)
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Transport for CachedTransport {
+    fn info(&self) -> TransportInfo {
+        self.inner.info()
+    }
+
+    async fn send_message(&self, message: &ton_block::Message) -> Result<()> {
+        self.inner.send_message(message).await
+    }
+
+    async fn get_contract_state(&self, address: &MsgAddressInt) -> Result<RawContractState> {
+        self.inner.get_contract_state(address).await
+    }
+
+    async fn get_library_cell(&self, hash: &ton_types::UInt256) -> Result<Option<Cell>> {
+        self.inner.get_library_cell(hash).await
+    }
+
+    async fn poll_contract_state(
+        &self,
+        address: &MsgAddressInt,
+        last_trans_lt: u64,
+    ) -> Result<PollContractState> {
+        self.inner.poll_contract_state(address, last_trans_lt).await
+    }
+
+    async fn get_accounts_by_code_hash(
+        &self,
+        code_hash: &ton_types::UInt256,
+        limit: u8,
+        continuation: &Option<MsgAddressInt>,
+    ) -> Result<Vec<MsgAddressInt>> {
+        self.inner
+            .get_accounts_by_code_hash(code_hash, limit, continuation)
+            .await
+    }
+
+    async fn get_transactions(
+        &self,
+        address: &MsgAddressInt,
+        from_lt: u64,
+        count: u8,
+    ) -> Result<Vec<RawTransaction>> {
+        self.inner.get_transactions(address, from_lt, count).await
+    }
+
+    async fn get_transaction(&self, id: &ton_types::UInt256) -> Result<Option<RawTransaction>> {
+        self.inner.get_transaction(id).await
+    }
+
+    async fn get_dst_transaction(
+        &self,
+        message_hash: &ton_types::UInt256,
+    ) -> Result<Option<RawTransaction>> {
+        self.inner.get_dst_transaction(message_hash).await
+    }
+
+    async fn get_latest_key_block(&self) -> Result<ton_block::Block> {
+        self.cache
+            .get_latest_key_block(self.clock.as_ref(), || self.inner.get_latest_key_block())
+            .await
+    }
+
+    async fn get_capabilities(&self, clock: &dyn Clock) -> Result<NetworkCapabilities> {
+        self.inner.get_capabilities(clock).await
+    }
+
+    async fn get_signature_id(&self, clock: &dyn Clock) -> Result<Option<SignatureId>> {
+        self.inner.get_signature_id(clock).await
+    }
+
+    async fn get_blockchain_config(
+        &self,
+        clock: &dyn Clock,
+        force: bool,
+    ) -> Result<ton_executor::BlockchainConfig> {
+        self.cache
+            .get_blockchain_config(clock, force, || {
+                self.inner.get_blockchain_config(clock, force)
+            })
+            .await
+    }
+
+    async fn get_contract_state_proof(
+        &self,
+        address: &MsgAddressInt,
+        block_hash: &ton_types::UInt256,
+    ) -> Result<AccountStateProof> {
+        self.inner
+            .get_contract_state_proof(address, block_hash)
+            .await
+    }
+}
+
+struct TtlCell<T> {
+    state: Mutex<Option<(u32, T)>>,
+}
+
+impl<T> Default for TtlCell<T> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(None),
+        }
+    }
+}
+
+impl<T: Clone> TtlCell<T> {
+    async fn get_or_fetch<F, Fut>(&self, now: u32, ttl: u32, force: bool, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        // NOTE: lock is held during the fetch so that concurrent callers reuse its result
+        let mut state = self.state.lock().await;
+        match &*state {
+            Some((updated_at, value)) if !force && now < updated_at.saturating_add(ttl) => {
+                Ok(value.clone())
+            }
+            _ => {
+                let value = f().await?;
+                *state = Some((now, value.clone()));
+                Ok(value)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use nekoton_utils::SimpleClock;
+
+    use super::*;
+    use crate::transport::tests::MockTransport;
+
+    #[tokio::test]
+    async fn single_flight_fetch() {
+        let cell = TtlCell::<u32>::default();
+        let fetches = AtomicUsize::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, Ordering::Relaxed);
+            Ok(42)
+        };
+
+        let results = futures_util::future::join_all(
+            (0..10).map(|_| cell.get_or_fetch(100, 10, false, fetch)),
+        )
+        .await;
+        assert!(results.into_iter().all(|result| result.unwrap() == 42));
+        assert_eq!(fetches.load(Ordering::Relaxed), 1);
+
+        cell.get_or_fetch(109, 10, false, fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::Relaxed), 1);
+
+        cell.get_or_fetch(110, 10, false, fetch).await.unwrap();
+        cell.get_or_fetch(110, 10, true, fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn network_config_from_cached_config() {
+        let transport =
+            CachedTransport::new(Arc::new(SimpleClock), Arc::new(MockTransport::new()), 60);
+
+        let config = transport.get_network_config().await.unwrap();
+        let expected = NetworkConfig::from_config(&Default::default()).unwrap();
+        assert_eq!(config.elector_params(), expected.elector_params());
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use nekoton_utils::Clock;
+use ton_block::{CommonMsgInfo, Deserializable, Serializable};
+use ton_types::UInt256;
+
+use super::models::RawTransaction;
+use super::Transport;
+
+/// Runtime-agnostic delay, used by the polling helpers
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+pub trait AsyncSleep: Send + Sync {
+    async fn sleep(&self, duration: Duration);
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DeliveryParams {
+    /// Delay between polling attempts
+    pub poll_interval: Duration,
+    /// Max depth of the collected child transactions (`0` to skip them)
+    pub max_depth: usize,
+    /// How long to wait for each child transaction, in seconds
+    pub child_timeout: u32,
+}
+
+impl Default for DeliveryParams {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            max_depth: 0,
+            child_timeout: 60,
+        }
+    }
+}
+
+/// Result of the external message delivery
+#[derive(Clone)]
+pub enum MessageDelivery {
+    /// Message was not included into any block before the expiration
+    Expired,
+    /// Message was delivered, but its transaction was aborted
+    Aborted {
+        transaction: RawTransaction,
+        exit_code: Option<i32>,
+    },
+    /// Message was delivered and successfully processed
+    Delivered {
+        transaction: RawTransaction,
+        /// Transactions, produced by the outgoing messages (breadth-first)
+        children: Vec<RawTransaction>,
+        /// Hashes of the outgoing messages without found transactions
+        /// (not processed in time or beyond the depth limit)
+        pending_messages: Vec<UInt256>,
+    },
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+pub trait TransportExt: Transport {
+    /// Sends an external message and waits until its transaction appears or the message expires
+    async fn send_message_reliably(
+        &self,
+        clock: &dyn Clock,
+        sleep: &dyn AsyncSleep,
+        message: &ton_block::Message,
+        expire_at: u32,
+        params: DeliveryParams,
+    ) -> Result<MessageDelivery> {
+        if !matches!(message.header(), CommonMsgInfo::ExtInMsgInfo(_)) {
+            return Err(TransportExtError::InvalidMessageType.into());
+        }
+        let message_hash = message.serialize()?.repr_hash();
+
+        self.send_message(message).await?;
+
+        let transaction = match self
+            .wait_for_transaction(clock, sleep, &message_hash, expire_at, params.poll_interval)
+            .await?
+        {
+            Some(transaction) => transaction,
+            None => return Ok(MessageDelivery::Expired),
+        };
+
+        let descr = transaction.data.read_description()?;
+        if descr.is_aborted() {
+            let exit_code = descr.compute_phase_ref().and_then(|phase| match phase {
+                ton_block::TrComputePhase::Vm(phase) => Some(phase.exit_code),
+                ton_block::TrComputePhase::Skipped(_) => None,
+            });
+            return Ok(MessageDelivery::Aborted {
+                transaction,
+                exit_code,
+            });
+        }
+
+        let mut children = Vec::new();
+        let mut pending_messages = Vec::new();
+
+        let mut queue = VecDeque::new();
+        queue.extend(
+            internal_out_messages(&transaction)?
+                .into_iter()
+                .map(|hash| (hash, 1)),
+        );
+
+        while let Some((hash, depth)) = queue.pop_front() {
+            if depth > params.max_depth {
+                pending_messages.push(hash);
+                continue;
+            }
+
+            let timeout = (clock.now_sec_u64() as u32).saturating_add(params.child_timeout);
+            match self
+                .wait_for_transaction(clock, sleep, &hash, timeout, params.poll_interval)
+                .await?
+            {
+                Some(child) => {
+                    queue.extend(
+                        internal_out_messages(&child)?
+                            .into_iter()
+                            .map(|hash| (hash, depth + 1)),
+                    );
+                    children.push(child);
+                }
+                None => pending_messages.push(hash),
+            }
+        }
+
+        Ok(MessageDelivery::Delivered {
+            transaction,
+            children,
+            pending_messages,
+        })
+    }
+
+    /// Polls the transaction by its incoming message hash until the deadline
+    async fn wait_for_transaction(
+        &self,
+        clock: &dyn Clock,
+        sleep: &dyn AsyncSleep,
+        message_hash: &UInt256,
+        deadline: u32,
+        poll_interval: Duration,
+    ) -> Result<Option<RawTransaction>> {
+        loop {
+            let expired = clock.now_sec_u64() as u32 > deadline;
+
+            // NOTE: check once more after the deadline, the message
+            // could have been processed right before it
+            if let Some(transaction) = self.get_dst_transaction(message_hash).await? {
+                return Ok(Some(transaction));
+            }
+            if expired {
+                return Ok(None);
+            }
+
+            sleep.sleep(poll_interval).await;
+        }
+    }
+}
+
+impl<T: Transport + ?Sized> TransportExt for T {}
+
+fn internal_out_messages(transaction: &RawTransaction) -> Result<Vec<UInt256>> {
+    let mut result = Vec::new();
+    transaction.data.out_msgs.iterate_slices(|slice| {
+        if let Ok(cell) = slice.reference(0) {
+            let message = ton_block::Message::construct_from_cell(cell.clone())?;
+            if matches!(message.header(), CommonMsgInfo::IntMsgInfo(_)) {
+                result.push(cell.repr_hash());
+            }
+        }
+        Ok(true)
+    })?;
+    Ok(result)
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+enum TransportExtError {
+    #[error("Invalid message type")]
+    InvalidMessageType,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use parking_lot::Mutex;
+    use ton_block::MsgAddressInt;
+
+    use nekoton_utils::MockClock;
+
+    use super::*;
+    use crate::transport::tests::MockTransport;
+
+    /// Advances the clock and publishes the scheduled transactions
+    struct TestSleep<'a> {
+        clock: &'a MockClock,
+        transport: &'a MockTransport,
+        /// Transactions which appear after the specified number of sleeps
+        scheduled: Mutex<Vec<(usize, RawTransaction)>>,
+        calls: AtomicUsize,
+    }
+
+    impl<'a> TestSleep<'a> {
+        fn new(clock: &'a MockClock, transport: &'a MockTransport) -> Self {
+            Self {
+                clock,
+                transport,
+                scheduled: Default::default(),
+                calls: Default::default(),
+            }
+        }
+
+        fn schedule(&self, after_sleeps: usize, transaction: RawTransaction) {
+            self.scheduled.lock().push((after_sleeps, transaction));
+        }
+    }
+
+    #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+    #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+    impl AsyncSleep for TestSleep<'_> {
+        async fn sleep(&self, duration: Duration) {
+            self.clock.advance_ms(duration.as_millis() as u64);
+            let calls = self.calls.fetch_add(1, Ordering::Relaxed) + 1;
+
+            let mut scheduled = self.scheduled.lock();
+            for (_, transaction) in scheduled.iter().filter(|(after, _)| *after == calls) {
+                self.transport
+                    .add_transaction(MsgAddressInt::default(), transaction.clone());
+            }
+            scheduled.retain(|(after, _)| *after != calls);
+        }
+    }
+
+    fn make_external_message() -> ton_block::Message {
+        ton_block::Message::with_ext_in_header(Default::default())
+    }
+
+    fn make_internal_message(created_lt: u64) -> ton_block::Message {
+        ton_block::Message::with_int_header(ton_block::InternalMessageHeader {
+            created_lt,
+            ..Default::default()
+        })
+    }
+
+    fn make_transaction(
+        lt: u64,
+        in_msg: &ton_block::Message,
+        out_msgs: &[ton_block::Message],
+        exit_code: i32,
+    ) -> RawTransaction {
+        let mut data = ton_block::Transaction::default();
+        data.lt = lt;
+        data.write_in_msg(Some(in_msg)).unwrap();
+        for msg in out_msgs {
+            data.add_out_message(msg).unwrap();
+        }
+        data.write_description(&ton_block::TransactionDescr::Ordinary(
+            ton_block::TransactionDescrOrdinary {
+                aborted: exit_code != 0,
+                compute_ph: ton_block::TrComputePhase::Vm(ton_block::TrComputePhaseVm {
+                    success: exit_code == 0,
+                    exit_code,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        ))
+        .unwrap();
+
+        RawTransaction {
+            hash: data.serialize().unwrap().repr_hash(),
+            data,
+        }
+    }
+
+    fn message_hash(message: &ton_block::Message) -> UInt256 {
+        message.serialize().unwrap().repr_hash()
+    }
+
+    const NOW_MS: u64 = 1_000_000;
+
+    #[tokio::test]
+    async fn delivered_after_polling() {
+        let clock = MockClock::new(NOW_MS);
+        let transport = MockTransport::new();
+        let sleep = TestSleep::new(&clock, &transport);
+
+        let message = make_external_message();
+        let first_child = make_internal_message(1);
+        let second_child = make_internal_message(2);
+
+        let transaction = make_transaction(10, &message, &[first_child.clone()], 0);
+        let child = make_transaction(20, &first_child, &[second_child.clone()], 0);
+        transport.add_transaction(MsgAddressInt::default(), child.clone());
+        sleep.schedule(2, transaction.clone());
+
+        let params = DeliveryParams {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let expire_at = (NOW_MS / 1000) as u32 + 60;
+        let delivery = transport
+            .send_message_reliably(&clock, &sleep, &message, expire_at, params)
+            .await
+            .unwrap();
+
+        assert_eq!(transport.take_sent_messages().len(), 1);
+        assert_eq!(sleep.calls.load(Ordering::Relaxed), 2);
+        match delivery {
+            MessageDelivery::Delivered {
+                transaction: delivered,
+                children,
+                pending_messages,
+            } => {
+                assert_eq!(delivered.hash, transaction.hash);
+                assert_eq!(children.len(), 1);
+                assert_eq!(children[0].hash, child.hash);
+                // The second child is beyond the depth limit
+                assert_eq!(pending_messages, [message_hash(&second_child)]);
+            }
+            _ => panic!("message must be delivered"),
+        }
+    }
+
+    #[tokio::test]
+    async fn aborted_transaction() {
+        let clock = MockClock::new(NOW_MS);
+        let transport = MockTransport::new();
+        let sleep = TestSleep::new(&clock, &transport);
+
+        let message = make_external_message();
+        let transaction = make_transaction(10, &message, &[], 60);
+        transport.add_transaction(MsgAddressInt::default(), transaction.clone());
+
+        let expire_at = (NOW_MS / 1000) as u32 + 60;
+        let delivery = transport
+            .send_message_reliably(&clock, &sleep, &message, expire_at, Default::default())
+            .await
+            .unwrap();
+
+        match delivery {
+            MessageDelivery::Aborted {
+                transaction: aborted,
+                exit_code,
+            } => {
+                assert_eq!(aborted.hash, transaction.hash);
+                assert_eq!(exit_code, Some(60));
+            }
+            _ => panic!("transaction must be aborted"),
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_message() {
+        let clock = MockClock::new(NOW_MS);
+        let transport = MockTransport::new();
+        let sleep = TestSleep::new(&clock, &transport);
+
+        let message = make_external_message();
+        let expire_at = (NOW_MS / 1000) as u32 + 5;
+        let delivery = transport
+            .send_message_reliably(&clock, &sleep, &message, expire_at, Default::default())
+            .await
+            .unwrap();
+
+        assert!(matches!(delivery, MessageDelivery::Expired));
+        assert_eq!(transport.take_sent_messages().len(), 1);
+        // Polled until the clock passed the expiration time
+        assert_eq!(sleep.calls.load(Ordering::Relaxed), 6);
+        assert!(clock.now_sec_u64() > expire_at as u64);
+    }
+
+    #[tokio::test]
+    async fn transaction_found_right_after_expiration() {
+        let clock = MockClock::new(NOW_MS);
+        let transport = MockTransport::new();
+        let sleep = TestSleep::new(&clock, &transport);
+
+        let message = make_external_message();
+        sleep.schedule(6, make_transaction(10, &message, &[], 0));
+
+        let expire_at = (NOW_MS / 1000) as u32 + 5;
+        let delivery = transport
+            .send_message_reliably(&clock, &sleep, &message, expire_at, Default::default())
+            .await
+            .unwrap();
+
+        assert!(matches!(delivery, MessageDelivery::Delivered { .. }));
+    }
+
+    #[tokio::test]
+    async fn send_errors() {
+        let clock = MockClock::new(NOW_MS);
+        let transport = MockTransport::new();
+        let sleep = TestSleep::new(&clock, &transport);
+
+        // Only external messages are accepted
+        let internal = make_internal_message(1);
+        let result = transport
+            .send_message_reliably(&clock, &sleep, &internal, u32::MAX, Default::default())
+            .await;
+        assert!(result.is_err());
+
+        transport.set_send_error(Some("rejected".to_owned()));
+        let message = make_external_message();
+        let result = transport
+            .send_message_reliably(&clock, &sleep, &message, u32::MAX, Default::default())
+            .await;
+        assert!(result.is_err());
+        assert_eq!(sleep.calls.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,308 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures_util::future::join_all;
+use futures_util::Future;
+use nekoton_abi::GenTimings;
+use nekoton_utils::Clock;
+use parking_lot::Mutex;
+use ton_block::MsgAddressInt;
+use ton_types::Cell;
+
+use super::models::{PollContractState, RawContractState, RawTransaction};
+use super::proofs::AccountStateProof;
+use super::{Transport, TransportInfo};
+use crate::core::models::NetworkCapabilities;
+use crate::crypto::SignatureId;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FailoverSettings {
+    /// How often endpoints health is checked (in seconds). Default: `60`
+    pub health_check_interval: u64,
+    /// Max age of the latest known block (in seconds) for a healthy endpoint. Default: `120`
+    pub max_block_age: u32,
+}
+
+impl Default for FailoverSettings {
+    fn default() -> Self {
+        Self {
+            health_check_interval: 60,
+            max_block_age: 120,
+        }
+    }
+}
+
+/// Transport which routes requests to the healthiest of the underlying transports
+/// and retries idempotent requests on the other ones in case of error
+pub struct FailoverTransport {
+    clock: Arc<dyn Clock>,
+    settings: FailoverSettings,
+    endpoints: Vec<Endpoint>,
+    health_check_address: MsgAddressInt,
+    last_health_check: Mutex<Option<u64>>,
+}
+
+impl FailoverTransport {
+    pub fn new(
+        clock: Arc<dyn Clock>,
+        transports: Vec<Arc<dyn Transport>>,
+        settings: FailoverSettings,
+    ) -> Result<Self> {
+        if transports.is_empty() {
+            return Err(FailoverTransportError::NoTransports.into());
+        }
+
+        Ok(Self {
+            clock,
+            settings,
+            endpoints: transports
+                .into_iter()
+                .map(|transport| Endpoint {
+                    transport,
+                    health: Mutex::new(Health::Unknown),
+                })
+                .collect(),
+            health_check_address: MsgAddressInt::from_str(CONFIG_ADDRESS)?,
+            last_health_check: Default::default(),
+        })
+    }
+
+    /// Returns the index of the healthiest transport
+    pub async fn best_transport_index(&self) -> usize {
+        self.check_health().await;
+        self.ordered_endpoints()[0]
+    }
+
+    async fn check_health(&self) {
+        let now = self.clock.now_sec_u64();
+        {
+            let mut last_health_check = self.last_health_check.lock();
+            if matches!(*last_health_check, Some(time) if time + self.settings.health_check_interval > now)
+            {
+                return;
+            }
+            *last_health_check = Some(now);
+        }
+
+        let checks = self.endpoints.iter().map(|endpoint| async move {
+            let health = match endpoint
+                .transport
+                .get_contract_state(&self.health_check_address)
+                .await
+            {
+                Ok(state) => match state_timings(&state) {
+                    GenTimings::Known { gen_utime, .. } => {
+                        Health::BlockAge((now as u32).saturating_sub(gen_utime))
+                    }
+                    GenTimings::Unknown => Health::Unknown,
+                },
+                Err(e) => {
+                    log::warn!("Transport health check failed: {e:?}");
+                    Health::Unreachable
+                }
+            };
+            *endpoint.health.lock() = health;
+        });
+        join_all(checks).await;
+    }
+
+    /// Endpoint indices sorted from the healthiest to the least healthy
+    fn ordered_endpoints(&self) -> Vec<usize> {
+        let max_block_age = self.settings.max_block_age;
+        let mut indices = (0..self.endpoints.len())
+            .map(|i| (i, self.endpoints[i].health.lock().rank(max_block_age)))
+            .collect::<Vec<_>>();
+        indices.sort_by_key(|(i, rank)| (*rank, *i));
+        indices.into_iter().map(|(i, _)| i).collect()
+    }
+
+    async fn call<'a, T, F, Fut>(&'a self, f: F) -> Result<T>
+    where
+        F: Fn(&'a dyn Transport) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.check_health().await;
+
+        let mut last_error = None;
+        for i in self.ordered_endpoints() {
+            let endpoint = &self.endpoints[i];
+            match f(endpoint.transport.as_ref()).await {
+                Ok(result) => return Ok(result),
+                Err(e) => {
+                    log::warn!("Transport request failed: {e:?}");
+                    *endpoint.health.lock() = Health::Unreachable;
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| FailoverTransportError::NoTransports.into()))
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Transport for FailoverTransport {
+    fn info(&self) -> TransportInfo {
+        let mut info = self.endpoints[0].transport.info();
+        for endpoint in &self.endpoints[1..] {
+            let other = endpoint.transport.info();
+            info.max_transactions_per_fetch = info
+                .max_transactions_per_fetch
+                .min(other.max_transactions_per_fetch);
+            info.has_key_blocks &= other.has_key_blocks;
+        }
+        info
+    }
+
+    async fn send_message(&self, message: &ton_block::Message) -> Result<()> {
+        // NOTE: message is sent only once to avoid unexpected side effects
+        let index = self.best_transport_index().await;
+        self.endpoints[index].transport.send_message(message).await
+    }
+
+    async fn get_contract_state(&self, address: &MsgAddressInt) -> Result<RawContractState> {
+        self.call(|transport| transport.get_contract_state(address))
+            .await
+    }
+
+    async fn get_library_cell(&self, hash: &ton_types::UInt256) -> Result<Option<Cell>> {
+        self.call(|transport| transport.get_library_cell(hash))
+            .await
+    }
+
+    async fn poll_contract_state(
+        &self,
+        address: &MsgAddressInt,
+        last_trans_lt: u64,
+    ) -> Result<PollContractState> {
+        self.call(|transport| transport.poll_contract_state(address, last_trans_lt))
+            .await
+    }
+
+    async fn get_accounts_by_code_hash(
+        &self,
+        code_hash: &ton_types::UInt256,
+        limit: u8,
+        continuation: &Option<MsgAddressInt>,
+    ) -> Result<Vec<MsgAddressInt>> {
+        self.call(|transport| transport.get_accounts_by_code_hash(code_hash, limit, continuation))
+            .await
+    }
+
+    async fn get_transactions(
+        &self,
+        address: &MsgAddressInt,
+        from_lt: u64,
+        count: u8,
+    ) -> Result<Vec<RawTransaction>> {
+        self.call(|transport| transport.get_transactions(address, from_lt, count))
+            .await
+    }
+
+    async fn get_transaction(&self, id: &ton_types::UInt256) -> Result<Option<RawTransaction>> {
+        self.call(|transport| transport.get_transaction(id)).await
+    }
+
+    async fn get_dst_transaction(
+        &self,
+        message_hash: &ton_types::UInt256,
+    ) -> Result<Option<RawTransaction>> {
+        self.call(|transport| transport.get_dst_transaction(message_hash))
+            .await
+    }
+
+    async fn get_latest_key_block(&self) -> Result<ton_block::Block> {
+        self.call(|transport| transport.get_latest_key_block())
+            .await
+    }
+
+    async fn get_capabilities(&self, clock: &dyn Clock) -> Result<NetworkCapabilities> {
+        self.call(|transport| transport.get_capabilities(clock))
+            .await
+    }
+
+    async fn get_signature_id(&self, clock: &dyn Clock) -> Result<Option<SignatureId>> {
+        self.call(|transport| transport.get_signature_id(clock))
+            .await
+    }
+
+    async fn get_blockchain_config(
+        &self,
+        clock: &dyn Clock,
+        force: bool,
+    ) -> Result<ton_executor::BlockchainConfig> {
+        self.call(|transport| transport.get_blockchain_config(clock, force))
+            .await
+    }
+
+    async fn get_contract_state_proof(
+        &self,
+        address: &MsgAddressInt,
+        block_hash: &ton_types::UInt256,
+    ) -> Result<AccountStateProof> {
+        self.call(|transport| transport.get_contract_state_proof(address, block_hash))
+            .await
+    }
+}
+
+struct Endpoint {
+    transport: Arc<dyn Transport>,
+    health: Mutex<Health>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Health {
+    Unknown,
+    BlockAge(u32),
+    Unreachable,
+}
+
+impl Health {
+    /// Lower is better
+    fn rank(&self, max_block_age: u32) -> (u8, u32) {
+        match *self {
+            Self::BlockAge(age) if age <= max_block_age => (0, age),
+            Self::Unknown => (1, 0),
+            Self::BlockAge(age) => (2, age),
+            Self::Unreachable => (3, 0),
+        }
+    }
+}
+
+fn state_timings(state: &RawContractState) -> GenTimings {
+    match state {
+        RawContractState::NotExists { timings } => *timings,
+        RawContractState::Exists(contract) => contract.timings,
+    }
+}
+
+const CONFIG_ADDRESS: &str = "-1:5555555555555555555555555555555555555555555555555555555555555555";
+
+#[derive(thiserror::Error, Debug)]
+enum FailoverTransportError {
+    #[error("No transports specified")]
+    NoTransports,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_ordering() {
+        let max_block_age = 120;
+        let mut ranks = [
+            Health::Unreachable,
+            Health::BlockAge(500),
+            Health::Unknown,
+            Health::BlockAge(30),
+            Health::BlockAge(10),
+        ]
+        .map(|health| health.rank(max_block_age));
+        ranks.sort();
+
+        assert_eq!(ranks, [(0, 10), (0, 30), (1, 0), (2, 500), (3, 0)],);
+    }
+}
@@ -13,15 +13,18 @@ use crate::core::models::{NetworkCapabilities, ReliableBehavior};
 use crate::external::{GqlConnection, GqlRequest};
 
 use self::queries::*;
+use super::ext::AsyncSleep;
 use super::models::*;
+use super::options::{RequestPolicy, TransportOptions};
 use super::utils::{ConfigCache, ConfigResponse};
 use super::{Transport, TransportInfo};
 
-mod queries;
+pub mod queries;
 
 pub struct GqlTransport {
     connection: Arc<dyn GqlConnection>,
     config_cache: ConfigCache,
+    overrides: GqlQueryOverrides,
 }
 
 impl GqlTransport {
@@ -31,19 +34,86 @@ impl GqlTransport {
         Self {
             connection,
             config_cache: ConfigCache::new(use_default_config),
+            overrides: Default::default(),
         }
     }
 
-    async fn fetch<T>(&self, params: T::Variables) -> Result<T::ResponseData>
+    /// Replaces the queries of the built-in methods (e.g. to select additional
+    /// fields or to use a custom indexer schema)
+    pub fn with_query_overrides(mut self, overrides: GqlQueryOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Creates the transport which applies timeouts, retries and rate limits to all requests.
+    ///
+    /// NOTE: long queries (e.g. waiting for the next block) are not limited by the request timeout
+    pub fn with_options(
+        connection: Arc<dyn GqlConnection>,
+        options: TransportOptions,
+        clock: Arc<dyn Clock>,
+        sleep: Arc<dyn AsyncSleep>,
+    ) -> Self {
+        Self::new(Arc::new(GqlConnectionWithOptions {
+            connection,
+            policy: RequestPolicy::new(options, clock, sleep),
+        }))
+    }
+
+    /// Executes the typed query (e.g. a custom [`GqlQuery`] implementation)
+    pub async fn query<T>(&self, params: T::Variables) -> Result<T::ResponseData>
     where
         T: GqlQuery,
     {
         let request_body = serde_json::to_string(&T::build_query(&params)).trust_me();
+        self.post(request_body, T::LONG_QUERY).await
+    }
+
+    /// Executes the query string (e.g. built with [`GqlQueryBuilder`])
+    /// and parses its `data` into the custom response type
+    pub async fn raw_query<V, R>(&self, query: &str, variables: &V, long_query: bool) -> Result<R>
+    where
+        V: serde::Serialize + Sync,
+        R: for<'de> Deserialize<'de>,
+    {
+        let request_body = serde_json::to_string(&QueryBody {
+            variables,
+            query: query.into(),
+        })
+        .trust_me();
+        self.post(request_body, long_query).await
+    }
+
+    /// Executes the custom query if it is specified, or the built-in one otherwise
+    async fn query_or_custom<T>(
+        &self,
+        custom: &Option<GqlCustomQuery<T>>,
+        params: T::Variables,
+    ) -> Result<T::ResponseData>
+    where
+        T: GqlQuery,
+        T::Variables: Sync,
+    {
+        match custom {
+            Some(custom) => {
+                let data = self
+                    .raw_query::<_, serde_json::Value>(&custom.query, &params, T::LONG_QUERY)
+                    .await?;
+                (custom.mapper)(data)
+            }
+            None => self.query::<T>(params).await,
+        }
+    }
+
+    async fn post<R>(&self, request_body: String, long_query: bool) -> Result<R>
+    where
+        R: for<'de> Deserialize<'de>,
+    {
         let response = self
             .connection
             .post(GqlRequest {
                 data: request_body,
-                long_query: T::LONG_QUERY,
+                long_query,
             })
             .await
             .map_err(api_failure)?;
@@ -53,7 +123,7 @@ impl GqlTransport {
             pub data: Option<T>,
         }
 
-        match serde_json::from_str::<Response<T::ResponseData>>(&response) {
+        match serde_json::from_str::<Response<R>>(&response) {
             Ok(response) => response.data.ok_or_else(|| invalid_response().into()),
             Err(e) => Err(api_failure(format!(
                 "Failed parsing api response: {e}. Response data: {response}"
@@ -66,7 +136,7 @@ impl GqlTransport {
         let workchain_id = addr.get_workchain_id();
 
         let block = self
-            .fetch::<QueryLatestMasterchainBlock>(())
+            .query::<QueryLatestMasterchainBlock>(())
             .await?
             .blocks
             .into_iter()
@@ -99,7 +169,7 @@ impl GqlTransport {
             // Node SE case (without masterchain and sharding)
             None => {
                 let blocks = self
-                    .fetch::<QueryNodeSeConditions>(query_node_se_conditions::Variables {
+                    .query::<QueryNodeSeConditions>(query_node_se_conditions::Variables {
                         workchain: workchain_id,
                     })
                     .await?
@@ -112,7 +182,7 @@ impl GqlTransport {
                 }
 
                 let blocks = self
-                    .fetch::<QueryNodeSeLatestBlock>(query_node_se_latest_block::Variables {
+                    .query::<QueryNodeSeLatestBlock>(query_node_se_latest_block::Variables {
                         workchain: workchain_id,
                     })
                     .await?
@@ -130,7 +200,10 @@ impl GqlTransport {
 
     pub async fn get_block(&self, id: &str) -> Result<ton_block::Block> {
         let blocks = self
-            .fetch::<QueryBlock>(query_block::Variables { id: id.to_owned() })
+            .query_or_custom(
+                &self.overrides.block,
+                query_block::Variables { id: id.to_owned() },
+            )
             .await?
             .blocks;
         let boc = blocks.into_iter().next().ok_or_else(no_blocks_found)?.boc;
@@ -148,7 +221,7 @@ impl GqlTransport {
         let timeout_ms = timeout.as_secs_f64() * 1000.0;
 
         let blocks = self
-            .fetch::<QueryNextBlock>(query_next_block::Variables {
+            .query::<QueryNextBlock>(query_next_block::Variables {
                 id: current.to_owned(),
                 timeout: timeout_ms,
             })
@@ -159,7 +232,7 @@ impl GqlTransport {
         let block_id =
             if block.after_split && !check_shard_match(block.workchain_id, &block.shard, addr)? {
                 let blocks = self
-                    .fetch::<QueryBlockAfterSplit>(query_block_after_split::Variables {
+                    .query::<QueryBlockAfterSplit>(query_block_after_split::Variables {
                         block_id: block.id,
                         prev_id: current.to_owned(),
                         timeout: timeout_ms,
@@ -212,7 +285,7 @@ impl Transport for GqlTransport {
         let id = base64::encode(cell.repr_hash());
 
         let _ = self
-            .fetch::<MutationSendMessage>(mutation_send_message::Variables { id, boc })
+            .query::<MutationSendMessage>(mutation_send_message::Variables { id, boc })
             .await?;
 
         Ok(())
@@ -220,9 +293,12 @@ impl Transport for GqlTransport {
 
     async fn get_contract_state(&self, address: &MsgAddressInt) -> Result<RawContractState> {
         let account_state = match self
-            .fetch::<QueryAccountState>(query_account_state::Variables {
-                address: address.to_string(),
-            })
+            .query_or_custom(
+                &self.overrides.account_state,
+                query_account_state::Variables {
+                    address: address.to_string(),
+                },
+            )
             .await?
             .accounts
             .into_iter()
@@ -276,11 +352,14 @@ impl Transport for GqlTransport {
         limit: u8,
         continuation: &Option<MsgAddressInt>,
     ) -> Result<Vec<MsgAddressInt>> {
-        self.fetch::<QueryAccountsByCodeHash>(query_accounts_by_code_hash::Variables {
-            code_hash: code_hash.to_hex_string(),
-            continuation: continuation.as_ref().map(ToString::to_string),
-            limit,
-        })
+        self.query_or_custom(
+            &self.overrides.accounts_by_code_hash,
+            query_accounts_by_code_hash::Variables {
+                code_hash: code_hash.to_hex_string(),
+                continuation: continuation.as_ref().map(ToString::to_string),
+                limit,
+            },
+        )
         .await?
         .accounts
         .into_iter()
@@ -294,11 +373,14 @@ impl Transport for GqlTransport {
         from_lt: u64,
         count: u8,
     ) -> Result<Vec<RawTransaction>> {
-        self.fetch::<QueryAccountTransactions>(query_account_transactions::Variables {
-            address: address.to_string(),
-            last_transaction_lt: from_lt.to_string(),
-            limit: count,
-        })
+        self.query_or_custom(
+            &self.overrides.account_transactions,
+            query_account_transactions::Variables {
+                address: address.to_string(),
+                last_transaction_lt: from_lt.to_string(),
+                limit: count,
+            },
+        )
         .await?
         .transactions
         .into_iter()
@@ -317,9 +399,12 @@ impl Transport for GqlTransport {
     }
 
     async fn get_transaction(&self, id: &ton_types::UInt256) -> Result<Option<RawTransaction>> {
-        self.fetch::<QueryTransaction>(query_transaction::Variables {
-            hash: id.to_hex_string(),
-        })
+        self.query_or_custom(
+            &self.overrides.transaction,
+            query_transaction::Variables {
+                hash: id.to_hex_string(),
+            },
+        )
         .await?
         .transactions
         .into_iter()
@@ -342,9 +427,12 @@ impl Transport for GqlTransport {
         &self,
         message_hash: &ton_types::UInt256,
     ) -> Result<Option<RawTransaction>> {
-        self.fetch::<QueryDstTransaction>(query_dst_transaction::Variables {
-            hash: message_hash.to_hex_string(),
-        })
+        self.query_or_custom(
+            &self.overrides.dst_transaction,
+            query_dst_transaction::Variables {
+                hash: message_hash.to_hex_string(),
+            },
+        )
         .await?
         .transactions
         .into_iter()
@@ -364,7 +452,10 @@ impl Transport for GqlTransport {
     }
 
     async fn get_latest_key_block(&self) -> Result<ton_block::Block> {
-        let blocks = self.fetch::<QueryLatestKeyBlock>(()).await?.blocks;
+        let blocks = self
+            .query_or_custom(&self.overrides.latest_key_block, ())
+            .await?
+            .blocks;
         let boc = blocks.into_iter().next().ok_or_else(no_blocks_found)?.boc;
 
         ton_block::Block::construct_from_base64(&boc)
@@ -455,6 +546,46 @@ pub enum NodeClientError {
     InvalidConfig,
 }
 
+struct GqlConnectionWithOptions {
+    connection: Arc<dyn GqlConnection>,
+    policy: RequestPolicy,
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl GqlConnection for GqlConnectionWithOptions {
+    fn is_local(&self) -> bool {
+        self.connection.is_local()
+    }
+
+    async fn post(&self, req: GqlRequest) -> Result<String> {
+        let mut params = self.policy.params(query_root_field(&req.data).as_deref());
+        if req.long_query {
+            params.request_timeout = None;
+        }
+
+        self.policy
+            .run(params, || self.connection.post(req.clone()))
+            .await
+    }
+}
+
+/// Extracts the name of the first root field of the query (e.g. `blocks`)
+fn query_root_field(request_body: &str) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Body {
+        query: String,
+    }
+
+    let Body { query } = serde_json::from_str(request_body).ok()?;
+    let (_, selection) = query.split_once('{')?;
+    let field = selection
+        .trim_start()
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .next()?;
+    (!field.is_empty()).then(|| field.to_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -483,6 +614,87 @@ mod tests {
         }
     }
 
+    /// Returns the same response for all requests
+    struct StaticConnection {
+        response: &'static str,
+        requests: parking_lot::Mutex<Vec<String>>,
+    }
+
+    #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+    #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+    impl GqlConnection for StaticConnection {
+        fn is_local(&self) -> bool {
+            false
+        }
+
+        async fn post(&self, req: GqlRequest) -> Result<String> {
+            self.requests.lock().push(req.data);
+            Ok(self.response.to_owned())
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_query_mappers() {
+        const QUERY: &str =
+            "query($h:String!,$c:String,$l:Int!){wallets(code_hash:$h,after:$c,first:$l){address}}";
+
+        let connection = Arc::new(StaticConnection {
+            response: r#"{"data":{"wallets":[{"address":"0:3333333333333333333333333333333333333333333333333333333333333333"}]}}"#,
+            requests: Default::default(),
+        });
+
+        let overrides = GqlQueryOverrides {
+            accounts_by_code_hash: Some(GqlCustomQuery::with_mapper(QUERY, |data| {
+                #[derive(Deserialize)]
+                struct Wallets {
+                    wallets: Vec<Wallet>,
+                }
+
+                #[derive(Deserialize)]
+                struct Wallet {
+                    address: String,
+                }
+
+                let Wallets { wallets } = serde_json::from_value(data)?;
+                Ok(query_accounts_by_code_hash::ResponseData {
+                    accounts: wallets
+                        .into_iter()
+                        .map(|wallet| {
+                            query_accounts_by_code_hash::QueryAccountsByCodeHashAccounts {
+                                id: wallet.address,
+                            }
+                        })
+                        .collect(),
+                })
+            })),
+            ..Default::default()
+        };
+        let transport = GqlTransport::new(connection.clone()).with_query_overrides(overrides);
+
+        let accounts = transport
+            .get_accounts_by_code_hash(&Default::default(), 10, &None)
+            .await
+            .unwrap();
+        assert_eq!(
+            accounts,
+            [MsgAddressInt::from_str(
+                "0:3333333333333333333333333333333333333333333333333333333333333333"
+            )
+            .unwrap()]
+        );
+
+        let request = connection.requests.lock().pop().unwrap();
+        let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+        assert_eq!(request["query"], QUERY);
+        assert_eq!(request["variables"]["l"], 10);
+
+        // Queries without overrides are not affected
+        let state = transport.get_contract_state(&Default::default()).await;
+        assert!(state.is_err());
+        let request = connection.requests.lock().pop().unwrap();
+        assert!(request.contains("accounts(filter:{id:{eq:$a}},limit:1){boc}"));
+    }
+
     #[tokio::test]
     async fn test_connection() {
         let transport = GqlTransport::new(Arc::new(reqwest::Client::new()));
@@ -1,5 +1,14 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+/// GraphQL query with typed variables and response.
+///
+/// Can be implemented for custom queries and executed with [`GqlTransport::query`]
+///
+/// [`GqlTransport::query`]: super::GqlTransport::query
 pub trait GqlQuery {
     type Variables: serde::Serialize;
     type ResponseData: for<'de> serde::Deserialize<'de>;
@@ -12,7 +21,173 @@ pub trait GqlQuery {
 #[derive(Serialize)]
 pub struct QueryBody<'a> {
     pub variables: &'a dyn erased_serde::Serialize,
-    pub query: &'static str,
+    pub query: Cow<'a, str>,
+}
+
+/// Builder for queries with custom field selection.
+///
+/// ```text
+/// query($address:String!){accounts(filter:{id:{eq:$address}},limit:1){boc balance_other{currency value}}}
+/// ```
+#[derive(Debug, Clone)]
+pub struct GqlQueryBuilder {
+    collection: String,
+    variables: Vec<(String, String)>,
+    filter: Option<String>,
+    order_by: Option<String>,
+    limit: Option<u32>,
+    timeout: Option<String>,
+    fields: Vec<String>,
+}
+
+impl GqlQueryBuilder {
+    /// Creates a query for the collection (e.g. `accounts`, `transactions`, `blocks`)
+    pub fn new<T: Into<String>>(collection: T) -> Self {
+        Self {
+            collection: collection.into(),
+            variables: Vec::new(),
+            filter: None,
+            order_by: None,
+            limit: None,
+            timeout: None,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Declares a query variable (e.g. `("address", "String!")`)
+    pub fn variable<N: Into<String>, T: Into<String>>(mut self, name: N, ty: T) -> Self {
+        self.variables.push((name.into(), ty.into()));
+        self
+    }
+
+    /// Sets the filter without outer braces (e.g. `id:{eq:$address}`)
+    pub fn filter<T: Into<String>>(mut self, filter: T) -> Self {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Sets the ordering without outer brackets (e.g. `{path:"lt",direction:DESC}`)
+    pub fn order_by<T: Into<String>>(mut self, order_by: T) -> Self {
+        self.order_by = Some(order_by.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sets the wait timeout (a number or a variable, e.g. `$t`)
+    pub fn timeout<T: Into<String>>(mut self, timeout: T) -> Self {
+        self.timeout = Some(timeout.into());
+        self
+    }
+
+    /// Adds the selected field (nested selections are allowed, e.g. `balance_other{currency value}`)
+    pub fn field<T: Into<String>>(mut self, field: T) -> Self {
+        self.fields.push(field.into());
+        self
+    }
+
+    /// Adds the selected field only if the condition is true
+    pub fn field_if<T: Into<String>>(self, condition: bool, field: T) -> Self {
+        if condition {
+            self.field(field)
+        } else {
+            self
+        }
+    }
+
+    pub fn build(&self) -> String {
+        let mut query = String::from("query");
+        if !self.variables.is_empty() {
+            let variables = self
+                .variables
+                .iter()
+                .map(|(name, ty)| format!("${name}:{ty}"))
+                .collect::<Vec<_>>();
+            query.push('(');
+            query.push_str(&variables.join(","));
+            query.push(')');
+        }
+
+        let mut args = Vec::new();
+        if let Some(filter) = &self.filter {
+            args.push(format!("filter:{{{filter}}}"));
+        }
+        if let Some(order_by) = &self.order_by {
+            args.push(format!("orderBy:[{order_by}]"));
+        }
+        if let Some(limit) = self.limit {
+            args.push(format!("limit:{limit}"));
+        }
+        if let Some(timeout) = &self.timeout {
+            args.push(format!("timeout:{timeout}"));
+        }
+
+        query.push('{');
+        query.push_str(&self.collection);
+        if !args.is_empty() {
+            query.push('(');
+            query.push_str(&args.join(","));
+            query.push(')');
+        }
+        query.push('{');
+        query.push_str(&self.fields.join(" "));
+        query.push_str("}}");
+        query
+    }
+}
+
+/// Custom query and response mapper, used instead of the built-in query `Q`.
+///
+/// The query receives the same variables as the built-in one
+pub struct GqlCustomQuery<Q: GqlQuery> {
+    pub query: String,
+    pub mapper: GqlResponseMapper<Q::ResponseData>,
+}
+
+/// Converts the `data` of the response into the built-in response type
+pub type GqlResponseMapper<T> = Arc<dyn Fn(serde_json::Value) -> Result<T> + Send + Sync>;
+
+impl<Q: GqlQuery> GqlCustomQuery<Q> {
+    /// Creates a query which response has the same shape as the built-in one
+    /// (e.g. with additional selected fields)
+    pub fn new<T: Into<String>>(query: T) -> Self {
+        Self::with_mapper(query, |data| Ok(serde_json::from_value(data)?))
+    }
+
+    pub fn with_mapper<T, F>(query: T, mapper: F) -> Self
+    where
+        T: Into<String>,
+        F: Fn(serde_json::Value) -> Result<Q::ResponseData> + Send + Sync + 'static,
+    {
+        Self {
+            query: query.into(),
+            mapper: Arc::new(mapper),
+        }
+    }
+}
+
+impl<Q: GqlQuery> Clone for GqlCustomQuery<Q> {
+    fn clone(&self) -> Self {
+        Self {
+            query: self.query.clone(),
+            mapper: self.mapper.clone(),
+        }
+    }
+}
+
+/// Custom queries for the built-in transport methods
+#[derive(Default, Clone)]
+pub struct GqlQueryOverrides {
+    pub block: Option<GqlCustomQuery<QueryBlock>>,
+    pub account_state: Option<GqlCustomQuery<QueryAccountState>>,
+    pub account_transactions: Option<GqlCustomQuery<QueryAccountTransactions>>,
+    pub transaction: Option<GqlCustomQuery<QueryTransaction>>,
+    pub dst_transaction: Option<GqlCustomQuery<QueryDstTransaction>>,
+    pub accounts_by_code_hash: Option<GqlCustomQuery<QueryAccountsByCodeHash>>,
+    pub latest_key_block: Option<GqlCustomQuery<QueryLatestKeyBlock>>,
 }
 
 macro_rules! declare_queries {
@@ -28,7 +203,7 @@ macro_rules! declare_queries {
             fn build_query(variables: &'_ Self::Variables) -> QueryBody<'_> {
                 QueryBody {
                     variables,
-                    query: $query_module::QUERY,
+                    query: Cow::Borrowed($query_module::QUERY),
                 }
             }
         })*
@@ -359,3 +534,32 @@ pub mod mutation_send_message {
     #[derive(Deserialize)]
     pub struct ResponseData {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_queries() {
+        let query = GqlQueryBuilder::new("blocks")
+            .variable("id", "String!")
+            .filter("id:{eq:$id}")
+            .limit(1)
+            .field("boc")
+            .build();
+        assert_eq!(query, query_block::QUERY);
+
+        let query = GqlQueryBuilder::new("accounts")
+            .variable("address", "String!")
+            .filter("id:{eq:$address}")
+            .limit(1)
+            .field("acc_type")
+            .field_if(false, "boc")
+            .field_if(true, "balance_other{currency value}")
+            .build();
+        assert_eq!(
+            query,
+            "query($address:String!){accounts(filter:{id:{eq:$address}},limit:1){acc_type balance_other{currency value}}}"
+        );
+    }
+}
@@ -9,7 +9,9 @@ use ton_types::{Cell, UInt256};
 use crate::core::models::{NetworkCapabilities, ReliableBehavior};
 use crate::external::{self, JrpcConnection};
 
+use super::ext::AsyncSleep;
 use super::models::{PollContractState, RawContractState, RawTransaction};
+use super::options::{RequestPolicy, TransportOptions};
 use super::utils::*;
 use super::{Transport, TransportInfo};
 
@@ -32,6 +34,19 @@ impl JrpcTransport {
         }
     }
 
+    /// Creates the transport which applies timeouts, retries and rate limits to all requests
+    pub fn with_options(
+        connection: Arc<dyn JrpcConnection>,
+        options: TransportOptions,
+        clock: Arc<dyn Clock>,
+        sleep: Arc<dyn AsyncSleep>,
+    ) -> Self {
+        Self::new(Arc::new(JrpcConnectionWithOptions {
+            connection,
+            policy: RequestPolicy::new(options, clock, sleep),
+        }))
+    }
+
     async fn fetch_config(&self) -> Result<ConfigResponse> {
         let req = external::JrpcRequest {
             data: make_jrpc_request("getBlockchainConfig", &()),
@@ -257,6 +272,32 @@ impl Transport for JrpcTransport {
     }
 }
 
+struct JrpcConnectionWithOptions {
+    connection: Arc<dyn JrpcConnection>,
+    policy: RequestPolicy,
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl JrpcConnection for JrpcConnectionWithOptions {
+    async fn post(&self, req: external::JrpcRequest) -> Result<String> {
+        #[derive(Deserialize)]
+        struct Method<'a> {
+            method: &'a str,
+        }
+
+        let method = serde_json::from_str::<Method>(&req.data)
+            .ok()
+            .map(|Method { method }| method);
+
+        self.policy
+            .run(self.policy.params(method), || {
+                self.connection.post(req.clone())
+            })
+            .await
+    }
+}
+
 pub fn make_jrpc_request<S>(method: &str, params: &S) -> String
 where
     S: Serialize,
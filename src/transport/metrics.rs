@@ -0,0 +1,259 @@
+//! Instrumentation hooks for the transport and subscription layers.
+//!
+//! Metrics are reported to the [`TransportMetrics`] implementation installed once
+//! with [`set_transport_metrics`], spans and events are emitted with `tracing`
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures_util::Future;
+use nekoton_utils::Clock;
+use once_cell::sync::OnceCell;
+use ton_block::MsgAddressInt;
+use ton_types::Cell;
+use tracing::Instrument;
+
+use super::models::{PollContractState, RawContractState, RawTransaction};
+use super::proofs::{AccountStateProof, ProofError};
+use super::{Transport, TransportInfo};
+use crate::core::models::NetworkCapabilities;
+use crate::crypto::SignatureId;
+
+static TRANSPORT_METRICS: OnceCell<Arc<dyn TransportMetrics>> = OnceCell::new();
+
+/// Receives metrics of the transport requests and subscriptions
+pub trait TransportMetrics: Send + Sync {
+    /// Called after each request of the [`InstrumentedTransport`]
+    fn on_request(&self, method: &'static str, latency: Duration, error: Option<RequestErrorKind>) {
+        let _ = (method, latency, error);
+    }
+
+    /// Called after each poll of the subscription manager
+    fn on_poll_cycle(&self, refreshed: usize, failed: usize, duration: Duration) {
+        let _ = (refreshed, failed, duration);
+    }
+
+    /// Called when the pending message of the subscription expired
+    fn on_message_expired(&self, address: &MsgAddressInt) {
+        let _ = address;
+    }
+}
+
+/// Installs the global metrics receiver. Can be called only once
+pub fn set_transport_metrics(metrics: Arc<dyn TransportMetrics>) -> Result<()> {
+    TRANSPORT_METRICS
+        .set(metrics)
+        .map_err(|_| MetricsError::AlreadyInstalled.into())
+}
+
+pub(crate) fn transport_metrics() -> Option<&'static dyn TransportMetrics> {
+    TRANSPORT_METRICS.get().map(Arc::as_ref)
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum RequestErrorKind {
+    Timeout,
+    NotSupported,
+    Other,
+}
+
+impl RequestErrorKind {
+    fn from_error(error: &anyhow::Error) -> Self {
+        #[cfg(any(
+            feature = "gql_transport",
+            feature = "jrpc_transport",
+            feature = "proto_transport",
+        ))]
+        if let Some(super::options::TransportOptionsError::Timeout) = error.downcast_ref() {
+            return Self::Timeout;
+        }
+
+        match error.downcast_ref() {
+            Some(ProofError::NotSupported) => Self::NotSupported,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Transport which reports the latency and errors of each request
+pub struct InstrumentedTransport {
+    transport: Arc<dyn Transport>,
+    clock: Arc<dyn Clock>,
+}
+
+impl InstrumentedTransport {
+    pub fn new(transport: Arc<dyn Transport>, clock: Arc<dyn Clock>) -> Self {
+        Self { transport, clock }
+    }
+
+    async fn call<T, Fut>(&self, method: &'static str, fut: Fut) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        let started_at = self.clock.now_ms_u64();
+        let result = fut
+            .instrument(tracing::debug_span!("transport_request", method))
+            .await;
+        let latency = Duration::from_millis(self.clock.now_ms_u64().saturating_sub(started_at));
+
+        let error = result.as_ref().err().map(|e| {
+            tracing::warn!(method, error = %e, "transport request failed");
+            RequestErrorKind::from_error(e)
+        });
+        if let Some(metrics) = transport_metrics() {
+            metrics.on_request(method, latency, error);
+        }
+
+        result
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Transport for InstrumentedTransport {
+    fn info(&self) -> TransportInfo {
+        self.transport.info()
+    }
+
+    async fn send_message(&self, message: &ton_block::Message) -> Result<()> {
+        self.call("send_message", self.transport.send_message(message))
+            .await
+    }
+
+    async fn get_contract_state(&self, address: &MsgAddressInt) -> Result<RawContractState> {
+        self.call(
+            "get_contract_state",
+            self.transport.get_contract_state(address),
+        )
+        .await
+    }
+
+    async fn get_library_cell(&self, hash: &ton_types::UInt256) -> Result<Option<Cell>> {
+        self.call("get_library_cell", self.transport.get_library_cell(hash))
+            .await
+    }
+
+    async fn poll_contract_state(
+        &self,
+        address: &MsgAddressInt,
+        last_trans_lt: u64,
+    ) -> Result<PollContractState> {
+        self.call(
+            "poll_contract_state",
+            self.transport.poll_contract_state(address, last_trans_lt),
+        )
+        .await
+    }
+
+    async fn get_accounts_by_code_hash(
+        &self,
+        code_hash: &ton_types::UInt256,
+        limit: u8,
+        continuation: &Option<MsgAddressInt>,
+    ) -> Result<Vec<MsgAddressInt>> {
+        self.call(
+            "get_accounts_by_code_hash",
+            self.transport
+                .get_accounts_by_code_hash(code_hash, limit, continuation),
+        )
+        .await
+    }
+
+    async fn get_transactions(
+        &self,
+        address: &MsgAddressInt,
+        from_lt: u64,
+        count: u8,
+    ) -> Result<Vec<RawTransaction>> {
+        self.call(
+            "get_transactions",
+            self.transport.get_transactions(address, from_lt, count),
+        )
+        .await
+    }
+
+    async fn get_transaction(&self, id: &ton_types::UInt256) -> Result<Option<RawTransaction>> {
+        self.call("get_transaction", self.transport.get_transaction(id))
+            .await
+    }
+
+    async fn get_dst_transaction(
+        &self,
+        message_hash: &ton_types::UInt256,
+    ) -> Result<Option<RawTransaction>> {
+        self.call(
+            "get_dst_transaction",
+            self.transport.get_dst_transaction(message_hash),
+        )
+        .await
+    }
+
+    async fn get_latest_key_block(&self) -> Result<ton_block::Block> {
+        self.call(
+            "get_latest_key_block",
+            self.transport.get_latest_key_block(),
+        )
+        .await
+    }
+
+    async fn get_capabilities(&self, clock: &dyn Clock) -> Result<NetworkCapabilities> {
+        self.call("get_capabilities", self.transport.get_capabilities(clock))
+            .await
+    }
+
+    async fn get_signature_id(&self, clock: &dyn Clock) -> Result<Option<SignatureId>> {
+        self.call("get_signature_id", self.transport.get_signature_id(clock))
+            .await
+    }
+
+    async fn get_blockchain_config(
+        &self,
+        clock: &dyn Clock,
+        force: bool,
+    ) -> Result<ton_executor::BlockchainConfig> {
+        self.call(
+            "get_blockchain_config",
+            self.transport.get_blockchain_config(clock, force),
+        )
+        .await
+    }
+
+    async fn get_contract_state_proof(
+        &self,
+        address: &MsgAddressInt,
+        block_hash: &ton_types::UInt256,
+    ) -> Result<AccountStateProof> {
+        self.call(
+            "get_contract_state_proof",
+            self.transport.get_contract_state_proof(address, block_hash),
+        )
+        .await
+    }
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+enum MetricsError {
+    #[error("Transport metrics are already installed")]
+    AlreadyInstalled,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_errors() {
+        let error = anyhow::Error::from(ProofError::NotSupported);
+        assert_eq!(
+            RequestErrorKind::from_error(&error),
+            RequestErrorKind::NotSupported
+        );
+
+        let error = anyhow::anyhow!("connection reset");
+        assert_eq!(
+            RequestErrorKind::from_error(&error),
+            RequestErrorKind::Other
+        );
+    }
+}
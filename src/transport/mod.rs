@@ -1,3 +1,4 @@
+use crate::crypto::SignatureId;
 use crate::models::{NetworkCapabilities, ReliableBehavior};
 use anyhow::Result;
 use nekoton_utils::Clock;
@@ -7,15 +8,33 @@ use ton_types::Cell;
 
 use self::models::*;
 
+#[cfg(feature = "adnl_transport")]
+pub mod adnl;
+pub mod cached;
+pub mod ext;
+pub mod failover;
 #[cfg(feature = "gql_transport")]
 pub mod gql;
 #[cfg(feature = "jrpc_transport")]
 pub mod jrpc;
+#[cfg(feature = "instrumentation")]
+pub mod metrics;
 #[cfg(feature = "proto_transport")]
 pub mod proto;
 
 pub mod models;
 #[cfg(any(
+    feature = "adnl_transport",
+    feature = "gql_transport",
+    feature = "jrpc_transport",
+    feature = "proto_transport",
+))]
+pub mod options;
+pub mod proofs;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod tests;
+#[cfg(any(
+    feature = "adnl_transport",
     feature = "gql_transport",
     feature = "jrpc_transport",
     feature = "proto_transport",
@@ -64,12 +83,42 @@ pub trait Transport: Send + Sync {
 
     async fn get_capabilities(&self, clock: &dyn Clock) -> Result<NetworkCapabilities>;
 
+    /// Signature id which must be used to sign messages in this network.
+    ///
+    /// Returns `None` if `CapSignatureWithId` capability is disabled
+    async fn get_signature_id(&self, clock: &dyn Clock) -> Result<Option<SignatureId>> {
+        Ok(self.get_capabilities(clock).await?.signature_id())
+    }
+
     // NOTE: clock is used for caching here
     async fn get_blockchain_config(
         &self,
         clock: &dyn Clock,
         force: bool,
     ) -> Result<ton_executor::BlockchainConfig>;
+
+    /// Account state at the specified block with its merkle proofs.
+    ///
+    /// Returns an error if the transport doesn't support proofs
+    async fn get_contract_state_proof(
+        &self,
+        _address: &MsgAddressInt,
+        _block_hash: &ton_types::UInt256,
+    ) -> Result<proofs::AccountStateProof> {
+        Err(proofs::ProofError::NotSupported.into())
+    }
+
+    /// Account state, verified against the trusted block
+    async fn get_contract_state_verified(
+        &self,
+        address: &MsgAddressInt,
+        trusted_block_hash: &ton_types::UInt256,
+    ) -> Result<RawContractState> {
+        let proof = self
+            .get_contract_state_proof(address, trusted_block_hash)
+            .await?;
+        proofs::verify_account_state(trusted_block_hash, address, &proof)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures_util::future::Either;
+use futures_util::Future;
+use nekoton_utils::Clock;
+use parking_lot::Mutex;
+
+use super::ext::AsyncSleep;
+
+/// Timeouts, retries and rate limiting of the transport requests
+#[derive(Debug, Clone)]
+pub struct TransportOptions {
+    /// Max duration of one request attempt. Default: `Some(30s)`
+    pub request_timeout: Option<Duration>,
+    /// Number of additional attempts after the failed one. Default: `2`.
+    ///
+    /// Connection errors are opaque, so any failed attempt is retried.
+    /// Methods which send messages are not retried unless they are
+    /// configured in `method_overrides`
+    pub retries: u32,
+    /// Delay between attempts
+    pub backoff: Backoff,
+    /// Max number of requests per second. Default: `None` (unlimited)
+    pub rate_limit: Option<NonZeroU32>,
+    /// Options for specific methods.
+    ///
+    /// Methods are identified by the JRPC method name (e.g. `getContractState`,
+    /// also used for the protobuf requests) or by the root field of the GraphQL
+    /// query (e.g. `blocks`, `postRequests`)
+    pub method_overrides: HashMap<String, MethodOptions>,
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
+        Self {
+            request_timeout: Some(Duration::from_secs(30)),
+            retries: 2,
+            backoff: Default::default(),
+            rate_limit: None,
+            method_overrides: Default::default(),
+        }
+    }
+}
+
+/// Overrides of the [`TransportOptions`] for a specific method
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MethodOptions {
+    pub request_timeout: Option<Duration>,
+    pub retries: Option<u32>,
+}
+
+/// Exponential backoff
+#[derive(Debug, Copy, Clone)]
+pub struct Backoff {
+    /// Delay after the first failed attempt. Default: `200ms`
+    pub initial_delay: Duration,
+    /// Max delay between attempts. Default: `5s`
+    pub max_delay: Duration,
+    /// Delay multiplier for each next attempt. Default: `2`
+    pub factor: u32,
+    /// Whether to randomize delays in range `[delay / 2, delay]`. Default: `true`
+    pub jitter: bool,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            factor: 2,
+            jitter: true,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay after the failed attempt (starting from `0`)
+    pub fn delay(&self, attempt: u32, seed: u64) -> Duration {
+        let factor = self.factor.checked_pow(attempt).unwrap_or(u32::MAX);
+        let delay = self
+            .initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+
+        if !self.jitter {
+            return delay;
+        }
+
+        let delay_ms = delay.as_millis() as u64;
+        let half = delay_ms / 2;
+        Duration::from_millis(half + splitmix64(seed) % (delay_ms - half + 1))
+    }
+}
+
+/// Applies [`TransportOptions`] to the requests
+pub(crate) struct RequestPolicy {
+    options: TransportOptions,
+    clock: Arc<dyn Clock>,
+    sleep: Arc<dyn AsyncSleep>,
+    next_slot_ms: Mutex<u64>,
+}
+
+impl RequestPolicy {
+    pub fn new(
+        options: TransportOptions,
+        clock: Arc<dyn Clock>,
+        sleep: Arc<dyn AsyncSleep>,
+    ) -> Self {
+        Self {
+            options,
+            clock,
+            sleep,
+            next_slot_ms: Default::default(),
+        }
+    }
+
+    /// Resolves the options for the specified method
+    pub fn params(&self, method: Option<&str>) -> RequestParams {
+        let overrides = method
+            .and_then(|method| self.options.method_overrides.get(method))
+            .copied()
+            .unwrap_or_default();
+
+        let retries = match method {
+            // NOTE: the failed attempt could still deliver the message
+            Some(method) if SEND_MESSAGE_METHODS.contains(&method) => 0,
+            _ => self.options.retries,
+        };
+
+        RequestParams {
+            request_timeout: overrides.request_timeout.or(self.options.request_timeout),
+            retries: overrides.retries.unwrap_or(retries),
+        }
+    }
+
+    pub async fn run<T, F, Fut>(&self, params: RequestParams, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            self.wait_rate_limit().await;
+
+            let result = match params.request_timeout {
+                Some(timeout) => {
+                    let request = Box::pin(f());
+                    match futures_util::future::select(request, self.sleep.sleep(timeout)).await {
+                        Either::Left((result, _)) => result,
+                        Either::Right(_) => Err(TransportOptionsError::Timeout.into()),
+                    }
+                }
+                None => f().await,
+            };
+
+            match result {
+                Ok(result) => return Ok(result),
+                Err(e) if attempt >= params.retries => return Err(e),
+                Err(e) => log::debug!("Request attempt {attempt} failed: {e:?}"),
+            }
+
+            let seed = self.clock.now_ms_u64() ^ u64::from(attempt);
+            self.sleep
+                .sleep(self.options.backoff.delay(attempt, seed))
+                .await;
+            attempt += 1;
+        }
+    }
+
+    async fn wait_rate_limit(&self) {
+        let rate_limit = match self.options.rate_limit {
+            Some(rate_limit) => rate_limit,
+            None => return,
+        };
+        let interval_ms = 1000 / u64::from(rate_limit.get());
+
+        let now = self.clock.now_ms_u64();
+        let delay_ms = {
+            let mut next_slot_ms = self.next_slot_ms.lock();
+            let slot = std::cmp::max(*next_slot_ms, now);
+            *next_slot_ms = slot + interval_ms;
+            slot - now
+        };
+
+        if delay_ms > 0 {
+            self.sleep.sleep(Duration::from_millis(delay_ms)).await;
+        }
+    }
+}
+
+/// Methods which are not retried by default
+const SEND_MESSAGE_METHODS: &[&str] = &["sendMessage", "postRequests"];
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct RequestParams {
+    pub request_timeout: Option<Duration>,
+    pub retries: u32,
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum TransportOptionsError {
+    #[error("Request timeout")]
+    Timeout,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use nekoton_utils::SimpleClock;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestSleep(Mutex<Vec<Duration>>);
+
+    #[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+    #[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+    impl AsyncSleep for TestSleep {
+        async fn sleep(&self, duration: Duration) {
+            self.0.lock().push(duration);
+        }
+    }
+
+    #[test]
+    fn backoff_delays() {
+        let backoff = Backoff {
+            jitter: false,
+            ..Default::default()
+        };
+        assert_eq!(backoff.delay(0, 0), Duration::from_millis(200));
+        assert_eq!(backoff.delay(2, 0), Duration::from_millis(800));
+        assert_eq!(backoff.delay(100, 0), Duration::from_secs(5));
+
+        let backoff = Backoff::default();
+        for seed in 0..100 {
+            let delay = backoff.delay(1, seed);
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn send_message_is_not_retried_by_default() {
+        let policy = |options: TransportOptions| {
+            RequestPolicy::new(
+                options,
+                Arc::new(SimpleClock),
+                Arc::new(TestSleep::default()),
+            )
+        };
+
+        let default_policy = policy(Default::default());
+        assert_eq!(default_policy.params(None).retries, 2);
+        assert_eq!(default_policy.params(Some("getContractState")).retries, 2);
+        assert_eq!(default_policy.params(Some("sendMessage")).retries, 0);
+        assert_eq!(default_policy.params(Some("postRequests")).retries, 0);
+
+        let custom_policy = policy(TransportOptions {
+            method_overrides: [(
+                "sendMessage".to_owned(),
+                MethodOptions {
+                    retries: Some(1),
+                    ..Default::default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        });
+        assert_eq!(custom_policy.params(Some("sendMessage")).retries, 1);
+    }
+
+    #[tokio::test]
+    async fn retries_and_timeouts() {
+        let sleep = Arc::new(TestSleep::default());
+        let policy = RequestPolicy::new(
+            TransportOptions {
+                method_overrides: [(
+                    "sendMessage".to_owned(),
+                    MethodOptions {
+                        retries: Some(0),
+                        ..Default::default()
+                    },
+                )]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            },
+            Arc::new(SimpleClock),
+            sleep.clone(),
+        );
+
+        let attempts = AtomicU32::new(0);
+        let result = policy
+            .run(policy.params(Some("getContractState")), || async {
+                match attempts.fetch_add(1, Ordering::Relaxed) {
+                    0 | 1 => Err(anyhow::anyhow!("connection error")),
+                    _ => Ok(()),
+                }
+            })
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        // Pending request is interrupted by the timeout and is not retried
+        let result = policy
+            .run(policy.params(Some("sendMessage")), || {
+                futures_util::future::pending::<Result<()>>()
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(sleep.0.lock().last(), Some(&Duration::from_secs(30)));
+    }
+}
@@ -0,0 +1,654 @@
+//! Merkle proof verification for trust-minimized clients.
+//!
+//! Proofs are checked against the root hash of the block which is already trusted
+//! by the caller (e.g. obtained from the verified key block chain). Validator
+//! signatures of the block itself are not checked here.
+//!
+//! Shard blocks can be trusted through the masterchain block which references
+//! them (see [`ShardBlockProof`]).
+
+use anyhow::Result;
+use nekoton_abi::{GenTimings, LastTransactionId, TransactionId};
+use serde::{Deserialize, Serialize};
+use ton_block::{Deserializable, HashmapAugType, MerkleProof, MsgAddressInt, Serializable};
+use ton_types::{Cell, UInt256};
+
+use super::models::{ExistingContract, RawContractState, RawTransaction};
+
+/// Proof of the account state at the specified block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountStateProof {
+    /// Merkle proof of the block, which contains at least its info and state update
+    #[serde(with = "nekoton_utils::serde_cell")]
+    pub block_proof: Cell,
+    /// Merkle proof of the shard state after the block, which contains the account
+    #[serde(with = "nekoton_utils::serde_cell")]
+    pub state_proof: Cell,
+    /// Full account cell, if it is pruned in the state proof
+    #[serde(default, with = "nekoton_utils::serde_optional_cell")]
+    pub account: Option<Cell>,
+    /// Proof of the shard block, if the trusted block is a masterchain block
+    /// and the account is outside of the masterchain
+    #[serde(default)]
+    pub shard_proof: Option<ShardBlockProof>,
+}
+
+/// Proof that the shard block is referenced by the masterchain block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardBlockProof {
+    /// Merkle proof of the masterchain block, which contains its state update
+    #[serde(with = "nekoton_utils::serde_cell")]
+    pub mc_block_proof: Cell,
+    /// Merkle proof of the masterchain state, which contains the shard hashes
+    #[serde(with = "nekoton_utils::serde_cell")]
+    pub mc_state_proof: Cell,
+}
+
+/// Verifies the account state proof and extracts the state
+pub fn verify_account_state(
+    trusted_block_hash: &UInt256,
+    address: &MsgAddressInt,
+    proof: &AccountStateProof,
+) -> Result<RawContractState> {
+    let block_hash = match &proof.shard_proof {
+        Some(shard_proof) => verify_shard_block(trusted_block_hash, address, shard_proof)?,
+        None => *trusted_block_hash,
+    };
+
+    let block = read_block_proof(&block_hash, &proof.block_proof)?;
+    let info = block
+        .info
+        .read_struct()
+        .map_err(|_| ProofError::InvalidBlockProof)?;
+    let state_update = block
+        .state_update
+        .read_struct()
+        .map_err(|_| ProofError::InvalidBlockProof)?;
+
+    let account_id = account_key(info.shard(), address)?;
+
+    let state_proof = MerkleProof::construct_from_cell(proof.state_proof.clone())
+        .map_err(|_| ProofError::InvalidStateProof)?;
+    if state_proof.hash != state_update.new_hash {
+        return Err(ProofError::StateHashMismatch.into());
+    }
+
+    let state: ton_block::ShardStateUnsplit = state_proof
+        .virtualize()
+        .map_err(|_| ProofError::InvalidStateProof)?;
+    let shard_account = state
+        .read_accounts()
+        .and_then(|accounts| accounts.get(&account_id))
+        .map_err(|_| ProofError::InvalidStateProof)?;
+
+    let timings = GenTimings::Known {
+        gen_lt: info.end_lt(),
+        gen_utime: info.gen_utime().as_u32(),
+    };
+
+    let shard_account = match shard_account {
+        Some(shard_account) => shard_account,
+        None => return Ok(RawContractState::NotExists { timings }),
+    };
+
+    // NOTE: the account itself may be pruned in the state proof (e.g. liteserver
+    // proofs), so the full cell is checked against its hash
+    let account = match &proof.account {
+        Some(account) if account.repr_hash() != shard_account.account_cell().repr_hash() => {
+            return Err(ProofError::InvalidStateProof.into())
+        }
+        Some(account) => ton_block::Account::construct_from_cell(account.clone()),
+        None => shard_account.read_account(),
+    };
+
+    match account.map_err(|_| ProofError::InvalidStateProof)? {
+        ton_block::Account::Account(account) if account.addr != *address => {
+            Err(ProofError::InvalidStateProof.into())
+        }
+        ton_block::Account::Account(account) => Ok(RawContractState::Exists(ExistingContract {
+            account,
+            timings,
+            last_transaction_id: LastTransactionId::Exact(TransactionId {
+                lt: shard_account.last_trans_lt(),
+                hash: *shard_account.last_trans_hash(),
+            }),
+        })),
+        ton_block::Account::AccountNone => Ok(RawContractState::NotExists { timings }),
+    }
+}
+
+/// Verifies that the transaction is included into the trusted block
+pub fn verify_transaction_inclusion(
+    trusted_block_hash: &UInt256,
+    block_proof: &Cell,
+    address: &MsgAddressInt,
+    transaction: &RawTransaction,
+) -> Result<()> {
+    // NOTE: the hash is not trusted, otherwise any data could be passed with it
+    let hash = transaction
+        .data
+        .serialize()
+        .map_err(|_| ProofError::TransactionHashMismatch)?
+        .repr_hash();
+    if hash != transaction.hash {
+        return Err(ProofError::TransactionHashMismatch.into());
+    }
+
+    let block = read_block_proof(trusted_block_hash, block_proof)?;
+    let info = block
+        .info
+        .read_struct()
+        .map_err(|_| ProofError::InvalidBlockProof)?;
+    let account_id = account_key(info.shard(), address)?;
+
+    let account_block = block
+        .extra
+        .read_struct()
+        .and_then(|extra| extra.read_account_blocks())
+        .and_then(|account_blocks| account_blocks.get(&account_id))
+        .map_err(|_| ProofError::InvalidBlockProof)?
+        .ok_or(ProofError::TransactionNotFound)?;
+
+    for item in account_block.transactions().iter() {
+        let hash = item
+            .and_then(|(_, value)| value.into_cell().reference(0))
+            .map_err(|_| ProofError::InvalidBlockProof)?
+            .repr_hash();
+        if hash == transaction.hash {
+            return Ok(());
+        }
+    }
+
+    Err(ProofError::TransactionNotFound.into())
+}
+
+/// Returns the hash of the shard block with the account, referenced by the trusted
+/// masterchain block
+fn verify_shard_block(
+    trusted_block_hash: &UInt256,
+    address: &MsgAddressInt,
+    proof: &ShardBlockProof,
+) -> Result<UInt256> {
+    let block = read_block_proof(trusted_block_hash, &proof.mc_block_proof)?;
+    let info = block
+        .info
+        .read_struct()
+        .map_err(|_| ProofError::InvalidBlockProof)?;
+    if !info.shard().is_masterchain() {
+        return Err(ProofError::InvalidShardProof.into());
+    }
+    let state_update = block
+        .state_update
+        .read_struct()
+        .map_err(|_| ProofError::InvalidBlockProof)?;
+
+    let state_proof = MerkleProof::construct_from_cell(proof.mc_state_proof.clone())
+        .map_err(|_| ProofError::InvalidShardProof)?;
+    if state_proof.hash != state_update.new_hash {
+        return Err(ProofError::StateHashMismatch.into());
+    }
+
+    let extra = state_proof
+        .virtualize::<ton_block::ShardStateUnsplit>()
+        .and_then(|state| state.read_custom())
+        .map_err(|_| ProofError::InvalidShardProof)?
+        .ok_or(ProofError::InvalidShardProof)?;
+
+    let account_id = UInt256::from_be_bytes(&address.address().get_bytestring(0));
+    let mut shard_block_hash = None;
+    extra
+        .shards()
+        .iterate_shards(|ident, descr| {
+            if ident.workchain_id() == address.workchain_id()
+                && shard_contains(ident.shard_prefix_with_tag(), &account_id)
+            {
+                shard_block_hash = Some(descr.root_hash);
+                return Ok(false);
+            }
+            Ok(true)
+        })
+        .map_err(|_| ProofError::InvalidShardProof)?;
+
+    shard_block_hash.ok_or_else(|| ProofError::AccountNotInShard.into())
+}
+
+fn read_block_proof(trusted_block_hash: &UInt256, proof: &Cell) -> Result<ton_block::Block> {
+    let proof = MerkleProof::construct_from_cell(proof.clone())
+        .map_err(|_| ProofError::InvalidBlockProof)?;
+    if proof.hash != *trusted_block_hash {
+        return Err(ProofError::BlockHashMismatch.into());
+    }
+
+    proof
+        .virtualize()
+        .map_err(|_| ProofError::InvalidBlockProof.into())
+}
+
+/// Returns the account id if the address belongs to the shard of the block.
+///
+/// Shard dictionaries are keyed only by the account id, so without this check
+/// a proof from another workchain or shard would "prove" that any account doesn't exist
+fn account_key(shard: &ton_block::ShardIdent, address: &MsgAddressInt) -> Result<UInt256> {
+    let account_id = UInt256::from_be_bytes(&address.address().get_bytestring(0));
+    if shard.workchain_id() != address.workchain_id()
+        || !shard_contains(shard.shard_prefix_with_tag(), &account_id)
+    {
+        return Err(ProofError::AccountNotInShard.into());
+    }
+    Ok(account_id)
+}
+
+fn shard_contains(shard_prefix_with_tag: u64, account_id: &UInt256) -> bool {
+    let mut account_prefix = [0; 8];
+    account_prefix.copy_from_slice(&account_id.as_slice()[..8]);
+    let account_prefix = u64::from_be_bytes(account_prefix);
+
+    // All bits above the tag bit must be equal
+    let tag = shard_prefix_with_tag & shard_prefix_with_tag.wrapping_neg();
+    let mask = tag.wrapping_neg() << 1;
+    (shard_prefix_with_tag ^ account_prefix) & mask == 0
+}
+
+#[derive(thiserror::Error, Debug, Copy, Clone)]
+pub enum ProofError {
+    #[error("Proofs are not supported by the transport")]
+    NotSupported,
+    #[error("Invalid block proof")]
+    InvalidBlockProof,
+    #[error("Block proof doesn't match the trusted block")]
+    BlockHashMismatch,
+    #[error("Invalid shard state proof")]
+    InvalidStateProof,
+    #[error("State proof doesn't match the block state update")]
+    StateHashMismatch,
+    #[error("Transaction not found in the block")]
+    TransactionNotFound,
+    #[error("Account doesn't belong to the shard of the block")]
+    AccountNotInShard,
+    #[error("Invalid shard block proof")]
+    InvalidShardProof,
+    #[error("Transaction data doesn't match its hash")]
+    TransactionHashMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ton_block::{
+        Account, BinTree, Block, BlockExtra, BlockInfo, CurrencyCollection, InRefValue,
+        McStateExtra, MerkleUpdate, ShardAccount, ShardAccountBlocks, ShardDescr, ShardHashes,
+        ShardIdent, ShardStateUnsplit, ValueFlow,
+    };
+
+    use super::*;
+    use crate::transport::tests::MockTransport;
+    use crate::transport::Transport;
+
+    const ADDRESS: &str = "0:f0d4e2fa5b35f9aa2cb8d0d8b9e4c3a1a06f3d1b2a4c5e6f708192a3b4c5d6e7";
+    const OTHER_ADDRESS: &str =
+        "0:10d4e2fa5b35f9aa2cb8d0d8b9e4c3a1a06f3d1b2a4c5e6f708192a3b4c5d6e7";
+
+    fn parse_address(address: &str) -> MsgAddressInt {
+        MsgAddressInt::from_str(address).unwrap()
+    }
+
+    fn account_id(address: &MsgAddressInt) -> UInt256 {
+        UInt256::from_be_bytes(&address.address().get_bytestring(0))
+    }
+
+    fn make_state(shard: ShardIdent, accounts: &[(&MsgAddressInt, u64)]) -> Cell {
+        let mut state = ShardStateUnsplit::with_ident(shard);
+        for (address, balance) in accounts {
+            let account = Account::with_address_and_ballance(
+                address,
+                &CurrencyCollection::with_grams(*balance),
+            );
+            let shard_account = ShardAccount::with_params(&account, UInt256::default(), 0).unwrap();
+            state
+                .insert_account(&account_id(address), &shard_account)
+                .unwrap();
+        }
+        state.serialize().unwrap()
+    }
+
+    fn make_block(shard: ShardIdent, state: &Cell) -> Cell {
+        make_block_with_extra(shard, state, BlockExtra::default())
+    }
+
+    fn make_block_with_extra(shard: ShardIdent, state: &Cell, extra: BlockExtra) -> Cell {
+        let mut info = BlockInfo::default();
+        info.set_shard(shard);
+        let state_update = MerkleUpdate {
+            new_hash: state.repr_hash(),
+            ..Default::default()
+        };
+        Block::with_params(0, info, ValueFlow::default(), state_update, extra)
+            .unwrap()
+            .serialize()
+            .unwrap()
+    }
+
+    /// Returns the masterchain block and state, which reference the shard block
+    fn make_mc_block(shard_block_hash: UInt256) -> (Cell, Cell) {
+        let descr = ShardDescr {
+            root_hash: shard_block_hash,
+            ..Default::default()
+        };
+        let mut shards = ShardHashes::default();
+        shards
+            .set(&0i32, &InRefValue(BinTree::with_item(&descr).unwrap()))
+            .unwrap();
+
+        let mut extra = McStateExtra::default();
+        extra.shards = shards;
+        let mut state = ShardStateUnsplit::with_ident(ShardIdent::masterchain());
+        state.write_custom(Some(&extra)).unwrap();
+        let state = state.serialize().unwrap();
+
+        let block = make_block(ShardIdent::masterchain(), &state);
+        (block, state)
+    }
+
+    fn make_proof(cell: &Cell) -> Cell {
+        MerkleProof::create(cell, |_| true)
+            .unwrap()
+            .serialize()
+            .unwrap()
+    }
+
+    /// Returns the trusted block hash and the account state proof
+    fn make_proofs(
+        shard: ShardIdent,
+        accounts: &[(&MsgAddressInt, u64)],
+    ) -> (UInt256, AccountStateProof) {
+        let state = make_state(shard, accounts);
+        let block = make_block(shard, &state);
+        let proof = AccountStateProof {
+            block_proof: make_proof(&block),
+            state_proof: make_proof(&state),
+            account: None,
+            shard_proof: None,
+        };
+        (block.repr_hash(), proof)
+    }
+
+    fn proof_error(result: Result<RawContractState>) -> ProofError {
+        *result.unwrap_err().downcast_ref::<ProofError>().unwrap()
+    }
+
+    #[test]
+    fn account_state_proof() {
+        let address = parse_address(ADDRESS);
+        let (block_hash, proof) = make_proofs(ShardIdent::full(0), &[(&address, 100)]);
+
+        match verify_account_state(&block_hash, &address, &proof).unwrap() {
+            RawContractState::Exists(contract) => {
+                assert_eq!(contract.account.storage.balance.grams.as_u128(), 100);
+            }
+            RawContractState::NotExists { .. } => panic!("account must exist"),
+        }
+
+        let other_address = parse_address(OTHER_ADDRESS);
+        assert!(matches!(
+            verify_account_state(&block_hash, &other_address, &proof).unwrap(),
+            RawContractState::NotExists { .. }
+        ));
+    }
+
+    #[test]
+    fn wrong_shard_proof() {
+        let address = parse_address(ADDRESS);
+
+        // Masterchain proof must not prove anything about basechain accounts
+        let (block_hash, proof) = make_proofs(ShardIdent::masterchain(), &[]);
+        assert!(matches!(
+            proof_error(verify_account_state(&block_hash, &address, &proof)),
+            ProofError::AccountNotInShard
+        ));
+
+        // Address starts with 0xf0, so it belongs to the right half of the workchain
+        let left_shard = ShardIdent::with_tagged_prefix(0, 0x4000_0000_0000_0000).unwrap();
+        let (block_hash, proof) = make_proofs(left_shard, &[]);
+        assert!(matches!(
+            proof_error(verify_account_state(&block_hash, &address, &proof)),
+            ProofError::AccountNotInShard
+        ));
+
+        let right_shard = ShardIdent::with_tagged_prefix(0, 0xc000_0000_0000_0000).unwrap();
+        let (block_hash, proof) = make_proofs(right_shard, &[(&address, 1)]);
+        assert!(matches!(
+            verify_account_state(&block_hash, &address, &proof).unwrap(),
+            RawContractState::Exists(_)
+        ));
+    }
+
+    #[test]
+    fn tampered_proof() {
+        let address = parse_address(ADDRESS);
+        let (block_hash, proof) = make_proofs(ShardIdent::full(0), &[(&address, 100)]);
+
+        // Untrusted block
+        let (other_block_hash, _) = make_proofs(ShardIdent::full(0), &[]);
+        assert!(matches!(
+            proof_error(verify_account_state(&other_block_hash, &address, &proof)),
+            ProofError::BlockHashMismatch
+        ));
+
+        // State with the modified balance
+        let tampered = AccountStateProof {
+            state_proof: make_proof(&make_state(ShardIdent::full(0), &[(&address, 1_000_000)])),
+            ..proof.clone()
+        };
+        assert!(matches!(
+            proof_error(verify_account_state(&block_hash, &address, &tampered)),
+            ProofError::StateHashMismatch
+        ));
+
+        // State without the account
+        let tampered = AccountStateProof {
+            state_proof: make_proof(&make_state(ShardIdent::full(0), &[])),
+            ..proof
+        };
+        assert!(matches!(
+            proof_error(verify_account_state(&block_hash, &address, &tampered)),
+            ProofError::StateHashMismatch
+        ));
+    }
+
+    #[test]
+    fn pruned_account_proof() {
+        let address = parse_address(ADDRESS);
+        let state = make_state(ShardIdent::full(0), &[(&address, 100)]);
+        let block = make_block(ShardIdent::full(0), &state);
+        let block_hash = block.repr_hash();
+
+        let account = ShardStateUnsplit::construct_from_cell(state.clone())
+            .and_then(|state| state.read_accounts())
+            .and_then(|accounts| accounts.get(&account_id(&address)))
+            .unwrap()
+            .unwrap()
+            .account_cell();
+        let account_hash = account.repr_hash();
+
+        // Liteservers prune the account in the state proof and send it separately
+        let state_proof = MerkleProof::create(&state, |hash| *hash != account_hash)
+            .unwrap()
+            .serialize()
+            .unwrap();
+        let proof = AccountStateProof {
+            block_proof: make_proof(&block),
+            state_proof,
+            account: Some(account),
+            shard_proof: None,
+        };
+        assert!(matches!(
+            verify_account_state(&block_hash, &address, &proof).unwrap(),
+            RawContractState::Exists(_)
+        ));
+
+        let tampered = AccountStateProof {
+            account: None,
+            ..proof.clone()
+        };
+        assert!(matches!(
+            proof_error(verify_account_state(&block_hash, &address, &tampered)),
+            ProofError::InvalidStateProof
+        ));
+
+        let other_account = Account::with_address_and_ballance(
+            &address,
+            &CurrencyCollection::with_grams(1_000_000),
+        );
+        let tampered = AccountStateProof {
+            account: Some(other_account.serialize().unwrap()),
+            ..proof
+        };
+        assert!(matches!(
+            proof_error(verify_account_state(&block_hash, &address, &tampered)),
+            ProofError::InvalidStateProof
+        ));
+    }
+
+    #[test]
+    fn shard_block_proof() {
+        let address = parse_address(ADDRESS);
+        let (shard_block_hash, proof) = make_proofs(ShardIdent::full(0), &[(&address, 100)]);
+        let (mc_block, mc_state) = make_mc_block(shard_block_hash);
+        let mc_block_hash = mc_block.repr_hash();
+
+        let proof = AccountStateProof {
+            shard_proof: Some(ShardBlockProof {
+                mc_block_proof: make_proof(&mc_block),
+                mc_state_proof: make_proof(&mc_state),
+            }),
+            ..proof
+        };
+        assert!(matches!(
+            verify_account_state(&mc_block_hash, &address, &proof).unwrap(),
+            RawContractState::Exists(_)
+        ));
+
+        // Shard block is not trusted without the masterchain block
+        assert!(matches!(
+            proof_error(verify_account_state(&shard_block_hash, &address, &proof)),
+            ProofError::BlockHashMismatch
+        ));
+
+        // Masterchain block references another shard block
+        let (other_mc_block, other_mc_state) = make_mc_block(UInt256::from([1; 32]));
+        let tampered = AccountStateProof {
+            shard_proof: Some(ShardBlockProof {
+                mc_block_proof: make_proof(&other_mc_block),
+                mc_state_proof: make_proof(&other_mc_state),
+            }),
+            ..proof.clone()
+        };
+        assert!(matches!(
+            proof_error(verify_account_state(
+                &other_mc_block.repr_hash(),
+                &address,
+                &tampered
+            )),
+            ProofError::BlockHashMismatch
+        ));
+
+        // Masterchain state of another block
+        let tampered = AccountStateProof {
+            shard_proof: Some(ShardBlockProof {
+                mc_block_proof: make_proof(&mc_block),
+                mc_state_proof: make_proof(&other_mc_state),
+            }),
+            ..proof
+        };
+        assert!(matches!(
+            proof_error(verify_account_state(&mc_block_hash, &address, &tampered)),
+            ProofError::StateHashMismatch
+        ));
+    }
+
+    #[test]
+    fn transaction_inclusion() {
+        let address = parse_address(ADDRESS);
+
+        let make_transaction = |lt: u64| {
+            let mut transaction = ton_block::Transaction::default();
+            transaction.account_addr = address.address();
+            transaction.lt = lt;
+            let cell = transaction.serialize().unwrap();
+            RawTransaction {
+                hash: cell.repr_hash(),
+                data: transaction,
+            }
+        };
+        let transaction = make_transaction(10);
+
+        let mut account_blocks = ShardAccountBlocks::default();
+        account_blocks
+            .add_serialized_transaction(&transaction.data, &transaction.data.serialize().unwrap())
+            .unwrap();
+        let mut extra = BlockExtra::default();
+        extra.write_account_blocks(&account_blocks).unwrap();
+
+        let state = make_state(ShardIdent::full(0), &[]);
+        let block = make_block_with_extra(ShardIdent::full(0), &state, extra);
+        let block_hash = block.repr_hash();
+        let block_proof = make_proof(&block);
+
+        verify_transaction_inclusion(&block_hash, &block_proof, &address, &transaction).unwrap();
+
+        let transaction_error = |transaction: &RawTransaction| {
+            *verify_transaction_inclusion(&block_hash, &block_proof, &address, transaction)
+                .unwrap_err()
+                .downcast_ref::<ProofError>()
+                .unwrap()
+        };
+
+        // Data of another transaction with the hash of the included one
+        let tampered = RawTransaction {
+            hash: transaction.hash,
+            data: make_transaction(20).data,
+        };
+        assert!(matches!(
+            transaction_error(&tampered),
+            ProofError::TransactionHashMismatch
+        ));
+
+        assert!(matches!(
+            transaction_error(&make_transaction(20)),
+            ProofError::TransactionNotFound
+        ));
+    }
+
+    #[test]
+    fn shard_prefix() {
+        let account_id = |prefix: u8| UInt256::from([prefix; 32]);
+        assert!(shard_contains(0x8000_0000_0000_0000, &account_id(0x00)));
+        assert!(shard_contains(0x8000_0000_0000_0000, &account_id(0xff)));
+        assert!(shard_contains(0x4000_0000_0000_0000, &account_id(0x7f)));
+        assert!(!shard_contains(0x4000_0000_0000_0000, &account_id(0x80)));
+        assert!(shard_contains(0xe000_0000_0000_0000, &account_id(0xc0)));
+        assert!(!shard_contains(0xe000_0000_0000_0000, &account_id(0x80)));
+    }
+
+    #[tokio::test]
+    async fn verified_state_from_transport() {
+        let address = parse_address(ADDRESS);
+        let (block_hash, proof) = make_proofs(ShardIdent::full(0), &[(&address, 100)]);
+
+        let transport = MockTransport::new();
+        assert!(transport
+            .get_contract_state_verified(&address, &block_hash)
+            .await
+            .is_err());
+
+        transport.set_contract_state_proof(address.clone(), proof);
+        let state = transport
+            .get_contract_state_verified(&address, &block_hash)
+            .await
+            .unwrap();
+        assert!(matches!(state, RawContractState::Exists(_)));
+    }
+}
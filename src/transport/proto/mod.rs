@@ -14,7 +14,9 @@ use crate::core::models::{NetworkCapabilities, ReliableBehavior};
 use crate::external::{self, ProtoConnection};
 use crate::transport::models::{ExistingContract, PollContractState};
 
+use super::ext::AsyncSleep;
 use super::models::{RawContractState, RawTransaction};
+use super::options::{RequestPolicy, TransportOptions};
 use super::utils::*;
 use super::{Transport, TransportInfo};
 
@@ -33,6 +35,21 @@ impl ProtoTransport {
         }
     }
 
+    /// Creates the transport which applies timeouts, retries and rate limits to all requests.
+    ///
+    /// Method overrides use the JRPC method names (e.g. `getContractState`)
+    pub fn with_options(
+        connection: Arc<dyn ProtoConnection>,
+        options: TransportOptions,
+        clock: Arc<dyn Clock>,
+        sleep: Arc<dyn AsyncSleep>,
+    ) -> Self {
+        Self::new(Arc::new(ProtoConnectionWithOptions {
+            connection,
+            policy: RequestPolicy::new(options, clock, sleep),
+        }))
+    }
+
     async fn fetch_config(&self) -> Result<ConfigResponse> {
         let data = rpc::Request {
             call: Some(rpc::request::Call::GetBlockchainConfig(())),
@@ -363,6 +380,52 @@ impl Transport for ProtoTransport {
     }
 }
 
+struct ProtoConnectionWithOptions {
+    connection: Arc<dyn ProtoConnection>,
+    policy: RequestPolicy,
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl ProtoConnection for ProtoConnectionWithOptions {
+    async fn post(&self, req: external::ProtoRequest) -> Result<Vec<u8>> {
+        let method = rpc::Request::decode(req.data.as_slice())
+            .ok()
+            .and_then(|req| req.call)
+            .map(|call| method_name(&call));
+
+        self.policy
+            .run(self.policy.params(method), || {
+                self.connection.post(req.clone())
+            })
+            .await
+    }
+}
+
+/// Returns the JRPC name of the method
+fn method_name(call: &rpc::request::Call) -> &'static str {
+    use rpc::request::Call;
+
+    match call {
+        Call::GetCapabilities(_) => "getCapabilities",
+        Call::GetLatestKeyBlock(_) => "getLatestKeyBlock",
+        Call::GetBlockchainConfig(_) => "getBlockchainConfig",
+        Call::GetStatus(_) => "getStatus",
+        Call::GetTimings(_) => "getTimings",
+        Call::GetContractState(_) => "getContractState",
+        Call::GetTransaction(_) => "getTransaction",
+        Call::GetDstTransaction(_) => "getDstTransaction",
+        Call::GetTransactionsList(_) => "getTransactionsList",
+        Call::GetAccountsByCodeHash(_) => "getAccountsByCodeHash",
+        Call::SendMessage(_) => "sendMessage",
+        Call::GetLibraryCell(_) => "getLibraryCell",
+        Call::GetTransactionBlockId(_) => "getTransactionBlockId",
+        Call::GetKeyBlockProof(_) => "getKeyBlockProof",
+        Call::GetBlockProof(_) => "getBlockProof",
+        Call::GetBlockData(_) => "getBlockData",
+    }
+}
+
 fn decode_raw_transaction(bytes: Bytes) -> Result<RawTransaction> {
     let cell = ton_types::deserialize_tree_of_cells(&mut bytes.as_ref())?;
     let hash = cell.repr_hash();
@@ -450,6 +513,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn proto_method_names() {
+        let encode = |call| rpc::Request { call: Some(call) }.encode_to_vec();
+
+        let send_message = encode(rpc::request::Call::SendMessage(Default::default()));
+        let call = rpc::Request::decode(send_message.as_slice())
+            .unwrap()
+            .call
+            .unwrap();
+        assert_eq!(method_name(&call), "sendMessage");
+
+        let get_state = encode(rpc::request::Call::GetContractState(Default::default()));
+        let call = rpc::Request::decode(get_state.as_slice())
+            .unwrap()
+            .call
+            .unwrap();
+        assert_eq!(method_name(&call), "getContractState");
+    }
+
     pub struct OverrideClient {
         client: reqwest::Client,
     }
@@ -0,0 +1,350 @@
+//! In-memory transport for unit tests.
+//!
+//! Available with the `test-utils` feature
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use anyhow::Result;
+use nekoton_abi::GenTimings;
+use nekoton_utils::Clock;
+use parking_lot::Mutex;
+use ton_block::MsgAddressInt;
+use ton_types::{Cell, UInt256};
+
+use super::models::*;
+use super::proofs::{AccountStateProof, ProofError};
+use super::{Transport, TransportInfo};
+use crate::models::{NetworkCapabilities, ReliableBehavior};
+
+/// Transport with scripted account states, transactions and key blocks
+pub struct MockTransport {
+    info: TransportInfo,
+    state: Mutex<MockState>,
+}
+
+struct MockState {
+    accounts: BTreeMap<MsgAddressInt, RawContractState>,
+    /// Account transactions in descending order by lt
+    transactions: HashMap<MsgAddressInt, Vec<RawTransaction>>,
+    library_cells: HashMap<UInt256, Cell>,
+    proofs: HashMap<MsgAddressInt, AccountStateProof>,
+    key_blocks: VecDeque<ton_block::Block>,
+    sent_messages: Vec<ton_block::Message>,
+    send_error: Option<String>,
+    capabilities: NetworkCapabilities,
+    config: ton_executor::BlockchainConfig,
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self {
+            info: TransportInfo {
+                max_transactions_per_fetch: 50,
+                reliable_behavior: ReliableBehavior::IntensivePolling,
+                has_key_blocks: true,
+            },
+            state: Mutex::new(MockState {
+                accounts: Default::default(),
+                transactions: Default::default(),
+                library_cells: Default::default(),
+                proofs: Default::default(),
+                key_blocks: Default::default(),
+                sent_messages: Default::default(),
+                send_error: None,
+                capabilities: NetworkCapabilities {
+                    global_id: 0,
+                    raw: 0,
+                },
+                config: Default::default(),
+            }),
+        }
+    }
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_info(mut self, info: TransportInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Replaces the account state, returned by all state requests
+    pub fn set_contract_state(&self, address: MsgAddressInt, state: RawContractState) {
+        self.state.lock().accounts.insert(address, state);
+    }
+
+    pub fn remove_contract_state(&self, address: &MsgAddressInt) {
+        self.state.lock().accounts.remove(address);
+    }
+
+    /// Adds the account transaction, keeping the descending order by lt
+    pub fn add_transaction(&self, address: MsgAddressInt, transaction: RawTransaction) {
+        let mut state = self.state.lock();
+        let transactions = state.transactions.entry(address).or_default();
+        let index = transactions
+            .iter()
+            .position(|item| item.data.lt < transaction.data.lt)
+            .unwrap_or(transactions.len());
+        transactions.insert(index, transaction);
+    }
+
+    /// Sets the proof, returned for the account regardless of the requested block
+    pub fn set_contract_state_proof(&self, address: MsgAddressInt, proof: AccountStateProof) {
+        self.state.lock().proofs.insert(address, proof);
+    }
+
+    pub fn add_library_cell(&self, cell: Cell) {
+        self.state
+            .lock()
+            .library_cells
+            .insert(cell.repr_hash(), cell);
+    }
+
+    /// Appends the block to the key blocks sequence.
+    ///
+    /// Each [`Transport::get_latest_key_block`] call returns the next block
+    /// in the sequence, the last one is returned repeatedly
+    pub fn push_key_block(&self, block: ton_block::Block) {
+        self.state.lock().key_blocks.push_back(block);
+    }
+
+    pub fn set_capabilities(&self, capabilities: NetworkCapabilities) {
+        self.state.lock().capabilities = capabilities;
+    }
+
+    pub fn set_blockchain_config(&self, config: ton_executor::BlockchainConfig) {
+        self.state.lock().config = config;
+    }
+
+    /// Makes all subsequent [`Transport::send_message`] calls fail
+    pub fn set_send_error(&self, error: Option<String>) {
+        self.state.lock().send_error = error;
+    }
+
+    /// Returns all successfully sent messages and clears the list
+    pub fn take_sent_messages(&self) -> Vec<ton_block::Message> {
+        std::mem::take(&mut self.state.lock().sent_messages)
+    }
+}
+
+#[cfg_attr(not(feature = "non_threadsafe"), async_trait::async_trait)]
+#[cfg_attr(feature = "non_threadsafe", async_trait::async_trait(?Send))]
+impl Transport for MockTransport {
+    fn info(&self) -> TransportInfo {
+        self.info
+    }
+
+    async fn send_message(&self, message: &ton_block::Message) -> Result<()> {
+        let mut state = self.state.lock();
+        if let Some(error) = &state.send_error {
+            anyhow::bail!("{error}");
+        }
+        state.sent_messages.push(message.clone());
+        Ok(())
+    }
+
+    async fn get_contract_state(&self, address: &MsgAddressInt) -> Result<RawContractState> {
+        Ok(match self.state.lock().accounts.get(address) {
+            Some(state) => state.clone(),
+            None => RawContractState::NotExists {
+                timings: GenTimings::Unknown,
+            },
+        })
+    }
+
+    async fn get_library_cell(&self, hash: &UInt256) -> Result<Option<Cell>> {
+        Ok(self.state.lock().library_cells.get(hash).cloned())
+    }
+
+    async fn poll_contract_state(
+        &self,
+        address: &MsgAddressInt,
+        last_trans_lt: u64,
+    ) -> Result<PollContractState> {
+        Ok(match self.state.lock().accounts.get(address) {
+            Some(RawContractState::Exists(state))
+                if state.account.storage.last_trans_lt == last_trans_lt =>
+            {
+                PollContractState::Unchanged {
+                    timings: state.timings,
+                }
+            }
+            Some(RawContractState::Exists(state)) => PollContractState::Exists(state.clone()),
+            Some(RawContractState::NotExists { timings }) => {
+                PollContractState::NotExists { timings: *timings }
+            }
+            None => PollContractState::NotExists {
+                timings: GenTimings::Unknown,
+            },
+        })
+    }
+
+    async fn get_accounts_by_code_hash(
+        &self,
+        code_hash: &UInt256,
+        limit: u8,
+        continuation: &Option<MsgAddressInt>,
+    ) -> Result<Vec<MsgAddressInt>> {
+        let state = self.state.lock();
+        Ok(state
+            .accounts
+            .iter()
+            .filter(|(address, _)| match continuation {
+                Some(continuation) => *address > continuation,
+                None => true,
+            })
+            .filter(|(_, state)| state.brief().code_hash.as_ref() == Some(code_hash))
+            .map(|(address, _)| address.clone())
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn get_transactions(
+        &self,
+        address: &MsgAddressInt,
+        from_lt: u64,
+        count: u8,
+    ) -> Result<Vec<RawTransaction>> {
+        let state = self.state.lock();
+        Ok(match state.transactions.get(address) {
+            Some(transactions) => transactions
+                .iter()
+                .filter(|transaction| transaction.data.lt <= from_lt)
+                .take(count as usize)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    async fn get_transaction(&self, id: &UInt256) -> Result<Option<RawTransaction>> {
+        let state = self.state.lock();
+        Ok(state
+            .transactions
+            .values()
+            .flatten()
+            .find(|transaction| transaction.hash == *id)
+            .cloned())
+    }
+
+    async fn get_dst_transaction(&self, message_hash: &UInt256) -> Result<Option<RawTransaction>> {
+        let state = self.state.lock();
+        Ok(state
+            .transactions
+            .values()
+            .flatten()
+            .find(|transaction| {
+                let in_msg = transaction.data.in_msg.as_ref();
+                in_msg.map(|msg| msg.cell().repr_hash()).as_ref() == Some(message_hash)
+            })
+            .cloned())
+    }
+
+    async fn get_contract_state_proof(
+        &self,
+        address: &MsgAddressInt,
+        _: &UInt256,
+    ) -> Result<AccountStateProof> {
+        match self.state.lock().proofs.get(address) {
+            Some(proof) => Ok(proof.clone()),
+            None => Err(ProofError::NotSupported.into()),
+        }
+    }
+
+    async fn get_latest_key_block(&self) -> Result<ton_block::Block> {
+        let mut state = self.state.lock();
+        let block = if state.key_blocks.len() > 1 {
+            state.key_blocks.pop_front()
+        } else {
+            state.key_blocks.front().cloned()
+        };
+        block.ok_or_else(|| anyhow::anyhow!("No key blocks scripted"))
+    }
+
+    async fn get_capabilities(&self, _: &dyn Clock) -> Result<NetworkCapabilities> {
+        Ok(self.state.lock().capabilities)
+    }
+
+    async fn get_blockchain_config(
+        &self,
+        _: &dyn Clock,
+        _: bool,
+    ) -> Result<ton_executor::BlockchainConfig> {
+        Ok(self.state.lock().config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_transaction(lt: u64) -> RawTransaction {
+        let mut data = ton_block::Transaction::default();
+        data.lt = lt;
+        RawTransaction {
+            hash: UInt256::from([lt as u8; 32]),
+            data,
+        }
+    }
+
+    fn make_state(last_trans_lt: u64) -> RawContractState {
+        let mut account = ton_block::AccountStuff::default();
+        account.storage.last_trans_lt = last_trans_lt;
+        RawContractState::Exists(ExistingContract {
+            account,
+            timings: GenTimings::Unknown,
+            last_transaction_id: nekoton_abi::LastTransactionId::Inexact {
+                latest_lt: last_trans_lt,
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn scripted_responses() {
+        let transport = MockTransport::new();
+        let address = MsgAddressInt::default();
+
+        assert!(matches!(
+            transport.get_contract_state(&address).await.unwrap(),
+            RawContractState::NotExists { .. }
+        ));
+
+        transport.set_contract_state(address.clone(), make_state(20));
+        assert!(matches!(
+            transport.poll_contract_state(&address, 20).await.unwrap(),
+            PollContractState::Unchanged { .. }
+        ));
+        assert!(matches!(
+            transport.poll_contract_state(&address, 10).await.unwrap(),
+            PollContractState::Exists(_)
+        ));
+
+        for lt in [10, 30, 20] {
+            transport.add_transaction(address.clone(), make_transaction(lt));
+        }
+        let lts = |transactions: Vec<RawTransaction>| {
+            transactions
+                .into_iter()
+                .map(|transaction| transaction.data.lt)
+                .collect::<Vec<_>>()
+        };
+        let page = transport.get_transactions(&address, u64::MAX, 2).await;
+        assert_eq!(lts(page.unwrap()), [30, 20]);
+        let page = transport.get_transactions(&address, 20, 10).await;
+        assert_eq!(lts(page.unwrap()), [20, 10]);
+
+        let id = UInt256::from([20; 32]);
+        let transaction = transport.get_transaction(&id).await.unwrap();
+        assert_eq!(transaction.unwrap().data.lt, 20);
+
+        transport.set_send_error(Some("rejected".to_owned()));
+        let message = ton_block::Message::default();
+        assert!(transport.send_message(&message).await.is_err());
+        transport.set_send_error(None);
+        transport.send_message(&message).await.unwrap();
+        assert_eq!(transport.take_sent_messages().len(), 1);
+    }
+}